@@ -46,7 +46,11 @@ cd "$PROJECT_ROOT"
 
 # Step 3: Stop existing backend process
 echo -e "\n${GREEN}[3/5] Stopping existing backend...${NC}"
-# Find and kill any process running kicad-backend
+# Find and kill any process running kicad-backend or grokicad-worker
+if pgrep -f "grokicad-worker" > /dev/null; then
+    echo "Stopping grokicad-worker process(es)..."
+    pkill -f "grokicad-worker" || true
+fi
 if pgrep -f "kicad-backend" > /dev/null; then
     echo "Found running kicad-backend process(es), stopping..."
     pkill -f "kicad-backend" || true
@@ -77,11 +81,26 @@ sleep 2
 if ps -p $NEW_PID > /dev/null 2>&1; then
     echo -e "${GREEN}✓ Backend started successfully (PID: $NEW_PID)${NC}"
     echo -e "  Logs: ${SCRIPT_DIR}/nohup.out"
-    echo -e "  Port: ${PORT:-8080}"
+    echo -e "  Listeners: see 'listening on' lines in nohup.out"
 else
     echo -e "${RED}✗ Backend failed to start. Check nohup.out for errors:${NC}"
     tail -20 nohup.out
     exit 1
 fi
 
+# Start a worker when the job queue is enabled in .env
+if grep -Eq '^JOB_QUEUE=(1|true|yes)' .env 2>/dev/null; then
+    nohup ./target/release/grokicad-worker > worker.out 2>&1 &
+    WORKER_PID=$!
+    sleep 1
+    if ps -p $WORKER_PID > /dev/null 2>&1; then
+        echo -e "${GREEN}✓ Worker started successfully (PID: $WORKER_PID)${NC}"
+        echo -e "  Logs: ${SCRIPT_DIR}/worker.out"
+    else
+        echo -e "${RED}✗ Worker failed to start. Check worker.out for errors:${NC}"
+        tail -20 worker.out
+        exit 1
+    fi
+fi
+
 echo -e "\n${GREEN}=== Redeploy Complete ===${NC}"
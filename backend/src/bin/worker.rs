@@ -0,0 +1,65 @@
+use anyhow::Context;
+use tracing::{info, warn};
+
+use kicad_backend::services::{blob_store, credentials, jobs, secret_store};
+
+/// Background worker: consumes the Postgres job queue so git clones,
+/// distillation and LLM calls run outside the API process.
+///
+/// Run as many workers as needed against the same database; jobs are claimed
+/// with row locks so each one runs once.
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Load environment variables from .env file
+    dotenvy::dotenv().ok();
+
+    tracing_subscriber::fmt().init();
+
+    let pool = kicad_db::create_pool()
+        .await
+        .context("Failed to create database pool")?;
+
+    // Jobs write renders and exports too, so fail on the same misconfiguration as the API
+    blob_store::init().context("Invalid blob store configuration")?;
+
+    // Workers look up parts and clone repositories too, so they need the same
+    // supplier credentials and stored GitHub token as the API
+    if let Err(e) = credentials::migrate_legacy(&pool).await {
+        warn!("Failed to move legacy provider credentials: {}", e);
+    }
+    match secret_store::reload(&pool).await {
+        Ok(0) => {}
+        Ok(n) => info!("Loaded {} stored secret(s)", n),
+        Err(e) => warn!("Failed to load stored secrets: {}", e),
+    }
+    secret_store::spawn_listener(pool.clone());
+
+    jobs::mark_worker_process();
+    jobs::spawn_requeue(pool.clone());
+
+    // Number of jobs this process runs at once (WORKER_CONCURRENCY, default 2)
+    let concurrency = std::env::var("WORKER_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(2);
+
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "worker".to_string());
+    info!(
+        "Starting grokicad-worker on {} with {} concurrent job(s)",
+        host, concurrency
+    );
+
+    let handles: Vec<_> = (0..concurrency)
+        .map(|n| {
+            let worker_id = format!("{}-{}-{}", host, std::process::id(), n);
+            tokio::spawn(jobs::run_worker(pool.clone(), worker_id))
+        })
+        .collect();
+
+    for handle in handles {
+        handle.await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,1818 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{Datelike, TimeZone, Utc};
+use kicad_db::secrets::{self, Keyring, SecretError};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::controllers::jobs::job_run_entry;
+use crate::errors::AppError;
+use crate::services::digikey::DigiKeyClient;
+use crate::services::distiller::BackendChoice;
+use crate::services::jobs::{queue_enabled, retry_failed};
+use crate::services::parts::PartsProvider;
+use crate::services::{
+    audit, blob_store, credentials, disk, distill, error_log, github, hook, log_filter,
+    runtime_config, secret_settings, summaries,
+};
+use crate::types::{
+    AdminApiKey, AdminApiKeyCreateResponse, AdminApiKeyQuotasRequest, AdminApiKeyRequest,
+    AdminApiKeysResponse, AdminAuditEntry, AdminAuditQuery, AdminAuditResponse, AdminBlobUsage,
+    AdminConfigResponse, AdminDistillCacheStats, AdminFailedJob, AdminFailedJobsQuery,
+    AdminFailedJobsResponse, AdminIndexStats, AdminJobStats, AdminLlmSpend, AdminLogFilterRequest,
+    AdminLogFilterResponse, AdminOverviewResponse, AdminQuarantineQuery,
+    AdminQuarantineReleaseRequest, AdminQuarantineReleaseResponse, AdminQuarantineResponse,
+    AdminQuarantinedCommit, AdminRecentError, AdminRefreshSummariesQuery,
+    AdminRefreshSummariesResponse, AdminRenameRepoRequest, AdminRepo, AdminRepoCommitStatusRequest,
+    AdminRepoStorage, AdminReposResponse, AdminRetryJobsRequest, AdminRetryJobsResponse,
+    AdminSecretRequest, AdminSecretsResponse, AdminSecretsRotateResponse, AdminStorageQuery,
+    AdminStorageResponse, AdminStorageTotals, AdminStoredSecret, ApiError, JobRunEntry,
+    ProviderCredentialStatus, ProviderCredentialsRequest, ProviderCredentialsResponse,
+};
+use crate::{demo, quota};
+use kicad_db::api_keys::{self, ApiKey, KeyQuotas};
+use kicad_db::jobs::FailedJobFilter;
+use kicad_db::quarantine::{self, CommitFailure};
+use kicad_db::repos::{self, RenameOutcome};
+use kicad_db::{audit as kdb_audit, jobs, llm_usage, read_pool, stats, PgPool};
+
+// How many recent errors the overview returns
+const RECENT_ERROR_LIMIT: usize = 20;
+
+pub type AppState = Arc<PgPool>;
+
+// Placeholder tokens from example configs and tutorials; admin routes refuse them
+const DEFAULT_ADMIN_TOKENS: &[&str] = &[
+    "change_me",
+    "changeme",
+    "change-me",
+    "admin",
+    "password",
+    "secret",
+    "token",
+];
+
+/// Whether `token` is a well-known placeholder rather than a chosen secret
+pub fn is_default_admin_token(token: &str) -> bool {
+    let token = token.trim();
+    DEFAULT_ADMIN_TOKENS
+        .iter()
+        .any(|d| d.eq_ignore_ascii_case(token))
+}
+
+/// Fail when ADMIN_TOKEN is set to a well-known placeholder, so the server
+/// doesn't start with admin routes anyone could guess their way into
+pub fn validate_admin_token() -> anyhow::Result<()> {
+    let token = std::env::var("ADMIN_TOKEN").unwrap_or_default();
+    if is_default_admin_token(&token) {
+        anyhow::bail!("ADMIN_TOKEN is set to the placeholder {:?}; choose a random token or leave it empty to disable admin endpoints", token.trim());
+    }
+    Ok(())
+}
+
+/// Check the request carries the admin token from ADMIN_TOKEN.
+///
+/// Accepts either `Authorization: Bearer <token>` or `X-Admin-Token: <token>`.
+/// Admin endpoints are disabled entirely when ADMIN_TOKEN is not set or is a
+/// well-known placeholder.
+pub fn require_admin(headers: &HeaderMap) -> Result<(), AppError> {
+    let expected = std::env::var("ADMIN_TOKEN").unwrap_or_default();
+    if expected.is_empty() || is_default_admin_token(&expected) {
+        return Err(AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::new(
+                "not_configured",
+                "Admin endpoints are disabled. Set the ADMIN_TOKEN environment variable to enable them.",
+            ),
+        ));
+    }
+
+    let provided = headers
+        .get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-admin-token").and_then(|v| v.to_str().ok()))
+        .unwrap_or_default();
+
+    // Compare digests so the comparison time doesn't depend on the token contents
+    if Sha256::digest(provided.as_bytes()) != Sha256::digest(expected.as_bytes()) {
+        warn!("Rejected admin request with invalid token");
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            ApiError::unauthorized("Invalid or missing admin token"),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parse a provider name from the path, rejecting providers without client credentials
+fn parse_credential_provider(provider: &str) -> Result<PartsProvider, AppError> {
+    match PartsProvider::from_name(provider) {
+        Some(p) if p.uses_client_credentials() => Ok(p),
+        _ => Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("Unknown credential provider: {}", provider)),
+        )),
+    }
+}
+
+/// Build the status entry for a provider, never exposing the secret
+fn credential_status(provider: PartsProvider) -> ProviderCredentialStatus {
+    let creds = credentials::get(provider.name());
+    let token_expires_at = match provider {
+        PartsProvider::DigiKey => DigiKeyClient::token_expires_at(),
+        PartsProvider::Lcsc => None,
+    };
+
+    ProviderCredentialStatus {
+        provider: provider.name().to_string(),
+        configured: creds.is_some(),
+        source: creds.as_ref().map(|c| c.source.as_str().to_string()),
+        client_id_hint: creds.as_ref().map(|c| {
+            let chars: Vec<char> = c.client_id.chars().collect();
+            let start = chars.len().saturating_sub(4);
+            format!("…{}", chars[start..].iter().collect::<String>())
+        }),
+        updated_at: creds.as_ref().and_then(|c| c.updated_at),
+        token_expires_at,
+    }
+}
+
+fn credentials_response() -> ProviderCredentialsResponse {
+    ProviderCredentialsResponse {
+        encryption_configured: credentials::is_encryption_configured(),
+        providers: PartsProvider::ALL
+            .iter()
+            .copied()
+            .filter(|p| p.uses_client_credentials())
+            .map(credential_status)
+            .collect(),
+    }
+}
+
+/// List supplier API credential status
+#[utoipa::path(
+    get,
+    path = "/api/admin/credentials",
+    responses(
+        (status = 200, description = "Credential status per provider", body = ProviderCredentialsResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_credentials(
+    State(_state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ProviderCredentialsResponse>, AppError> {
+    require_admin(&headers)?;
+    Ok(Json(credentials_response()))
+}
+
+/// Set or rotate the OAuth client credentials for a supplier API
+///
+/// The secret is encrypted with SECRETS_ENCRYPTION_KEY before it is
+/// stored. New credentials take effect immediately without a restart.
+#[utoipa::path(
+    put,
+    path = "/api/admin/credentials/{provider}",
+    params(
+        ("provider" = String, Path, description = "Provider name (e.g., digikey)")
+    ),
+    request_body = ProviderCredentialsRequest,
+    responses(
+        (status = 200, description = "Credentials stored", body = ProviderCredentialsResponse),
+        (status = 400, description = "Bad request", body = ApiError),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "Unknown provider", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints or encryption not configured", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn set_credentials(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<ProviderCredentialsRequest>,
+) -> Result<Json<ProviderCredentialsResponse>, AppError> {
+    require_admin(&headers)?;
+    let provider = parse_credential_provider(&provider)?;
+
+    if req.client_id.trim().is_empty() || req.client_secret.trim().is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("client_id and client_secret must not be empty"),
+        ));
+    }
+
+    if !credentials::is_encryption_configured() {
+        return Err(AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::new(
+                "not_configured",
+                "Credential storage is not configured. Set SECRETS_ENCRYPTION_KEY to a base64-encoded 32-byte key.",
+            ),
+        ));
+    }
+
+    credentials::set(
+        &state,
+        provider.name(),
+        req.client_id.trim(),
+        req.client_secret.trim(),
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to store credentials for {}: {}", provider.name(), e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to store credentials: {}", e)),
+        )
+    })?;
+
+    info!("Credentials updated for {}", provider.name());
+    audit::record(
+        &state,
+        audit::CREDENTIALS_CHANGED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({ "provider": provider.name(), "removed": false }),
+    )
+    .await;
+    Ok(Json(credentials_response()))
+}
+
+/// Remove stored credentials for a supplier API, reverting to environment variables
+#[utoipa::path(
+    delete,
+    path = "/api/admin/credentials/{provider}",
+    params(
+        ("provider" = String, Path, description = "Provider name (e.g., digikey)")
+    ),
+    responses(
+        (status = 200, description = "Stored credentials removed", body = ProviderCredentialsResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "Unknown provider", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn delete_credentials(
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<ProviderCredentialsResponse>, AppError> {
+    require_admin(&headers)?;
+    let provider = parse_credential_provider(&provider)?;
+
+    credentials::remove(&state, provider.name())
+        .await
+        .map_err(|e| {
+            error!(
+                "Failed to remove credentials for {}: {}",
+                provider.name(),
+                e
+            );
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to remove credentials: {}", e)),
+            )
+        })?;
+
+    info!("Stored credentials removed for {}", provider.name());
+    audit::record(
+        &state,
+        audit::CREDENTIALS_CHANGED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({ "provider": provider.name(), "removed": true }),
+    )
+    .await;
+    Ok(Json(credentials_response()))
+}
+
+/// Encryption keys and the secrets stored with them
+///
+/// Lists the active and previous key ids and every stored secret and provider
+/// credential with the key it is sealed with. Values are never returned.
+#[utoipa::path(
+    get,
+    path = "/api/admin/secrets",
+    responses(
+        (status = 200, description = "Keys and stored secrets", body = AdminSecretsResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_secrets(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminSecretsResponse>, AppError> {
+    require_admin(&headers)?;
+    Ok(Json(secrets_response(&state).await?))
+}
+
+async fn secrets_response(state: &PgPool) -> Result<AdminSecretsResponse, AppError> {
+    let internal = |e: sqlx::Error| {
+        error!("Failed to list stored secrets: {}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to list secrets: {}", e)),
+        )
+    };
+    let stored = secrets::list_secrets(state).await.map_err(internal)?;
+    let stored_credentials = kicad_db::credentials::list_credentials(state)
+        .await
+        .map_err(internal)?;
+
+    let keyring = Keyring::from_env();
+    let active = keyring.as_ref().ok().map(|k| k.active_key_id().to_string());
+    let on_active = |key_id: Option<&str>| key_id.is_some() && key_id == active.as_deref();
+
+    let mut listed: Vec<AdminStoredSecret> = stored
+        .into_iter()
+        .map(|s| {
+            let provider = kicad_db::credentials::provider_of(&s.name).map(str::to_string);
+            AdminStoredSecret {
+                kind: if provider.is_some() {
+                    "credential"
+                } else {
+                    "secret"
+                }
+                .to_string(),
+                on_active_key: on_active(Some(&s.key_id)),
+                name: provider.unwrap_or(s.name),
+                key_id: Some(s.key_id),
+                updated_at: s.updated_at,
+            }
+        })
+        .collect();
+    listed.extend(stored_credentials.into_iter().map(|c| AdminStoredSecret {
+        kind: "credential".to_string(),
+        on_active_key: on_active(c.secret_key_id.as_deref()),
+        name: c.provider,
+        key_id: c.secret_key_id,
+        updated_at: c.updated_at,
+    }));
+
+    Ok(AdminSecretsResponse {
+        pending_rotation: listed.iter().filter(|s| !s.on_active_key).count(),
+        previous_key_ids: keyring
+            .as_ref()
+            .map(|k| {
+                k.previous_key_ids()
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        error: keyring.as_ref().err().map(|e| e.to_string()),
+        active_key_id: active,
+        secrets: listed,
+    })
+}
+
+fn parse_secret_setting(name: &str) -> Result<&str, AppError> {
+    if secret_settings::is_managed(name) {
+        return Ok(name);
+    }
+    Err(AppError::new(
+        StatusCode::NOT_FOUND,
+        ApiError::not_found(format!(
+            "{} can't be stored; stored settings are {}",
+            name,
+            secret_settings::MANAGED.join(", ")
+        )),
+    ))
+}
+
+/// Store a secret setting
+///
+/// Encrypts the value with SECRETS_ENCRYPTION_KEY and keeps it in the database
+/// in place of the environment variable of the same name. Only GITHUB_TOKEN,
+/// value takes effect on this replica immediately and on others once notified.
+/// value takes effect on this replica immediately and on others within a minute.
+#[utoipa::path(
+    put,
+    path = "/api/admin/secrets/{name}",
+    params(
+        ("name" = String, Path, description = "Setting name, e.g. GITHUB_TOKEN")
+    ),
+    request_body = AdminSecretRequest,
+    responses(
+        (status = 200, description = "Secret stored", body = AdminSecretsResponse),
+        (status = 400, description = "Empty value", body = ApiError),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "Setting can't be stored", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints or encryption not configured", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn set_secret(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<AdminSecretRequest>,
+) -> Result<Json<AdminSecretsResponse>, AppError> {
+    require_admin(&headers)?;
+    let name = parse_secret_setting(&name)?;
+
+    let value = req.value.trim();
+    if value.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("value must not be empty"),
+        ));
+    }
+
+    secret_settings::set(&state, name, value)
+        .await
+        .map_err(|e| match e {
+            SecretError::NotConfigured | SecretError::InvalidKey(_) => AppError::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                ApiError::new("not_configured", e.to_string()),
+            ),
+            e => {
+                error!("Failed to store {}: {}", name, e);
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiError::internal(format!("Failed to store {}: {}", name, e)),
+                )
+            }
+        })?;
+
+    audit::record(
+        &state,
+        audit::SECRET_CHANGED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({ "name": name, "removed": false }),
+    )
+    .await;
+    Ok(Json(secrets_response(&state).await?))
+}
+
+/// Remove a stored secret setting, reverting to the environment variable
+#[utoipa::path(
+    delete,
+    path = "/api/admin/secrets/{name}",
+    params(
+        ("name" = String, Path, description = "Setting name, e.g. GITHUB_TOKEN")
+    ),
+    responses(
+        (status = 200, description = "Stored secret removed", body = AdminSecretsResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "Setting can't be stored", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn delete_secret(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<AdminSecretsResponse>, AppError> {
+    require_admin(&headers)?;
+    let name = parse_secret_setting(&name)?;
+
+    let removed = secret_settings::remove(&state, name).await.map_err(|e| {
+        error!("Failed to remove stored {}: {}", name, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to remove {}: {}", name, e)),
+        )
+    })?;
+
+    if removed {
+        info!("Stored {} removed", name);
+        audit::record(
+            &state,
+            audit::SECRET_CHANGED,
+            audit::ACTOR_ADMIN,
+            None,
+            None,
+            serde_json::json!({ "name": name, "removed": true }),
+        )
+        .await;
+    }
+    Ok(Json(secrets_response(&state).await?))
+}
+
+/// Reseal stored secrets with the active encryption key
+///
+/// After SECRETS_ENCRYPTION_KEY is changed and the old key moved to
+/// SECRETS_PREVIOUS_KEYS, this re-encrypts every secret and provider credential
+/// still sealed with an old key, in one transaction. Ones that open with no
+/// configured key are reported and left as they were. Once nothing is pending
+/// the old key can be dropped.
+#[utoipa::path(
+    post,
+    path = "/api/admin/secrets/rotate",
+    responses(
+        (status = 200, description = "Secrets resealed", body = AdminSecretsRotateResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints or encryption not configured", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn rotate_secrets(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminSecretsRotateResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let keyring = Keyring::from_env().map_err(|e| {
+        AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::new("not_configured", e.to_string()),
+        )
+    })?;
+    let rotation = secrets::rotate(&state, &keyring).await.map_err(|e| {
+        error!("Failed to rotate secrets: {}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to rotate secrets: {}", e)),
+        )
+    })?;
+
+    for failed in &rotation.failed {
+        warn!("Left {} as it was: it opens with no configured key", failed);
+    }
+    info!(
+        "Resealed {} secrets and {} credentials with key {}",
+        rotation.secrets,
+        rotation.credentials,
+        keyring.active_key_id()
+    );
+    audit::record(
+        &state,
+        audit::SECRETS_ROTATED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({
+            "key_id": keyring.active_key_id(),
+            "secrets": rotation.secrets,
+            "credentials": rotation.credentials,
+            "failed": rotation.failed,
+        }),
+    )
+    .await;
+
+    Ok(Json(AdminSecretsRotateResponse {
+        secrets: rotation.secrets,
+        credentials: rotation.credentials,
+        failed: rotation.failed,
+        active_key_id: keyring.active_key_id().to_string(),
+    }))
+}
+
+fn hit_rate(hits: u64, misses: u64) -> Option<f64> {
+    let total = hits + misses;
+    (total > 0).then(|| hits as f64 / total as f64)
+}
+
+/// Operational overview for an ops dashboard
+///
+/// Index size, distill cache size and hit rates, job queue depth, LLM spend
+/// for the current month, free disk space and recent errors. Hit rates and logged errors cover
+/// this API process since it started; failed jobs come from the database.
+#[utoipa::path(
+    get,
+    path = "/api/admin/overview",
+    responses(
+        (status = 200, description = "Operational statistics", body = AdminOverviewResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn overview(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminOverviewResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let db_error = |e: sqlx::Error| {
+        error!("Failed to collect admin overview: {}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to collect statistics: {}", e)),
+        )
+    };
+
+    let now = Utc::now();
+    let month_start = Utc
+        .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now);
+
+    let index = stats::index_stats(read_pool(&state))
+        .await
+        .map_err(db_error)?;
+    let cache_size = stats::distill_cache_size(read_pool(&state))
+        .await
+        .map_err(db_error)?;
+    let job_counts = jobs::count_by_status(&state).await.map_err(db_error)?;
+    let oldest_queued_at = jobs::oldest_queued_at(&state).await.map_err(db_error)?;
+    let spend = llm_usage::usage_since(&state, month_start)
+        .await
+        .map_err(db_error)?;
+    let failed_jobs = jobs::recent_failures(&state, RECENT_ERROR_LIMIT as i64)
+        .await
+        .map_err(db_error)?;
+
+    let job_count = |status: &str| {
+        job_counts
+            .iter()
+            .find(|(s, _)| s == status)
+            .map(|(_, n)| *n)
+            .unwrap_or(0)
+    };
+
+    let counters = distill::cache_counters();
+
+    let mut recent_errors: Vec<AdminRecentError> = error_log::recent(RECENT_ERROR_LIMIT)
+        .into_iter()
+        .map(|e| AdminRecentError {
+            at: e.at,
+            source: "api".to_string(),
+            context: e.target,
+            message: e.message,
+            request_id: e.request_id,
+        })
+        .chain(failed_jobs.into_iter().map(|job| AdminRecentError {
+            at: job.finished_at.or(job.started_at).unwrap_or(job.created_at),
+            source: "job".to_string(),
+            context: format!("{} job {}", job.kind, job.id),
+            message: job.last_error.unwrap_or_default(),
+            request_id: None,
+        }))
+        .collect();
+    recent_errors.sort_by_key(|e| std::cmp::Reverse(e.at));
+    recent_errors.truncate(RECENT_ERROR_LIMIT);
+
+    Ok(Json(AdminOverviewResponse {
+        generated_at: now,
+        index: AdminIndexStats {
+            repos_tracked: index.repos_tracked,
+            commits_indexed: index.commits_indexed,
+            commits_distilled: index.commits_distilled,
+        },
+        distill_cache: AdminDistillCacheStats {
+            commit_entries: cache_size.commit_entries,
+            commit_contents: cache_size.commit_contents,
+            commit_bytes: cache_size.commit_bytes,
+            file_entries: cache_size.file_entries,
+            file_bytes: cache_size.file_bytes,
+            commit_hits: counters.commit_hits,
+            commit_misses: counters.commit_misses,
+            commit_hit_rate: hit_rate(counters.commit_hits, counters.commit_misses),
+            file_hits: counters.file_hits,
+            file_misses: counters.file_misses,
+            file_hit_rate: hit_rate(counters.file_hits, counters.file_misses),
+            coalesced_requests: counters.coalesced,
+            counters_since: counters.since,
+        },
+        jobs: AdminJobStats {
+            queued: job_count(jobs::STATUS_QUEUED),
+            running: job_count(jobs::STATUS_RUNNING),
+            succeeded: job_count(jobs::STATUS_SUCCEEDED),
+            failed: job_count(jobs::STATUS_FAILED),
+            oldest_queued_at,
+        },
+        llm_spend: AdminLlmSpend {
+            since: month_start,
+            requests: spend.requests,
+            prompt_tokens: spend.prompt_tokens,
+            completion_tokens: spend.completion_tokens,
+            cost_usd: spend.cost_usd,
+        },
+        disk: disk::stats().await,
+        recent_errors,
+    }))
+}
+
+/// Regenerate summaries produced by an older prompt or distiller version
+///
+/// Flags stale summaries, then regenerates up to `batch` of them: as
+/// background jobs when JOB_QUEUE is enabled, otherwise before responding.
+/// The same pass runs periodically in the background (see SUMMARY_REFRESH_*).
+#[utoipa::path(
+    post,
+    path = "/api/admin/summaries/refresh",
+    params(AdminRefreshSummariesQuery),
+    responses(
+        (status = 200, description = "Refresh pass finished", body = AdminRefreshSummariesResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn refresh_stale_summaries(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminRefreshSummariesQuery>,
+) -> Result<Json<AdminRefreshSummariesResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let batch = query
+        .batch
+        .unwrap_or_else(|| summaries::RefreshSettings::from_env().batch)
+        .clamp(1, 1000);
+    let outcome = summaries::refresh_stale(&state, batch, audit::ACTOR_ADMIN)
+        .await
+        .map_err(|e| {
+            error!("Stale summary refresh failed: {:#}", e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to refresh stale summaries: {}", e)),
+            )
+        })?;
+
+    info!(
+        "Admin stale summary refresh: marked={}, queued={}, regenerated={}, failed={}",
+        outcome.marked_stale, outcome.queued, outcome.regenerated, outcome.failed
+    );
+
+    Ok(Json(AdminRefreshSummariesResponse {
+        prompt_version: hook::OVERVIEW_PROMPT_VERSION.to_string(),
+        distiller_version: distill::distiller_version().to_string(),
+        marked_stale: outcome.marked_stale,
+        queued: outcome.queued,
+        regenerated: outcome.regenerated,
+        failed: outcome.failed,
+        remaining_stale: outcome.remaining,
+    }))
+}
+
+/// List tracked repositories with their stored commit counts
+#[utoipa::path(
+    get,
+    path = "/api/admin/repos",
+    responses(
+        (status = 200, description = "Tracked repositories", body = AdminReposResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_repos(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminReposResponse>, AppError> {
+    require_admin(&headers)?;
+
+    Ok(Json(AdminReposResponse {
+        repos: tracked_repos(&state).await?,
+    }))
+}
+
+async fn tracked_repos(pool: &PgPool) -> Result<Vec<AdminRepo>, AppError> {
+    let repos = repos::list_repos(pool).await.map_err(|e| {
+        error!("Failed to list repos: {}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to list repos: {}", e)),
+        )
+    })?;
+
+    Ok(repos
+        .into_iter()
+        .map(|r| AdminRepo {
+            id: r.id,
+            provider: r.provider,
+            slug: r.slug,
+            repo_url: r.repo_url,
+            commit_count: r.commit_count,
+            last_commit_date: r.last_commit_date,
+            commit_status: r.commit_status,
+        })
+        .collect())
+}
+
+/// Accept a full clone URL, or an "owner/name" slug meaning a GitHub repository
+fn repo_url_from_input(input: &str) -> String {
+    let input = input.trim();
+    if input.contains("://") || input.contains('@') {
+        input.to_string()
+    } else {
+        github::repo_url(input.trim_end_matches(".git"))
+    }
+}
+
+/// Move a repository's stored data to a new URL
+///
+/// Use after a repository is renamed, transferred or moved to another provider.
+/// Commits, parts, blobs, feedback and release notes follow the repository.
+#[utoipa::path(
+    post,
+    path = "/api/admin/repos/rename",
+    request_body = AdminRenameRepoRequest,
+    responses(
+        (status = 200, description = "Repository renamed", body = AdminRepo),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "No repository matches `from`", body = ApiError),
+        (status = 409, description = "`to` is invalid or already tracked", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn rename_repo(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AdminRenameRepoRequest>,
+) -> Result<Json<AdminRepo>, AppError> {
+    require_admin(&headers)?;
+
+    let from = repo_url_from_input(&request.from);
+    let to = repo_url_from_input(&request.to);
+    let outcome = repos::rename_repo(&state, &from, &to).await.map_err(|e| {
+        error!("Failed to rename repo {} to {}: {}", from, to, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to rename repo: {}", e)),
+        )
+    })?;
+
+    match outcome {
+        RenameOutcome::Renamed(renamed) => {
+            info!("Renamed repo {} to {}", from, renamed.repo_url);
+            audit::record(
+                &state,
+                audit::REPO_RENAMED,
+                audit::ACTOR_ADMIN,
+                Some(&renamed.repo_url),
+                None,
+                serde_json::json!({ "from": from }),
+            )
+            .await;
+            tracked_repos(&state)
+                .await?
+                .into_iter()
+                .find(|r| r.id == renamed.id)
+                .map(Json)
+                .ok_or_else(|| {
+                    AppError::new(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        ApiError::internal("Renamed repository disappeared"),
+                    )
+                })
+        }
+        RenameOutcome::NotFound => Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("Repository not tracked: {}", from)),
+        )),
+        RenameOutcome::Conflict => Err(AppError::new(
+            StatusCode::CONFLICT,
+            ApiError::new(
+                "conflict",
+                format!("{} is not a valid repository URL or is already tracked", to),
+            ),
+        )),
+    }
+}
+
+/// Turn GitHub commit statuses on or off for a repository
+///
+/// When on, each commit processed by the webhook gets a status summarizing its
+/// CI checks (e.g. "grokicad: analyzed, 2 warnings") that links to the commit's
+/// report page. Posted with the GitHub App credentials (GITHUB_APP_ID and
+/// GITHUB_APP_PRIVATE_KEY_PATH), else with GITHUB_TOKEN.
+#[utoipa::path(
+    put,
+    path = "/api/admin/repos/commit-status",
+    request_body = AdminRepoCommitStatusRequest,
+    responses(
+        (status = 200, description = "Setting changed", body = AdminRepo),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "Repository not tracked", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn set_repo_commit_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AdminRepoCommitStatusRequest>,
+) -> Result<Json<AdminRepo>, AppError> {
+    require_admin(&headers)?;
+
+    let repo_url = repo_url_from_input(&request.repo);
+    let repo = repos::set_commit_status(&state, &repo_url, request.enabled)
+        .await
+        .map_err(|e| {
+            error!("Failed to set commit statuses of {}: {}", repo_url, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to set commit statuses: {}", e)),
+            )
+        })?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!("Repository not tracked: {}", repo_url)),
+            )
+        })?;
+
+    info!(
+        "Commit statuses {} for {}",
+        if request.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        },
+        repo.repo_url
+    );
+    audit::record(
+        &state,
+        audit::REPO_COMMIT_STATUS_CHANGED,
+        audit::ACTOR_ADMIN,
+        Some(&repo.repo_url),
+        None,
+        serde_json::json!({ "enabled": request.enabled }),
+    )
+    .await;
+    tracked_repos(&state)
+        .await?
+        .into_iter()
+        .find(|r| r.id == repo.id)
+        .map(Json)
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal("Repository disappeared"),
+            )
+        })
+}
+
+/// Browse the audit trail of processing actions
+///
+/// Records onboarding, cache clears and invalidations, summary generation and
+/// admin changes, with the actor that triggered each and its parameters.
+#[utoipa::path(
+    get,
+    path = "/api/admin/audit",
+    params(AdminAuditQuery),
+    responses(
+        (status = 200, description = "Audit entries, newest first", body = AdminAuditResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_audit(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminAuditQuery>,
+) -> Result<Json<AdminAuditResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let filter = kdb_audit::AuditFilter {
+        action: query.action,
+        actor: query.actor,
+        repo_url: query.repo.as_deref().map(repo_url_from_input),
+        since: query.since,
+        until: query.until,
+    };
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let entries = kdb_audit::list_entries(read_pool(&state), &filter, limit)
+        .await
+        .map_err(|e| {
+            error!("Failed to list audit entries: {}", e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to list audit entries: {}", e)),
+            )
+        })?;
+
+    Ok(Json(AdminAuditResponse {
+        entries: entries
+            .into_iter()
+            .map(|e| AdminAuditEntry {
+                id: e.id,
+                action: e.action,
+                actor: e.actor,
+                repo_url: e.repo_url,
+                commit: e.commit_hash,
+                params: e.params,
+                created_at: e.created_at,
+            })
+            .collect(),
+    }))
+}
+
+fn jobs_error(action: &str, e: anyhow::Error) -> AppError {
+    error!("Failed to {}: {:#}", action, e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Failed to {}: {}", action, e)),
+    )
+}
+
+/// Failed jobs with the history of their runs
+async fn failed_jobs(
+    pool: &PgPool,
+    filter: &FailedJobFilter,
+    limit: i64,
+) -> anyhow::Result<Vec<AdminFailedJob>> {
+    let failed = jobs::list_failed_jobs(pool, filter, limit).await?;
+    let ids: Vec<i64> = failed.iter().map(|job| job.id).collect();
+    let mut runs: HashMap<i64, Vec<JobRunEntry>> = HashMap::new();
+    for run in jobs::runs_for_jobs(pool, &ids).await? {
+        runs.entry(run.job_id).or_default().push(job_run_entry(run));
+    }
+    Ok(failed
+        .into_iter()
+        .map(|job| AdminFailedJob {
+            runs: runs.remove(&job.id).unwrap_or_default(),
+            id: job.id,
+            kind: job.kind,
+            payload: job.payload,
+            attempts: job.attempts,
+            last_error: job.last_error,
+            created_at: job.created_at,
+            finished_at: job.finished_at,
+        })
+        .collect())
+}
+
+/// List jobs that exhausted their attempts
+///
+/// Each job comes with its runs: which worker ran every attempt, how long it
+/// took and the error it ended with.
+#[utoipa::path(
+    get,
+    path = "/api/admin/jobs/failed",
+    params(AdminFailedJobsQuery),
+    responses(
+        (status = 200, description = "Failed jobs, most recently failed first", body = AdminFailedJobsResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_failed_jobs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminFailedJobsQuery>,
+) -> Result<Json<AdminFailedJobsResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let filter = FailedJobFilter {
+        ids: None,
+        kind: query.kind,
+        since: query.since,
+    };
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let jobs = failed_jobs(read_pool(&state), &filter, limit)
+        .await
+        .map_err(|e| jobs_error("list failed jobs", e))?;
+
+    Ok(Json(AdminFailedJobsResponse { jobs }))
+}
+
+async fn retry(pool: &PgPool, filter: &FailedJobFilter, limit: i64) -> Result<Vec<i64>, AppError> {
+    let retried: Vec<i64> = retry_failed(pool, filter, limit)
+        .await
+        .map_err(|e| jobs_error("retry jobs", e))?
+        .into_iter()
+        .map(|job| job.id)
+        .collect();
+    if !retried.is_empty() {
+        audit::record(
+            pool,
+            audit::JOBS_RETRIED,
+            audit::ACTOR_ADMIN,
+            None,
+            None,
+            serde_json::json!({ "ids": retried }),
+        )
+        .await;
+    }
+    Ok(retried)
+}
+
+/// Retry one failed job
+///
+/// The job goes back on the queue with as many attempts as a new job (run in
+/// the API process without JOB_QUEUE). Earlier runs stay in its history.
+#[utoipa::path(
+    post,
+    path = "/api/admin/jobs/{id}/retry",
+    params(
+        ("id" = i64, Path, description = "Job id")
+    ),
+    responses(
+        (status = 200, description = "Job retried", body = AdminRetryJobsResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "Job not found", body = ApiError),
+        (status = 409, description = "Job has not failed", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn retry_job(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<i64>,
+) -> Result<Json<AdminRetryJobsResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let filter = FailedJobFilter {
+        ids: Some(vec![id]),
+        ..Default::default()
+    };
+    let retried = retry(&state, &filter, 1).await?;
+    if retried.is_empty() {
+        let job = jobs::get_job(&state, id)
+            .await
+            .map_err(|e| jobs_error("load job", e.into()))?;
+        return Err(match job {
+            None => AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!("Job {} not found", id)),
+            ),
+            Some(job) => AppError::new(
+                StatusCode::CONFLICT,
+                ApiError::new(
+                    "conflict",
+                    format!("Job {} is {}, not failed", id, job.status),
+                ),
+            ),
+        });
+    }
+
+    Ok(Json(AdminRetryJobsResponse { retried }))
+}
+
+/// Retry failed jobs in bulk
+///
+/// Retries the most recently failed jobs matching the request, e.g. every
+/// `regenerate_summary` job that failed since an xAI outage began. An empty
+/// request retries any failed job, up to `limit`.
+#[utoipa::path(
+    post,
+    path = "/api/admin/jobs/retry",
+    request_body = AdminRetryJobsRequest,
+    responses(
+        (status = 200, description = "Jobs retried", body = AdminRetryJobsResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn retry_jobs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AdminRetryJobsRequest>,
+) -> Result<Json<AdminRetryJobsResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let filter = FailedJobFilter {
+        ids: request.ids,
+        kind: request.kind,
+        since: request.since,
+    };
+    let limit = request.limit.unwrap_or(100).clamp(1, 1000);
+    let retried = retry(&state, &filter, limit).await?;
+    info!("Retried {} failed jobs", retried.len());
+
+    Ok(Json(AdminRetryJobsResponse { retried }))
+}
+
+fn quarantined_commit(entry: CommitFailure) -> AdminQuarantinedCommit {
+    AdminQuarantinedCommit {
+        repo_url: entry.repo_url,
+        commit: entry.commit_hash,
+        stage: entry.stage,
+        failures: entry.failures,
+        last_error: entry.last_error,
+        first_failed_at: entry.first_failed_at,
+        last_failed_at: entry.last_failed_at,
+        quarantined_at: entry.quarantined_at,
+    }
+}
+
+fn quarantine_error(action: &str, e: sqlx::Error) -> AppError {
+    error!("Failed to {}: {}", action, e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Failed to {}: {}", action, e)),
+    )
+}
+
+/// List quarantined commits
+///
+/// A commit is quarantined once a stage (distillation, or overview generation
+/// in the webhook loop) fails on it QUARANTINE_AFTER_FAILURES times in a row.
+/// The stage skips it from then on, until it's released.
+#[utoipa::path(
+    get,
+    path = "/api/admin/quarantine",
+    params(AdminQuarantineQuery),
+    responses(
+        (status = 200, description = "Quarantined commits, most recently quarantined first", body = AdminQuarantineResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_quarantine(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminQuarantineQuery>,
+) -> Result<Json<AdminQuarantineResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let repo_url = query.repo.as_deref().map(repo_url_from_input);
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let commits = quarantine::list_quarantined(
+        read_pool(&state),
+        repo_url.as_deref(),
+        query.stage.as_deref(),
+        limit,
+    )
+    .await
+    .map_err(|e| quarantine_error("list quarantined commits", e))?;
+
+    Ok(Json(AdminQuarantineResponse {
+        commits: commits.into_iter().map(quarantined_commit).collect(),
+    }))
+}
+
+/// Release a quarantined commit
+///
+/// Resets the commit's failure count so the next webhook, job or request
+/// processes it again, e.g. after a distiller fix is deployed.
+#[utoipa::path(
+    post,
+    path = "/api/admin/quarantine/release",
+    request_body = AdminQuarantineReleaseRequest,
+    responses(
+        (status = 200, description = "Released entries", body = AdminQuarantineReleaseResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn release_quarantine(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AdminQuarantineReleaseRequest>,
+) -> Result<Json<AdminQuarantineReleaseResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let repo_url = repo_url_from_input(&request.repo);
+    let released = quarantine::release(
+        &state,
+        &repo_url,
+        request.commit.trim(),
+        request.stage.as_deref(),
+    )
+    .await
+    .map_err(|e| quarantine_error("release quarantined commit", e))?;
+
+    if !released.is_empty() {
+        let stages: Vec<&str> = released.iter().map(|r| r.stage.as_str()).collect();
+        info!(
+            "Released {} at {} from quarantine ({})",
+            repo_url,
+            request.commit,
+            stages.join(", ")
+        );
+        audit::record(
+            &state,
+            audit::QUARANTINE_RELEASED,
+            audit::ACTOR_ADMIN,
+            Some(&repo_url),
+            Some(request.commit.trim()),
+            serde_json::json!({ "stages": stages }),
+        )
+        .await;
+    }
+
+    Ok(Json(AdminQuarantineReleaseResponse {
+        released: released.into_iter().map(quarantined_commit).collect(),
+    }))
+}
+
+/// Show the log filter in effect
+#[utoipa::path(
+    get,
+    path = "/api/admin/log-filter",
+    responses(
+        (status = 200, description = "Log filter in effect", body = AdminLogFilterResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Logging has no reloadable filter", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn get_log_filter(headers: HeaderMap) -> Result<Json<AdminLogFilterResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let filter = log_filter::current().ok_or_else(|| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal("Logging has no reloadable filter"),
+        )
+    })?;
+    Ok(Json(AdminLogFilterResponse {
+        filter,
+        previous: None,
+    }))
+}
+
+/// Change the log filter without restarting
+///
+/// Takes `EnvFilter` directives, e.g. "info,kicad_backend::services::git=debug"
+/// to debug clones. The change lasts until the process restarts; send no
+/// filter to go back to the one it started with (LOG_FILTER or RUST_LOG).
+#[utoipa::path(
+    put,
+    path = "/api/admin/log-filter",
+    request_body = AdminLogFilterRequest,
+    responses(
+        (status = 200, description = "Log filter changed", body = AdminLogFilterResponse),
+        (status = 400, description = "Invalid filter directives", body = ApiError),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Logging has no reloadable filter", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn set_log_filter(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AdminLogFilterRequest>,
+) -> Result<Json<AdminLogFilterResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let filter = match request.filter.as_deref().filter(|f| !f.trim().is_empty()) {
+        Some(directives) => Some(log_filter::parse(directives).map_err(|e| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!("{:#}", e)),
+            )
+        })?),
+        None => None,
+    };
+    let previous = log_filter::current();
+    let filter = log_filter::set(filter).map_err(|e| {
+        error!("Failed to change the log filter: {:#}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("{:#}", e)),
+        )
+    })?;
+
+    warn!("Log filter changed from {:?} to {:?}", previous, filter);
+    audit::record(
+        &state,
+        audit::LOG_FILTER_CHANGED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({ "filter": filter, "previous": previous }),
+    )
+    .await;
+
+    Ok(Json(AdminLogFilterResponse { filter, previous }))
+}
+
+/// Show the effective configuration
+///
+/// Lists every setting documented in .env.example with its value in this
+/// process, next to a few derived settings. Keys, secrets, tokens, passwords
+/// and passwords inside URLs are redacted.
+#[utoipa::path(
+    get,
+    path = "/api/admin/config",
+    responses(
+        (status = 200, description = "Effective configuration", body = AdminConfigResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn get_config(headers: HeaderMap) -> Result<Json<AdminConfigResponse>, AppError> {
+    require_admin(&headers)?;
+
+    Ok(Json(AdminConfigResponse {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        log_filter: log_filter::current(),
+        distiller_backend: BackendChoice::configured().name().to_string(),
+        distiller_version: distill::distiller_version().to_string(),
+        job_queue: queue_enabled(),
+        demo_mode: demo::config().is_some(),
+        blob_store: blob_store::store().name().to_string(),
+        settings: runtime_config::entries(),
+    }))
+}
+
+/// Show storage usage per repository
+///
+/// Breaks Postgres (commits, parts, distilled JSON) and blob store (schematic
+/// images, renders, exports) usage down by repository, largest first, so retention can be
+/// applied to the projects that need it.
+#[utoipa::path(
+    get,
+    path = "/api/admin/storage",
+    params(AdminStorageQuery),
+    responses(
+        (status = 200, description = "Storage usage", body = AdminStorageResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn storage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<AdminStorageQuery>,
+) -> Result<Json<AdminStorageResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let internal = |e: sqlx::Error| {
+        error!("Failed to read storage usage: {}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to read storage usage: {}", e)),
+        )
+    };
+    let pool = read_pool(&state);
+    let repo_url = query.repo.as_deref().map(repo_url_from_input);
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000) as usize;
+
+    let totals = stats::storage_totals(pool).await.map_err(internal)?;
+    let mut repos: HashMap<String, AdminRepoStorage> = HashMap::new();
+    for usage in stats::repo_storage(pool, repo_url.as_deref())
+        .await
+        .map_err(internal)?
+    {
+        repos.insert(
+            usage.repo_url.clone(),
+            AdminRepoStorage {
+                repo_url: usage.repo_url,
+                schematics: usage.schematics,
+                parts: usage.parts,
+                distilled_commits: usage.distilled_commits,
+                distilled_bytes: usage.distilled_bytes,
+                ..Default::default()
+            },
+        );
+    }
+    for usage in stats::repo_blob_usage(pool, repo_url.as_deref())
+        .await
+        .map_err(internal)?
+    {
+        let repo = repos
+            .entry(usage.repo_url.clone())
+            .or_insert_with(|| AdminRepoStorage {
+                repo_url: usage.repo_url,
+                ..Default::default()
+            });
+        repo.blob_bytes += usage.bytes;
+        repo.blobs.push(AdminBlobUsage {
+            kind: usage.kind,
+            blobs: usage.blobs,
+            bytes: usage.bytes,
+        });
+    }
+
+    let mut repos: Vec<AdminRepoStorage> = repos
+        .into_values()
+        .map(|mut repo| {
+            repo.total_bytes = repo.distilled_bytes + repo.blob_bytes;
+            repo
+        })
+        .collect();
+    repos.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then_with(|| a.repo_url.cmp(&b.repo_url))
+    });
+    repos.truncate(limit);
+
+    Ok(Json(AdminStorageResponse {
+        generated_at: Utc::now(),
+        totals: AdminStorageTotals {
+            database_bytes: totals.database_bytes,
+            distilled_bytes: totals.distilled_bytes,
+            file_cache_bytes: totals.file_cache_bytes,
+            blob_bytes: totals.blob_bytes,
+            blob_store: blob_store::store().name().to_string(),
+        },
+        repos,
+    }))
+}
+
+/// Delete a file from the blob store
+///
+/// Removes the file's metadata, and its bytes unless other files share them,
+/// e.g. to apply retention to the renders and exports listed by `/api/admin/storage`.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/blobs/{key}",
+    params(
+        ("key" = String, Path, description = "Blob key, e.g. render/owner/repo/<commit>/report.html")
+    ),
+    responses(
+        (status = 204, description = "Blob deleted"),
+        (status = 400, description = "Invalid blob key", body = ApiError),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "No blob with this key", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn delete_blob(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    require_admin(&headers)?;
+
+    if let Err(e) = blob_store::validate_key(&key) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(e.to_string()),
+        ));
+    }
+    let deleted = blob_store::delete_blob(&state, &key).await.map_err(|e| {
+        error!("Failed to delete blob {}: {:#}", key, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to delete blob: {}", e)),
+        )
+    })?;
+    if !deleted {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("No blob {}", key)),
+        ));
+    }
+
+    info!("Deleted blob {}", key);
+    audit::record(
+        &state,
+        audit::BLOB_DELETED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({ "key": key }),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn admin_api_key(key: ApiKey) -> AdminApiKey {
+    AdminApiKey {
+        id: key.id,
+        name: key.name,
+        key_prefix: key.key_prefix,
+        request_quota: key.request_quota,
+        llm_token_quota: key.llm_token_quota,
+        cpu_seconds_quota: key.cpu_seconds_quota,
+        revoked_at: key.revoked_at,
+        created_at: key.created_at,
+    }
+}
+
+fn key_quotas(request: &AdminApiKeyQuotasRequest) -> Result<KeyQuotas, AppError> {
+    let negative = request.request_quota.is_some_and(|q| q < 0)
+        || request.llm_token_quota.is_some_and(|q| q < 0)
+        || request
+            .cpu_seconds_quota
+            .is_some_and(|q| q < 0.0 || q.is_nan());
+    if negative {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("Quotas must be zero or more"),
+        ));
+    }
+    Ok(KeyQuotas {
+        request_quota: request.request_quota,
+        llm_token_quota: request.llm_token_quota,
+        cpu_seconds_quota: request.cpu_seconds_quota,
+    })
+}
+
+fn api_key_error(action: &str, e: sqlx::Error) -> AppError {
+    error!("Failed to {}: {}", action, e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Failed to {}: {}", action, e)),
+    )
+}
+
+/// Issue an API key with monthly quotas
+///
+/// The key is returned once; only its hash is stored.
+#[utoipa::path(
+    post,
+    path = "/api/admin/keys",
+    request_body = AdminApiKeyRequest,
+    responses(
+        (status = 200, description = "Key created", body = AdminApiKeyCreateResponse),
+        (status = 400, description = "Missing name or negative quota", body = ApiError),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AdminApiKeyRequest>,
+) -> Result<Json<AdminApiKeyCreateResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("name must not be empty"),
+        ));
+    }
+    let quotas = key_quotas(&request.quotas)?;
+
+    let secret = format!("gk_{}", uuid::Uuid::new_v4().simple());
+    let key = api_keys::create_key(
+        &state,
+        name,
+        &quota::hash_key(&secret),
+        &secret[..10],
+        &quotas,
+    )
+    .await
+    .map_err(|e| api_key_error("create API key", e))?;
+
+    info!("Created API key {} for {}", key.id, key.name);
+    audit::record(
+        &state,
+        audit::API_KEY_CHANGED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({ "id": key.id, "change": "created", "quotas": quotas }),
+    )
+    .await;
+
+    Ok(Json(AdminApiKeyCreateResponse {
+        key: admin_api_key(key),
+        secret,
+    }))
+}
+
+/// List API keys with their quotas
+#[utoipa::path(
+    get,
+    path = "/api/admin/keys",
+    responses(
+        (status = 200, description = "API keys", body = AdminApiKeysResponse),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<AdminApiKeysResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let keys = api_keys::list_keys(&state)
+        .await
+        .map_err(|e| api_key_error("list API keys", e))?;
+    Ok(Json(AdminApiKeysResponse {
+        keys: keys.into_iter().map(admin_api_key).collect(),
+    }))
+}
+
+/// Replace an API key's monthly quotas
+#[utoipa::path(
+    put,
+    path = "/api/admin/keys/{id}",
+    params(
+        ("id" = i64, Path, description = "API key id")
+    ),
+    request_body = AdminApiKeyQuotasRequest,
+    responses(
+        (status = 200, description = "Quotas updated", body = AdminApiKey),
+        (status = 400, description = "Negative quota", body = ApiError),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "No such key", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn set_api_key_quotas(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+    Json(request): Json<AdminApiKeyQuotasRequest>,
+) -> Result<Json<AdminApiKey>, AppError> {
+    require_admin(&headers)?;
+    let quotas = key_quotas(&request)?;
+
+    let key = api_keys::set_quotas(&state, id, &quotas)
+        .await
+        .map_err(|e| api_key_error("update API key", e))?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!("No API key {}", id)),
+            )
+        })?;
+
+    audit::record(
+        &state,
+        audit::API_KEY_CHANGED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({ "id": id, "change": "quotas", "quotas": quotas }),
+    )
+    .await;
+    Ok(Json(admin_api_key(key)))
+}
+
+/// Revoke an API key. Its usage history is kept.
+#[utoipa::path(
+    delete,
+    path = "/api/admin/keys/{id}",
+    params(
+        ("id" = i64, Path, description = "API key id")
+    ),
+    responses(
+        (status = 204, description = "Key revoked"),
+        (status = 401, description = "Invalid admin token", body = ApiError),
+        (status = 404, description = "No active key with this id", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError)
+    ),
+    tag = "admin"
+)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    headers: HeaderMap,
+) -> Result<StatusCode, AppError> {
+    require_admin(&headers)?;
+
+    let revoked = api_keys::revoke_key(&state, id)
+        .await
+        .map_err(|e| api_key_error("revoke API key", e))?;
+    if !revoked {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("No active API key {}", id)),
+        ));
+    }
+
+    info!("Revoked API key {}", id);
+    audit::record(
+        &state,
+        audit::API_KEY_CHANGED,
+        audit::ACTOR_ADMIN,
+        None,
+        None,
+        serde_json::json!({ "id": id, "change": "revoked" }),
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
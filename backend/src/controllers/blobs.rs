@@ -0,0 +1,153 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::blob_store::{self, BlobKind};
+use crate::services::github;
+use crate::types::{ApiError, BlobFileQuery, BlobInfo, BlobListRequest, BlobListResponse};
+use kicad_db::{blobs, read_pool, PgPool};
+
+pub type AppState = Arc<PgPool>;
+
+/// List stored files (schematic images, renders, datasheets, export archives) for a commit
+#[utoipa::path(
+    post,
+    path = "/api/blobs/list",
+    request_body = BlobListRequest,
+    responses(
+        (status = 200, description = "Stored files with signed download links", body = BlobListResponse),
+        (status = 400, description = "Unknown blob kind", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "blobs"
+)]
+pub async fn list_blobs(
+    State(state): State<AppState>,
+    Json(mut req): Json<BlobListRequest>,
+) -> Result<Json<BlobListResponse>, AppError> {
+    if let Some(kind) = req.kind.as_deref() {
+        if BlobKind::parse(kind).is_none() {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!("Unknown blob kind: {}", kind)),
+            ));
+        }
+    }
+
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
+    let internal = |e: String| {
+        error!(
+            "Failed to list blobs for {}@{}: {}",
+            req.repo, req.commit, e
+        );
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to list blobs: {}", e)),
+        )
+    };
+
+    let repo_url = github::repo_url(&req.repo);
+    let records = blobs::list_blobs(
+        read_pool(&state),
+        &repo_url,
+        &req.commit,
+        req.kind.as_deref(),
+    )
+    .await
+    .map_err(|e| internal(e.to_string()))?;
+
+    let store = blob_store::store();
+    let mut infos = Vec::with_capacity(records.len());
+    for record in records {
+        let signed = store
+            .record_url(&record)
+            .map_err(|e| internal(e.to_string()))?;
+        infos.push(BlobInfo {
+            key: record.key,
+            kind: record.kind,
+            content_type: record.content_type,
+            size_bytes: record.size_bytes,
+            sha256: record.sha256,
+            url: signed.url,
+            url_expires_at: signed.expires_at,
+            created_at: record.created_at,
+        });
+    }
+
+    Ok(Json(BlobListResponse {
+        repo: req.repo,
+        commit: req.commit,
+        blobs: infos,
+    }))
+}
+
+/// Download a file from the local blob store using a signed link.
+/// With the S3 backend, links point at the bucket directly and this endpoint is unused.
+#[utoipa::path(
+    get,
+    path = "/api/blobs/file/{key}",
+    params(
+        ("key" = String, Path, description = "Blob key"),
+        BlobFileQuery
+    ),
+    responses(
+        (status = 200, description = "File contents"),
+        (status = 403, description = "Missing, invalid or expired signature", body = ApiError),
+        (status = 404, description = "Blob not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "blobs"
+)]
+pub async fn get_blob_file(
+    State(state): State<AppState>,
+    Path(key): Path<String>,
+    Query(query): Query<BlobFileQuery>,
+) -> Result<Response, AppError> {
+    let store = blob_store::store();
+    if !store.verify(&key, query.expires, &query.signature) {
+        return Err(AppError::new(
+            StatusCode::FORBIDDEN,
+            ApiError::new("forbidden", "Invalid or expired blob link"),
+        ));
+    }
+
+    let not_found = || {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("Blob {} not found", key)),
+        )
+    };
+    let internal = |e: anyhow::Error| {
+        error!("Failed to read blob {}: {:#}", key, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to read blob: {}", e)),
+        )
+    };
+
+    let record = blobs::get_blob(&state, &key)
+        .await
+        .map_err(|e| internal(e.into()))?
+        .ok_or_else(not_found)?;
+    let data = store
+        .get(record.location())
+        .await
+        .map_err(internal)?
+        .ok_or_else(not_found)?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, record.content_type),
+            (header::CACHE_CONTROL, "private, max-age=300".to_string()),
+        ],
+        data,
+    )
+        .into_response())
+}
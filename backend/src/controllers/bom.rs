@@ -0,0 +1,291 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::controllers::admin::require_admin;
+use crate::controllers::distill::distillation_error;
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::bom;
+use crate::services::distill;
+use crate::services::lcsc::LcscClient;
+use crate::services::{alternates, github, mpn};
+use crate::types::{
+    ApiError, BomLine, BomRequest, BomResponse, PartAlternateEntry, PartAlternateRemoveRequest,
+    PartAlternateRequest, PartAlternatesRequest, PartAlternatesResponse,
+};
+use kicad_db::alternates::{add_alternate, list_alternates, remove_alternate, PartAlternate};
+use kicad_db::{read_pool, PgPool};
+
+pub type AppState = Arc<PgPool>;
+
+/// Get the bill of materials for a repository at a specific commit
+///
+/// Components are grouped into lines by value, footprint and MPN. When
+/// `include_lcsc` is set, each line is looked up on LCSC/JLCPCB to report its
+/// assembly part number, basic/extended classification and whether JLCPCB can
+/// assemble it. A line the part can't cover counts as assemblable when one of
+/// its approved alternates can be.
+#[utoipa::path(
+    post,
+    path = "/api/bom",
+    request_body = BomRequest,
+    responses(
+        (status = 200, description = "Bill of materials", body = BomResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "bom"
+)]
+pub async fn get_bom(
+    State(state): State<AppState>,
+    Json(mut req): Json<BomRequest>,
+) -> Result<Json<BomResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("BOM request for {}/{}", req.repo, req.commit);
+
+    let distilled = distill::get_or_distill(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    let components = bom::extract_components(&distilled);
+    let groups = bom::group_components(&components);
+    let total_components = groups.iter().map(|g| g.references.len()).sum();
+
+    let lcsc = LcscClient::new();
+    let check_lcsc = req.include_lcsc && LcscClient::is_configured();
+    let alternate_groups = alternates::by_mpn(&state, &req.repo).await;
+    let mut lines = Vec::with_capacity(groups.len());
+
+    for group in groups {
+        let mut line = BomLine {
+            quantity: group.references.len(),
+            references: group.references,
+            value: group.value,
+            footprint: group.footprint,
+            mpn: group.mpn,
+            manufacturer: group.manufacturer,
+            lcsc_part_number: group.lcsc_part_number,
+            jlc_part_type: None,
+            jlc_assemblable: None,
+            alternates: Vec::new(),
+            assemblable_alternate: None,
+        };
+        if let Some(group) = line.mpn.as_ref().and_then(|m| alternate_groups.get(m)) {
+            line.alternates = group.iter().map(|a| a.alternate_mpn.clone()).collect();
+        }
+
+        // Prefer the LCSC number from the schematic, fall back to the MPN
+        let lookup_key = line.lcsc_part_number.clone().or_else(|| line.mpn.clone());
+        if let (true, Some(key)) = (check_lcsc, lookup_key) {
+            match lcsc.lookup(&key).await {
+                Ok(Some(part)) => {
+                    line.jlc_assemblable = Some(
+                        part.jlc_part_type.is_some() && part.quantity_available.unwrap_or(0) > 0,
+                    );
+                    line.jlc_part_type = part.jlc_part_type;
+                    line.lcsc_part_number = Some(part.lcsc_part_number);
+                }
+                // LCSC doesn't list this exact part, so there's nothing to report
+                Ok(None) => {}
+                Err(e) => warn!("LCSC lookup failed for {}: {}", key, e),
+            }
+        }
+
+        if check_lcsc && line.jlc_assemblable != Some(true) {
+            for alternate in &line.alternates {
+                match lcsc.lookup(alternate).await {
+                    Ok(Some(part))
+                        if part.jlc_part_type.is_some()
+                            && part.quantity_available.unwrap_or(0) > 0 =>
+                    {
+                        line.jlc_assemblable = Some(true);
+                        line.assemblable_alternate = Some(alternate.clone());
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!("LCSC lookup failed for {}: {}", alternate, e),
+                }
+            }
+        }
+
+        lines.push(line);
+    }
+
+    info!(
+        "BOM for {}/{}: {} components in {} lines",
+        req.repo,
+        req.commit,
+        total_components,
+        lines.len()
+    );
+
+    Ok(Json(BomResponse {
+        repo: req.repo,
+        commit: req.commit,
+        total_components,
+        lines,
+    }))
+}
+
+fn alternate_entry(alternate: PartAlternate) -> PartAlternateEntry {
+    PartAlternateEntry {
+        mpn: alternate.mpn,
+        alternate_mpn: alternate.alternate_mpn,
+        manufacturer: alternate.manufacturer,
+        note: alternate.note,
+        created_at: alternate.created_at,
+    }
+}
+
+fn alternates_error(action: &str, e: sqlx::Error) -> AppError {
+    error!("Failed to {}: {}", action, e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Failed to {}: {}", action, e)),
+    )
+}
+
+/// Normalized line and alternate MPNs, or a 400 when either is empty or they match
+fn alternate_pair(line_mpn: &str, alternate_mpn: &str) -> Result<(String, String), AppError> {
+    match (mpn::normalize(line_mpn), mpn::normalize(alternate_mpn)) {
+        (Some(line), Some(alternate)) if line != alternate => Ok((line, alternate)),
+        (Some(_), Some(_)) => Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("alternate_mpn must differ from the line's mpn"),
+        )),
+        _ => Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("mpn and alternate_mpn are required"),
+        )),
+    }
+}
+
+/// Approve an alternate for a BOM line
+///
+/// Alternates are stored per repository against the line's normalized MPN and
+/// apply at every commit. BOM availability checks consider the whole group.
+/// Requires the admin token.
+#[utoipa::path(
+    post,
+    path = "/api/bom/alternates",
+    request_body = PartAlternateRequest,
+    responses(
+        (status = 200, description = "Approved alternate", body = PartAlternateEntry),
+        (status = 400, description = "Missing or identical MPNs, or invalid repository", body = ApiError),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "bom"
+)]
+pub async fn add_part_alternate(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<PartAlternateRequest>,
+) -> Result<Json<PartAlternateEntry>, AppError> {
+    require_admin(&headers)?;
+    let (line_mpn, alternate_mpn) = alternate_pair(&req.mpn, &req.alternate_mpn)?;
+    let repo_url = github::repo_url(&req.repo);
+    let field = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(ToString::to_string)
+    };
+
+    let alternate = add_alternate(
+        &state,
+        &repo_url,
+        &line_mpn,
+        &alternate_mpn,
+        field(&req.manufacturer).as_deref(),
+        field(&req.note).as_deref(),
+    )
+    .await
+    .map_err(|e| alternates_error("store part alternate", e))?
+    .ok_or_else(|| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!("Invalid repository {:?}", req.repo)),
+        )
+    })?;
+
+    info!(
+        "Approved {} as an alternate of {} in {}",
+        alternate_mpn, line_mpn, req.repo
+    );
+    Ok(Json(alternate_entry(alternate)))
+}
+
+/// List the approved alternates of a repository
+#[utoipa::path(
+    post,
+    path = "/api/bom/alternates/list",
+    request_body = PartAlternatesRequest,
+    responses(
+        (status = 200, description = "Approved alternates", body = PartAlternatesResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "bom"
+)]
+pub async fn list_part_alternates(
+    State(state): State<AppState>,
+    Json(req): Json<PartAlternatesRequest>,
+) -> Result<Json<PartAlternatesResponse>, AppError> {
+    let repo_url = github::repo_url(&req.repo);
+    let alternates = list_alternates(read_pool(&state), &repo_url)
+        .await
+        .map_err(|e| alternates_error("list part alternates", e))?;
+
+    Ok(Json(PartAlternatesResponse {
+        repo: req.repo,
+        alternates: alternates.into_iter().map(alternate_entry).collect(),
+    }))
+}
+
+/// Withdraw an approved alternate. Requires the admin token.
+#[utoipa::path(
+    post,
+    path = "/api/bom/alternates/remove",
+    request_body = PartAlternateRemoveRequest,
+    responses(
+        (status = 204, description = "Alternate removed"),
+        (status = 400, description = "Missing or identical MPNs", body = ApiError),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 404, description = "No such alternate", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "bom"
+)]
+pub async fn remove_part_alternate(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<PartAlternateRemoveRequest>,
+) -> Result<StatusCode, AppError> {
+    require_admin(&headers)?;
+    let (line_mpn, alternate_mpn) = alternate_pair(&req.mpn, &req.alternate_mpn)?;
+    let repo_url = github::repo_url(&req.repo);
+
+    let removed = remove_alternate(&state, &repo_url, &line_mpn, &alternate_mpn)
+        .await
+        .map_err(|e| alternates_error("remove part alternate", e))?;
+    if !removed {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!(
+                "{} is not an approved alternate of {}",
+                alternate_mpn, line_mpn
+            )),
+        ));
+    }
+
+    info!(
+        "Removed alternate {} of {} in {}",
+        alternate_mpn, line_mpn, req.repo
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,76 @@
+use axum::http::{header, HeaderMap};
+
+/// An inclusive byte range within a resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// Content-Range header value for this range of a `total`-byte resource
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total)
+    }
+}
+
+/// What a request's Range header asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No usable Range header: send everything
+    Full,
+    Partial(ByteRange),
+    /// The range lies outside the resource (answer 416)
+    Unsatisfiable,
+}
+
+/// Parse a single-range `Range: bytes=...` header against a `total`-byte resource.
+///
+/// Supports `start-end`, `start-` and the suffix form `-length`. Multiple ranges
+/// and malformed headers are ignored, which the spec allows, so the whole
+/// resource is sent instead.
+pub fn parse(headers: &HeaderMap, total: u64) -> RangeRequest {
+    let Some(spec) = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().strip_prefix("bytes="))
+    else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return RangeRequest::Full;
+    };
+
+    let range = match (start.trim(), end.trim()) {
+        ("", "") => return RangeRequest::Full,
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(len) if total > 0 => ByteRange {
+                start: total.saturating_sub(len),
+                end: total - 1,
+            },
+            Ok(_) => return RangeRequest::Unsatisfiable,
+            Err(_) => return RangeRequest::Full,
+        },
+        (start, end) => {
+            let Ok(start) = start.parse::<u64>() else {
+                return RangeRequest::Full;
+            };
+            let end = match end {
+                "" => total.saturating_sub(1),
+                end => match end.parse::<u64>() {
+                    Ok(end) if end >= start => end.min(total.saturating_sub(1)),
+                    _ => return RangeRequest::Full,
+                },
+            };
+            if start >= total {
+                return RangeRequest::Unsatisfiable;
+            }
+            ByteRange { start, end }
+        }
+    };
+    RangeRequest::Partial(range)
+}
@@ -0,0 +1,71 @@
+use axum::{extract::State, http::StatusCode, response::Json};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::controllers::distill::distillation_error;
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::verdict;
+use crate::types::{ApiError, CiVerdictRequest, CiVerdictResponse};
+use kicad_db::PgPool;
+
+pub type AppState = Arc<PgPool>;
+
+/// Judge a commit for CI: diff against the base, electrical rules check, part
+/// obsolescence and the repository's design rules, combined into a
+/// pass/warn/fail verdict
+///
+/// Legacy designs with many existing violations can use `erc_mode: "differential"`
+/// so only violations the change introduced affect the verdict.
+///
+/// Meant to be called from GitHub Actions to gate hardware pull requests;
+/// fail the job on `"fail"` (and optionally on `"warn"`).
+#[utoipa::path(
+    post,
+    path = "/api/ci/verdict",
+    request_body = CiVerdictRequest,
+    responses(
+        (status = 200, description = "Commit verdict with per-check details", body = CiVerdictResponse),
+        (status = 400, description = "Unknown commit or tag, or unknown ERC mode", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "ci"
+)]
+pub async fn get_verdict(
+    State(state): State<AppState>,
+    Json(mut req): Json<CiVerdictRequest>,
+) -> Result<Json<CiVerdictResponse>, AppError> {
+    let differential_erc = match req.erc_mode.as_deref().unwrap_or("full") {
+        "full" => false,
+        "differential" => true,
+        other => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!(
+                    "Unknown ERC mode: {} (expected full or differential)",
+                    other
+                )),
+            ))
+        }
+    };
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    let base = match &req.base {
+        Some(base) => Some(resolve_revision(&req.repo, base).await?),
+        None => None,
+    };
+    info!("CI verdict request for {}/{}", req.repo, req.commit);
+
+    let response = verdict::evaluate(
+        &state,
+        &req.repo,
+        &req.commit,
+        base,
+        req.lookup_parts,
+        differential_erc,
+    )
+    .await
+    .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    Ok(Json(response))
+}
@@ -0,0 +1,241 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::controllers::admin::require_admin;
+use crate::controllers::distill::distillation_error;
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::{audit, design_rules, distill, github};
+use crate::types::{
+    ApiError, DesignRuleCheckRequest, DesignRuleCheckResponse, DesignRuleDeleteRequest,
+    DesignRuleEntry, DesignRuleListRequest, DesignRuleListResponse, DesignRuleRequest,
+};
+use kicad_db::{design_rules as rules_db, read_pool, PgPool};
+
+pub type AppState = Arc<PgPool>;
+
+fn bad_request(message: impl Into<String>) -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(message))
+}
+
+fn internal(what: &str, e: sqlx::Error) -> AppError {
+    error!("Failed to {}: {}", what, e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Failed to {}: {}", what, e)),
+    )
+}
+
+fn to_entry(record: rules_db::DesignRuleRecord) -> Option<DesignRuleEntry> {
+    match serde_json::from_value(record.definition) {
+        Ok(rule) => Some(DesignRuleEntry {
+            name: record.name,
+            rule,
+            enabled: record.enabled,
+            updated_by: record.updated_by,
+            updated_at: record.updated_at,
+        }),
+        Err(e) => {
+            warn!("Skipping unreadable design rule {}: {}", record.name, e);
+            None
+        }
+    }
+}
+
+/// Store a design rule of a repository
+///
+/// Rules describe policies the team wants every commit to follow, e.g. each
+/// MCU VDD pin shares a net with a 100nF capacitor:
+///
+/// `{"applies_to": {"category": "ic", "lib_id": "MCU_*"}, "pins": "VDD*",
+///   "require": {"category": "capacitor", "value": "100n*|0.1u*"}}`
+///
+/// or each connector has an ESD part close by:
+///
+/// `{"applies_to": {"category": "connector"}, "require": {"value": "*ESD*|*TVS*"},
+///   "relation": "nearby", "max_distance_mm": 25}`
+///
+/// Storing a rule under an existing name replaces it. Enabled rules are
+/// checked by the CI verdict and listed in commit reports, so changing them
+/// requires the admin token.
+#[utoipa::path(
+    post,
+    path = "/api/rules",
+    request_body = DesignRuleRequest,
+    responses(
+        (status = 200, description = "Stored rule", body = DesignRuleEntry),
+        (status = 400, description = "Invalid rule or repository", body = ApiError),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "rules"
+)]
+pub async fn set_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<DesignRuleRequest>,
+) -> Result<Json<DesignRuleEntry>, AppError> {
+    require_admin(&headers)?;
+    let name = req.name.trim();
+    design_rules::validate(name, &req.rule).map_err(bad_request)?;
+    let updated_by = req
+        .updated_by
+        .as_deref()
+        .map(str::trim)
+        .filter(|v| !v.is_empty());
+    let enabled = req.enabled.unwrap_or(true);
+
+    let repo_url = github::repo_url(&req.repo);
+    let definition = serde_json::to_value(&req.rule).unwrap_or_default();
+    let stored = rules_db::upsert_rule(&state, &repo_url, name, &definition, enabled, updated_by)
+        .await
+        .map_err(|e| internal("store design rule", e))?
+        .ok_or_else(|| bad_request(format!("Invalid repository: {}", req.repo)))?;
+    info!("Stored design rule {} of {}", name, req.repo);
+
+    audit::record(
+        &state,
+        audit::DESIGN_RULE_CHANGED,
+        audit::ACTOR_ADMIN,
+        Some(&repo_url),
+        None,
+        serde_json::json!({
+            "name": name,
+            "enabled": enabled,
+            "updated_by": updated_by,
+        }),
+    )
+    .await;
+
+    let entry = to_entry(stored).ok_or_else(|| {
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal("Stored rule could not be read back"),
+        )
+    })?;
+    Ok(Json(entry))
+}
+
+/// List the design rules of a repository, disabled ones included
+#[utoipa::path(
+    post,
+    path = "/api/rules/list",
+    request_body = DesignRuleListRequest,
+    responses(
+        (status = 200, description = "Rules by name", body = DesignRuleListResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "rules"
+)]
+pub async fn list_rules(
+    State(state): State<AppState>,
+    Json(req): Json<DesignRuleListRequest>,
+) -> Result<Json<DesignRuleListResponse>, AppError> {
+    let repo_url = github::repo_url(&req.repo);
+    let rules = rules_db::list_rules(read_pool(&state), &repo_url, false)
+        .await
+        .map_err(|e| internal("list design rules", e))?;
+
+    Ok(Json(DesignRuleListResponse {
+        repo: req.repo,
+        rules: rules.into_iter().filter_map(to_entry).collect(),
+    }))
+}
+
+/// Delete a design rule of a repository. Requires the admin token.
+#[utoipa::path(
+    post,
+    path = "/api/rules/delete",
+    request_body = DesignRuleDeleteRequest,
+    responses(
+        (status = 204, description = "Rule deleted"),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 404, description = "No rule by that name", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "rules"
+)]
+pub async fn delete_rule(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<DesignRuleDeleteRequest>,
+) -> Result<StatusCode, AppError> {
+    require_admin(&headers)?;
+    let repo_url = github::repo_url(&req.repo);
+    if !rules_db::delete_rule(&state, &repo_url, &req.name)
+        .await
+        .map_err(|e| internal("delete design rule", e))?
+    {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("No design rule {} for {}", req.name, req.repo)),
+        ));
+    }
+    info!("Deleted design rule {} of {}", req.name, req.repo);
+
+    audit::record(
+        &state,
+        audit::DESIGN_RULE_CHANGED,
+        audit::ACTOR_ADMIN,
+        Some(&repo_url),
+        None,
+        serde_json::json!({ "name": req.name, "deleted": true }),
+    )
+    .await;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Check a commit against the repository's enabled design rules
+#[utoipa::path(
+    post,
+    path = "/api/rules/check",
+    request_body = DesignRuleCheckRequest,
+    responses(
+        (status = 200, description = "Rule violations", body = DesignRuleCheckResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "rules"
+)]
+pub async fn check_rules(
+    State(state): State<AppState>,
+    Json(mut req): Json<DesignRuleCheckRequest>,
+) -> Result<Json<DesignRuleCheckResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
+    let rules = design_rules::enabled_rules(&state, &req.repo)
+        .await
+        .map_err(|e| {
+            error!("Failed to load design rules of {}: {}", req.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to load design rules: {}", e)),
+            )
+        })?;
+    let distilled = distill::get_or_distill(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    let violations = design_rules::evaluate(&rules, &distilled);
+    info!(
+        "Checked {} design rule(s) on {}/{}: {} violation(s)",
+        rules.len(),
+        req.repo,
+        req.commit,
+        violations.len()
+    );
+
+    Ok(Json(DesignRuleCheckResponse {
+        repo: req.repo,
+        commit: req.commit,
+        rules_checked: rules.len(),
+        violations,
+    }))
+}
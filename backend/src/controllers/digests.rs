@@ -0,0 +1,183 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::errors::AppError;
+use crate::services::digests as digest_service;
+use crate::services::{git, github};
+use crate::types::{
+    ApiError, DigestConfirmQuery, DigestConfirmationResponse, DigestSubscribeRequest,
+    DigestSubscriptionResponse, DigestUnsubscribeRequest,
+};
+use kicad_db::digests;
+use kicad_db::PgPool;
+
+pub type AppState = Arc<PgPool>;
+
+// Longest address accepted (RFC 5321 path limit)
+const MAX_EMAIL_LEN: usize = 254;
+
+fn bad_request(message: impl Into<String>) -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(message))
+}
+
+fn internal(action: &str, e: sqlx::Error) -> AppError {
+    error!("Failed to {}: {}", action, e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Failed to {}: {}", action, e)),
+    )
+}
+
+/// Only a shape check; delivery is left to the mail relay
+fn is_plausible_email(email: &str) -> bool {
+    email.len() <= MAX_EMAIL_LEN
+        && !email.chars().any(|c| c.is_whitespace() || c.is_control())
+        && email
+            .split_once('@')
+            .is_some_and(|(local, domain)| !local.is_empty() && domain.contains('.'))
+}
+
+/// Request a subscription to a repository's schematic activity digest
+///
+/// Emails the address a confirmation link; the subscription is created only
+/// once it is followed. Requesting again for a subscribed address changes its
+/// frequency, also after confirmation. Due digests list the period's indexed
+/// schematic commits with their blurbs, plus component count and estimated BOM
+/// cost changes. Confirmation emails and digests are delivered through the
+/// notification webhook as `digest.confirm` and `digest.email` events.
+#[utoipa::path(
+    post,
+    path = "/api/digests/subscribe",
+    request_body = DigestSubscribeRequest,
+    responses(
+        (status = 202, description = "Confirmation email queued", body = DigestConfirmationResponse),
+        (status = 400, description = "Invalid address, frequency or repository", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "digests"
+)]
+pub async fn subscribe(
+    State(state): State<AppState>,
+    Json(req): Json<DigestSubscribeRequest>,
+) -> Result<(StatusCode, Json<DigestConfirmationResponse>), AppError> {
+    let email = req.email.trim();
+    if !is_plausible_email(email) {
+        return Err(bad_request(format!("Invalid email address: {}", email)));
+    }
+    let frequency = req.frequency.as_deref().unwrap_or("weekly");
+    if !digests::FREQUENCIES.contains(&frequency) {
+        return Err(bad_request(format!(
+            "frequency must be one of: {}",
+            digests::FREQUENCIES.join(", ")
+        )));
+    }
+
+    let repo_url = github::repo_url(&req.repo);
+    let requested = digest_service::request_subscription(&state, &repo_url, email, frequency)
+        .await
+        .map_err(|e| {
+            error!("Failed to request digest subscription: {:#}", e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to request digest subscription: {}", e)),
+            )
+        })?;
+    if !requested {
+        return Err(bad_request(format!("Invalid repository: {}", req.repo)));
+    }
+
+    info!(
+        "Digest subscription requested for {}: {}",
+        req.repo, frequency
+    );
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(DigestConfirmationResponse {
+            repo: req.repo,
+            email: email.to_string(),
+            frequency: frequency.to_string(),
+            expires_in_hours: digests::CONFIRMATION_TTL_HOURS,
+        }),
+    ))
+}
+
+/// Confirm a digest subscription request with the token from its email
+///
+/// The link in the confirmation email. Creates the subscription, or changes
+/// the frequency of an existing one; each token works once.
+#[utoipa::path(
+    get,
+    path = "/api/digests/confirm",
+    params(DigestConfirmQuery),
+    responses(
+        (status = 200, description = "Subscription in effect", body = DigestSubscriptionResponse),
+        (status = 404, description = "Unknown or expired confirmation token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "digests"
+)]
+pub async fn confirm(
+    State(state): State<AppState>,
+    Query(query): Query<DigestConfirmQuery>,
+) -> Result<Json<DigestSubscriptionResponse>, AppError> {
+    let subscription = digests::confirm(&state, query.token.trim())
+        .await
+        .map_err(|e| internal("confirm digest subscription", e))?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(
+                    "Unknown or expired confirmation token; subscribe again for a new link",
+                ),
+            )
+        })?;
+
+    info!(
+        "Digest subscription {} confirmed: {}",
+        subscription.id, subscription.frequency
+    );
+    let repo = git::repo_slug(&subscription.repo_url)
+        .unwrap_or(&subscription.repo_url)
+        .to_string();
+    Ok(Json(DigestSubscriptionResponse {
+        repo,
+        email: subscription.email,
+        frequency: subscription.frequency,
+        last_sent_at: subscription.last_sent_at,
+        created_at: subscription.created_at,
+    }))
+}
+
+/// Remove a digest subscription by its unsubscribe token
+#[utoipa::path(
+    post,
+    path = "/api/digests/unsubscribe",
+    request_body = DigestUnsubscribeRequest,
+    responses(
+        (status = 204, description = "Subscription removed"),
+        (status = 404, description = "No subscription with this token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "digests"
+)]
+pub async fn unsubscribe(
+    State(state): State<AppState>,
+    Json(req): Json<DigestUnsubscribeRequest>,
+) -> Result<StatusCode, AppError> {
+    if !digests::unsubscribe(&state, req.token.trim())
+        .await
+        .map_err(|e| internal("remove digest subscription", e))?
+    {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found("No digest subscription with this token"),
+        ));
+    }
+    info!("Removed a digest subscription");
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,10 +1,19 @@
 use axum::{extract::State, http::StatusCode, response::Json};
+use std::collections::HashMap;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::services::digikey::DigiKeyClient;
-use crate::types::{ApiError, DigiKeySearchRequest, DigiKeySearchResponse};
-use kicad_db::PgPool;
+use crate::controllers::distill::distillation_error;
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::bom::{self, BomComponent};
+use crate::services::digikey::{self, DigiKeyClient};
+use crate::services::{distill, github, metrics};
+use crate::types::{
+    ApiError, DigiKeyEnrichRequest, DigiKeyEnrichResponse, DigiKeyEnrichedPart,
+    DigiKeyMissingPart, DigiKeySearchRequest, DigiKeySearchResponse,
+};
+use kicad_db::{merge_part_properties, PgPool};
 
 pub type AppState = Arc<PgPool>;
 
@@ -24,15 +33,15 @@ pub type AppState = Arc<PgPool>;
 pub async fn search_parts(
     State(_state): State<AppState>,
     Json(req): Json<DigiKeySearchRequest>,
-) -> Result<Json<DigiKeySearchResponse>, (StatusCode, Json<ApiError>)> {
+) -> Result<Json<DigiKeySearchResponse>, AppError> {
     // Check if DigiKey is configured
     if !DigiKeyClient::is_configured() {
-        return Err((
+        return Err(AppError::new(
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(ApiError::new(
+            ApiError::new(
                 "not_configured",
                 "DigiKey API is not configured. Please set DIGIKEY_CLIENT_ID and DIGIKEY_CLIENT_SECRET environment variables.",
-            )),
+            ),
         ));
     }
 
@@ -94,3 +103,163 @@ pub async fn get_status(
         }
     }))
 }
+
+/// Enrich every MPN-bearing component of a schematic with DigiKey data
+///
+/// Distills the schematic (or uses the cached result), looks up each unique
+/// manufacturer part number on DigiKey, and merges lifecycle, price and
+/// datasheet information into the stored part properties.
+#[utoipa::path(
+    post,
+    path = "/api/digikey/enrich",
+    request_body = DigiKeyEnrichRequest,
+    responses(
+        (status = 200, description = "Summary of enriched and missing parts", body = DigiKeyEnrichResponse),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "DigiKey API not configured", body = ApiError)
+    ),
+    tag = "digikey"
+)]
+pub async fn enrich_parts(
+    State(state): State<AppState>,
+    Json(mut req): Json<DigiKeyEnrichRequest>,
+) -> Result<Json<DigiKeyEnrichResponse>, AppError> {
+    if !DigiKeyClient::is_configured() {
+        return Err(AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::new(
+                "not_configured",
+                "DigiKey API is not configured. Please set DIGIKEY_CLIENT_ID and DIGIKEY_CLIENT_SECRET environment variables.",
+            ),
+        ));
+    }
+
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
+    info!("DigiKey enrichment requested for {}/{}", req.repo, req.commit);
+
+    let distilled = distill::get_or_distill(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    let components = bom::extract_components(&distilled);
+    let total_components = components.len();
+
+    // Group references by MPN so each part number is only looked up once
+    let mut by_mpn: HashMap<String, Vec<BomComponent>> = HashMap::new();
+    let mut skipped_without_mpn = 0;
+    for component in components {
+        match component.mpn.clone() {
+            Some(mpn) => by_mpn.entry(mpn).or_default().push(component),
+            None => skipped_without_mpn += 1,
+        }
+    }
+
+    info!(
+        "Enriching {} unique MPN(s) across {} component(s)",
+        by_mpn.len(),
+        total_components
+    );
+
+    let client = DigiKeyClient::new();
+    let enriched_at = chrono::Utc::now().to_rfc3339();
+    let mut enriched = Vec::new();
+    let mut missing = Vec::new();
+    let mut properties = HashMap::new();
+
+    let mut mpns: Vec<String> = by_mpn.keys().cloned().collect();
+    mpns.sort();
+
+    for mpn in mpns {
+        let refs = &by_mpn[&mpn];
+        match client.lookup_mpn(&mpn).await {
+            Ok(Some(part)) => {
+                for component in refs {
+                    properties.insert(
+                        bom::part_uuid_for_reference(&component.reference),
+                        serde_json::json!({
+                            "reference": component.reference,
+                            "mpn": mpn,
+                            "digikey": {
+                                "digikey_part_number": part.digikey_part_number,
+                                "manufacturer_part_number": part.manufacturer_part_number,
+                                "manufacturer": part.manufacturer,
+                                "lifecycle_status": part.lifecycle_status,
+                                "is_obsolete": part.is_obsolete,
+                                "unit_price": part.unit_price,
+                                "currency": digikey::CURRENCY,
+                                "quantity_available": part.quantity_available,
+                                "datasheet_url": part.datasheet_url,
+                                "product_url": part.product_url,
+                                "package": digikey::package_of(&part),
+                                "enriched_at": enriched_at,
+                            },
+                        }),
+                    );
+                    enriched.push(DigiKeyEnrichedPart {
+                        reference: component.reference.clone(),
+                        mpn: mpn.clone(),
+                        digikey_part_number: part.digikey_part_number.clone(),
+                        lifecycle_status: part.lifecycle_status.clone(),
+                        is_obsolete: part.is_obsolete,
+                        unit_price: part.unit_price,
+                        datasheet_url: part.datasheet_url.clone(),
+                    });
+                }
+            }
+            Ok(None) => {
+                for component in refs {
+                    missing.push(DigiKeyMissingPart {
+                        reference: component.reference.clone(),
+                        mpn: mpn.clone(),
+                        reason: "No matching DigiKey product".to_string(),
+                    });
+                }
+            }
+            Err(e) => {
+                warn!("DigiKey lookup failed for {}: {}", mpn, e);
+                for component in refs {
+                    missing.push(DigiKeyMissingPart {
+                        reference: component.reference.clone(),
+                        mpn: mpn.clone(),
+                        reason: format!("DigiKey lookup failed: {}", e),
+                    });
+                }
+            }
+        }
+    }
+
+    let repo_url = github::repo_url(&req.repo);
+    merge_part_properties(&state, &repo_url, &req.commit, properties)
+        .await
+        .map_err(|e| {
+            error!("Failed to store enriched parts: {}", e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to store enriched parts: {}", e)),
+            )
+        })?;
+    // Prices changed, so refresh the commit's estimated BOM cost
+    metrics::record(&state, &req.repo, &req.commit, &distilled).await;
+
+    enriched.sort_by(|a, b| a.reference.cmp(&b.reference));
+    missing.sort_by(|a, b| a.reference.cmp(&b.reference));
+
+    info!(
+        "DigiKey enrichment complete for {}/{}: {} enriched, {} missing, {} without MPN",
+        req.repo,
+        req.commit,
+        enriched.len(),
+        missing.len(),
+        skipped_without_mpn
+    );
+
+    Ok(Json(DigiKeyEnrichResponse {
+        repo: req.repo,
+        commit: req.commit,
+        total_components,
+        skipped_without_mpn,
+        enriched,
+        missing,
+    }))
+}
@@ -1,44 +1,188 @@
-use axum::{extract::State, http::StatusCode, response::Json};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+};
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::services::distill;
-use crate::types::{ApiError, DistillRequest, DistillResponse};
-use kicad_db::{retrieve_distilled_json, store_distilled_json, PgPool};
+use crate::controllers::etag;
+use crate::controllers::repo::resolve_revision;
+use crate::demo::DemoLimit;
+use crate::errors::AppError;
+use crate::repo_policy::RepoRejected;
+use crate::services::disk::DiskFull;
+use crate::services::distill::{self, DistillPending};
+use crate::services::distiller::{BackendChoice, DistillTimeout};
+use crate::services::github;
+use crate::services::kicad_format::UnsupportedFormat;
+use crate::services::prefetch;
+use crate::services::quarantine::Quarantined;
+use crate::types::{
+    ApiError, DistillRequest, DistillResponse, DistillSheetRequest, DistillSheetResponse,
+};
+use kicad_db::{retrieve_distilled_json, PgPool};
 
 pub type AppState = Arc<PgPool>;
 
+/// Map a distillation failure to an API error.
+///
+/// A commit whose schematics are all in an unsupported KiCad format gets a
+/// 422 `unsupported_format` error naming a file and, in `details`, its
+/// detected version, a distill script killed for
+/// running too long a 422 `distill_timeout`, a commit quarantined after
+/// failing repeatedly a 422 `commit_quarantined`, and a commit a demo instance
+/// has no cached data for a 503 `demo_limit`; anything else is a 500. A
+/// distillation handed to a worker is a 202 `distill_pending` with the job
+/// id in `details`.
+pub fn distillation_error(repo: &str, commit: &str, e: anyhow::Error) -> AppError {
+    // Callers that waited on another request's distillation share its error
+    let e = distill::cause(&e);
+    if let Some(pending) = e.downcast_ref::<DistillPending>() {
+        info!("Distillation of {}/{} queued: {}", repo, commit, pending);
+        return AppError::new(
+            StatusCode::ACCEPTED,
+            ApiError::new("distill_pending", pending.to_string()).with_details(serde_json::json!({
+                "job_id": pending.job_id,
+                "status_url": format!("/api/jobs/{}", pending.job_id),
+            })),
+        );
+    }
+
+    if let Some(unsupported) = e.downcast_ref::<UnsupportedFormat>() {
+        info!(
+            "Unsupported schematic format in {}/{}: {}",
+            repo, commit, unsupported
+        );
+        return AppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::unsupported_format(unsupported.to_string())
+                .with_details(unsupported.details()),
+        );
+    }
+
+    if let Some(rejected) = e.downcast_ref::<RepoRejected>() {
+        info!("Not distilling {}/{}: {}", repo, commit, rejected);
+        return rejected.into();
+    }
+
+    if let Some(full) = e.downcast_ref::<DiskFull>() {
+        warn!("Not distilling {}/{}: {}", repo, commit, full);
+        return full.into();
+    }
+
+    if let Some(limit) = e.downcast_ref::<DemoLimit>() {
+        info!("Not distilling {}/{} in demo mode", repo, commit);
+        return DemoLimit(limit.0.clone()).into();
+    }
+
+    if let Some(quarantined) = e.downcast_ref::<Quarantined>() {
+        info!("Not distilling {}/{}: {}", repo, commit, quarantined);
+        return quarantined.into();
+    }
+
+    if let Some(timeout) = e.downcast_ref::<DistillTimeout>() {
+        warn!("Distillation of {}/{} cancelled: {}", repo, commit, timeout);
+        return AppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::new("distill_timeout", timeout.to_string()),
+        );
+    }
+
+    error!("Distillation failed for {}/{}: {}", repo, commit, e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Distillation failed: {}", e)),
+    )
+}
+
 /// Distill schematic files from a repository at a specific commit
+///
+/// The response carries an ETag derived from the distilled data; send it back
+/// in If-None-Match to get a 304 instead of the full payload when unchanged.
+/// `backend` picks the distiller for this request; `compare` also returns the
+/// differences between the Python and native output.
 #[utoipa::path(
     post,
     path = "/api/distill",
     request_body = DistillRequest,
     responses(
         (status = 200, description = "Distilled schematic data", body = DistillResponse),
+        (status = 202, description = "Distillation queued on a worker; poll the job in `details` and retry", body = ApiError),
+        (status = 304, description = "Distilled data unchanged since the If-None-Match ETag"),
+        (status = 400, description = "Unknown backend", body = ApiError),
+        (status = 422, description = "Schematic uses an unsupported KiCad file format, or distillation timed out", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "distill"
 )]
 pub async fn distill_schematics(
     State(state): State<AppState>,
-    Json(req): Json<DistillRequest>,
-) -> Result<Json<DistillResponse>, (StatusCode, Json<ApiError>)> {
+    headers: HeaderMap,
+    Json(mut req): Json<DistillRequest>,
+) -> Result<Response, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
     info!("Distill request for {}/{}", req.repo, req.commit);
+    prefetch::after_view(&state, &req.repo, &req.commit);
+
+    let backend = match req.backend.as_deref() {
+        Some(name) => BackendChoice::from_name(name).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!(
+                    "Unknown backend {:?}; expected one of {}",
+                    name,
+                    BackendChoice::NAMES.join(", ")
+                )),
+            )
+        })?,
+        None => BackendChoice::configured(),
+    };
+    // The cache holds the configured backend's output; others always run fresh
+    if backend != BackendChoice::configured() {
+        let outcome = distill::distill_with(&state, &req.repo, &req.commit, backend)
+            .await
+            .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+        let sheet_meta = distill::sheet_meta(&state, &req.repo).await;
+        return Ok(etag::respond(
+            &headers,
+            DistillResponse {
+                repo: req.repo,
+                commit: req.commit,
+                cached: false,
+                warnings: distill::warnings(&outcome.distilled),
+                file_cache: Some(outcome.stats),
+                distilled: outcome.distilled,
+                sheet_meta,
+                comparison: outcome.comparison,
+            },
+        ));
+    }
 
-    let repo_url = format!("https://github.com/{}.git", req.repo);
+    let repo_url = github::repo_url(&req.repo);
 
     // Check cache first
     match retrieve_distilled_json(&state, &repo_url, &req.commit).await {
         Ok(Some(cached_json)) => {
             info!("Cache hit for {}/{}", req.repo, req.commit);
-            return Ok(Json(DistillResponse {
-                repo: req.repo,
-                commit: req.commit,
-                cached: true,
-                distilled: cached_json,
-            }));
+            distill::record_commit_cache_lookup(true);
+            let sheet_meta = distill::sheet_meta(&state, &req.repo).await;
+            return Ok(etag::respond(
+                &headers,
+                DistillResponse {
+                    repo: req.repo,
+                    commit: req.commit,
+                    cached: true,
+                    warnings: distill::warnings(&cached_json),
+                    file_cache: None,
+                    distilled: cached_json,
+                    sheet_meta,
+                    comparison: None,
+                },
+            ));
         }
         Ok(None) => {
+            distill::record_commit_cache_lookup(false);
             info!(
                 "Cache miss for {}/{}, running distillation",
                 req.repo, req.commit
@@ -50,29 +194,85 @@ pub async fn distill_schematics(
         }
     }
 
-    // Run distillation
-    let distilled = distill::distill_repo_schematics(&req.repo, &req.commit)
+    // Run distillation (on a worker when the job queue is enabled) and cache the result
+    let (distilled, file_cache) = distill::distill_commit(&state, &req.repo, &req.commit)
         .await
-        .map_err(|e| {
-            error!("Distillation failed for {}/{}: {}", req.repo, req.commit, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!("Distillation failed: {}", e))),
-            )
-        })?;
-
-    // Store in cache
-    if let Err(e) = store_distilled_json(&state, &repo_url, &req.commit, &distilled).await {
-        // Log but don't fail - we still have the result
-        error!("Failed to cache distilled result: {}", e);
-    } else {
-        info!("Cached distilled result for {}/{}", req.repo, req.commit);
-    }
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    let sheet_meta = distill::sheet_meta(&state, &req.repo).await;
+    Ok(etag::respond(
+        &headers,
+        DistillResponse {
+            repo: req.repo,
+            commit: req.commit,
+            cached: false,
+            warnings: distill::warnings(&distilled),
+            file_cache: Some(file_cache),
+            distilled,
+            sheet_meta,
+            comparison: None,
+        },
+    ))
+}
+
+/// Get the distilled data of one schematic sheet
+///
+/// Returns the sheet's components, the nets they connect to (limited to this
+/// sheet's pins) and their proximities. Use with `summary_only` on
+/// `/api/repo/init` to load large designs one sheet at a time. The commit is
+/// distilled first if it isn't cached yet.
+#[utoipa::path(
+    post,
+    path = "/api/distill/sheet",
+    request_body = DistillSheetRequest,
+    responses(
+        (status = 200, description = "Distilled data of the sheet", body = DistillSheetResponse),
+        (status = 202, description = "Distillation queued on a worker; poll the job in `details` and retry", body = ApiError),
+        (status = 304, description = "Sheet data unchanged since the If-None-Match ETag"),
+        (status = 404, description = "The sheet has no distilled data (unknown path or no components)", body = ApiError),
+        (status = 422, description = "Schematic uses an unsupported KiCad file format, or distillation timed out", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "distill"
+)]
+pub async fn distill_sheet(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut req): Json<DistillSheetRequest>,
+) -> Result<Response, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!(
+        "Distill sheet request for {}/{}: {}",
+        req.repo, req.commit, req.sheet
+    );
+    prefetch::after_view(&state, &req.repo, &req.commit);
+
+    let distilled = distill::get_or_distill(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
 
-    Ok(Json(DistillResponse {
-        repo: req.repo,
-        commit: req.commit,
-        cached: false,
-        distilled,
-    }))
+    let Some(sheet) = distill::sheet_distilled(&distilled, &req.sheet) else {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!(
+                "No distilled data for sheet {} in {}/{}",
+                req.sheet, req.repo, req.commit
+            )),
+        ));
+    };
+
+    let sheet_meta = distill::sheet_meta(&state, &req.repo)
+        .await
+        .into_iter()
+        .find(|m| m.sheet == req.sheet);
+    Ok(etag::respond(
+        &headers,
+        DistillSheetResponse {
+            repo: req.repo,
+            commit: req.commit,
+            sheet: req.sheet,
+            distilled: sheet,
+            sheet_meta,
+        },
+    ))
 }
@@ -0,0 +1,47 @@
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// ETag for a JSON value: the SHA-256 of its serialized form.
+///
+/// Weak, because the compression layer re-encodes the body and the tag
+/// identifies the content rather than the exact bytes on the wire.
+fn for_json<T: Serialize>(value: &T) -> String {
+    let bytes = serde_json::to_vec(value).unwrap_or_default();
+    format!("W/\"{}\"", hex::encode(Sha256::digest(&bytes)))
+}
+
+fn opaque_tag(etag: &str) -> &str {
+    etag.trim().trim_start_matches("W/")
+}
+
+/// Whether the request's If-None-Match header already names this ETag
+pub fn matches(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(value) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+    let etag = opaque_tag(etag);
+    value
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || opaque_tag(candidate) == etag)
+}
+
+/// Respond with `body` tagged with the ETag of its whole serialized form, or
+/// an empty 304 when the client already has it
+pub fn respond<T: Serialize>(headers: &HeaderMap, body: T) -> Response {
+    let cache_headers = [
+        (header::ETAG, for_json(&body)),
+        // Clients may keep the response but must revalidate before reusing it
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+    if matches(headers, &cache_headers[0].1) {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (cache_headers, Json(body)).into_response()
+}
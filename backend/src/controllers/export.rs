@@ -0,0 +1,110 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::controllers::distill::distillation_error;
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::blob_store::{self, BlobKind};
+use crate::services::design_export;
+use crate::types::{ApiError, DesignExportRequest};
+use kicad_db::PgPool;
+
+pub type AppState = Arc<PgPool>;
+
+/// Export a commit's design in the vendor-neutral schema
+///
+/// Components (with attributes and pins), nets and the sheet hierarchy, for
+/// ingestion into PLM systems. The schema is versioned separately from the
+/// distilled format (see `schema_version` and backend/docs/design-export.md).
+/// The document is also kept in the blob store as an `export` file of the commit.
+#[utoipa::path(
+    post,
+    path = "/api/export/design",
+    request_body = DesignExportRequest,
+    responses(
+        (status = 200, description = "Design document (application/json or application/xml)"),
+        (status = 400, description = "Unknown commit or tag, or unknown format", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "export"
+)]
+pub async fn export_design(
+    State(state): State<AppState>,
+    Json(mut req): Json<DesignExportRequest>,
+) -> Result<Response, AppError> {
+    let format = req.format.as_deref().unwrap_or("json");
+    if format != "json" && format != "xml" {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!(
+                "Unknown export format: {} (expected json or xml)",
+                format
+            )),
+        ));
+    }
+
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("Design export ({}) for {}/{}", format, req.repo, req.commit);
+
+    let design = design_export::export(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    let (content_type, body) = if format == "xml" {
+        (
+            "application/xml",
+            design_export::to_xml(&design).into_bytes(),
+        )
+    } else {
+        let json = serde_json::to_vec_pretty(&design).map_err(|e| {
+            error!("Failed to serialize design export: {}", e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to serialize design: {}", e)),
+            )
+        })?;
+        ("application/json", json)
+    };
+
+    // Keep the export with the commit's other files (see /api/blobs/list)
+    if let Err(e) = blob_store::store_commit_blob(
+        &state,
+        BlobKind::Export,
+        &req.repo,
+        &req.commit,
+        &format!("design.{}", format),
+        &body,
+        content_type,
+    )
+    .await
+    {
+        warn!(
+            "Failed to store the design export of {}/{}: {:#}",
+            req.repo, req.commit, e
+        );
+    }
+
+    let file_name = format!(
+        "{}-{}.{}",
+        req.repo.replace('/', "-"),
+        &req.commit[..8.min(req.commit.len())],
+        format
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
@@ -0,0 +1,241 @@
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::controllers::admin::require_admin;
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::github;
+use crate::types::{
+    ApiError, FeedbackEntry, FeedbackExportQuery, FeedbackExportResponse, FeedbackTotal,
+    SummaryFeedbackRequest, SummaryFeedbackResponse,
+};
+use kicad_db::{feedback, read_pool, PgPool};
+
+pub type AppState = Arc<PgPool>;
+
+// Longest comment we keep; anything past this is truncated
+const MAX_COMMENT_CHARS: usize = 2000;
+// Longest client-supplied summary text we keep
+const MAX_SUMMARY_CHARS: usize = 20_000;
+const DEFAULT_EXPORT_LIMIT: i64 = 1000;
+const MAX_EXPORT_LIMIT: i64 = 10_000;
+
+fn bad_request(message: impl Into<String>) -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(message))
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars().take(max_chars).collect()
+}
+
+/// Rate a generated commit blurb, description or summary
+///
+/// Ratings of the stored blurb/description are attributed to the model and
+/// prompt version that generated them. For `commit_summary`, send back the
+/// text, model and prompt version from the summary response.
+#[utoipa::path(
+    post,
+    path = "/api/feedback/summary",
+    request_body = SummaryFeedbackRequest,
+    responses(
+        (status = 200, description = "Rating stored", body = SummaryFeedbackResponse),
+        (status = 400, description = "Invalid target or rating", body = ApiError),
+        (status = 404, description = "No stored summary for this commit", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "feedback"
+)]
+pub async fn submit_summary_feedback(
+    State(state): State<AppState>,
+    Json(mut req): Json<SummaryFeedbackRequest>,
+) -> Result<Json<SummaryFeedbackResponse>, AppError> {
+    if req.rating != 1 && req.rating != -1 {
+        return Err(bad_request(
+            "rating must be 1 (thumbs up) or -1 (thumbs down)",
+        ));
+    }
+
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
+    let internal = |e: sqlx::Error| {
+        error!(
+            "Failed to store feedback for {}@{}: {}",
+            req.repo, req.commit, e
+        );
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to store feedback: {}", e)),
+        )
+    };
+
+    let repo_url = github::repo_url(&req.repo);
+    let (schematic_id, summary_text, model, prompt_version) = match req.target.as_str() {
+        "blurb" | "description" => {
+            let stored = feedback::stored_summary(&state, &repo_url, &req.commit)
+                .await
+                .map_err(internal)?;
+            let Some(stored) = stored else {
+                return Err(AppError::new(
+                    StatusCode::NOT_FOUND,
+                    ApiError::not_found(format!(
+                        "No stored summary for {}@{}",
+                        req.repo, req.commit
+                    )),
+                ));
+            };
+            let text = if req.target == "blurb" {
+                stored.blurb
+            } else {
+                stored.description
+            };
+            (
+                Some(stored.schematic_id),
+                text,
+                stored.summary_model,
+                stored.summary_prompt_version,
+            )
+        }
+        "commit_summary" => {
+            let (Some(text), Some(model), Some(prompt_version)) =
+                (&req.summary_text, &req.model, &req.prompt_version)
+            else {
+                return Err(bad_request(
+                    "commit_summary feedback requires summary_text, model and prompt_version",
+                ));
+            };
+            let schematic_id = feedback::stored_summary(&state, &repo_url, &req.commit)
+                .await
+                .map_err(internal)?
+                .map(|s| s.schematic_id);
+            (
+                schematic_id,
+                Some(truncate(text, MAX_SUMMARY_CHARS)),
+                Some(model.clone()),
+                Some(prompt_version.clone()),
+            )
+        }
+        other => {
+            return Err(bad_request(format!(
+                "Unknown target: {} (expected blurb, description or commit_summary)",
+                other
+            )))
+        }
+    };
+
+    let comment = req
+        .comment
+        .as_deref()
+        .map(str::trim)
+        .filter(|c| !c.is_empty())
+        .map(|c| truncate(c, MAX_COMMENT_CHARS));
+
+    let record = feedback::insert_feedback(
+        &state,
+        &feedback::NewFeedback {
+            schematic_id,
+            repo_url: &repo_url,
+            commit_hash: &req.commit,
+            target: &req.target,
+            rating: req.rating,
+            comment: comment.as_deref(),
+            summary_text: summary_text.as_deref(),
+            model: model.as_deref(),
+            prompt_version: prompt_version.as_deref(),
+        },
+    )
+    .await
+    .map_err(internal)?;
+
+    info!(
+        "Stored {} feedback ({:+}) for {}@{}",
+        record.target, record.rating, req.repo, req.commit
+    );
+
+    Ok(Json(SummaryFeedbackResponse {
+        id: record.id,
+        model: record.model,
+        prompt_version: record.prompt_version,
+    }))
+}
+
+/// Export summary ratings for prompt iteration
+///
+/// Requires the admin token. Returns vote totals per target/model/prompt
+/// version alongside the individual ratings and the text that was rated.
+#[utoipa::path(
+    get,
+    path = "/api/feedback/summary/export",
+    params(FeedbackExportQuery),
+    responses(
+        (status = 200, description = "Matching ratings", body = FeedbackExportResponse),
+        (status = 401, description = "Invalid or missing admin token", body = ApiError),
+        (status = 503, description = "Admin endpoints disabled", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "feedback"
+)]
+pub async fn export_summary_feedback(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<FeedbackExportQuery>,
+) -> Result<Json<FeedbackExportResponse>, AppError> {
+    require_admin(&headers)?;
+
+    let filter = feedback::FeedbackFilter {
+        target: query.target.as_deref(),
+        prompt_version: query.prompt_version.as_deref(),
+        since: query.since,
+        limit: query
+            .limit
+            .unwrap_or(DEFAULT_EXPORT_LIMIT)
+            .clamp(1, MAX_EXPORT_LIMIT),
+    };
+
+    let internal = |e: sqlx::Error| {
+        error!("Failed to export feedback: {}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to export feedback: {}", e)),
+        )
+    };
+
+    let totals = feedback::feedback_totals(&state, &filter)
+        .await
+        .map_err(internal)?;
+    let entries = feedback::export_feedback(read_pool(&state), &filter)
+        .await
+        .map_err(internal)?;
+
+    Ok(Json(FeedbackExportResponse {
+        totals: totals
+            .into_iter()
+            .map(|t| FeedbackTotal {
+                target: t.target,
+                model: t.model,
+                prompt_version: t.prompt_version,
+                up: t.up,
+                down: t.down,
+            })
+            .collect(),
+        entries: entries
+            .into_iter()
+            .map(|e| FeedbackEntry {
+                id: e.id,
+                repo_url: e.repo_url,
+                commit: e.commit_hash,
+                target: e.target,
+                rating: e.rating,
+                comment: e.comment,
+                summary_text: e.summary_text,
+                model: e.model,
+                prompt_version: e.prompt_version,
+                created_at: e.created_at,
+            })
+            .collect(),
+    }))
+}
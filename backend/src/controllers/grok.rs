@@ -1,24 +1,45 @@
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
     response::{
         sse::{Event, Sse},
-        Json,
+        IntoResponse, Json, Response,
     },
 };
 use futures_util::{stream::Stream, StreamExt};
-use std::{convert::Infallible, path::PathBuf, sync::Arc, time::Duration};
+use std::{collections::BTreeMap, convert::Infallible, path::PathBuf, sync::Arc, time::Duration};
 use tracing::{error, info, warn};
-
-use crate::services::{distill, git};
+use uuid::Uuid;
+
+use crate::controllers::distill::distillation_error;
+use crate::controllers::repo::resolve_revision;
+use crate::demo;
+use crate::errors::AppError;
+use crate::services::{
+    analysis_sessions, comments, commit_diff, component_notes, decoupling, deterministic_summary,
+    distill, git, github, llm_usage, mpn, net_explain, output_filter, parts, provenance,
+    release_notes,
+    retrieval::{self, RetrievalQuery},
+    staged_summary::{self, StagedSettings, STAGED_SUMMARY_MODEL, STAGED_SUMMARY_PROMPT_VERSION},
+    summary_cache::{self, Generation},
+};
+use crate::services::speech::SummaryFormat;
 use crate::types::{
+    GrokAskRepoRequest, GrokExplainNetRequest,
     ApiError, GrokCommitSummaryRequest, GrokCommitSummaryResponse,
     GrokObsoleteReplacementRequest, GrokObsoleteReplacementResponse,
-    GrokRepoSummaryRequest, GrokRepoSummaryResponse, GrokSelectionStreamRequest,
-    GrokSelectionSummaryRequest, GrokSelectionSummaryResponse,
+    GrokReplacementSuggestion, GrokRepoSummaryRequest, GrokRepoSummaryResponse,
+    GrokReleaseNotesRequest, GrokSelectionStreamRequest, GrokSelectionSummaryRequest,
+    GrokSelectionSummaryResponse, GrokSessionExport, GrokSessionExportQuery, GrokSessionRequest,
+    GrokFileSummary, GrokSessionResponse, GrokSummarySource,
+    ReleaseNotesListRequest, ReleaseNotesListResponse, ReleaseNotesResponse, SheetMetaEntry,
 };
 use kicad_db::{
+    alternates::add_alternate,
+    file_summaries::list_file_summaries,
+    analysis_sessions::{create_session, find_session, get_session, AnalysisSession, NewTurn},
     messages::{ChatCompletionRequest, Message, ReasoningEffort},
+    provenance::{NewProvenance, ARTIFACT_ALTERNATE},
     utilities::load_environment_file::load_environment_file,
     xai_client::{InputMessage, ResponsesRequest, Tool, XaiClient},
     PgPool,
@@ -80,10 +101,38 @@ Keep answers **short and easy to read**:
 
 Use everyday language and avoid heavy jargon. When you must use a technical term, add a short explanation."#;
 
+/// Owner, subsystem and review status of a sheet, as " (owner: ..., ...)"
+fn describe_sheet_meta(meta: &SheetMetaEntry) -> String {
+    let fields: Vec<String> = [
+        ("owner", &meta.owner),
+        ("subsystem", &meta.subsystem),
+        ("review status", &meta.review_status),
+    ]
+    .iter()
+    .filter_map(|(name, value)| value.as_ref().map(|v| format!("{}: {}", name, v)))
+    .collect();
+    if fields.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", fields.join(", "))
+    }
+}
+
+/// System prompt of a selection analysis: the loaded prompt and the schematic summary
+fn selection_system_prompt(schematic_summary: &str) -> String {
+    format!(
+        "{}\n\n---\n\n## Schematic Context\n{}",
+        load_system_prompt(),
+        schematic_summary
+    )
+}
+
 /// Build semantic context for selected components from distilled data
 fn build_component_context(
     distilled: &serde_json::Value,
     component_ids: &[String],
+    sheet_meta: &[SheetMetaEntry],
+    notes: &BTreeMap<String, String>,
 ) -> (String, String) {
     // Components can be either:
     // - An object keyed by reference (from Python distiller): {"U1": {...}, "R1": {...}}
@@ -157,9 +206,16 @@ fn build_component_context(
         if let Some(sp) = sheet_path {
             if sp != "/" {
                 detail.push_str(&format!("\n  - Sheet: {}", sp));
+                if let Some(meta) = sheet_meta.iter().find(|m| m.sheet == sp) {
+                    detail.push_str(&describe_sheet_meta(meta));
+                }
             }
         }
 
+        if let Some(note) = notes.get(reference) {
+            detail.push_str(&format!("\n  - Team note: {}", note));
+        }
+
         // Add pin connections
         if let Some(pins) = comp.get("pins").and_then(|p| p.as_array()) {
             let pin_strs: Vec<String> = pins
@@ -298,14 +354,141 @@ fn build_component_context(
             ));
         }
 
+        // Decoupling found from the distilled geometry, so answers about
+        // supply filtering rest on the actual capacitors and distances
+        let decoupling_lines = decoupling::describe(&decoupling::analyze(distilled), component_ids);
+        if !decoupling_lines.is_empty() {
+            ctx.push_str(&format!(
+                "\n\n## Decoupling (capacitors to ground within {} mm)\n- {}",
+                decoupling::DECOUPLING_RADIUS_MM,
+                decoupling_lines.join("\n- ")
+            ));
+        }
+
         ctx
     };
 
     (selected_context, schematic_overview)
 }
 
+/// Model used for commit summaries
+const COMMIT_SUMMARY_MODEL: &str = "grok-4-1-fast";
+/// Bump whenever the commit summary prompt changes so feedback can be compared across versions
+pub const COMMIT_SUMMARY_PROMPT_VERSION: &str = "commit-summary-v1";
+
+/// Bump whenever the replacement prompts change
+pub const REPLACEMENT_PROMPT_VERSION: &str = "replacement-v1";
+
+/// Instructions appended to the replacement prompt when stock verification is requested
+const REPLACEMENT_JSON_INSTRUCTIONS: &str = r#"
+
+After your analysis, list every recommended replacement in a fenced ```json code block containing an array of objects with these fields:
+- "manufacturer_part_number": the exact orderable part number
+- "manufacturer": the manufacturer name
+- "reason": one sentence on why it is a good replacement"#;
+
+/// A replacement candidate parsed from Grok's structured output
+#[derive(Debug, serde::Deserialize)]
+struct SuggestedReplacement {
+    manufacturer_part_number: String,
+    manufacturer: Option<String>,
+    reason: Option<String>,
+}
+
+/// Split the trailing ```json block out of a Grok answer.
+///
+/// Returns the analysis text without the block, plus the parsed suggestions
+/// (None if the block is missing or malformed, leaving the answer whole).
+fn extract_suggested_replacements(
+    analysis: &str,
+) -> (String, Option<Vec<SuggestedReplacement>>) {
+    let Some(start) = analysis.rfind("```json") else {
+        return (analysis.to_string(), None);
+    };
+    let body_start = start + "```json".len();
+    let Some(body_len) = analysis[body_start..].find("```") else {
+        return (analysis.to_string(), None);
+    };
+
+    let body = &analysis[body_start..body_start + body_len];
+    match serde_json::from_str::<Vec<SuggestedReplacement>>(body.trim()) {
+        Ok(suggestions) => {
+            let mut text = analysis[..start].trim_end().to_string();
+            text.push_str(&analysis[body_start + body_len + 3..]);
+            (text, Some(suggestions))
+        }
+        Err(e) => {
+            warn!("Failed to parse replacement suggestions JSON: {}", e);
+            (analysis.to_string(), None)
+        }
+    }
+}
+
+/// Whether a line of an answer starts a new section: a markdown heading, a
+/// numbered item or a bold title at the start of the line
+fn starts_section(line: &str) -> bool {
+    let numbered = line.split_once(['.', ')']).is_some_and(|(n, rest)| {
+        !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()) && rest.starts_with(' ')
+    });
+    line.starts_with('#') || line.starts_with("**") || numbered
+}
+
+/// Leave the sections of an answer that recommend dropped parts out of it.
+///
+/// A section that names a dropped part number and none of the kept ones is
+/// removed; a note listing the dropped parts is added at the end.
+fn without_dropped_parts(analysis: &str, dropped: &[String], kept: &[&str]) -> String {
+    if dropped.is_empty() {
+        return analysis.to_string();
+    }
+    let mut sections: Vec<String> = Vec::new();
+    for line in analysis.lines() {
+        match sections.last_mut() {
+            Some(section) if !starts_section(line) => {
+                section.push('\n');
+                section.push_str(line);
+            }
+            _ => sections.push(line.to_string()),
+        }
+    }
+    let mut text = sections
+        .into_iter()
+        .filter(|section| {
+            let section = section.to_uppercase();
+            let names = |mpn: &str| {
+                let mpn = mpn.trim().to_uppercase();
+                !mpn.is_empty() && section.contains(&mpn)
+            };
+            !dropped.iter().any(|m| names(m)) || kept.iter().any(|m| names(m))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_end()
+        .to_string();
+    text.push_str(&format!(
+        "\n\nNot in stock at any configured distributor, so left out: {}",
+        dropped.join(", ")
+    ));
+    text
+}
+
 pub type AppState = Arc<PgPool>;
 
+/// Whether a summary request asked for the template-based generator instead of the LLM
+fn deterministic_mode(mode: Option<&str>) -> Result<bool, AppError> {
+    match mode.unwrap_or("llm") {
+        "llm" => Ok(false),
+        "deterministic" => Ok(true),
+        other => Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!(
+                "Unknown summary mode: {} (expected llm or deterministic)",
+                other
+            )),
+        )),
+    }
+}
+
 /// Get an AI-generated summary for a specific commit
 #[utoipa::path(
     post,
@@ -313,60 +496,200 @@ pub type AppState = Arc<PgPool>;
     request_body = GrokCommitSummaryRequest,
     responses(
         (status = 200, description = "AI-generated commit summary", body = GrokCommitSummaryResponse),
+        (status = 400, description = "Unknown summary mode or format", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "grok"
 )]
 pub async fn summarize_commit(
-    State(_state): State<AppState>,
-    Json(req): Json<GrokCommitSummaryRequest>,
-) -> Result<Json<GrokCommitSummaryResponse>, (StatusCode, Json<ApiError>)> {
+    State(state): State<AppState>,
+    Json(mut req): Json<GrokCommitSummaryRequest>,
+) -> Result<Json<GrokCommitSummaryResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
     info!(
         "Grok summarize_commit called for {}/{}",
         req.repo, req.commit
     );
+    let format = SummaryFormat::parse(req.format.as_deref())
+        .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(e)))?;
+
+    if deterministic_mode(req.mode.as_deref())? {
+        let diff = commit_diff::compute(&state, &req.repo, &req.commit)
+            .await
+            .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+        let (summary, details) = deterministic_summary::commit_summary(&diff);
+        let comments = comments::for_commit(&state, &req.repo, &req.commit).await;
+        return Ok(Json(GrokCommitSummaryResponse {
+            repo: req.repo,
+            commit: req.commit,
+            summary: format.apply(summary),
+            details,
+            model: deterministic_summary::MODEL.to_string(),
+            prompt_version: deterministic_summary::TEMPLATE_VERSION.to_string(),
+            comments,
+            reused_from: None,
+            file_summaries: Vec::new(),
+        }));
+    }
+
+    // Construct GitHub commit URL
+    let github_url = github::commit_url(&req.repo, &req.commit);
+
+    // Create user message with GitHub URL
+    let user_message = format!(
+        "Search online for the changes in the commit {} and summarize the changes",
+        github_url
+    );
+
+    // Without a diff the summary is neither cached nor staged
+    let diff = match commit_diff::compute(&state, &req.repo, &req.commit).await {
+        Ok(diff) => Some(diff),
+        Err(e) => {
+            warn!("Failed to diff {}/{}: {}", req.repo, req.commit, e);
+            None
+        }
+    };
+    let diff_sha256 = diff.as_ref().and_then(summary_cache::diff_hash);
+
+    // A commit touching many sheets overflows one prompt: summarize each
+    // file first, then the commit from those summaries
+    let staged_settings = StagedSettings::from_env();
+    let staged_files = diff
+        .as_ref()
+        .map(staged_summary::split)
+        .filter(|files| files.len() >= staged_settings.min_files);
+    let (model, prompt_version) = match staged_files {
+        Some(_) => (STAGED_SUMMARY_MODEL, STAGED_SUMMARY_PROMPT_VERSION),
+        None => (COMMIT_SUMMARY_MODEL, COMMIT_SUMMARY_PROMPT_VERSION),
+    };
+
+    // A cherry-picked or re-applied change has the same diff as a commit
+    // already summarized: reuse that summary rather than paying for another
+    let repo_url = github::repo_url(&req.repo);
+    let mut generation = Generation {
+        repo_url: &repo_url,
+        commit: &req.commit,
+        model,
+        prompt_version,
+        prompt: &user_message,
+        tool_calls: &[],
+    };
+    if let Some(diff_sha256) = &diff_sha256 {
+        if let Some(cached) = summary_cache::reuse(&state, diff_sha256, &generation).await {
+            let comments = comments::for_commit(&state, &req.repo, &req.commit).await;
+            let file_summaries = list_file_summaries(&state, &cached.repo_url, &cached.commit_hash)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Failed to read file summaries of {}: {}", cached.commit_hash, e);
+                    Vec::new()
+                })
+                .into_iter()
+                .map(|f| GrokFileSummary {
+                    path: f.file_path,
+                    summary: f.summary,
+                })
+                .collect();
+            let same_commit = cached.commit_hash == req.commit
+                && cached.repo_url.eq_ignore_ascii_case(&repo_url);
+            let reused_from = (!same_commit).then(|| GrokSummarySource {
+                repo: git::repo_slug(&cached.repo_url)
+                    .unwrap_or(&cached.repo_url)
+                    .to_string(),
+                commit: cached.commit_hash,
+                provenance_id: cached.provenance_id,
+                generated_at: cached.created_at,
+            });
+            return Ok(Json(GrokCommitSummaryResponse {
+                repo: req.repo,
+                commit: req.commit,
+                summary: format.apply(cached.summary),
+                details: cached.details,
+                model: cached.model,
+                prompt_version: cached.prompt_version,
+                comments,
+                reused_from,
+                file_summaries,
+            }));
+        }
+    }
 
     // Load environment file to get XAI_API_KEY
     load_environment_file(None).map_err(|e| {
         error!("Failed to load environment file: {}", e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to load environment: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to load environment: {}", e)),
         )
     })?;
 
+    demo::check_llm_budget(&state).await?;
+
     // Create XAI client
     let xai_client = XaiClient::new().map_err(|e| {
         error!("Failed to create XAI client: {}", e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to initialize XAI client: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to initialize XAI client: {}", e)),
         )
     })?;
 
-    // Construct GitHub commit URL
-    let github_url = format!("https://github.com/{}/commit/{}", req.repo, req.commit);
-
-    // Create user message with GitHub URL
-    let user_message = format!(
-        "Search online for the changes in the commit {} and summarize the changes",
-        github_url
-    );
+    if let (Some(diff), Some(files)) = (&diff, &staged_files) {
+        let staged = staged_summary::summarize(
+            &state,
+            &xai_client,
+            &req.repo,
+            &req.commit,
+            diff,
+            files,
+            staged_settings.concurrency,
+        )
+        .await
+        .map_err(|e| {
+            error!("Staged summary of {}/{} failed: {}", req.repo, req.commit, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to get AI summary: {}", e)),
+            )
+        })?;
+        if let Some(diff_sha256) = &diff_sha256 {
+            let generation = Generation {
+                prompt: &staged.prompt,
+                ..generation
+            };
+            summary_cache::store(&state, diff_sha256, &generation, &staged.summary, &staged.details)
+                .await;
+        }
+        let comments = comments::for_commit(&state, &req.repo, &req.commit).await;
+        return Ok(Json(GrokCommitSummaryResponse {
+            repo: req.repo,
+            commit: req.commit,
+            summary: format.apply(staged.summary),
+            details: staged.details,
+            model: STAGED_SUMMARY_MODEL.to_string(),
+            prompt_version: STAGED_SUMMARY_PROMPT_VERSION.to_string(),
+            comments,
+            reused_from: None,
+            file_summaries: staged
+                .files
+                .into_iter()
+                .map(|f| GrokFileSummary {
+                    path: f.path,
+                    summary: f.summary,
+                })
+                .collect(),
+        }));
+    }
 
     // Create input message for responses API
-    let input = vec![InputMessage::user(user_message)];
+    let input = vec![InputMessage::user(user_message.clone())];
 
     // Create tools - use both web_search and x_search for comprehensive results
     let tools = vec![Tool::web_search(), Tool::x_search()];
 
     // Create responses request with hardcoded model
-    let responses_request = ResponsesRequest::new("grok-4-1-fast".to_string(), input, tools);
+    let responses_request =
+        ResponsesRequest::new(COMMIT_SUMMARY_MODEL.to_string(), input, tools);
 
     // Make API call using responses endpoint
     let api_response = xai_client
@@ -374,15 +697,20 @@ pub async fn summarize_commit(
         .await
         .map_err(|e| {
             error!("XAI API call failed: {}", e);
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!(
-                    "Failed to get AI summary: {}",
-                    e
-                ))),
+                ApiError::internal(format!("Failed to get AI summary: {}", e)),
             )
         })?;
 
+    llm_usage::record(
+        &state,
+        "commit_summary",
+        &responses_request.model,
+        api_response.usage.as_ref(),
+    )
+    .await;
+
     // TODO: Implement this or not.
     // Get changed files for context
     // let changed_files = git::get_changed_schematic_files(&req.repo, &req.commit)
@@ -438,6 +766,8 @@ pub async fn summarize_commit(
         format!("No results returned for commit {}/{}", req.repo, req.commit)
     };
 
+    let summary = output_filter::apply(&summary);
+
     // For details, include more information about the response
     let details = format!(
         "Response ID: {:?}\nModel: {:?}\nTool Results: {}\n\n{}",
@@ -452,6 +782,12 @@ pub async fn summarize_commit(
         req.repo, req.commit
     );
 
+    if let Some(diff_sha256) = &diff_sha256 {
+        let tool_calls = provenance::tool_calls(api_response.output.as_deref());
+        generation.tool_calls = &tool_calls;
+        summary_cache::store(&state, diff_sha256, &generation, &summary, &details).await;
+    }
+
     // Mock response - TODO: integrate with actual Grok API
     // let summary = format!(
     //     "[MOCK] This commit modified {} schematic file(s) in the {} repository.",
@@ -476,11 +812,18 @@ pub async fn summarize_commit(
     //         .join("\n")
     // );
 
+    let comments = comments::for_commit(&state, &req.repo, &req.commit).await;
+
     Ok(Json(GrokCommitSummaryResponse {
         repo: req.repo,
         commit: req.commit,
-        summary,
+        summary: format.apply(summary),
         details,
+        model: COMMIT_SUMMARY_MODEL.to_string(),
+        prompt_version: COMMIT_SUMMARY_PROMPT_VERSION.to_string(),
+        comments,
+        reused_from: None,
+        file_summaries: Vec::new(),
     }))
 }
 
@@ -491,20 +834,40 @@ pub async fn summarize_commit(
     request_body = GrokSelectionSummaryRequest,
     responses(
         (status = 200, description = "AI-generated component selection summary", body = GrokSelectionSummaryResponse),
+        (status = 400, description = "Unknown summary mode or format", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "grok"
 )]
 pub async fn summarize_selection(
-    State(_state): State<AppState>,
-    Json(req): Json<GrokSelectionSummaryRequest>,
-) -> Result<Json<GrokSelectionSummaryResponse>, (StatusCode, Json<ApiError>)> {
+    State(state): State<AppState>,
+    Json(mut req): Json<GrokSelectionSummaryRequest>,
+) -> Result<Json<GrokSelectionSummaryResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
     info!(
         "Grok summarize_selection called for {}/{} with {} components",
         req.repo,
         req.commit,
         req.component_ids.len()
     );
+    let format = SummaryFormat::parse(req.format.as_deref())
+        .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(e)))?;
+
+    if deterministic_mode(req.mode.as_deref())? {
+        let distilled = distill::get_or_distill(&state, &req.repo, &req.commit)
+            .await
+            .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+        let (summary, details) =
+            deterministic_summary::selection_summary(&distilled, &req.component_ids);
+        return Ok(Json(GrokSelectionSummaryResponse {
+            repo: req.repo,
+            commit: req.commit,
+            component_ids: req.component_ids,
+            summary: format.apply(summary),
+            details,
+        }));
+    }
 
     // Mock response - TODO: integrate with actual Grok API
     let summary = format!(
@@ -529,7 +892,7 @@ pub async fn summarize_selection(
         repo: req.repo,
         commit: req.commit,
         component_ids: req.component_ids,
-        summary,
+        summary: format.apply(summary),
         details,
     }))
 }
@@ -541,6 +904,7 @@ pub async fn summarize_selection(
     request_body = GrokRepoSummaryRequest,
     responses(
         (status = 200, description = "AI-generated repository summary", body = GrokRepoSummaryResponse),
+        (status = 400, description = "Unknown summary format", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "grok"
@@ -548,17 +912,16 @@ pub async fn summarize_selection(
 pub async fn summarize_repo(
     State(_state): State<AppState>,
     Json(req): Json<GrokRepoSummaryRequest>,
-) -> Result<Json<GrokRepoSummaryResponse>, (StatusCode, Json<ApiError>)> {
+) -> Result<Json<GrokRepoSummaryResponse>, AppError> {
     info!("Grok summarize_repo called for {}", req.repo);
+    let format = SummaryFormat::parse(req.format.as_deref())
+        .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(e)))?;
 
     // Get the latest commit
     let latest_commit = git::get_latest_commit(&req.repo).await.map_err(|e| {
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to fetch latest commit: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to fetch latest commit: {}", e)),
         )
     })?;
 
@@ -566,12 +929,9 @@ pub async fn summarize_repo(
     let files = git::get_schematic_files(&req.repo, &latest_commit)
         .await
         .map_err(|e| {
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!(
-                    "Failed to fetch schematic files: {}",
-                    e
-                ))),
+                ApiError::internal(format!("Failed to fetch schematic files: {}", e)),
             )
         })?;
 
@@ -603,57 +963,93 @@ pub async fn summarize_repo(
 
     Ok(Json(GrokRepoSummaryResponse {
         repo: req.repo,
-        summary,
+        summary: format.apply(summary),
         details,
     }))
 }
 
 /// Find replacement parts for an obsolete component using Grok AI
+///
+/// With `add_alternate` the part stays in the design: Grok looks for drop-in
+/// second sources instead, and the in-stock ones are approved as alternates
+/// of the part's BOM line in `repo`.
 #[utoipa::path(
     post,
     path = "/api/grok/obsolete/replacement",
     request_body = GrokObsoleteReplacementRequest,
     responses(
         (status = 200, description = "AI-generated replacement recommendations", body = GrokObsoleteReplacementResponse),
+        (status = 400, description = "`add_alternate` without a repository or a valid part number", body = ApiError),
+        (status = 503, description = "`in_stock_only` or `add_alternate` without a configured parts provider", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "grok"
 )]
 pub async fn find_replacement(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Json(req): Json<GrokObsoleteReplacementRequest>,
-) -> Result<Json<GrokObsoleteReplacementResponse>, (StatusCode, Json<ApiError>)> {
+) -> Result<Json<GrokObsoleteReplacementResponse>, AppError> {
     info!(
         "Grok find_replacement called for obsolete part: {}",
         req.manufacturer_part_number
     );
 
+    // Repository and line MPN the verified suggestions are approved under
+    let alternate_target = if req.add_alternate {
+        let repo = req.repo.as_deref().map(str::trim).filter(|r| !r.is_empty());
+        match (repo, mpn::normalize(&req.manufacturer_part_number)) {
+            (Some(repo), Some(line_mpn)) => Some((repo.to_string(), line_mpn)),
+            _ => {
+                return Err(AppError::new(
+                    StatusCode::BAD_REQUEST,
+                    ApiError::bad_request(
+                        "add_alternate needs a repo and a manufacturer_part_number",
+                    ),
+                ))
+            }
+        }
+    } else {
+        None
+    };
+
+    // Alternates are only approved once verified in stock
+    let verify_stock = req.in_stock_only || req.add_alternate;
+    if verify_stock && parts::PartsProvider::configured().is_empty() {
+        return Err(AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::new(
+                "not_configured",
+                "No parts provider is configured, so suggestions can't be checked against stock",
+            ),
+        ));
+    }
+
     // Load environment file to get XAI_API_KEY
     load_environment_file(None).map_err(|e| {
         error!("Failed to load environment file: {}", e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to load environment: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to load environment: {}", e)),
         )
     })?;
 
+    demo::check_llm_budget(&state).await?;
+
     // Create XAI client
     let xai_client = XaiClient::new().map_err(|e| {
         error!("Failed to create XAI client: {}", e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to initialize XAI client: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to initialize XAI client: {}", e)),
         )
     })?;
 
     // Build the context about the obsolete part
-    let mut part_info = format!("Obsolete Part: {}\n", req.manufacturer_part_number);
+    let mut part_info = if req.add_alternate {
+        format!("Part: {}\n", req.manufacturer_part_number)
+    } else {
+        format!("Obsolete Part: {}\n", req.manufacturer_part_number)
+    };
 
     if let Some(ref mfr) = req.manufacturer {
         part_info.push_str(&format!("Manufacturer: {}\n", mfr));
@@ -706,9 +1102,38 @@ For each recommended replacement, provide:
 Format the response clearly with headers and bullet points."#,
         part_info
     );
+    let user_message = if req.add_alternate {
+        format!(
+            r#"I need approved alternates (second sources) for an electronic component that stays in the design. Here is the information about the part:
+
+{}
+
+Please find parts that can be fitted in its place without changing the design:
+1. First, analyze the datasheet and product page (if URLs provided) to understand the full specifications
+2. Only suggest parts with the same footprint and pinout (true drop-in replacements)
+3. Match or exceed every key specification
+4. Prefer other manufacturers, so supply doesn't depend on a single source
+5. Only suggest parts that are actively manufactured (not NRND or obsolete)
+
+For each alternate, provide:
+- Part number and manufacturer
+- Why it can be fitted in place of the part (key matching specs)
+- Any differences to be aware of
+
+Format the response clearly with headers and bullet points."#,
+            part_info
+        )
+    } else {
+        user_message
+    };
+    let user_message = if verify_stock {
+        format!("{}{}", user_message, REPLACEMENT_JSON_INSTRUCTIONS)
+    } else {
+        user_message
+    };
 
     // Create input message for responses API
-    let input = vec![InputMessage::user(user_message)];
+    let input = vec![InputMessage::user(user_message.clone())];
 
     // Use web_search tool for comprehensive online research
     let tools = vec![Tool::web_search()];
@@ -723,15 +1148,20 @@ Format the response clearly with headers and bullet points."#,
         .await
         .map_err(|e| {
             error!("XAI API call failed: {}", e);
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!(
-                    "Failed to get AI replacement suggestions: {}",
-                    e
-                ))),
+                ApiError::internal(format!("Failed to get AI replacement suggestions: {}", e)),
             )
         })?;
 
+    llm_usage::record(
+        &state,
+        "obsolete_replacement",
+        &responses_request.model,
+        api_response.usage.as_ref(),
+    )
+    .await;
+
     // Extract the analysis from the response
     let analysis = if let Some(output) = &api_response.output {
         let mut result_parts = Vec::new();
@@ -790,14 +1220,129 @@ Format the response clearly with headers and bullet points."#,
         )
     };
 
+    // Cross-check each suggestion against live distributor stock
+    let mut suggestions = Vec::new();
+    let mut dropped_suggestions = Vec::new();
+    let mut verified = false;
+    let analysis = if verify_stock {
+        let (analysis, suggested) = extract_suggested_replacements(&analysis);
+        if suggested.is_none() {
+            warn!(
+                "No replacement list in the reply for {}; suggestions not stock-checked",
+                req.manufacturer_part_number
+            );
+        }
+        verified = suggested.is_some();
+        for suggestion in suggested.unwrap_or_default() {
+            let search = parts::find_offers(&suggestion.manufacturer_part_number).await;
+            let offers: Vec<_> = search
+                .offers
+                .into_iter()
+                .filter(|o| o.is_purchasable())
+                .collect();
+
+            // A failed lookup says nothing about stock, so the part is kept unverified
+            if offers.is_empty() && search.failed.is_empty() {
+                info!(
+                    "Dropping replacement {}: not in stock at any distributor",
+                    suggestion.manufacturer_part_number
+                );
+                dropped_suggestions.push(suggestion.manufacturer_part_number);
+            } else {
+                suggestions.push(GrokReplacementSuggestion {
+                    manufacturer_part_number: suggestion.manufacturer_part_number,
+                    manufacturer: suggestion.manufacturer,
+                    reason: suggestion.reason.map(|r| output_filter::apply(&r)),
+                    stock_verified: !offers.is_empty(),
+                    offers,
+                });
+            }
+        }
+        let kept: Vec<&str> = suggestions
+            .iter()
+            .map(|s| s.manufacturer_part_number.as_str())
+            .collect();
+        without_dropped_parts(&analysis, &dropped_suggestions, &kept)
+    } else {
+        analysis
+    };
+    let analysis = output_filter::apply(&analysis);
+
+    let mut added_alternates = Vec::new();
+    let mut provenance_id = None;
+    if let Some((repo, line_mpn)) = &alternate_target {
+        let repo_url = github::repo_url(repo);
+        for suggestion in suggestions.iter().filter(|s| s.stock_verified) {
+            let Some(alternate_mpn) =
+                mpn::normalize(&suggestion.manufacturer_part_number).filter(|m| m != line_mpn)
+            else {
+                continue;
+            };
+            match add_alternate(
+                &state,
+                &repo_url,
+                line_mpn,
+                &alternate_mpn,
+                suggestion.manufacturer.as_deref(),
+                suggestion.reason.as_deref(),
+            )
+            .await
+            {
+                Ok(Some(_)) => added_alternates.push(alternate_mpn),
+                Ok(None) => warn!("Invalid repository {:?}; alternates not stored", repo),
+                Err(e) => error!("Failed to store alternate {}: {}", alternate_mpn, e),
+            }
+        }
+        info!(
+            "Approved {} alternate(s) of {} in {}",
+            added_alternates.len(),
+            line_mpn,
+            repo
+        );
+
+        if !added_alternates.is_empty() {
+            let tool_calls = provenance::tool_calls(api_response.output.as_deref());
+            let approved = added_alternates.join("\n");
+            provenance_id = provenance::record(
+                &state,
+                &NewProvenance {
+                    artifact: ARTIFACT_ALTERNATE,
+                    artifact_ref: line_mpn,
+                    repo_url: Some(&repo_url),
+                    commit_hash: None,
+                    model: &responses_request.model,
+                    prompt_version: REPLACEMENT_PROMPT_VERSION,
+                    prompt: Some(&user_message),
+                    tool_calls: &tool_calls,
+                    inputs: &[(
+                        "manufacturer_part_number",
+                        req.manufacturer_part_number.as_bytes(),
+                    )],
+                    outputs: &[
+                        ("analysis", analysis.as_bytes()),
+                        ("alternates", approved.as_bytes()),
+                    ],
+                },
+            )
+            .await;
+        }
+    }
+
     info!(
-        "Successfully generated replacement suggestions for {}",
-        req.manufacturer_part_number
+        "Successfully generated replacement suggestions for {} ({} verified, {} dropped)",
+        req.manufacturer_part_number,
+        suggestions.len(),
+        dropped_suggestions.len()
     );
 
     Ok(Json(GrokObsoleteReplacementResponse {
         original_part: req.manufacturer_part_number,
         analysis,
+        suggestions,
+        dropped_suggestions,
+        verified,
+        added_alternates,
+        provenance_id,
         success: true,
         error: None,
     }))
@@ -814,31 +1359,27 @@ Format the response clearly with headers and bullet points."#,
     tag = "grok"
 )]
 pub async fn chat_stream(
-    State(_state): State<AppState>,
-) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<ApiError>)> {
+    State(state): State<AppState>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
     info!("Grok chat_stream called");
 
     // Load environment file to get XAI_API_KEY
     load_environment_file(None).map_err(|e| {
         error!("Failed to load environment file: {}", e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to load environment: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to load environment: {}", e)),
         )
     })?;
 
+    demo::check_llm_budget(&state).await?;
+
     // Create XAI client
     let xai_client = XaiClient::new().map_err(|e| {
         error!("Failed to create XAI client: {}", e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to initialize XAI client: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to initialize XAI client: {}", e)),
         )
     })?;
 
@@ -861,28 +1402,31 @@ pub async fn chat_stream(
         ChatCompletionRequest::with_stream(messages, "grok-3-fast".to_string(), true);
 
     // Get the stream
-    let stream = xai_client
+    let (stream, usage) = xai_client
         .chat_completion_stream(&chat_request)
         .await
         .map_err(|e| {
             error!("Failed to create XAI stream: {}", e);
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!(
-                    "Failed to start AI stream: {}",
-                    e
-                ))),
+                ApiError::internal(format!("Failed to start AI stream: {}", e)),
             )
         })?;
+    let mut meter = llm_usage::StreamMeter::new(state.clone(), "chat", &chat_request, usage);
 
     // Convert the stream to SSE events
     let sse_stream = async_stream::stream! {
         tokio::pin!(stream);
+        let mut filter = output_filter::stream();
 
         while let Some(result) = stream.next().await {
             match result {
                 Ok(content) => {
-                    yield Ok(Event::default().data(content));
+                    meter.add(&content);
+                    let content = filter.push(&content);
+                    if !content.is_empty() {
+                        yield Ok(Event::default().data(content));
+                    }
                 }
                 Err(e) => {
                     error!("Stream error: {}", e);
@@ -892,6 +1436,11 @@ pub async fn chat_stream(
             }
         }
 
+        let rest = filter.finish();
+        if !rest.is_empty() {
+            yield Ok(Event::default().data(rest));
+        }
+
         // Send a done event
         yield Ok(Event::default().data("[DONE]"));
     };
@@ -903,21 +1452,220 @@ pub async fn chat_stream(
     ))
 }
 
+/// Load an active analysis session for a selection stream on `repo` at `commit`
+async fn load_session(
+    pool: &PgPool,
+    id: &str,
+    repo: &str,
+    commit: &str,
+) -> Result<AnalysisSession, AppError> {
+    let not_found = || {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("Analysis session {} not found or expired", id)),
+        )
+    };
+    let Ok(uuid) = Uuid::parse_str(id) else {
+        return Err(not_found());
+    };
+    let session = get_session(pool, uuid, analysis_sessions::idle_timeout_secs())
+        .await
+        .map_err(|e| {
+            error!("Failed to load analysis session {}: {}", id, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to load analysis session: {}", e)),
+            )
+        })?
+        .ok_or_else(not_found)?;
+
+    let repo_url = github::repo_url(repo);
+    if !session.repo_url.eq_ignore_ascii_case(&repo_url) || session.commit_hash != commit {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!(
+                "Analysis session {} belongs to another repository or commit",
+                id
+            )),
+        ));
+    }
+    Ok(session)
+}
+
+/// Start an analysis session over a commit's schematic
+///
+/// The schematic context is built once and kept with the session (with
+/// `distilled`, if sent). Selection streams that pass the session id reuse it
+/// along with the earlier questions and answers, so follow-ups don't resend
+/// the schematic and share a prompt prefix the provider can cache. Sessions
+/// close after ANALYSIS_SESSION_IDLE_SECS without a question.
+#[utoipa::path(
+    post,
+    path = "/api/grok/session",
+    request_body = GrokSessionRequest,
+    responses(
+        (status = 200, description = "Started analysis session", body = GrokSessionResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "grok"
+)]
+pub async fn start_session(
+    State(state): State<AppState>,
+    Json(mut req): Json<GrokSessionRequest>,
+) -> Result<Json<GrokSessionResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    analysis_sessions::prune(&state).await;
+
+    let distilled = match &req.distilled {
+        Some(d) => d.clone(),
+        None => distill::get_or_distill(&state, &req.repo, &req.commit)
+            .await
+            .map_err(|e| distillation_error(&req.repo, &req.commit, e))?,
+    };
+    let sheet_meta = distill::sheet_meta(&state, &req.repo).await;
+    let (_, schematic_summary) =
+        build_component_context(&distilled, &[], &sheet_meta, &BTreeMap::new());
+    let context = selection_system_prompt(&schematic_summary);
+
+    let repo_url = github::repo_url(&req.repo);
+    let session = create_session(
+        &state,
+        &repo_url,
+        &req.commit,
+        &context,
+        req.distilled.as_ref(),
+    )
+    .await
+    .map_err(|e| {
+        error!("Failed to start analysis session for {}: {}", req.repo, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to start analysis session: {}", e)),
+        )
+    })?
+    .ok_or_else(|| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!("Invalid repository: {}", req.repo)),
+        )
+    })?;
+    info!(
+        "Started analysis session {} for {}/{} ({} chars of context)",
+        session.id,
+        req.repo,
+        req.commit,
+        context.len()
+    );
+
+    Ok(Json(GrokSessionResponse {
+        session_id: session.id.to_string(),
+        repo: req.repo,
+        commit: req.commit,
+        context_chars: context.len(),
+        idle_timeout_secs: analysis_sessions::idle_timeout_secs(),
+    }))
+}
+
+/// Export an analysis session's transcript
+///
+/// Every question with the components it was about and Grok's answer, as
+/// Markdown (default) for pasting into design review documents, or as JSON.
+/// Sessions can be exported after they close, until they are deleted.
+#[utoipa::path(
+    get,
+    path = "/api/grok/sessions/{id}/export",
+    params(
+        ("id" = String, Path, description = "Session id"),
+        GrokSessionExportQuery
+    ),
+    responses(
+        (status = 200, description = "Transcript (text/markdown or application/json)", body = GrokSessionExport),
+        (status = 400, description = "Unknown format", body = ApiError),
+        (status = 404, description = "Analysis session not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "grok"
+)]
+pub async fn export_session(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<GrokSessionExportQuery>,
+) -> Result<Response, AppError> {
+    let markdown = match query.format.as_deref().unwrap_or("markdown") {
+        "markdown" | "md" => true,
+        "json" => false,
+        other => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!(
+                    "Unknown export format: {} (expected markdown or json)",
+                    other
+                )),
+            ))
+        }
+    };
+    let not_found = || {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("Analysis session {} not found", id)),
+        )
+    };
+    let Ok(uuid) = Uuid::parse_str(&id) else {
+        return Err(not_found());
+    };
+    let session = find_session(&state, uuid)
+        .await
+        .map_err(|e| {
+            error!("Failed to load analysis session {}: {}", id, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to load analysis session: {}", e)),
+            )
+        })?
+        .ok_or_else(not_found)?;
+
+    let export: GrokSessionExport = analysis_sessions::export(&session);
+    info!(
+        "Exported analysis session {} ({} turns)",
+        id,
+        export.turns.len()
+    );
+    if !markdown {
+        return Ok(Json(export).into_response());
+    }
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            "text/markdown; charset=utf-8".to_string(),
+        )],
+        analysis_sessions::export_markdown(&export),
+    )
+        .into_response())
+}
+
 /// Stream an AI analysis of selected components using Server-Sent Events
+///
+/// With `session_id`, the session's schematic context and earlier turns are
+/// reused and this question and its answer are added to the session.
 #[utoipa::path(
     post,
     path = "/api/grok/selection/stream",
     request_body = GrokSelectionStreamRequest,
     responses(
         (status = 200, description = "Streaming AI analysis response via SSE"),
+        (status = 400, description = "Session belongs to another repository or commit", body = ApiError),
+        (status = 404, description = "Analysis session not found or expired", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "grok"
 )]
 pub async fn selection_stream(
     State(state): State<AppState>,
-    Json(req): Json<GrokSelectionStreamRequest>,
-) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<ApiError>)> {
+    Json(mut req): Json<GrokSelectionStreamRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
     info!(
         "Grok selection_stream called for {}/{} with {} components",
         req.repo,
@@ -928,81 +1676,76 @@ pub async fn selection_stream(
     // Load environment file to get XAI_API_KEY
     load_environment_file(None).map_err(|e| {
         error!("Failed to load environment file: {}", e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to load environment: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to load environment: {}", e)),
         )
     })?;
 
-    // Create XAI client
+    let session = match &req.session_id {
+        Some(id) => Some(load_session(&state, id, &req.repo, &req.commit).await?),
+        None => None,
+    };
+
+    demo::check_llm_budget(&state).await?;
+
+    // Create XAI client; a session's requests share a conversation for prompt caching
     let xai_client = XaiClient::new().map_err(|e| {
         error!("Failed to create XAI client: {}", e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to initialize XAI client: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to initialize XAI client: {}", e)),
         )
     })?;
+    let xai_client = match &session {
+        Some(session) => xai_client.with_conversation(session.id.to_string()),
+        None => xai_client,
+    };
 
-    // Get distilled schematic data - either from request or fetch it
-    let distilled = if let Some(d) = req.distilled {
+    // Get distilled schematic data - from the session, the request, or fetch it
+    let distilled = if let Some(d) = session
+        .as_ref()
+        .and_then(|s| s.distilled_json.clone())
+        .or(req.distilled)
+    {
         d
     } else {
         // Fetch distilled data from cache or generate it
-        let repo_url = format!("https://github.com/{}.git", req.repo);
-        match kicad_db::retrieve_distilled_json(&state, &repo_url, &req.commit).await {
-            Ok(Some(cached)) => cached,
-            _ => {
-                // Generate if not cached
-                distill::distill_repo_schematics(&req.repo, &req.commit)
-                    .await
-                    .map_err(|e| {
-                        error!("Failed to distill schematic: {}", e);
-                        (
-                            StatusCode::INTERNAL_SERVER_ERROR,
-                            Json(ApiError::internal(format!(
-                                "Failed to distill schematic: {}",
-                                e
-                            ))),
-                        )
-                    })?
-            }
-        }
+        distill::get_or_distill(&state, &req.repo, &req.commit)
+            .await
+            .map_err(|e| distillation_error(&req.repo, &req.commit, e))?
     };
 
     // Build rich semantic context from distilled data
-    let (selected_context, schematic_summary) = build_component_context(&distilled, &req.component_ids);
-
-    // Load the system prompt from file
-    let base_system_prompt = load_system_prompt();
-
-    // Build system and user messages with the loaded system prompt
-    let system_prompt = format!(
-        "{}\n\n---\n\n## Schematic Context\n{}",
-        base_system_prompt,
-        schematic_summary
-    );
+    let sheet_meta = distill::sheet_meta(&state, &req.repo).await;
+    let notes = component_notes::for_repo(&state, &req.repo).await;
+    let (selected_context, schematic_summary) =
+        build_component_context(&distilled, &req.component_ids, &sheet_meta, &notes);
 
     let user_prompt = format!(
-        "{}\n\n---\n\n## User's Question\n{}",
+        "{}{}{}",
         selected_context,
+        analysis_sessions::QUESTION_HEADING,
         req.query
     );
 
+    // Build system and user messages; a session brings its own context and earlier turns
+    let messages = match &session {
+        Some(session) => analysis_sessions::messages(session, user_prompt.clone()),
+        None => vec![
+            Message::system(selection_system_prompt(&schematic_summary)),
+            Message::user(user_prompt.clone()),
+        ],
+    };
+
     info!(
-        "Using system prompt ({} chars), context ({} chars), thinking_mode: {}",
-        system_prompt.len(),
+        "Using system prompt ({} chars), context ({} chars), {} message(s), thinking_mode: {}",
+        messages[0].content.len(),
         user_prompt.len(),
+        messages.len(),
         req.thinking_mode
     );
 
-    let messages = vec![Message::system(system_prompt), Message::user(user_prompt)];
-
     // Create chat completion request with streaming
     // Use grok-4-1-fast model, with optional reasoning/thinking mode
     let chat_request = if req.thinking_mode {
@@ -1017,37 +1760,67 @@ pub async fn selection_stream(
     };
 
     // Get the stream
-    let stream = xai_client
+    let (stream, usage) = xai_client
         .chat_completion_stream(&chat_request)
         .await
         .map_err(|e| {
             error!("Failed to create XAI stream: {}", e);
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!(
-                    "Failed to start AI stream: {}",
-                    e
-                ))),
+                ApiError::internal(format!("Failed to start AI stream: {}", e)),
             )
         })?;
+    let mut meter =
+        llm_usage::StreamMeter::new(state.clone(), "selection_chat", &chat_request, usage);
 
-    // Convert the stream to SSE events
+    // Convert the stream to SSE events, collecting the answer for the session
+    let session_id = session.map(|s| s.id);
+    let pool = state.clone();
+    let (question, components) = (req.query, req.component_ids);
     let sse_stream = async_stream::stream! {
         tokio::pin!(stream);
+        let mut filter = output_filter::stream();
+        let mut answer = String::new();
+        let mut complete = true;
 
         while let Some(result) = stream.next().await {
             match result {
                 Ok(content) => {
+                    meter.add(&content);
+                    let content = filter.push(&content);
+                    if content.is_empty() {
+                        continue;
+                    }
+                    if !content.starts_with("<thinking>") {
+                        answer.push_str(&content);
+                    }
                     yield Ok(Event::default().data(content));
                 }
                 Err(e) => {
                     error!("Stream error: {}", e);
                     yield Ok(Event::default().data(format!("[ERROR: {}]", e)));
+                    complete = false;
                     break;
                 }
             }
         }
 
+        let rest = filter.finish();
+        if !rest.is_empty() {
+            answer.push_str(&rest);
+            yield Ok(Event::default().data(rest));
+        }
+
+        if let (Some(id), true) = (session_id, complete) {
+            let turn = NewTurn {
+                user: &user_prompt,
+                assistant: &answer,
+                question: &question,
+                components: &components,
+            };
+            analysis_sessions::record_turn(&pool, id, &turn).await;
+        }
+
         // Send a done event
         yield Ok(Event::default().data("[DONE]"));
     };
@@ -1058,3 +1831,603 @@ pub async fn selection_stream(
             .text("keep-alive"),
     ))
 }
+
+/// System prompt of a repository question: the loaded prompt, how to use
+/// and cite the excerpts, and the size of the design
+fn repo_question_system_prompt(repo: &str, commit: &str, searched: usize) -> String {
+    format!(
+        "{}\n\n---\n\n## Repository Questions\n\
+        The user asks about the whole project {} at commit {}. Its schematic, \
+        summaries and comments make up {} excerpts; only the ones most related to the \
+        question are given, numbered [1], [2], and so on. Answer from the excerpts, \
+        citing the ones you use by their numbers. If they don't answer the question, \
+        say so and suggest what to ask or select instead.",
+        load_system_prompt(),
+        repo,
+        &commit[..8.min(commit.len())],
+        searched
+    )
+}
+
+/// Stream an answer to a question about a whole repository using Server-Sent Events
+///
+/// Instead of sending the entire design, the commit's retrieval index (its
+/// components, nets, sheets, summaries and comments, see
+/// `services::retrieval`) is ranked against the question and only the best
+/// excerpts that fit a fixed context budget are sent. `sheet` and
+/// `categories` narrow the excerpts searched. The first event, named
+/// `citations`, lists those excerpts (`GrokCitation`) by the numbers the
+/// answer cites them with; the answer follows as data events, then `[DONE]`.
+#[utoipa::path(
+    post,
+    path = "/api/grok/ask/repo",
+    request_body = GrokAskRepoRequest,
+    responses(
+        (status = 200, description = "`citations` event, then the streamed answer via SSE"),
+        (status = 400, description = "Empty question, unknown category or unknown revision", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "grok"
+)]
+pub async fn ask_repo(
+    State(state): State<AppState>,
+    Json(req): Json<GrokAskRepoRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    let question = req.question.trim().to_string();
+    if question.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("question is required"),
+        ));
+    }
+    if let Some(unknown) = req
+        .categories
+        .iter()
+        .find(|c| !retrieval::CATEGORIES.contains(&c.as_str()))
+    {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!(
+                "Unknown category: {} (expected one of {})",
+                unknown,
+                retrieval::CATEGORIES.join(", ")
+            )),
+        ));
+    }
+    let commit = resolve_revision(&req.repo, req.commit.as_deref().unwrap_or("HEAD")).await?;
+    info!("Grok ask_repo called for {}/{}", req.repo, commit);
+
+    // Load environment file to get XAI_API_KEY
+    load_environment_file(None).map_err(|e| {
+        error!("Failed to load environment file: {}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to load environment: {}", e)),
+        )
+    })?;
+
+    demo::check_llm_budget(&state).await?;
+
+    let xai_client = XaiClient::new().map_err(|e| {
+        error!("Failed to create XAI client: {}", e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to initialize XAI client: {}", e)),
+        )
+    })?;
+
+    let categories: Vec<&str> = req.categories.iter().map(String::as_str).collect();
+    let query = RetrievalQuery {
+        repo: &req.repo,
+        commit: &commit,
+        question: &question,
+        sheet: req.sheet.as_deref(),
+        categories: &categories,
+    };
+    let retrieved = retrieval::query(&state, &query)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &commit, e))?;
+
+    let excerpts = if retrieved.citations.is_empty() {
+        "No part of the design matched the question.".to_string()
+    } else {
+        retrieved.context.clone()
+    };
+    let notes = component_notes::prompt_section(
+        &component_notes::for_repo(&state, &req.repo).await,
+        retrieved
+            .citations
+            .iter()
+            .filter(|c| c.kind == "component")
+            .map(|c| c.key.as_str()),
+    );
+    let messages = vec![
+        Message::system(repo_question_system_prompt(
+            &req.repo,
+            &commit,
+            retrieved.searched,
+        )),
+        Message::user(format!(
+            "## Design Excerpts\n{}{}{}{}",
+            excerpts,
+            notes,
+            analysis_sessions::QUESTION_HEADING,
+            question
+        )),
+    ];
+
+    info!(
+        "Using {} of {} excerpts ({} chars) for {}/{}, thinking_mode: {}",
+        retrieved.citations.len(),
+        retrieved.searched,
+        retrieved.context.len(),
+        req.repo,
+        commit,
+        req.thinking_mode
+    );
+
+    let chat_request = if req.thinking_mode {
+        ChatCompletionRequest::with_reasoning(
+            messages,
+            "grok-4-1-fast".to_string(),
+            true,
+            ReasoningEffort::Low,
+        )
+    } else {
+        ChatCompletionRequest::with_stream(messages, "grok-4-1-fast".to_string(), true)
+    };
+
+    let (stream, usage) = xai_client
+        .chat_completion_stream(&chat_request)
+        .await
+        .map_err(|e| {
+            error!("Failed to create XAI stream: {}", e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to start AI stream: {}", e)),
+            )
+        })?;
+    let mut meter = llm_usage::StreamMeter::new(state.clone(), "ask_repo", &chat_request, usage);
+
+    let citations = Event::default()
+        .event("citations")
+        .json_data(&retrieved.citations)
+        .unwrap_or_else(|_| Event::default().event("citations").data("[]"));
+    let sse_stream = async_stream::stream! {
+        yield Ok(citations);
+        tokio::pin!(stream);
+        let mut filter = output_filter::stream();
+        while let Some(result) = stream.next().await {
+            match result {
+                Ok(content) => {
+                    meter.add(&content);
+                    let content = filter.push(&content);
+                    if !content.is_empty() {
+                        yield Ok(Event::default().data(content));
+                    }
+                }
+                Err(e) => {
+                    error!("Stream error: {}", e);
+                    yield Ok(Event::default().data(format!("[ERROR: {}]", e)));
+                    break;
+                }
+            }
+        }
+        let rest = filter.finish();
+        if !rest.is_empty() {
+            yield Ok(Event::default().data(rest));
+        }
+        yield Ok(Event::default().data("[DONE]"));
+    };
+
+    Ok(Sse::new(sse_stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(15))
+            .text("keep-alive"),
+    ))
+}
+
+/// Stream an explanation of a net's likely function using Server-Sent Events
+///
+/// The explanation is generated from the components with pins on the net and
+/// the passives placed near them. The first event, named `net`, carries that
+/// context (`GrokNetContext`); the explanation follows as data events, then
+/// `[DONE]`. Explanations are stored: a net whose context is unchanged, at
+/// this commit or another, replays the stored one unless `regenerate` is set.
+#[utoipa::path(
+    post,
+    path = "/api/grok/explain/net",
+    request_body = GrokExplainNetRequest,
+    responses(
+        (status = 200, description = "`net` event, then the streamed explanation via SSE"),
+        (status = 400, description = "Unknown revision", body = ApiError),
+        (status = 404, description = "No net of that name at the commit", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "grok"
+)]
+pub async fn explain_net(
+    State(state): State<AppState>,
+    Json(mut req): Json<GrokExplainNetRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!(
+        "Grok explain_net called for {} in {}/{}",
+        req.net, req.repo, req.commit
+    );
+
+    let distilled = distill::get_or_distill(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+    let notes = component_notes::for_repo(&state, &req.repo).await;
+    let mut context = net_explain::context(&distilled, &req.repo, &req.commit, &req.net, &notes)
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!(
+                    "No net named {} in {} at {}",
+                    req.net, req.repo, req.commit
+                )),
+            )
+        })?;
+    let repo_url = github::repo_url(&req.repo);
+    let hash = net_explain::context_hash(&context);
+
+    let stored = if req.regenerate {
+        None
+    } else {
+        net_explain::find(&state, &repo_url, &req.net, &hash).await
+    };
+    let prompt = net_explain::prompt(&context);
+    let stream = match &stored {
+        Some(stored) => {
+            info!(
+                "Replaying explanation of {} generated at {}",
+                req.net, stored.commit_hash
+            );
+            context.reused_from = Some(stored.commit_hash.clone());
+            context.explained_at = Some(stored.created_at);
+            None
+        }
+        None => {
+            // Load environment file to get XAI_API_KEY
+            load_environment_file(None).map_err(|e| {
+                error!("Failed to load environment file: {}", e);
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiError::internal(format!("Failed to load environment: {}", e)),
+                )
+            })?;
+
+            demo::check_llm_budget(&state).await?;
+
+            let xai_client = XaiClient::new().map_err(|e| {
+                error!("Failed to create XAI client: {}", e);
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiError::internal(format!("Failed to initialize XAI client: {}", e)),
+                )
+            })?;
+
+            let messages = vec![
+                Message::system(net_explain::SYSTEM_PROMPT.to_string()),
+                Message::user(prompt.clone()),
+            ];
+            let chat_request = ChatCompletionRequest::with_stream(
+                messages,
+                net_explain::NET_EXPLAIN_MODEL.to_string(),
+                true,
+            );
+            let (stream, usage) = xai_client
+                .chat_completion_stream(&chat_request)
+                .await
+                .map_err(|e| {
+                    error!("Failed to create XAI stream: {}", e);
+                    AppError::new(
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        ApiError::internal(format!("Failed to start AI stream: {}", e)),
+                    )
+                })?;
+            let meter =
+                llm_usage::StreamMeter::new(state.clone(), "net_explain", &chat_request, usage);
+            Some((stream, meter))
+        }
+    };
+
+    let net_event = Event::default()
+        .event("net")
+        .json_data(&context)
+        .unwrap_or_else(|_| Event::default().event("net").data("{}"));
+    let stored = stored.map(|s| s.explanation);
+    let pool = state.clone();
+    let sse_stream = async_stream::stream! {
+        yield Ok(net_event);
+        if let Some(explanation) = stored {
+            yield Ok(Event::default().data(explanation));
+        }
+        if let Some((stream, mut meter)) = stream {
+            tokio::pin!(stream);
+            let mut filter = output_filter::stream();
+            let mut explanation = String::new();
+            let mut complete = true;
+            while let Some(result) = stream.next().await {
+                match result {
+                    Ok(content) => {
+                        meter.add(&content);
+                        let content = filter.push(&content);
+                        if !content.is_empty() {
+                            explanation.push_str(&content);
+                            yield Ok(Event::default().data(content));
+                        }
+                    }
+                    Err(e) => {
+                        error!("Stream error: {}", e);
+                        yield Ok(Event::default().data(format!("[ERROR: {}]", e)));
+                        complete = false;
+                        break;
+                    }
+                }
+            }
+            let rest = filter.finish();
+            if !rest.is_empty() {
+                explanation.push_str(&rest);
+                yield Ok(Event::default().data(rest));
+            }
+            if complete && !explanation.trim().is_empty() {
+                net_explain::store(&pool, &repo_url, &context, &hash, &prompt, &explanation).await;
+            }
+        }
+        yield Ok(Event::default().data("[DONE]"));
+    };
+
+    Ok(Sse::new(sse_stream).keep_alive(
+        axum::response::sse::KeepAlive::new()
+            .interval(Duration::from_secs(15))
+            .text("keep-alive"),
+    ))
+}
+
+/// Generate hardware release notes for a commit range
+///
+/// `from` and `to` accept commit hashes or tags; the range covers commits
+/// reachable from `to` but not from `from`. The notes consolidate every
+/// schematic commit in the range with the BOM delta between its ends and are
+/// stored, so asking for the same range again returns the stored notes unless
+/// `regenerate` is set.
+#[utoipa::path(
+    post,
+    path = "/api/grok/release-notes",
+    request_body = GrokReleaseNotesRequest,
+    responses(
+        (status = 200, description = "Release notes for the range", body = ReleaseNotesResponse),
+        (status = 400, description = "Unknown revision or no schematic changes in the range", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "grok"
+)]
+pub async fn generate_release_notes(
+    State(state): State<AppState>,
+    Json(req): Json<GrokReleaseNotesRequest>,
+) -> Result<Json<ReleaseNotesResponse>, AppError> {
+    info!(
+        "Grok release notes requested for {} {}..{}",
+        req.repo, req.from, req.to
+    );
+
+    // Resolve both ends first so a typo comes back as a 400
+    for rev in [&req.from, &req.to] {
+        resolve_revision(&req.repo, rev).await?;
+    }
+
+    let range = git::get_schematic_changes_in_range(&req.repo, &req.from, &req.to)
+        .await
+        .map_err(|e| {
+            error!("Failed to walk {} {}..{}: {:#}", req.repo, req.from, req.to, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to read commit range: {}", e)),
+            )
+        })?;
+
+    if range.commits.is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!(
+                "No schematic changes between {} and {}",
+                req.from, req.to
+            )),
+        ));
+    }
+
+    let repo_url = github::repo_url(&req.repo);
+    if !req.regenerate {
+        match kicad_db::release_notes::find_release_notes(
+            &state,
+            &repo_url,
+            &range.from_commit,
+            &range.to_commit,
+        )
+        .await
+        {
+            Ok(Some(stored)) => {
+                return Ok(Json(release_notes::to_response(stored, &req.repo, true)));
+            }
+            Ok(None) => {}
+            Err(e) => error!("Failed to look up stored release notes: {}", e),
+        }
+    }
+
+    let record = release_notes::generate(&state, &req.repo, &req.from, &req.to, &range)
+        .await
+        .map_err(|e| {
+            let cause = distill::cause(&e);
+            if cause.downcast_ref::<crate::services::kicad_format::UnsupportedFormat>().is_some()
+                || cause.downcast_ref::<demo::DemoLimit>().is_some()
+            {
+                return distillation_error(&req.repo, &range.to_commit, e);
+            }
+            error!(
+                "Failed to generate release notes for {} {}..{}: {:#}",
+                req.repo, req.from, req.to, e
+            );
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to generate release notes: {:#}", e)),
+            )
+        })?;
+
+    Ok(Json(release_notes::to_response(record, &req.repo, false)))
+}
+
+/// Get stored release notes by id
+#[utoipa::path(
+    get,
+    path = "/api/grok/release-notes/{id}",
+    params(
+        ("id" = i64, Path, description = "Release notes id")
+    ),
+    responses(
+        (status = 200, description = "Stored release notes", body = ReleaseNotesResponse),
+        (status = 404, description = "Release notes not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "grok"
+)]
+pub async fn get_release_notes(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ReleaseNotesResponse>, AppError> {
+    let record = kicad_db::release_notes::get_release_notes(&state, id)
+        .await
+        .map_err(|e| {
+            error!("Failed to load release notes {}: {}", id, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to load release notes: {}", e)),
+            )
+        })?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!("Release notes {} not found", id)),
+            )
+        })?;
+
+    let repo = git::repo_slug(&record.repo_url)
+        .unwrap_or(&record.repo_url)
+        .to_string();
+    Ok(Json(release_notes::to_response(record, &repo, true)))
+}
+
+/// List stored release notes for a repository
+#[utoipa::path(
+    post,
+    path = "/api/grok/release-notes/list",
+    request_body = ReleaseNotesListRequest,
+    responses(
+        (status = 200, description = "Stored release notes, newest first", body = ReleaseNotesListResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "grok"
+)]
+pub async fn list_release_notes(
+    State(state): State<AppState>,
+    Json(req): Json<ReleaseNotesListRequest>,
+) -> Result<Json<ReleaseNotesListResponse>, AppError> {
+    let repo_url = github::repo_url(&req.repo);
+    let limit = req.limit.unwrap_or(20).clamp(1, 100);
+    let records = kicad_db::release_notes::list_release_notes(&state, &repo_url, limit)
+        .await
+        .map_err(|e| {
+            error!("Failed to list release notes for {}: {}", req.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to list release notes: {}", e)),
+            )
+        })?;
+
+    Ok(Json(ReleaseNotesListResponse {
+        release_notes: records
+            .into_iter()
+            .map(|r| release_notes::to_response(r, &req.repo, true))
+            .collect(),
+        repo: req.repo,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fenced_json_block_is_split_out() {
+        let answer = "## Replacements\nTPS7A4700 fits.\n\n```json\n[{\"manufacturer_part_number\": \"TPS7A4700RGWR\", \"manufacturer\": \"Texas Instruments\", \"reason\": \"Same pinout\"}, {\"manufacturer_part_number\": \"LT3045EDD\"}]\n```\n";
+        let (analysis, suggested) = extract_suggested_replacements(answer);
+        let suggested = suggested.unwrap();
+
+        assert_eq!(analysis, "## Replacements\nTPS7A4700 fits.\n");
+        assert_eq!(suggested.len(), 2);
+        assert_eq!(suggested[0].manufacturer_part_number, "TPS7A4700RGWR");
+        assert_eq!(
+            suggested[0].manufacturer.as_deref(),
+            Some("Texas Instruments")
+        );
+        assert_eq!(suggested[0].reason.as_deref(), Some("Same pinout"));
+        assert_eq!(suggested[1].manufacturer_part_number, "LT3045EDD");
+        assert!(suggested[1].manufacturer.is_none());
+    }
+
+    #[test]
+    fn answer_without_a_json_block_is_kept_whole() {
+        let answer = "## Replacements\nTPS7A4700 fits.";
+        let (analysis, suggested) = extract_suggested_replacements(answer);
+        assert_eq!(analysis, answer);
+        assert!(suggested.is_none());
+
+        let unterminated = "Parts:\n```json\n[{\"manufacturer_part_number\": \"LT3045EDD\"}]";
+        let (analysis, suggested) = extract_suggested_replacements(unterminated);
+        assert_eq!(analysis, unterminated);
+        assert!(suggested.is_none());
+    }
+
+    #[test]
+    fn malformed_json_block_is_kept_whole() {
+        let answer = "Parts:\n```json\n[{\"manufacturer\": \"Analog Devices\"}]\n```";
+        let (analysis, suggested) = extract_suggested_replacements(answer);
+        assert_eq!(analysis, answer);
+        assert!(suggested.is_none());
+
+        let answer = "Parts:\n```json\n[{\"manufacturer_part_number\": \"LT3045EDD\",]\n```";
+        let (analysis, suggested) = extract_suggested_replacements(answer);
+        assert_eq!(analysis, answer);
+        assert!(suggested.is_none());
+    }
+
+    #[test]
+    fn sections_about_dropped_parts_are_left_out() {
+        let analysis = "Two candidates.\n\n### 1. TPS7A4700RGWR (Texas Instruments)\n- Same pinout\n\n### 2. LT3045EDD (Analog Devices)\n- Lower noise\n\n## Summary\nBoth beat the original.";
+        let dropped = vec!["LT3045EDD".to_string()];
+
+        let text = without_dropped_parts(analysis, &dropped, &["TPS7A4700RGWR"]);
+
+        assert!(text.contains("### 1. TPS7A4700RGWR (Texas Instruments)\n- Same pinout"));
+        assert!(!text.contains("Lower noise"));
+        assert!(text.contains("## Summary\nBoth beat the original."));
+        assert!(text.ends_with("so left out: LT3045EDD"));
+    }
+
+    #[test]
+    fn sections_naming_a_kept_part_stay() {
+        let analysis =
+            "1. LT3045EDD or TPS7A4700RGWR, lt3045edd is quieter\n2. Prefer tps7a4700rgwr for cost";
+        let dropped = vec!["LT3045EDD".to_string()];
+
+        let text = without_dropped_parts(analysis, &dropped, &["TPS7A4700RGWR"]);
+
+        assert!(text.starts_with(analysis));
+        assert_eq!(without_dropped_parts(analysis, &[], &[]), analysis);
+    }
+}
@@ -1,16 +1,19 @@
 use axum::{
+    body::Bytes,
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::Json,
 };
 use serde::Deserialize;
-use std::collections::HashMap;
+use serde_json::json;
 use std::sync::Arc;
 use tracing::{error, info, warn};
 
-use crate::services::git;
+use crate::errors::AppError;
+use crate::services::jobs::{self, JobRequest};
+use crate::services::{audit, git, github, hook};
 use crate::types::{ApiError, HookUpdateResponse};
-use kicad_db::{retrieve_schematic, store_schematic, PgPool};
+use kicad_db::PgPool;
 
 pub type AppState = Arc<PgPool>;
 
@@ -36,6 +39,10 @@ pub struct GitHubCommit {
 
 /// GitHub webhook endpoint - receives push events from GitHub
 /// This forces a fresh clone to ensure we have the latest commits
+///
+/// Deliveries must come from the configured GitHub instance (github.com or the
+/// Enterprise Server at GITHUB_BASE_URL) and, when GITHUB_WEBHOOK_SECRET is set,
+/// carry a matching `X-Hub-Signature-256`.
 #[utoipa::path(
     post,
     path = "/api/hook/github/{repo}",
@@ -44,6 +51,8 @@ pub struct GitHubCommit {
     ),
     responses(
         (status = 200, description = "Webhook processed successfully", body = HookUpdateResponse),
+        (status = 400, description = "Payload isn't a GitHub push event", body = ApiError),
+        (status = 401, description = "Delivery from another GitHub instance, or with a bad signature", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "hook"
@@ -51,10 +60,30 @@ pub struct GitHubCommit {
 pub async fn github_webhook(
     State(state): State<AppState>,
     Path(repo): Path<String>,
-    Json(payload): Json<GitHubPushEvent>,
-) -> Result<Json<HookUpdateResponse>, (StatusCode, Json<ApiError>)> {
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<HookUpdateResponse>, AppError> {
     let repo = repo.trim_start_matches('/').to_string();
 
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Err(rejection) = github::verify_webhook(
+        header("x-github-enterprise-host"),
+        header("x-hub-signature-256"),
+        &body,
+    ) {
+        warn!("Refused GitHub webhook for {}: {}", repo, rejection);
+        return Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            ApiError::unauthorized(rejection.to_string()),
+        ));
+    }
+    let payload: GitHubPushEvent = serde_json::from_slice(&body).map_err(|e| {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!("Invalid push event: {}", e)),
+        )
+    })?;
+
     info!("Received GitHub webhook for repo: {}", repo);
     if let Some(commits) = &payload.commits {
         info!("Webhook contains {} commits", commits.len());
@@ -67,13 +96,8 @@ pub async fn github_webhook(
         }
     }
 
-    // Invalidate cache to force fresh clone
-    if let Err(e) = git::invalidate_cache(&repo).await {
-        warn!("Failed to invalidate cache for {}: {}", repo, e);
-    }
-
-    // Now process with fresh data
-    process_repo_internal(state, repo).await
+    // Force a fresh clone, then process with fresh data
+    process_repo(&state, repo, true, audit::ACTOR_WEBHOOK).await
 }
 
 /// Refresh a repository - forces a fresh clone and reprocesses
@@ -92,18 +116,13 @@ pub async fn github_webhook(
 pub async fn refresh_repo(
     State(state): State<AppState>,
     Path(repo): Path<String>,
-) -> Result<Json<HookUpdateResponse>, (StatusCode, Json<ApiError>)> {
+) -> Result<Json<HookUpdateResponse>, AppError> {
     let repo = repo.trim_start_matches('/').to_string();
 
     info!("Refresh requested for repo: {}", repo);
 
-    // Invalidate cache to force fresh clone
-    if let Err(e) = git::invalidate_cache(&repo).await {
-        warn!("Failed to invalidate cache for {}: {}", repo, e);
-    }
-
-    // Now process with fresh data
-    process_repo_internal(state, repo).await
+    // Force a fresh clone, then process with fresh data
+    process_repo(&state, repo, true, audit::ACTOR_API).await
 }
 
 /// Process a repository and generate overviews for commits missing them
@@ -123,180 +142,81 @@ pub async fn refresh_repo(
 pub async fn update_repo(
     State(state): State<AppState>,
     Path(repo): Path<String>,
-) -> Result<Json<HookUpdateResponse>, (StatusCode, Json<ApiError>)> {
+) -> Result<Json<HookUpdateResponse>, AppError> {
     let repo = repo.trim_start_matches('/').to_string();
     info!("Processing update hook for repo: {}", repo);
-    process_repo_internal(state, repo).await
+    process_repo(&state, repo, false, audit::ACTOR_API).await
 }
 
-/// Internal function to process a repository
-async fn process_repo_internal(
-    state: AppState,
+/// Process a repository inline, or hand it to a worker when the job queue is enabled.
+/// `refresh` drops the cached clone first so new commits are picked up.
+/// `actor` is recorded in the audit log against the invalidation and generated overviews.
+async fn process_repo(
+    state: &AppState,
     repo: String,
-) -> Result<Json<HookUpdateResponse>, (StatusCode, Json<ApiError>)> {
-    let repo_url = format!("https://github.com/{}.git", repo);
-
-    // Get all commits with schematic changes
-    let commits = git::get_schematic_commits(&repo).await.map_err(|e| {
-        error!("Failed to get commits for {}: {}", repo, e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to fetch commits: {}",
-                e
-            ))),
-        )
-    })?;
-
-    info!(
-        "Found {} commits with schematic changes for repo: {}",
-        commits.len(),
-        repo
-    );
-    for (idx, commit) in commits.iter().enumerate() {
-        info!(
-            "  Commit {}: {} - {:?}",
-            idx + 1,
-            &commit.commit_hash[..8.min(commit.commit_hash.len())],
-            commit.message
-        );
-    }
-
-    let mut processed = 0;
-    let mut errors = Vec::new();
-
-    for commit_info in commits {
-        // Check if we already have an overview for this commit
-        let existing = retrieve_schematic(&state, &repo_url, &commit_info.commit_hash)
-            .await
-            .ok()
-            .flatten();
-
-        let needs_processing = existing
-            .as_ref()
-            .map(|s| s.blurb.is_none() || s.description.is_none())
-            .unwrap_or(true);
-
-        info!(
-            "Commit {} needs_processing={}, existing={:?}",
-            &commit_info.commit_hash[..8.min(commit_info.commit_hash.len())],
-            needs_processing,
-            existing.as_ref().map(|s| format!(
-                "blurb={}, desc={}",
-                s.blurb.is_some(),
-                s.description.is_some()
-            ))
-        );
-
-        if needs_processing {
-            match generate_and_store_overview(
-                &state,
-                &repo,
-                &repo_url,
-                &commit_info.commit_hash,
-                commit_info.commit_date,
-                commit_info.message.as_deref(),
+    refresh: bool,
+    actor: &str,
+) -> Result<Json<HookUpdateResponse>, AppError> {
+    let repo_url = github::repo_url(&repo);
+
+    if jobs::queue_enabled() {
+        let request = JobRequest::ProcessRepo {
+            repo: repo.clone(),
+            refresh,
+        };
+        let job_id = jobs::enqueue(state, &request).await.map_err(|e| {
+            error!("Failed to enqueue processing for {}: {}", repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to enqueue job: {}", e)),
             )
-            .await
-            {
-                Ok(_) => {
-                    processed += 1;
-                    info!(
-                        "Generated overview for {}/{}",
-                        repo, commit_info.commit_hash
-                    );
-                }
-                Err(e) => {
-                    let err_msg = format!("Commit {}: {}", commit_info.commit_hash, e);
-                    // Check for rate limiting
-                    if e.to_string().contains("429")
-                        || e.to_string().to_lowercase().contains("rate")
-                    {
-                        error!(
-                            "RATE LIMITED while processing commit {}: {}",
-                            commit_info.commit_hash, e
-                        );
-                        warn!("XAI API rate limit hit! Stopping further processing.");
-                        errors.push(format!("RATE LIMITED: {}", err_msg));
-                        // Break out of the loop to avoid hitting more rate limits
-                        break;
-                    }
-                    error!("Failed to generate overview: {}", err_msg);
-                    errors.push(err_msg);
-                }
-            }
+        })?;
+        info!("Queued processing for {} as job {}", repo, job_id);
+        if refresh {
+            audit::record(
+                state,
+                audit::CACHE_INVALIDATED,
+                actor,
+                Some(&repo_url),
+                None,
+                json!({ "job_id": job_id }),
+            )
+            .await;
         }
+        return Ok(Json(HookUpdateResponse {
+            repo,
+            processed: 0,
+            errors: Vec::new(),
+            quarantined: Vec::new(),
+            job_id: Some(job_id),
+        }));
     }
 
-    info!(
-        "Hook processing complete for {}: processed={}, errors={}",
-        repo,
-        processed,
-        errors.len()
-    );
-    if !errors.is_empty() {
-        warn!("Errors during processing: {:?}", errors);
-    }
-
-    Ok(Json(HookUpdateResponse {
-        repo,
-        processed,
-        errors,
-    }))
-}
-
-/// Generate a placeholder overview and store it in the database
-async fn generate_and_store_overview(
-    pool: &PgPool,
-    repo_slug: &str,
-    repo_url: &str,
-    commit_hash: &str,
-    commit_date: Option<chrono::DateTime<chrono::Utc>>,
-    git_message: Option<&str>,
-) -> anyhow::Result<()> {
-    // Get changed files for context
-    let changed_files = git::get_changed_schematic_files(repo_slug, commit_hash).await?;
-
-    // Generate placeholder overview (TODO: integrate with Grok)
-    let num_files = changed_files.len();
-    let blurb = if num_files > 0 {
-        format!(
-            "Schematic changes in {} file(s): {}",
-            num_files,
-            git_message
-                .unwrap_or("Update")
-                .split_whitespace()
-                .take(5)
-                .collect::<Vec<_>>()
-                .join(" ")
-        )
-    } else {
-        "Initial schematic commit".to_string()
-    };
-
-    let mut description = format!(
-        "Commit message: {}\nChanged files:\n",
-        git_message.unwrap_or("(no message)")
-    );
-    for path in &changed_files {
-        description.push_str(&format!("  - {}\n", path));
+    if refresh {
+        match git::invalidate_cache(&repo).await {
+            Ok(()) => {
+                audit::record(
+                    state,
+                    audit::CACHE_INVALIDATED,
+                    actor,
+                    Some(&repo_url),
+                    None,
+                    json!({}),
+                )
+                .await
+            }
+            Err(e) => warn!("Failed to invalidate cache for {}: {}", repo, e),
+        }
     }
 
-    let empty_parts = HashMap::new();
-    store_schematic(
-        pool,
-        repo_url,
-        commit_hash,
-        commit_date,
-        git_message,
-        None, // image
-        None, // summary
-        None, // overview
-        Some(&blurb),
-        Some(&description),
-        empty_parts,
-    )
-    .await?;
-
-    Ok(())
+    hook::process_repo(state, &repo, actor)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to process {}: {}", repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to process repo: {}", e)),
+            )
+        })
 }
@@ -0,0 +1,80 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::errors::AppError;
+use crate::types::{ApiError, JobRunEntry, JobStatusResponse};
+use kicad_db::jobs::{self, JobRun};
+use kicad_db::PgPool;
+
+pub type AppState = Arc<PgPool>;
+
+pub fn job_run_entry(run: JobRun) -> JobRunEntry {
+    JobRunEntry {
+        attempt: run.attempt,
+        worker: run.worker,
+        status: run.status,
+        error: run.error,
+        artifacts: run.artifacts,
+        started_at: run.started_at,
+        finished_at: run.finished_at,
+        duration_ms: run.duration_ms,
+    }
+}
+
+/// Get the status of a background job, with the history of its attempts
+#[utoipa::path(
+    get,
+    path = "/api/jobs/{id}",
+    params(
+        ("id" = i64, Path, description = "Job id")
+    ),
+    responses(
+        (status = 200, description = "Job status", body = JobStatusResponse),
+        (status = 404, description = "Job not found", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "jobs"
+)]
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<JobStatusResponse>, AppError> {
+    let load_error = |e: sqlx::Error| {
+        error!("Failed to load job {}: {}", id, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to load job: {}", e)),
+        )
+    };
+    let job = jobs::get_job(&state, id)
+        .await
+        .map_err(load_error)?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!("Job {} not found", id)),
+            )
+        })?;
+    let runs = jobs::runs_for_jobs(&state, &[id])
+        .await
+        .map_err(load_error)?;
+
+    Ok(Json(JobStatusResponse {
+        id: job.id,
+        kind: job.kind,
+        status: job.status,
+        attempts: job.attempts,
+        result: job.result,
+        progress: job.progress,
+        last_error: job.last_error,
+        created_at: job.created_at,
+        started_at: job.started_at,
+        finished_at: job.finished_at,
+        runs: runs.into_iter().map(job_run_entry).collect(),
+    }))
+}
@@ -1,5 +1,22 @@
+pub mod admin;
+pub mod blobs;
+pub mod bom;
+pub mod byte_range;
+pub mod ci;
+pub mod design_rules;
+pub mod digests;
 pub mod digikey;
 pub mod distill;
+pub mod etag;
+pub mod export;
+pub mod feedback;
 pub mod grok;
 pub mod hook;
+pub mod jobs;
+pub mod presence;
+pub mod provenance;
 pub mod repo;
+pub mod report;
+pub mod sync;
+pub mod usage;
+pub mod views;
@@ -0,0 +1,198 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::{Json, Response},
+};
+use futures_util::{SinkExt, StreamExt};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+use crate::controllers::repo::store_comment;
+use crate::demo;
+use crate::errors::AppError;
+use crate::services::comments;
+use crate::services::presence::{self, RoomFull, RoomKey, Session};
+use crate::types::{ApiError, PresenceEvent, PresenceMessage, PresenceQuery, PresenceResponse};
+use kicad_db::PgPool;
+
+pub type AppState = Arc<PgPool>;
+
+/// Pings keep idle sockets open through proxies that close quiet connections
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+fn room_key(query: &PresenceQuery) -> Result<RoomKey, AppError> {
+    if query.repo.trim().is_empty() || query.commit.trim().is_empty() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("repo and commit are required"),
+        ));
+    }
+    Ok(RoomKey::new(&query.repo, &query.commit))
+}
+
+/// List who is viewing a commit
+///
+/// For clients that poll instead of holding a presence socket open.
+#[utoipa::path(
+    get,
+    path = "/api/presence",
+    params(PresenceQuery),
+    responses(
+        (status = 200, description = "Viewers of the commit", body = PresenceResponse),
+        (status = 400, description = "Missing repo or commit", body = ApiError)
+    ),
+    tag = "presence"
+)]
+pub async fn list_viewers(
+    Query(query): Query<PresenceQuery>,
+) -> Result<Json<PresenceResponse>, AppError> {
+    let key = room_key(&query)?;
+    Ok(Json(PresenceResponse {
+        viewers: presence::viewers(&key),
+        repo: query.repo,
+        commit: query.commit,
+    }))
+}
+
+/// Join a commit's presence channel
+///
+/// Upgrades to a WebSocket shared by everyone viewing the same repository and
+/// commit. The server sends `PresenceEvent`s: a `snapshot` of the viewers on
+/// connecting, then `join`, `leave`, `selection_changed` and `comment` as
+/// they happen. Clients send `PresenceMessage`s, e.g.
+/// `{"type": "selection", "selection": ["U1", "R3"]}`. Presence lives in the
+/// memory of the API instance the socket is on; only pinned comments are stored,
+/// and a demo instance refuses to pin them.
+#[utoipa::path(
+    get,
+    path = "/api/presence/ws",
+    params(PresenceQuery),
+    responses(
+        (status = 101, description = "Switched to the presence WebSocket"),
+        (status = 400, description = "Missing repo or commit, or not a WebSocket request", body = ApiError),
+        (status = 429, description = "The commit already has the most viewers allowed", body = ApiError)
+    ),
+    tag = "presence"
+)]
+pub async fn presence_socket(
+    State(state): State<AppState>,
+    ws: WebSocketUpgrade,
+    Query(query): Query<PresenceQuery>,
+) -> Result<Response, AppError> {
+    let key = room_key(&query)?;
+    // Joined before upgrading so a full room is refused with a status; if the
+    // upgrade fails the session is dropped, which leaves the room again
+    let session = presence::join(key, query.name.as_deref()).map_err(|RoomFull| {
+        AppError::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            ApiError::new(
+                "room_full",
+                format!(
+                    "{}@{} already has {} viewers",
+                    query.repo,
+                    query.commit,
+                    presence::MAX_VIEWERS_PER_ROOM
+                ),
+            ),
+        )
+    })?;
+    Ok(ws.on_upgrade(move |socket| run(socket, state, query, session)))
+}
+
+fn to_message(event: &PresenceEvent) -> Message {
+    Message::Text(serde_json::to_string(event).unwrap_or_default())
+}
+
+/// Relay or pin a viewer's comment. Returns the reason it was refused.
+/// Pinning stores the comment, so it gets the checks of
+/// `/api/repo/commit/comments`, which a demo instance doesn't serve.
+async fn comment(
+    pool: &PgPool,
+    query: &PresenceQuery,
+    session: &Session,
+    message: PresenceMessage,
+) -> Result<(), String> {
+    let PresenceMessage::Comment {
+        body,
+        anchor_kind,
+        anchor,
+        parent_id,
+        pin,
+    } = message
+    else {
+        return Ok(());
+    };
+    let author = session
+        .name
+        .as_deref()
+        .ok_or("Connect with a name to comment")?;
+    let valid = comments::validate(author, &body, anchor_kind.as_deref(), anchor.as_deref())?;
+    if !pin {
+        presence::relay_comment(session, valid, parent_id);
+        return Ok(());
+    }
+    if demo::config().is_some() {
+        return Err("Comments can't be pinned in the demo".to_string());
+    }
+    let (_, stored) = store_comment(pool, &query.repo, &query.commit, parent_id, valid)
+        .await
+        .map_err(|e| e.error.message)?;
+    presence::broadcast_comment(presence::room_of(session), Some(&session.id), &stored);
+    Ok(())
+}
+
+/// Relay room events to the viewer and its messages to the room until either
+/// side closes
+async fn run(socket: WebSocket, pool: AppState, query: PresenceQuery, mut session: Session) {
+    let (mut sender, mut receiver) = socket.split();
+    if sender
+        .send(to_message(&presence::snapshot(&session)))
+        .await
+        .is_err()
+    {
+        return;
+    }
+
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.tick().await;
+    loop {
+        let outgoing = tokio::select! {
+            event = session.events.recv() => match event {
+                Ok(event) if event.is_echo(&session.id) => continue,
+                Ok(event) => to_message(&event),
+                Err(RecvError::Lagged(_)) => to_message(&presence::snapshot(&session)),
+                Err(RecvError::Closed) => break,
+            },
+            message = receiver.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<PresenceMessage>(&text) {
+                        Ok(PresenceMessage::Selection { selection }) => {
+                            presence::select(&session, selection);
+                            continue;
+                        }
+                        Ok(message) => match comment(&pool, &query, &session, message).await {
+                            Ok(()) => continue,
+                            Err(message) => to_message(&PresenceEvent::Error { message }),
+                        },
+                        Err(e) => {
+                            debug!("Ignoring presence message {:?}: {}", text, e);
+                            continue;
+                        }
+                    }
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+            _ = ping.tick() => Message::Ping(Vec::new()),
+        };
+        if sender.send(outgoing).await.is_err() {
+            break;
+        }
+    }
+    // Dropping the session leaves the room
+}
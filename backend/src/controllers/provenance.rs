@@ -0,0 +1,94 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::errors::AppError;
+use crate::services::github;
+use crate::services::provenance::to_entry;
+use crate::types::{ApiError, ProvenanceEntry, ProvenanceListResponse, ProvenanceQuery};
+use kicad_db::provenance::{get_provenance, list_provenance, ProvenanceFilter};
+use kicad_db::{read_pool, PgPool};
+
+pub type AppState = Arc<PgPool>;
+
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+fn internal(e: sqlx::Error) -> AppError {
+    error!("Failed to read provenance records: {}", e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Failed to read provenance records: {}", e)),
+    )
+}
+
+/// How stored AI artifacts were produced
+///
+/// Every stored summary, set of release notes and approved alternate
+/// suggestion has a record of the model and prompt version, a hash of the
+/// prompt, the tools the model called and hashes of its inputs and outputs.
+/// Records are never changed; `verified` is false for one that no longer
+/// matches the digest taken when it was written.
+#[utoipa::path(
+    get,
+    path = "/api/provenance",
+    params(ProvenanceQuery),
+    responses(
+        (status = 200, description = "Matching records, newest first", body = ProvenanceListResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "provenance"
+)]
+pub async fn list_records(
+    State(state): State<AppState>,
+    Query(query): Query<ProvenanceQuery>,
+) -> Result<Json<ProvenanceListResponse>, AppError> {
+    let filter = ProvenanceFilter {
+        artifact: query.artifact,
+        artifact_ref: query.artifact_ref,
+        repo_url: query.repo.map(|repo| github::repo_url(repo.trim())),
+        commit_hash: query.commit,
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let records = list_provenance(read_pool(&state), &filter, limit)
+        .await
+        .map_err(internal)?;
+    Ok(Json(ProvenanceListResponse {
+        records: records.into_iter().map(to_entry).collect(),
+    }))
+}
+
+/// A provenance record by id
+#[utoipa::path(
+    get,
+    path = "/api/provenance/{id}",
+    params(
+        ("id" = i64, Path, description = "Provenance record id")
+    ),
+    responses(
+        (status = 200, description = "The record", body = ProvenanceEntry),
+        (status = 404, description = "No such record", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "provenance"
+)]
+pub async fn get_record(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<ProvenanceEntry>, AppError> {
+    let record = get_provenance(read_pool(&state), id)
+        .await
+        .map_err(internal)?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!("No provenance record {}", id)),
+            )
+        })?;
+    Ok(Json(to_entry(record)))
+}
@@ -1,46 +1,158 @@
-use axum::{extract::State, http::StatusCode, response::Json};
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::services::{distill, git};
+use crate::controllers::admin::require_admin;
+use crate::controllers::byte_range::{self, RangeRequest};
+use crate::controllers::distill::distillation_error;
+use crate::controllers::etag;
+use crate::errors::AppError;
+use crate::repo_policy::RepoRejected;
+use crate::services::disk::DiskFull;
+use crate::services::presence::{self, RoomKey};
+use crate::services::speech::{self, SummaryFormat};
+use crate::services::{
+    audit, blob_store, bom_diff, comments, component_notes, component_search, currency, decoupling,
+    distill, footprints, git, github, harness, impact, jobs, metrics, mirror, pinmap, prefetch,
+    repo_cards, retrieval, risk, suggestions, symbols, test_points, value_changes, xref,
+};
 use crate::types::{
-    ApiError, CommitFilesRequest, CommitFilesResponse, CommitInfoRequest, CommitInfoResponse,
-    RepoClearCacheRequest, RepoClearCacheResponse, RepoCommitsRequest, RepoCommitsResponse,
-    RepoInitRequest, RepoInitResponse,
+    ApiError, BomDiffRequest, BomDiffResponse, CommitCommentEntry, CommitCommentRequest,
+    CommitCommentsRequest, CommitCommentsResponse, CommitFilesRequest, CommitFilesResponse,
+    CommitInfoRequest, CommitInfoResponse, CommitOverviewEditRequest, CommitOverviewResponse,
+    ComponentNoteRequest, ComponentNoteResponse, ComponentNotesQuery, ComponentNotesResponse,
+    ComponentSearchRequest, ComponentSearchResponse, DecouplingCheckRequest,
+    DecouplingCheckResponse, FootprintCheckRequest, FootprintCheckResponse, GithubReleaseInfo,
+    HarnessRequest, HarnessResponse, ImpactRequest, ImpactResponse, MetricsHistoryRequest,
+    MetricsHistoryResponse, PinMapRequest, RepoClearCacheRequest, RepoClearCacheResponse,
+    RepoCommitsRequest, RepoCommitsResponse, RepoInitRequest, RepoInitResponse, RepoRelease,
+    RepoReleasesRequest, RepoReleasesResponse, RepoSuggestionsQuery, RepoSuggestionsResponse,
+    RepoSummaryCardQuery, RepoSummaryCardResponse, RepoUploadQuery, RepoUploadResponse,
+    SchematicImageQuery, SheetMetaEntry,
+    SheetMetaRequest, StoredCommit, StoredCommitsRequest, StoredCommitsResponse,
+    SummaryReviewRequest, SummaryReviewResponse, SymbolCheckRequest, SymbolCheckResponse,
+    TestPointCoverageRequest, TestPointCoverageResponse, ValueCompareRequest, ValueCompareResponse,
+    XrefRequest, XrefResponse,
 };
 use kicad_db::{
-    clear_distilled_json, retrieve_distilled_json, retrieve_schematic, store_distilled_json,
-    PgPool,
+    clear_distilled_json, comments as kdb_comments, component_notes as kdb_component_notes,
+    list_schematics, read_pool, repos, retrieve_distilled_json, retrieve_schematic_meta,
+    sheet_meta::{upsert_sheet_meta, SheetMetaUpdate, REVIEW_STATUSES},
+    summaries::{review_summary, set_override, REVIEW_APPROVED, REVIEW_DECISIONS},
+    Pagination, PgPool,
 };
 
 pub type AppState = Arc<PgPool>;
 
+const DEFAULT_STORED_LIMIT: i64 = 100;
+const MAX_STORED_LIMIT: i64 = 1000;
+
+/// Storage URL of a repository that was onboarded, answering 404 for any
+/// other, so writes can't create rows or clones for arbitrary slugs
+pub async fn onboarded_repo_url(state: &PgPool, repo: &str) -> Result<String, AppError> {
+    let repo_url = github::repo_url(repo);
+    let found = repos::find_repo(state, &repo_url).await.map_err(|e| {
+        error!("Failed to look up repository {}: {}", repo, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to look up repository: {}", e)),
+        )
+    })?;
+    if found.is_none() {
+        return Err(AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!("Repository {} hasn't been onboarded", repo)),
+        ));
+    }
+    Ok(repo_url)
+}
+
+/// Resolve a commit hash or tag name to a full commit hash, answering 400 for unknown revisions
+pub async fn resolve_revision(repo: &str, rev: &str) -> Result<String, AppError> {
+    git::resolve_commit(repo, rev).await.map_err(|e| {
+        if let Some(rejected) = e.downcast_ref::<RepoRejected>() {
+            rejected.into()
+        } else if let Some(full) = e.downcast_ref::<DiskFull>() {
+            full.into()
+        } else if let Some(missing) = e.downcast_ref::<mirror::NotUploaded>() {
+            missing.into()
+        } else if e.downcast_ref::<git::UnknownRevision>().is_some() {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(e.to_string()),
+            )
+        } else {
+            error!("Failed to resolve {} in {}: {}", rev, repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to resolve revision: {}", e)),
+            )
+        }
+    })
+}
+
 /// Get all commits (with flag indicating schematic changes)
 #[utoipa::path(
     post,
     path = "/api/repo/commits",
     request_body = RepoCommitsRequest,
     responses(
-        (status = 200, description = "List of all commits with schematic change flags", body = RepoCommitsResponse),
+        (status = 200, description = "List of all commits with schematic change flags and risk scores", body = RepoCommitsResponse),
+        (status = 400, description = "Unknown sort order", body = ApiError),
+        (status = 404, description = "Repository not uploaded to this mirror-mode instance", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "repo"
 )]
 pub async fn get_commits(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Json(req): Json<RepoCommitsRequest>,
-) -> Result<Json<RepoCommitsResponse>, (StatusCode, Json<ApiError>)> {
-    let commits = git::get_all_commits(&req.repo).await.map_err(|e| {
+) -> Result<Json<RepoCommitsResponse>, AppError> {
+    let by_risk = match req.sort.as_deref().unwrap_or("date") {
+        "date" => false,
+        "risk" => true,
+        other => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!(
+                    "Unknown sort order: {} (expected date or risk)",
+                    other
+                )),
+            ))
+        }
+    };
+
+    let mut commits = git::get_all_commits(&req.repo).await.map_err(|e| {
+        if let Some(missing) = e.downcast_ref::<mirror::NotUploaded>() {
+            return missing.into();
+        }
         error!("Failed to get commits for {}: {}", req.repo, e);
-        (
+        AppError::new(
             StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::internal(format!(
-                "Failed to fetch commits: {}",
-                e
-            ))),
+            ApiError::internal(format!("Failed to fetch commits: {}", e)),
         )
     })?;
 
+    // Scores are auxiliary; the list is still useful without them
+    let mut scores = risk::scores(read_pool(&state), &req.repo)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to load risk scores for {}: {}", req.repo, e);
+            Default::default()
+        });
+    for commit in &mut commits {
+        commit.risk = scores.remove(&commit.commit_hash);
+    }
+    if by_risk {
+        // Stable, so equal scores keep history order
+        commits.sort_by_key(|c| std::cmp::Reverse(c.risk.as_ref().map(|r| r.score)));
+    }
+
     Ok(Json(RepoCommitsResponse {
         repo: req.repo,
         commits,
@@ -54,46 +166,63 @@ pub async fn get_commits(
     request_body = CommitFilesRequest,
     responses(
         (status = 200, description = "List of schematic files at this commit", body = CommitFilesResponse),
+        (status = 304, description = "Files unchanged since the If-None-Match ETag"),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "repo"
 )]
 pub async fn get_commit_files(
     State(_state): State<AppState>,
-    Json(req): Json<CommitFilesRequest>,
-) -> Result<Json<CommitFilesResponse>, (StatusCode, Json<ApiError>)> {
+    headers: HeaderMap,
+    Json(mut req): Json<CommitFilesRequest>,
+) -> Result<Response, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
     let files = git::get_schematic_files(&req.repo, &req.commit)
         .await
         .map_err(|e| {
             error!("Failed to get files for {}/{}: {}", req.repo, req.commit, e);
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!("Failed to fetch files: {}", e))),
+                ApiError::internal(format!("Failed to fetch files: {}", e)),
             )
         })?;
 
-    Ok(Json(CommitFilesResponse {
-        repo: req.repo,
-        commit: req.commit,
-        files,
-    }))
+    Ok(etag::respond(
+        &headers,
+        CommitFilesResponse {
+            repo: req.repo,
+            commit: req.commit,
+            files,
+        },
+    ))
 }
 
 /// Get summary information about a specific commit
+///
+/// The stored blurb and description (a person's edit in place of generated
+/// text, when there is one) come with their review state; set
+/// `approved_only` to leave them out until a reviewer approved them. With
+/// `format=tts` the blurb is the read-aloud variant, made once per blurb and
+/// stored alongside it.
 #[utoipa::path(
     post,
     path = "/api/repo/commit/info",
     request_body = CommitInfoRequest,
     responses(
         (status = 200, description = "Commit information with AI-generated summary", body = CommitInfoResponse),
+        (status = 400, description = "Unknown summary format", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "repo"
 )]
 pub async fn get_commit_info(
     State(state): State<AppState>,
-    Json(req): Json<CommitInfoRequest>,
-) -> Result<Json<CommitInfoResponse>, (StatusCode, Json<ApiError>)> {
+    Json(mut req): Json<CommitInfoRequest>,
+) -> Result<Json<CommitInfoResponse>, AppError> {
+    let format = SummaryFormat::parse(req.format.as_deref())
+        .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(e)))?;
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
     // Get git commit info
     let commit_info = git::get_commit_info(&req.repo, &req.commit)
         .await
@@ -102,12 +231,9 @@ pub async fn get_commit_info(
                 "Failed to get commit info for {}/{}: {}",
                 req.repo, req.commit, e
             );
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!(
-                    "Failed to fetch commit info: {}",
-                    e
-                ))),
+                ApiError::internal(format!("Failed to fetch commit info: {}", e)),
             )
         })?;
 
@@ -119,26 +245,30 @@ pub async fn get_commit_info(
                 "Failed to get changed files for {}/{}: {}",
                 req.repo, req.commit, e
             );
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!(
-                    "Failed to fetch changed files: {}",
-                    e
-                ))),
+                ApiError::internal(format!("Failed to fetch changed files: {}", e)),
             )
         })?;
 
     // Try to get stored blurb/description from database
-    let repo_url = format!("https://github.com/{}.git", req.repo);
-    let stored = retrieve_schematic(&state, &repo_url, &req.commit)
+    let repo_url = github::repo_url(&req.repo);
+    let stored = retrieve_schematic_meta(&state, &repo_url, &req.commit)
         .await
         .ok()
         .flatten();
 
-    let (blurb, description) = match stored {
-        Some(s) => (s.blurb, s.description),
-        None => (None, None),
-    };
+    // With approved_only, text that wasn't approved is left out (its review state isn't)
+    let text = stored
+        .as_ref()
+        .filter(|s| !req.approved_only || s.summary_review_status == REVIEW_APPROVED);
+
+    let comments = comments::for_commit(&state, &req.repo, &req.commit).await;
+
+    let mut blurb = text.and_then(|s| s.blurb.clone());
+    if let (SummaryFormat::Tts, Some(text)) = (format, &blurb) {
+        blurb = Some(speech::blurb(&state, &repo_url, &req.commit, text).await);
+    }
 
     Ok(Json(CommitInfoResponse {
         repo: req.repo,
@@ -146,54 +276,638 @@ pub async fn get_commit_info(
         commit_date: commit_info.commit_date,
         message: commit_info.message,
         blurb,
-        description,
+        description: text.and_then(|s| s.description.clone()),
+        generated_blurb: text.and_then(|s| s.generated_blurb.clone()),
+        generated_description: text.and_then(|s| s.generated_description.clone()),
+        blurb_edited: text.is_some_and(|s| s.blurb_edited),
+        description_edited: text.is_some_and(|s| s.description_edited),
+        edited_by: stored.as_ref().and_then(|s| s.summary_edited_by.clone()),
+        edited_at: stored.as_ref().and_then(|s| s.summary_edited_at),
+        review_status: stored.as_ref().map(|s| s.summary_review_status.clone()),
+        reviewed_by: stored.as_ref().and_then(|s| s.summary_reviewed_by.clone()),
+        reviewed_at: stored.and_then(|s| s.summary_reviewed_at),
         changed_files,
+        comments,
+    }))
+}
+
+/// Edit a commit's blurb or description by hand
+///
+/// The generated text is kept next to the edit, and every read returns the
+/// edit in its place. Sending an empty string drops an edit. Edited text
+/// needs review again. Requires the admin token.
+#[utoipa::path(
+    patch,
+    path = "/api/repo/commit/overview",
+    request_body = CommitOverviewEditRequest,
+    responses(
+        (status = 200, description = "Edited and generated text of the commit summary", body = CommitOverviewResponse),
+        (status = 400, description = "Nothing to edit or text too long", body = ApiError),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 404, description = "Repository not onboarded, or no summary stored for the commit", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn edit_commit_overview(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut req): Json<CommitOverviewEditRequest>,
+) -> Result<Json<CommitOverviewResponse>, AppError> {
+    require_admin(&headers)?;
+    if req.blurb.is_none() && req.description.is_none() {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("Send a blurb or description to edit"),
+        ));
+    }
+    let too_long = |text: &Option<String>| {
+        text.as_ref()
+            .is_some_and(|t| t.chars().count() > MAX_COMMENT_BODY_CHARS)
+    };
+    if too_long(&req.blurb) || too_long(&req.description) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!(
+                "blurb and description are limited to {} characters",
+                MAX_COMMENT_BODY_CHARS
+            )),
+        ));
+    }
+    let repo_url = onboarded_repo_url(&state, &req.repo).await?;
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
+    let edited = set_override(
+        &state,
+        &repo_url,
+        &req.commit,
+        req.blurb.as_deref().map(str::trim),
+        req.description.as_deref().map(str::trim),
+        audit::ACTOR_ADMIN,
+    )
+    .await
+    .map_err(|e| {
+        error!(
+            "Failed to edit summary of {}/{}: {}",
+            req.repo, req.commit, e
+        );
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to store edit: {}", e)),
+        )
+    })?
+    .ok_or_else(|| {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!(
+                "No summary stored for {} at {}",
+                req.repo, req.commit
+            )),
+        )
+    })?;
+    info!("Summary of {}/{} edited", req.repo, req.commit);
+    retrieval::index_later(&state, &req.repo, &req.commit).await;
+    repo_cards::refresh_later(&state, &req.repo);
+
+    audit::record(
+        &state,
+        audit::SUMMARY_EDITED,
+        audit::ACTOR_ADMIN,
+        Some(&repo_url),
+        Some(&req.commit),
+        serde_json::json!({
+            "blurb": req.blurb.is_some(),
+            "description": req.description.is_some(),
+        }),
+    )
+    .await;
+
+    Ok(Json(CommitOverviewResponse {
+        repo: req.repo,
+        commit: req.commit,
+        blurb_edited: edited.blurb_override.is_some(),
+        description_edited: edited.description_override.is_some(),
+        blurb: edited
+            .blurb_override
+            .or_else(|| edited.generated_blurb.clone()),
+        description: edited
+            .description_override
+            .or_else(|| edited.generated_description.clone()),
+        generated_blurb: edited.generated_blurb,
+        generated_description: edited.generated_description,
+        edited_by: edited.edited_by,
+        edited_at: edited.edited_at,
+        review_status: edited.review_status,
+    }))
+}
+
+/// Approve or reject a commit's AI-generated blurb and description
+///
+/// Generated text starts as a draft. A reviewer can correct it while
+/// reviewing (stored as a human edit); the review is cleared when the summary
+/// is regenerated with different text or edited again. Requires the admin
+/// token; the review is recorded as the admin's.
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/overview/review",
+    request_body = SummaryReviewRequest,
+    responses(
+        (status = 200, description = "Review state of the commit summary", body = SummaryReviewResponse),
+        (status = 400, description = "Invalid status", body = ApiError),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 404, description = "No summary stored for the commit", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn review_commit_overview(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(mut req): Json<SummaryReviewRequest>,
+) -> Result<Json<SummaryReviewResponse>, AppError> {
+    require_admin(&headers)?;
+    if !REVIEW_DECISIONS.contains(&req.status.as_str()) {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(format!(
+                "status must be one of: {}",
+                REVIEW_DECISIONS.join(", ")
+            )),
+        ));
+    }
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
+    let repo_url = github::repo_url(&req.repo);
+    let review = review_summary(
+        &state,
+        &repo_url,
+        &req.commit,
+        &req.status,
+        audit::ACTOR_ADMIN,
+        req.blurb.as_deref(),
+        req.description.as_deref(),
+    )
+    .await
+    .map_err(|e| {
+        error!(
+            "Failed to review summary of {}/{}: {}",
+            req.repo, req.commit, e
+        );
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to store review: {}", e)),
+        )
+    })?
+    .ok_or_else(|| {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!(
+                "No summary stored for {} at {}",
+                req.repo, req.commit
+            )),
+        )
+    })?;
+    info!(
+        "Summary of {}/{} {}",
+        req.repo, req.commit, review.review_status
+    );
+    repo_cards::refresh_later(&state, &req.repo);
+
+    audit::record(
+        &state,
+        audit::SUMMARY_REVIEWED,
+        audit::ACTOR_ADMIN,
+        Some(&repo_url),
+        Some(&req.commit),
+        serde_json::json!({
+            "status": review.review_status,
+            "edited": req.blurb.is_some() || req.description.is_some(),
+        }),
+    )
+    .await;
+
+    Ok(Json(SummaryReviewResponse {
+        repo: req.repo,
+        commit: req.commit,
+        blurb: review.blurb,
+        description: review.description,
+        review_status: review.review_status,
+        reviewed_by: review.reviewed_by,
+        reviewed_at: review.reviewed_at,
+    }))
+}
+
+const MAX_COMMENT_BODY_CHARS: usize = comments::MAX_BODY_CHARS;
+
+/// Comment on a commit
+///
+/// Comments can reply to another comment on the same commit (`parent_id`) and
+/// can be anchored to a component or net. They are returned with the AI
+/// summaries by `/api/repo/commit/info` and `/api/grok/commit/summary`, and
+/// sent to everyone viewing the commit over the presence channel.
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/comments",
+    request_body = CommitCommentRequest,
+    responses(
+        (status = 200, description = "Stored comment", body = CommitCommentEntry),
+        (status = 400, description = "Invalid comment, anchor or parent", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn add_commit_comment(
+    State(state): State<AppState>,
+    Json(req): Json<CommitCommentRequest>,
+) -> Result<Json<CommitCommentEntry>, AppError> {
+    let comment = comments::validate(
+        &req.author,
+        &req.body,
+        req.anchor_kind.as_deref(),
+        req.anchor.as_deref(),
+    )
+    .map_err(|message| AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(message)))?;
+
+    let (commit, stored) =
+        store_comment(&state, &req.repo, &req.commit, req.parent_id, comment).await?;
+    presence::broadcast_comment(&RoomKey::new(&req.repo, &commit), None, &stored);
+    Ok(Json(stored))
+}
+
+/// Store a validated comment on a commit hash or tag, checking that a parent
+/// is on the same commit. Returns the resolved commit hash and the comment.
+pub async fn store_comment(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    parent_id: Option<i64>,
+    comment: comments::ValidComment<'_>,
+) -> Result<(String, CommitCommentEntry), AppError> {
+    let bad_request =
+        |message: String| AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(message));
+    let commit = resolve_revision(repo, commit).await?;
+    let repo_url = github::repo_url(repo);
+
+    let internal = |e: sqlx::Error| {
+        error!("Failed to store comment for {}@{}: {}", repo, commit, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to store comment: {}", e)),
+        )
+    };
+
+    if let Some(parent_id) = parent_id {
+        let parent = kdb_comments::get_comment(pool, parent_id)
+            .await
+            .map_err(internal)?;
+        let same_commit = parent.is_some_and(|p| {
+            p.commit_hash == commit && git::repo_slug(&p.repo_url) == Some(repo)
+        });
+        if !same_commit {
+            return Err(bad_request(format!(
+                "Comment {} is not on {}@{}",
+                parent_id, repo, commit
+            )));
+        }
+    }
+
+    let stored = kdb_comments::insert_comment(
+        pool,
+        &kdb_comments::NewComment {
+            repo_url: &repo_url,
+            commit_hash: &commit,
+            parent_id,
+            author: comment.author,
+            body: comment.body,
+            anchor_kind: comment.anchor_kind,
+            anchor: comment.anchor,
+        },
+    )
+    .await
+    .map_err(internal)?
+    .ok_or_else(|| bad_request(format!("Invalid repository: {}", repo)))?;
+
+    info!("Stored comment {} on {}@{}", stored.id, repo, commit);
+    retrieval::index_later(pool, repo, &commit).await;
+    repo_cards::refresh_later(pool, repo);
+    Ok((commit, comments::to_entry(stored)))
+}
+
+/// List the comments on a commit, oldest first
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/comments/list",
+    request_body = CommitCommentsRequest,
+    responses(
+        (status = 200, description = "Comments on the commit", body = CommitCommentsResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn list_commit_comments(
+    State(state): State<AppState>,
+    Json(mut req): Json<CommitCommentsRequest>,
+) -> Result<Json<CommitCommentsResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
+    let repo_url = github::repo_url(&req.repo);
+    let stored = kdb_comments::list_comments(read_pool(&state), &repo_url, &req.commit)
+        .await
+        .map_err(|e| {
+            error!(
+                "Failed to list comments for {}@{}: {}",
+                req.repo, req.commit, e
+            );
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to list comments: {}", e)),
+            )
+        })?;
+
+    Ok(Json(CommitCommentsResponse {
+        repo: req.repo,
+        commit: req.commit,
+        comments: stored.into_iter().map(comments::to_entry).collect(),
+    }))
+}
+
+/// Get the stored schematic image of a commit
+///
+/// Supports single `Range` requests, so large images can be fetched in parts
+/// or resumed. Commit metadata is served by `/api/repo/commit/info`, which
+/// never loads the image.
+#[utoipa::path(
+    get,
+    path = "/api/repo/commit/image",
+    params(SchematicImageQuery),
+    responses(
+        (status = 200, description = "Schematic image"),
+        (status = 206, description = "Requested byte range of the schematic image"),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 404, description = "No image stored for the commit", body = ApiError),
+        (status = 416, description = "Range outside the image"),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn get_schematic_image(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(mut query): Query<SchematicImageQuery>,
+) -> Result<Response, AppError> {
+    query.commit = resolve_revision(&query.repo, &query.commit).await?;
+    let repo_url = github::repo_url(&query.repo);
+
+    let not_found = || {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::not_found(format!(
+                "No schematic image stored for {}/{}",
+                query.repo, query.commit
+            )),
+        )
+    };
+    let internal = |e: anyhow::Error| {
+        error!(
+            "Failed to read schematic image for {}/{}: {:#}",
+            query.repo, query.commit, e
+        );
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to read schematic image: {}", e)),
+        )
+    };
+
+    let record = blob_store::schematic_image(read_pool(&state), &repo_url, &query.commit)
+        .await
+        .map_err(internal)?
+        .ok_or_else(not_found)?;
+    let data = blob_store::read(&record)
+        .await
+        .map_err(internal)?
+        .ok_or_else(not_found)?;
+    let total = data.len() as u64;
+
+    let range = match byte_range::parse(&headers, total) {
+        RangeRequest::Full => None,
+        RangeRequest::Partial(range) => Some(range),
+        RangeRequest::Unsatisfiable => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{}", total))],
+            )
+                .into_response());
+        }
+    };
+
+    let content_headers = [
+        (header::CONTENT_TYPE, record.content_type),
+        (header::ACCEPT_RANGES, "bytes".to_string()),
+        (header::CACHE_CONTROL, "private, max-age=300".to_string()),
+    ];
+    Ok(match range {
+        Some(range) => (
+            StatusCode::PARTIAL_CONTENT,
+            content_headers,
+            [(header::CONTENT_RANGE, range.content_range(total))],
+            data[range.start as usize..=range.end as usize].to_vec(),
+        )
+            .into_response(),
+        None => (content_headers, data).into_response(),
+    })
+}
+
+/// List the commits stored for a repository, with flags for which artifacts
+/// (blurb, description, distilled data, image) have been generated
+#[utoipa::path(
+    post,
+    path = "/api/repo/stored",
+    request_body = StoredCommitsRequest,
+    responses(
+        (status = 200, description = "Stored commits, newest first", body = StoredCommitsResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn list_stored(
+    State(state): State<AppState>,
+    Json(req): Json<StoredCommitsRequest>,
+) -> Result<Json<StoredCommitsResponse>, AppError> {
+    let pagination = Pagination {
+        limit: req
+            .limit
+            .unwrap_or(DEFAULT_STORED_LIMIT)
+            .clamp(1, MAX_STORED_LIMIT),
+        offset: req.offset.max(0),
+    };
+    let repo_url = github::repo_url(&req.repo);
+
+    let (stored, total) = list_schematics(read_pool(&state), &repo_url, pagination)
+        .await
+        .map_err(|e| {
+            error!("Failed to list stored commits for {}: {}", req.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to list stored commits: {}", e)),
+            )
+        })?;
+
+    let commits = stored
+        .into_iter()
+        .map(|s| StoredCommit {
+            commit: s.commit_hash,
+            commit_date: s.commit_date,
+            message: s.git_message,
+            has_blurb: s.has_blurb,
+            has_description: s.has_description,
+            review_status: s.summary_review_status,
+            has_distilled: s.has_distilled_json,
+            has_image: s.has_image,
+            part_count: s.part_count,
+            stored_at: s.created_at,
+        })
+        .collect();
+
+    Ok(Json(StoredCommitsResponse {
+        repo: req.repo,
+        total,
+        limit: pagination.limit,
+        offset: pagination.offset,
+        commits,
     }))
 }
 
+/// Summary card of a repository for the repo list
+///
+/// The newest stored schematic commit, counts, the latest blurb and a
+/// thumbnail link, from a cache refreshed whenever the repository's commits
+/// are stored, distilled, summarised or commented on. Cheap to call for every
+/// repository in a list.
+#[utoipa::path(
+    get,
+    path = "/api/repo/summary-card",
+    params(RepoSummaryCardQuery),
+    responses(
+        (status = 200, description = "Repository summary card", body = RepoSummaryCardResponse),
+        (status = 404, description = "Nothing stored for the repository", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn get_summary_card(
+    State(state): State<AppState>,
+    Query(query): Query<RepoSummaryCardQuery>,
+) -> Result<Json<RepoSummaryCardResponse>, AppError> {
+    let card = repo_cards::card(&state, &query.repo)
+        .await
+        .map_err(|e| {
+            error!("Failed to read the summary card of {}: {:#}", query.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to read the summary card: {:#}", e)),
+            )
+        })?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!("Nothing stored for {}", query.repo)),
+            )
+        })?;
+
+    Ok(Json(card))
+}
+
+/// Suggested questions about a repository
+///
+/// Questions worth asking about the design ("How is the USB-PD contract
+/// negotiated?"), for the chat to offer as prompts. Generated in the
+/// background from the distilled data when the repository is first
+/// initialized, by the model when available and from templates otherwise.
+#[utoipa::path(
+    get,
+    path = "/api/repo/suggestions",
+    params(RepoSuggestionsQuery),
+    responses(
+        (status = 200, description = "Suggested questions", body = RepoSuggestionsResponse),
+        (status = 404, description = "No suggestions generated for the repository", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn get_suggestions(
+    State(state): State<AppState>,
+    Query(query): Query<RepoSuggestionsQuery>,
+) -> Result<Json<RepoSuggestionsResponse>, AppError> {
+    let stored = suggestions::get(&state, &query.repo)
+        .await
+        .map_err(|e| {
+            error!("Failed to read the suggestions of {}: {:#}", query.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to read suggestions: {:#}", e)),
+            )
+        })?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::NOT_FOUND,
+                ApiError::not_found(format!("No suggestions generated for {}", query.repo)),
+            )
+        })?;
+
+    Ok(Json(stored))
+}
+
 /// Initialize a repository by distilling its schematic files
 ///
 /// This endpoint fetches the schematic files from the repository, runs the
 /// Python distillation script to extract semantic information, and caches
 /// the result in the database. Call this when a user first loads a repository.
+/// With `backfill`, earlier schematic commits are distilled and their parts
+/// indexed by a background job whose id is returned.
 #[utoipa::path(
     post,
     path = "/api/repo/init",
     request_body = RepoInitRequest,
     responses(
         (status = 200, description = "Repository initialized with distilled schematic data", body = RepoInitResponse),
+        (status = 304, description = "Distilled data unchanged since the If-None-Match ETag"),
+        (status = 404, description = "Repository not uploaded to this mirror-mode instance", body = ApiError),
+        (status = 422, description = "Schematic uses an unsupported KiCad file format, or distillation timed out", body = ApiError),
         (status = 500, description = "Internal server error", body = ApiError)
     ),
     tag = "repo"
 )]
 pub async fn init_repo(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(req): Json<RepoInitRequest>,
-) -> Result<Json<RepoInitResponse>, (StatusCode, Json<ApiError>)> {
+) -> Result<Response, AppError> {
     info!("Initializing repo: {}", req.repo);
 
     // Get the commit hash - use provided or fetch latest
     let commit = match req.commit {
-        Some(c) => c,
+        Some(c) => resolve_revision(&req.repo, &c).await?,
         None => git::get_latest_commit(&req.repo).await.map_err(|e| {
+            if let Some(missing) = e.downcast_ref::<mirror::NotUploaded>() {
+                return missing.into();
+            }
             error!("Failed to get latest commit for {}: {}", req.repo, e);
-            (
+            AppError::new(
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::internal(format!(
-                    "Failed to fetch latest commit: {}",
-                    e
-                ))),
+                ApiError::internal(format!("Failed to fetch latest commit: {}", e)),
             )
         })?,
     };
 
-    let repo_url = format!("https://github.com/{}.git", req.repo);
+    let repo_url = github::repo_url(&req.repo);
 
     // Check if we already have distilled data cached
     let cached_distilled = retrieve_distilled_json(&state, &repo_url, &commit)
         .await
         .ok()
         .flatten();
+    distill::record_commit_cache_lookup(cached_distilled.is_some());
 
     let (distilled, cached, schematic_files) = if let Some(cached_json) = cached_distilled {
         info!("Using cached distilled data for {}/{}", req.repo, commit);
@@ -203,12 +917,9 @@ pub async fn init_repo(
             .await
             .map_err(|e| {
                 error!("Failed to get schematic files: {}", e);
-                (
+                AppError::new(
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ApiError::internal(format!(
-                        "Failed to fetch schematic files: {}",
-                        e
-                    ))),
+                    ApiError::internal(format!("Failed to fetch schematic files: {}", e)),
                 )
             })?;
 
@@ -225,45 +936,38 @@ pub async fn init_repo(
             .await
             .map_err(|e| {
                 error!("Failed to get schematic files: {}", e);
-                (
+                AppError::new(
                     StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ApiError::internal(format!(
-                        "Failed to fetch schematic files: {}",
-                        e
-                    ))),
+                    ApiError::internal(format!("Failed to fetch schematic files: {}", e)),
                 )
             })?;
 
         let file_paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
 
         if files.is_empty() {
-            return Err((
+            return Err(AppError::new(
                 StatusCode::NOT_FOUND,
-                Json(ApiError::not_found(format!(
+                ApiError::not_found(format!(
                     "No .kicad_sch files found in {}/{}",
                     req.repo, commit
-                ))),
+                )),
             ));
         }
 
-        // Run distillation
-        let distilled_json = distill::distill_repo_schematics(&req.repo, &commit)
+        // Run distillation (on a worker when the job queue is enabled) and cache the result
+        let (distilled_json, _) = distill::distill_commit(&state, &req.repo, &commit)
             .await
-            .map_err(|e| {
-                error!("Distillation failed for {}/{}: {}", req.repo, commit, e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ApiError::internal(format!("Distillation failed: {}", e))),
-                )
-            })?;
-
-        // Cache the result
-        if let Err(e) = store_distilled_json(&state, &repo_url, &commit, &distilled_json).await {
-            error!("Failed to cache distilled result: {}", e);
-            // Continue anyway - we have the data
-        } else {
-            info!("Cached distilled result for {}/{}", req.repo, commit);
-        }
+            .map_err(|e| distillation_error(&req.repo, &commit, e))?;
+        audit::record(
+            &state,
+            audit::REPO_ONBOARDED,
+            audit::ACTOR_API,
+            Some(&repo_url),
+            Some(&commit),
+            serde_json::json!({ "schematic_files": file_paths.len() }),
+        )
+        .await;
+        suggestions::generate_later(&state, &req.repo, &commit).await;
 
         (distilled_json, false, file_paths)
     };
@@ -298,15 +1002,66 @@ pub async fn init_repo(
         schematic_files.len()
     );
 
-    Ok(Json(RepoInitResponse {
-        repo: req.repo,
-        commit,
-        cached,
-        component_count,
-        net_count,
-        schematic_files,
-        distilled,
-    }))
+    let sheet_meta = distill::sheet_meta(&state, &req.repo).await;
+
+    // Earlier commits are distilled in the background
+    let backfill_job_id = match req.backfill.filter(|n| *n > 0) {
+        Some(limit) => {
+            let request = jobs::JobRequest::Backfill {
+                repo: req.repo.clone(),
+                head: commit.clone(),
+                limit,
+            };
+            let id = jobs::start(&state, &request).await.map_err(|e| {
+                error!("Failed to start backfill for {}: {}", req.repo, e);
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiError::internal(format!("Failed to start backfill: {}", e)),
+                )
+            })?;
+            info!(
+                "Backfilling up to {} commit(s) of {} in job {}",
+                limit, req.repo, id
+            );
+            Some(id)
+        }
+        None => None,
+    };
+
+    if req.summary_only {
+        let sheets = distill::sheet_manifest(&distilled, &schematic_files);
+        return Ok(etag::respond(
+            &headers,
+            RepoInitResponse {
+                repo: req.repo,
+                commit,
+                cached,
+                component_count,
+                net_count,
+                schematic_files,
+                distilled: None,
+                sheets: Some(sheets),
+                sheet_meta,
+                backfill_job_id,
+            },
+        ));
+    }
+
+    Ok(etag::respond(
+        &headers,
+        RepoInitResponse {
+            repo: req.repo,
+            commit,
+            cached,
+            component_count,
+            net_count,
+            schematic_files,
+            distilled: Some(distilled),
+            sheets: None,
+            sheet_meta,
+            backfill_job_id,
+        },
+    ))
 }
 
 /// Clear cached distilled schematic data for a repository
@@ -325,25 +1080,38 @@ pub async fn init_repo(
 )]
 pub async fn clear_cache(
     State(state): State<AppState>,
-    Json(req): Json<RepoClearCacheRequest>,
-) -> Result<Json<RepoClearCacheResponse>, (StatusCode, Json<ApiError>)> {
+    Json(mut req): Json<RepoClearCacheRequest>,
+) -> Result<Json<RepoClearCacheResponse>, AppError> {
+    if let Some(commit) = req.commit.take() {
+        req.commit = Some(resolve_revision(&req.repo, &commit).await?);
+    }
     info!(
         "Clearing cache for repo: {}, commit: {:?}",
         req.repo, req.commit
     );
 
-    let repo_url = format!("https://github.com/{}.git", req.repo);
+    let repo_url = github::repo_url(&req.repo);
 
-    let rows_affected =
-        clear_distilled_json(&state, &repo_url, req.commit.as_deref())
-            .await
-            .map_err(|e| {
-                error!("Failed to clear cache: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(ApiError::internal(format!("Failed to clear cache: {}", e))),
-                )
-            })?;
+    let rows_affected = clear_distilled_json(&state, &repo_url, req.commit.as_deref())
+        .await
+        .map_err(|e| {
+            error!("Failed to clear cache: {}", e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to clear cache: {}", e)),
+            )
+        })?;
+    repo_cards::refresh_later(&state, &req.repo);
+
+    audit::record(
+        &state,
+        audit::CACHE_CLEARED,
+        audit::ACTOR_API,
+        Some(&repo_url),
+        req.commit.as_deref(),
+        serde_json::json!({ "records": rows_affected }),
+    )
+    .await;
 
     let message = if let Some(ref commit) = req.commit {
         format!(
@@ -365,3 +1133,873 @@ pub async fn clear_cache(
         message,
     }))
 }
+
+/// Upload a repository to an instance in mirror mode
+///
+/// Air-gapped installs (MIRROR_MODE=true) never clone; each repository is
+/// served from its last upload instead. The body is a git bundle
+/// (`git bundle create board.bundle --all`) or a tarball of a bare repository
+/// or of a working copy with its `.git` directory. It replaces any earlier
+/// upload, and overviews of its schematic commits are generated by a
+/// background job whose id is returned. Requires the admin token.
+#[utoipa::path(
+    post,
+    path = "/api/repo/upload",
+    params(RepoUploadQuery),
+    request_body(content = Vec<u8>, description = "Git bundle or repository tarball", content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Repository stored", body = RepoUploadResponse),
+        (status = 400, description = "Invalid repository name, or the body isn't a git bundle or repository tarball", body = ApiError),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 409, description = "Instance isn't in mirror mode", body = ApiError),
+        (status = 413, description = "Upload larger than GIT_MAX_CLONE_BYTES", body = ApiError),
+        (status = 507, description = "Not enough free disk space", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn upload_repo(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<RepoUploadQuery>,
+    body: Body,
+) -> Result<Json<RepoUploadResponse>, AppError> {
+    require_admin(&headers)?;
+    if !mirror::enabled() {
+        return Err(AppError::new(
+            StatusCode::CONFLICT,
+            ApiError::new(
+                "mirror_mode_off",
+                "Uploads are only accepted in mirror mode (MIRROR_MODE=true)",
+            ),
+        ));
+    }
+
+    let upload_error = |e: anyhow::Error| -> AppError {
+        if let Some(rejected) = e.downcast_ref::<RepoRejected>() {
+            rejected.into()
+        } else if let Some(full) = e.downcast_ref::<DiskFull>() {
+            full.into()
+        } else if let Some(invalid) = e.downcast_ref::<mirror::InvalidUpload>() {
+            invalid.into()
+        } else {
+            error!("Failed to store upload of {}: {:#}", query.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to store upload: {}", e)),
+            )
+        }
+    };
+    let archive = mirror::receive(body).await.map_err(upload_error)?;
+    let uploaded = mirror::ingest(&query.repo, archive.path())
+        .await
+        .map_err(upload_error)?;
+    drop(archive);
+
+    let repo_url = github::repo_url(&query.repo);
+    audit::record(
+        &state,
+        audit::REPO_UPLOADED,
+        audit::ACTOR_ADMIN,
+        Some(&repo_url),
+        Some(&uploaded.head),
+        serde_json::json!({ "format": uploaded.format, "branches": uploaded.branches }),
+    )
+    .await;
+
+    let request = jobs::JobRequest::ProcessRepo {
+        repo: query.repo.clone(),
+        refresh: false,
+    };
+    let job_id = match jobs::start(&state, &request).await {
+        Ok(id) => Some(id),
+        Err(e) => {
+            warn!("Failed to start processing of uploaded {}: {}", query.repo, e);
+            None
+        }
+    };
+    repo_cards::refresh_later(&state, &query.repo);
+
+    Ok(Json(RepoUploadResponse {
+        repo: query.repo,
+        format: uploaded.format.to_string(),
+        head_commit: uploaded.head,
+        branches: uploaded.branches,
+        job_id,
+    }))
+}
+
+/// List git tags and GitHub releases, flagging which ones change schematics
+#[utoipa::path(
+    post,
+    path = "/api/repo/releases",
+    request_body = RepoReleasesRequest,
+    responses(
+        (status = 200, description = "Tags and releases, newest first", body = RepoReleasesResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn list_releases(
+    State(_state): State<AppState>,
+    Json(req): Json<RepoReleasesRequest>,
+) -> Result<Json<RepoReleasesResponse>, AppError> {
+    let tags = git::get_tags(&req.repo).await.map_err(|e| {
+        error!("Failed to list tags for {}: {}", req.repo, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to list tags: {}", e)),
+        )
+    })?;
+
+    // Tags are the source of truth; GitHub releases only add titles and links
+    let (github_releases_available, mut github_releases) =
+        match github::list_releases(&req.repo).await {
+            Ok(releases) => (true, releases),
+            Err(e) => {
+                warn!("GitHub releases unavailable for {}: {:#}", req.repo, e);
+                (false, Vec::new())
+            }
+        };
+    let mut take_release = |tag: &str| {
+        github_releases
+            .iter()
+            .position(|r| r.tag_name == tag)
+            .map(|i| github_releases.remove(i))
+    };
+
+    let mut releases: Vec<RepoRelease> = tags
+        .into_iter()
+        .map(|tag| RepoRelease {
+            github_release: take_release(&tag.name).map(github_release_info),
+            tag: tag.name,
+            commit: Some(tag.commit_hash),
+            commit_date: tag.commit_date,
+            message: tag.message,
+            previous_tag: tag.previous_tag,
+            schematic_commits: tag.schematic_commits,
+            touches_schematics: tag.schematic_commits > 0,
+        })
+        .collect();
+
+    // Releases whose tag hasn't been pushed (or was deleted) can't be resolved to a commit
+    releases.extend(github_releases.into_iter().map(|release| RepoRelease {
+        tag: release.tag_name.clone(),
+        commit: None,
+        commit_date: None,
+        message: None,
+        previous_tag: None,
+        schematic_commits: 0,
+        touches_schematics: false,
+        github_release: Some(github_release_info(release)),
+    }));
+
+    info!(
+        "Listed {} releases for {} (GitHub releases available: {})",
+        releases.len(),
+        req.repo,
+        github_releases_available
+    );
+
+    Ok(Json(RepoReleasesResponse {
+        repo: req.repo,
+        github_releases_available,
+        releases,
+    }))
+}
+
+fn github_release_info(release: github::GithubRelease) -> GithubReleaseInfo {
+    GithubReleaseInfo {
+        name: release.name.filter(|n| !n.is_empty()),
+        url: release.html_url,
+        published_at: release.published_at,
+        draft: release.draft,
+        prerelease: release.prerelease,
+    }
+}
+
+/// Find components by value, MPN or library ID across all indexed commits
+#[utoipa::path(
+    post,
+    path = "/api/repo/search/components",
+    request_body = ComponentSearchRequest,
+    responses(
+        (status = 200, description = "Matching components grouped by commit, newest first", body = ComponentSearchResponse),
+        (status = 400, description = "No search pattern given", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn search_components(
+    State(state): State<AppState>,
+    Json(req): Json<ComponentSearchRequest>,
+) -> Result<Json<ComponentSearchResponse>, AppError> {
+    fn pattern(p: &Option<String>) -> Option<&str> {
+        p.as_deref().map(str::trim).filter(|p| !p.is_empty())
+    }
+    let patterns = component_search::SearchPatterns {
+        query: pattern(&req.query),
+        value: pattern(&req.value),
+        mpn: pattern(&req.mpn),
+        lib_id: pattern(&req.lib_id),
+    };
+    if patterns.query.is_none()
+        && patterns.value.is_none()
+        && patterns.mpn.is_none()
+        && patterns.lib_id.is_none()
+    {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request("Provide at least one of query, value, mpn or lib_id"),
+        ));
+    }
+    let limit = req
+        .limit
+        .unwrap_or(component_search::DEFAULT_LIMIT)
+        .clamp(1, component_search::MAX_LIMIT);
+
+    let results = component_search::search(read_pool(&state), &req.repo, &patterns, limit)
+        .await
+        .map_err(|e| {
+            error!("Component search failed for {}: {}", req.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Component search failed: {}", e)),
+            )
+        })?;
+
+    info!(
+        "Component search in {} found {} matches across {} commits",
+        req.repo,
+        results.total_matches,
+        results.commits.len()
+    );
+
+    Ok(Json(ComponentSearchResponse {
+        repo: req.repo,
+        total_matches: results.total_matches,
+        truncated: results.truncated,
+        commits: results.commits,
+    }))
+}
+
+/// Pre-layout footprint check: unassigned footprints, libraries missing from the
+/// repository and footprints that disagree with distributor package data
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/footprint-check",
+    request_body = FootprintCheckRequest,
+    responses(
+        (status = 200, description = "Footprint coverage report", body = FootprintCheckResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn check_footprints(
+    State(state): State<AppState>,
+    Json(mut req): Json<FootprintCheckRequest>,
+) -> Result<Json<FootprintCheckResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("Footprint check for {}/{}", req.repo, req.commit);
+
+    let report = footprints::check(&state, &req.repo, &req.commit, req.lookup_packages)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    Ok(Json(report))
+}
+
+/// Check symbol instances against the repository's symbol libraries, flagging
+/// instances whose pins no longer match the library definition
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/symbol-check",
+    request_body = SymbolCheckRequest,
+    responses(
+        (status = 200, description = "Symbol pin validation report", body = SymbolCheckResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn check_symbols(
+    State(state): State<AppState>,
+    Json(mut req): Json<SymbolCheckRequest>,
+) -> Result<Json<SymbolCheckResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("Symbol check for {}/{}", req.repo, req.commit);
+
+    let report = symbols::check(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    Ok(Json(report))
+}
+
+/// Check that every IC supply pin has a decoupling capacitor close by
+///
+/// For each IC and supply net, lists the capacitors between that net and ground
+/// within `radius_mm` on the same sheet, using the distiller's proximity data.
+/// Entries are "missing" when nothing decouples the net, "distant" when the
+/// capacitors are elsewhere, and "insufficient" when there are fewer capacitors
+/// than pins. Capacitors counted for several ICs are listed as shared.
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/decoupling-check",
+    request_body = DecouplingCheckRequest,
+    responses(
+        (status = 200, description = "Decoupling of each IC supply net", body = DecouplingCheckResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn check_decoupling(
+    State(state): State<AppState>,
+    Json(mut req): Json<DecouplingCheckRequest>,
+) -> Result<Json<DecouplingCheckResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("Decoupling check for {}/{}", req.repo, req.commit);
+
+    let report = decoupling::check(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    Ok(Json(report))
+}
+
+/// Report which nets have a test point, for design-for-test reviews
+///
+/// Coverage is given overall and per subsystem (from the sheet metadata), with
+/// the nets that lack a test point. Test points are recognised by the
+/// distiller's "test_point" category, TP references or test point symbols and
+/// footprints.
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/test-point-coverage",
+    request_body = TestPointCoverageRequest,
+    responses(
+        (status = 200, description = "Test point coverage", body = TestPointCoverageResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn test_point_coverage(
+    State(state): State<AppState>,
+    Json(mut req): Json<TestPointCoverageRequest>,
+) -> Result<Json<TestPointCoverageResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("Test point coverage for {}/{}", req.repo, req.commit);
+
+    let report = test_points::check(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    Ok(Json(report))
+}
+
+/// Cross-reference index of the labels and power symbols of a commit
+///
+/// Lists every global label, hierarchical label (with the sheet pins it
+/// connects to) and power symbol, and the sheets that use it. Likely mistakes
+/// are flagged: global labels used on only one sheet, hierarchical labels and
+/// sheet pins without a counterpart, and names differing only in case.
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/xref",
+    request_body = XrefRequest,
+    responses(
+        (status = 200, description = "Cross-reference index", body = XrefResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn get_xref(Json(mut req): Json<XrefRequest>) -> Result<Json<XrefResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("Cross-reference for {}/{}", req.repo, req.commit);
+
+    let xref = xref::build(&req.repo, &req.commit).await.map_err(|e| {
+        error!(
+            "Failed to build cross-reference for {}/{}: {}",
+            req.repo, req.commit, e
+        );
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to build cross-reference: {}", e)),
+        )
+    })?;
+
+    Ok(Json(xref))
+}
+
+/// Blast radius of a commit: components whose connectivity changed
+///
+/// Nets are matched across the commit by the pins they share, so renames and
+/// regenerated net names don't count as changes. Besides the components the
+/// commit added, removed, edited or (dis)connected directly, every component
+/// on nets that merged, split or swapped pins is listed, with the reasons.
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/impact",
+    request_body = ImpactRequest,
+    responses(
+        (status = 200, description = "Affected components and net changes", body = ImpactResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn commit_impact(
+    State(state): State<AppState>,
+    Json(mut req): Json<ImpactRequest>,
+) -> Result<Json<ImpactResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    let base = match &req.base {
+        Some(base) => Some(resolve_revision(&req.repo, base).await?),
+        None => None,
+    };
+    info!("Impact analysis for {}/{}", req.repo, req.commit);
+    prefetch::after_view(&state, &req.repo, &req.commit);
+
+    let impact = impact::check(&state, &req.repo, &req.commit, base)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    Ok(Json(impact))
+}
+
+/// Wiring table of a commit's connectors, documenting how boards connect
+///
+/// Lists every connector pin with its net. When the repository holds several
+/// KiCad projects, pins of connectors on other boards sharing the net are given
+/// as mates; otherwise the destination is guessed from the net name
+/// ("MAIN_TO_DISP_SDA", or a net prefixed with another board's name).
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/harness",
+    request_body = HarnessRequest,
+    responses(
+        (status = 200, description = "Connectors and their wiring", body = HarnessResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn get_harness(
+    State(state): State<AppState>,
+    Json(mut req): Json<HarnessRequest>,
+) -> Result<Json<HarnessResponse>, AppError> {
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("Harness table for {}/{}", req.repo, req.commit);
+
+    let harness = harness::build(&state, &req.repo, &req.commit)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    Ok(Json(harness))
+}
+
+/// Export the pin assignments of a commit's microcontrollers for firmware
+///
+/// Maps every pin of the detected MCUs (or of the requested components) to its
+/// net, with the pin's kind. `format` "c" returns a header with pad and pin
+/// name macros per labelled signal, "rust" a module with a `Pin` constant per
+/// signal; the default is the JSON response below.
+#[utoipa::path(
+    post,
+    path = "/api/repo/commit/pinmap",
+    request_body = PinMapRequest,
+    responses(
+        (status = 200, description = "Pin map (JSON, text/x-c or text/x-rust)", body = PinMapResponse),
+        (status = 400, description = "Unknown commit, tag, format or component", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn export_pinmap(
+    State(state): State<AppState>,
+    Json(mut req): Json<PinMapRequest>,
+) -> Result<Response, AppError> {
+    let format = req.format.as_deref().unwrap_or("json");
+    let extension = match format {
+        "json" => None,
+        "c" => Some("h"),
+        "rust" => Some("rs"),
+        other => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!(
+                    "Unknown pin map format: {} (expected json, c or rust)",
+                    other
+                )),
+            ))
+        }
+    };
+
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    info!("Pin map ({}) for {}/{}", format, req.repo, req.commit);
+
+    let map = pinmap::build(&state, &req.repo, &req.commit, req.components.as_deref())
+        .await
+        .map_err(|e| distillation_error(&req.repo, &req.commit, e))?;
+
+    if let Some(requested) = &req.components {
+        let missing: Vec<&str> = requested
+            .iter()
+            .filter(|r| !map.mcus.iter().any(|m| &m.reference == *r))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!("Unknown components: {}", missing.join(", "))),
+            ));
+        }
+    }
+
+    let Some(extension) = extension else {
+        return Ok(Json(map).into_response());
+    };
+    let (content_type, body) = if extension == "h" {
+        ("text/x-c; charset=utf-8", pinmap::to_c_header(&map))
+    } else {
+        ("text/x-rust; charset=utf-8", pinmap::to_rust_module(&map))
+    };
+    let file_name = format!(
+        "pinmap-{}-{}.{}",
+        req.repo.replace('/', "-"),
+        &req.commit[..8.min(req.commit.len())],
+        extension
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", file_name),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Audit component value and MPN changes between two revisions
+///
+/// Changes are grouped by old → new value (e.g. every 10k resistor that became
+/// 4.7k), so bulk library-wide swaps stand out and accidental global edits can
+/// be caught in review.
+#[utoipa::path(
+    post,
+    path = "/api/repo/compare/values",
+    request_body = ValueCompareRequest,
+    responses(
+        (status = 200, description = "Value and MPN changes grouped by old and new value", body = ValueCompareResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn compare_values(
+    State(state): State<AppState>,
+    Json(req): Json<ValueCompareRequest>,
+) -> Result<Json<ValueCompareResponse>, AppError> {
+    let from = resolve_revision(&req.repo, &req.from).await?;
+    let to = resolve_revision(&req.repo, &req.to).await?;
+    info!("Value compare for {} {}..{}", req.repo, from, to);
+
+    let min_count = req
+        .min_count
+        .unwrap_or(value_changes::DEFAULT_MIN_COUNT)
+        .max(1);
+    let report = value_changes::compare(&state, &req.repo, &from, &to, min_count)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &to, e))?;
+
+    Ok(Json(report))
+}
+
+/// Compare the bill of materials between two revisions
+///
+/// Lines (grouped by value, footprint and MPN) that were added, removed or
+/// changed in quantity, each priced from cached distributor enrichment, with
+/// the net cost change per board. Prices keep the distributor's currency and
+/// are also converted to the display currency at the day's exchange rates.
+#[utoipa::path(
+    post,
+    path = "/api/repo/bom/diff",
+    request_body = BomDiffRequest,
+    responses(
+        (status = 200, description = "Changed BOM lines and cost delta", body = BomDiffResponse),
+        (status = 400, description = "Unknown commit or tag, or invalid currency", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn diff_bom(
+    State(state): State<AppState>,
+    Json(req): Json<BomDiffRequest>,
+) -> Result<Json<BomDiffResponse>, AppError> {
+    let display_currency = match req.currency.as_deref() {
+        Some(code) => currency::parse_code(code).ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request("currency must be a three-letter ISO 4217 code"),
+            )
+        })?,
+        None => currency::display_currency(),
+    };
+    let from = resolve_revision(&req.repo, &req.from).await?;
+    let to = resolve_revision(&req.repo, &req.to).await?;
+    info!("BOM diff for {} {}..{}", req.repo, from, to);
+
+    let diff = bom_diff::diff(&state, &req.repo, &from, &to, &display_currency)
+        .await
+        .map_err(|e| distillation_error(&req.repo, &to, e))?;
+
+    Ok(Json(diff))
+}
+
+/// Attach an owner, subsystem and review status to a schematic sheet
+///
+/// Metadata is stored per repository and sheet path, replacing what was there
+/// (omitted fields are cleared). It is returned with distilled data and given
+/// to Grok, so reviews can name who owns an affected block. Requires the admin
+/// token.
+#[utoipa::path(
+    post,
+    path = "/api/repo/sheet/meta",
+    request_body = SheetMetaRequest,
+    responses(
+        (status = 200, description = "Stored sheet metadata", body = SheetMetaEntry),
+        (status = 400, description = "Invalid sheet path or review status", body = ApiError),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 404, description = "Repository not onboarded", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn set_sheet_meta(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<SheetMetaRequest>,
+) -> Result<Json<SheetMetaEntry>, AppError> {
+    require_admin(&headers)?;
+    let sheet = req.sheet.trim().trim_start_matches('/');
+    if !sheet.ends_with(".kicad_sch") {
+        return Err(AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::bad_request(
+                "sheet must be a .kicad_sch path relative to the repository root",
+            ),
+        ));
+    }
+    let field = |value: &Option<String>| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(ToString::to_string)
+    };
+    let review_status = field(&req.review_status);
+    if let Some(status) = &review_status {
+        if !REVIEW_STATUSES.contains(&status.as_str()) {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!(
+                    "review_status must be one of: {}",
+                    REVIEW_STATUSES.join(", ")
+                )),
+            ));
+        }
+    }
+    let (owner, subsystem) = (field(&req.owner), field(&req.subsystem));
+
+    let repo_url = onboarded_repo_url(&state, &req.repo).await?;
+    let update = SheetMetaUpdate {
+        owner: owner.as_deref(),
+        subsystem: subsystem.as_deref(),
+        review_status: review_status.as_deref(),
+    };
+    let stored = upsert_sheet_meta(&state, &repo_url, sheet, &update)
+        .await
+        .map_err(|e| {
+            error!("Failed to store sheet metadata for {}: {}", req.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to store sheet metadata: {}", e)),
+            )
+        })?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!("Invalid repository: {}", req.repo)),
+            )
+        })?;
+    info!("Stored metadata for {} in {}", stored.sheet_path, req.repo);
+
+    Ok(Json(SheetMetaEntry {
+        sheet: stored.sheet_path,
+        owner: stored.owner,
+        subsystem: stored.subsystem,
+        review_status: stored.review_status,
+        updated_at: stored.updated_at,
+    }))
+}
+
+/// Attach a note to a component, or remove it
+///
+/// Notes are stored per repository and reference designator, so they follow
+/// the component across commits; omitting the note or sending "" removes it.
+/// They are given to Grok with any prompt that includes the component
+/// (selection analyses, design questions, net explanations), so what the team
+/// knows about a part accumulates. Since notes end up in prompts, changing
+/// them requires the admin token.
+#[utoipa::path(
+    patch,
+    path = "/api/repo/component/note",
+    request_body = ComponentNoteRequest,
+    responses(
+        (status = 200, description = "Stored note, or null once removed", body = ComponentNoteResponse),
+        (status = 400, description = "Invalid reference, note or author", body = ApiError),
+        (status = 401, description = "Missing or invalid admin token", body = ApiError),
+        (status = 404, description = "Repository not onboarded", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn set_component_note(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ComponentNoteRequest>,
+) -> Result<Json<ComponentNoteResponse>, AppError> {
+    require_admin(&headers)?;
+    let bad_request =
+        |message: String| AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(message));
+    let valid =
+        component_notes::validate(&req.reference, req.note.as_deref(), req.author.as_deref())
+            .map_err(bad_request)?;
+    let internal = |e: sqlx::Error| {
+        error!("Failed to store component note for {}: {}", req.repo, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to store component note: {}", e)),
+        )
+    };
+
+    let repo_url = onboarded_repo_url(&state, &req.repo).await?;
+    let note = match valid.note {
+        Some(note) => {
+            let stored = kdb_component_notes::upsert_note(
+                &state,
+                &repo_url,
+                valid.reference,
+                note,
+                valid.author,
+            )
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| bad_request(format!("Invalid repository: {}", req.repo)))?;
+            info!("Stored note on {} in {}", stored.reference, req.repo);
+            Some(component_notes::to_entry(stored))
+        }
+        None => {
+            if kdb_component_notes::delete_note(&state, &repo_url, valid.reference)
+                .await
+                .map_err(internal)?
+            {
+                info!("Removed note on {} in {}", valid.reference, req.repo);
+            }
+            None
+        }
+    };
+
+    Ok(Json(ComponentNoteResponse {
+        repo: req.repo,
+        reference: valid.reference.to_string(),
+        note,
+    }))
+}
+
+/// Notes attached to a repository's components
+#[utoipa::path(
+    get,
+    path = "/api/repo/component/notes",
+    params(ComponentNotesQuery),
+    responses(
+        (status = 200, description = "Component notes by reference", body = ComponentNotesResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn list_component_notes(
+    State(state): State<AppState>,
+    Query(query): Query<ComponentNotesQuery>,
+) -> Result<Json<ComponentNotesResponse>, AppError> {
+    let repo_url = github::repo_url(&query.repo);
+    let notes = kdb_component_notes::list_notes(read_pool(&state), &repo_url)
+        .await
+        .map_err(|e| {
+            error!("Failed to list component notes for {}: {}", query.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to list component notes: {}", e)),
+            )
+        })?;
+
+    Ok(Json(ComponentNotesResponse {
+        repo: query.repo,
+        notes: notes.into_iter().map(component_notes::to_entry).collect(),
+    }))
+}
+
+/// Per-commit design metrics (component, net and sheet counts, unique MPNs,
+/// estimated BOM cost) for charting how a design grows over time
+#[utoipa::path(
+    post,
+    path = "/api/repo/metrics/history",
+    request_body = MetricsHistoryRequest,
+    responses(
+        (status = 200, description = "Design metrics per commit, oldest first", body = MetricsHistoryResponse),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "repo"
+)]
+pub async fn metrics_history(
+    State(state): State<AppState>,
+    Json(req): Json<MetricsHistoryRequest>,
+) -> Result<Json<MetricsHistoryResponse>, AppError> {
+    let limit = req
+        .limit
+        .unwrap_or(metrics::DEFAULT_LIMIT)
+        .clamp(1, metrics::MAX_LIMIT);
+
+    let display_currency = currency::display_currency();
+    let points = metrics::history(&state, &req.repo, limit, &display_currency)
+        .await
+        .map_err(|e| {
+            error!("Failed to load metrics history for {}: {}", req.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to load metrics history: {}", e)),
+            )
+        })?;
+
+    Ok(Json(MetricsHistoryResponse {
+        repo: req.repo,
+        display_currency,
+        points,
+    }))
+}
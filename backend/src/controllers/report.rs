@@ -0,0 +1,196 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+use crate::controllers::distill::distillation_error;
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::blob_store::{self, BlobKind};
+use crate::services::report;
+use crate::types::{ApiError, CommitReportQuery, CommitReportRequest};
+use kicad_db::PgPool;
+
+pub type AppState = Arc<PgPool>;
+
+/// Build and render the HTML report of a resolved commit
+async fn report_html(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    base: Option<&str>,
+    approved_only: bool,
+) -> Result<String, AppError> {
+    let base = match base {
+        Some(base) => Some(resolve_revision(repo, base).await?),
+        None => None,
+    };
+    info!("Commit report request for {}/{}", repo, commit);
+
+    let commit_report = report::build(pool, repo, commit, base, approved_only)
+        .await
+        .map_err(|e| distillation_error(repo, commit, e))?;
+    Ok(report::render_html(&commit_report))
+}
+
+/// Export a standalone commit report for archiving or emailing
+///
+/// The report combines the stored AI summary, changed sheets, component and
+/// BOM changes against the base (default: the first parent), ERC findings and
+/// the stored schematic image. HTML reports inline their styles and image;
+/// PDF reports are converted from the HTML by REPORT_PDF_COMMAND. Each report
+/// is also kept in the blob store as a `render` file of the commit.
+#[utoipa::path(
+    post,
+    path = "/api/report/commit",
+    request_body = CommitReportRequest,
+    responses(
+        (status = 200, description = "Report document (text/html or application/pdf)"),
+        (status = 400, description = "Unknown commit or tag, or unknown format", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError),
+        (status = 503, description = "PDF requested but no converter is configured", body = ApiError)
+    ),
+    tag = "report"
+)]
+pub async fn commit_report(
+    State(state): State<AppState>,
+    Json(mut req): Json<CommitReportRequest>,
+) -> Result<Response, AppError> {
+    let pdf = match req.format.as_deref().unwrap_or("html") {
+        "html" => None,
+        "pdf" => match report::pdf_command() {
+            Some(command) => Some(command),
+            None => {
+                return Err(AppError::new(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    ApiError::new(
+                        "not_configured",
+                        "PDF reports are not configured. Set REPORT_PDF_COMMAND to an HTML to PDF converter.",
+                    ),
+                ))
+            }
+        },
+        other => {
+            return Err(AppError::new(
+                StatusCode::BAD_REQUEST,
+                ApiError::bad_request(format!(
+                    "Unknown report format: {} (expected html or pdf)",
+                    other
+                )),
+            ))
+        }
+    };
+
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+    let html = report_html(
+        &state,
+        &req.repo,
+        &req.commit,
+        req.base.as_deref(),
+        req.approved_only,
+    )
+    .await?;
+
+    let file_name = format!(
+        "{}-{}",
+        req.repo.replace('/', "-"),
+        &req.commit[..8.min(req.commit.len())]
+    );
+    let (content_type, extension, body) = match pdf {
+        Some(command) => {
+            let pdf = report::render_pdf(&command, &html).await.map_err(|e| {
+                error!(
+                    "PDF conversion failed for {}/{}: {}",
+                    req.repo, req.commit, e
+                );
+                AppError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ApiError::internal(format!("PDF conversion failed: {}", e)),
+                )
+            })?;
+            ("application/pdf", "pdf", pdf)
+        }
+        None => ("text/html; charset=utf-8", "html", html.into_bytes()),
+    };
+
+    // Keep the rendered report with the commit's other files (see /api/blobs/list)
+    let mut name = String::from("report");
+    if let Some(base) = req.base.as_deref() {
+        name.push('-');
+        name.push_str(&base.replace('/', "-"));
+    }
+    if req.approved_only {
+        name.push_str("-approved");
+    }
+    name.push('.');
+    name.push_str(extension);
+    if let Err(e) = blob_store::store_commit_blob(
+        &state,
+        BlobKind::Render,
+        &req.repo,
+        &req.commit,
+        &name,
+        &body,
+        content_type,
+    )
+    .await
+    {
+        warn!(
+            "Failed to store the report of {}/{}: {:#}",
+            req.repo, req.commit, e
+        );
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.{}\"", file_name, extension),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// View a commit report in the browser
+///
+/// The HTML report of `commit_report` served inline, so it can be linked to,
+/// e.g. from the GitHub commit statuses posted for processed commits.
+#[utoipa::path(
+    get,
+    path = "/api/report/commit",
+    params(CommitReportQuery),
+    responses(
+        (status = 200, description = "Report page (text/html)"),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 422, description = "Schematic format not supported", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "report"
+)]
+pub async fn commit_report_page(
+    State(state): State<AppState>,
+    Query(query): Query<CommitReportQuery>,
+) -> Result<Response, AppError> {
+    let commit = resolve_revision(&query.repo, &query.commit).await?;
+    let html = report_html(
+        &state,
+        &query.repo,
+        &commit,
+        query.base.as_deref(),
+        query.approved_only,
+    )
+    .await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/html; charset=utf-8".to_string())],
+        html,
+    )
+        .into_response())
+}
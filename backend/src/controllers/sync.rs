@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::errors::AppError;
+use crate::services::github;
+use crate::types::{ApiError, SyncChange, SyncChangesQuery, SyncChangesResponse};
+use kicad_db::sync::{self, Change, SyncCursor};
+use kicad_db::PgPool;
+
+pub type AppState = Arc<PgPool>;
+
+const DEFAULT_LIMIT: i64 = 500;
+const MAX_LIMIT: i64 = 5000;
+
+fn to_change(change: Change) -> SyncChange {
+    match change {
+        Change::Commit(c) => SyncChange::Commit {
+            commit: c.commit_hash,
+            commit_date: c.commit_date,
+            message: c.git_message,
+            blurb: c.blurb,
+            description: c.description,
+            review_status: c.summary_review_status,
+            has_distilled: c.has_distilled_json,
+            distilled_version: c.distilled_version,
+            has_image: c.has_image,
+            stored_at: c.created_at,
+        },
+        Change::Part(p) => SyncChange::Part {
+            commit: p.commit_hash,
+            part_uuid: p.part_uuid,
+            blurb: p.blurb,
+            properties: p.properties,
+        },
+    }
+}
+
+/// Changes to a repository's commits, summaries and parts since a cursor
+///
+/// For clients keeping a local mirror: start without a cursor to get
+/// everything, then send each page's `next_cursor` until `has_more` is false,
+/// and later again to pick up what changed meanwhile. Each entity is returned
+/// whole, as it is now. Distilled data and images aren't included; fetch them
+/// when `distilled_version` or `has_image` changes.
+#[utoipa::path(
+    get,
+    path = "/api/sync/changes",
+    params(SyncChangesQuery),
+    responses(
+        (status = 200, description = "Page of changes, oldest first", body = SyncChangesResponse),
+        (status = 400, description = "Invalid cursor", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "sync"
+)]
+pub async fn list_changes(
+    State(state): State<AppState>,
+    Query(query): Query<SyncChangesQuery>,
+) -> Result<Json<SyncChangesResponse>, AppError> {
+    let cursor = match query.cursor.as_deref() {
+        Some(cursor) => cursor
+            .parse::<SyncCursor>()
+            .map_err(|e| AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(e)))?,
+        None => SyncCursor::default(),
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let repo_url = github::repo_url(&query.repo);
+
+    // The primary, not the replica: cursors are positions on the primary
+    let page = sync::changes_since(&state, &repo_url, cursor, limit)
+        .await
+        .map_err(|e| {
+            error!("Failed to list changes of {}: {}", query.repo, e);
+            AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal(format!("Failed to list changes: {}", e)),
+            )
+        })?;
+
+    Ok(Json(SyncChangesResponse {
+        repo: query.repo,
+        changes: page.changes.into_iter().map(to_change).collect(),
+        next_cursor: page.next.to_string(),
+        has_more: page.has_more,
+    }))
+}
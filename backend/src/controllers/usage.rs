@@ -0,0 +1,79 @@
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use chrono::{TimeZone, Utc};
+use std::sync::Arc;
+use tracing::error;
+
+use crate::errors::AppError;
+use crate::quota;
+use crate::types::{ApiError, UsageCount, UsageResponse, UsageSeconds};
+use kicad_db::{api_keys, PgPool};
+
+pub type AppState = Arc<PgPool>;
+
+fn count(used: i64, limit: Option<i64>) -> UsageCount {
+    UsageCount {
+        used,
+        limit,
+        remaining: limit.map(|l| (l - used).max(0)),
+    }
+}
+
+/// Usage and remaining quota of the calling API key for the current month
+///
+/// Calls to this endpoint are not counted.
+#[utoipa::path(
+    get,
+    path = "/api/usage",
+    params(
+        ("x-api-key" = String, Header, description = "API key")
+    ),
+    responses(
+        (status = 200, description = "Usage of the key this month", body = UsageResponse),
+        (status = 401, description = "Missing, unknown or revoked API key", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "usage"
+)]
+pub async fn get_usage(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<UsageResponse>, AppError> {
+    let key = quota::authenticate(&state, &headers)
+        .await?
+        .ok_or_else(|| {
+            AppError::new(
+                StatusCode::UNAUTHORIZED,
+                ApiError::unauthorized(format!("Send an API key in the {} header", quota::HEADER)),
+            )
+        })?;
+
+    let now = Utc::now();
+    let period = quota::period_start(now);
+    let usage = api_keys::usage(&state, key.id, period).await.map_err(|e| {
+        error!("Failed to read usage of API key {}: {}", key.id, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal(format!("Failed to read usage: {}", e)),
+        )
+    })?;
+
+    Ok(Json(UsageResponse {
+        key_name: key.name,
+        key_prefix: key.key_prefix,
+        period_start: Utc.from_utc_datetime(&period.and_hms_opt(0, 0, 0).unwrap_or_default()),
+        resets_at: quota::period_end(now),
+        requests: count(usage.requests, key.request_quota),
+        llm_tokens: count(usage.llm_tokens, key.llm_token_quota),
+        cpu_seconds: UsageSeconds {
+            used: usage.cpu_seconds,
+            limit: key.cpu_seconds_quota,
+            remaining: key
+                .cpu_seconds_quota
+                .map(|l| (l - usage.cpu_seconds).max(0.0)),
+        },
+    }))
+}
@@ -0,0 +1,253 @@
+use axum::{
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::Json,
+};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::controllers::repo::resolve_revision;
+use crate::errors::AppError;
+use crate::services::git;
+use crate::services::github;
+use crate::types::{
+    ApiError, SavedViewCreateResponse, SavedViewEntry, SavedViewListRequest, SavedViewListResponse,
+    SavedViewRequest, SavedViewZoom,
+};
+use kicad_db::{read_pool, views, PgPool};
+
+pub type AppState = Arc<PgPool>;
+
+/// Header carrying the edit token when deleting a view
+pub const EDIT_TOKEN_HEADER: &str = "x-view-token";
+
+const MAX_NAME_CHARS: usize = 200;
+const MAX_NOTES_CHARS: usize = 10_000;
+const MAX_SELECTED_COMPONENTS: usize = 5000;
+const DEFAULT_LIST_LIMIT: i64 = 100;
+const MAX_LIST_LIMIT: i64 = 1000;
+
+fn bad_request(message: impl Into<String>) -> AppError {
+    AppError::new(StatusCode::BAD_REQUEST, ApiError::bad_request(message))
+}
+
+fn not_found(id: &str) -> AppError {
+    AppError::new(
+        StatusCode::NOT_FOUND,
+        ApiError::not_found(format!("No saved view {}", id)),
+    )
+}
+
+fn internal(what: &str, e: sqlx::Error) -> AppError {
+    error!("Failed to {}: {}", what, e);
+    AppError::new(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        ApiError::internal(format!("Failed to {}: {}", what, e)),
+    )
+}
+
+fn token_hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+fn to_entry(view: views::SavedView) -> SavedViewEntry {
+    SavedViewEntry {
+        id: view.id.to_string(),
+        repo: git::repo_slug(&view.repo_url)
+            .unwrap_or(&view.repo_url)
+            .to_string(),
+        commit: view.commit_hash,
+        name: view.name,
+        sheet: view.sheet_path,
+        selected_components: view.selected_components,
+        zoom: view.zoom.and_then(|z| serde_json::from_value(z).ok()),
+        notes: view.notes,
+        created_at: view.created_at,
+    }
+}
+
+/// Save a named view of the schematic viewer
+///
+/// Stores the commit, sheet, selected components, visible region and notes
+/// under a new id that can be shared as a link. The response carries an edit
+/// token, needed to delete the view later; it is not shown again.
+#[utoipa::path(
+    post,
+    path = "/api/views",
+    request_body = SavedViewRequest,
+    responses(
+        (status = 200, description = "View saved", body = SavedViewCreateResponse),
+        (status = 400, description = "Invalid view", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "views"
+)]
+pub async fn create_view(
+    State(state): State<AppState>,
+    Json(mut req): Json<SavedViewRequest>,
+) -> Result<Json<SavedViewCreateResponse>, AppError> {
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(bad_request(format!(
+            "name must be 1 to {} characters",
+            MAX_NAME_CHARS
+        )));
+    }
+    if req.notes.as_ref().map_or(0, |n| n.chars().count()) > MAX_NOTES_CHARS {
+        return Err(bad_request(format!(
+            "notes must be at most {} characters",
+            MAX_NOTES_CHARS
+        )));
+    }
+    if req.selected_components.len() > MAX_SELECTED_COMPONENTS {
+        return Err(bad_request(format!(
+            "At most {} selected components can be saved",
+            MAX_SELECTED_COMPONENTS
+        )));
+    }
+    if let Some(zoom) = &req.zoom {
+        let finite = [zoom.x, zoom.y, zoom.width, zoom.height]
+            .iter()
+            .all(|v| v.is_finite());
+        if !finite || zoom.width <= 0.0 || zoom.height <= 0.0 {
+            return Err(bad_request(
+                "zoom must have finite coordinates and a positive width and height",
+            ));
+        }
+    }
+
+    req.commit = resolve_revision(&req.repo, &req.commit).await?;
+
+    let edit_token = Uuid::new_v4().simple().to_string();
+    let zoom = req
+        .zoom
+        .as_ref()
+        .and_then(|z: &SavedViewZoom| serde_json::to_value(z).ok());
+    let repo_url = github::repo_url(&req.repo);
+    let view = views::insert_view(
+        &state,
+        &views::NewSavedView {
+            repo_url: &repo_url,
+            commit_hash: &req.commit,
+            name,
+            sheet_path: req.sheet.as_deref(),
+            selected_components: &req.selected_components,
+            zoom: zoom.as_ref(),
+            notes: req.notes.as_deref(),
+            owner: None,
+            edit_token_hash: &token_hash(&edit_token),
+        },
+    )
+    .await
+    .map_err(|e| internal("save view", e))?
+    .ok_or_else(|| bad_request(format!("Invalid repository: {}", req.repo)))?;
+
+    info!("Saved view {} for {}@{}", view.id, req.repo, req.commit);
+    Ok(Json(SavedViewCreateResponse {
+        view: to_entry(view),
+        edit_token,
+    }))
+}
+
+/// Get a saved view by id
+#[utoipa::path(
+    get,
+    path = "/api/views/{id}",
+    params(("id" = String, Path, description = "View id")),
+    responses(
+        (status = 200, description = "The saved view", body = SavedViewEntry),
+        (status = 404, description = "No such view", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "views"
+)]
+pub async fn get_view(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<SavedViewEntry>, AppError> {
+    let Ok(uuid) = Uuid::parse_str(&id) else {
+        return Err(not_found(&id));
+    };
+    let view = views::get_view(read_pool(&state), uuid)
+        .await
+        .map_err(|e| internal("load view", e))?
+        .ok_or_else(|| not_found(&id))?;
+    Ok(Json(to_entry(view)))
+}
+
+/// List saved views of a repository, newest first
+#[utoipa::path(
+    post,
+    path = "/api/views/list",
+    request_body = SavedViewListRequest,
+    responses(
+        (status = 200, description = "Saved views", body = SavedViewListResponse),
+        (status = 400, description = "Unknown commit or tag", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "views"
+)]
+pub async fn list_views(
+    State(state): State<AppState>,
+    Json(req): Json<SavedViewListRequest>,
+) -> Result<Json<SavedViewListResponse>, AppError> {
+    let commit = match &req.commit {
+        Some(commit) => Some(resolve_revision(&req.repo, commit).await?),
+        None => None,
+    };
+    let limit = req
+        .limit
+        .unwrap_or(DEFAULT_LIST_LIMIT)
+        .clamp(1, MAX_LIST_LIMIT);
+
+    let repo_url = github::repo_url(&req.repo);
+    let views = views::list_views(read_pool(&state), &repo_url, commit.as_deref(), limit)
+        .await
+        .map_err(|e| internal("list views", e))?;
+
+    Ok(Json(SavedViewListResponse {
+        repo: req.repo,
+        views: views.into_iter().map(to_entry).collect(),
+    }))
+}
+
+/// Delete a saved view
+///
+/// Requires the edit token returned when the view was created, in the
+/// `x-view-token` header.
+#[utoipa::path(
+    delete,
+    path = "/api/views/{id}",
+    params(("id" = String, Path, description = "View id")),
+    responses(
+        (status = 204, description = "View deleted"),
+        (status = 404, description = "No such view, or wrong edit token", body = ApiError),
+        (status = 500, description = "Internal server error", body = ApiError)
+    ),
+    tag = "views"
+)]
+pub async fn delete_view(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let token = headers
+        .get(EDIT_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let Ok(uuid) = Uuid::parse_str(&id) else {
+        return Err(not_found(&id));
+    };
+
+    // A wrong token looks like a missing view, so ids can't be probed
+    if !views::delete_view(&state, uuid, &token_hash(token))
+        .await
+        .map_err(|e| internal("delete view", e))?
+    {
+        return Err(not_found(&id));
+    }
+    info!("Deleted view {}", id);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,280 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::repo_policy::{body_repo, is_json, query_repo, MAX_INSPECTED_BODY_BYTES};
+use crate::server::client_ip;
+use crate::types::ApiError;
+use crate::versioning;
+use kicad_db::{llm_usage, PgPool};
+
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+/// Clients tracked for rate limiting before expired windows are dropped
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+// Routes a demo instance doesn't serve: administration, webhooks, emails,
+// live supplier lookups and everything that changes shared state
+const BLOCKED_PREFIXES: &[&str] = &["/api/admin", "/api/hook", "/api/digests"];
+const BLOCKED_PATHS: &[&str] = &[
+    "/api/digikey/search",
+    "/api/digikey/enrich",
+    "/api/repo/clear-cache",
+    "/api/repo/upload",
+    "/api/repo/sheet/meta",
+    "/api/repo/commit/overview",
+    "/api/repo/commit/overview/review",
+    "/api/repo/commit/comments",
+    "/api/repo/component/note",
+    "/api/rules",
+    "/api/rules/delete",
+    "/api/bom/alternates",
+    "/api/bom/alternates/remove",
+];
+
+/// Settings of a public demo instance, read once from the environment:
+///
+/// - DEMO_MODE: "true" to enable (default off)
+/// - DEMO_REPOS: comma-separated "owner/repo" allow-list; other repositories are refused
+/// - DEMO_REQUESTS_PER_MINUTE: per client address, default 30
+/// - DEMO_LLM_DAILY_TOKENS: LLM tokens the whole instance may spend per UTC
+///   day, default 0 (AI features serve stored results only)
+///
+/// Distillation is cached-only: commits of the sample repositories must be
+/// distilled before the instance goes public, e.g. by running it with
+/// DEMO_MODE off once.
+#[derive(Debug, Clone)]
+pub struct DemoConfig {
+    /// Lowercase "owner/repo" slugs
+    pub repos: Vec<String>,
+    pub requests_per_minute: u32,
+    pub llm_daily_tokens: i64,
+}
+
+impl DemoConfig {
+    fn from_env() -> Option<Self> {
+        let enabled = std::env::var("DEMO_MODE")
+            .map(|v| matches!(v.trim(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+        if !enabled {
+            return None;
+        }
+        let number = |var: &str| {
+            std::env::var(var)
+                .ok()
+                .and_then(|v| v.trim().parse::<i64>().ok())
+        };
+        let config = Self {
+            repos: std::env::var("DEMO_REPOS")
+                .unwrap_or_default()
+                .split(',')
+                .map(|r| r.trim().to_lowercase())
+                .filter(|r| !r.is_empty())
+                .collect(),
+            requests_per_minute: number("DEMO_REQUESTS_PER_MINUTE")
+                .and_then(|n| u32::try_from(n).ok())
+                .unwrap_or(30),
+            llm_daily_tokens: number("DEMO_LLM_DAILY_TOKENS").unwrap_or(0),
+        };
+        if config.repos.is_empty() {
+            warn!("DEMO_MODE is on but DEMO_REPOS is empty; every repository is refused");
+        }
+        info!(
+            "Demo mode: {} repositories, {} requests/min, {} LLM tokens/day",
+            config.repos.len(),
+            config.requests_per_minute,
+            config.llm_daily_tokens
+        );
+        Some(config)
+    }
+
+    /// Whether a repository is on the allow-list
+    pub fn allows(&self, repo: &str) -> bool {
+        let repo = repo.trim().trim_end_matches(".git").to_lowercase();
+        self.repos.contains(&repo)
+    }
+}
+
+/// Demo settings, or None when this isn't a demo instance
+pub fn config() -> Option<&'static DemoConfig> {
+    static CONFIG: OnceLock<Option<DemoConfig>> = OnceLock::new();
+    CONFIG.get_or_init(DemoConfig::from_env).as_ref()
+}
+
+/// Something a demo instance won't do: distill a commit that isn't cached, or
+/// call the LLM past the spend cap. Mapped to 503 `demo_limit`.
+#[derive(Debug)]
+pub struct DemoLimit(pub String);
+
+impl std::fmt::Display for DemoLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for DemoLimit {}
+
+impl From<DemoLimit> for AppError {
+    fn from(limit: DemoLimit) -> Self {
+        AppError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::new("demo_limit", limit.0),
+        )
+    }
+}
+
+/// Refuse fresh distillation on a demo instance
+pub fn check_distill() -> Result<(), DemoLimit> {
+    match config() {
+        Some(_) => Err(DemoLimit(
+            "This demo only serves commits that were distilled in advance".to_string(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Refuse an LLM call on a demo instance once today's tokens reach
+/// DEMO_LLM_DAILY_TOKENS. Fails closed when usage can't be read.
+pub async fn check_llm_budget(pool: &PgPool) -> Result<(), DemoLimit> {
+    let Some(config) = config() else {
+        return Ok(());
+    };
+    let exhausted = || {
+        DemoLimit(
+            "The AI budget of this demo is used up for today; stored results are still available"
+                .to_string(),
+        )
+    };
+    if config.llm_daily_tokens <= 0 {
+        return Err(DemoLimit(
+            "AI features of this demo only show stored results".to_string(),
+        ));
+    }
+    let today = Utc::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .map(|midnight| midnight.and_utc())
+        .unwrap_or_else(Utc::now);
+    match llm_usage::usage_since(pool, today).await {
+        Ok(usage) if usage.prompt_tokens + usage.completion_tokens < config.llm_daily_tokens => {
+            Ok(())
+        }
+        Ok(_) => Err(exhausted()),
+        Err(e) => {
+            warn!("Failed to read LLM usage for the demo budget: {}", e);
+            Err(exhausted())
+        }
+    }
+}
+
+fn refuse(status: StatusCode, code: &str, message: String) -> Response {
+    AppError::new(status, ApiError::new(code, message)).into_response()
+}
+
+/// Count a request against its client's window; Err(seconds until the window
+/// resets) when the client is over the limit
+fn rate_limit(client: &str, limit: u32) -> Result<(), u64> {
+    static WINDOWS: OnceLock<Mutex<HashMap<String, (Instant, u32)>>> = OnceLock::new();
+    let mut windows = WINDOWS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    if windows.len() >= MAX_TRACKED_CLIENTS {
+        windows.retain(|_, (started, _)| now.duration_since(*started) < RATE_WINDOW);
+    }
+    let (started, count) = windows.entry(client.to_string()).or_insert((now, 0));
+    if now.duration_since(*started) >= RATE_WINDOW {
+        *started = now;
+        *count = 0;
+    }
+    if *count >= limit {
+        let remaining = RATE_WINDOW.saturating_sub(now.duration_since(*started));
+        return Err(remaining.as_secs().max(1));
+    }
+    *count += 1;
+    Ok(())
+}
+
+/// Middleware for demo instances: refuses blocked routes, rate limits each
+/// client address and refuses requests naming a repository (the `repo` field of
+/// a JSON body, or the `repo` query parameter) that isn't on the allow-list.
+/// Bodies over MAX_INSPECTED_BODY_BYTES are refused rather than let through.
+/// `proxy_hops` is the listener's setting, for finding the client address.
+pub async fn enforce(State(proxy_hops): State<usize>, request: Request, next: Next) -> Response {
+    let Some(config) = config() else {
+        return next.run(request).await;
+    };
+    let path = versioning::unversioned(request.uri().path().trim_end_matches('/'));
+    if !path.starts_with("/api/") {
+        return next.run(request).await;
+    }
+    if BLOCKED_PREFIXES.iter().any(|p| path.starts_with(p))
+        || BLOCKED_PATHS.contains(&path.as_str())
+    {
+        return refuse(
+            StatusCode::FORBIDDEN,
+            "demo_limit",
+            "This endpoint is not available in the demo".to_string(),
+        );
+    }
+
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| *addr);
+    let client = client_ip(request.headers(), peer, proxy_hops);
+    if let Err(retry_after) = rate_limit(&client, config.requests_per_minute) {
+        let mut response = refuse(
+            StatusCode::TOO_MANY_REQUESTS,
+            "rate_limited",
+            format!(
+                "The demo allows {} requests per minute",
+                config.requests_per_minute
+            ),
+        );
+        if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        return response;
+    }
+
+    let (parts, body) = request.into_parts();
+    let mut repos: Vec<String> = query_repo(&parts.uri).into_iter().collect();
+    let body = if is_json(&parts.headers) {
+        let Ok(bytes) = to_bytes(body, MAX_INSPECTED_BODY_BYTES).await else {
+            return refuse(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "payload_too_large",
+                "Request body is too large for the demo".to_string(),
+            );
+        };
+        repos.extend(body_repo(&bytes));
+        Body::from(bytes)
+    } else {
+        body
+    };
+
+    if let Some(repo) = repos.into_iter().find(|r| !config.allows(r)) {
+        return refuse(
+            StatusCode::FORBIDDEN,
+            "demo_limit",
+            format!(
+                "{} is not one of the demo's sample repositories ({})",
+                repo,
+                config.repos.join(", ")
+            ),
+        );
+    }
+    next.run(Request::from_parts(parts, body)).await
+}
@@ -0,0 +1,156 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+use crate::request_id;
+use crate::types::ApiError;
+
+/// Media type of RFC 7807 problem details
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+// Error bodies larger than this are passed through untouched
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// Error returned by every API handler: a status and the `ApiError` body sent
+/// with it. The request id is filled in on the way out, and `envelope` turns
+/// the body into problem details for clients that ask for them.
+#[derive(Debug)]
+pub struct AppError {
+    pub status: StatusCode,
+    pub error: ApiError,
+}
+
+impl AppError {
+    pub fn new(status: StatusCode, error: ApiError) -> Self {
+        Self { status, error }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.error)).into_response()
+    }
+}
+
+/// `error` code of a status whose response didn't carry an `ApiError`
+fn error_code(status: StatusCode) -> String {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request".to_string(),
+        StatusCode::UNAUTHORIZED => "unauthorized".to_string(),
+        StatusCode::NOT_FOUND => "not_found".to_string(),
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large".to_string(),
+        StatusCode::TOO_MANY_REQUESTS => "rate_limited".to_string(),
+        StatusCode::INTERNAL_SERVER_ERROR => "internal_error".to_string(),
+        _ => status
+            .canonical_reason()
+            .unwrap_or("error")
+            .to_lowercase()
+            .replace(|c: char| !c.is_ascii_alphanumeric(), "_"),
+    }
+}
+
+fn wants_problem_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|media| media.split(';').next().unwrap_or("").trim() == PROBLEM_JSON)
+}
+
+/// An `ApiError` body as RFC 7807 problem details. `error` and `request_id`
+/// are kept as extension members, so clients can match on the same code.
+fn problem(error: Map<String, Value>, status: StatusCode, instance: &str) -> Value {
+    let mut problem = json!({
+        "type": "about:blank",
+        "title": status.canonical_reason().unwrap_or("Error"),
+        "status": status.as_u16(),
+        "instance": instance,
+    });
+    if let Some(message) = error.get("message") {
+        problem["detail"] = message.clone();
+    }
+    for (key, value) in error {
+        if key != "message" {
+            problem[key] = value;
+        }
+    }
+    problem
+}
+
+/// Middleware giving every API error the same envelope. Handlers return
+/// `AppError`s with `ApiError` bodies; errors produced before a handler runs (axum's
+/// plain-text extractor rejections, empty 404s and 405s for unknown routes)
+/// are wrapped in one too, with the request id. Clients sending
+/// `Accept: application/problem+json` get the error as RFC 7807 problem
+/// details instead.
+pub async fn envelope(request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let problem_json = wants_problem_json(request.headers());
+    let response = next.run(request).await;
+
+    let status = response.status();
+    if !path.starts_with("/api/") || !(status.is_client_error() || status.is_server_error()) {
+        return response;
+    }
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"));
+    if is_json && !problem_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ERROR_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read error body of {}: {}", path, e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let error = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(error)) if is_json && error.contains_key("error") => error,
+        // Some other JSON body; leave it alone
+        Ok(_) if is_json => return Response::from_parts(parts, Body::from(bytes)),
+        _ => {
+            let text = String::from_utf8_lossy(&bytes).trim().to_string();
+            let message = if text.is_empty() {
+                status.canonical_reason().unwrap_or("Error").to_string()
+            } else {
+                text
+            };
+            let mut error = Map::new();
+            error.insert("error".to_string(), Value::String(error_code(status)));
+            error.insert("message".to_string(), Value::String(message));
+            if let Some(id) = parts
+                .headers
+                .get(request_id::HEADER)
+                .and_then(|v| v.to_str().ok())
+            {
+                error.insert("request_id".to_string(), Value::String(id.to_string()));
+            }
+            error
+        }
+    };
+
+    let (body, content_type) = if problem_json {
+        (problem(error, status, &path), PROBLEM_JSON)
+    } else {
+        (Value::Object(error), "application/json")
+    };
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts
+        .headers
+        .insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    let body = serde_json::to_vec(&body).unwrap_or_else(|_| bytes.to_vec());
+    Response::from_parts(parts, Body::from(body))
+}
@@ -0,0 +1,14 @@
+pub mod controllers;
+pub mod demo;
+pub mod errors;
+pub mod limits;
+pub mod openapi;
+pub mod quota;
+pub mod repo_policy;
+pub mod request_id;
+pub mod routes;
+pub mod self_check;
+pub mod server;
+pub mod services;
+pub mod types;
+pub mod versioning;
@@ -0,0 +1,142 @@
+use axum::{
+    extract::{DefaultBodyLimit, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::errors::AppError;
+use crate::types::ApiError;
+
+const MIB: usize = 1024 * 1024;
+
+/// Timeout and request body size limit for a group of routes.
+///
+/// The timeout covers the handler up to the response headers, so SSE streams
+/// keep running once they have started.
+#[derive(Debug, Clone, Copy)]
+pub struct RouteLimits {
+    pub timeout: Duration,
+    pub max_body_bytes: usize,
+}
+
+impl RouteLimits {
+    /// Quick lookups (DigiKey search, admin, jobs, blobs).
+    /// REQUEST_TIMEOUT_SECS / REQUEST_MAX_BODY_BYTES, default 60s / 1 MiB.
+    pub fn standard() -> Self {
+        Self::from_env("REQUEST", 60, MIB)
+    }
+
+    /// Cloning, distillation and BOM building, which run in the API process
+    /// unless the job queue is enabled, and DigiKey enrichment of a whole BOM.
+    /// HEAVY_REQUEST_TIMEOUT_SECS / HEAVY_REQUEST_MAX_BODY_BYTES, default 11 min / 1 MiB.
+    pub fn heavy() -> Self {
+        Self::from_env("HEAVY_REQUEST", 660, MIB)
+    }
+
+    /// Grok endpoints that call the xAI API.
+    /// LLM_REQUEST_TIMEOUT_SECS / LLM_REQUEST_MAX_BODY_BYTES, default 5 min / 1 MiB.
+    pub fn llm() -> Self {
+        Self::from_env("LLM_REQUEST", 300, MIB)
+    }
+
+    /// GitHub webhooks, whose payloads can reach 25 MB.
+    /// WEBHOOK_REQUEST_TIMEOUT_SECS / WEBHOOK_REQUEST_MAX_BODY_BYTES, default 2 min / 25 MiB.
+    pub fn webhook() -> Self {
+        Self::from_env("WEBHOOK_REQUEST", 120, 25 * MIB)
+    }
+
+    /// Repository uploads of a mirror-mode instance.
+    /// UPLOAD_REQUEST_TIMEOUT_SECS / UPLOAD_REQUEST_MAX_BODY_BYTES, default 30 min / 2 GiB.
+    pub fn upload() -> Self {
+        Self::from_env("UPLOAD_REQUEST", 1800, 2048 * MIB)
+    }
+
+    fn from_env(prefix: &str, default_secs: u64, default_bytes: usize) -> Self {
+        let read = |suffix: &str| {
+            std::env::var(format!("{}_{}", prefix, suffix))
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+        };
+        Self {
+            timeout: Duration::from_secs(read("TIMEOUT_SECS").unwrap_or(default_secs)),
+            max_body_bytes: read("MAX_BODY_BYTES")
+                .map(|v| v as usize)
+                .unwrap_or(default_bytes),
+        }
+    }
+
+    /// Enforce these limits on every route in `router`
+    pub fn apply<S>(self, router: Router<S>) -> Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        router
+            .layer(middleware::from_fn_with_state(self, enforce))
+            // Catches bodies without a Content-Length once an extractor reads them
+            .layer(DefaultBodyLimit::max(self.max_body_bytes))
+    }
+}
+
+fn too_large(limits: &RouteLimits) -> Response {
+    AppError::new(
+        StatusCode::PAYLOAD_TOO_LARGE,
+        ApiError::new(
+            "payload_too_large",
+            format!(
+                "Request body exceeds the {} byte limit for this endpoint",
+                limits.max_body_bytes
+            ),
+        ),
+    )
+    .into_response()
+}
+
+async fn enforce(State(limits): State<RouteLimits>, request: Request, next: Next) -> Response {
+    let declared_length = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    if declared_length.is_some_and(|len| len > limits.max_body_bytes) {
+        return too_large(&limits);
+    }
+
+    let method = request.method().clone();
+    let uri = request.uri().clone();
+    let response = match tokio::time::timeout(limits.timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            warn!(
+                "{} {} timed out after {}s",
+                method,
+                uri,
+                limits.timeout.as_secs()
+            );
+            return AppError::new(
+                StatusCode::REQUEST_TIMEOUT,
+                ApiError::new(
+                    "timeout",
+                    format!(
+                        "Request took longer than {}s and was cancelled",
+                        limits.timeout.as_secs()
+                    ),
+                ),
+            )
+            .into_response();
+        }
+    };
+
+    // Extractor rejections for streamed bodies come back as plain text; keep errors JSON
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|v| v.as_bytes().starts_with(b"application/json"));
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE && !is_json {
+        return too_large(&limits);
+    }
+    response
+}
@@ -1,65 +1,136 @@
 use anyhow::Context;
 use axum::Router;
 use std::sync::Arc;
-use tower_http::cors::{Any, CorsLayer};
 use tracing::info;
-use utoipa::OpenApi;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
 use utoipa_swagger_ui::SwaggerUi;
 
-mod controllers;
-mod openapi;
-mod routes;
-mod services;
-mod types;
-
-use openapi::ApiDoc;
+use kicad_backend::openapi::ApiDoc;
+use kicad_backend::{controllers, routes, self_check, server, services, versioning};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     // Load environment variables from .env file
     dotenvy::dotenv().ok();
-    
-    tracing_subscriber::fmt().init();
+
+    // `--check`: verify the deployment, print a report and exit instead of serving
+    if std::env::args().skip(1).any(|arg| arg == "--check") {
+        tracing_subscriber::fmt()
+            .with_writer(std::io::stderr)
+            .with_max_level(LevelFilter::WARN)
+            .init();
+        let report = self_check::run().await;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        std::process::exit(if report.ok { 0 } else { 1 });
+    }
+
+    // Log to stdout and keep recent errors for the admin overview. The filter
+    // (LOG_FILTER or RUST_LOG, default info) can be changed via /api/admin/log-filter
+    tracing_subscriber::registry()
+        .with(services::log_filter::reloadable())
+        .with(tracing_subscriber::fmt::layer())
+        .with(services::error_log::ErrorLog)
+        .init();
+
+    controllers::admin::validate_admin_token()?;
 
     let pool = kicad_db::create_pool()
         .await
         .context("Failed to create database pool")?;
 
+    // Heavy read endpoints use the replica when DATABASE_READ_URL is set
+    match kicad_db::init_read_pool().await {
+        Ok(true) => tracing::info!("Read replica pool connected (DATABASE_READ_URL)"),
+        Ok(false) => {}
+        Err(e) => tracing::warn!(
+            "Failed to connect read replica; reading from the primary: {}",
+            e
+        ),
+    }
+
+    // A misconfigured blob store stops startup rather than writing files elsewhere
+    services::blob_store::init().context("Invalid blob store configuration")?;
+
+    // Supplier credentials and secret settings stored via the admin endpoints. They
+    // stay sealed in memory, and are reloaded when any replica changes one
+    if let Err(e) = services::credentials::migrate_legacy(&pool).await {
+        tracing::warn!("Failed to move legacy provider credentials: {}", e);
+    }
+    match services::secret_store::reload(&pool).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!("Loaded {} stored secret(s)", n),
+        Err(e) => tracing::warn!("Failed to load stored secrets: {}", e),
+    }
+    services::secret_store::spawn_listener(pool.clone());
+
+    // Attach schematics stored before repos were tracked by id
+    match kicad_db::repos::backfill_repo_ids(&pool).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!("Attached {} stored schematic(s) to their repo", n),
+        Err(e) => tracing::warn!("Failed to backfill schematic repo ids: {}", e),
+    }
+
+    // Move schematic images earlier versions kept in Postgres into the blob store
+    let migration_pool = pool.clone();
+    tokio::spawn(async move {
+        if !services::leader::is_leader(&migration_pool).await {
+            return;
+        }
+        match services::blob_store::migrate_legacy_images(&migration_pool).await {
+            Ok(0) => {}
+            Ok(n) => tracing::info!("Moved {} schematic image(s) to the blob store", n),
+            Err(e) => tracing::warn!("Failed to move schematic images to the blob store: {:#}", e),
+        }
+    });
+
+    // The schedulers below only do work on the replica holding the scheduler
+    // lock (see services::leader), so replicas don't duplicate each other's passes
+
+    // Regenerate summaries left behind by prompt or distiller changes
+    services::summaries::spawn_refresh_loop(
+        pool.clone(),
+        services::summaries::RefreshSettings::from_env(),
+    );
+
+    // Deliver events recorded in the outbox alongside state changes
+    services::outbox::spawn_relay(
+        pool.clone(),
+        services::outbox::RelaySettings::from_env(),
+    );
+
+    // Compile due digest emails into the outbox
+    services::digests::spawn_scheduler(
+        pool.clone(),
+        services::digests::DigestSettings::from_env(),
+    );
+
+    // Follow repositories whose default branch changes upstream
+    services::default_branch::spawn_checker(
+        pool.clone(),
+        services::default_branch::BranchCheckSettings::from_env(),
+    );
+
     let app_state = Arc::new(pool);
 
-    // Configure CORS to allow requests from the frontend domain
-    // Note: If you want to restrict to specific origins, use:
-    // .allow_origin("https://grokicad.com".parse::<HeaderValue>().unwrap())
-    let cors = CorsLayer::new()
-        .allow_origin(Any) // Allow all origins in production (can be restricted to specific domains)
-        .allow_methods(Any)
-        .allow_headers(Any)
-        .expose_headers(Any)
-        .allow_credentials(false) // Set to true if you need to send cookies/auth headers
-        .max_age(std::time::Duration::from_secs(3600));
-
-    let app = Router::new()
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        .nest("/api/repo", routes::repo::router())
-        .nest("/api/hook", routes::hook::router())
-        .nest("/api/grok", routes::grok::router())
-        .nest("/api/distill", routes::distill::router())
-        .nest("/api/digikey", routes::digikey::router())
-        .layer(cors)
-        .layer(tower_http::trace::TraceLayer::new_for_http())
+    let server_config = server::ServerConfig::from_env().context("Invalid listener configuration")?;
+
+    let routes = Router::new()
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::versioned()))
+        .nest(versioning::CURRENT_PREFIX, routes::api())
+        // Paths from before versioning, answered with Deprecation and Sunset headers
+        .nest(
+            versioning::LEGACY_PREFIX,
+            routes::api().layer(axum::middleware::from_fn(versioning::deprecate_legacy)),
+        );
+    let app = server::with_middleware(routes, app_state.clone(), server_config.proxy_hops)
         .with_state(app_state);
 
-    // Listen on HTTP port (Cloudflare will handle HTTPS termination)
-    // Use port 8080 for HTTP, or port 80 if running as root
-    let port = std::env::var("PORT")
-        .ok()
-        .and_then(|p| p.parse::<u16>().ok())
-        .unwrap_or(8080);
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-    info!("Server listening on 0.0.0.0:{}", port);
-    info!("Swagger UI available at http://localhost:{}/swagger-ui/", port);
-
-    axum::serve(listener, app).await?;
+    // Plain HTTP (e.g. behind nginx/Cloudflare), native HTTPS, or both - see ServerConfig
+    if let Some(port) = server_config.http_port {
+        info!("Swagger UI available at http://localhost:{}/swagger-ui/", port);
+    }
+    server::serve(app, server_config).await?;
 
     Ok(())
 }
@@ -1,16 +1,68 @@
 use utoipa::OpenApi;
 
-use crate::controllers::{digikey, distill, grok, hook, repo};
+use crate::controllers::{
+    admin, blobs, bom, ci, design_rules, digests, digikey, distill, export, feedback, grok, hook,
+    jobs, presence, provenance, repo, report, sync, usage, views,
+};
 use crate::types::{
-    ApiError, CommitFilesRequest, CommitFilesResponse, CommitInfo, CommitInfoRequest,
-    CommitInfoResponse, DigiKeyParameter, DigiKeyPartInfo, DigiKeySearchRequest,
-    DigiKeySearchResponse, DistillRequest, DistillResponse, GrokCommitSummaryRequest,
-    GrokCommitSummaryResponse, GrokObsoleteReplacementRequest, GrokObsoleteReplacementResponse,
+    AdminApiKey, AdminApiKeyCreateResponse, AdminApiKeyQuotasRequest, AdminApiKeyRequest,
+    AdminApiKeysResponse, AdminAuditEntry, AdminAuditResponse, AdminBlobUsage, AdminConfigEntry,
+    AdminConfigResponse, AdminDiskStats, AdminDistillCacheStats, AdminFailedJob,
+    AdminFailedJobsResponse, AdminIndexStats, AdminJobStats, AdminLlmSpend, AdminLogFilterRequest,
+    AdminLogFilterResponse, AdminOverviewResponse, AdminQuarantineReleaseRequest,
+    AdminQuarantineReleaseResponse, AdminQuarantineResponse, AdminQuarantinedCommit,
+    AdminRecentError, AdminRefreshSummariesResponse, AdminRenameRepoRequest, AdminRepo,
+    AdminRepoCommitStatusRequest, AdminRepoStorage, AdminReposResponse, AdminRetryJobsRequest,
+    AdminRetryJobsResponse, AdminSecretRequest, AdminSecretsResponse, AdminSecretsRotateResponse,
+    AdminStorageResponse, AdminStorageTotals, AdminStoredSecret, ApiError, BlobInfo,
+    BlobListRequest, BlobListResponse, BomDelta, BomDeltaChange, BomDeltaPart, BomDiffLine,
+    BomDiffRequest, BomDiffResponse, BomLine, BomRequest, BomResponse, CiCheck, CiErcViolation,
+    CiObsoletePart, CiVerdictRequest, CiVerdictResponse, CommitCommentEntry, CommitCommentRequest,
+    CommitCommentsRequest, CommitCommentsResponse, CommitFilesRequest, CommitFilesResponse,
+    CommitInfo, CommitInfoRequest, CommitInfoResponse, CommitOverviewEditRequest,
+    CommitOverviewResponse, CommitReportRequest, CommitRisk, ComponentNoteEntry,
+    ComponentNoteRequest, ComponentNoteResponse, ComponentNotesResponse, ComponentSearchCommit,
+    ComponentSearchMatch, ComponentSearchRequest, ComponentSearchResponse, ComponentSelector,
+    ConnectorPinChange, ConnectorPinoutChange, DecouplingCapacitor, DecouplingCheckRequest,
+    DecouplingCheckResponse, DecouplingEntry, DesignExportRequest, DesignMetricsPoint,
+    DesignRuleCheckRequest, DesignRuleCheckResponse, DesignRuleDeleteRequest, DesignRuleEntry,
+    DesignRuleListRequest, DesignRuleListResponse, DesignRuleRequest, DesignRuleSpec,
+    DesignRuleViolation, DigestConfirmationResponse, DigestSubscribeRequest,
+    DigestSubscriptionResponse, DigestUnsubscribeRequest, DigiKeyEnrichRequest,
+    DigiKeyEnrichResponse, DigiKeyEnrichedPart, DigiKeyMissingPart, DigiKeyParameter,
+    DigiKeyPartInfo, DigiKeySearchRequest, DigiKeySearchResponse, DistillCacheStats,
+    DistillComparison, DistillDifference, DistillRequest, DistillResponse, DistillSheet,
+    DistillSheetRequest, DistillSheetResponse, DistillWarning, FeedbackEntry,
+    FeedbackExportResponse, FeedbackTotal, FootprintCheckRequest, FootprintCheckResponse,
+    FootprintIssue, FootprintLibraryIssue, GithubReleaseInfo, GrokAskRepoRequest, GrokCitation,
+    GrokCommitSummaryRequest, GrokCommitSummaryResponse, GrokExplainNetRequest, GrokFileSummary,
+    GrokNetComponent, GrokNetContext, GrokNetPin, GrokObsoleteReplacementRequest,
+    GrokObsoleteReplacementResponse, GrokReleaseNotesRequest, GrokReplacementSuggestion,
     GrokRepoSummaryRequest, GrokRepoSummaryResponse, GrokSelectionStreamRequest,
-    GrokSelectionSummaryRequest, GrokSelectionSummaryResponse, HookUpdateResponse,
-    RepoClearCacheRequest, RepoClearCacheResponse, RepoCommitsRequest, RepoCommitsResponse,
-    RepoInitRequest, RepoInitResponse, SchematicFile,
+    GrokSelectionSummaryRequest, GrokSelectionSummaryResponse, GrokSessionExport,
+    GrokSessionExportTurn, GrokSessionRequest, GrokSessionResponse, GrokSummarySource,
+    HarnessConnector, HarnessRequest, HarnessResponse, HarnessRow, HookUpdateResponse,
+    ImpactNetChange, ImpactRequest, ImpactResponse, ImpactedComponent, JlcPartType, JobRunEntry,
+    JobStatusResponse, LcscPartInfo, MetricsHistoryRequest, MetricsHistoryResponse,
+    PackageMismatch, PartAlternateEntry, PartAlternateRemoveRequest, PartAlternateRequest,
+    PartAlternatesRequest, PartAlternatesResponse, PartOffer, PinMapMcu, PinMapPin, PinMapRequest,
+    PinMapResponse, PresenceEvent, PresenceMessage, PresenceResponse, PresenceViewer,
+    ProvenanceEntry, ProvenanceListResponse, ProvenanceToolCall, ProviderCredentialStatus,
+    ProviderCredentialsRequest, ProviderCredentialsResponse, ReleaseNotesCommit,
+    ReleaseNotesListRequest, ReleaseNotesListResponse, ReleaseNotesResponse, RepoClearCacheRequest,
+    RepoClearCacheResponse, RepoCommitsRequest, RepoCommitsResponse, RepoInitRequest,
+    RepoInitResponse, RepoRelease, RepoReleasesRequest, RepoReleasesResponse, RepoSuggestion,
+    RepoSuggestionsResponse, RepoSummaryCardResponse, RepoUploadResponse, RiskFactor,
+    SavedViewCreateResponse, SavedViewEntry, SavedViewListRequest, SavedViewListResponse,
+    SavedViewRequest, SavedViewZoom, SchematicFile, SharedDecouplingCapacitor, SheetMetaEntry,
+    SheetMetaRequest, StoredCommit, StoredCommitsRequest, StoredCommitsResponse, SubsystemCoverage,
+    SummaryFeedbackRequest, SummaryFeedbackResponse, SummaryReviewRequest, SummaryReviewResponse,
+    SymbolCheckRequest, SymbolCheckResponse, SymbolPinIssue, SyncChange, SyncChangesResponse,
+    TestPointCoverageRequest, TestPointCoverageResponse, UncoveredNet, UsageCount, UsageResponse,
+    UsageSeconds, ValueChangeGroup, ValueCompareRequest, ValueCompareResponse, XrefEntry,
+    XrefRequest, XrefResponse,
 };
+use crate::versioning;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -23,8 +75,33 @@ use crate::types::{
         repo::get_commits,
         repo::get_commit_files,
         repo::get_commit_info,
+        repo::edit_commit_overview,
+        repo::review_commit_overview,
+        repo::get_schematic_image,
         repo::init_repo,
         repo::clear_cache,
+        repo::upload_repo,
+        repo::list_releases,
+        repo::list_stored,
+        repo::get_summary_card,
+        repo::get_suggestions,
+        repo::search_components,
+        repo::check_footprints,
+        repo::check_symbols,
+        repo::check_decoupling,
+        repo::test_point_coverage,
+        repo::get_xref,
+        repo::commit_impact,
+        repo::export_pinmap,
+        repo::get_harness,
+        repo::compare_values,
+        repo::diff_bom,
+        repo::set_sheet_meta,
+        repo::set_component_note,
+        repo::list_component_notes,
+        repo::add_commit_comment,
+        repo::list_commit_comments,
+        repo::metrics_history,
         hook::update_repo,
         hook::refresh_repo,
         hook::github_webhook,
@@ -32,11 +109,77 @@ use crate::types::{
         grok::summarize_selection,
         grok::summarize_repo,
         grok::chat_stream,
+        grok::start_session,
+        grok::export_session,
         grok::selection_stream,
+        grok::ask_repo,
+        grok::explain_net,
         grok::find_replacement,
+        grok::generate_release_notes,
+        grok::get_release_notes,
+        grok::list_release_notes,
         distill::distill_schematics,
+        distill::distill_sheet,
         digikey::search_parts,
         digikey::get_status,
+        digikey::enrich_parts,
+        bom::get_bom,
+        bom::add_part_alternate,
+        bom::list_part_alternates,
+        bom::remove_part_alternate,
+        ci::get_verdict,
+        design_rules::set_rule,
+        design_rules::list_rules,
+        design_rules::delete_rule,
+        design_rules::check_rules,
+        report::commit_report,
+        report::commit_report_page,
+        export::export_design,
+        admin::list_credentials,
+        admin::set_credentials,
+        admin::delete_credentials,
+        admin::list_secrets,
+        admin::rotate_secrets,
+        admin::set_secret,
+        admin::delete_secret,
+        admin::overview,
+        admin::refresh_stale_summaries,
+        admin::list_repos,
+        admin::rename_repo,
+        admin::set_repo_commit_status,
+        admin::list_audit,
+        admin::list_failed_jobs,
+        admin::retry_job,
+        admin::retry_jobs,
+        admin::list_quarantine,
+        admin::release_quarantine,
+        admin::get_log_filter,
+        admin::set_log_filter,
+        admin::get_config,
+        admin::storage,
+        admin::delete_blob,
+        admin::create_api_key,
+        admin::list_api_keys,
+        admin::set_api_key_quotas,
+        admin::revoke_api_key,
+        usage::get_usage,
+        jobs::get_job,
+        blobs::list_blobs,
+        blobs::get_blob_file,
+        feedback::submit_summary_feedback,
+        feedback::export_summary_feedback,
+        views::create_view,
+        views::get_view,
+        views::list_views,
+        views::delete_view,
+        presence::list_viewers,
+        presence::presence_socket,
+        digests::subscribe,
+        digests::confirm,
+        digests::unsubscribe,
+        sync::list_changes,
+        provenance::list_records,
+        provenance::get_record,
     ),
     components(schemas(
         RepoCommitsRequest,
@@ -45,28 +188,236 @@ use crate::types::{
         RepoInitResponse,
         RepoClearCacheRequest,
         RepoClearCacheResponse,
+        RepoReleasesRequest,
+        RepoReleasesResponse,
+        RepoSummaryCardResponse,
+        RepoSuggestion,
+        RepoSuggestionsResponse,
+        RepoUploadResponse,
+        StoredCommitsRequest,
+        StoredCommit,
+        StoredCommitsResponse,
+        RepoRelease,
+        GithubReleaseInfo,
+        ComponentSearchRequest,
+        ComponentSearchResponse,
+        ComponentSearchCommit,
+        ComponentSearchMatch,
+        FootprintCheckRequest,
+        FootprintCheckResponse,
+        FootprintIssue,
+        FootprintLibraryIssue,
+        PackageMismatch,
+        SymbolCheckRequest,
+        SymbolCheckResponse,
+        ValueCompareRequest,
+        ValueCompareResponse,
+        ValueChangeGroup,
+        SheetMetaRequest,
+        SheetMetaEntry,
+        ComponentNoteRequest,
+        ComponentNoteEntry,
+        ComponentNoteResponse,
+        ComponentNotesResponse,
+        CommitCommentRequest,
+        CommitCommentEntry,
+        CommitCommentsRequest,
+        CommitCommentsResponse,
+        MetricsHistoryRequest,
+        DesignMetricsPoint,
+        MetricsHistoryResponse,
+        SymbolPinIssue,
         CommitInfo,
+        CommitRisk,
+        RiskFactor,
         CommitFilesRequest,
         CommitFilesResponse,
         SchematicFile,
         CommitInfoRequest,
         CommitInfoResponse,
+        CommitOverviewEditRequest,
+        CommitOverviewResponse,
+        SummaryReviewRequest,
+        SummaryReviewResponse,
         HookUpdateResponse,
         GrokCommitSummaryRequest,
         GrokCommitSummaryResponse,
+        GrokSummarySource,
+        GrokFileSummary,
         GrokSelectionStreamRequest,
+        GrokSessionRequest,
+        GrokSessionResponse,
+        GrokSessionExport,
+        GrokAskRepoRequest,
+        GrokExplainNetRequest,
+        GrokNetContext,
+        GrokNetComponent,
+        GrokNetPin,
+        GrokCitation,
+        GrokSessionExportTurn,
         GrokSelectionSummaryRequest,
         GrokSelectionSummaryResponse,
         GrokRepoSummaryRequest,
         GrokRepoSummaryResponse,
         GrokObsoleteReplacementRequest,
         GrokObsoleteReplacementResponse,
+        GrokReplacementSuggestion,
+        GrokReleaseNotesRequest,
+        ReleaseNotesListRequest,
+        ReleaseNotesResponse,
+        ReleaseNotesListResponse,
+        ReleaseNotesCommit,
+        BomDelta,
+        BomDeltaPart,
+        BomDeltaChange,
         DistillRequest,
         DistillResponse,
+        DistillWarning,
+        DistillComparison,
+        DistillDifference,
+        DistillCacheStats,
+        DistillSheet,
+        DistillSheetRequest,
+        DistillSheetResponse,
         DigiKeySearchRequest,
         DigiKeySearchResponse,
         DigiKeyPartInfo,
         DigiKeyParameter,
+        DigiKeyEnrichRequest,
+        DigiKeyEnrichResponse,
+        DigiKeyEnrichedPart,
+        DigiKeyMissingPart,
+        PartOffer,
+        JlcPartType,
+        LcscPartInfo,
+        BomRequest,
+        BomResponse,
+        CiVerdictRequest,
+        CiVerdictResponse,
+        CiCheck,
+        CiErcViolation,
+        CiObsoletePart,
+        ConnectorPinChange,
+        ConnectorPinoutChange,
+        ComponentSelector,
+        DecouplingCheckRequest,
+        DecouplingCheckResponse,
+        DecouplingCapacitor,
+        DecouplingEntry,
+        SharedDecouplingCapacitor,
+        TestPointCoverageRequest,
+        TestPointCoverageResponse,
+        SubsystemCoverage,
+        UncoveredNet,
+        XrefRequest,
+        XrefEntry,
+        XrefResponse,
+        ImpactRequest,
+        ImpactNetChange,
+        ImpactedComponent,
+        ImpactResponse,
+        PinMapRequest,
+        PinMapPin,
+        PinMapMcu,
+        PinMapResponse,
+        HarnessRequest,
+        HarnessConnector,
+        HarnessRow,
+        HarnessResponse,
+        DesignRuleSpec,
+        DesignRuleRequest,
+        DesignRuleEntry,
+        DesignRuleListRequest,
+        DesignRuleListResponse,
+        DesignRuleDeleteRequest,
+        DesignRuleCheckRequest,
+        DesignRuleCheckResponse,
+        DesignRuleViolation,
+        BomLine,
+        BomDiffRequest,
+        BomDiffLine,
+        BomDiffResponse,
+        PartAlternateRequest,
+        PartAlternateRemoveRequest,
+        PartAlternatesRequest,
+        PartAlternateEntry,
+        PartAlternatesResponse,
+        ProviderCredentialsRequest,
+        ProviderCredentialStatus,
+        ProviderCredentialsResponse,
+        AdminStoredSecret,
+        AdminSecretRequest,
+        AdminSecretsResponse,
+        AdminSecretsRotateResponse,
+        AdminOverviewResponse,
+        AdminIndexStats,
+        AdminDistillCacheStats,
+        AdminJobStats,
+        AdminDiskStats,
+        AdminLlmSpend,
+        AdminRecentError,
+        AdminRefreshSummariesResponse,
+        AdminRepo,
+        AdminReposResponse,
+        AdminRenameRepoRequest,
+        AdminRepoCommitStatusRequest,
+        AdminAuditEntry,
+        AdminFailedJob,
+        AdminFailedJobsResponse,
+        AdminRetryJobsRequest,
+        AdminRetryJobsResponse,
+        AdminQuarantinedCommit,
+        AdminQuarantineResponse,
+        AdminQuarantineReleaseRequest,
+        AdminQuarantineReleaseResponse,
+        AdminLogFilterRequest,
+        AdminLogFilterResponse,
+        AdminConfigEntry,
+        AdminConfigResponse,
+        AdminBlobUsage,
+        AdminRepoStorage,
+        AdminStorageTotals,
+        AdminStorageResponse,
+        AdminAuditResponse,
+        AdminApiKeyRequest,
+        AdminApiKeyQuotasRequest,
+        AdminApiKey,
+        AdminApiKeyCreateResponse,
+        AdminApiKeysResponse,
+        UsageCount,
+        UsageSeconds,
+        UsageResponse,
+        JobStatusResponse,
+        JobRunEntry,
+        BlobListRequest,
+        BlobListResponse,
+        BlobInfo,
+        SummaryFeedbackRequest,
+        SummaryFeedbackResponse,
+        FeedbackExportResponse,
+        FeedbackTotal,
+        FeedbackEntry,
+        SavedViewZoom,
+        SavedViewRequest,
+        SavedViewEntry,
+        SavedViewCreateResponse,
+        SavedViewListRequest,
+        SavedViewListResponse,
+        PresenceViewer,
+        PresenceResponse,
+        PresenceEvent,
+        PresenceMessage,
+        CommitReportRequest,
+        DesignExportRequest,
+        DigestSubscribeRequest,
+        DigestConfirmationResponse,
+        DigestSubscriptionResponse,
+        DigestUnsubscribeRequest,
+        SyncChange,
+        SyncChangesResponse,
+        ProvenanceToolCall,
+        ProvenanceEntry,
+        ProvenanceListResponse,
         ApiError,
     )),
     tags(
@@ -74,7 +425,34 @@ use crate::types::{
         (name = "hook", description = "Webhook endpoints for triggering updates"),
         (name = "grok", description = "AI-powered analysis endpoints"),
         (name = "distill", description = "Schematic distillation endpoints"),
-        (name = "digikey", description = "DigiKey part lookup endpoints")
+        (name = "digikey", description = "DigiKey part lookup endpoints"),
+        (name = "bom", description = "Bill of materials endpoints"),
+        (name = "ci", description = "Commit checks for gating CI pipelines"),
+        (name = "rules", description = "Team-defined design policy rules"),
+        (name = "report", description = "Exportable commit reports"),
+        (name = "export", description = "Vendor-neutral design exports for PLM systems"),
+        (name = "admin", description = "Administrative endpoints (require ADMIN_TOKEN)"),
+        (name = "jobs", description = "Background job status endpoints"),
+        (name = "blobs", description = "Stored images, renders, datasheets and export archives"),
+        (name = "feedback", description = "Ratings of generated summaries"),
+        (name = "views", description = "Saved and shared schematic viewer states"),
+        (name = "presence", description = "Who else is viewing a commit, over a WebSocket"),
+        (name = "digests", description = "Scheduled schematic activity digests"),
+        (name = "usage", description = "API key usage and remaining quota"),
+        (name = "sync", description = "Changes since a cursor, for clients keeping a local mirror"),
+        (name = "provenance", description = "How stored AI artifacts were produced")
     )
 )]
 pub struct ApiDoc;
+
+impl ApiDoc {
+    /// The document with every path under the current API version
+    pub fn versioned() -> utoipa::openapi::OpenApi {
+        let mut doc = Self::openapi();
+        doc.paths.paths = std::mem::take(&mut doc.paths.paths)
+            .into_iter()
+            .map(|(path, item)| (versioning::current(&path), item))
+            .collect();
+        doc
+    }
+}
@@ -0,0 +1,267 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use std::future::Future;
+use std::sync::Arc;
+use tracing::{error, warn};
+
+use crate::errors::AppError;
+use crate::types::ApiError;
+use crate::versioning;
+use kicad_db::api_keys::{self, ApiKey, KeyUsage};
+use kicad_db::PgPool;
+
+/// Header carrying the API key
+pub const HEADER: &str = "x-api-key";
+
+// Routes outside the quota: admin (own token), webhooks and the usage endpoint itself
+const UNMETERED_PREFIXES: &[&str] = &["/api/admin", "/api/hook", "/api/usage"];
+
+tokio::task_local! {
+    // Key of the request being handled, so deep call sites can charge it
+    static CURRENT_KEY: i64;
+}
+
+/// Whether requests without an API key are refused (API_KEYS_REQUIRED, default
+/// false). Self-hosted deployments leave this off; requests without a key are
+/// then metered against the shared anonymous key, so leaving the header out
+/// doesn't escape a quota.
+pub fn keys_required() -> bool {
+    std::env::var("API_KEYS_REQUIRED")
+        .map(|v| matches!(v.trim(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// SHA-256 hex of a key, as stored in `api_keys.key_hash`
+pub fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// First day of the current quota period (the calendar month, UTC)
+pub fn period_start(now: DateTime<Utc>) -> NaiveDate {
+    now.date_naive().with_day(1).unwrap_or(now.date_naive())
+}
+
+/// When the current quota period ends and usage resets
+pub fn period_end(now: DateTime<Utc>) -> DateTime<Utc> {
+    let (year, month) = if now.month() == 12 {
+        (now.year() + 1, 1)
+    } else {
+        (now.year(), now.month() + 1)
+    };
+    Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
+
+type QuotaError = AppError;
+
+/// Resolve the key sent with a request. Ok(None) when no key was sent.
+pub async fn authenticate(
+    pool: &PgPool,
+    headers: &HeaderMap,
+) -> Result<Option<ApiKey>, QuotaError> {
+    let Some(key) = headers
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+    else {
+        return Ok(None);
+    };
+
+    match api_keys::find_active_key(pool, &hash_key(key)).await {
+        Ok(Some(key)) => Ok(Some(key)),
+        Ok(None) => Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            ApiError::unauthorized("Unknown or revoked API key"),
+        )),
+        Err(e) => {
+            error!("Failed to look up API key: {}", e);
+            Err(AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal("Failed to check API key"),
+            ))
+        }
+    }
+}
+
+/// The shared key metering requests sent without one; Err when it was revoked
+async fn anonymous_key(pool: &PgPool) -> Result<ApiKey, QuotaError> {
+    match api_keys::find_active_key(pool, api_keys::ANONYMOUS_KEY_HASH).await {
+        Ok(Some(key)) => Ok(key),
+        Ok(None) => Err(AppError::new(
+            StatusCode::UNAUTHORIZED,
+            ApiError::unauthorized(format!("An API key is required in the {} header", HEADER)),
+        )),
+        Err(e) => {
+            error!("Failed to look up the anonymous API key: {}", e);
+            Err(AppError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiError::internal("Failed to check API key"),
+            ))
+        }
+    }
+}
+
+/// Refuse the request when one of the key's quotas is used up: 402 for the
+/// billable resources (LLM tokens, distillation time), 429 for requests.
+/// Otherwise count the request and return the key's usage.
+async fn check_and_charge(pool: &PgPool, key: &ApiKey) -> Result<KeyUsage, Response> {
+    let now = Utc::now();
+    let period = period_start(now);
+    let resets_at = period_end(now);
+    let internal = |e: sqlx::Error| {
+        error!("Failed to read usage of API key {}: {}", key.id, e);
+        AppError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::internal("Failed to check quota"),
+        )
+        .into_response()
+    };
+
+    let usage = api_keys::usage(pool, key.id, period)
+        .await
+        .map_err(internal)?;
+    let exhausted = if key.llm_token_quota.is_some_and(|q| usage.llm_tokens >= q) {
+        Some(format!(
+            "LLM token quota of {} for this month is used up",
+            key.llm_token_quota.unwrap_or_default()
+        ))
+    } else if key
+        .cpu_seconds_quota
+        .is_some_and(|q| usage.cpu_seconds >= q)
+    {
+        Some(format!(
+            "Distillation quota of {}s for this month is used up",
+            key.cpu_seconds_quota.unwrap_or_default()
+        ))
+    } else {
+        None
+    };
+    if let Some(message) = exhausted {
+        return Err(AppError::new(
+            StatusCode::PAYMENT_REQUIRED,
+            ApiError::new(
+                "quota_exceeded",
+                format!("{}; it resets at {}", message, resets_at.to_rfc3339()),
+            ),
+        )
+        .into_response());
+    }
+
+    match api_keys::charge_request(pool, key.id, period, key.request_quota)
+        .await
+        .map_err(internal)?
+    {
+        Some(usage) => Ok(usage),
+        None => {
+            let retry_after = (resets_at - now).num_seconds().max(1);
+            let mut response = AppError::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                ApiError::new(
+                    "rate_limited",
+                    format!(
+                        "Request quota of {} for this month is used up; it resets at {}",
+                        key.request_quota.unwrap_or_default(),
+                        resets_at.to_rfc3339()
+                    ),
+                ),
+            )
+            .into_response();
+            if let Ok(value) = HeaderValue::from_str(&retry_after.to_string()) {
+                response.headers_mut().insert(header::RETRY_AFTER, value);
+            }
+            Err(response)
+        }
+    }
+}
+
+/// Middleware metering API keys: authenticates `x-api-key` (the anonymous key
+/// when none is sent), enforces the key's monthly quotas and makes the key
+/// available to `charge_llm_tokens` and `charge_distill_seconds` while the
+/// request is handled.
+pub async fn enforce(State(pool): State<Arc<PgPool>>, request: Request, next: Next) -> Response {
+    let path = &versioning::unversioned(request.uri().path());
+    if !path.starts_with("/api/") || UNMETERED_PREFIXES.iter().any(|p| path.starts_with(p)) {
+        return next.run(request).await;
+    }
+
+    let key = match authenticate(&pool, request.headers()).await {
+        Ok(Some(key)) => key,
+        Ok(None) if keys_required() => {
+            return AppError::new(
+                StatusCode::UNAUTHORIZED,
+                ApiError::unauthorized(format!("An API key is required in the {} header", HEADER)),
+            )
+            .into_response()
+        }
+        Ok(None) => match anonymous_key(&pool).await {
+            Ok(key) => key,
+            Err(e) => return e.into_response(),
+        },
+        Err(e) => return e.into_response(),
+    };
+
+    let usage = match check_and_charge(&pool, &key).await {
+        Ok(usage) => usage,
+        Err(response) => return response,
+    };
+
+    let mut response = CURRENT_KEY.scope(key.id, next.run(request)).await;
+    if let Some(quota) = key.request_quota {
+        if let Ok(value) = HeaderValue::from_str(&(quota - usage.requests).max(0).to_string()) {
+            response
+                .headers_mut()
+                .insert("x-quota-requests-remaining", value);
+        }
+    }
+    response
+}
+
+/// Key of the request being handled. Streamed responses take it before the
+/// handler returns, since their bodies are sent outside the middleware.
+pub fn current_key() -> Option<i64> {
+    CURRENT_KEY.try_with(|id| *id).ok()
+}
+
+/// Run `future` with `key` as the key to charge, for work that outlives the
+/// request, such as a streamed response
+pub async fn with_key<F: Future>(key: Option<i64>, future: F) -> F::Output {
+    match key {
+        Some(key) => CURRENT_KEY.scope(key, future).await,
+        None => future.await,
+    }
+}
+
+async fn charge(pool: &PgPool, llm_tokens: i64, cpu_seconds: f64) {
+    let Ok(key_id) = CURRENT_KEY.try_with(|id| *id) else {
+        return;
+    };
+    if let Err(e) = api_keys::add_usage(
+        pool,
+        key_id,
+        period_start(Utc::now()),
+        llm_tokens,
+        cpu_seconds,
+    )
+    .await
+    {
+        warn!("Failed to record usage of API key {}: {}", key_id, e);
+    }
+}
+
+/// Charge LLM tokens to the key of the current request, if any
+pub async fn charge_llm_tokens(pool: &PgPool, tokens: i64) {
+    charge(pool, tokens, 0.0).await;
+}
+
+/// Charge distillation time to the key of the current request, if any
+pub async fn charge_distill_seconds(pool: &PgPool, seconds: f64) {
+    charge(pool, 0, seconds).await;
+}
@@ -0,0 +1,305 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Query, Request},
+    http::{header, HeaderMap, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use tracing::{info, warn};
+
+use crate::errors::AppError;
+use crate::services::design_rules::glob_match;
+use crate::types::ApiError;
+use crate::versioning;
+
+/// Bodies larger than this aren't inspected for a repository
+pub const MAX_INSPECTED_BODY_BYTES: usize = 1024 * 1024;
+
+// GitHub's limits on account and repository names
+const MAX_OWNER_LEN: usize = 39;
+const MAX_NAME_LEN: usize = 100;
+
+// Routes whose `repo` isn't a slug: admin accepts clone URLs (behind its own
+// token) and webhook payloads name the repository elsewhere. Both still pass
+// the check in front of every clone.
+const UNCHECKED_PREFIXES: &[&str] = &["/api/admin", "/api/hook"];
+
+/// Which repositories the instance will clone, read once from the environment:
+///
+/// - REPO_ALLOW: comma-separated "owner/name" patterns (`*` and `?` wildcards,
+///   ignoring case); when set, other repositories are refused
+/// - REPO_DENY: patterns that are refused even when allowed
+///
+/// Repository size is capped separately by GIT_MAX_CLONE_BYTES.
+#[derive(Debug, Clone, Default)]
+pub struct RepoPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl RepoPolicy {
+    fn from_env() -> Self {
+        let patterns = |var: &str| -> Vec<String> {
+            std::env::var(var)
+                .unwrap_or_default()
+                .split(',')
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect()
+        };
+        let policy = Self {
+            allow: patterns("REPO_ALLOW"),
+            deny: patterns("REPO_DENY"),
+        };
+        if !policy.allow.is_empty() || !policy.deny.is_empty() {
+            info!(
+                "Repository policy: {} allow and {} deny patterns",
+                policy.allow.len(),
+                policy.deny.len()
+            );
+        }
+        policy
+    }
+
+    /// Whether a valid slug passes the allow and deny patterns
+    pub fn permits(&self, slug: &str) -> bool {
+        let matches = |patterns: &[String]| patterns.iter().any(|p| glob_match(p, slug));
+        (self.allow.is_empty() || matches(&self.allow)) && !matches(&self.deny)
+    }
+}
+
+/// The instance's repository policy
+pub fn policy() -> &'static RepoPolicy {
+    static POLICY: OnceLock<RepoPolicy> = OnceLock::new();
+    POLICY.get_or_init(RepoPolicy::from_env)
+}
+
+/// A repository the instance won't clone: a malformed slug (400), one outside
+/// the allow/deny patterns (403) or one over the clone size cap (413)
+#[derive(Debug)]
+pub enum RepoRejected {
+    Invalid(String),
+    NotAllowed(String),
+    TooLarge(String),
+}
+
+impl std::fmt::Display for RepoRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid(message) | Self::NotAllowed(message) | Self::TooLarge(message) => {
+                write!(f, "{}", message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RepoRejected {}
+
+impl From<&RepoRejected> for AppError {
+    fn from(rejected: &RepoRejected) -> Self {
+        let (status, code) = match rejected {
+            RepoRejected::Invalid(_) => (StatusCode::BAD_REQUEST, "invalid_repo"),
+            RepoRejected::NotAllowed(_) => (StatusCode::FORBIDDEN, "repo_not_allowed"),
+            RepoRejected::TooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, "repo_too_large"),
+        };
+        AppError::new(status, ApiError::new(code, rejected.to_string()))
+    }
+}
+
+/// Check that `slug` is a plain GitHub "owner/name": letters, digits, `-`, `_`
+/// and `.` only, so it can't carry a URL, credentials, a port or a path
+/// outside the repository's cache directory
+pub fn validate_slug(slug: &str) -> Result<(), RepoRejected> {
+    let invalid = |why: String| {
+        RepoRejected::Invalid(format!(
+            "Invalid repository {:?}: {}; expected a GitHub \"owner/name\"",
+            slug, why
+        ))
+    };
+    let Some((owner, name)) = slug.split_once('/') else {
+        return Err(invalid("no owner".to_string()));
+    };
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    for (label, part, max_len) in [
+        ("owner", owner, MAX_OWNER_LEN),
+        ("name", name, MAX_NAME_LEN),
+    ] {
+        if part.is_empty() || part.len() > max_len {
+            return Err(invalid(format!(
+                "the {} must be 1 to {} characters",
+                label, max_len
+            )));
+        }
+        if !part.chars().all(allowed) {
+            return Err(invalid(format!(
+                "the {} may only contain letters, digits, '-', '_' and '.'",
+                label
+            )));
+        }
+        if part == "." || part == ".." || part.starts_with('-') {
+            return Err(invalid(format!("{:?} is not a valid {}", part, label)));
+        }
+    }
+    Ok(())
+}
+
+/// Refuse a repository that isn't a valid slug or that the policy excludes
+pub fn check(slug: &str) -> Result<(), RepoRejected> {
+    validate_slug(slug)?;
+    if !policy().permits(slug) {
+        return Err(RepoRejected::NotAllowed(format!(
+            "Repository {} is not allowed on this instance",
+            slug
+        )));
+    }
+    Ok(())
+}
+
+/// The `repo` query parameter of a request
+pub fn query_repo(uri: &Uri) -> Option<String> {
+    let Query(mut params) = Query::<HashMap<String, String>>::try_from_uri(uri).ok()?;
+    params.remove("repo")
+}
+
+/// Whether a request carries a JSON body
+pub fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"))
+}
+
+/// The `repo` field of a JSON body
+pub fn body_repo(bytes: &[u8]) -> Option<String> {
+    serde_json::from_slice::<serde_json::Value>(bytes)
+        .ok()?
+        .get("repo")?
+        .as_str()
+        .map(ToString::to_string)
+}
+
+/// Middleware refusing requests whose repository (the `repo` query parameter
+/// or the `repo` field of a JSON body) fails [`check`], before any handler
+/// records or clones it. Bodies declared larger than MAX_INSPECTED_BODY_BYTES
+/// are left to the route's limits and the check in front of every clone.
+pub async fn enforce(request: Request, next: Next) -> Response {
+    let path = &versioning::unversioned(request.uri().path());
+    if !path.starts_with("/api/") || UNCHECKED_PREFIXES.iter().any(|p| path.starts_with(p)) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    let mut repos: Vec<String> = query_repo(&parts.uri).into_iter().collect();
+    let declared_length = parts
+        .headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    let body = if is_json(&parts.headers)
+        && declared_length.is_none_or(|len| len <= MAX_INSPECTED_BODY_BYTES)
+    {
+        let Ok(bytes) = to_bytes(body, MAX_INSPECTED_BODY_BYTES).await else {
+            return AppError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                ApiError::new("payload_too_large", "Request body is too large".to_string()),
+            )
+            .into_response();
+        };
+        repos.extend(body_repo(&bytes));
+        Body::from(bytes)
+    } else {
+        body
+    };
+
+    for repo in &repos {
+        if let Err(rejected) = check(repo) {
+            warn!("Refused request for {:?}: {}", repo, rejected);
+            return AppError::from(&rejected).into_response();
+        }
+    }
+    next.run(Request::from_parts(parts, body)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_slug_accepts_github_names() {
+        for slug in [
+            "owner/repo",
+            "Some-Org/board.v2",
+            "a/b",
+            "user_1/.github",
+            "owner/repo-",
+        ] {
+            assert!(validate_slug(slug).is_ok(), "{} should be valid", slug);
+        }
+    }
+
+    #[test]
+    fn test_validate_slug_rejects_paths_and_urls() {
+        for slug in [
+            "",
+            "repo",
+            "/repo",
+            "owner/",
+            "../repo",
+            "owner/..",
+            "owner/.",
+            "./repo",
+            "owner/../../etc",
+            "owner/repo/extra",
+            "owner\\repo",
+            "owner/repo%2F..",
+            "owner%2Frepo",
+            "owner/%2e%2e",
+            "-owner/repo",
+            "owner/-repo",
+            "owner/repo name",
+            "owner/repo\n",
+            "owner/repo\0",
+            "user:pass@host/repo",
+            "https://github.com/owner/repo",
+            "owner/repo.git?x=1",
+            "owner/repo#frag",
+        ] {
+            assert!(
+                matches!(validate_slug(slug), Err(RepoRejected::Invalid(_))),
+                "{:?} should be rejected",
+                slug
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_slug_rejects_non_ascii() {
+        // Look-alikes of valid names: fullwidth and Cyrillic letters, a
+        // fraction slash, a combining accent and a zero-width space
+        for slug in [
+            "ｏwner/repo",
+            "оwner/repo",
+            "owner\u{2215}repo",
+            "owner/re\u{301}po",
+            "owner/repo\u{200b}",
+            "owner/rëpo",
+        ] {
+            assert!(
+                matches!(validate_slug(slug), Err(RepoRejected::Invalid(_))),
+                "{:?} should be rejected",
+                slug
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_slug_length_limits() {
+        let owner = "o".repeat(MAX_OWNER_LEN);
+        let name = "n".repeat(MAX_NAME_LEN);
+        assert!(validate_slug(&format!("{}/{}", owner, name)).is_ok());
+        assert!(validate_slug(&format!("{}o/{}", owner, name)).is_err());
+        assert!(validate_slug(&format!("{}/{}n", owner, name)).is_err());
+    }
+}
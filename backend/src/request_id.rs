@@ -0,0 +1,90 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Header carrying the request id, in both directions
+pub const HEADER: &str = "x-request-id";
+
+// Longest client-supplied id that is passed through; longer ones are replaced
+const MAX_LEN: usize = 128;
+
+// Error bodies larger than this are passed through untouched
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// The id of the current request, stored in the request extensions
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// Keep a client-supplied id if it is short printable ASCII, otherwise generate one
+fn request_id(request: &Request) -> String {
+    request
+        .headers()
+        .get(HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|id| {
+            !id.is_empty() && id.len() <= MAX_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map(ToString::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+/// Add `request_id` to a JSON `ApiError` body. Other bodies come back unchanged.
+async fn with_request_id(response: Response, id: &str) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, MAX_ERROR_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!("Failed to read error body for request {}: {}", id, e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let body = match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(mut error)) if error.contains_key("error") => {
+            error.insert("request_id".to_string(), Value::String(id.to_string()));
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Body::from(serde_json::to_vec(&error).unwrap_or_else(|_| bytes.to_vec()))
+        }
+        _ => Body::from(bytes),
+    };
+    Response::from_parts(parts, body)
+}
+
+/// Middleware giving every request an id: taken from `x-request-id` or
+/// generated, recorded in the request span (see `server::trace_layer`),
+/// echoed in the response header and added to JSON error bodies, so a
+/// reported error can be matched with its logs.
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+    let id = request_id(&request);
+    // Validated above, so the id is a valid header value
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        request.headers_mut().insert(HEADER, value);
+    }
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let mut response = next.run(request).await;
+    if response.status().is_client_error() || response.status().is_server_error() {
+        response = with_request_id(response, &id).await;
+    }
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(HEADER, value);
+    }
+    response
+}
@@ -0,0 +1,42 @@
+use axum::{
+    routing::{delete, get, post, put},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::controllers::admin::{
+    create_api_key, delete_blob, delete_credentials, delete_secret, get_config, get_log_filter,
+    list_api_keys, list_audit, list_credentials, list_failed_jobs, list_quarantine, list_repos,
+    list_secrets, overview, refresh_stale_summaries, release_quarantine, rename_repo, retry_job,
+    retry_jobs, revoke_api_key, rotate_secrets, set_api_key_quotas, set_credentials,
+    set_log_filter, set_repo_commit_status, set_secret, storage,
+};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/overview", get(overview))
+        .route("/summaries/refresh", post(refresh_stale_summaries))
+        .route("/repos", get(list_repos))
+        .route("/repos/rename", post(rename_repo))
+        .route("/repos/commit-status", put(set_repo_commit_status))
+        .route("/storage", get(storage))
+        .route("/blobs/*key", delete(delete_blob))
+        .route("/audit", get(list_audit))
+        .route("/config", get(get_config))
+        .route("/log-filter", get(get_log_filter).put(set_log_filter))
+        .route("/jobs/failed", get(list_failed_jobs))
+        .route("/jobs/retry", post(retry_jobs))
+        .route("/jobs/:id/retry", post(retry_job))
+        .route("/quarantine", get(list_quarantine))
+        .route("/quarantine/release", post(release_quarantine))
+        .route("/keys", get(list_api_keys).post(create_api_key))
+        .route("/keys/:id", put(set_api_key_quotas).delete(revoke_api_key))
+        .route("/secrets", get(list_secrets))
+        .route("/secrets/rotate", post(rotate_secrets))
+        .route("/secrets/:name", put(set_secret).delete(delete_secret))
+        .route("/credentials", get(list_credentials))
+        .route(
+            "/credentials/:provider",
+            put(set_credentials).delete(delete_credentials),
+        )
+}
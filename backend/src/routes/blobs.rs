@@ -0,0 +1,13 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::controllers::blobs::{get_blob_file, list_blobs};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/list", post(list_blobs))
+        .route("/file/*key", get(get_blob_file))
+}
@@ -0,0 +1,14 @@
+use axum::{routing::post, Router};
+use std::sync::Arc;
+
+use crate::controllers::bom::{
+    add_part_alternate, get_bom, list_part_alternates, remove_part_alternate,
+};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/", post(get_bom))
+        .route("/alternates", post(add_part_alternate))
+        .route("/alternates/list", post(list_part_alternates))
+        .route("/alternates/remove", post(remove_part_alternate))
+}
@@ -0,0 +1,8 @@
+use axum::{routing::post, Router};
+use std::sync::Arc;
+
+use crate::controllers::ci::get_verdict;
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new().route("/verdict", post(get_verdict))
+}
@@ -0,0 +1,12 @@
+use axum::{routing::post, Router};
+use std::sync::Arc;
+
+use crate::controllers::design_rules::{check_rules, delete_rule, list_rules, set_rule};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/", post(set_rule))
+        .route("/list", post(list_rules))
+        .route("/delete", post(delete_rule))
+        .route("/check", post(check_rules))
+}
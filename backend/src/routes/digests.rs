@@ -0,0 +1,14 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::controllers::digests::{confirm, subscribe, unsubscribe};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/subscribe", post(subscribe))
+        .route("/confirm", get(confirm))
+        .route("/unsubscribe", post(unsubscribe))
+}
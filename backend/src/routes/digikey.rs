@@ -4,10 +4,15 @@ use axum::{
 };
 use std::sync::Arc;
 
-use crate::controllers::digikey::{get_status, search_parts};
+use crate::controllers::digikey::{enrich_parts, get_status, search_parts};
 
 pub fn router() -> Router<Arc<sqlx::PgPool>> {
     Router::new()
         .route("/search", post(search_parts))
         .route("/status", get(get_status))
 }
+
+/// Enrichment, which distills the schematic and looks up every part
+pub fn enrich_router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new().route("/enrich", post(enrich_parts))
+}
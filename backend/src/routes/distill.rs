@@ -1,8 +1,10 @@
 use axum::{routing::post, Router};
 use std::sync::Arc;
 
-use crate::controllers::distill::distill_schematics;
+use crate::controllers::distill::{distill_schematics, distill_sheet};
 
 pub fn router() -> Router<Arc<sqlx::PgPool>> {
-    Router::new().route("/", post(distill_schematics))
+    Router::new()
+        .route("/", post(distill_schematics))
+        .route("/sheet", post(distill_sheet))
 }
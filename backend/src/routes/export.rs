@@ -0,0 +1,8 @@
+use axum::{routing::post, Router};
+use std::sync::Arc;
+
+use crate::controllers::export::export_design;
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new().route("/design", post(export_design))
+}
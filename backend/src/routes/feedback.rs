@@ -0,0 +1,13 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::controllers::feedback::{export_summary_feedback, submit_summary_feedback};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/summary", post(submit_summary_feedback))
+        .route("/summary/export", get(export_summary_feedback))
+}
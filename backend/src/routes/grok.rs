@@ -5,7 +5,9 @@ use axum::{
 use std::sync::Arc;
 
 use crate::controllers::grok::{
-    chat_stream, find_replacement, selection_stream, summarize_commit, summarize_repo, summarize_selection,
+    ask_repo, chat_stream, explain_net, export_session, find_replacement, generate_release_notes,
+    get_release_notes, list_release_notes, selection_stream, start_session, summarize_commit,
+    summarize_repo, summarize_selection,
 };
 
 pub fn router() -> Router<Arc<sqlx::PgPool>> {
@@ -15,5 +17,12 @@ pub fn router() -> Router<Arc<sqlx::PgPool>> {
         .route("/summary/repo", post(summarize_repo))
         .route("/obsolete/replacement", post(find_replacement))
         .route("/chat/stream", get(chat_stream))
+        .route("/session", post(start_session))
+        .route("/sessions/:id/export", get(export_session))
         .route("/selection/stream", post(selection_stream))
+        .route("/ask/repo", post(ask_repo))
+        .route("/explain/net", post(explain_net))
+        .route("/release-notes", post(generate_release_notes))
+        .route("/release-notes/list", post(list_release_notes))
+        .route("/release-notes/:id", get(get_release_notes))
 }
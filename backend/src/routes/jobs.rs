@@ -0,0 +1,8 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use crate::controllers::jobs::get_job;
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new().route("/:id", get(get_job))
+}
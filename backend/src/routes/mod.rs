@@ -1,5 +1,70 @@
+pub mod admin;
+pub mod blobs;
+pub mod bom;
+pub mod ci;
+pub mod design_rules;
+pub mod digests;
 pub mod digikey;
 pub mod distill;
+pub mod export;
+pub mod feedback;
 pub mod grok;
 pub mod hook;
+pub mod jobs;
+pub mod presence;
+pub mod provenance;
 pub mod repo;
+pub mod report;
+pub mod sync;
+pub mod usage;
+pub mod views;
+
+use axum::Router;
+use std::sync::Arc;
+
+use crate::limits::RouteLimits;
+
+/// Every route group of the API, under its path below the version prefix and
+/// with its limits. Both the current version and the deprecated unversioned
+/// paths are served from this one list (see `versioning`), so they can't
+/// drift apart.
+pub fn api() -> Router<Arc<sqlx::PgPool>> {
+    // Timeouts and body size limits per route group
+    let standard = RouteLimits::standard();
+    let heavy = RouteLimits::heavy();
+    let llm = RouteLimits::llm();
+    let webhook = RouteLimits::webhook();
+    let upload = RouteLimits::upload();
+
+    Router::new()
+        .nest(
+            "/repo",
+            heavy
+                .apply(repo::router())
+                .merge(upload.apply(repo::upload_router())),
+        )
+        .nest("/hook", webhook.apply(hook::router()))
+        .nest("/grok", llm.apply(grok::router()))
+        .nest("/distill", heavy.apply(distill::router()))
+        .nest(
+            "/digikey",
+            standard
+                .apply(digikey::router())
+                .merge(heavy.apply(digikey::enrich_router())),
+        )
+        .nest("/bom", heavy.apply(bom::router()))
+        .nest("/ci", heavy.apply(ci::router()))
+        .nest("/rules", heavy.apply(design_rules::router()))
+        .nest("/report", heavy.apply(report::router()))
+        .nest("/export", heavy.apply(export::router()))
+        .nest("/admin", standard.apply(admin::router()))
+        .nest("/jobs", standard.apply(jobs::router()))
+        .nest("/blobs", standard.apply(blobs::router()))
+        .nest("/feedback", standard.apply(feedback::router()))
+        .nest("/views", standard.apply(views::router()))
+        .nest("/presence", standard.apply(presence::router()))
+        .nest("/digests", standard.apply(digests::router()))
+        .nest("/usage", standard.apply(usage::router()))
+        .nest("/sync", standard.apply(sync::router()))
+        .nest("/provenance", standard.apply(provenance::router()))
+}
@@ -0,0 +1,10 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use crate::controllers::presence::{list_viewers, presence_socket};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/", get(list_viewers))
+        .route("/ws", get(presence_socket))
+}
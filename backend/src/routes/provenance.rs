@@ -0,0 +1,10 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use crate::controllers::provenance::{get_record, list_records};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/", get(list_records))
+        .route("/:id", get(get_record))
+}
@@ -1,13 +1,52 @@
-use axum::{routing::post, Router};
+use axum::{
+    routing::{get, patch, post},
+    Router,
+};
 use std::sync::Arc;
 
-use crate::controllers::repo::{clear_cache, get_commit_files, get_commit_info, get_commits, init_repo};
+use crate::controllers::repo::{
+    add_commit_comment, check_decoupling, check_footprints, check_symbols, clear_cache,
+    commit_impact, compare_values, diff_bom, edit_commit_overview, export_pinmap, get_commit_files,
+    get_commit_info, get_commits, get_harness, get_schematic_image, get_suggestions,
+    get_summary_card, get_xref, init_repo, list_commit_comments, list_component_notes,
+    list_releases, list_stored, metrics_history, review_commit_overview, search_components,
+    set_component_note, set_sheet_meta, test_point_coverage, upload_repo,
+};
 
 pub fn router() -> Router<Arc<sqlx::PgPool>> {
     Router::new()
         .route("/commits", post(get_commits))
         .route("/commit/files", post(get_commit_files))
         .route("/commit/info", post(get_commit_info))
+        .route("/commit/overview", patch(edit_commit_overview))
+        .route("/commit/overview/review", post(review_commit_overview))
+        .route("/commit/comments", post(add_commit_comment))
+        .route("/commit/comments/list", post(list_commit_comments))
+        .route("/commit/image", get(get_schematic_image))
+        .route("/commit/footprint-check", post(check_footprints))
+        .route("/commit/symbol-check", post(check_symbols))
+        .route("/commit/decoupling-check", post(check_decoupling))
+        .route("/commit/test-point-coverage", post(test_point_coverage))
+        .route("/commit/xref", post(get_xref))
+        .route("/commit/impact", post(commit_impact))
+        .route("/commit/pinmap", post(export_pinmap))
+        .route("/commit/harness", post(get_harness))
+        .route("/compare/values", post(compare_values))
+        .route("/bom/diff", post(diff_bom))
+        .route("/sheet/meta", post(set_sheet_meta))
+        .route("/component/note", patch(set_component_note))
+        .route("/component/notes", get(list_component_notes))
         .route("/init", post(init_repo))
         .route("/clear-cache", post(clear_cache))
+        .route("/releases", post(list_releases))
+        .route("/stored", post(list_stored))
+        .route("/summary-card", get(get_summary_card))
+        .route("/suggestions", get(get_suggestions))
+        .route("/search/components", post(search_components))
+        .route("/metrics/history", post(metrics_history))
+}
+
+/// Repository uploads of a mirror-mode instance, which take much larger bodies
+pub fn upload_router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new().route("/upload", post(upload_repo))
 }
@@ -0,0 +1,8 @@
+use axum::{routing::post, Router};
+use std::sync::Arc;
+
+use crate::controllers::report::{commit_report, commit_report_page};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new().route("/commit", post(commit_report).get(commit_report_page))
+}
@@ -0,0 +1,8 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use crate::controllers::sync::list_changes;
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new().route("/changes", get(list_changes))
+}
@@ -0,0 +1,8 @@
+use axum::{routing::get, Router};
+use std::sync::Arc;
+
+use crate::controllers::usage::get_usage;
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new().route("/", get(get_usage))
+}
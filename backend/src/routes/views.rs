@@ -0,0 +1,14 @@
+use axum::{
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+use crate::controllers::views::{create_view, delete_view, get_view, list_views};
+
+pub fn router() -> Router<Arc<sqlx::PgPool>> {
+    Router::new()
+        .route("/", post(create_view))
+        .route("/list", post(list_views))
+        .route("/:id", get(get_view).delete(delete_view))
+}
@@ -0,0 +1,355 @@
+use serde::Serialize;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::controllers::admin::is_default_admin_token;
+use crate::server::ServerConfig;
+use crate::services::blob_store::BlobStore;
+use crate::services::disk;
+use crate::services::distill::{get_distiller_path, get_python_path};
+use crate::services::distiller::BackendChoice;
+use kicad_db::secrets::{Keyring, SecretError};
+use kicad_db::xai_client::XaiClient;
+use kicad_db::{schema, PgPool};
+
+pub const STATUS_OK: &str = "ok";
+/// Works, but a feature is off or degraded
+pub const STATUS_WARN: &str = "warn";
+pub const STATUS_FAIL: &str = "fail";
+
+/// How long a check that talks to another service may take
+const CHECK_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: &'static str,
+    pub detail: String,
+}
+
+/// Outcome of `kicad-backend --check`
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// False when any check failed
+    pub ok: bool,
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    fn push(&mut self, name: &'static str, status: &'static str, detail: impl Into<String>) {
+        self.ok &= status != STATUS_FAIL;
+        self.checks.push(Check {
+            name,
+            status,
+            detail: detail.into(),
+        });
+    }
+}
+
+async fn timed<T>(future: impl Future<Output = T>) -> Option<T> {
+    tokio::time::timeout(CHECK_TIMEOUT, future).await.ok()
+}
+
+/// Create, write and remove a file in `dir`
+async fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    let probe = dir.join(format!(".grokicad-check-{}", std::process::id()));
+    tokio::fs::write(&probe, b"check").await?;
+    tokio::fs::remove_file(&probe).await
+}
+
+fn check_config(report: &mut Report) {
+    match ServerConfig::from_env() {
+        Ok(config) => {
+            let mut listeners = Vec::new();
+            if let Some(port) = config.http_port {
+                listeners.push(format!("HTTP on {}:{}", config.bind_addr, port));
+            }
+            if let Some(tls) = &config.tls {
+                listeners.push(format!("HTTPS on {}:{}", config.bind_addr, tls.port));
+                for path in [&tls.cert_path, &tls.key_path] {
+                    if !path.is_file() {
+                        report.push(
+                            "config.listener",
+                            STATUS_FAIL,
+                            format!("{} is not a readable file", path.display()),
+                        );
+                        return;
+                    }
+                }
+            }
+            report.push("config.listener", STATUS_OK, listeners.join(", "));
+        }
+        Err(e) => report.push("config.listener", STATUS_FAIL, format!("{:#}", e)),
+    }
+
+    let backend = std::env::var("DISTILLER_BACKEND").unwrap_or_default();
+    if backend.trim().is_empty() || BackendChoice::from_name(&backend).is_some() {
+        let name = BackendChoice::configured().name();
+        report.push("config.distiller_backend", STATUS_OK, name);
+    } else {
+        report.push(
+            "config.distiller_backend",
+            STATUS_FAIL,
+            format!(
+                "Unknown DISTILLER_BACKEND {:?} (expected one of {})",
+                backend,
+                BackendChoice::NAMES.join(", ")
+            ),
+        );
+    }
+
+    match Keyring::from_env() {
+        Ok(keyring) => report.push(
+            "config.credentials_key",
+            STATUS_OK,
+            format!(
+                "valid (key {}, {} previous)",
+                keyring.active_key_id(),
+                keyring.previous_key_ids().len()
+            ),
+        ),
+        Err(SecretError::NotConfigured) => report.push(
+            "config.credentials_key",
+            STATUS_WARN,
+            "SECRETS_ENCRYPTION_KEY is not set; supplier credentials can't be stored",
+        ),
+        Err(e) => report.push("config.credentials_key", STATUS_FAIL, e.to_string()),
+    }
+
+    let admin_token = std::env::var("ADMIN_TOKEN").unwrap_or_default();
+    if is_default_admin_token(&admin_token) {
+        report.push(
+            "config.admin_token",
+            STATUS_FAIL,
+            "ADMIN_TOKEN is a well-known placeholder; the server won't start",
+        );
+    } else if !admin_token.trim().is_empty() {
+        report.push("config.admin_token", STATUS_OK, "set");
+    } else {
+        report.push(
+            "config.admin_token",
+            STATUS_WARN,
+            "ADMIN_TOKEN is not set; admin endpoints are disabled",
+        );
+    }
+}
+
+async fn check_database(report: &mut Report) {
+    let connected = match timed(kicad_db::create_pool()).await {
+        Some(Ok(pool)) => Ok(pool),
+        Some(Err(e)) => Err(format!("Failed to connect: {}", e)),
+        None => Err("Timed out connecting".to_string()),
+    };
+    let pool: PgPool = match connected {
+        Ok(pool) => pool,
+        Err(detail) => {
+            report.push("database", STATUS_FAIL, detail);
+            report.push(
+                "database.schema",
+                STATUS_FAIL,
+                "Not checked without a database",
+            );
+            return;
+        }
+    };
+    match timed(sqlx::query_scalar::<_, String>("SELECT version()").fetch_one(&pool)).await {
+        Some(Ok(version)) => report.push("database", STATUS_OK, version),
+        Some(Err(e)) => report.push("database", STATUS_FAIL, format!("Query failed: {}", e)),
+        None => report.push("database", STATUS_FAIL, "Timed out querying"),
+    }
+
+    match timed(schema::missing_columns(&pool)).await {
+        Some(Ok(missing)) if missing.is_empty() => {
+            report.push("database.schema", STATUS_OK, "Up to date with init.sql")
+        }
+        Some(Ok(missing)) => report.push(
+            "database.schema",
+            STATUS_FAIL,
+            format!(
+                "{} column(s) missing, apply database/init.sql: {}",
+                missing.len(),
+                missing.join(", ")
+            ),
+        ),
+        Some(Err(e)) => report.push(
+            "database.schema",
+            STATUS_FAIL,
+            format!("Failed to read the schema: {}", e),
+        ),
+        None => report.push(
+            "database.schema",
+            STATUS_FAIL,
+            "Timed out reading the schema",
+        ),
+    }
+
+    if std::env::var("DATABASE_READ_URL").is_ok_and(|url| !url.trim().is_empty()) {
+        match timed(kicad_db::init_read_pool()).await {
+            Some(Ok(_)) => report.push("database.replica", STATUS_OK, "Connected"),
+            Some(Err(e)) => report.push(
+                "database.replica",
+                STATUS_FAIL,
+                format!("Failed to connect: {}", e),
+            ),
+            None => report.push("database.replica", STATUS_FAIL, "Timed out connecting"),
+        }
+    }
+}
+
+async fn check_distiller(report: &mut Report) {
+    if BackendChoice::configured() == BackendChoice::Native {
+        report.push("distiller", STATUS_OK, "Native backend; Python isn't used");
+        return;
+    }
+    let python = get_python_path();
+    if !python.exists() {
+        report.push(
+            "distiller",
+            STATUS_FAIL,
+            format!(
+                "Python venv not found at {}; run setup_venv.sh",
+                python.display()
+            ),
+        );
+        return;
+    }
+    let output = Command::new(&python)
+        .arg("-c")
+        .arg("import kicad_sch_api; print(kicad_sch_api.__version__)")
+        .current_dir(get_distiller_path())
+        .kill_on_drop(true)
+        .output();
+    match timed(output).await {
+        Some(Ok(output)) if output.status.success() => report.push(
+            "distiller",
+            STATUS_OK,
+            format!(
+                "kicad_sch_api {} in {}",
+                String::from_utf8_lossy(&output.stdout).trim(),
+                get_distiller_path().display()
+            ),
+        ),
+        Some(Ok(output)) => report.push(
+            "distiller",
+            STATUS_FAIL,
+            format!(
+                "Failed to import kicad_sch_api: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ),
+        Some(Err(e)) => report.push(
+            "distiller",
+            STATUS_FAIL,
+            format!("Failed to run {}: {}", python.display(), e),
+        ),
+        None => report.push("distiller", STATUS_FAIL, "Timed out starting Python"),
+    }
+}
+
+async fn check_xai(report: &mut Report) {
+    let client = match XaiClient::new() {
+        Ok(client) => client,
+        Err(_) => {
+            report.push(
+                "xai",
+                STATUS_WARN,
+                "XAI_API_KEY is not set; Grok features will fail",
+            );
+            return;
+        }
+    };
+    let (accepted, timed_out) = match client.local_endpoint() {
+        Some(local) => (
+            format!("On-prem LLM server {} reachable", local.base_url),
+            format!("Timed out reaching the LLM server {}", local.base_url),
+        ),
+        None => (
+            "API key accepted".to_string(),
+            "Timed out reaching the xAI API".to_string(),
+        ),
+    };
+    match timed(client.verify_api_key()).await {
+        Some(Ok(())) => report.push("xai", STATUS_OK, accepted),
+        Some(Err(e)) => report.push("xai", STATUS_FAIL, e.to_string()),
+        None => report.push("xai", STATUS_FAIL, timed_out),
+    }
+}
+
+async fn check_storage(report: &mut Report) {
+    let work_dir = disk::work_dir();
+    if let Err(e) = probe_writable(&work_dir).await {
+        report.push(
+            "storage.work_dir",
+            STATUS_FAIL,
+            format!("{} is not writable: {}", work_dir.display(), e),
+        );
+    } else {
+        match disk::space().await {
+            Ok(space) => {
+                let status = match space.status(&disk::DiskThresholds::from_env()) {
+                    disk::STATUS_CRITICAL => STATUS_FAIL,
+                    disk::STATUS_LOW => STATUS_WARN,
+                    _ => STATUS_OK,
+                };
+                report.push(
+                    "storage.work_dir",
+                    status,
+                    format!(
+                        "{}: {} MiB ({:.1}%) free",
+                        work_dir.display(),
+                        space.available_bytes / (1024 * 1024),
+                        space.available_percent()
+                    ),
+                );
+            }
+            Err(e) => report.push(
+                "storage.work_dir",
+                STATUS_WARN,
+                format!(
+                    "{} is writable; free space unknown: {:#}",
+                    work_dir.display(),
+                    e
+                ),
+            ),
+        }
+    }
+
+    match BlobStore::from_env() {
+        Ok(store) => match store.local_dir() {
+            Some(dir) => match probe_writable(dir).await {
+                Ok(()) => report.push(
+                    "storage.blobs",
+                    STATUS_OK,
+                    format!("local: {}", dir.display()),
+                ),
+                Err(e) => report.push(
+                    "storage.blobs",
+                    STATUS_FAIL,
+                    format!("{} is not writable: {}", dir.display(), e),
+                ),
+            },
+            None => report.push("storage.blobs", STATUS_OK, store.name()),
+        },
+        Err(e) => report.push("storage.blobs", STATUS_FAIL, format!("{:#}", e)),
+    }
+}
+
+/// Check that this deployment can serve: configuration, the database and its
+/// schema, the distiller, the xAI key and the directories it writes to.
+/// Run by `kicad-backend --check`, e.g. in a deploy pipeline before traffic
+/// is switched over.
+pub async fn run() -> Report {
+    let mut report = Report {
+        ok: true,
+        checks: Vec::new(),
+    };
+    check_config(&mut report);
+    check_database(&mut report).await;
+    check_distiller(&mut report).await;
+    check_xai(&mut report).await;
+    check_storage(&mut report).await;
+    report
+}
@@ -0,0 +1,381 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{ConnectInfo, Request},
+    http::HeaderMap,
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use kicad_db::PgPool;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{Any, CorsLayer};
+use tower_http::trace::TraceLayer;
+use tracing::{info, info_span, warn, Span};
+
+use crate::{demo, errors, quota, repo_policy, request_id};
+
+/// Listener configuration, read from the environment.
+///
+/// - `BIND_ADDR`: interface to listen on (default 0.0.0.0)
+/// - `PORT`: plain HTTP port (default 8080, or disabled when TLS is on and PORT is unset;
+///   `PORT=0` always disables it)
+/// - `TLS_CERT_PATH` / `TLS_KEY_PATH`: PEM certificate chain and private key; enables HTTPS
+/// - `TLS_PORT`: HTTPS port (default 443)
+/// - `TRUST_PROXY`: take the client address from `X-Forwarded-For` / `X-Real-IP` / `CF-Connecting-IP`
+///   (set when running behind nginx or Cloudflare)
+/// - `TRUSTED_PROXY_HOPS`: proxies in front of the server that append to `X-Forwarded-For`
+///   (default 1); the client is the entry that many places from the right
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind_addr: IpAddr,
+    pub http_port: Option<u16>,
+    pub tls: Option<TlsSettings>,
+    /// Proxies whose forwarding headers are trusted; 0 when TRUST_PROXY is off
+    pub proxy_hops: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    pub port: u16,
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+fn env_flag(name: &str) -> bool {
+    std::env::var(name)
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+fn env_port(name: &str) -> Result<Option<u16>> {
+    match std::env::var(name) {
+        Ok(v) if !v.is_empty() => {
+            let port = v
+                .parse::<u16>()
+                .with_context(|| format!("{} must be a port number, got {:?}", name, v))?;
+            Ok(Some(port))
+        }
+        _ => Ok(None),
+    }
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Result<Self> {
+        let bind_addr = match std::env::var("BIND_ADDR") {
+            Ok(v) if !v.is_empty() => v
+                .parse()
+                .with_context(|| format!("BIND_ADDR must be an IP address, got {:?}", v))?,
+            _ => IpAddr::from([0, 0, 0, 0]),
+        };
+
+        let cert_path = std::env::var("TLS_CERT_PATH")
+            .ok()
+            .filter(|v| !v.is_empty());
+        let key_path = std::env::var("TLS_KEY_PATH").ok().filter(|v| !v.is_empty());
+        let tls = match (cert_path, key_path) {
+            (Some(cert_path), Some(key_path)) => Some(TlsSettings {
+                port: env_port("TLS_PORT")?.unwrap_or(443),
+                cert_path: PathBuf::from(cert_path),
+                key_path: PathBuf::from(key_path),
+            }),
+            (None, None) => None,
+            _ => anyhow::bail!("TLS_CERT_PATH and TLS_KEY_PATH must be set together"),
+        };
+
+        // Plain HTTP stays on by default unless TLS is configured without an explicit PORT
+        let http_port = match env_port("PORT")? {
+            Some(0) => None,
+            Some(port) => Some(port),
+            None if tls.is_some() => None,
+            None => Some(8080),
+        };
+
+        if http_port.is_none() && tls.is_none() {
+            anyhow::bail!("No listener configured: set PORT or TLS_CERT_PATH/TLS_KEY_PATH");
+        }
+
+        let proxy_hops = if env_flag("TRUST_PROXY") {
+            match std::env::var("TRUSTED_PROXY_HOPS") {
+                Ok(v) if !v.is_empty() => {
+                    v.parse::<usize>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .with_context(|| {
+                            format!("TRUSTED_PROXY_HOPS must be a positive number, got {:?}", v)
+                        })?
+                }
+                _ => 1,
+            }
+        } else {
+            0
+        };
+
+        Ok(Self {
+            bind_addr,
+            http_port,
+            tls,
+            proxy_hops,
+        })
+    }
+}
+
+/// The `X-Forwarded-For` entry added by the outermost of `proxy_hops` trusted
+/// proxies. Entries left of it were sent by the client and can't be trusted.
+fn forwarded_for(value: &str, proxy_hops: usize) -> Option<&str> {
+    let entries: Vec<&str> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .collect();
+    entries
+        .len()
+        .checked_sub(proxy_hops)
+        .and_then(|i| entries.get(i))
+        .or(entries.first())
+        .copied()
+}
+
+/// The client address for a request.
+///
+/// Behind `proxy_hops` trusted proxies, the `X-Forwarded-For` entry the outermost
+/// one added, `X-Real-IP` or `CF-Connecting-IP` is used, in that order;
+/// these headers are client-controlled, so only trust them behind a proxy that sets them.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>, proxy_hops: usize) -> String {
+    if proxy_hops > 0 {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        let forwarded = header("x-forwarded-for")
+            .and_then(|v| forwarded_for(v, proxy_hops))
+            .or_else(|| header("x-real-ip"))
+            .or_else(|| header("cf-connecting-ip"))
+            .map(str::trim)
+            .filter(|v| !v.is_empty());
+        if let Some(ip) = forwarded {
+            return ip.to_string();
+        }
+    }
+    peer.map(|p| p.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Request tracing that records the request id, real client address and original scheme.
+/// Runs inside `request_id::propagate`, which sets the id header.
+pub fn trace_layer(
+    proxy_hops: usize,
+) -> TraceLayer<
+    tower_http::classify::SharedClassifier<tower_http::classify::ServerErrorsAsFailures>,
+    impl Fn(&Request) -> Span + Clone,
+> {
+    TraceLayer::new_for_http().make_span_with(move |request: &Request| {
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| *addr);
+        let headers = request.headers();
+        let scheme = if proxy_hops > 0 {
+            headers
+                .get("x-forwarded-proto")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("http")
+        } else {
+            "http"
+        };
+        let request_id = headers
+            .get(request_id::HEADER)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("-");
+        info_span!(
+            "request",
+            request_id = %request_id,
+            method = %request.method(),
+            uri = %request.uri(),
+            client = %client_ip(headers, peer, proxy_hops),
+            scheme = %scheme,
+        )
+    })
+}
+
+/// Wrap the API routes in the middleware every request passes through,
+/// innermost first
+pub fn with_middleware(
+    router: Router<Arc<PgPool>>,
+    state: Arc<PgPool>,
+    proxy_hops: usize,
+) -> Router<Arc<PgPool>> {
+    // Configure CORS to allow requests from the frontend domain
+    // Note: If you want to restrict to specific origins, use:
+    // .allow_origin("https://grokicad.com".parse::<HeaderValue>().unwrap())
+    let cors = CorsLayer::new()
+        .allow_origin(Any) // Allow all origins in production (can be restricted to specific domains)
+        .allow_methods(Any)
+        .allow_headers(Any)
+        .expose_headers(Any)
+        .allow_credentials(false) // Set to true if you need to send cookies/auth headers
+        .max_age(std::time::Duration::from_secs(3600));
+
+    router
+        // Per-key quotas, checked before the route's own limits
+        .layer(axum::middleware::from_fn_with_state(state, quota::enforce))
+        // Demo instances: repo allow-list, blocked routes and per-client rate limit
+        .layer(axum::middleware::from_fn_with_state(
+            proxy_hops,
+            demo::enforce,
+        ))
+        // Malformed or disallowed repositories, refused before anything else sees them
+        .layer(axum::middleware::from_fn(repo_policy::enforce))
+        .layer(trace_layer(proxy_hops))
+        .layer(axum::middleware::from_fn(request_id::propagate))
+        // The same error body for every failure, as problem+json when asked for
+        .layer(axum::middleware::from_fn(errors::envelope))
+        // Outside the two above, which read and rewrite error bodies, so they
+        // see them before they are compressed
+        .layer(CompressionLayer::new())
+        .layer(cors)
+}
+
+/// Reload the certificate and key whenever the process receives SIGHUP,
+/// so renewed certificates are picked up without a restart
+#[cfg(unix)]
+fn reload_on_sighup(config: RustlsConfig, tls: TlsSettings) {
+    tokio::spawn(async move {
+        let mut hangups =
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+                Ok(signal) => signal,
+                Err(e) => {
+                    warn!("Cannot listen for SIGHUP, TLS reload disabled: {}", e);
+                    return;
+                }
+            };
+        while hangups.recv().await.is_some() {
+            match config
+                .reload_from_pem_file(&tls.cert_path, &tls.key_path)
+                .await
+            {
+                Ok(()) => info!("Reloaded TLS certificate from {}", tls.cert_path.display()),
+                Err(e) => warn!("Failed to reload TLS certificate: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn reload_on_sighup(_config: RustlsConfig, _tls: TlsSettings) {}
+
+/// Serve the app on the configured HTTP and/or HTTPS listeners until one of them stops
+pub async fn serve(app: Router, config: ServerConfig) -> Result<()> {
+    let mut listeners = tokio::task::JoinSet::new();
+
+    if let Some(port) = config.http_port {
+        let addr = SocketAddr::new(config.bind_addr, port);
+        let service = app
+            .clone()
+            .into_make_service_with_connect_info::<SocketAddr>();
+        info!("HTTP listening on {}", addr);
+        listeners.spawn(async move {
+            axum_server::bind(addr)
+                .serve(service)
+                .await
+                .with_context(|| format!("HTTP listener on {} failed", addr))
+        });
+    }
+
+    if let Some(tls) = config.tls {
+        // Only the ring provider is compiled in; make it the process default
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        let rustls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path)
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load TLS certificate {} / key {}",
+                    tls.cert_path.display(),
+                    tls.key_path.display()
+                )
+            })?;
+        reload_on_sighup(rustls_config.clone(), tls.clone());
+
+        let addr = SocketAddr::new(config.bind_addr, tls.port);
+        let service = app.into_make_service_with_connect_info::<SocketAddr>();
+        info!("HTTPS listening on {}", addr);
+        listeners.spawn(async move {
+            axum_server::bind_rustls(addr, rustls_config)
+                .serve(service)
+                .await
+                .with_context(|| format!("HTTPS listener on {} failed", addr))
+        });
+    }
+
+    // Listeners only return on failure; stop the process if either does
+    match listeners.join_next().await {
+        Some(result) => result.context("Listener task panicked")?,
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, StatusCode};
+    use axum::routing::get;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_error_envelope_inside_compression() {
+        let pool = Arc::new(PgPool::connect_lazy("postgres://localhost/unused").unwrap());
+        // Under an unmetered prefix, so the quota middleware needs no database
+        let routes = Router::new().route(
+            "/api/usage/fails",
+            get(|| async { (StatusCode::BAD_REQUEST, "plain text rejection") }),
+        );
+        let app = with_middleware(routes, pool.clone(), 0).with_state(pool);
+
+        let request = Request::builder()
+            .uri("/api/usage/fails")
+            .header(header::ACCEPT_ENCODING, "gzip")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+        let id = response.headers()[request_id::HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        // The envelope and request id were added before the body was compressed
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let mut json = String::new();
+        GzDecoder::new(&body[..]).read_to_string(&mut json).unwrap();
+        let error: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(error["error"], "bad_request");
+        assert_eq!(error["message"], "plain text rejection");
+        assert_eq!(error["request_id"], id.as_str());
+    }
+
+    #[test]
+    fn test_client_ip_ignores_client_supplied_forwarded_for() {
+        let peer = Some(SocketAddr::from(([10, 0, 0, 2], 443)));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "1.2.3.4, 203.0.113.7, 10.0.0.1".parse().unwrap(),
+        );
+
+        assert_eq!(client_ip(&headers, peer, 0), "10.0.0.2");
+        assert_eq!(client_ip(&headers, peer, 1), "10.0.0.1");
+        assert_eq!(client_ip(&headers, peer, 2), "203.0.113.7");
+        // More hops than entries: the leftmost is the best we have
+        assert_eq!(client_ip(&headers, peer, 5), "1.2.3.4");
+
+        // A spoofed header can't push the proxy-added entry out of place
+        headers.insert("x-forwarded-for", "6.6.6.6, 198.51.100.9".parse().unwrap());
+        assert_eq!(client_ip(&headers, peer, 1), "198.51.100.9");
+    }
+}
@@ -0,0 +1,26 @@
+use std::collections::HashMap;
+use tracing::warn;
+
+use crate::services::github;
+use kicad_db::alternates::{list_alternates, PartAlternate};
+use kicad_db::PgPool;
+
+/// Approved alternates of a repository keyed by the normalized MPN of their
+/// BOM line. Failures are logged and read as no alternates, so availability
+/// checks fall back to the parts themselves.
+pub async fn by_mpn(pool: &PgPool, repo: &str) -> HashMap<String, Vec<PartAlternate>> {
+    let repo_url = github::repo_url(repo);
+    let mut groups: HashMap<String, Vec<PartAlternate>> = HashMap::new();
+    match list_alternates(pool, &repo_url).await {
+        Ok(alternates) => {
+            for alternate in alternates {
+                groups
+                    .entry(alternate.mpn.clone())
+                    .or_default()
+                    .push(alternate);
+            }
+        }
+        Err(e) => warn!("Failed to load part alternates of {}: {}", repo, e),
+    }
+    groups
+}
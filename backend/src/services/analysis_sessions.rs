@@ -0,0 +1,123 @@
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::services::git;
+use crate::types::{GrokSessionExport, GrokSessionExportTurn};
+use kicad_db::analysis_sessions::{append_turn, delete_idle_sessions, AnalysisSession, NewTurn};
+use kicad_db::messages::Message;
+use kicad_db::PgPool;
+
+// Default for ANALYSIS_SESSION_IDLE_SECS
+const DEFAULT_IDLE_SECS: i64 = 60 * 60;
+
+/// Earlier turns kept in a session and resent with each question
+pub const MAX_TURNS: i64 = 20;
+
+/// Separates the selected components' context from the question in a turn's user message
+pub const QUESTION_HEADING: &str = "\n\n---\n\n## User's Question\n";
+
+/// How long an unused session stays open (ANALYSIS_SESSION_IDLE_SECS)
+pub fn idle_timeout_secs() -> i64 {
+    std::env::var("ANALYSIS_SESSION_IDLE_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|secs: &i64| *secs > 0)
+        .unwrap_or(DEFAULT_IDLE_SECS)
+}
+
+/// Messages for a question in a session: the session's context, its earlier
+/// turns and the question. The prefix is the same on every turn, so the
+/// provider can serve it from its prompt cache.
+pub fn messages(session: &AnalysisSession, question: String) -> Vec<Message> {
+    let mut messages = vec![Message::system(session.context.clone())];
+    for (user, assistant) in session.turn_messages() {
+        messages.push(Message::user(user));
+        messages.push(Message::assistant(assistant));
+    }
+    messages.push(Message::user(question));
+    messages
+}
+
+/// Store a finished turn. Failures are logged: the answer was already sent,
+/// and the next question just goes without it.
+pub async fn record_turn(pool: &PgPool, id: Uuid, turn: &NewTurn<'_>) {
+    match append_turn(pool, id, turn, MAX_TURNS).await {
+        Ok(true) => {}
+        Ok(false) => warn!("Analysis session {} ended before its turn was stored", id),
+        Err(e) => warn!("Failed to store turn of analysis session {}: {}", id, e),
+    }
+}
+
+/// Delete sessions past the idle timeout
+pub async fn prune(pool: &PgPool) {
+    match delete_idle_sessions(pool, idle_timeout_secs()).await {
+        Ok(0) => {}
+        Ok(deleted) => info!("Deleted {} idle analysis session(s)", deleted),
+        Err(e) => warn!("Failed to delete idle analysis sessions: {}", e),
+    }
+}
+
+/// A session's questions and answers with the components each was about.
+/// Turns stored before questions were kept apart take the question from the
+/// user message.
+pub fn export(session: &AnalysisSession) -> GrokSessionExport {
+    let turns = session
+        .turn_list()
+        .into_iter()
+        .map(|turn| GrokSessionExportTurn {
+            question: turn.question.unwrap_or_else(|| {
+                turn.user
+                    .rsplit_once(QUESTION_HEADING)
+                    .map_or(turn.user.as_str(), |(_, question)| question)
+                    .to_string()
+            }),
+            components: turn.components,
+            answer: turn.assistant,
+        })
+        .collect();
+    GrokSessionExport {
+        session_id: session.id.to_string(),
+        repo: git::repo_slug(&session.repo_url)
+            .unwrap_or(&session.repo_url)
+            .to_string(),
+        commit: session.commit_hash.clone(),
+        created_at: session.created_at,
+        last_used_at: session.last_used_at,
+        turns,
+    }
+}
+
+/// Markdown transcript of an exported session, for design review documents
+pub fn export_markdown(export: &GrokSessionExport) -> String {
+    let mut md = format!(
+        "# Grok session: {} @ {}\n\n",
+        export.repo,
+        &export.commit[..8.min(export.commit.len())]
+    );
+    md.push_str(&format!("- Repository: {}\n", export.repo));
+    md.push_str(&format!("- Commit: `{}`\n", export.commit));
+    md.push_str(&format!("- Session: `{}`\n", export.session_id));
+    md.push_str(&format!(
+        "- Started: {}\n",
+        export.created_at.format("%Y-%m-%d %H:%M UTC")
+    ));
+    if export.turns.is_empty() {
+        md.push_str("\n_No questions were asked._\n");
+    }
+    for (n, turn) in export.turns.iter().enumerate() {
+        md.push_str(&format!("\n## Question {}\n\n", n + 1));
+        if !turn.components.is_empty() {
+            let components: Vec<String> =
+                turn.components.iter().map(|c| format!("`{}`", c)).collect();
+            md.push_str(&format!("Components: {}\n\n", components.join(", ")));
+        }
+        for line in turn.question.trim().lines() {
+            md.push_str(format!("> {}", line).trim_end());
+            md.push('\n');
+        }
+        md.push_str("\n### Answer\n\n");
+        md.push_str(turn.answer.trim());
+        md.push('\n');
+    }
+    md
+}
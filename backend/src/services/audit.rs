@@ -0,0 +1,78 @@
+use serde_json::Value;
+use tracing::warn;
+
+use kicad_db::audit::record_action;
+use kicad_db::PgPool;
+
+/// A commit was distilled and cached for the first time through `/api/repo/init`
+pub const REPO_ONBOARDED: &str = "repo.onboarded";
+/// Cached distilled data was deleted on request
+pub const CACHE_CLEARED: &str = "cache.cleared";
+/// The cached clone was refreshed after upstream history changed
+pub const CACHE_INVALIDATED: &str = "cache.invalidated";
+/// A commit summary was generated and stored
+pub const SUMMARY_GENERATED: &str = "summary.generated";
+/// A commit summary was edited by hand
+pub const SUMMARY_EDITED: &str = "summary.edited";
+/// A commit summary was approved or rejected
+pub const SUMMARY_REVIEWED: &str = "summary.reviewed";
+/// A stale summary was queued for regeneration
+pub const SUMMARY_QUEUED: &str = "summary.queued";
+/// A repository's stored data moved to a new URL
+pub const REPO_RENAMED: &str = "repo.renamed";
+/// A repository's default branch changed upstream
+pub const REPO_DEFAULT_BRANCH_CHANGED: &str = "repo.default_branch_changed";
+/// A repository was uploaded to an instance in mirror mode
+pub const REPO_UPLOADED: &str = "repo.uploaded";
+/// GitHub commit statuses were turned on or off for a repository
+pub const REPO_COMMIT_STATUS_CHANGED: &str = "repo.commit_status_changed";
+/// Repository credentials were set or removed (the secret itself is never recorded)
+pub const CREDENTIALS_CHANGED: &str = "credentials.changed";
+/// Stored secrets were resealed with the active encryption key
+pub const SECRETS_ROTATED: &str = "secrets.rotated";
+/// A secret setting was stored or removed (the value itself is never recorded)
+pub const SECRET_CHANGED: &str = "secret.changed";
+/// An API key was created, revoked or had its quotas changed
+pub const API_KEY_CHANGED: &str = "api_key.changed";
+/// A design rule was stored or deleted
+pub const DESIGN_RULE_CHANGED: &str = "design_rule.changed";
+/// Failed jobs were put back on the queue
+pub const JOBS_RETRIED: &str = "jobs.retried";
+/// A quarantined commit was released so it's processed again
+pub const QUARANTINE_RELEASED: &str = "quarantine.released";
+/// The log filter was changed at runtime
+pub const LOG_FILTER_CHANGED: &str = "log_filter.changed";
+/// A file was removed from the blob store
+pub const BLOB_DELETED: &str = "blob.deleted";
+
+/// Actor of requests to the public API
+pub const ACTOR_API: &str = "api";
+/// Actor of requests authenticated with the admin token
+pub const ACTOR_ADMIN: &str = "admin";
+/// Actor of GitHub webhook deliveries
+pub const ACTOR_WEBHOOK: &str = "webhook";
+/// Actor of background loops
+pub const ACTOR_SCHEDULER: &str = "scheduler";
+
+/// Actor of work done by a queued job
+pub fn job_actor(job_id: i64) -> String {
+    format!("job:{}", job_id)
+}
+
+/// Record an action in the audit log. Failures are logged, never surfaced:
+/// the action itself already happened.
+pub async fn record(
+    pool: &PgPool,
+    action: &str,
+    actor: &str,
+    repo_url: Option<&str>,
+    commit: Option<&str>,
+    params: Value,
+) {
+    if let Err(e) = record_action(pool, action, actor, repo_url, commit, &params).await {
+        warn!(
+            "Failed to record audit entry {} by {}: {}",
+            action, actor, e
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::services::{bom, distill, git, github};
+use kicad_db::{merge_part_properties, retrieve_distilled_json, set_commit_info, PgPool};
+
+/// Most earlier commits a single onboarding may backfill
+pub const MAX_BACKFILL_COMMITS: usize = 50;
+
+/// Outcome of a backfill, stored as the job result
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BackfillSummary {
+    pub repo: String,
+    /// Commits picked for the backfill
+    pub commits: usize,
+    /// Commits distilled by this backfill
+    pub distilled: usize,
+    /// Commits whose distilled data was already cached
+    pub cached: usize,
+    /// Part rows written across all commits
+    pub parts: u64,
+    pub errors: Vec<String>,
+}
+
+/// Part properties indexed for a component: its BOM fields. Merged into any
+/// existing row, so distributor enrichment is kept.
+fn part_properties(component: &bom::BomComponent) -> Value {
+    json!({
+        "reference": component.reference,
+        "value": component.value,
+        "footprint": component.footprint,
+        "mpn": component.mpn,
+        "manufacturer": component.manufacturer,
+        "lcsc_part_number": component.lcsc_part_number,
+    })
+}
+
+/// Distill up to `limit` schematic commits before `head` (newest first) and
+/// index their parts, so part history has data for a freshly onboarded repo.
+///
+/// Commits are distilled in this process. A commit that fails is recorded in
+/// `errors` and skipped. `progress` is called after each commit with the
+/// number done and the total.
+pub async fn backfill_repo<F, Fut>(
+    pool: &PgPool,
+    repo: &str,
+    head: &str,
+    limit: usize,
+    progress: F,
+) -> Result<BackfillSummary>
+where
+    F: Fn(usize, usize) -> Fut,
+    Fut: std::future::Future<Output = ()>,
+{
+    let repo_url = github::repo_url(repo);
+    let commits: Vec<_> = git::get_schematic_commits(repo)
+        .await
+        .context("Failed to fetch commits")?
+        .into_iter()
+        .filter(|c| c.commit_hash != head)
+        .take(limit.min(MAX_BACKFILL_COMMITS))
+        .collect();
+
+    info!("Backfilling {} commit(s) of {}", commits.len(), repo);
+    let mut summary = BackfillSummary {
+        repo: repo.to_string(),
+        commits: commits.len(),
+        ..Default::default()
+    };
+    progress(0, commits.len()).await;
+
+    for (idx, commit) in commits.iter().enumerate() {
+        let hash = &commit.commit_hash;
+        let distilled = match retrieve_distilled_json(pool, &repo_url, hash).await {
+            Ok(Some(cached)) => {
+                summary.cached += 1;
+                Ok(cached)
+            }
+            _ => distill::distill_and_store(pool, repo, hash)
+                .await
+                .map(|(distilled, _)| {
+                    summary.distilled += 1;
+                    distilled
+                }),
+        };
+
+        match distilled {
+            Ok(distilled) => {
+                let parts: HashMap<_, _> = bom::extract_components(&distilled)
+                    .iter()
+                    .filter(|c| !c.reference.starts_with('#'))
+                    .map(|c| {
+                        (
+                            bom::part_uuid_for_reference(&c.reference),
+                            part_properties(c),
+                        )
+                    })
+                    .collect();
+                match merge_part_properties(pool, &repo_url, hash, parts).await {
+                    Ok(written) => summary.parts += written,
+                    Err(e) => summary.errors.push(format!("Commit {}: {}", hash, e)),
+                }
+                if let Err(e) = set_commit_info(
+                    pool,
+                    &repo_url,
+                    hash,
+                    commit.commit_date,
+                    commit.message.as_deref(),
+                )
+                .await
+                {
+                    warn!("Failed to store commit info of {}/{}: {}", repo, hash, e);
+                }
+            }
+            Err(e) => {
+                warn!("Backfill of {}/{} failed: {:#}", repo, hash, e);
+                summary.errors.push(format!("Commit {}: {:#}", hash, e));
+            }
+        }
+
+        progress(idx + 1, commits.len()).await;
+    }
+
+    info!(
+        "Backfill of {} complete: distilled={}, cached={}, parts={}, errors={}",
+        repo,
+        summary.distilled,
+        summary.cached,
+        summary.parts,
+        summary.errors.len()
+    );
+    Ok(summary)
+}
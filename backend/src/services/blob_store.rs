@@ -0,0 +1,825 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Duration, Utc};
+use once_cell::sync::OnceCell;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use reqwest::{Client, Method, StatusCode, Url};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use crate::services::{git, github, report};
+use crate::versioning;
+use kicad_db::blobs::{self, BlobRecord, NewBlob};
+use kicad_db::secrets::{Keyring, SecretError};
+use kicad_db::PgPool;
+
+// Default lifetime of links handed out by the API (BLOB_URL_TTL_SECS)
+const DEFAULT_URL_TTL_SECS: i64 = 60 * 60;
+
+// S3 rejects presigned URLs valid for longer than a week
+const MAX_S3_URL_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+// Characters left unescaped by SigV4 URI encoding (RFC 3986 unreserved)
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+// Same as UNRESERVED but keeps '/' so keys map onto object paths
+const KEY_PATH: &AsciiSet = &UNRESERVED.remove(b'/');
+
+// Purpose the link signing key is derived from the secrets key for
+const SIGNING_KEY_PURPOSE: &str = "grokicad blob links";
+
+// Legacy schematic images copied into the store per database round trip
+const LEGACY_IMAGE_BATCH: i64 = 20;
+
+/// What a blob holds. Used as the first segment of its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlobKind {
+    SchematicImage,
+    Render,
+    Datasheet,
+    Export,
+}
+
+impl BlobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlobKind::SchematicImage => "schematic_image",
+            BlobKind::Render => "render",
+            BlobKind::Datasheet => "datasheet",
+            BlobKind::Export => "export",
+        }
+    }
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        match kind {
+            "schematic_image" => Some(BlobKind::SchematicImage),
+            "render" => Some(BlobKind::Render),
+            "datasheet" => Some(BlobKind::Datasheet),
+            "export" => Some(BlobKind::Export),
+            _ => None,
+        }
+    }
+}
+
+/// A time-limited link to a blob
+#[derive(Debug, Clone)]
+pub struct SignedUrl {
+    pub url: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Reject keys that could escape the store root or collide after normalisation
+pub fn validate_key(key: &str) -> Result<()> {
+    if key.is_empty() || key.starts_with('/') || key.contains('\\') {
+        anyhow::bail!("Invalid blob key: {:?}", key);
+    }
+    if key
+        .split('/')
+        .any(|segment| segment.is_empty() || segment == "." || segment == "..")
+    {
+        anyhow::bail!("Invalid blob key: {:?}", key);
+    }
+    Ok(())
+}
+
+/// Key for a file attached to a commit, e.g. `render/owner/repo/<commit>/root.svg`
+pub fn commit_key(kind: BlobKind, repo: &str, commit: &str, name: &str) -> Result<String> {
+    let key = format!("{}/{}/{}/{}", kind.as_str(), repo, commit, name);
+    validate_key(&key)?;
+    Ok(key)
+}
+
+/// Key the bytes of every blob with this content are kept under, e.g. `content/<sha256>`
+fn content_key(sha256: &str) -> String {
+    format!("content/{}", sha256)
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| default.to_string())
+}
+
+fn require_env(name: &str) -> Result<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|v| !v.is_empty())
+        .with_context(|| format!("{} is not set", name))
+}
+
+// ============================================================================
+// Local filesystem
+// ============================================================================
+
+/// Key local blob links are signed with. Every API replica has to use the
+/// same one, so it is BLOB_SIGNING_KEY, else derived from the secrets
+/// encryption key; a random per-process key is only made up when
+/// API_REPLICAS says a single process serves the API.
+fn signing_key() -> Result<Vec<u8>> {
+    if let Some(encoded) = std::env::var("BLOB_SIGNING_KEY")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+    {
+        return BASE64
+            .decode(encoded.trim())
+            .context("BLOB_SIGNING_KEY is not valid base64");
+    }
+
+    match Keyring::from_env() {
+        Ok(keyring) => return Ok(keyring.derive_key(SIGNING_KEY_PURPOSE)),
+        Err(SecretError::NotConfigured) => {}
+        Err(e) => {
+            return Err(anyhow::anyhow!("{}", e)).context("Can't derive the blob signing key")
+        }
+    }
+
+    let replicas: u32 = env_or("API_REPLICAS", "1")
+        .parse()
+        .context("API_REPLICAS is not a number")?;
+    if replicas > 1 {
+        anyhow::bail!(
+            "BLOB_SIGNING_KEY (or SECRETS_ENCRYPTION_KEY to derive it from) must be set when API_REPLICAS is {}; replicas can't verify each other's blob links otherwise",
+            replicas
+        );
+    }
+    warn!("BLOB_SIGNING_KEY and SECRETS_ENCRYPTION_KEY are not set; blob links will stop working on restart");
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow::anyhow!("Failed to generate blob signing key"))?;
+    Ok(bytes.to_vec())
+}
+
+/// Blobs stored under a directory on the API host, served by `/api/blobs/file`
+pub struct LocalStore {
+    root: PathBuf,
+    public_url: String,
+    signing_key: hmac::Key,
+}
+
+impl LocalStore {
+    fn from_env() -> Result<Self> {
+        let signing_key = signing_key()?;
+
+        Ok(Self {
+            root: PathBuf::from(env_or("BLOB_LOCAL_DIR", "./blobs")),
+            public_url: env_or("BLOB_PUBLIC_URL", "")
+                .trim_end_matches('/')
+                .to_string(),
+            signing_key: hmac::Key::new(hmac::HMAC_SHA256, &signing_key),
+        })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+
+    async fn put(&self, key: &str, data: &[u8]) -> Result<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        // Write then rename so readers never see a partial file
+        let mut partial = path.clone().into_os_string();
+        partial.push(".partial");
+        let partial = PathBuf::from(partial);
+        tokio::fs::write(&partial, data)
+            .await
+            .with_context(|| format!("Failed to write {}", partial.display()))?;
+        tokio::fs::rename(&partial, &path)
+            .await
+            .with_context(|| format!("Failed to move blob into place at {}", path.display()))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read blob {}", key)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to delete blob {}", key))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn signature(&self, key: &str, expires: i64) -> String {
+        let message = format!("{}\n{}", key, expires);
+        hex::encode(hmac::sign(&self.signing_key, message.as_bytes()))
+    }
+
+    fn url(&self, key: &str, ttl_secs: i64) -> Result<SignedUrl> {
+        validate_key(key)?;
+        let expires_at = Utc::now() + Duration::seconds(ttl_secs);
+        let expires = expires_at.timestamp();
+        let url = format!(
+            "{}{}/blobs/file/{}?expires={}&signature={}",
+            self.public_url,
+            versioning::CURRENT_PREFIX,
+            utf8_percent_encode(key, KEY_PATH),
+            expires,
+            self.signature(key, expires)
+        );
+        Ok(SignedUrl { url, expires_at })
+    }
+
+    /// Check a link produced by `url`
+    fn verify(&self, key: &str, expires: i64, signature: &str) -> bool {
+        if expires < Utc::now().timestamp() {
+            return false;
+        }
+        let Ok(signature) = hex::decode(signature) else {
+            return false;
+        };
+        let message = format!("{}\n{}", key, expires);
+        hmac::verify(&self.signing_key, message.as_bytes(), &signature).is_ok()
+    }
+}
+
+// ============================================================================
+// S3-compatible object storage
+// ============================================================================
+
+/// Blobs stored in an S3-compatible bucket (AWS S3, MinIO, R2, ...).
+/// Uses path-style addressing and SigV4 request signing.
+pub struct S3Store {
+    client: Client,
+    endpoint: Url,
+    bucket: String,
+    region: String,
+    access_key_id: String,
+    secret_access_key: String,
+}
+
+impl S3Store {
+    fn from_env() -> Result<Self> {
+        let region = env_or("S3_REGION", "us-east-1");
+        let endpoint = env_or(
+            "S3_ENDPOINT",
+            &format!("https://s3.{}.amazonaws.com", region),
+        );
+        let endpoint =
+            Url::parse(&endpoint).with_context(|| format!("Invalid S3_ENDPOINT {}", endpoint))?;
+        endpoint.host_str().context("S3_ENDPOINT has no host")?;
+
+        Ok(Self {
+            client: Client::new(),
+            endpoint,
+            bucket: require_env("S3_BUCKET")?,
+            region,
+            access_key_id: require_env("S3_ACCESS_KEY_ID")?,
+            secret_access_key: require_env("S3_SECRET_ACCESS_KEY")?,
+        })
+    }
+
+    /// Host header value, including the port when it isn't the scheme default
+    fn host(&self) -> String {
+        let host = self.endpoint.host_str().unwrap_or_default();
+        match self.endpoint.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        }
+    }
+
+    /// URI-encoded object path, used both in the request URL and the canonical request
+    fn object_path(&self, key: &str) -> Result<String> {
+        validate_key(key)?;
+        let base = self.endpoint.path().trim_end_matches('/');
+        Ok(format!(
+            "{}/{}/{}",
+            base,
+            utf8_percent_encode(&self.bucket, UNRESERVED),
+            utf8_percent_encode(key, KEY_PATH)
+        ))
+    }
+
+    fn object_url(&self, path: &str, query: &str) -> String {
+        let mut url = format!("{}://{}{}", self.endpoint.scheme(), self.host(), path);
+        if !query.is_empty() {
+            url.push('?');
+            url.push_str(query);
+        }
+        url
+    }
+
+    fn scope(&self, now: DateTime<Utc>) -> String {
+        format!("{}/{}/s3/aws4_request", now.format("%Y%m%d"), self.region)
+    }
+
+    /// SigV4 signature over a canonical request.
+    /// `headers` must be lowercase and sorted by name.
+    fn sign(
+        &self,
+        now: DateTime<Utc>,
+        method: &Method,
+        path: &str,
+        query: &str,
+        headers: &[(&str, String)],
+        payload_hash: &str,
+    ) -> String {
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect();
+        let signed_headers = signed_headers(headers);
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method, path, query, canonical_headers, signed_headers, payload_hash
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            now.format("%Y%m%dT%H%M%SZ"),
+            self.scope(now),
+            sha256_hex(canonical_request.as_bytes())
+        );
+
+        let mut key = format!("AWS4{}", self.secret_access_key).into_bytes();
+        for part in [
+            now.format("%Y%m%d").to_string().as_str(),
+            self.region.as_str(),
+            "s3",
+            "aws4_request",
+        ] {
+            key = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), part.as_bytes())
+                .as_ref()
+                .to_vec();
+        }
+        hex::encode(hmac::sign(
+            &hmac::Key::new(hmac::HMAC_SHA256, &key),
+            string_to_sign.as_bytes(),
+        ))
+    }
+
+    /// Send a header-signed request for an object
+    async fn request(
+        &self,
+        method: Method,
+        key: &str,
+        body: Vec<u8>,
+        content_type: Option<&str>,
+    ) -> Result<reqwest::Response> {
+        let now = Utc::now();
+        let path = self.object_path(key)?;
+        let payload_hash = sha256_hex(&body);
+
+        let mut headers = vec![
+            ("host", self.host()),
+            ("x-amz-content-sha256", payload_hash.clone()),
+            ("x-amz-date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+        ];
+        if let Some(content_type) = content_type {
+            headers.push(("content-type", content_type.to_string()));
+        }
+        headers.sort();
+
+        let signature = self.sign(now, &method, &path, "", &headers, &payload_hash);
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id,
+            self.scope(now),
+            signed_headers(&headers),
+            signature
+        );
+
+        let mut request = self
+            .client
+            .request(method, self.object_url(&path, ""))
+            .header("authorization", authorization);
+        for (name, value) in headers.into_iter().filter(|(name, _)| *name != "host") {
+            request = request.header(name, value);
+        }
+        request
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("S3 request for {} failed", key))
+    }
+
+    async fn put(&self, key: &str, data: &[u8], content_type: &str) -> Result<()> {
+        self.request(Method::PUT, key, data.to_vec(), Some(content_type))
+            .await?
+            .error_for_status()
+            .with_context(|| format!("Failed to upload blob {}", key))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let response = self.request(Method::GET, key, Vec::new(), None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let data = response
+            .error_for_status()
+            .with_context(|| format!("Failed to download blob {}", key))?
+            .bytes()
+            .await?;
+        Ok(Some(data.to_vec()))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let response = self.request(Method::DELETE, key, Vec::new(), None).await?;
+        if response.status() != StatusCode::NOT_FOUND {
+            response
+                .error_for_status()
+                .with_context(|| format!("Failed to delete blob {}", key))?;
+        }
+        Ok(())
+    }
+
+    /// Presigned GET link that can be handed straight to a browser
+    fn url(&self, key: &str, ttl_secs: i64) -> Result<SignedUrl> {
+        let now = Utc::now();
+        let ttl_secs = ttl_secs.clamp(1, MAX_S3_URL_TTL_SECS);
+        let path = self.object_path(key)?;
+        let credential = format!("{}/{}", self.access_key_id, self.scope(now));
+
+        // Parameter names are already sorted, as SigV4 requires
+        let query = [
+            ("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string()),
+            ("X-Amz-Credential", credential),
+            ("X-Amz-Date", now.format("%Y%m%dT%H%M%SZ").to_string()),
+            ("X-Amz-Expires", ttl_secs.to_string()),
+            ("X-Amz-SignedHeaders", "host".to_string()),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, utf8_percent_encode(value, UNRESERVED)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+        let headers = [("host", self.host())];
+        let signature = self.sign(
+            now,
+            &Method::GET,
+            &path,
+            &query,
+            &headers,
+            "UNSIGNED-PAYLOAD",
+        );
+        let query = format!("{}&X-Amz-Signature={}", query, signature);
+
+        Ok(SignedUrl {
+            url: self.object_url(&path, &query),
+            expires_at: now + Duration::seconds(ttl_secs),
+        })
+    }
+}
+
+fn signed_headers(headers: &[(&str, String)]) -> String {
+    headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+// ============================================================================
+// Store selection
+// ============================================================================
+
+/// Where images, renders, datasheets and export archives are kept.
+/// Selected with BLOB_STORE ("local" or "s3").
+pub enum BlobStore {
+    Local(LocalStore),
+    S3(S3Store),
+}
+
+static STORE: OnceCell<BlobStore> = OnceCell::new();
+
+/// Set up the store configured by BLOB_STORE. Processes call this at startup,
+/// so a misconfigured store stops them rather than writing somewhere else.
+pub fn init() -> Result<&'static BlobStore> {
+    let store = STORE.get_or_try_init(BlobStore::from_env)?;
+    info!("Using {} blob store", store.name());
+    Ok(store)
+}
+
+/// The configured blob store
+pub fn store() -> &'static BlobStore {
+    STORE.get_or_init(|| {
+        BlobStore::from_env().expect("blob store configuration is checked by init at startup")
+    })
+}
+
+impl BlobStore {
+    /// The store configured by BLOB_STORE and its settings
+    pub fn from_env() -> Result<Self> {
+        match env_or("BLOB_STORE", "local").to_lowercase().as_str() {
+            "local" => Ok(BlobStore::Local(LocalStore::from_env()?)),
+            "s3" => Ok(BlobStore::S3(S3Store::from_env()?)),
+            other => anyhow::bail!(
+                "Unknown BLOB_STORE {:?} (expected \"local\" or \"s3\")",
+                other
+            ),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlobStore::Local(_) => "local",
+            BlobStore::S3(_) => "s3",
+        }
+    }
+
+    /// Directory blobs are written to, for the local store
+    pub fn local_dir(&self) -> Option<&Path> {
+        match self {
+            BlobStore::Local(store) => Some(&store.root),
+            BlobStore::S3(_) => None,
+        }
+    }
+
+    pub async fn put(&self, key: &str, data: &[u8], content_type: &str) -> Result<()> {
+        match self {
+            BlobStore::Local(store) => store.put(key, data).await,
+            BlobStore::S3(store) => store.put(key, data, content_type).await,
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            BlobStore::Local(store) => store.get(key).await,
+            BlobStore::S3(store) => store.get(key).await,
+        }
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<()> {
+        match self {
+            BlobStore::Local(store) => store.delete(key).await,
+            BlobStore::S3(store) => store.delete(key).await,
+        }
+    }
+
+    /// Signed link to a recorded blob. S3 links point at the shared content
+    /// directly; local links name the blob and are resolved when served.
+    pub fn record_url(&self, record: &BlobRecord) -> Result<SignedUrl> {
+        match self {
+            BlobStore::Local(_) => self.url(&record.key),
+            BlobStore::S3(_) => self.url(record.location()),
+        }
+    }
+
+    /// Signed link to a blob, valid for BLOB_URL_TTL_SECS (default one hour)
+    pub fn url(&self, key: &str) -> Result<SignedUrl> {
+        let ttl_secs = std::env::var("BLOB_URL_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_URL_TTL_SECS);
+        match self {
+            BlobStore::Local(store) => store.url(key, ttl_secs),
+            BlobStore::S3(store) => store.url(key, ttl_secs),
+        }
+    }
+
+    /// Check a `/api/blobs/file` link. Only the local store serves blobs through the API.
+    pub fn verify(&self, key: &str, expires: i64, signature: &str) -> bool {
+        match self {
+            BlobStore::Local(store) => store.verify(key, expires, signature),
+            BlobStore::S3(_) => false,
+        }
+    }
+}
+
+/// Drop the bytes at a shared content key once no blob references them
+async fn release_content(pool: &PgPool, content_key: &str) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    // Deleted while the content row is still locked, so a writer reusing the
+    // content waits and then writes the bytes again
+    if blobs::release_content(&mut tx, content_key).await? {
+        store().delete(content_key).await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Write a file belonging to a commit and record its metadata.
+///
+/// The bytes are stored once per content hash: a file identical to one
+/// already stored, e.g. the render of a sheet an earlier commit didn't
+/// change, only adds a metadata row pointing at the existing copy.
+pub async fn store_commit_blob(
+    pool: &PgPool,
+    kind: BlobKind,
+    repo: &str,
+    commit: &str,
+    name: &str,
+    data: &[u8],
+    content_type: &str,
+) -> Result<BlobRecord> {
+    let key = commit_key(kind, repo, commit, name)?;
+    let repo_url = github::repo_url(repo);
+    store_blob(pool, &key, kind, &repo_url, commit, data, content_type).await
+}
+
+async fn store_blob(
+    pool: &PgPool,
+    key: &str,
+    kind: BlobKind,
+    repo_url: &str,
+    commit: &str,
+    data: &[u8],
+    content_type: &str,
+) -> Result<BlobRecord> {
+    let sha256 = sha256_hex(data);
+    let content_key = content_key(&sha256);
+    let previous = blobs::get_blob(pool, key).await?;
+
+    // Like distilled JSON, the bytes are stored once per content hash. The
+    // content stays locked until the metadata commits, and only its first
+    // user uploads the bytes.
+    let mut tx = pool.begin().await?;
+    let (record, new_content) = blobs::record_blob(
+        &mut tx,
+        &NewBlob {
+            key,
+            kind: kind.as_str(),
+            repo_url: Some(repo_url),
+            commit_hash: Some(commit),
+            content_type,
+            size_bytes: data.len() as i64,
+            sha256: &sha256,
+            content_key: Some(&content_key),
+        },
+    )
+    .await?;
+    if new_content {
+        store().put(&content_key, data, content_type).await?;
+    } else {
+        info!("Blob {} shares stored content {}", key, content_key);
+    }
+    tx.commit().await?;
+
+    if let Some(previous) = previous.filter(|p| p.location() != content_key) {
+        match previous.content_key.as_deref() {
+            Some(old) => release_content(pool, old).await?,
+            None => store().delete(&previous.key).await?,
+        }
+    }
+    Ok(record)
+}
+
+/// Remove a blob's metadata, and its bytes unless other blobs share them
+pub async fn delete_blob(pool: &PgPool, key: &str) -> Result<bool> {
+    let Some(record) = blobs::get_blob(pool, key).await? else {
+        return Ok(false);
+    };
+    let deleted = blobs::delete_blob(pool, key).await?;
+    match record.content_key.as_deref() {
+        Some(content_key) => release_content(pool, content_key).await?,
+        None => store().delete(key).await?,
+    }
+    Ok(deleted)
+}
+
+/// Read a recorded blob's bytes. None when they are missing from the store.
+pub async fn read(record: &BlobRecord) -> Result<Option<Vec<u8>>> {
+    store().get(record.location()).await
+}
+
+/// File name a schematic image is stored under, by its format
+fn image_name(data: &[u8]) -> &'static str {
+    match report::image_content_type(data) {
+        "image/png" => "schematic.png",
+        "image/jpeg" => "schematic.jpg",
+        "image/webp" => "schematic.webp",
+        "image/svg+xml" => "schematic.svg",
+        _ => "schematic.bin",
+    }
+}
+
+/// The schematic image stored for a commit, if any. Replica-safe.
+pub async fn schematic_image(
+    pool: &PgPool,
+    repo_url: &str,
+    commit: &str,
+) -> Result<Option<BlobRecord>> {
+    let images = blobs::list_blobs(
+        pool,
+        repo_url,
+        commit,
+        Some(BlobKind::SchematicImage.as_str()),
+    )
+    .await?;
+    Ok(images.into_iter().max_by_key(|record| record.created_at))
+}
+
+/// Store a commit's schematic image, replacing one stored before in another format
+pub async fn store_schematic_image(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    data: &[u8],
+) -> Result<BlobRecord> {
+    let record = store_commit_blob(
+        pool,
+        BlobKind::SchematicImage,
+        repo,
+        commit,
+        image_name(data),
+        data,
+        report::image_content_type(data),
+    )
+    .await?;
+    remove_other_images(pool, &record).await?;
+    Ok(record)
+}
+
+async fn remove_other_images(pool: &PgPool, keep: &BlobRecord) -> Result<()> {
+    let (Some(repo_url), Some(commit)) = (keep.repo_url.as_deref(), keep.commit_hash.as_deref())
+    else {
+        return Ok(());
+    };
+    for other in blobs::list_blobs(
+        pool,
+        repo_url,
+        commit,
+        Some(BlobKind::SchematicImage.as_str()),
+    )
+    .await?
+    {
+        if other.key != keep.key {
+            delete_blob(pool, &other.key).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy schematic images left in the `schematics.schematic_image` column by
+/// earlier versions into the store, then drop the column. Returns the number
+/// of images moved.
+pub async fn migrate_legacy_images(pool: &PgPool) -> Result<usize> {
+    if !blobs::has_legacy_image_column(pool).await? {
+        return Ok(0);
+    }
+
+    let mut moved = 0;
+    loop {
+        let batch = blobs::legacy_images(pool, LEGACY_IMAGE_BATCH).await?;
+        if batch.is_empty() {
+            break;
+        }
+        for image in batch {
+            // Repositories outside the configured GitHub host keep their
+            // stored URL but are filed under their schematic id
+            let key = match git::repo_slug(&image.repo_url) {
+                Some(slug) => commit_key(
+                    BlobKind::SchematicImage,
+                    slug,
+                    &image.commit_hash,
+                    image_name(&image.image),
+                ),
+                None => Err(anyhow::anyhow!("not a repository URL")),
+            }
+            .or_else(|_| {
+                commit_key(
+                    BlobKind::SchematicImage,
+                    "legacy",
+                    &image.id.to_string(),
+                    image_name(&image.image),
+                )
+            })?;
+            let record = store_blob(
+                pool,
+                &key,
+                BlobKind::SchematicImage,
+                &image.repo_url,
+                &image.commit_hash,
+                &image.image,
+                report::image_content_type(&image.image),
+            )
+            .await
+            .with_context(|| format!("Failed to move the schematic image of {}", key))?;
+            remove_other_images(pool, &record).await?;
+            blobs::clear_legacy_image(pool, image.id).await?;
+            moved += 1;
+        }
+    }
+
+    if blobs::drop_legacy_image_column(pool).await? {
+        info!(
+            "Dropped schematics.schematic_image after moving {} image(s) to the blob store",
+            moved
+        );
+    }
+    Ok(moved)
+}
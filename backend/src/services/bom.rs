@@ -0,0 +1,158 @@
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::services::mpn;
+
+/// Property names that commonly hold the manufacturer name
+const MANUFACTURER_PROPERTY_KEYS: &[&str] = &["Manufacturer", "MFR", "Mfr", "Manufacturer_Name"];
+
+/// Property names used by JLCPCB/LCSC libraries for the LCSC part number
+const LCSC_PROPERTY_KEYS: &[&str] = &[
+    "LCSC",
+    "LCSC Part",
+    "LCSC Part #",
+    "LCSC Part Number",
+    "JLCPCB Part#",
+    "JLC",
+];
+
+/// A component extracted from distilled schematic data
+#[derive(Debug, Clone)]
+pub struct BomComponent {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    /// Component value (e.g., "10k")
+    pub value: Option<String>,
+    /// Footprint library ID
+    pub footprint: Option<String>,
+    /// Manufacturer part number, if the symbol carries one
+    pub mpn: Option<String>,
+    /// Manufacturer name, if the symbol carries one
+    pub manufacturer: Option<String>,
+    /// LCSC part number, if the symbol carries one
+    pub lcsc_part_number: Option<String>,
+}
+
+/// Components that share value, footprint and MPN, forming one BOM line
+#[derive(Debug, Clone)]
+pub struct BomGroup {
+    pub value: Option<String>,
+    pub footprint: Option<String>,
+    pub mpn: Option<String>,
+    pub manufacturer: Option<String>,
+    pub lcsc_part_number: Option<String>,
+    pub references: Vec<String>,
+}
+
+/// Look up the first non-empty string property matching one of `keys` (case-insensitive)
+fn find_property(properties: &serde_json::Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| {
+        properties
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .and_then(|(_, v)| v.as_str())
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty() && *v != "~")
+            .map(ToString::to_string)
+    })
+}
+
+/// Build a BomComponent from a single distilled component entry
+pub fn to_bom_component(reference: &str, comp: &Value) -> BomComponent {
+    let empty = serde_json::Map::new();
+    let properties = comp
+        .get("properties")
+        .and_then(|p| p.as_object())
+        .unwrap_or(&empty);
+
+    BomComponent {
+        reference: reference.to_string(),
+        value: comp
+            .get("value")
+            .and_then(|v| v.as_str())
+            .map(ToString::to_string),
+        footprint: comp
+            .get("footprint")
+            .and_then(|v| v.as_str())
+            .filter(|f| !f.is_empty())
+            .map(ToString::to_string),
+        mpn: mpn::extract(properties),
+        manufacturer: find_property(properties, MANUFACTURER_PROPERTY_KEYS),
+        lcsc_part_number: find_property(properties, LCSC_PROPERTY_KEYS),
+    }
+}
+
+/// Extract all components from distilled schematic data, sorted by reference.
+///
+/// Components can be a dict keyed by reference (from Python distiller) or an
+/// array of objects with a `reference` field.
+pub fn extract_components(distilled: &Value) -> Vec<BomComponent> {
+    let mut components: Vec<BomComponent> = match distilled.get("components") {
+        Some(Value::Object(obj)) => obj
+            .iter()
+            .map(|(reference, comp)| to_bom_component(reference, comp))
+            .collect(),
+        Some(Value::Array(arr)) => arr
+            .iter()
+            .filter_map(|comp| {
+                let reference = comp.get("reference").and_then(|r| r.as_str())?;
+                Some(to_bom_component(reference, comp))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    components.sort_by(|a, b| a.reference.cmp(&b.reference));
+    components
+}
+
+/// Group components into BOM lines keyed by (value, footprint, MPN).
+///
+/// Lines are ordered by their first reference designator. Power symbols and
+/// other virtual parts (references starting with '#') are skipped.
+pub fn group_components(components: &[BomComponent]) -> Vec<BomGroup> {
+    let mut groups: Vec<BomGroup> = Vec::new();
+
+    for component in components.iter().filter(|c| !c.reference.starts_with('#')) {
+        let existing = groups.iter_mut().find(|g| {
+            g.value == component.value
+                && g.footprint == component.footprint
+                && g.mpn == component.mpn
+        });
+
+        match existing {
+            Some(group) => {
+                group.references.push(component.reference.clone());
+                if group.manufacturer.is_none() {
+                    group.manufacturer = component.manufacturer.clone();
+                }
+                if group.lcsc_part_number.is_none() {
+                    group.lcsc_part_number = component.lcsc_part_number.clone();
+                }
+            }
+            None => groups.push(BomGroup {
+                value: component.value.clone(),
+                footprint: component.footprint.clone(),
+                mpn: component.mpn.clone(),
+                manufacturer: component.manufacturer.clone(),
+                lcsc_part_number: component.lcsc_part_number.clone(),
+                references: vec![component.reference.clone()],
+            }),
+        }
+    }
+
+    groups
+}
+
+/// Derive a stable part UUID from a reference designator.
+///
+/// Distilled output doesn't carry KiCad symbol UUIDs, so parts rows are keyed
+/// by a hash of the reference instead. The same reference always maps to the
+/// same UUID, which keeps upserts idempotent across runs.
+pub fn part_uuid_for_reference(reference: &str) -> Uuid {
+    let digest = Sha256::digest(reference.as_bytes());
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    uuid::Builder::from_custom_bytes(bytes).into_uuid()
+}
@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, warn};
+
+use crate::services::bom::{self, BomComponent, BomGroup};
+use crate::services::currency::{self, Rates};
+use crate::services::github;
+use crate::services::release_notes;
+use crate::types::{BomDiffLine, BomDiffResponse};
+use kicad_db::metrics::{self, PartPrice};
+use kicad_db::PgPool;
+
+pub const CHANGE_ADDED: &str = "added";
+pub const CHANGE_REMOVED: &str = "removed";
+pub const CHANGE_QUANTITY: &str = "quantity_changed";
+
+/// Value, footprint, MPN: what makes two components the same BOM line
+type LineKey = (Option<String>, Option<String>, Option<String>);
+
+fn lines_by_key(components: &BTreeMap<String, BomComponent>) -> BTreeMap<LineKey, BomGroup> {
+    let components: Vec<BomComponent> = components.values().cloned().collect();
+    bom::group_components(&components)
+        .into_iter()
+        .map(|g| ((g.value.clone(), g.footprint.clone(), g.mpn.clone()), g))
+        .collect()
+}
+
+/// Unit price of a line: the first of its references priced at `to`, else at `from`
+fn unit_price(
+    references: &[String],
+    to_prices: &HashMap<String, PartPrice>,
+    from_prices: &HashMap<String, PartPrice>,
+) -> Option<PartPrice> {
+    let priced_in = |prices: &HashMap<String, PartPrice>| {
+        references
+            .iter()
+            .find_map(|reference| prices.get(reference).cloned())
+    };
+    priced_in(to_prices).or_else(|| priced_in(from_prices))
+}
+
+/// Lines added, removed or changed in quantity between two component sets.
+/// Lines with the same value, footprint and MPN are matched; ones whose
+/// references merely moved are left out. Priced lines carry the cost change
+/// in their price's currency and, where `rates` allow, in `display_currency`.
+pub fn diff_lines(
+    before: &BTreeMap<String, BomComponent>,
+    after: &BTreeMap<String, BomComponent>,
+    from_prices: &HashMap<String, PartPrice>,
+    to_prices: &HashMap<String, PartPrice>,
+    rates: &Rates,
+    display_currency: &str,
+) -> Vec<BomDiffLine> {
+    let before = lines_by_key(before);
+    let mut after = lines_by_key(after);
+    let mut lines = Vec::new();
+
+    let mut push = |key: LineKey, old: Option<BomGroup>, new: Option<BomGroup>| {
+        let old_refs = old
+            .as_ref()
+            .map(|g| g.references.clone())
+            .unwrap_or_default();
+        let new_refs = new
+            .as_ref()
+            .map(|g| g.references.clone())
+            .unwrap_or_default();
+        if !old_refs.is_empty() && old_refs.len() == new_refs.len() {
+            return;
+        }
+        let change = match (old_refs.is_empty(), new_refs.is_empty()) {
+            (true, _) => CHANGE_ADDED,
+            (_, true) => CHANGE_REMOVED,
+            _ => CHANGE_QUANTITY,
+        };
+
+        let all_refs: Vec<String> = new_refs.iter().chain(&old_refs).cloned().collect();
+        let price = unit_price(&all_refs, to_prices, from_prices);
+        let quantity_delta = (new_refs.len() as i64 - old_refs.len() as i64) as f64;
+        let display_price = price
+            .as_ref()
+            .and_then(|p| rates.convert(p.unit_price, &p.currency, display_currency));
+        let manufacturer = new
+            .as_ref()
+            .and_then(|g| g.manufacturer.clone())
+            .or_else(|| old.as_ref().and_then(|g| g.manufacturer.clone()));
+
+        let (value, footprint, mpn) = key;
+        lines.push(BomDiffLine {
+            change: change.to_string(),
+            value,
+            footprint,
+            mpn,
+            manufacturer,
+            before_quantity: old_refs.len(),
+            after_quantity: new_refs.len(),
+            added_references: new_refs
+                .iter()
+                .filter(|r| !old_refs.contains(r))
+                .cloned()
+                .collect(),
+            removed_references: old_refs
+                .iter()
+                .filter(|r| !new_refs.contains(r))
+                .cloned()
+                .collect(),
+            unit_price: price.as_ref().map(|p| p.unit_price),
+            cost_delta: price.as_ref().map(|p| p.unit_price * quantity_delta),
+            currency: price.map(|p| p.currency),
+            display_unit_price: display_price,
+            display_cost_delta: display_price.map(|p| p * quantity_delta),
+        });
+    };
+
+    for (key, old) in before {
+        let new = after.remove(&key);
+        push(key, Some(old), new);
+    }
+    for (key, new) in after {
+        push(key, None, Some(new));
+    }
+
+    lines
+}
+
+/// Unit prices cached by part enrichment, keyed by reference. Pricing never
+/// blocks a diff, so failures read as unpriced.
+async fn cached_prices(pool: &PgPool, repo: &str, commit: &str) -> HashMap<String, PartPrice> {
+    let repo_url = github::repo_url(repo);
+    metrics::part_prices(pool, &repo_url, commit)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to read unit prices for {}/{}: {}", repo, commit, e);
+            HashMap::new()
+        })
+}
+
+/// Compare the BOMs of two resolved commits, showing costs in `display_currency`
+pub async fn diff(
+    pool: &PgPool,
+    repo: &str,
+    from_commit: &str,
+    to_commit: &str,
+    display_currency: &str,
+) -> Result<BomDiffResponse> {
+    let before = release_notes::components_at(pool, repo, from_commit)
+        .await
+        .with_context(|| format!("Failed to read components at {}", from_commit))?;
+    let after = release_notes::components_at(pool, repo, to_commit)
+        .await
+        .with_context(|| format!("Failed to read components at {}", to_commit))?;
+    let from_prices = cached_prices(pool, repo, from_commit).await;
+    let to_prices = cached_prices(pool, repo, to_commit).await;
+
+    let rates = currency::current_rates(pool).await;
+
+    let lines = diff_lines(
+        &before,
+        &after,
+        &from_prices,
+        &to_prices,
+        &rates,
+        display_currency,
+    );
+    let net_cost_delta = lines.iter().filter_map(|l| l.display_cost_delta).sum();
+    let unpriced_lines = lines
+        .iter()
+        .filter(|l| l.display_unit_price.is_none())
+        .count();
+
+    info!(
+        "BOM diff for {} {}..{}: {} line(s) changed, net cost {:+.4} {} ({} unpriced)",
+        repo,
+        from_commit,
+        to_commit,
+        lines.len(),
+        net_cost_delta,
+        display_currency,
+        unpriced_lines
+    );
+
+    Ok(BomDiffResponse {
+        repo: repo.to_string(),
+        from_commit: from_commit.to_string(),
+        to_commit: to_commit.to_string(),
+        lines,
+        display_currency: display_currency.to_string(),
+        exchange_rates_date: rates.fetched_on,
+        net_cost_delta,
+        unpriced_lines,
+    })
+}
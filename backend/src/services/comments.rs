@@ -0,0 +1,82 @@
+use tracing::warn;
+
+use crate::services::github;
+use crate::types::CommitCommentEntry;
+use kicad_db::{comments, read_pool, PgPool};
+
+/// Longest comment body we accept
+pub const MAX_BODY_CHARS: usize = 10_000;
+pub const MAX_AUTHOR_CHARS: usize = 100;
+
+/// A new comment's fields, trimmed and checked by `validate`
+#[derive(Debug, Clone, Copy)]
+pub struct ValidComment<'a> {
+    pub author: &'a str,
+    pub body: &'a str,
+    pub anchor_kind: Option<&'a str>,
+    pub anchor: Option<&'a str>,
+}
+
+/// Check a new comment: author and body within their limits, and an anchor
+/// given together with a known kind. Returns the reason it's refused.
+pub fn validate<'a>(
+    author: &'a str,
+    body: &'a str,
+    anchor_kind: Option<&'a str>,
+    anchor: Option<&'a str>,
+) -> Result<ValidComment<'a>, String> {
+    let author = author.trim();
+    let body = body.trim();
+    if author.is_empty() || author.chars().count() > MAX_AUTHOR_CHARS {
+        return Err(format!(
+            "author must be 1 to {} characters",
+            MAX_AUTHOR_CHARS
+        ));
+    }
+    if body.is_empty() || body.chars().count() > MAX_BODY_CHARS {
+        return Err(format!("body must be 1 to {} characters", MAX_BODY_CHARS));
+    }
+    let anchor = anchor.map(str::trim).filter(|a| !a.is_empty());
+    match (anchor_kind, anchor) {
+        (None, None) => {}
+        (Some(kind), Some(_)) if comments::ANCHOR_KINDS.contains(&kind) => {}
+        _ => {
+            return Err(format!(
+                "anchor_kind ({}) and anchor must be given together",
+                comments::ANCHOR_KINDS.join(" or ")
+            ))
+        }
+    }
+    Ok(ValidComment {
+        author,
+        body,
+        anchor_kind: anchor.and(anchor_kind),
+        anchor,
+    })
+}
+
+/// API representation of a stored comment
+pub fn to_entry(comment: comments::CommitComment) -> CommitCommentEntry {
+    CommitCommentEntry {
+        id: comment.id,
+        parent_id: comment.parent_id,
+        author: comment.author,
+        body: comment.body,
+        anchor_kind: comment.anchor_kind,
+        anchor: comment.anchor,
+        created_at: comment.created_at,
+    }
+}
+
+/// Comments on a commit, for responses that carry them next to AI summaries.
+/// A failed lookup is logged and yields none rather than failing the response.
+pub async fn for_commit(pool: &PgPool, repo: &str, commit: &str) -> Vec<CommitCommentEntry> {
+    let repo_url = github::repo_url(repo);
+    match comments::list_comments(read_pool(pool), &repo_url, commit).await {
+        Ok(comments) => comments.into_iter().map(to_entry).collect(),
+        Err(e) => {
+            warn!("Failed to load comments for {}@{}: {}", repo, commit, e);
+            Vec::new()
+        }
+    }
+}
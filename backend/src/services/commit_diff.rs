@@ -0,0 +1,210 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::services::{distill, git, release_notes};
+use crate::types::BomDelta;
+use kicad_db::canonical::content_hash;
+use kicad_db::PgPool;
+
+/// Connections of each net: net name -> reference -> pins
+pub type NetMembers = BTreeMap<String, BTreeMap<String, BTreeSet<String>>>;
+
+/// What a commit changed in the design relative to its first parent, computed
+/// from the distilled data of both sides
+#[derive(Debug, Default)]
+pub struct CommitDiff {
+    /// Commit diffed against, normally the first parent; None for a root
+    /// commit, which is diffed against an empty design
+    pub parent: Option<String>,
+    /// Schematic files the commit touched
+    pub changed_files: Vec<String>,
+    pub bom: BomDelta,
+    /// Nets only present after the commit, sorted
+    pub nets_added: Vec<String>,
+    /// Nets only present before the commit, sorted
+    pub nets_removed: Vec<String>,
+    /// Nets present on both sides whose connections changed, sorted
+    pub nets_changed: Vec<String>,
+    /// Components after the commit
+    pub component_count: usize,
+    /// Nets after the commit
+    pub net_count: usize,
+    /// Distilled data before the commit, for checks that need more than the lists above
+    pub before: Value,
+    /// Distilled data after the commit
+    pub after: Value,
+}
+
+impl CommitDiff {
+    /// True when neither components nor nets changed
+    pub fn is_empty(&self) -> bool {
+        self.bom.added.is_empty()
+            && self.bom.removed.is_empty()
+            && self.bom.changed.is_empty()
+            && self.nets_added.is_empty()
+            && self.nets_removed.is_empty()
+            && self.nets_changed.is_empty()
+    }
+
+    /// SHA-256 of what the commit changed, independent of which commit it is:
+    /// the touched files, the BOM delta and the connections on both sides of
+    /// every added, removed or changed net. A cherry-picked change, or one
+    /// re-applied after a revert, hashes the same.
+    pub fn content_hash(&self) -> String {
+        let before = net_members(&self.before);
+        let after = net_members(&self.after);
+        let nets = |names: &[String]| {
+            names
+                .iter()
+                .map(|name| json!([name, before.get(name), after.get(name)]))
+                .collect::<Vec<_>>()
+        };
+        content_hash(&json!({
+            "changed_files": self.changed_files,
+            "bom": self.bom,
+            "nets_added": nets(&self.nets_added),
+            "nets_removed": nets(&self.nets_removed),
+            "nets_changed": nets(&self.nets_changed),
+        }))
+    }
+
+    /// Labelled nets that disappeared while a net with exactly the same
+    /// connections appeared: (old, new), sorted by old name
+    pub fn net_renames(&self) -> Vec<(String, String)> {
+        let before = net_members(&self.before);
+        let after = net_members(&self.after);
+
+        let mut taken = BTreeSet::new();
+        let mut renames = Vec::new();
+        for old in self.nets_removed.iter().filter(|n| !is_generated_net(n)) {
+            let Some(members) = before.get(old).filter(|m| !m.is_empty()) else {
+                continue;
+            };
+            let new = self.nets_added.iter().find(|new| {
+                !is_generated_net(new) && !taken.contains(*new) && after.get(*new) == Some(members)
+            });
+            if let Some(new) = new {
+                taken.insert(new.clone());
+                renames.push((old.clone(), new.clone()));
+            }
+        }
+        renames
+    }
+}
+
+/// Names generated for unlabelled nets, which shift whenever the wiring does
+pub fn is_generated_net(name: &str) -> bool {
+    name.starts_with("Net-") || name.contains(":Net-")
+}
+
+// Net members list pins as {"Pin": "1"}; older results used bare pin numbers
+fn pin_label(pin: &Value) -> String {
+    match pin.get("Pin").unwrap_or(pin) {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Connections of every net in distilled data
+pub fn net_members(distilled: &Value) -> NetMembers {
+    let Some(nets) = distilled.get("nets").and_then(Value::as_object) else {
+        return NetMembers::new();
+    };
+    nets.iter()
+        .map(|(name, members)| {
+            let members = members
+                .as_object()
+                .map(|members| {
+                    members
+                        .iter()
+                        .map(|(reference, pins)| {
+                            let pins = pins
+                                .as_array()
+                                .map(|pins| pins.iter().map(pin_label).collect())
+                                .unwrap_or_default();
+                            (reference.clone(), pins)
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            (name.clone(), members)
+        })
+        .collect()
+}
+
+/// Diff two sides of a commit's distilled data
+pub fn diff(
+    parent: Option<String>,
+    changed_files: Vec<String>,
+    before: Value,
+    after: Value,
+) -> CommitDiff {
+    let components_before = release_notes::components_of(&before);
+    let components_after = release_notes::components_of(&after);
+    let nets_before = net_members(&before);
+    let nets_after = net_members(&after);
+
+    CommitDiff {
+        parent,
+        changed_files,
+        bom: release_notes::bom_delta(&components_before, &components_after),
+        nets_added: nets_after
+            .keys()
+            .filter(|name| !nets_before.contains_key(*name))
+            .cloned()
+            .collect(),
+        nets_removed: nets_before
+            .keys()
+            .filter(|name| !nets_after.contains_key(*name))
+            .cloned()
+            .collect(),
+        nets_changed: nets_after
+            .iter()
+            .filter(|(name, members)| nets_before.get(*name).is_some_and(|was| was != *members))
+            .map(|(name, _)| name.clone())
+            .collect(),
+        component_count: components_after.len(),
+        net_count: nets_after.len(),
+        before,
+        after,
+    }
+}
+
+/// Distilled data of a commit, empty when the commit has no schematics
+async fn distilled_at(pool: &PgPool, repo: &str, commit: &str) -> Result<Value> {
+    let files = git::get_schematic_files(repo, commit).await?;
+    if !files.iter().any(|f| f.path.ends_with(".kicad_sch")) {
+        return Ok(serde_json::json!({}));
+    }
+    distill::get_or_distill(pool, repo, commit).await
+}
+
+/// Diff a commit against its first parent
+pub async fn compute(pool: &PgPool, repo: &str, commit: &str) -> Result<CommitDiff> {
+    let parent = git::get_parent_commit(repo, commit).await?;
+    compute_against(pool, repo, parent, commit).await
+}
+
+/// Diff a commit against `base`, or against an empty design when there is none.
+/// `changed_files` still lists what the commit itself touched.
+pub async fn compute_against(
+    pool: &PgPool,
+    repo: &str,
+    base: Option<String>,
+    commit: &str,
+) -> Result<CommitDiff> {
+    let changed_files = git::get_changed_schematic_files(repo, commit).await?;
+
+    let after = distilled_at(pool, repo, commit)
+        .await
+        .with_context(|| format!("Failed to distill {}", commit))?;
+    let before = match &base {
+        Some(base) => distilled_at(pool, repo, base)
+            .await
+            .with_context(|| format!("Failed to distill {}", base))?,
+        None => serde_json::json!({}),
+    };
+
+    Ok(diff(base, changed_files, before, after))
+}
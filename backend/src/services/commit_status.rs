@@ -0,0 +1,98 @@
+use reqwest::Url;
+use tracing::{info, warn};
+
+use crate::services::github::{self, CommitStatus};
+use crate::services::verdict;
+use crate::types::CiVerdictResponse;
+use crate::versioning;
+use kicad_db::{repos, PgPool};
+
+/// Context the statuses are posted under; GitHub keeps the latest per context
+pub const CONTEXT: &str = "grokicad";
+
+/// Link to a commit's report page, or None without PUBLIC_URL (GitHub only
+/// accepts absolute links)
+fn report_url(repo_slug: &str, commit_hash: &str) -> Option<String> {
+    let origin = std::env::var("PUBLIC_URL").ok()?;
+    let origin = origin.trim().trim_end_matches('/');
+    if origin.is_empty() {
+        return None;
+    }
+    Url::parse_with_params(
+        &format!("{}{}/report/commit", origin, versioning::CURRENT_PREFIX),
+        [("repo", repo_slug), ("commit", commit_hash)],
+    )
+    .map(String::from)
+    .map_err(|e| warn!("Invalid PUBLIC_URL {:?}: {}", origin, e))
+    .ok()
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+/// The status for a verdict: failing checks fail the commit, warnings don't.
+/// E.g. "analyzed, 2 warnings".
+fn describe(verdict: &CiVerdictResponse) -> (&'static str, String) {
+    let count = |status: &str| verdict.checks.iter().filter(|c| c.status == status).count();
+    let (failing, warnings) = (count("fail"), count("warn"));
+    let mut findings = Vec::new();
+    if failing > 0 {
+        findings.push(plural(failing, "failing check"));
+    }
+    if warnings > 0 {
+        findings.push(plural(warnings, "warning"));
+    }
+    if findings.is_empty() {
+        findings.push("no findings".to_string());
+    }
+    let state = if failing > 0 { "failure" } else { "success" };
+    (state, format!("analyzed, {}", findings.join(", ")))
+}
+
+/// Post a commit status summarizing the CI checks of a processed commit
+/// (see `verdict::evaluate`), linking to its report page. Only for
+/// repositories with commit statuses turned on (`repos.commit_status`).
+///
+/// Best effort: failures are logged, never returned, so a GitHub outage or a
+/// missing App installation doesn't fail the processing.
+pub async fn post(pool: &PgPool, repo_slug: &str, commit_hash: &str) {
+    let repo_url = github::repo_url(repo_slug);
+    match repos::find_repo(pool, &repo_url).await {
+        Ok(Some(repo)) if repo.commit_status => {}
+        Ok(_) => return,
+        Err(e) => {
+            warn!("Failed to look up {} for commit statuses: {}", repo_slug, e);
+            return;
+        }
+    }
+
+    let (state, description) =
+        match verdict::evaluate(pool, repo_slug, commit_hash, None, false, true).await {
+            Ok(verdict) => describe(&verdict),
+            Err(e) => {
+                warn!(
+                    "Failed to run checks for the status of {}/{}: {:#}",
+                    repo_slug, commit_hash, e
+                );
+                ("error", "analysis failed".to_string())
+            }
+        };
+    let status = CommitStatus {
+        state,
+        description,
+        target_url: report_url(repo_slug, commit_hash),
+        context: CONTEXT,
+    };
+
+    match github::create_commit_status(repo_slug, commit_hash, &status).await {
+        Ok(()) => info!(
+            "Posted commit status for {}/{}: {} ({})",
+            repo_slug, commit_hash, status.state, status.description
+        ),
+        Err(e) => warn!(
+            "Failed to post commit status for {}/{}: {:#}",
+            repo_slug, commit_hash, e
+        ),
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use crate::services::comments::MAX_AUTHOR_CHARS;
+use crate::services::github;
+use crate::types::ComponentNoteEntry;
+use kicad_db::{component_notes, read_pool, PgPool};
+
+/// Longest note we accept; notes go into prompts whole
+pub const MAX_NOTE_CHARS: usize = 2_000;
+const MAX_REFERENCE_CHARS: usize = 64;
+
+/// A note update's fields, trimmed and checked by `validate`
+#[derive(Debug, Clone, Copy)]
+pub struct ValidNote<'a> {
+    pub reference: &'a str,
+    /// None removes the note
+    pub note: Option<&'a str>,
+    pub author: Option<&'a str>,
+}
+
+/// Check a note update: a reference without spaces, and a note and author
+/// within their limits. Returns the reason it's refused.
+pub fn validate<'a>(
+    reference: &'a str,
+    note: Option<&'a str>,
+    author: Option<&'a str>,
+) -> Result<ValidNote<'a>, String> {
+    let reference = reference.trim();
+    if reference.is_empty()
+        || reference.chars().count() > MAX_REFERENCE_CHARS
+        || reference.contains(char::is_whitespace)
+    {
+        return Err(format!(
+            "reference must be 1 to {} characters without spaces",
+            MAX_REFERENCE_CHARS
+        ));
+    }
+    let note = note.map(str::trim).filter(|n| !n.is_empty());
+    if note.is_some_and(|n| n.chars().count() > MAX_NOTE_CHARS) {
+        return Err(format!(
+            "note must be at most {} characters",
+            MAX_NOTE_CHARS
+        ));
+    }
+    let author = author.map(str::trim).filter(|a| !a.is_empty());
+    if author.is_some_and(|a| a.chars().count() > MAX_AUTHOR_CHARS) {
+        return Err(format!(
+            "author must be at most {} characters",
+            MAX_AUTHOR_CHARS
+        ));
+    }
+    Ok(ValidNote {
+        reference,
+        note,
+        author,
+    })
+}
+
+/// API representation of a stored note
+pub fn to_entry(note: component_notes::ComponentNote) -> ComponentNoteEntry {
+    ComponentNoteEntry {
+        reference: note.reference,
+        note: note.note,
+        author: note.author,
+        updated_at: note.updated_at,
+    }
+}
+
+/// A repository's notes by reference, for prompts that include components.
+/// Auxiliary to the design data, so a failed lookup is logged and yields none.
+pub async fn for_repo(pool: &PgPool, repo: &str) -> BTreeMap<String, String> {
+    let repo_url = github::repo_url(repo);
+    match component_notes::list_notes(read_pool(pool), &repo_url).await {
+        Ok(notes) => notes.into_iter().map(|n| (n.reference, n.note)).collect(),
+        Err(e) => {
+            warn!("Failed to load component notes for {}: {}", repo, e);
+            BTreeMap::new()
+        }
+    }
+}
+
+/// Prompt section with the notes of `references`, or "" when none has one
+pub fn prompt_section<'a>(
+    notes: &BTreeMap<String, String>,
+    references: impl IntoIterator<Item = &'a str>,
+) -> String {
+    let mut section = String::new();
+    for reference in references {
+        if let Some(note) = notes.get(reference) {
+            section.push_str(&format!("- {}: {}\n", reference, note));
+        }
+    }
+    if section.is_empty() {
+        return section;
+    }
+    format!("\n## Notes From the Team\n{}", section)
+}
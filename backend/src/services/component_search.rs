@@ -0,0 +1,126 @@
+use serde_json::Value;
+
+use crate::services::{bom, github, mpn};
+use crate::types::{ComponentSearchCommit, ComponentSearchMatch};
+use kicad_db::component_search::{self, ComponentMatch, ComponentSearch};
+use kicad_db::PgPool;
+
+pub const DEFAULT_LIMIT: i64 = 500;
+pub const MAX_LIMIT: i64 = 5000;
+
+/// Filters for [`search`], as user-facing patterns
+#[derive(Debug, Default)]
+pub struct SearchPatterns<'a> {
+    pub query: Option<&'a str>,
+    pub value: Option<&'a str>,
+    pub mpn: Option<&'a str>,
+    pub lib_id: Option<&'a str>,
+}
+
+/// Matches grouped by commit, plus whether the limit cut the results short
+#[derive(Debug)]
+pub struct SearchResults {
+    pub commits: Vec<ComponentSearchCommit>,
+    pub total_matches: usize,
+    pub truncated: bool,
+}
+
+/// Convert a user pattern to a case-insensitive LIKE pattern.
+///
+/// `*` and `?` are wildcards; a pattern without wildcards matches anywhere in
+/// the field, so "LM317" finds "LM317T" and "LM317_TO-220".
+pub fn like_pattern(pattern: &str) -> String {
+    let mut like = String::with_capacity(pattern.len() + 2);
+    for c in pattern.chars() {
+        match c {
+            '*' => like.push('%'),
+            '?' => like.push('_'),
+            '%' | '_' | '\\' => {
+                like.push('\\');
+                like.push(c);
+            }
+            c => like.push(c),
+        }
+    }
+    if !pattern.contains(['*', '?']) {
+        like = format!("%{}%", like);
+    }
+    like
+}
+
+fn to_match(m: &ComponentMatch) -> ComponentSearchMatch {
+    let text = |key: &str| {
+        m.component
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|v| !v.is_empty())
+            .map(ToString::to_string)
+    };
+    let stored_mpn = m.part_properties.as_ref().and_then(|p| {
+        p.get("mpn")
+            .or_else(|| p.pointer("/digikey/manufacturer_part_number"))
+            .and_then(Value::as_str)
+            .and_then(mpn::normalize)
+    });
+    let component = bom::to_bom_component(&m.reference, &m.component);
+
+    ComponentSearchMatch {
+        reference: m.reference.clone(),
+        value: component.value,
+        lib_id: text("lib_id"),
+        footprint: component.footprint,
+        mpn: component.mpn.or(stored_mpn),
+        sheet_path: text("sheet_path"),
+    }
+}
+
+/// Search every indexed commit of a repository for matching components
+pub async fn search(
+    pool: &PgPool,
+    repo: &str,
+    patterns: &SearchPatterns<'_>,
+    limit: i64,
+) -> Result<SearchResults, sqlx::Error> {
+    let query = patterns.query.map(like_pattern);
+    let value = patterns.value.map(like_pattern);
+    let mpn_pattern = patterns.mpn.map(like_pattern);
+    let lib_id = patterns.lib_id.map(like_pattern);
+
+    let repo_url = github::repo_url(repo);
+    // One extra row tells us whether the limit truncated the results
+    let mut matches = component_search::search_components(
+        pool,
+        &repo_url,
+        &ComponentSearch {
+            value: value.as_deref(),
+            lib_id: lib_id.as_deref(),
+            mpn: mpn_pattern.as_deref(),
+            any: query.as_deref(),
+            mpn_keys: mpn::property_keys().to_vec(),
+            limit: limit + 1,
+        },
+    )
+    .await?;
+    let truncated = matches.len() as i64 > limit;
+    matches.truncate(limit as usize);
+
+    // Rows arrive ordered by commit, so consecutive rows share a commit
+    let mut commits: Vec<ComponentSearchCommit> = Vec::new();
+    for m in &matches {
+        match commits.last_mut() {
+            Some(commit) if commit.commit == m.commit_hash => commit.matches.push(to_match(m)),
+            _ => commits.push(ComponentSearchCommit {
+                commit: m.commit_hash.clone(),
+                commit_date: m.commit_date,
+                message: m.git_message.clone(),
+                matches: vec![to_match(m)],
+            }),
+        }
+    }
+
+    Ok(SearchResults {
+        commits,
+        total_matches: matches.len(),
+        truncated,
+    })
+}
@@ -0,0 +1,134 @@
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::services::commit_diff::{self, CommitDiff};
+use crate::services::test_points::TEST_POINT_CATEGORY;
+use crate::types::{ConnectorPinChange, ConnectorPinoutChange};
+
+/// Component category the distiller assigns to connectors
+const CONNECTOR_CATEGORY: &str = "connector";
+// Reference prefixes of connectors, for results distilled before the category existed
+const CONNECTOR_PREFIXES: &[&str] = &["J", "CN"];
+
+fn component<'a>(distilled: &'a Value, reference: &str) -> Option<&'a Value> {
+    distilled.get("components")?.get(reference)
+}
+
+/// Whether a distilled component is a connector
+pub fn is_connector(distilled: &Value, reference: &str) -> bool {
+    let Some(comp) = component(distilled, reference) else {
+        return false;
+    };
+    match comp.get("category").and_then(Value::as_str) {
+        Some(CONNECTOR_CATEGORY) => true,
+        // Test points come from the Connector library but have no mating side
+        Some(TEST_POINT_CATEGORY) => false,
+        Some(_) | None => {
+            let prefix: String = reference
+                .chars()
+                .take_while(char::is_ascii_alphabetic)
+                .collect();
+            let lib_id = comp
+                .get("lib_id")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            CONNECTOR_PREFIXES.contains(&prefix.to_ascii_uppercase().as_str())
+                || lib_id.to_ascii_lowercase().starts_with("connector")
+        }
+    }
+}
+
+/// Pin number -> (pin name, net) of a component
+fn pinout(
+    distilled: &Value,
+    reference: &str,
+) -> BTreeMap<String, (Option<String>, Option<String>)> {
+    component(distilled, reference)
+        .and_then(|c| c.get("pins"))
+        .and_then(Value::as_array)
+        .map(|pins| {
+            pins.iter()
+                .filter_map(|pin| {
+                    let number = pin.get("number").and_then(Value::as_str)?;
+                    let name = pin
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .filter(|n| !n.is_empty() && *n != "~")
+                        .map(ToString::to_string);
+                    let net = pin
+                        .get("net")
+                        .and_then(Value::as_str)
+                        .map(ToString::to_string);
+                    Some((number.to_string(), (name, net)))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Net compared across the commit: generated names collapse to one placeholder
+/// since they shift whenever the wiring does, and renamed labelled nets map to
+/// their new name
+fn comparable<'a>(net: Option<&'a str>, renames: &HashMap<&str, &'a str>) -> Option<&'a str> {
+    net.map(|net| {
+        if commit_diff::is_generated_net(net) {
+            "(unnamed)"
+        } else {
+            renames.get(net).copied().unwrap_or(net)
+        }
+    })
+}
+
+/// Connectors present on both sides of the diff whose pins moved to different
+/// nets, were connected or disconnected, or appeared or disappeared. Renaming a
+/// net without changing its connections isn't a pinout change.
+pub fn pinout_changes(diff: &CommitDiff) -> Vec<ConnectorPinoutChange> {
+    let renames = diff.net_renames();
+    let renames: HashMap<&str, &str> = renames
+        .iter()
+        .map(|(old, new)| (old.as_str(), new.as_str()))
+        .collect();
+
+    let Some(components) = diff.after.get("components").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+
+    components
+        .iter()
+        .filter(|(reference, _)| {
+            component(&diff.before, reference).is_some() && is_connector(&diff.after, reference)
+        })
+        .filter_map(|(reference, comp)| {
+            let before = pinout(&diff.before, reference);
+            let after = pinout(&diff.after, reference);
+            let numbers: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+
+            let pins: Vec<ConnectorPinChange> = numbers
+                .into_iter()
+                .filter_map(|number| {
+                    let (old_name, was) = before.get(number).cloned().unwrap_or_default();
+                    let (new_name, now) = after.get(number).cloned().unwrap_or_default();
+                    let moved = !before.contains_key(number)
+                        || !after.contains_key(number)
+                        || comparable(was.as_deref(), &renames)
+                            != comparable(now.as_deref(), &HashMap::new());
+                    moved.then(|| ConnectorPinChange {
+                        pin: number.clone(),
+                        name: new_name.or(old_name),
+                        before: was,
+                        after: now,
+                    })
+                })
+                .collect();
+
+            (!pins.is_empty()).then(|| ConnectorPinoutChange {
+                reference: reference.clone(),
+                value: comp
+                    .get("value")
+                    .and_then(Value::as_str)
+                    .map(ToString::to_string),
+                pins,
+            })
+        })
+        .collect()
+}
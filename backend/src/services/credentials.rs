@@ -0,0 +1,142 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tracing::{info, warn};
+
+use crate::services::digikey::DigiKeyClient;
+use crate::services::secret_store;
+use kicad_db::credentials::{self, ClientCredentials};
+use kicad_db::secrets::Keyring;
+use kicad_db::PgPool;
+
+/// Where a provider's active credentials came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialSource {
+    Database,
+    Environment,
+}
+
+impl CredentialSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialSource::Database => "database",
+            CredentialSource::Environment => "environment",
+        }
+    }
+}
+
+/// Decrypted OAuth client credentials for a supplier API
+#[derive(Debug, Clone)]
+pub struct ProviderCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    pub source: CredentialSource,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Environment variable fallback, e.g. DIGIKEY_CLIENT_ID / DIGIKEY_CLIENT_SECRET
+fn from_environment(provider: &str) -> Option<ProviderCredentials> {
+    let prefix = provider.to_uppercase();
+    let client_id = std::env::var(format!("{}_CLIENT_ID", prefix)).ok()?;
+    let client_secret = std::env::var(format!("{}_CLIENT_SECRET", prefix)).ok()?;
+    if client_id.is_empty() || client_secret.is_empty() {
+        return None;
+    }
+    Some(ProviderCredentials {
+        client_id,
+        client_secret,
+        source: CredentialSource::Environment,
+        updated_at: None,
+    })
+}
+
+/// Get the active credentials for a provider (database first, then environment).
+/// Stored credentials are opened on every call; ones that don't open are
+/// skipped, as `secret_store::reload` warns.
+pub fn get(provider: &str) -> Option<ProviderCredentials> {
+    if let Some(creds) = stored(provider) {
+        return Some(creds);
+    }
+    from_environment(provider)
+}
+
+fn stored(provider: &str) -> Option<ProviderCredentials> {
+    let sealed = secret_store::sealed(&credentials::secret_name(provider))?;
+    let creds = credentials::open(&sealed, &Keyring::from_env().ok()?).ok()?;
+    Some(ProviderCredentials {
+        client_id: creds.client_id,
+        client_secret: creds.client_secret,
+        source: CredentialSource::Database,
+        updated_at: Some(sealed.updated_at),
+    })
+}
+
+/// Whether credentials can be encrypted/decrypted: the keys of
+/// `kicad_db::secrets` are configured and valid
+pub fn is_encryption_configured() -> bool {
+    Keyring::from_env().is_ok()
+}
+
+/// Move any credentials still in the legacy table into the secrets table.
+/// Called at startup, before the stored secrets are loaded.
+pub async fn migrate_legacy(pool: &PgPool) -> Result<()> {
+    if credentials::list_credentials(pool).await?.is_empty() {
+        return Ok(());
+    }
+    let moved = credentials::migrate_legacy(pool, &Keyring::from_env()?).await?;
+    if !moved.is_empty() {
+        info!(
+            "Moved stored credentials of {} to the secrets table",
+            moved.join(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Called by `secret_store::reload` for every stored secret that changed, so
+/// access tokens issued with credentials that changed are dropped
+pub fn secret_changed(name: &str) {
+    if let Some(provider) = credentials::provider_of(name) {
+        invalidate_tokens(provider);
+    }
+}
+
+/// Encrypt and store new credentials for a provider, replacing any existing ones.
+/// Takes effect immediately; cached access tokens for the provider are dropped.
+pub async fn set(
+    pool: &PgPool,
+    provider: &str,
+    client_id: &str,
+    client_secret: &str,
+) -> Result<()> {
+    let creds = ClientCredentials {
+        client_id: client_id.to_string(),
+        client_secret: client_secret.to_string(),
+    };
+    credentials::put(pool, &Keyring::from_env()?, provider, &creds).await?;
+    reload(pool).await;
+    invalidate_tokens(provider);
+    info!("Rotated credentials for provider {}", provider);
+    Ok(())
+}
+
+/// Delete stored credentials for a provider, falling back to the environment
+pub async fn remove(pool: &PgPool, provider: &str) -> Result<bool> {
+    let removed = credentials::remove(pool, provider).await?;
+    reload(pool).await;
+    invalidate_tokens(provider);
+    Ok(removed)
+}
+
+// Pick up a change made here without waiting for its notification
+async fn reload(pool: &PgPool) {
+    if let Err(e) = secret_store::reload(pool).await {
+        warn!("Failed to reload stored secrets: {}", e);
+    }
+}
+
+/// Drop any cached access tokens issued with the old credentials
+fn invalidate_tokens(provider: &str) {
+    if provider == "digikey" {
+        DigiKeyClient::invalidate_token();
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, Utc};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use kicad_db::exchange_rates::{latest_rates, store_rates};
+use kicad_db::PgPool;
+
+/// Currency rates are fetched against, and that stored costs (design metrics)
+/// are kept in. Distributor prices are requested in it.
+pub const BASE_CURRENCY: &str = "USD";
+
+// Default for EXCHANGE_RATES_URL: free, keyless daily rates for BASE_CURRENCY
+const DEFAULT_RATES_URL: &str = "https://open.er-api.com/v6/latest/USD";
+
+static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(15))
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+// How long stale rates are used after a failed fetch before fetching again
+const RETRY_AFTER: Duration = Duration::from_secs(60 * 60);
+
+// Rates loaded last and when, so conversions don't hit the database each time
+static CACHE: Lazy<Mutex<Option<(Rates, Instant)>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Deserialize)]
+struct RatesPayload {
+    rates: HashMap<String, f64>,
+}
+
+/// A three-letter ISO 4217 code, uppercased; None for anything else
+pub fn parse_code(code: &str) -> Option<String> {
+    let code = code.trim();
+    (code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()))
+        .then(|| code.to_ascii_uppercase())
+}
+
+/// Currency costs are shown in (DISPLAY_CURRENCY, default the base currency)
+pub fn display_currency() -> String {
+    match std::env::var("DISPLAY_CURRENCY") {
+        Ok(code) if !code.trim().is_empty() => parse_code(&code).unwrap_or_else(|| {
+            warn!(
+                "Invalid DISPLAY_CURRENCY {:?}; using {}",
+                code, BASE_CURRENCY
+            );
+            BASE_CURRENCY.to_string()
+        }),
+        _ => BASE_CURRENCY.to_string(),
+    }
+}
+
+/// Exchange rates: units of each currency per unit of `BASE_CURRENCY`
+#[derive(Debug, Clone, Default)]
+pub struct Rates {
+    /// Day the rates were fetched; None when none could be loaded
+    pub fetched_on: Option<NaiveDate>,
+    rates: HashMap<String, f64>,
+}
+
+impl Rates {
+    fn rate(&self, currency: &str) -> Option<f64> {
+        if currency.eq_ignore_ascii_case(BASE_CURRENCY) {
+            return Some(1.0);
+        }
+        self.rates
+            .get(&currency.to_ascii_uppercase())
+            .copied()
+            .filter(|r| *r > 0.0)
+    }
+
+    /// Convert an amount between currencies. None when either rate is unknown.
+    pub fn convert(&self, amount: f64, from: &str, to: &str) -> Option<f64> {
+        if from.eq_ignore_ascii_case(to) {
+            return Some(amount);
+        }
+        Some(amount / self.rate(from)? * self.rate(to)?)
+    }
+
+    /// Whether amounts can be converted to `currency`
+    pub fn knows(&self, currency: &str) -> bool {
+        self.rate(currency).is_some()
+    }
+}
+
+async fn fetch_rates() -> Result<HashMap<String, f64>> {
+    let url = std::env::var("EXCHANGE_RATES_URL")
+        .ok()
+        .filter(|u| !u.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_RATES_URL.to_string());
+    let response = HTTP_CLIENT
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch exchange rates from {}", url))?
+        .error_for_status()
+        .context("Exchange rate request failed")?;
+    let payload: RatesPayload = response
+        .json()
+        .await
+        .context("Failed to parse exchange rates")?;
+    Ok(payload.rates)
+}
+
+/// Today's exchange rates. Rates are fetched at most once a day and stored;
+/// when fetching fails the latest stored rates are used, and without any
+/// only same-currency conversions succeed.
+pub async fn current_rates(pool: &PgPool) -> Rates {
+    let today = Utc::now().date_naive();
+    let mut cache = CACHE.lock().await;
+    if let Some((rates, loaded)) = cache.as_ref() {
+        if rates.fetched_on == Some(today) || loaded.elapsed() < RETRY_AFTER {
+            return rates.clone();
+        }
+    }
+
+    let stored = match latest_rates(pool, BASE_CURRENCY).await {
+        Ok(stored) => stored.map(|s| Rates {
+            fetched_on: Some(s.fetched_on),
+            rates: s.by_currency(),
+        }),
+        Err(e) => {
+            warn!("Failed to read stored exchange rates: {}", e);
+            None
+        }
+    };
+    if let Some(rates) = stored.as_ref().filter(|r| r.fetched_on == Some(today)) {
+        *cache = Some((rates.clone(), Instant::now()));
+        return rates.clone();
+    }
+
+    let rates = match fetch_rates().await {
+        Ok(fetched) => {
+            info!("Fetched {} exchange rate(s)", fetched.len());
+            if let Err(e) = store_rates(pool, BASE_CURRENCY, today, &fetched).await {
+                warn!("Failed to store exchange rates: {}", e);
+            }
+            Rates {
+                fetched_on: Some(today),
+                rates: fetched,
+            }
+        }
+        Err(e) => {
+            warn!("{:#}; using the latest stored rates", e);
+            stored.unwrap_or_default()
+        }
+    };
+    *cache = Some((rates.clone(), Instant::now()));
+    rates
+}
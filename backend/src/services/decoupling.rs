@@ -0,0 +1,346 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::services::{distill, risk};
+use crate::types::{
+    DecouplingCapacitor, DecouplingCheckResponse, DecouplingEntry, SharedDecouplingCapacitor,
+};
+use kicad_db::PgPool;
+
+/// Capacitors further than this from an IC don't count as its decoupling. The
+/// distiller links IC/capacitor pairs up to 1.5x its 20 mm proximity radius.
+pub const DECOUPLING_RADIUS_MM: f64 = 30.0;
+
+pub const STATUS_OK: &str = "ok";
+/// Fewer nearby capacitors than supply pins on the net
+pub const STATUS_INSUFFICIENT: &str = "insufficient";
+/// Decoupling exists on the net, but none of it near the IC
+pub const STATUS_DISTANT: &str = "distant";
+/// No capacitor connects the net to ground anywhere in the design
+pub const STATUS_MISSING: &str = "missing";
+
+/// Decoupling of every IC supply net in a design
+#[derive(Debug, Default)]
+pub struct DecouplingAnalysis {
+    pub entries: Vec<DecouplingEntry>,
+    pub shared_capacitors: Vec<SharedDecouplingCapacitor>,
+}
+
+impl DecouplingAnalysis {
+    /// Entries whose decoupling is missing or not near the IC
+    pub fn issues(&self) -> impl Iterator<Item = &DecouplingEntry> {
+        self.entries
+            .iter()
+            .filter(|e| e.status == STATUS_MISSING || e.status == STATUS_DISTANT)
+    }
+}
+
+// Pin names of supply inputs, for pins on nets whose names don't say so
+const SUPPLY_PIN_PREFIXES: &[&str] = &["VDD", "VCC", "AVDD", "DVDD", "VBAT", "VIN", "V+"];
+
+/// Net name without its sheet path ("/power/+3V3" -> "+3V3")
+pub fn base_name(net: &str) -> &str {
+    net.rsplit(['/', ':']).next().unwrap_or(net)
+}
+
+pub fn is_ground_net(net: &str) -> bool {
+    let name = base_name(net).to_ascii_uppercase();
+    name.starts_with("GND") || name.ends_with("GND") || name.starts_with("VSS") || name == "0V"
+}
+
+fn is_supply_net(net: &str) -> bool {
+    !is_ground_net(net) && risk::is_power_net(base_name(net))
+}
+
+fn is_supply_pin(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SUPPLY_PIN_PREFIXES.iter().any(|p| name.starts_with(p))
+}
+
+fn category(component: &Value) -> Option<&str> {
+    component.get("category").and_then(Value::as_str)
+}
+
+fn has_prefix(reference: &str, prefix: &str) -> bool {
+    reference
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+fn is_ic(reference: &str, component: &Value) -> bool {
+    category(component) == Some("ic") || has_prefix(reference, "U")
+}
+
+fn is_capacitor(reference: &str, component: &Value) -> bool {
+    category(component) == Some("capacitor") || has_prefix(reference, "C")
+}
+
+fn value(component: &Value) -> Option<String> {
+    component
+        .get("value")
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+}
+
+fn sheet(component: &Value) -> Option<&str> {
+    component.get("sheet_path").and_then(Value::as_str)
+}
+
+/// Pins of a component as (number, name, net), unconnected pins left out
+fn connected_pins(component: &Value) -> Vec<(String, Option<String>, String)> {
+    component
+        .get("pins")
+        .and_then(Value::as_array)
+        .map(|pins| {
+            pins.iter()
+                .filter_map(|pin| {
+                    let number = pin.get("number").and_then(Value::as_str)?;
+                    let net = pin.get("net").and_then(Value::as_str)?;
+                    let name = pin
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .filter(|n| !n.is_empty() && *n != "~")
+                        .map(ToString::to_string);
+                    Some((number.to_string(), name, net.to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Distances between component pairs from the distiller's proximity edges
+fn proximities(distilled: &Value) -> HashMap<(String, String), f64> {
+    distilled
+        .get("proximities")
+        .and_then(Value::as_array)
+        .map(|edges| {
+            edges
+                .iter()
+                .filter_map(|edge| {
+                    let a = edge.get("ref_a").and_then(Value::as_str)?;
+                    let b = edge.get("ref_b").and_then(Value::as_str)?;
+                    let distance = edge.get("distance_mm").and_then(Value::as_f64)?;
+                    Some((pair(a, b), distance))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn pair(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+fn position(component: &Value) -> Option<(f64, f64)> {
+    let position = component.get("position")?;
+    Some((
+        position.get("x").and_then(Value::as_f64)?,
+        position.get("y").and_then(Value::as_f64)?,
+    ))
+}
+
+/// Distance between two components on the same sheet: the proximity edge when
+/// there is one, else their positions (results distilled without proximities)
+fn distance(
+    proximities: &HashMap<(String, String), f64>,
+    (a, comp_a): (&str, &Value),
+    (b, comp_b): (&str, &Value),
+) -> Option<f64> {
+    if sheet(comp_a) != sheet(comp_b) {
+        return None;
+    }
+    if let Some(distance) = proximities.get(&pair(a, b)) {
+        return Some(*distance);
+    }
+    if !proximities.is_empty() {
+        // Edges only exist within the distiller's radius
+        return None;
+    }
+    let ((ax, ay), (bx, by)) = (position(comp_a)?, position(comp_b)?);
+    Some((ax - bx).hypot(ay - by))
+}
+
+/// Check that every IC supply pin has decoupling close by: a capacitor between
+/// the pin's net and ground within `DECOUPLING_RADIUS_MM` on the same sheet.
+/// Pins of one IC on the same net are judged together, since the distilled
+/// geometry only places components, not pins. Capacitors near more than one IC
+/// on a net are reported as shared.
+pub fn analyze(distilled: &Value) -> DecouplingAnalysis {
+    let mut analysis = DecouplingAnalysis::default();
+    let Some(components) = distilled.get("components").and_then(Value::as_object) else {
+        return analysis;
+    };
+    let proximities = proximities(distilled);
+
+    // Supply net -> capacitors between it and ground
+    let mut capacitors: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    for (reference, component) in components {
+        if !is_capacitor(reference, component) {
+            continue;
+        }
+        let nets: BTreeSet<String> = connected_pins(component)
+            .into_iter()
+            .map(|(_, _, net)| net)
+            .collect();
+        if !nets.iter().any(|n| is_ground_net(n)) {
+            continue;
+        }
+        for net in nets.into_iter().filter(|n| !is_ground_net(n)) {
+            capacitors.entry(net).or_default().insert(reference);
+        }
+    }
+
+    // (net, capacitor) -> ICs it sits near
+    let mut served: BTreeMap<(String, String), BTreeSet<&str>> = BTreeMap::new();
+
+    for (reference, component) in components {
+        if !is_ic(reference, component) {
+            continue;
+        }
+        let mut supply_pins: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (number, name, net) in connected_pins(component) {
+            let supply = is_supply_net(&net)
+                || (!is_ground_net(&net) && name.as_deref().is_some_and(is_supply_pin));
+            if supply {
+                let label = match name {
+                    Some(name) => format!("{} ({})", number, name),
+                    None => number,
+                };
+                supply_pins.entry(net).or_default().push(label);
+            }
+        }
+
+        for (net, pins) in supply_pins {
+            let on_net = capacitors.get(&net);
+            let mut nearby: Vec<DecouplingCapacitor> = on_net
+                .into_iter()
+                .flatten()
+                .filter_map(|cap| {
+                    let distance = distance(
+                        &proximities,
+                        (reference, component),
+                        (cap, &components[*cap]),
+                    )?;
+                    (distance <= DECOUPLING_RADIUS_MM).then(|| DecouplingCapacitor {
+                        reference: cap.to_string(),
+                        value: value(&components[*cap]),
+                        distance_mm: distance,
+                    })
+                })
+                .collect();
+            nearby.sort_by(|a, b| a.distance_mm.total_cmp(&b.distance_mm));
+
+            for cap in &nearby {
+                served
+                    .entry((net.clone(), cap.reference.clone()))
+                    .or_default()
+                    .insert(reference);
+            }
+
+            let status = if on_net.is_none_or(BTreeSet::is_empty) {
+                STATUS_MISSING
+            } else if nearby.is_empty() {
+                STATUS_DISTANT
+            } else if nearby.len() < pins.len() {
+                STATUS_INSUFFICIENT
+            } else {
+                STATUS_OK
+            };
+            analysis.entries.push(DecouplingEntry {
+                reference: reference.clone(),
+                value: value(component),
+                net,
+                pins,
+                sheet: sheet(component).map(ToString::to_string),
+                capacitors: nearby,
+                status: status.to_string(),
+            });
+        }
+    }
+
+    analysis.shared_capacitors = served
+        .into_iter()
+        .filter(|(_, ics)| ics.len() > 1)
+        .map(|((net, cap), ics)| SharedDecouplingCapacitor {
+            value: value(&components[&cap]),
+            reference: cap,
+            net,
+            components: ics.into_iter().map(ToString::to_string).collect(),
+        })
+        .collect();
+    analysis
+}
+
+/// Decoupling of the given components as prompt lines: for ICs, the
+/// capacitors on each supply net; for capacitors, the ICs they decouple
+pub fn describe(analysis: &DecouplingAnalysis, component_ids: &[String]) -> Vec<String> {
+    let selected = |reference: &str| component_ids.iter().any(|id| id == reference);
+    let mut lines: Vec<String> = analysis
+        .entries
+        .iter()
+        .filter(|entry| selected(&entry.reference))
+        .map(|entry| {
+            let caps = if entry.capacitors.is_empty() {
+                "no capacitor nearby".to_string()
+            } else {
+                entry
+                    .capacitors
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{} {} at {:.1} mm",
+                            c.reference,
+                            c.value.as_deref().unwrap_or("?"),
+                            c.distance_mm
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            format!(
+                "{} {} (pins {}): {} [{}]",
+                entry.reference,
+                entry.net,
+                entry.pins.join(", "),
+                caps,
+                entry.status
+            )
+        })
+        .collect();
+
+    let mut decoupled: BTreeMap<&str, BTreeSet<String>> = BTreeMap::new();
+    for entry in &analysis.entries {
+        for cap in entry.capacitors.iter().filter(|c| selected(&c.reference)) {
+            decoupled
+                .entry(&cap.reference)
+                .or_default()
+                .insert(format!("{} on {}", entry.reference, entry.net));
+        }
+    }
+    lines.extend(decoupled.into_iter().map(|(cap, ics)| {
+        format!(
+            "{} decouples {}",
+            cap,
+            ics.into_iter().collect::<Vec<_>>().join(", ")
+        )
+    }));
+    lines
+}
+
+/// Decoupling check of a commit
+pub async fn check(pool: &PgPool, repo: &str, commit: &str) -> Result<DecouplingCheckResponse> {
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+    let analysis = analyze(&distilled);
+    Ok(DecouplingCheckResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        radius_mm: DECOUPLING_RADIUS_MM,
+        entries: analysis.entries,
+        shared_capacitors: analysis.shared_capacitors,
+    })
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use serde_json::json;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::services::jobs::{self, JobRequest};
+use crate::services::{audit, git, github, leader, mirror, repo_cards};
+use kicad_db::default_branches::{self, DefaultBranchChange};
+use kicad_db::{repos, PgPool};
+
+/// Background checks for repositories whose default branch changed.
+///
+/// - `DEFAULT_BRANCH_CHECK_INTERVAL_SECS`: time between checks of every tracked
+///   repository (default 21600; 0 disables)
+#[derive(Debug, Clone, Copy)]
+pub struct BranchCheckSettings {
+    pub interval: Option<Duration>,
+}
+
+impl BranchCheckSettings {
+    pub fn from_env() -> Self {
+        let interval = match std::env::var("DEFAULT_BRANCH_CHECK_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(6 * 60 * 60)
+        {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        Self { interval }
+    }
+}
+
+/// What one check pass did
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BranchCheckOutcome {
+    pub checked: usize,
+    pub changed: usize,
+    pub failed: usize,
+}
+
+/// Fetch a repository and store its default branch. When it changed since the
+/// last check, the change is audited and what was built from the old branch's
+/// latest commit is regenerated from the new one.
+pub async fn check(pool: &PgPool, repo: &str, actor: &str) -> Result<Option<DefaultBranchChange>> {
+    let repo_url = github::repo_url(repo);
+    let branch = git::get_default_branch(repo).await?;
+    let Some(change) =
+        default_branches::record_default_branch(pool, &repo_url, &branch.name, &branch.head)
+            .await?
+    else {
+        return Ok(None);
+    };
+
+    info!(
+        "Default branch of {} changed from {} to {}",
+        repo, change.old_branch, change.new_branch
+    );
+    audit::record(
+        pool,
+        audit::REPO_DEFAULT_BRANCH_CHANGED,
+        actor,
+        Some(&repo_url),
+        Some(&change.head_commit),
+        json!({ "from": change.old_branch, "to": change.new_branch }),
+    )
+    .await;
+    rebuild_head(pool, repo, &change.head_commit).await;
+    Ok(Some(change))
+}
+
+/// Regenerate the artifacts that follow the latest commit: its distilled
+/// design, the overviews of the new branch's schematic commits, and the
+/// suggested questions. Each runs as a job, queued or in this process.
+async fn rebuild_head(pool: &PgPool, repo: &str, head: &str) {
+    let requests = [
+        JobRequest::Distill {
+            repo: repo.to_string(),
+            commit: head.to_string(),
+            api_key: None,
+        },
+        JobRequest::ProcessRepo {
+            repo: repo.to_string(),
+            refresh: false,
+        },
+        JobRequest::GenerateSuggestions {
+            repo: repo.to_string(),
+            commit: head.to_string(),
+        },
+    ];
+    for request in &requests {
+        match jobs::start(pool, request).await {
+            Ok(id) => info!("Started {} job {} for {}", request.kind(), id, repo),
+            Err(e) => warn!("Failed to start {} job for {}: {}", request.kind(), repo, e),
+        }
+    }
+    repo_cards::refresh_later(pool, repo);
+}
+
+/// Check the default branch of every tracked repository of the configured GitHub instance
+pub async fn check_all(pool: &PgPool, actor: &str) -> Result<BranchCheckOutcome> {
+    let mut outcome = BranchCheckOutcome::default();
+    for repo in repos::list_repos(pool).await? {
+        if repo.provider != github::host().host {
+            continue;
+        }
+        outcome.checked += 1;
+        match check(pool, &repo.slug, actor).await {
+            Ok(Some(_)) => outcome.changed += 1,
+            Ok(None) => {}
+            Err(e) => {
+                outcome.failed += 1;
+                warn!(
+                    "Failed to check the default branch of {}: {:#}",
+                    repo.slug, e
+                );
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+/// Check every repository's default branch on the configured interval
+pub fn spawn_checker(pool: PgPool, settings: BranchCheckSettings) {
+    let Some(interval) = settings.interval else {
+        info!("Default branch checks disabled (DEFAULT_BRANCH_CHECK_INTERVAL_SECS=0)");
+        return;
+    };
+    // Uploaded repositories have no upstream to follow
+    if mirror::enabled() {
+        info!("Default branch checks disabled in mirror mode");
+        return;
+    }
+
+    tokio::spawn(async move {
+        // Every repository is fetched, so the first pass waits out one interval
+        // rather than running at each restart
+        let start = tokio::time::Instant::now() + interval;
+        let mut ticker = tokio::time::interval_at(start, interval);
+        loop {
+            ticker.tick().await;
+            if !leader::is_leader(&pool).await {
+                continue;
+            }
+            match check_all(&pool, audit::ACTOR_SCHEDULER).await {
+                Ok(outcome) if outcome.changed + outcome.failed > 0 => info!(
+                    "Default branch check: checked={}, changed={}, failed={}",
+                    outcome.checked, outcome.changed, outcome.failed
+                ),
+                Ok(_) => {}
+                Err(e) => error!("Default branch check failed: {:#}", e),
+            }
+        }
+    });
+}
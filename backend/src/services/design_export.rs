@@ -0,0 +1,431 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::services::symbols::{parse_sexp_children, Sexp};
+use crate::services::{bom, distill, git};
+use crate::types::SchematicFile;
+use kicad_db::PgPool;
+
+/// Identifier of the neutral design schema, documented in docs/design-export.md
+pub const SCHEMA: &str = "grokicad-design";
+
+/// Bump the minor version for additive changes and the major version for
+/// anything that can break an existing consumer; note both in docs/design-export.md
+pub const SCHEMA_VERSION: &str = "1.0";
+
+// Symbol properties exported as dedicated fields rather than attributes
+const MAPPED_PROPERTIES: &[&str] = &["Reference", "Value", "Footprint"];
+
+/// A design in the neutral export schema
+#[derive(Debug, Serialize)]
+pub struct NeutralDesign {
+    pub schema: &'static str,
+    pub schema_version: &'static str,
+    pub source: Source,
+    pub sheets: Vec<Sheet>,
+    pub components: Vec<Component>,
+    pub nets: Vec<Net>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Source {
+    /// Repository in "owner/repo" format
+    pub repo: String,
+    pub commit: String,
+    pub commit_date: Option<DateTime<Utc>>,
+    pub exported_at: DateTime<Utc>,
+}
+
+/// A schematic file and the sheets it instantiates
+#[derive(Debug, Serialize)]
+pub struct Sheet {
+    /// File path relative to the repository root
+    pub path: String,
+    /// Not instantiated by any other sheet
+    pub root: bool,
+    pub children: Vec<SheetInstance>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SheetInstance {
+    /// Sheet name shown on the parent
+    pub name: Option<String>,
+    /// File path of the child sheet relative to the repository root
+    pub path: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Component {
+    pub reference: String,
+    pub value: Option<String>,
+    pub footprint: Option<String>,
+    /// Symbol library id (e.g. "Device:R")
+    pub library_id: Option<String>,
+    /// Path of the sheet the component is placed on
+    pub sheet: Option<String>,
+    /// Manufacturer part number
+    pub mpn: Option<String>,
+    pub manufacturer: Option<String>,
+    /// Remaining symbol properties, by name
+    pub attributes: BTreeMap<String, String>,
+    pub pins: Vec<Pin>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Pin {
+    pub number: String,
+    pub name: Option<String>,
+    pub net: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Net {
+    pub name: String,
+    pub nodes: Vec<Node>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Node {
+    pub reference: String,
+    pub pin: String,
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|s| !s.is_empty() && *s != "~")
+        .map(ToString::to_string)
+}
+
+/// Join a sheet file reference onto the directory of its parent sheet,
+/// resolving "." and ".." segments
+pub fn resolve_sheet_path(parent: &str, file: &str) -> String {
+    let mut segments: Vec<&str> = match parent.rsplit_once('/') {
+        Some((dir, _)) => dir.split('/').collect(),
+        None => Vec::new(),
+    };
+    for segment in file.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Value of a `(property "<name>" "<value>" ...)` entry among a sheet's items
+pub fn sheet_property<'a>(items: &'a [Sexp], names: &[&str]) -> Option<&'a str> {
+    items.iter().find_map(|item| {
+        let Sexp::List(property) = item else {
+            return None;
+        };
+        match property.as_slice() {
+            [keyword, name, value, ..]
+                if keyword.atom() == Some("property")
+                    && name.atom().is_some_and(|n| names.contains(&n)) =>
+            {
+                value.atom()
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Sheets instantiated by one schematic file
+fn child_sheets(file: &SchematicFile) -> Vec<SheetInstance> {
+    let mut children = Vec::new();
+    parse_sexp_children(&file.content, |node| {
+        let Sexp::List(items) = node else {
+            return;
+        };
+        if items.first().and_then(Sexp::atom) != Some("sheet") {
+            return;
+        }
+        // KiCad 6 wrote "Sheet file"/"Sheet name", later versions drop the space
+        if let Some(sheet_file) = sheet_property(&items, &["Sheetfile", "Sheet file"]) {
+            children.push(SheetInstance {
+                name: sheet_property(&items, &["Sheetname", "Sheet name"]).map(ToString::to_string),
+                path: resolve_sheet_path(&file.path, sheet_file),
+            });
+        }
+    });
+    children
+}
+
+/// The sheet hierarchy: every schematic file with the sheets it instantiates
+fn sheet_hierarchy(files: &[SchematicFile]) -> Vec<Sheet> {
+    let mut sheets: Vec<Sheet> = files
+        .iter()
+        .filter(|f| f.path.ends_with(".kicad_sch"))
+        .map(|file| Sheet {
+            path: file.path.clone(),
+            root: true,
+            children: child_sheets(file),
+        })
+        .collect();
+
+    let instantiated: BTreeSet<String> = sheets
+        .iter()
+        .flat_map(|s| s.children.iter().map(|c| c.path.clone()))
+        .collect();
+    for sheet in &mut sheets {
+        sheet.root = !instantiated.contains(&sheet.path);
+    }
+    sheets.sort_by(|a, b| a.path.cmp(&b.path));
+    sheets
+}
+
+/// Components of distilled data in the neutral schema. Power symbols and other
+/// virtual parts (references starting with '#') are left out.
+fn components(distilled: &Value) -> Vec<Component> {
+    let Some(components) = distilled.get("components").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+
+    let mut components: Vec<Component> = components
+        .iter()
+        .filter(|(reference, _)| !reference.starts_with('#'))
+        .map(|(reference, comp)| {
+            let part = bom::to_bom_component(reference, comp);
+            let attributes = comp
+                .get("properties")
+                .and_then(Value::as_object)
+                .map(|properties| {
+                    properties
+                        .iter()
+                        .filter(|(name, _)| !MAPPED_PROPERTIES.contains(&name.as_str()))
+                        .filter_map(|(name, value)| {
+                            let value = value.as_str()?.trim();
+                            (!value.is_empty() && value != "~")
+                                .then(|| (name.clone(), value.to_string()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let pins = comp
+                .get("pins")
+                .and_then(Value::as_array)
+                .map(|pins| {
+                    pins.iter()
+                        .filter_map(|pin| {
+                            Some(Pin {
+                                number: str_field(pin, "number")?,
+                                name: str_field(pin, "name"),
+                                net: str_field(pin, "net"),
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Component {
+                reference: reference.clone(),
+                value: part.value.filter(|v| !v.is_empty() && v != "~"),
+                footprint: part.footprint,
+                library_id: str_field(comp, "lib_id"),
+                sheet: str_field(comp, "sheet_path"),
+                mpn: part.mpn,
+                manufacturer: part.manufacturer,
+                attributes,
+                pins,
+            }
+        })
+        .collect();
+    components.sort_by(|a, b| a.reference.cmp(&b.reference));
+    components
+}
+
+/// Nets of distilled data with their component pins, by name. Pins of virtual
+/// parts are left out, so a net only tied to power symbols has no nodes.
+fn nets(distilled: &Value) -> Vec<Net> {
+    let Some(nets) = distilled.get("nets").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+
+    let mut nets: Vec<Net> = nets
+        .iter()
+        .map(|(name, members)| {
+            let mut nodes: Vec<Node> = members
+                .as_object()
+                .into_iter()
+                .flatten()
+                .filter(|(reference, _)| !reference.starts_with('#'))
+                .flat_map(|(reference, pins)| {
+                    pins.as_array()
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|pin| str_field(pin, "Pin"))
+                        .map(|pin| Node {
+                            reference: reference.clone(),
+                            pin,
+                        })
+                })
+                .collect();
+            nodes.sort_by(|a, b| (&a.reference, &a.pin).cmp(&(&b.reference, &b.pin)));
+            Net {
+                name: name.clone(),
+                nodes,
+            }
+        })
+        .collect();
+    nets.sort_by(|a, b| a.name.cmp(&b.name));
+    nets
+}
+
+/// Export a commit's design in the neutral schema
+pub async fn export(pool: &PgPool, repo: &str, commit: &str) -> Result<NeutralDesign> {
+    let info = git::get_commit_info(repo, commit).await?;
+    let files = git::get_schematic_files(repo, commit)
+        .await
+        .context("Failed to fetch schematic files from repo")?;
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+
+    Ok(NeutralDesign {
+        schema: SCHEMA,
+        schema_version: SCHEMA_VERSION,
+        source: Source {
+            repo: repo.to_string(),
+            commit: commit.to_string(),
+            commit_date: info.commit_date,
+            exported_at: Utc::now(),
+        },
+        sheets: sheet_hierarchy(&files),
+        components: components(&distilled),
+        nets: nets(&distilled),
+    })
+}
+
+/// Escape text for an XML attribute value
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            '\r' => escaped.push_str("&#13;"),
+            '\t' => escaped.push_str("&#9;"),
+            // Other control characters aren't allowed in XML 1.0
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// ` name="value"` pairs for the attributes that are set
+fn xml_attrs(attrs: &[(&str, Option<&str>)]) -> String {
+    attrs
+        .iter()
+        .filter_map(|(name, value)| value.map(|v| format!(" {}=\"{}\"", name, xml_escape(v))))
+        .collect()
+}
+
+/// Render a design as XML. Elements and attributes mirror the JSON field
+/// names; unset optional fields are omitted.
+pub fn to_xml(design: &NeutralDesign) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<design{}>\n",
+        xml_attrs(&[
+            ("schema", Some(design.schema)),
+            ("schema_version", Some(design.schema_version)),
+        ])
+    ));
+
+    let commit_date = design.source.commit_date.map(|d| d.to_rfc3339());
+    xml.push_str(&format!(
+        "  <source{}/>\n",
+        xml_attrs(&[
+            ("repo", Some(&design.source.repo)),
+            ("commit", Some(&design.source.commit)),
+            ("commit_date", commit_date.as_deref()),
+            ("exported_at", Some(&design.source.exported_at.to_rfc3339())),
+        ])
+    ));
+
+    xml.push_str("  <sheets>\n");
+    for sheet in &design.sheets {
+        xml.push_str(&format!(
+            "    <sheet{}>\n",
+            xml_attrs(&[
+                ("path", Some(&sheet.path)),
+                ("root", Some(if sheet.root { "true" } else { "false" })),
+            ])
+        ));
+        for child in &sheet.children {
+            xml.push_str(&format!(
+                "      <child{}/>\n",
+                xml_attrs(&[("name", child.name.as_deref()), ("path", Some(&child.path))])
+            ));
+        }
+        xml.push_str("    </sheet>\n");
+    }
+    xml.push_str("  </sheets>\n");
+
+    xml.push_str("  <components>\n");
+    for component in &design.components {
+        xml.push_str(&format!(
+            "    <component{}>\n",
+            xml_attrs(&[
+                ("reference", Some(&component.reference)),
+                ("value", component.value.as_deref()),
+                ("footprint", component.footprint.as_deref()),
+                ("library_id", component.library_id.as_deref()),
+                ("sheet", component.sheet.as_deref()),
+                ("mpn", component.mpn.as_deref()),
+                ("manufacturer", component.manufacturer.as_deref()),
+            ])
+        ));
+        for (name, value) in &component.attributes {
+            xml.push_str(&format!(
+                "      <attribute{}/>\n",
+                xml_attrs(&[("name", Some(name)), ("value", Some(value))])
+            ));
+        }
+        for pin in &component.pins {
+            xml.push_str(&format!(
+                "      <pin{}/>\n",
+                xml_attrs(&[
+                    ("number", Some(&pin.number)),
+                    ("name", pin.name.as_deref()),
+                    ("net", pin.net.as_deref()),
+                ])
+            ));
+        }
+        xml.push_str("    </component>\n");
+    }
+    xml.push_str("  </components>\n");
+
+    xml.push_str("  <nets>\n");
+    for net in &design.nets {
+        xml.push_str(&format!(
+            "    <net{}>\n",
+            xml_attrs(&[("name", Some(&net.name))])
+        ));
+        for node in &net.nodes {
+            xml.push_str(&format!(
+                "      <node{}/>\n",
+                xml_attrs(&[
+                    ("reference", Some(&node.reference)),
+                    ("pin", Some(&node.pin))
+                ])
+            ));
+        }
+        xml.push_str("    </net>\n");
+    }
+    xml.push_str("  </nets>\n");
+
+    xml.push_str("</design>\n");
+    xml
+}
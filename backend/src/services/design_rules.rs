@@ -0,0 +1,338 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use tracing::warn;
+
+use crate::services::commit_diff::{self, NetMembers};
+use crate::services::github;
+use crate::types::{ComponentSelector, DesignRuleSpec, DesignRuleViolation};
+use kicad_db::{design_rules, read_pool, PgPool};
+
+/// Accepted `severity` values; the first is the default
+pub const SEVERITIES: &[&str] = &["error", "warning"];
+/// Accepted `relation` values; the first is the default
+pub const RELATIONS: &[&str] = &["same_net", "nearby"];
+
+const MAX_NAME_CHARS: usize = 100;
+
+/// Case-insensitive glob with `*` and `?`; `|` separates alternatives
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    pattern.split('|').any(|alternative| {
+        let pattern: Vec<char> = alternative.trim().to_lowercase().chars().collect();
+        glob_match_chars(&pattern, &text)
+    })
+}
+
+fn glob_match_chars(pattern: &[char], text: &[char]) -> bool {
+    // Iterative matching with backtracking to the last `*`
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn patterns(selector: &ComponentSelector) -> [(&'static str, Option<&str>); 5] {
+    [
+        ("reference", selector.reference.as_deref()),
+        ("category", selector.category.as_deref()),
+        ("lib_id", selector.lib_id.as_deref()),
+        ("value", selector.value.as_deref()),
+        ("footprint", selector.footprint.as_deref()),
+    ]
+}
+
+/// Whether a distilled component matches every pattern of a selector
+fn matches(selector: &ComponentSelector, reference: &str, component: &Value) -> bool {
+    patterns(selector).into_iter().all(|(field, pattern)| {
+        let Some(pattern) = pattern else {
+            return true;
+        };
+        let text = if field == "reference" {
+            Some(reference)
+        } else {
+            component.get(field).and_then(Value::as_str)
+        };
+        text.is_some_and(|text| glob_match(pattern, text))
+    })
+}
+
+/// Patterns of a selector as "field pattern" pairs, for messages
+fn describe(selector: &ComponentSelector) -> String {
+    patterns(selector)
+        .into_iter()
+        .filter_map(|(field, pattern)| pattern.map(|p| format!("{} {}", field, p)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Check a rule before it's stored; the error is shown to the client
+pub fn validate(name: &str, rule: &DesignRuleSpec) -> Result<(), String> {
+    if name.trim().is_empty() || name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!("name must be 1 to {} characters", MAX_NAME_CHARS));
+    }
+    if let Some(severity) = &rule.severity {
+        if !SEVERITIES.contains(&severity.as_str()) {
+            return Err(format!(
+                "severity must be one of: {}",
+                SEVERITIES.join(", ")
+            ));
+        }
+    }
+    let relation = rule.relation.as_deref().unwrap_or(RELATIONS[0]);
+    if !RELATIONS.contains(&relation) {
+        return Err(format!("relation must be one of: {}", RELATIONS.join(", ")));
+    }
+    match rule.max_distance_mm {
+        Some(distance) if !distance.is_finite() || distance <= 0.0 => {
+            return Err("max_distance_mm must be a positive number".to_string());
+        }
+        None if relation == "nearby" => {
+            return Err("relation \"nearby\" needs max_distance_mm".to_string());
+        }
+        _ => {}
+    }
+    for (field, selector) in [("applies_to", &rule.applies_to), ("require", &rule.require)] {
+        let given: Vec<&str> = patterns(selector)
+            .into_iter()
+            .filter_map(|(_, pattern)| pattern)
+            .collect();
+        if given.is_empty() {
+            return Err(format!("{} needs at least one pattern", field));
+        }
+        if given.iter().any(|p| p.trim().is_empty()) {
+            return Err(format!("{} has an empty pattern", field));
+        }
+    }
+    if rule.pins.as_deref().is_some_and(|p| p.trim().is_empty()) {
+        return Err("pins must not be empty".to_string());
+    }
+    if rule.min_count == Some(0) {
+        return Err("min_count must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+fn position(component: &Value) -> Option<(f64, f64)> {
+    let position = component.get("position")?;
+    Some((
+        position.get("x").and_then(Value::as_f64)?,
+        position.get("y").and_then(Value::as_f64)?,
+    ))
+}
+
+fn sheet(component: &Value) -> Option<&str> {
+    component.get("sheet_path").and_then(Value::as_str)
+}
+
+fn distance(a: &Value, b: &Value) -> Option<f64> {
+    let ((ax, ay), (bx, by)) = (position(a)?, position(b)?);
+    Some((ax - bx).hypot(ay - by))
+}
+
+/// Pins of a component as (number, name, net)
+fn pins(component: &Value) -> Vec<(String, Option<String>, Option<String>)> {
+    component
+        .get("pins")
+        .and_then(Value::as_array)
+        .map(|pins| {
+            pins.iter()
+                .filter_map(|pin| {
+                    let number = pin.get("number").and_then(Value::as_str)?;
+                    let field = |key: &str| {
+                        pin.get(key)
+                            .and_then(Value::as_str)
+                            .filter(|v| !v.is_empty() && *v != "~")
+                            .map(ToString::to_string)
+                    };
+                    Some((number.to_string(), field("name"), field("net")))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A component or pin a rule is checked on
+struct Target {
+    /// "3 (VDD)" when checking a single pin
+    pin: Option<String>,
+    /// Net of the pin, when checking a single pin
+    net: Option<String>,
+    /// Nets required parts may share
+    nets: Vec<String>,
+}
+
+fn targets(rule: &DesignRuleSpec, component: &Value) -> Vec<Target> {
+    let pins = pins(component);
+    match &rule.pins {
+        Some(pattern) => pins
+            .into_iter()
+            .filter(|(number, name, _)| {
+                glob_match(pattern, number)
+                    || name.as_deref().is_some_and(|n| glob_match(pattern, n))
+            })
+            .map(|(number, name, net)| Target {
+                pin: Some(match name {
+                    Some(name) => format!("{} ({})", number, name),
+                    None => number,
+                }),
+                nets: net.iter().cloned().collect(),
+                net,
+            })
+            .collect(),
+        None => vec![Target {
+            pin: None,
+            net: None,
+            nets: pins
+                .into_iter()
+                .filter_map(|(_, _, net)| net)
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect(),
+        }],
+    }
+}
+
+fn evaluate_rule(
+    name: &str,
+    rule: &DesignRuleSpec,
+    components: &serde_json::Map<String, Value>,
+    nets: &NetMembers,
+    violations: &mut Vec<DesignRuleViolation>,
+) {
+    let severity = rule.severity.as_deref().unwrap_or(SEVERITIES[0]);
+    let nearby = rule.relation.as_deref() == Some("nearby");
+    let min_count = rule.min_count.unwrap_or(1) as usize;
+
+    let checked = components
+        .iter()
+        .filter(|(reference, _)| !reference.starts_with('#'))
+        .filter(|(reference, component)| matches(&rule.applies_to, reference, component));
+
+    for (reference, component) in checked {
+        for target in targets(rule, component) {
+            let candidates: BTreeSet<&str> = if nearby {
+                components.keys().map(String::as_str).collect()
+            } else {
+                target
+                    .nets
+                    .iter()
+                    .filter_map(|net| nets.get(net))
+                    .flat_map(|members| members.keys().map(String::as_str))
+                    .collect()
+            };
+            let found = candidates
+                .into_iter()
+                .filter(|other| *other != reference && !other.starts_with('#'))
+                .filter(|other| {
+                    let other_component = &components[*other];
+                    matches(&rule.require, other, other_component)
+                        && (!(rule.same_sheet || nearby)
+                            || sheet(component) == sheet(other_component))
+                        && rule.max_distance_mm.is_none_or(|max| {
+                            distance(component, other_component).is_some_and(|d| d <= max)
+                        })
+                })
+                .count();
+            if found >= min_count {
+                continue;
+            }
+
+            let subject = match &target.pin {
+                Some(pin) => format!("{} pin {}", reference, pin),
+                None => reference.clone(),
+            };
+            let mut whereabouts = Vec::new();
+            if !nearby {
+                whereabouts.push(match (&target.pin, &target.net) {
+                    (Some(_), Some(net)) => format!("on net {}", net),
+                    (Some(_), None) => "on its net (the pin is unconnected)".to_string(),
+                    (None, _) => "on its nets".to_string(),
+                });
+            }
+            if let Some(max) = rule.max_distance_mm {
+                whereabouts.push(format!("within {} mm", max));
+            }
+            if rule.same_sheet && !nearby {
+                whereabouts.push("on the same sheet".to_string());
+            }
+            violations.push(DesignRuleViolation {
+                rule: name.to_string(),
+                severity: severity.to_string(),
+                message: format!(
+                    "{}{} has {} of {} required part(s) ({}) {}",
+                    rule.description
+                        .as_deref()
+                        .map(|d| format!("{}: ", d))
+                        .unwrap_or_default(),
+                    subject,
+                    found,
+                    min_count,
+                    describe(&rule.require),
+                    whereabouts.join(", ")
+                ),
+                reference: reference.clone(),
+                pin: target.pin,
+                net: target.net,
+                sheet: sheet(component).map(ToString::to_string),
+            });
+        }
+    }
+}
+
+/// Evaluate rules against a commit's distilled data. Violations are ordered by
+/// rule, then reference.
+pub fn evaluate(rules: &[(String, DesignRuleSpec)], distilled: &Value) -> Vec<DesignRuleViolation> {
+    let Some(components) = distilled.get("components").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    let nets = commit_diff::net_members(distilled);
+
+    let mut violations = Vec::new();
+    for (name, rule) in rules {
+        evaluate_rule(name, rule, components, &nets, &mut violations);
+    }
+    violations
+}
+
+/// Enabled rules of a repository by name. Stored definitions that no longer
+/// parse are skipped with a warning.
+pub async fn enabled_rules(
+    pool: &PgPool,
+    repo_slug: &str,
+) -> Result<Vec<(String, DesignRuleSpec)>> {
+    let repo_url = github::repo_url(repo_slug);
+    Ok(design_rules::list_rules(read_pool(pool), &repo_url, true)
+        .await?
+        .into_iter()
+        .filter_map(|record| match serde_json::from_value(record.definition) {
+            Ok(rule) => Some((record.name, rule)),
+            Err(e) => {
+                warn!(
+                    "Skipping unreadable design rule {} of {}: {}",
+                    record.name, repo_slug, e
+                );
+                None
+            }
+        })
+        .collect())
+}
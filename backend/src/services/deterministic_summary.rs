@@ -0,0 +1,245 @@
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+use crate::services::commit_diff::{self, CommitDiff};
+use crate::services::release_notes;
+
+/// Reported as the model of template-based summaries
+pub const MODEL: &str = "deterministic";
+/// Bump whenever the templates change
+pub const TEMPLATE_VERSION: &str = "deterministic-v1";
+
+// References named in a blurb before the rest are only counted
+const MAX_BLURB_REFERENCES: usize = 5;
+
+fn short(commit: &str) -> &str {
+    &commit[..8.min(commit.len())]
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+}
+
+fn list_references<'a>(references: impl IntoIterator<Item = &'a str>) -> String {
+    let references: Vec<&str> = references.into_iter().collect();
+    let mut listed = references
+        .iter()
+        .take(MAX_BLURB_REFERENCES)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if references.len() > MAX_BLURB_REFERENCES {
+        listed.push_str(&format!(
+            " and {} more",
+            references.len() - MAX_BLURB_REFERENCES
+        ));
+    }
+    listed
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn push_section(description: &mut String, title: &str, lines: &[String]) {
+    if lines.is_empty() {
+        return;
+    }
+    description.push_str(&format!("\n{} ({}):\n", title, lines.len()));
+    for line in lines {
+        description.push_str(&format!("  - {}\n", line));
+    }
+}
+
+/// Blurb and description of a commit built only from its structured diff.
+/// The same diff always yields the same text, so CI can compare it verbatim.
+pub fn commit_summary(diff: &CommitDiff) -> (String, String) {
+    let changed_refs: BTreeSet<&str> = diff
+        .bom
+        .changed
+        .iter()
+        .map(|c| c.reference.as_str())
+        .collect();
+
+    let blurb = if diff.parent.is_none() {
+        format!(
+            "Initial schematic commit: {}, {}",
+            plural(diff.component_count, "component"),
+            plural(diff.net_count, "net")
+        )
+    } else if diff.is_empty() {
+        format!(
+            "Schematic changes in {} without component or net changes",
+            plural(diff.changed_files.len(), "file")
+        )
+    } else {
+        let mut parts = Vec::new();
+        if !diff.bom.added.is_empty() {
+            parts.push(format!(
+                "added {}",
+                list_references(diff.bom.added.iter().map(|p| p.reference.as_str()))
+            ));
+        }
+        if !diff.bom.removed.is_empty() {
+            parts.push(format!(
+                "removed {}",
+                list_references(diff.bom.removed.iter().map(|p| p.reference.as_str()))
+            ));
+        }
+        if !changed_refs.is_empty() {
+            parts.push(format!(
+                "changed {}",
+                list_references(changed_refs.iter().copied())
+            ));
+        }
+        let net_counts = [
+            (diff.nets_added.len(), "added"),
+            (diff.nets_removed.len(), "removed"),
+            (diff.nets_changed.len(), "rewired"),
+        ];
+        for (count, verb) in net_counts {
+            if count > 0 {
+                parts.push(format!("{} {}", plural(count, "net"), verb));
+            }
+        }
+        capitalize(&parts.join("; "))
+    };
+
+    let mut description = match &diff.parent {
+        Some(parent) => format!("Changes relative to {}\n", short(parent)),
+        None => "Initial commit\n".to_string(),
+    };
+    description.push_str(&format!(
+        "Components: {} added, {} removed, {} changed ({} total)\n\
+         Nets: {} added, {} removed, {} rewired ({} total)\n",
+        diff.bom.added.len(),
+        diff.bom.removed.len(),
+        changed_refs.len(),
+        diff.component_count,
+        diff.nets_added.len(),
+        diff.nets_removed.len(),
+        diff.nets_changed.len(),
+        diff.net_count
+    ));
+
+    push_section(&mut description, "Changed files", &diff.changed_files);
+    let added: Vec<String> = diff
+        .bom
+        .added
+        .iter()
+        .map(release_notes::describe_part)
+        .collect();
+    push_section(&mut description, "Added components", &added);
+    let removed: Vec<String> = diff
+        .bom
+        .removed
+        .iter()
+        .map(release_notes::describe_part)
+        .collect();
+    push_section(&mut description, "Removed components", &removed);
+    let changed: Vec<String> = diff
+        .bom
+        .changed
+        .iter()
+        .map(|c| {
+            format!(
+                "{} {}: {} -> {}",
+                c.reference,
+                c.field,
+                c.before.as_deref().unwrap_or("(none)"),
+                c.after.as_deref().unwrap_or("(none)")
+            )
+        })
+        .collect();
+    push_section(&mut description, "Changed fields", &changed);
+    push_section(&mut description, "Added nets", &diff.nets_added);
+    push_section(&mut description, "Removed nets", &diff.nets_removed);
+    push_section(
+        &mut description,
+        "Nets with changed connections",
+        &diff.nets_changed,
+    );
+
+    (blurb, description)
+}
+
+/// Summary and details of selected components built from a commit's distilled
+/// data: value, footprint, sheet and the nets each one connects to
+pub fn selection_summary(distilled: &Value, component_ids: &[String]) -> (String, String) {
+    let components = release_notes::components_of(distilled);
+    let nets = commit_diff::net_members(distilled);
+
+    let selected: BTreeSet<&str> = component_ids.iter().map(String::as_str).collect();
+    let found: Vec<&str> = selected
+        .iter()
+        .copied()
+        .filter(|id| components.contains_key(*id))
+        .collect();
+    let missing: Vec<String> = selected
+        .iter()
+        .filter(|id| !components.contains_key(**id))
+        .map(|id| id.to_string())
+        .collect();
+
+    let shared: BTreeSet<&str> = nets
+        .iter()
+        .filter(|(_, members)| found.iter().filter(|id| members.contains_key(**id)).count() > 1)
+        .map(|(name, _)| name.as_str())
+        .collect();
+
+    let mut summary = format!(
+        "{} selected: {}",
+        plural(found.len(), "component"),
+        list_references(found.iter().copied())
+    );
+    if !shared.is_empty() {
+        summary.push_str(&format!(
+            "; {} shared between them",
+            plural(shared.len(), "net")
+        ));
+    }
+
+    let mut details = String::new();
+    for id in &found {
+        let component = &components[*id];
+        details.push_str(&format!(
+            "{}: value {}, footprint {}",
+            id,
+            component.value.as_deref().unwrap_or("(none)"),
+            component.footprint.as_deref().unwrap_or("(none)")
+        ));
+        if let Some(mpn) = &component.mpn {
+            details.push_str(&format!(", MPN {}", mpn));
+        }
+        if let Some(sheet) = distilled
+            .get("components")
+            .and_then(|c| c.get(*id))
+            .and_then(|c| c.get("sheet_path"))
+            .and_then(Value::as_str)
+        {
+            details.push_str(&format!(", sheet {}", sheet));
+        }
+        details.push('\n');
+        for (name, members) in &nets {
+            if let Some(pins) = members.get(*id) {
+                details.push_str(&format!(
+                    "  - {} (pins {})\n",
+                    name,
+                    pins.iter().cloned().collect::<Vec<_>>().join(", ")
+                ));
+            }
+        }
+    }
+    push_section(
+        &mut details,
+        "Shared nets",
+        &shared.iter().map(|n| n.to_string()).collect::<Vec<_>>(),
+    );
+    push_section(&mut details, "Not found in this commit", &missing);
+
+    (summary, details)
+}
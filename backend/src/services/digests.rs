@@ -0,0 +1,333 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde_json::{json, Value};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::services::{git, leader};
+use crate::versioning;
+use kicad_db::digests::{self, ConfirmationRequest, DigestCommit, DigestSubscription};
+use kicad_db::{outbox, read_pool, PgPool};
+
+/// Outbox event type of a compiled digest
+pub const EVENT_TYPE: &str = "digest.email";
+
+/// Outbox event type of a subscription's confirmation email
+pub const CONFIRM_EVENT_TYPE: &str = "digest.confirm";
+
+// Subscriptions handled per pass
+const DIGEST_BATCH: i64 = 50;
+
+// Commits listed in one digest; the rest are counted
+const MAX_DIGEST_COMMITS: usize = 50;
+
+/// Background digest scheduling.
+///
+/// - `DIGEST_INTERVAL_SECS`: time between checks for due digests (default 900; 0 disables)
+#[derive(Debug, Clone, Copy)]
+pub struct DigestSettings {
+    pub interval: Option<Duration>,
+}
+
+impl DigestSettings {
+    pub fn from_env() -> Self {
+        let interval = match std::env::var("DIGEST_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(900)
+        {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        Self { interval }
+    }
+}
+
+/// What one digest pass did
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DigestOutcome {
+    /// Digests handed to the outbox
+    pub sent: usize,
+    /// Periods without schematic commits, closed without an email
+    pub empty: usize,
+    pub failed: usize,
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..8.min(commit.len())]
+}
+
+/// "10 -> 12 (+2)" style change of a metric over the period
+fn describe_change(before: Option<f64>, after: Option<f64>, decimals: usize) -> Option<String> {
+    match (before, after) {
+        (Some(before), Some(after)) => Some(format!(
+            "{:.*} -> {:.*} ({:+.*})",
+            decimals,
+            before,
+            decimals,
+            after,
+            decimals,
+            after - before
+        )),
+        (None, Some(after)) => Some(format!("{:.*}", decimals, after)),
+        _ => None,
+    }
+}
+
+/// Plain-text body and structured payload of a digest
+fn compose(
+    subscription: &DigestSubscription,
+    period_end: DateTime<Utc>,
+    commits: &[DigestCommit],
+    baseline: Option<&DigestCommit>,
+) -> Value {
+    let repo = git::repo_slug(&subscription.repo_url).unwrap_or(&subscription.repo_url);
+    let period = if subscription.frequency == "daily" {
+        "today"
+    } else {
+        "this week"
+    };
+    let subject = format!(
+        "[{}] {} schematic commit(s) {}",
+        repo,
+        commits.len(),
+        period
+    );
+
+    let mut text = format!(
+        "Schematic activity in {} from {} to {}\n\n",
+        repo,
+        subscription.period_start().format("%Y-%m-%d %H:%M UTC"),
+        period_end.format("%Y-%m-%d %H:%M UTC")
+    );
+    for commit in commits.iter().take(MAX_DIGEST_COMMITS) {
+        let summary = commit
+            .blurb
+            .as_deref()
+            .or(commit.git_message.as_deref())
+            .unwrap_or("(no summary)");
+        text.push_str(&format!(
+            "- {} {}: {}\n",
+            short(&commit.commit_hash),
+            commit
+                .commit_date
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            summary.lines().next().unwrap_or_default()
+        ));
+    }
+    if commits.len() > MAX_DIGEST_COMMITS {
+        text.push_str(&format!(
+            "... and {} more\n",
+            commits.len() - MAX_DIGEST_COMMITS
+        ));
+    }
+
+    // Deltas run from the last commit before the period to the last one in it with metrics
+    let latest = commits.iter().rev().find(|c| c.component_count.is_some());
+    let components = describe_change(
+        baseline.and_then(|b| b.component_count).map(f64::from),
+        latest.and_then(|c| c.component_count).map(f64::from),
+        0,
+    );
+    let cost = describe_change(
+        baseline.and_then(|b| b.estimated_bom_cost),
+        latest.and_then(|c| c.estimated_bom_cost),
+        2,
+    );
+    if components.is_some() || cost.is_some() {
+        text.push('\n');
+    }
+    if let Some(components) = &components {
+        text.push_str(&format!("Components: {}\n", components));
+    }
+    if let Some(cost) = &cost {
+        text.push_str(&format!("Estimated BOM cost per board: {}\n", cost));
+    }
+    text.push_str(&format!(
+        "\nYou receive this {} digest because you subscribed to {}. Unsubscribe token: {}\n",
+        subscription.frequency, repo, subscription.unsubscribe_token
+    ));
+
+    json!({
+        "to": subscription.email,
+        "subject": subject,
+        "text": text,
+        "repo": repo,
+        "frequency": subscription.frequency,
+        "period_start": subscription.period_start(),
+        "period_end": period_end,
+        "commits": commits.iter().map(|c| json!({
+            "commit": c.commit_hash,
+            "commit_date": c.commit_date,
+            "message": c.git_message,
+            "blurb": c.blurb,
+        })).collect::<Vec<_>>(),
+        "component_count": {
+            "before": baseline.and_then(|b| b.component_count),
+            "after": latest.and_then(|c| c.component_count),
+        },
+        "estimated_bom_cost": {
+            "before": baseline.and_then(|b| b.estimated_bom_cost),
+            "after": latest.and_then(|c| c.estimated_bom_cost),
+        },
+        "unsubscribe_token": subscription.unsubscribe_token,
+    })
+}
+
+/// Link that confirms a pending subscription, or None without PUBLIC_URL
+fn confirmation_url(token: &str) -> Option<String> {
+    let origin = std::env::var("PUBLIC_URL").ok()?;
+    let origin = origin.trim().trim_end_matches('/');
+    if origin.is_empty() {
+        return None;
+    }
+    Url::parse_with_params(
+        &format!("{}{}/digests/confirm", origin, versioning::CURRENT_PREFIX),
+        [("token", token)],
+    )
+    .map(String::from)
+    .map_err(|e| warn!("Invalid PUBLIC_URL {:?}: {}", origin, e))
+    .ok()
+}
+
+/// Confirmation email asking the address to confirm a subscription request
+fn compose_confirmation(repo: &str, email: &str, frequency: &str, token: &str) -> Value {
+    let url = confirmation_url(token);
+    let action = match &url {
+        Some(url) => format!(
+            "follow this link within {} hours:\n{}",
+            digests::CONFIRMATION_TTL_HOURS,
+            url
+        ),
+        None => format!(
+            "confirm within {} hours at {}/digests/confirm with the token {}",
+            digests::CONFIRMATION_TTL_HOURS,
+            versioning::CURRENT_PREFIX,
+            token
+        ),
+    };
+    let change = if frequency == "off" {
+        "pause".to_string()
+    } else {
+        format!("receive {}", frequency)
+    };
+    json!({
+        "to": email,
+        "subject": format!("[{}] Confirm your schematic digest subscription", repo),
+        "text": format!(
+            "Someone asked to {} schematic activity digests for {} at this address. To confirm, {}\n\nIf this wasn't you, ignore this email and nothing changes.\n",
+            change, repo, action
+        ),
+        "repo": repo,
+        "frequency": frequency,
+        "confirm_url": url,
+        "token": token,
+    })
+}
+
+/// Record a subscription request and queue its confirmation email. The
+/// subscription is created or changed only once the link is followed; a
+/// repeated request within a few minutes sends nothing. Returns false when the
+/// repository URL can't be parsed.
+pub async fn request_subscription(
+    pool: &PgPool,
+    repo_url: &str,
+    email: &str,
+    frequency: &str,
+) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let token = match digests::request_confirmation(&mut tx, repo_url, email, frequency).await? {
+        ConfirmationRequest::Created { token } => token,
+        ConfirmationRequest::Throttled => return Ok(true),
+        ConfirmationRequest::InvalidRepo => return Ok(false),
+    };
+    let repo = git::repo_slug(repo_url).unwrap_or(repo_url);
+    let payload = compose_confirmation(repo, email, frequency, &token);
+    outbox::enqueue_event(&mut *tx, CONFIRM_EVENT_TYPE, &payload).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Compile one subscription's digest and close its period. Returns whether
+/// an email was queued (false for a period without commits, or one another
+/// scheduler already handled).
+async fn send_digest(pool: &PgPool, subscription: &DigestSubscription) -> Result<bool> {
+    let period_end = Utc::now();
+    let commits = digests::commits_between(
+        read_pool(pool),
+        &subscription.repo_url,
+        subscription.period_start(),
+        period_end,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await?;
+    if !digests::mark_sent(&mut *tx, subscription, period_end).await? {
+        return Ok(false);
+    }
+    if commits.is_empty() {
+        tx.commit().await?;
+        return Ok(false);
+    }
+
+    let baseline = digests::baseline_commit(
+        read_pool(pool),
+        &subscription.repo_url,
+        subscription.period_start(),
+    )
+    .await?;
+    let payload = compose(subscription, period_end, &commits, baseline.as_ref());
+    outbox::enqueue_event(&mut *tx, EVENT_TYPE, &payload).await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Send every digest whose period has ended, as `digest.email` outbox events
+/// delivered by the notification relay. Periods without schematic commits are
+/// closed without an email.
+pub async fn send_due(pool: &PgPool) -> Result<DigestOutcome> {
+    let mut outcome = DigestOutcome::default();
+    digests::purge_expired_confirmations(pool).await?;
+    for subscription in digests::due_subscriptions(pool, DIGEST_BATCH).await? {
+        match send_digest(pool, &subscription).await {
+            Ok(true) => outcome.sent += 1,
+            Ok(false) => outcome.empty += 1,
+            Err(e) => {
+                warn!(
+                    "Failed to compile digest {} for {}: {:#}",
+                    subscription.id, subscription.repo_url, e
+                );
+                outcome.failed += 1;
+            }
+        }
+    }
+    Ok(outcome)
+}
+
+/// Periodically send due digests in the background
+pub fn spawn_scheduler(pool: PgPool, settings: DigestSettings) {
+    let Some(interval) = settings.interval else {
+        info!("Digest emails disabled (DIGEST_INTERVAL_SECS=0)");
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if !leader::is_leader(&pool).await {
+                continue;
+            }
+            match send_due(&pool).await {
+                Ok(outcome) if outcome.sent + outcome.failed > 0 => info!(
+                    "Digests: sent={}, empty={}, failed={}",
+                    outcome.sent, outcome.empty, outcome.failed
+                ),
+                Ok(_) => {}
+                Err(e) => error!("Digest pass failed: {:#}", e),
+            }
+        }
+    });
+}
@@ -4,24 +4,18 @@ use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::sync::RwLock;
 use std::time::{Duration, Instant};
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info};
 
+use crate::services::credentials::{self, ProviderCredentials};
+use crate::services::mpn;
+use crate::services::search_cache::{RateLimiter, SearchCache};
 use crate::types::{DigiKeyParameter, DigiKeyPartInfo};
 
-// DigiKey API Configuration
-static DIGIKEY_CLIENT_ID: Lazy<String> = Lazy::new(|| {
-    std::env::var("DIGIKEY_CLIENT_ID").unwrap_or_else(|_| {
-        warn!("DIGIKEY_CLIENT_ID not set - DigiKey integration will not work");
-        String::new()
-    })
-});
+// Provider name used for credential lookup
+const PROVIDER: &str = "digikey";
 
-static DIGIKEY_CLIENT_SECRET: Lazy<String> = Lazy::new(|| {
-    std::env::var("DIGIKEY_CLIENT_SECRET").unwrap_or_else(|_| {
-        warn!("DIGIKEY_CLIENT_SECRET not set - DigiKey integration will not work");
-        String::new()
-    })
-});
+/// Currency DigiKey is asked to quote prices in
+pub const CURRENCY: &str = "USD";
 
 // DigiKey API endpoints
 const DIGIKEY_AUTH_URL: &str = "https://api.digikey.com/v1/oauth2/token";
@@ -30,6 +24,18 @@ const DIGIKEY_SEARCH_URL: &str = "https://api.digikey.com/products/v4/search/key
 // Token cache with thread-safe access
 static TOKEN_CACHE: Lazy<RwLock<Option<TokenCache>>> = Lazy::new(|| RwLock::new(None));
 
+// How long keyword search results stay cached, and how many queries are kept
+const SEARCH_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const SEARCH_CACHE_ENTRIES: usize = 2_000;
+
+// Minimum spacing between DigiKey API calls (DigiKey allows 120 requests/minute)
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(500);
+
+static SEARCH_CACHE: Lazy<SearchCache<Vec<DigiKeyPartInfo>>> =
+    Lazy::new(|| SearchCache::new(SEARCH_CACHE_TTL, SEARCH_CACHE_ENTRIES));
+
+static RATE_LIMITER: RateLimiter = RateLimiter::new(MIN_REQUEST_INTERVAL);
+
 // Shared HTTP client - reqwest Client uses connection pooling internally
 static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
     Client::builder()
@@ -42,6 +48,7 @@ static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
 struct TokenCache {
     access_token: String,
     expires_at: Instant,
+    expires_at_utc: chrono::DateTime<chrono::Utc>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -172,11 +179,32 @@ impl DigiKeyClient {
 
     /// Check if DigiKey integration is configured
     pub fn is_configured() -> bool {
-        !DIGIKEY_CLIENT_ID.is_empty() && !DIGIKEY_CLIENT_SECRET.is_empty()
+        credentials::get(PROVIDER).is_some()
+    }
+
+    /// Get the active client credentials
+    fn credentials() -> Result<ProviderCredentials> {
+        credentials::get(PROVIDER).context(
+            "DigiKey API not configured. Set DIGIKEY_CLIENT_ID and DIGIKEY_CLIENT_SECRET environment variables.",
+        )
+    }
+
+    /// Drop the cached access token so the next request re-authenticates
+    pub fn invalidate_token() {
+        *TOKEN_CACHE.write().unwrap() = None;
+    }
+
+    /// Expiry time of the cached access token, if one has been issued
+    pub fn token_expires_at() -> Option<chrono::DateTime<chrono::Utc>> {
+        TOKEN_CACHE
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|cached| cached.expires_at_utc)
     }
 
     /// Get a valid access token, refreshing if necessary
-    async fn get_access_token(&self) -> Result<String> {
+    async fn get_access_token(&self, creds: &ProviderCredentials) -> Result<String> {
         // Check cache first
         {
             let cache = TOKEN_CACHE.read().unwrap();
@@ -193,8 +221,8 @@ impl DigiKeyClient {
         info!("Refreshing DigiKey access token");
         
         let params = [
-            ("client_id", DIGIKEY_CLIENT_ID.as_str()),
-            ("client_secret", DIGIKEY_CLIENT_SECRET.as_str()),
+            ("client_id", creds.client_id.as_str()),
+            ("client_secret", creds.client_secret.as_str()),
             ("grant_type", "client_credentials"),
         ];
 
@@ -217,7 +245,10 @@ impl DigiKeyClient {
             .await
             .context("Failed to parse DigiKey token response")?;
 
-        let expires_at = Instant::now() + Duration::from_secs(token_response.expires_in);
+        let expires_in = Duration::from_secs(token_response.expires_in);
+        let expires_at = Instant::now() + expires_in;
+        let expires_at_utc = chrono::Utc::now()
+            + chrono::Duration::from_std(expires_in).unwrap_or_else(|_| chrono::Duration::zero());
         
         // Update cache
         {
@@ -225,6 +256,7 @@ impl DigiKeyClient {
             *cache = Some(TokenCache {
                 access_token: token_response.access_token.clone(),
                 expires_at,
+                expires_at_utc,
             });
         }
 
@@ -234,13 +266,19 @@ impl DigiKeyClient {
 
     /// Search for parts by keyword/MPN
     /// This uses the keyword search endpoint which is more flexible and returns
-    /// exact manufacturer matches when searching by MPN
+    /// exact manufacturer matches when searching by MPN.
+    /// Results are cached per query and outgoing requests are rate limited.
     pub async fn search_keyword(&self, query: &str) -> Result<Vec<DigiKeyPartInfo>> {
-        if !Self::is_configured() {
-            anyhow::bail!("DigiKey API not configured. Set DIGIKEY_CLIENT_ID and DIGIKEY_CLIENT_SECRET environment variables.");
+        let creds = Self::credentials()?;
+
+        if let Some(parts) = SEARCH_CACHE.get(query) {
+            debug!("Using cached DigiKey results for: {}", query);
+            return Ok(parts);
         }
 
-        let access_token = self.get_access_token().await?;
+        let access_token = self.get_access_token(&creds).await?;
+
+        RATE_LIMITER.wait().await;
 
         let request_body = KeywordSearchRequest {
             keywords: query.to_string(),
@@ -253,10 +291,10 @@ impl DigiKeyClient {
         let response = HTTP_CLIENT
             .post(DIGIKEY_SEARCH_URL)
             .header("Authorization", format!("Bearer {}", access_token))
-            .header("X-DIGIKEY-Client-Id", DIGIKEY_CLIENT_ID.as_str())
+            .header("X-DIGIKEY-Client-Id", creds.client_id.as_str())
             .header("X-DIGIKEY-Locale-Site", "US")
             .header("X-DIGIKEY-Locale-Language", "en")
-            .header("X-DIGIKEY-Locale-Currency", "USD")
+            .header("X-DIGIKEY-Locale-Currency", CURRENCY)
             .json(&request_body)
             .send()
             .await
@@ -311,9 +349,20 @@ impl DigiKeyClient {
         }
 
         info!("DigiKey search returned {} total parts", parts.len());
+
+        SEARCH_CACHE.insert(query, parts.clone());
+
         Ok(parts)
     }
 
+    /// Find the part with exactly this manufacturer part number.
+    /// MPNs are compared after [`mpn::normalize`]; fuzzy keyword hits that don't
+    /// match are ignored, so `None` means DigiKey doesn't list the part.
+    pub async fn lookup_mpn(&self, mpn: &str) -> Result<Option<DigiKeyPartInfo>> {
+        let parts = self.search_keyword(mpn).await?;
+        Ok(exact_match(parts, mpn))
+    }
+
     /// Convert DigiKey API product to our internal representation
     fn convert_product(product: DigiKeyProduct) -> DigiKeyPartInfo {
         // Get DigiKey part number from first product variation
@@ -384,8 +433,92 @@ impl DigiKeyClient {
     }
 }
 
+/// The search hit whose MPN is the same part as `mpn`
+fn exact_match(parts: Vec<DigiKeyPartInfo>, mpn: &str) -> Option<DigiKeyPartInfo> {
+    parts.into_iter().find(|p| {
+        p.manufacturer_part_number
+            .as_deref()
+            .map(|m| mpn::same_part(m, mpn))
+            .unwrap_or(false)
+    })
+}
+
 impl Default for DigiKeyClient {
     fn default() -> Self {
         Self::new()
     }
 }
+
+/// Package/case of a part from its parameters (e.g. "0603 (1608 Metric)")
+pub fn package_of(part: &DigiKeyPartInfo) -> Option<String> {
+    ["Package / Case", "Supplier Device Package"]
+        .iter()
+        .find_map(|name| {
+            part.parameters
+                .iter()
+                .find(|p| p.name.eq_ignore_ascii_case(name))
+                .map(|p| p.value.trim().to_string())
+        })
+        .filter(|v| !v.is_empty() && v != "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn part(mpn: Option<&str>, digikey_part_number: &str) -> DigiKeyPartInfo {
+        DigiKeyPartInfo {
+            digikey_part_number: Some(digikey_part_number.to_string()),
+            manufacturer_part_number: mpn.map(str::to_string),
+            manufacturer: None,
+            description: None,
+            detailed_description: None,
+            product_url: None,
+            datasheet_url: None,
+            photo_url: None,
+            quantity_available: None,
+            unit_price: None,
+            product_status: None,
+            is_obsolete: false,
+            lifecycle_status: None,
+            category: None,
+            parameters: Vec::new(),
+        }
+    }
+
+    fn matched(parts: Vec<DigiKeyPartInfo>, mpn: &str) -> Option<String> {
+        exact_match(parts, mpn).and_then(|p| p.digikey_part_number)
+    }
+
+    #[test]
+    fn exact_match_skips_fuzzy_hits() {
+        let parts = vec![
+            part(Some("ESP32-WROOM-32E-N4R2"), "1965-ESP32-WROOM-32E-N4R2-ND"),
+            part(None, "NO-MPN-ND"),
+            part(Some("ESP32-WROOM-32E-N4"), "1965-ESP32-WROOM-32E-N4-ND"),
+        ];
+        assert_eq!(
+            matched(parts, "ESP32-WROOM-32E-N4").as_deref(),
+            Some("1965-ESP32-WROOM-32E-N4-ND")
+        );
+    }
+
+    #[test]
+    fn exact_match_compares_normalized_mpns() {
+        let parts = vec![part(Some("lm358dr2g"), "LM358DR2GOSCT-ND")];
+        assert_eq!(
+            matched(parts, " LM358DR2G ").as_deref(),
+            Some("LM358DR2GOSCT-ND")
+        );
+    }
+
+    #[test]
+    fn no_exact_match_is_none() {
+        let parts = vec![
+            part(Some("LM358DR"), "296-1014-1-ND"),
+            part(Some("LM358P"), "296-1395-5-ND"),
+        ];
+        assert_eq!(matched(parts, "LM358DR2G"), None);
+        assert_eq!(matched(Vec::new(), "LM358DR2G"), None);
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use axum::http::StatusCode;
+use once_cell::sync::Lazy;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::errors::AppError;
+use crate::types::{AdminDiskStats, ApiError};
+
+pub const STATUS_OK: &str = "ok";
+pub const STATUS_LOW: &str = "low";
+pub const STATUS_CRITICAL: &str = "critical";
+
+/// How long a free-space reading is reused before `df` runs again
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Free-space thresholds for the directory holding repository caches, LFS
+/// objects and distiller scratch files (the system temp dir), read from the
+/// environment:
+///
+/// - `DISK_MIN_FREE_BYTES`: refuse clones and distillation below this (default 1 GiB)
+/// - `DISK_MIN_FREE_PERCENT`: refuse them below this share of the disk (default 5)
+/// - `DISK_WARN_FREE_PERCENT`: log a warning and report "low" below this (default 15)
+///
+/// 0 turns a threshold off.
+#[derive(Debug, Clone, Copy)]
+pub struct DiskThresholds {
+    pub min_free_bytes: u64,
+    pub min_free_percent: f64,
+    pub warn_free_percent: f64,
+}
+
+fn read<T: std::str::FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok().and_then(|v| v.trim().parse().ok())
+}
+
+impl DiskThresholds {
+    pub fn from_env() -> Self {
+        Self {
+            min_free_bytes: read("DISK_MIN_FREE_BYTES").unwrap_or(1024 * 1024 * 1024),
+            min_free_percent: read("DISK_MIN_FREE_PERCENT").unwrap_or(5.0),
+            warn_free_percent: read("DISK_WARN_FREE_PERCENT").unwrap_or(15.0),
+        }
+    }
+}
+
+static THRESHOLDS: Lazy<DiskThresholds> = Lazy::new(DiskThresholds::from_env);
+
+/// Size and free space of the filesystem holding a directory
+#[derive(Debug, Clone, Copy)]
+pub struct DiskSpace {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+impl DiskSpace {
+    pub fn available_percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 100.0;
+        }
+        self.available_bytes as f64 * 100.0 / self.total_bytes as f64
+    }
+
+    pub fn status(&self, thresholds: &DiskThresholds) -> &'static str {
+        let percent = self.available_percent();
+        if self.available_bytes < thresholds.min_free_bytes || percent < thresholds.min_free_percent
+        {
+            STATUS_CRITICAL
+        } else if percent < thresholds.warn_free_percent {
+            STATUS_LOW
+        } else {
+            STATUS_OK
+        }
+    }
+}
+
+/// The directory clones and distillation write to
+pub fn work_dir() -> PathBuf {
+    std::env::temp_dir()
+}
+
+/// Parse `df -Pk` output: a header line, then the filesystem with its size,
+/// used and available space in KiB
+fn parse_df(output: &str) -> Option<DiskSpace> {
+    let line = output.lines().nth(1)?;
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let kib = |i: usize| fields.get(i)?.parse::<u64>().ok();
+    Some(DiskSpace {
+        total_bytes: kib(1)? * 1024,
+        available_bytes: kib(3)? * 1024,
+    })
+}
+
+async fn measure() -> Result<DiskSpace> {
+    let output = tokio::process::Command::new("df")
+        .arg("-Pk")
+        .arg(work_dir())
+        .output()
+        .await
+        .context("Failed to run df")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "df failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    parse_df(&String::from_utf8_lossy(&output.stdout)).context("Unexpected df output")
+}
+
+static LAST_READING: Lazy<Mutex<Option<(Instant, DiskSpace)>>> = Lazy::new(|| Mutex::new(None));
+// Status of the last reading, so warnings are logged when it changes
+static LAST_STATUS: AtomicU8 = AtomicU8::new(0);
+// Whether measuring has failed before, so a host without `df` warns once
+static MEASURE_FAILED: AtomicBool = AtomicBool::new(false);
+// Requests refused since the process started
+static REJECTED: AtomicU64 = AtomicU64::new(0);
+
+fn status_code(status: &str) -> u8 {
+    match status {
+        STATUS_OK => 1,
+        STATUS_LOW => 2,
+        _ => 3,
+    }
+}
+
+/// Free space of the work directory, measured at most every REFRESH_INTERVAL.
+/// Logs a warning when space runs low and an error when it becomes critical.
+pub async fn space() -> Result<DiskSpace> {
+    let cached = *LAST_READING.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((_, space)) = cached.filter(|(at, _)| at.elapsed() < REFRESH_INTERVAL) {
+        return Ok(space);
+    }
+    let space = measure().await?;
+    *LAST_READING.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), space));
+
+    let status = space.status(&THRESHOLDS);
+    let previous = LAST_STATUS.swap(status_code(status), Ordering::Relaxed);
+    if previous != status_code(status) {
+        let available_mib = space.available_bytes / (1024 * 1024);
+        match status {
+            STATUS_CRITICAL => error!(
+                "Disk space critical in {:?}: {} MiB ({:.1}%) free; refusing clones and distillation",
+                work_dir(),
+                available_mib,
+                space.available_percent()
+            ),
+            STATUS_LOW => warn!(
+                "Disk space low in {:?}: {} MiB ({:.1}%) free",
+                work_dir(),
+                available_mib,
+                space.available_percent()
+            ),
+            _ if previous != 0 => info!(
+                "Disk space in {:?} back to {} MiB ({:.1}%) free",
+                work_dir(),
+                available_mib,
+                space.available_percent()
+            ),
+            _ => {}
+        }
+    }
+    Ok(space)
+}
+
+/// Not enough free disk space to clone or distill. Mapped to 507 `disk_full`.
+#[derive(Debug)]
+pub struct DiskFull(pub String);
+
+impl std::fmt::Display for DiskFull {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for DiskFull {}
+
+impl From<&DiskFull> for AppError {
+    fn from(full: &DiskFull) -> Self {
+        AppError::new(
+            StatusCode::INSUFFICIENT_STORAGE,
+            ApiError::new("disk_full", full.0.clone()),
+        )
+    }
+}
+
+/// Refuse work that writes to disk while free space is under the thresholds,
+/// before it fails halfway. Lets the work through when space can't be
+/// measured (e.g. no `df` on the host).
+pub async fn check_admission() -> Result<(), DiskFull> {
+    let space = match space().await {
+        Ok(space) => space,
+        Err(e) => {
+            if !MEASURE_FAILED.swap(true, Ordering::Relaxed) {
+                warn!("Skipping disk space checks: {:#}", e);
+            }
+            return Ok(());
+        }
+    };
+    if space.status(&THRESHOLDS) != STATUS_CRITICAL {
+        return Ok(());
+    }
+    REJECTED.fetch_add(1, Ordering::Relaxed);
+    Err(DiskFull(format!(
+        "The server is low on disk space ({} MiB free); try again later",
+        space.available_bytes / (1024 * 1024)
+    )))
+}
+
+/// Disk statistics for the admin overview; None when space can't be measured
+pub async fn stats() -> Option<AdminDiskStats> {
+    let space = match space().await {
+        Ok(space) => space,
+        Err(e) => {
+            warn!("Failed to measure disk space: {:#}", e);
+            return None;
+        }
+    };
+    let thresholds = *THRESHOLDS;
+    Some(AdminDiskStats {
+        path: work_dir().display().to_string(),
+        total_bytes: space.total_bytes,
+        available_bytes: space.available_bytes,
+        available_percent: (space.available_percent() * 10.0).round() / 10.0,
+        status: space.status(&thresholds).to_string(),
+        min_free_bytes: thresholds.min_free_bytes,
+        min_free_percent: thresholds.min_free_percent,
+        warn_free_percent: thresholds.warn_free_percent,
+        rejected: REJECTED.load(Ordering::Relaxed),
+    })
+}
@@ -1,12 +1,138 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use futures_util::future::{BoxFuture, FutureExt, Shared};
+use once_cell::sync::Lazy;
 use serde_json::Value;
-use std::path::{Path, PathBuf};
-use std::process::Stdio;
-use tokio::process::Command;
-use tracing::{error, info};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tempfile::TempDir;
+use tracing::{error, info, warn};
+
+use crate::services::distiller::{
+    self, BackendChoice, DistillerBackend, NativeDistiller, PythonDistiller,
+};
+use crate::services::jobs::{self, JobRequest};
+use crate::services::{disk, git, github, kicad_format, metrics, quarantine, repo_cards};
+use crate::types::{
+    DistillCacheStats, DistillComparison, DistillSheet, DistillWarning, SchematicFile,
+    SheetMetaEntry,
+};
+use crate::{demo, quota};
+use kicad_db::{
+    canonical, distilled_version, read_pool, retrieve_distilled_json, retrieve_schematic_parses,
+    sheet_meta::list_sheet_meta, store_distilled_json, store_schematic_parse, PgPool,
+    StoreDistilledError,
+};
+
+// Distill cache lookups made by this process, reported by the admin overview
+static COMMIT_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static COMMIT_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static FILE_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static FILE_CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+static COALESCED: AtomicU64 = AtomicU64::new(0);
+static COUNTERS_SINCE: Lazy<DateTime<Utc>> = Lazy::new(Utc::now);
+
+/// Outcome of a distillation, shared by every caller waiting on it: the
+/// distilled data, parse cache stats and seconds spent
+type SharedOutcome = Result<Arc<(Value, DistillCacheStats, f64)>, Arc<anyhow::Error>>;
+
+type Distillation = Shared<BoxFuture<'static, SharedOutcome>>;
+
+/// A distillation running in this process, with whether its time has been
+/// charged to an API key yet
+#[derive(Clone)]
+struct Running {
+    distillation: Distillation,
+    charged: Arc<AtomicBool>,
+}
+
+// Distillations running in this process by (lowercased repo, commit), so
+// concurrent requests for the same commit wait on one instead of starting their own
+static IN_FLIGHT: Lazy<Mutex<HashMap<(String, String), Running>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A distillation failure handed to every caller that waited on it. Use
+/// [`cause`] to check what it failed with.
+#[derive(Debug)]
+pub struct SharedFailure(pub Arc<anyhow::Error>);
+
+impl std::fmt::Display for SharedFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl std::error::Error for SharedFailure {}
+
+/// The error a distillation failed with, looking through [`SharedFailure`],
+/// so its type can be checked with `downcast_ref`
+pub fn cause(e: &anyhow::Error) -> &anyhow::Error {
+    match e.downcast_ref::<SharedFailure>() {
+        Some(shared) => &shared.0,
+        None => e,
+    }
+}
+
+/// A commit's distillation was handed to a worker as job `job_id`. Poll
+/// `/api/jobs/{job_id}` and ask again once it has succeeded.
+#[derive(Debug, Clone)]
+pub struct DistillPending {
+    pub job_id: i64,
+}
 
-use crate::services::git;
-use crate::types::SchematicFile;
+impl std::fmt::Display for DistillPending {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Distillation is running as job {}; poll /api/jobs/{} and retry once it has succeeded",
+            self.job_id, self.job_id
+        )
+    }
+}
+
+impl std::error::Error for DistillPending {}
+
+/// Distill cache hit/miss counts since this process started
+#[derive(Debug, Clone, Copy)]
+pub struct CacheCounters {
+    pub commit_hits: u64,
+    pub commit_misses: u64,
+    pub file_hits: u64,
+    pub file_misses: u64,
+    /// Distill requests that waited on one already running for the same commit
+    pub coalesced: u64,
+    pub since: DateTime<Utc>,
+}
+
+/// Count a lookup in the per-commit distilled JSON cache
+pub fn record_commit_cache_lookup(hit: bool) {
+    Lazy::force(&COUNTERS_SINCE);
+    let counter = if hit {
+        &COMMIT_CACHE_HITS
+    } else {
+        &COMMIT_CACHE_MISSES
+    };
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Distillations currently running in this process
+pub fn in_flight() -> usize {
+    IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner()).len()
+}
+
+pub fn cache_counters() -> CacheCounters {
+    CacheCounters {
+        commit_hits: COMMIT_CACHE_HITS.load(Ordering::Relaxed),
+        commit_misses: COMMIT_CACHE_MISSES.load(Ordering::Relaxed),
+        file_hits: FILE_CACHE_HITS.load(Ordering::Relaxed),
+        file_misses: FILE_CACHE_MISSES.load(Ordering::Relaxed),
+        coalesced: COALESCED.load(Ordering::Relaxed),
+        since: *COUNTERS_SINCE,
+    }
+}
 
 /// Get the path to the schematic-distiller directory.
 ///
@@ -16,7 +142,7 @@ use crate::types::SchematicFile;
 /// 3. Relative to current executable
 /// 4. Relative to current working directory
 /// 5. Parent of current working directory (if running from backend/)
-fn get_distiller_path() -> PathBuf {
+pub fn get_distiller_path() -> PathBuf {
     // Try CARGO_MANIFEST_DIR first (set during cargo build/run)
     if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
         let path = PathBuf::from(manifest_dir)
@@ -72,87 +198,90 @@ fn get_distiller_path() -> PathBuf {
     PathBuf::from("schematic-distiller")
 }
 
+static DISTILLER_VERSION: Lazy<String> = Lazy::new(|| {
+    if let Ok(version) = std::env::var("DISTILLER_VERSION") {
+        if !version.is_empty() {
+            return version;
+        }
+    }
+    let pyproject = get_distiller_path().join("pyproject.toml");
+    match std::fs::read_to_string(&pyproject) {
+        Ok(contents) => project_version(&contents).unwrap_or_else(|| {
+            warn!("No version found in {}", pyproject.display());
+            "unknown".to_string()
+        }),
+        Err(e) => {
+            warn!("Failed to read {}: {}", pyproject.display(), e);
+            "unknown".to_string()
+        }
+    }
+});
+
+/// The `version` key of the `[project]` table in a pyproject.toml
+fn project_version(pyproject: &str) -> Option<String> {
+    let mut in_project = false;
+    for line in pyproject.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_project = line == "[project]";
+            continue;
+        }
+        if !in_project {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim() == "version" {
+                return Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Version of the schematic-distiller package, recorded against generated summaries
+/// so they can be regenerated when distillation changes.
+///
+/// Read from schematic-distiller/pyproject.toml, or DISTILLER_VERSION when set.
+pub fn distiller_version() -> &'static str {
+    &DISTILLER_VERSION
+}
+
 /// Get the path to the Python executable in the venv.
-fn get_python_path() -> PathBuf {
+pub fn get_python_path() -> PathBuf {
     get_distiller_path()
         .join(".venv")
         .join("bin")
         .join("python")
 }
 
-/// Get the path to the distill_demo.py script.
-fn get_distill_script_path() -> PathBuf {
-    get_distiller_path()
-        .join("examples")
-        .join("distill")
-        .join("distill_demo.py")
-}
-
-/// Run the distill_demo.py script on a directory and return the JSON output.
-async fn run_distill_script(directory: &Path) -> Result<Value> {
-    let python_path = get_python_path();
-    let script_path = get_distill_script_path();
-
-    info!(
-        "Running distill script: {:?} {:?} --dir {:?}",
-        python_path, script_path, directory
-    );
-
-    if !python_path.exists() {
-        anyhow::bail!(
-            "Python venv not found at {:?}. Run setup_venv.sh first.",
-            python_path
-        );
-    }
-
-    if !script_path.exists() {
-        anyhow::bail!("Distill script not found at {:?}", script_path);
-    }
-
-    let output = Command::new(&python_path)
-        .arg(&script_path)
-        .arg("--dir")
-        .arg(directory)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .await
-        .context("Failed to execute distill script")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        error!("Distill script failed: {}", stderr);
-        anyhow::bail!("Distill script failed: {}", stderr);
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    serde_json::from_str(&stdout).context("Failed to parse distill script output as JSON")
+/// A fresh directory below the work dir, named after `purpose` (e.g.
+/// "kicad-distill"), the repository and the commit. It is removed when dropped.
+fn scratch_dir(purpose: &str, repo_slug: &str, commit_hash: &str) -> Result<TempDir> {
+    tempfile::Builder::new()
+        .prefix(&format!(
+            "{}-{}-{}-",
+            purpose,
+            repo_slug.replace('/', "-"),
+            commit_hash
+        ))
+        .tempdir_in(disk::work_dir())
+        .with_context(|| format!("Failed to create {} directory", purpose))
 }
 
 /// Write schematic files to a temporary directory, preserving directory structure.
-async fn write_schematic_files_to_temp(
+///
+/// Each call gets its own directory (see `scratch_dir`), so tools and requests
+/// working on the same commit don't clobber each other. Keep the returned
+/// guard until the files have been read; dropping it deletes them.
+pub async fn write_schematic_files_to_temp(
     files: &[SchematicFile],
+    purpose: &str,
     repo_slug: &str,
     commit_hash: &str,
-) -> Result<PathBuf> {
-    let temp_dir = std::env::temp_dir()
-        .join("kicad-distill")
-        .join(repo_slug.replace('/', "-"))
-        .join(commit_hash);
-
-    // Clean up any existing temp dir for this repo/commit
-    if temp_dir.exists() {
-        tokio::fs::remove_dir_all(&temp_dir)
-            .await
-            .context("Failed to clean up existing temp directory")?;
-    }
-
-    tokio::fs::create_dir_all(&temp_dir)
-        .await
-        .context("Failed to create temp directory")?;
+) -> Result<TempDir> {
+    let temp_dir = scratch_dir(purpose, repo_slug, commit_hash)?;
 
     for file in files {
-        let file_path = temp_dir.join(&file.path);
+        let file_path = temp_dir.path().join(&file.path);
 
         // Create parent directories if needed
         if let Some(parent) = file_path.parent() {
@@ -171,18 +300,146 @@ async fn write_schematic_files_to_temp(
     Ok(temp_dir)
 }
 
-/// Distill all schematic files from a repo at a specific commit.
+/// Output of a backend for the project made up of `files`
+async fn run_backend(
+    backend: &dyn DistillerBackend,
+    files: &[SchematicFile],
+    repo_slug: &str,
+    commit_hash: &str,
+) -> Result<Value> {
+    let purpose = format!("kicad-distill-{}", backend.name());
+    let temp_dir = write_schematic_files_to_temp(files, &purpose, repo_slug, commit_hash)
+        .await
+        .context("Failed to write schematic files to temp directory")?;
+    backend.distill_dir(temp_dir.path()).await
+}
+
+/// Python output for the whole project. The parse of each schematic is taken
+/// from the parse cache (keyed by git blob OID and distiller version) where
+/// possible, and files the script had to parse are added to it; hierarchy
+/// and connectivity always run on the full project.
+async fn python_distill(
+    pool: &PgPool,
+    repo_slug: &str,
+    commit_hash: &str,
+    files: &[SchematicFile],
+    schematics: &[&SchematicFile],
+) -> Result<(Value, DistillCacheStats)> {
+    let version = distiller_version();
+    let blob_oids: Vec<String> = schematics.iter().map(|f| f.blob_oid.clone()).collect();
+    let cached = match retrieve_schematic_parses(pool, version, &blob_oids).await {
+        Ok(cached) => cached,
+        Err(e) => {
+            error!("Failed to check schematic parse cache: {}", e);
+            HashMap::new()
+        }
+    };
+
+    let cache_dir = scratch_dir("kicad-parse-cache", repo_slug, commit_hash)?;
+    for (blob_oid, parsed) in &cached {
+        tokio::fs::write(cache_dir.path().join(format!("{}.json", blob_oid)), parsed)
+            .await
+            .context("Failed to write cached schematic parse")?;
+    }
+
+    let backend = PythonDistiller::with_parse_cache(cache_dir.path().to_path_buf());
+    let output = run_backend(&backend, files, repo_slug, commit_hash).await;
+
+    // Files that failed to parse leave no entry behind
+    for blob_oid in blob_oids.iter().filter(|oid| !cached.contains_key(*oid)) {
+        let Ok(parsed) = tokio::fs::read(cache_dir.path().join(format!("{}.json", blob_oid))).await
+        else {
+            continue;
+        };
+        if let Err(e) = store_schematic_parse(pool, blob_oid, version, &parsed).await {
+            error!("Failed to cache schematic parse {}: {}", blob_oid, e);
+        }
+    }
+    if let Err(e) = cache_dir.close() {
+        warn!("Failed to remove parse cache directory: {}", e);
+    }
+
+    let hits = schematics
+        .iter()
+        .filter(|f| cached.contains_key(&f.blob_oid))
+        .count();
+    let stats = DistillCacheStats {
+        hits,
+        misses: schematics.len() - hits,
+    };
+    Ok((output?, stats))
+}
+
+/// A commit distilled by a chosen backend
+pub struct DistillOutcome {
+    pub distilled: Value,
+    pub stats: DistillCacheStats,
+    /// Differences between the Python and native output, in compare mode
+    pub comparison: Option<DistillComparison>,
+}
+
+/// Distill all schematic files from a repo at a specific commit with the
+/// configured backend (DISTILLER_BACKEND).
+///
+/// Fails with `kicad_format::UnsupportedFormat` if every schematic predates KiCad 6.
+pub async fn distill_repo_schematics(
+    pool: &PgPool,
+    repo_slug: &str,
+    commit_hash: &str,
+) -> Result<(Value, DistillCacheStats)> {
+    let outcome = distill_with(pool, repo_slug, commit_hash, BackendChoice::configured()).await?;
+    if let Some(comparison) = &outcome.comparison {
+        if comparison.difference_count > 0 {
+            warn!(
+                "Native distiller differs from python for {}/{} in {} place(s)",
+                repo_slug, commit_hash, comparison.difference_count
+            );
+        }
+    }
+    Ok((outcome.distilled, outcome.stats))
+}
+
+/// Distill all schematic files from a repo at a specific commit with `backend`.
+///
+/// The commit is distilled as one hierarchical project, so sheet pins and
+/// hierarchical labels join nets across sheets. The Python backend reuses
+/// cached parses of unchanged files. In compare mode the Python output is
+/// returned together with its differences from the native output.
+///
+/// The result is in canonical form (see `kicad_db::canonical`).
 ///
-/// Fetches schematic files from the repository, writes them to a temp directory,
-/// runs the Python distill script, and returns the JSON output.
-pub async fn distill_repo_schematics(repo_slug: &str, commit_hash: &str) -> Result<Value> {
-    info!("Distilling schematics for {}/{}", repo_slug, commit_hash);
+/// Schematics that predate KiCad 6 are skipped with a warning each; fails
+/// with `kicad_format::UnsupportedFormat` if that leaves nothing to distill.
+pub async fn distill_with(
+    pool: &PgPool,
+    repo_slug: &str,
+    commit_hash: &str,
+    backend: BackendChoice,
+) -> Result<DistillOutcome> {
+    info!(
+        "Distilling schematics for {}/{} ({} backend)",
+        repo_slug,
+        commit_hash,
+        backend.name()
+    );
 
-    let files = git::get_schematic_files(repo_slug, commit_hash)
+    let mut files = git::get_schematic_files(repo_slug, commit_hash)
         .await
         .context("Failed to fetch schematic files from repo")?;
 
-    if files.is_empty() {
+    // Leave out schematics the parser would choke on rather than failing the commit
+    let unsupported = kicad_format::unsupported(&files);
+    files.retain(|f| !unsupported.iter().any(|u| u.path == f.path));
+
+    let schematics: Vec<&SchematicFile> = files
+        .iter()
+        .filter(|f| f.path.ends_with(".kicad_sch"))
+        .collect();
+
+    if schematics.is_empty() {
+        if let Some(unsupported) = unsupported.into_iter().next() {
+            return Err(unsupported.into());
+        }
         anyhow::bail!(
             "No .kicad_sch files found in repo {} at commit {}",
             repo_slug,
@@ -190,20 +447,437 @@ pub async fn distill_repo_schematics(repo_slug: &str, commit_hash: &str) -> Resu
         );
     }
 
-    info!("Found {} schematic file(s) to distill", files.len());
+    info!("Found {} schematic file(s) to distill", schematics.len());
+    for skipped in &unsupported {
+        warn!("Skipping {}/{}: {}", repo_slug, commit_hash, skipped);
+    }
 
-    let temp_dir = write_schematic_files_to_temp(&files, repo_slug, commit_hash)
-        .await
-        .context("Failed to write schematic files to temp directory")?;
+    let native = || run_backend(&NativeDistiller, &files, repo_slug, commit_hash);
+    let uncached = DistillCacheStats {
+        hits: 0,
+        misses: schematics.len(),
+    };
+    let (output, stats, comparison) = match backend {
+        BackendChoice::Python => {
+            let (output, stats) =
+                python_distill(pool, repo_slug, commit_hash, &files, &schematics).await?;
+            (output, stats, None)
+        }
+        BackendChoice::Native => (native().await?, uncached, None),
+        BackendChoice::Compare => {
+            let (output, stats) =
+                python_distill(pool, repo_slug, commit_hash, &files, &schematics).await?;
+            let candidate = native().await?;
+            let comparison = distiller::compare(
+                &PythonDistiller::default(),
+                &output,
+                &NativeDistiller,
+                &candidate,
+            );
+            (output, stats, Some(comparison))
+        }
+    };
 
-    let distilled = run_distill_script(&temp_dir).await?;
+    let mut distilled = output;
+    if !unsupported.is_empty() {
+        let mut skipped = warnings(&distilled);
+        skipped.extend(unsupported.iter().map(DistillWarning::from));
+        distilled["warnings"] = serde_json::to_value(skipped).unwrap_or_default();
+    }
+
+    // Record the format version of each schematic alongside the distilled data
+    if let Some(obj) = distilled.as_object_mut() {
+        let versions: serde_json::Map<String, Value> = files
+            .iter()
+            .filter_map(|f| Some((f.path.clone(), Value::from(f.format_version?))))
+            .collect();
+        obj.insert("format_versions".to_string(), Value::Object(versions));
+    }
+    // The same design must store and hash to the same bytes, whatever order
+    // the backend listed things in
+    canonical::canonicalize(&mut distilled);
 
     info!(
-        "Distillation complete for {}/{}: {} file(s) processed",
+        "Distillation complete for {}/{}: {} file(s), {} parse(s) from cache",
         repo_slug,
         commit_hash,
-        files.len()
+        schematics.len(),
+        stats.hits
     );
 
+    Ok(DistillOutcome {
+        distilled,
+        stats,
+        comparison,
+    })
+}
+
+/// Files the distiller skipped as unparseable or in an unsupported format, as
+/// recorded in the distilled output
+pub fn warnings(distilled: &Value) -> Vec<DistillWarning> {
+    distilled
+        .get("warnings")
+        .cloned()
+        .and_then(|w| serde_json::from_value(w).ok())
+        .unwrap_or_default()
+}
+
+/// Sheet each component was distilled from, by reference. Components from
+/// data cached before sheet paths were recorded fall under "".
+fn component_sheets(distilled: &Value) -> HashMap<&str, &str> {
+    distilled
+        .get("components")
+        .and_then(Value::as_object)
+        .map(|components| {
+            components
+                .iter()
+                .map(|(reference, comp)| {
+                    let sheet = comp
+                        .get("sheet_path")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default();
+                    (reference.as_str(), sheet)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn empty_sheet(path: &str) -> DistillSheet {
+    DistillSheet {
+        path: path.to_string(),
+        component_count: 0,
+        net_count: 0,
+    }
+}
+
+fn manifest_entry<'a>(
+    manifest: &'a mut BTreeMap<String, DistillSheet>,
+    path: &str,
+) -> &'a mut DistillSheet {
+    manifest
+        .entry(path.to_string())
+        .or_insert_with(|| empty_sheet(path))
+}
+
+/// Per-sheet counts of a commit's distilled data, for clients that load
+/// sheets on demand. Every schematic in `files` is listed, including sheets
+/// without components. A net counts towards each sheet it has pins on.
+pub fn sheet_manifest(distilled: &Value, files: &[String]) -> Vec<DistillSheet> {
+    let sheets_by_ref = component_sheets(distilled);
+
+    let mut manifest: BTreeMap<String, DistillSheet> = files
+        .iter()
+        .filter(|path| path.ends_with(".kicad_sch"))
+        .map(|path| (path.clone(), empty_sheet(path)))
+        .collect();
+    for path in sheets_by_ref.values() {
+        manifest_entry(&mut manifest, path).component_count += 1;
+    }
+    if let Some(nets) = distilled.get("nets").and_then(Value::as_object) {
+        for members in nets.values().filter_map(Value::as_object) {
+            let on: BTreeSet<&str> = members
+                .keys()
+                .filter_map(|reference| sheets_by_ref.get(reference.as_str()).copied())
+                .collect();
+            for path in on {
+                manifest_entry(&mut manifest, path).net_count += 1;
+            }
+        }
+    }
+
+    manifest.into_values().collect()
+}
+
+/// The part of a commit's distilled data that belongs to one sheet: its
+/// components, the nets they connect to (with only this sheet's members),
+/// proximities between them and any parse warning for the file. None if the
+/// sheet has neither components nor a warning.
+pub fn sheet_distilled(distilled: &Value, sheet: &str) -> Option<Value> {
+    let sheets_by_ref = component_sheets(distilled);
+    let on_sheet = |reference: &str| sheets_by_ref.get(reference) == Some(&sheet);
+
+    let components: serde_json::Map<String, Value> = distilled
+        .get("components")
+        .and_then(Value::as_object)
+        .map(|components| {
+            components
+                .iter()
+                .filter(|(reference, _)| on_sheet(reference))
+                .map(|(reference, comp)| (reference.clone(), comp.clone()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let nets: serde_json::Map<String, Value> = distilled
+        .get("nets")
+        .and_then(Value::as_object)
+        .map(|nets| {
+            nets.iter()
+                .filter_map(|(name, members)| {
+                    let members: serde_json::Map<String, Value> = members
+                        .as_object()?
+                        .iter()
+                        .filter(|(reference, _)| on_sheet(reference))
+                        .map(|(reference, pins)| (reference.clone(), pins.clone()))
+                        .collect();
+                    (!members.is_empty()).then(|| (name.clone(), Value::Object(members)))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let proximities: Vec<Value> = distilled
+        .get("proximities")
+        .and_then(Value::as_array)
+        .map(|edges| {
+            edges
+                .iter()
+                .filter(|edge| {
+                    ["ref_a", "ref_b"]
+                        .iter()
+                        .all(|key| edge.get(key).and_then(Value::as_str).is_some_and(on_sheet))
+                })
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let warnings: Vec<DistillWarning> = warnings(distilled)
+        .into_iter()
+        .filter(|w| w.file == sheet)
+        .collect();
+    if components.is_empty() && warnings.is_empty() {
+        return None;
+    }
+
+    let mut result = serde_json::json!({
+        "components": components,
+        "nets": nets,
+        "proximities": proximities,
+    });
+    if !warnings.is_empty() {
+        result["warnings"] = serde_json::to_value(warnings).unwrap_or_default();
+    }
+    Some(result)
+}
+
+/// Metadata users attached to the repository's sheets. Auxiliary to the
+/// distilled data, so a failed lookup is logged and yields none.
+pub async fn sheet_meta(pool: &PgPool, repo_slug: &str) -> Vec<SheetMetaEntry> {
+    let repo_url = github::repo_url(repo_slug);
+    match list_sheet_meta(read_pool(pool), &repo_url).await {
+        Ok(meta) => meta
+            .into_iter()
+            .map(|m| SheetMetaEntry {
+                sheet: m.sheet_path,
+                owner: m.owner,
+                subsystem: m.subsystem,
+                review_status: m.review_status,
+                updated_at: m.updated_at,
+            })
+            .collect(),
+        Err(e) => {
+            warn!("Failed to load sheet metadata for {}: {}", repo_slug, e);
+            Vec::new()
+        }
+    }
+}
+
+/// Distill a commit in this process and store the result in the distilled JSON cache
+pub async fn distill_and_store(
+    pool: &PgPool,
+    repo_slug: &str,
+    commit_hash: &str,
+) -> Result<(Value, DistillCacheStats)> {
+    disk::check_admission().await?;
+    let repo_url = github::repo_url(repo_slug);
+    quarantine::check(pool, &repo_url, commit_hash, quarantine::STAGE_DISTILL).await?;
+    // Read before distilling so a result stored meanwhile by another worker isn't clobbered
+    let version = distilled_version(pool, &repo_url, commit_hash).await?;
+    let (distilled, stats) = match distill_repo_schematics(pool, repo_slug, commit_hash).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            quarantine::record_failure(pool, &repo_url, commit_hash, quarantine::STAGE_DISTILL, &e)
+                .await;
+            return Err(e);
+        }
+    };
+    quarantine::record_success(pool, &repo_url, commit_hash, quarantine::STAGE_DISTILL).await;
+
+    match store_distilled_json(pool, &repo_url, commit_hash, &distilled, Some(version)).await {
+        Ok(_) => {
+            info!("Cached distilled result for {}/{}", repo_slug, commit_hash);
+            metrics::record(pool, repo_slug, commit_hash, &distilled).await;
+            repo_cards::refresh_later(pool, repo_slug);
+        }
+        // The other writer's result is just as fresh; keep it and return it
+        Err(StoreDistilledError::Conflict { current_version }) => {
+            info!(
+                "Distilled result for {}/{} was stored concurrently (version {}); keeping it",
+                repo_slug, commit_hash, current_version
+            );
+            if let Some(stored) = retrieve_distilled_json(pool, &repo_url, commit_hash).await? {
+                return Ok((stored, stats));
+            }
+        }
+        Err(e) => error!("Failed to cache distilled result: {}", e),
+    }
+
+    Ok((distilled, stats))
+}
+
+/// Distill a commit and cache the result.
+///
+/// With the job queue enabled the API doesn't wait for the work: it is
+/// handed to a `grokicad-worker` and this fails with [`DistillPending`]
+/// naming the job (see [`queue_distill`]). Otherwise, and on workers, it
+/// runs in this process.
+/// Callers asking for a commit that is already being distilled wait for that
+/// distillation and get its result, or its error as a [`SharedFailure`].
+/// The time is charged once, to the first caller with an API key, so a
+/// distillation started by a job is charged to a request that joins it. A
+/// queued job is charged to the key of the request that queued it.
+pub async fn distill_commit(
+    pool: &PgPool,
+    repo_slug: &str,
+    commit_hash: &str,
+) -> Result<(Value, DistillCacheStats)> {
+    demo::check_distill()?;
+    if jobs::hand_off() {
+        // Refuse here so the caller gets the quarantine rather than a failed job
+        let repo_url = github::repo_url(repo_slug);
+        quarantine::check(pool, &repo_url, commit_hash, quarantine::STAGE_DISTILL).await?;
+        let job_id = queue_distill(pool, repo_slug, commit_hash).await?;
+        return Err(DistillPending { job_id }.into());
+    }
+
+    let key = (repo_slug.to_lowercase(), commit_hash.to_string());
+    let (running, started) = {
+        let mut in_flight = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+        match in_flight.get(&key) {
+            Some(running) => (running.clone(), false),
+            None => {
+                let running = Running {
+                    distillation: run_distillation(
+                        pool.clone(),
+                        repo_slug.to_string(),
+                        commit_hash.to_string(),
+                        key.clone(),
+                    )
+                    .boxed()
+                    .shared(),
+                    charged: Arc::new(AtomicBool::new(false)),
+                };
+                in_flight.insert(key, running.clone());
+                (running, true)
+            }
+        }
+    };
+    if !started {
+        COALESCED.fetch_add(1, Ordering::Relaxed);
+        info!(
+            "Waiting on the distillation of {}/{} already in progress",
+            repo_slug, commit_hash
+        );
+    }
+
+    let (distilled, stats, seconds) = match running.distillation.await {
+        Ok(outcome) => outcome.as_ref().clone(),
+        Err(e) => return Err(SharedFailure(e).into()),
+    };
+    if quota::current_key().is_some() && !running.charged.swap(true, Ordering::Relaxed) {
+        quota::charge_distill_seconds(pool, seconds).await;
+    }
+    Ok((distilled, stats))
+}
+
+/// The distillation behind [`distill_commit`], run once however many callers
+/// wait on it. Leaves `IN_FLIGHT` when done, so later requests start afresh.
+async fn run_distillation(
+    pool: PgPool,
+    repo_slug: String,
+    commit_hash: String,
+    key: (String, String),
+) -> SharedOutcome {
+    let outcome = distill_once(&pool, &repo_slug, &commit_hash).await;
+    IN_FLIGHT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&key);
+    outcome.map(Arc::new).map_err(Arc::new)
+}
+
+async fn distill_once(
+    pool: &PgPool,
+    repo_slug: &str,
+    commit_hash: &str,
+) -> Result<(Value, DistillCacheStats, f64)> {
+    let started = Instant::now();
+    let (distilled, stats) = distill_and_store(pool, repo_slug, commit_hash).await?;
+    let seconds = started.elapsed().as_secs_f64();
+
+    FILE_CACHE_HITS.fetch_add(stats.hits as u64, Ordering::Relaxed);
+    FILE_CACHE_MISSES.fetch_add(stats.misses as u64, Ordering::Relaxed);
+
+    Ok((distilled, stats, seconds))
+}
+
+/// Id of the distill job for a commit: the one already queued or running,
+/// or a new one. Fails with the commit's `UnsupportedFormat` if its last job
+/// was abandoned for one, since queueing it again would only fail the same way.
+async fn queue_distill(pool: &PgPool, repo_slug: &str, commit_hash: &str) -> Result<i64> {
+    let request = JobRequest::Distill {
+        repo: repo_slug.to_string(),
+        commit: commit_hash.to_string(),
+        api_key: None,
+    };
+    // Join a job queued under any key; a new one is charged to this caller's key
+    if let Some(job) = jobs::latest(pool, &request).await? {
+        match job.status.as_str() {
+            kicad_db::jobs::STATUS_QUEUED | kicad_db::jobs::STATUS_RUNNING => return Ok(job.id),
+            kicad_db::jobs::STATUS_FAILED => {
+                let unsupported = job
+                    .result
+                    .as_ref()
+                    .filter(|r| r.get("error").and_then(Value::as_str) == Some("unsupported_format"))
+                    .and_then(|r| r.get("details"))
+                    .and_then(kicad_format::UnsupportedFormat::from_details);
+                if let Some(unsupported) = unsupported {
+                    return Err(unsupported.into());
+                }
+            }
+            _ => {}
+        }
+    }
+    let request = JobRequest::Distill {
+        repo: repo_slug.to_string(),
+        commit: commit_hash.to_string(),
+        api_key: quota::current_key(),
+    };
+    let job_id = jobs::enqueue(pool, &request).await?;
+    info!(
+        "Queued distill job {} for {}/{}",
+        job_id, repo_slug, commit_hash
+    );
+    Ok(job_id)
+}
+
+/// Get distilled data for a repo/commit, using the database cache when available.
+///
+/// On a cache miss the schematics are distilled and the result is stored for next time.
+pub async fn get_or_distill(pool: &PgPool, repo_slug: &str, commit_hash: &str) -> Result<Value> {
+    let repo_url = github::repo_url(repo_slug);
+
+    match retrieve_distilled_json(pool, &repo_url, commit_hash).await {
+        Ok(Some(cached)) => {
+            record_commit_cache_lookup(true);
+            return Ok(cached);
+        }
+        Ok(None) => record_commit_cache_lookup(false),
+        Err(e) => error!("Failed to check distill cache: {}", e),
+    }
+
+    let (distilled, _) = distill_commit(pool, repo_slug, commit_hash).await?;
     Ok(distilled)
 }
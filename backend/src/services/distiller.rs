@@ -0,0 +1,725 @@
+use anyhow::{Context, Result};
+use futures_util::future::BoxFuture;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{error, info, warn};
+
+use crate::services::distill::{self, get_distiller_path, get_python_path};
+use crate::services::symbols::{parse_sexp_children, Sexp};
+use crate::types::{DistillComparison, DistillDifference};
+
+// Differences listed in a comparison; the rest are only counted
+const MAX_DIFFERENCES: usize = 200;
+
+// Default for DISTILL_TIMEOUT_SECS
+const DEFAULT_DISTILL_TIMEOUT_SECS: u64 = 300;
+
+/// A way of turning schematic files into distilled JSON.
+///
+/// Backends distill the project under a directory as one hierarchical design
+/// and return `{"components": ..., "nets": ..., "proximities": ..., "warnings": [...]}`,
+/// where each component's `sheet_path` is the file it is placed in, relative
+/// to the directory. Caching stays in `services::distill`, so backends can be
+/// swapped per request.
+pub trait DistillerBackend: Send + Sync {
+    /// Name used in DISTILLER_BACKEND and the `backend` request field
+    fn name(&self) -> &'static str;
+
+    fn distill_dir<'a>(&'a self, directory: &'a Path) -> BoxFuture<'a, Result<Value>>;
+}
+
+/// The schematic-distiller Python package, run as a script in its venv
+#[derive(Debug, Clone, Default)]
+pub struct PythonDistiller {
+    /// Directory of parsed files (`<blob oid>.json`) the script reads before
+    /// parsing and writes the files it had to parse to
+    parse_cache: Option<PathBuf>,
+}
+
+impl PythonDistiller {
+    pub fn with_parse_cache(directory: PathBuf) -> Self {
+        Self {
+            parse_cache: Some(directory),
+        }
+    }
+}
+
+/// Native Rust parser. Reads components, their properties and pin numbers;
+/// connectivity (nets, pin names) and proximities aren't implemented yet, so
+/// use `compare` to track how far it is from the Python output.
+pub struct NativeDistiller;
+
+/// Which backend distills a request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendChoice {
+    Python,
+    Native,
+    /// Run Python and native, return the Python output and a diff of the two
+    Compare,
+}
+
+impl BackendChoice {
+    pub const NAMES: &'static [&'static str] = &["python", "native", "compare"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "python" => Some(Self::Python),
+            "native" => Some(Self::Native),
+            "compare" => Some(Self::Compare),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Python => "python",
+            Self::Native => "native",
+            Self::Compare => "compare",
+        }
+    }
+
+    /// DISTILLER_BACKEND (default python). An unknown name falls back to python.
+    pub fn configured() -> Self {
+        match std::env::var("DISTILLER_BACKEND") {
+            Ok(name) if !name.trim().is_empty() => Self::from_name(&name).unwrap_or_else(|| {
+                warn!("Unknown DISTILLER_BACKEND {:?}; using python", name);
+                Self::Python
+            }),
+            _ => Self::Python,
+        }
+    }
+}
+
+/// The distill script ran longer than the configured timeout and was killed.
+/// Retrying would only hang again, so callers treat this as final.
+#[derive(Debug, Clone)]
+pub struct DistillTimeout {
+    pub timeout: Duration,
+}
+
+impl std::fmt::Display for DistillTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Distillation timed out after {}s and was cancelled",
+            self.timeout.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for DistillTimeout {}
+
+/// How long the distill script may run (DISTILL_TIMEOUT_SECS, default 300)
+pub fn distill_timeout() -> Duration {
+    let secs = std::env::var("DISTILL_TIMEOUT_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(DEFAULT_DISTILL_TIMEOUT_SECS);
+    Duration::from_secs(secs)
+}
+
+/// Get the path to the distill_demo.py script.
+fn get_distill_script_path() -> PathBuf {
+    get_distiller_path()
+        .join("examples")
+        .join("distill")
+        .join("distill_demo.py")
+}
+
+/// Components name the file they are placed in as `sheet_path`, like the
+/// other backends; the script's hierarchy path (e.g. "/power/") moves to
+/// `hierarchy_path`
+fn file_sheet_paths(distilled: &mut Value) {
+    let Some(components) = distilled.get_mut("components").and_then(Value::as_object_mut) else {
+        return;
+    };
+    for component in components.values_mut().filter_map(Value::as_object_mut) {
+        let Some(file) = component.remove("sheet_file") else {
+            continue;
+        };
+        if let Some(hierarchy) = component.insert("sheet_path".to_string(), file) {
+            component.insert("hierarchy_path".to_string(), hierarchy);
+        }
+    }
+}
+
+/// Run the distill_demo.py script on a directory as one hierarchical project
+/// and return the JSON output.
+async fn run_distill_script(directory: &Path, parse_cache: Option<&Path>) -> Result<Value> {
+    let python_path = get_python_path();
+    let script_path = get_distill_script_path();
+
+    info!(
+        "Running distill script: {:?} {:?} --dir {:?}",
+        python_path, script_path, directory
+    );
+
+    if !python_path.exists() {
+        anyhow::bail!(
+            "Python venv not found at {:?}. Run setup_venv.sh first.",
+            python_path
+        );
+    }
+
+    if !script_path.exists() {
+        anyhow::bail!("Distill script not found at {:?}", script_path);
+    }
+
+    // kill_on_drop reaps the child when the timeout drops its future
+    let mut command = Command::new(&python_path);
+    command.arg(&script_path).arg("--dir").arg(directory);
+    if let Some(parse_cache) = parse_cache {
+        command.arg("--parse-cache").arg(parse_cache);
+    }
+    let child = command
+        // Generated net names follow set iteration order; pin the hash seed
+        // so the same design always distills to the same bytes
+        .env("PYTHONHASHSEED", "0")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute distill script")?;
+
+    let timeout = distill_timeout();
+    let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(output) => output.context("Failed to execute distill script")?,
+        Err(_) => {
+            error!(
+                "Distill script on {:?} exceeded {}s; killed",
+                directory,
+                timeout.as_secs()
+            );
+            return Err(DistillTimeout { timeout }.into());
+        }
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("Distill script failed: {}", stderr);
+        anyhow::bail!("Distill script failed: {}", stderr);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut distilled: Value =
+        serde_json::from_str(&stdout).context("Failed to parse distill script output as JSON")?;
+    file_sheet_paths(&mut distilled);
+
+    for warning in distill::warnings(&distilled) {
+        warn!(
+            "Skipped unparseable schematic {}: {}",
+            warning.file, warning.error
+        );
+    }
+
+    Ok(distilled)
+}
+
+impl DistillerBackend for PythonDistiller {
+    fn name(&self) -> &'static str {
+        "python"
+    }
+
+    fn distill_dir<'a>(&'a self, directory: &'a Path) -> BoxFuture<'a, Result<Value>> {
+        Box::pin(run_distill_script(directory, self.parse_cache.as_deref()))
+    }
+}
+
+/// `.kicad_sch` files under `directory`, relative to it with `/` separators
+fn schematic_paths(directory: &Path) -> Result<Vec<String>> {
+    let mut found = Vec::new();
+    let mut pending = vec![directory.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in
+            std::fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))?
+        {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if path.extension().is_some_and(|e| e == "kicad_sch") {
+                if let Ok(relative) = path.strip_prefix(directory) {
+                    let segments: Vec<String> = relative
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                        .collect();
+                    found.push(segments.join("/"));
+                }
+            }
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// The `(<keyword> ...)` entry among a node's items
+fn child<'a>(items: &'a [Sexp], keyword: &str) -> Option<&'a [Sexp]> {
+    items.iter().find_map(|item| match item {
+        Sexp::List(list) if list.first().and_then(Sexp::atom) == Some(keyword) => {
+            Some(list.as_slice())
+        }
+        _ => None,
+    })
+}
+
+/// A placed `(symbol (lib_id ...) ...)` as a component entry; None for
+/// library definitions and power/virtual symbols (references starting with '#')
+fn native_component(items: &[Sexp]) -> Option<(String, Value)> {
+    let lib_id = child(items, "lib_id")?.get(1)?.atom()?;
+
+    let mut properties = BTreeMap::new();
+    for item in items {
+        let Sexp::List(property) = item else {
+            continue;
+        };
+        if let [keyword, name, value, ..] = property.as_slice() {
+            if keyword.atom() == Some("property") {
+                if let (Some(name), Some(value)) = (name.atom(), value.atom()) {
+                    properties.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+    }
+
+    let reference = properties.remove("Reference")?;
+    if reference.starts_with('#') {
+        return None;
+    }
+    let value = properties.remove("Value").unwrap_or_default();
+    let footprint = properties
+        .remove("Footprint")
+        .filter(|f| !f.is_empty() && f != "~");
+    properties.retain(|_, v| !v.is_empty());
+
+    let at = child(items, "at").unwrap_or_default();
+    let coordinate = |i: usize| {
+        at.get(i)
+            .and_then(Sexp::atom)
+            .and_then(|v| v.parse::<f64>().ok())
+            .unwrap_or_default()
+    };
+
+    let pins: Vec<Value> = items
+        .iter()
+        .filter_map(|item| match item {
+            Sexp::List(pin) if pin.first().and_then(Sexp::atom) == Some("pin") => {
+                pin.get(1).and_then(Sexp::atom)
+            }
+            _ => None,
+        })
+        .map(|number| json!({ "number": number, "name": null, "net": null }))
+        .collect();
+
+    Some((
+        reference.clone(),
+        json!({
+            "lib_id": lib_id,
+            "value": value,
+            "position": { "x": coordinate(1), "y": coordinate(2) },
+            "footprint": footprint,
+            "properties": properties,
+            "pins": pins,
+            "reference": reference,
+        }),
+    ))
+}
+
+/// Distill one schematic natively; None if it isn't a well-formed S-expression
+fn native_distill(content: &str) -> Option<Value> {
+    let mut components = Map::new();
+    let complete = parse_sexp_children(content, |node| {
+        let Sexp::List(items) = node else {
+            return;
+        };
+        if items.first().and_then(Sexp::atom) != Some("symbol") {
+            return;
+        }
+        let Some((reference, component)) = native_component(&items) else {
+            return;
+        };
+        // Units of a multi-unit part share a reference; collect their pins on one entry
+        match components.get_mut(&reference) {
+            Some(existing) => {
+                let extra = component["pins"].as_array().cloned().unwrap_or_default();
+                if let Some(pins) = existing["pins"].as_array_mut() {
+                    pins.extend(extra);
+                }
+            }
+            None => {
+                components.insert(reference, component);
+            }
+        }
+    });
+    complete.then(|| {
+        json!({
+            "components": components,
+            "nets": {},
+            "proximities": [],
+        })
+    })
+}
+
+impl DistillerBackend for NativeDistiller {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    fn distill_dir<'a>(&'a self, directory: &'a Path) -> BoxFuture<'a, Result<Value>> {
+        Box::pin(async move {
+            let directory = directory.to_path_buf();
+            tokio::task::spawn_blocking(move || {
+                let mut components = Map::new();
+                let mut warnings = Vec::new();
+                for path in schematic_paths(&directory)? {
+                    let content = std::fs::read_to_string(directory.join(&path))
+                        .with_context(|| format!("Failed to read {}", path))?;
+                    let Some(distilled) = native_distill(&content) else {
+                        warnings.push(json!({
+                            "file": path,
+                            "error": "Malformed S-expression",
+                        }));
+                        continue;
+                    };
+                    let Some(Value::Object(placed)) = distilled.get("components").cloned() else {
+                        continue;
+                    };
+                    for (reference, mut component) in placed {
+                        if components.contains_key(&reference) {
+                            warn!(
+                                "Duplicate reference {} in {}; keeping the first",
+                                reference, path
+                            );
+                            continue;
+                        }
+                        component["sheet_path"] = Value::from(path.as_str());
+                        components.insert(reference, component);
+                    }
+                }
+                let mut distilled = json!({
+                    "components": components,
+                    "nets": {},
+                    "proximities": [],
+                });
+                if !warnings.is_empty() {
+                    distilled["warnings"] = Value::Array(warnings);
+                }
+                Ok(distilled)
+            })
+            .await
+            .context("Native distiller panicked")?
+        })
+    }
+}
+
+/// Arrays of pins are compared by pin number rather than position
+fn keyed_by_number(items: &[Value]) -> Option<BTreeMap<&str, &Value>> {
+    items
+        .iter()
+        .map(|item| Some((item.get("number")?.as_str()?, item)))
+        .collect()
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+fn diff_values(
+    file: &str,
+    pointer: &str,
+    primary: Option<&Value>,
+    candidate: Option<&Value>,
+    comparison: &mut DistillComparison,
+) {
+    let mut descend = |key: &str, a: Option<&Value>, b: Option<&Value>| {
+        let pointer = format!("{}/{}", pointer, escape_pointer(key));
+        diff_values(file, &pointer, a, b, comparison);
+    };
+
+    match (primary, candidate) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            let keys: std::collections::BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+            for key in keys {
+                descend(key, a.get(key), b.get(key));
+            }
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => {
+            match (keyed_by_number(a), keyed_by_number(b)) {
+                (Some(a), Some(b)) => {
+                    let keys: std::collections::BTreeSet<&str> =
+                        a.keys().chain(b.keys()).copied().collect();
+                    for key in keys {
+                        descend(key, a.get(key).copied(), b.get(key).copied());
+                    }
+                }
+                _ => {
+                    for i in 0..a.len().max(b.len()) {
+                        descend(&i.to_string(), a.get(i), b.get(i));
+                    }
+                }
+            }
+        }
+        (a, b) if a == b => {}
+        // Numbers that differ only by float formatting are equal
+        (Some(Value::Number(a)), Some(Value::Number(b)))
+            if a.as_f64()
+                .zip(b.as_f64())
+                .is_some_and(|(a, b)| (a - b).abs() < 1e-6) => {}
+        (a, b) => {
+            comparison.difference_count += 1;
+            if comparison.differences.len() < MAX_DIFFERENCES {
+                comparison.differences.push(DistillDifference {
+                    file: file.to_string(),
+                    pointer: pointer.to_string(),
+                    primary: a.cloned(),
+                    candidate: b.cloned(),
+                });
+            }
+        }
+    }
+}
+
+/// Diff two backends' output for the same commit. Differences are reported
+/// per sheet as JSON pointers into that sheet's part of the distilled data
+/// (see `distill::sheet_distilled`).
+pub fn compare(
+    primary: &dyn DistillerBackend,
+    primary_output: &Value,
+    candidate: &dyn DistillerBackend,
+    candidate_output: &Value,
+) -> DistillComparison {
+    let mut comparison = DistillComparison {
+        primary: primary.name().to_string(),
+        candidate: candidate.name().to_string(),
+        difference_count: 0,
+        differences: Vec::new(),
+    };
+
+    let sheets = |output: &Value| {
+        let listed = distill::sheet_manifest(output, &[])
+            .into_iter()
+            .map(|sheet| sheet.path);
+        let skipped = distill::warnings(output).into_iter().map(|w| w.file);
+        listed.chain(skipped).collect::<Vec<_>>()
+    };
+    let paths: std::collections::BTreeSet<String> = sheets(primary_output)
+        .into_iter()
+        .chain(sheets(candidate_output))
+        .collect();
+    for path in paths {
+        let a = distill::sheet_distilled(primary_output, &path);
+        let b = distill::sheet_distilled(candidate_output, &path);
+        diff_values(&path, "", a.as_ref(), b.as_ref(), &mut comparison);
+    }
+    comparison
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// A placed symbol as the native distiller reads it
+    #[derive(Debug, Clone)]
+    struct Placed {
+        lib_id: String,
+        value: String,
+        footprint: Option<String>,
+        properties: BTreeMap<String, String>,
+        x: f64,
+        y: f64,
+        pins: Vec<String>,
+    }
+
+    fn atom(s: &str) -> Sexp {
+        Sexp::Atom(s.to_string())
+    }
+
+    fn list(items: Vec<Sexp>) -> Sexp {
+        Sexp::List(items)
+    }
+
+    fn property(name: &str, value: &str) -> Sexp {
+        list(vec![
+            atom("property"),
+            atom(name),
+            atom(value),
+            list(vec![atom("at"), atom("0"), atom("0"), atom("0")]),
+        ])
+    }
+
+    impl Placed {
+        fn to_sexp(&self, reference: &str) -> Sexp {
+            let mut items = vec![
+                atom("symbol"),
+                list(vec![atom("lib_id"), atom(&self.lib_id)]),
+                list(vec![
+                    atom("at"),
+                    atom(&self.x.to_string()),
+                    atom(&self.y.to_string()),
+                    atom("0"),
+                ]),
+                list(vec![atom("unit"), atom("1")]),
+                property("Reference", reference),
+                property("Value", &self.value),
+                property("Footprint", self.footprint.as_deref().unwrap_or("")),
+            ];
+            items.extend(self.properties.iter().map(|(k, v)| property(k, v)));
+            items.extend(self.pins.iter().map(|number| {
+                list(vec![
+                    atom("pin"),
+                    atom(number),
+                    list(vec![atom("uuid"), atom("00000000-0000-0000-0000-000000000000")]),
+                ])
+            }));
+            list(items)
+        }
+
+        fn expected(&self, reference: &str) -> Value {
+            json!({
+                "lib_id": self.lib_id,
+                "value": self.value,
+                "position": { "x": self.x, "y": self.y },
+                "footprint": self.footprint,
+                "properties": self.properties,
+                "pins": self
+                    .pins
+                    .iter()
+                    .map(|number| json!({ "number": number, "name": null, "net": null }))
+                    .collect::<Vec<_>>(),
+                "reference": reference,
+            })
+        }
+    }
+
+    fn coordinate() -> impl Strategy<Value = f64> {
+        (-100_000i32..100_000).prop_map(|hundredths| f64::from(hundredths) / 100.0)
+    }
+
+    fn placed() -> impl Strategy<Value = Placed> {
+        (
+            "[A-Za-z_]{1,10}:[A-Za-z0-9_+-]{1,16}",
+            any::<String>(),
+            prop::option::of("[A-Za-z_]{1,10}:[A-Za-z0-9_. -]{1,24}"),
+            prop::collection::btree_map("[A-Z][A-Za-z ]{1,12}", ".+", 0..4),
+            coordinate(),
+            coordinate(),
+            prop::collection::vec("[A-Z]?[0-9]{1,3}", 0..6),
+        )
+            .prop_map(|(lib_id, value, footprint, mut properties, x, y, pins)| {
+                for reserved in ["Reference", "Value", "Footprint"] {
+                    properties.remove(reserved);
+                }
+                Placed {
+                    lib_id,
+                    value,
+                    footprint,
+                    properties,
+                    x,
+                    y,
+                    pins,
+                }
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn native_distill_reads_back_placed_symbols(
+            parts in prop::collection::btree_map("[A-Z]{1,3}[1-9][0-9]{0,2}", placed(), 0..6)
+        ) {
+            let mut items = vec![
+                atom("kicad_sch"),
+                list(vec![atom("version"), atom("20231120")]),
+                list(vec![atom("lib_symbols")]),
+            ];
+            items.extend(parts.iter().map(|(reference, part)| part.to_sexp(reference)));
+            // A power symbol is skipped like in the Python distiller
+            items.push(
+                Placed {
+                    lib_id: "power:GND".to_string(),
+                    value: "GND".to_string(),
+                    footprint: None,
+                    properties: BTreeMap::new(),
+                    x: 0.0,
+                    y: 0.0,
+                    pins: vec!["1".to_string()],
+                }
+                .to_sexp("#PWR01"),
+            );
+
+            let distilled = native_distill(&list(items).to_string()).expect("well-formed");
+            let expected: Map<String, Value> = parts
+                .iter()
+                .map(|(reference, part)| (reference.clone(), part.expected(reference)))
+                .collect();
+            prop_assert_eq!(&distilled["components"], &Value::Object(expected));
+        }
+    }
+
+    #[test]
+    fn native_distill_reads_every_kicad_version_alike() {
+        let corpus = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../schematic-distiller/tests/fixtures/distill_corpus");
+        let distilled: Vec<(String, Value)> = ["kicad6", "kicad7", "kicad8", "kicad9"]
+            .iter()
+            .map(|version| {
+                let name = format!("rc_divider_{}.kicad_sch", version);
+                let content = std::fs::read_to_string(corpus.join(&name)).unwrap();
+                (name, native_distill(&content).expect("well-formed"))
+            })
+            .collect();
+
+        let (_, baseline) = &distilled[0];
+        let references: Vec<&String> = baseline["components"].as_object().unwrap().keys().collect();
+        assert_eq!(references, ["C1", "R1", "R2"]);
+        for (name, data) in &distilled {
+            assert_eq!(data, baseline, "{} distills differently", name);
+        }
+    }
+
+    #[test]
+    fn python_components_name_their_file_as_sheet_path() {
+        let mut distilled = json!({
+            "components": {
+                "R1": { "sheet_path": "/", "sheet_file": "board.kicad_sch" },
+                "R2": { "sheet_path": "/sensor/", "sheet_file": "sensor.kicad_sch" },
+            },
+        });
+        file_sheet_paths(&mut distilled);
+        assert_eq!(
+            distilled["components"]["R2"],
+            json!({ "sheet_path": "sensor.kicad_sch", "hierarchy_path": "/sensor/" })
+        );
+        assert_eq!(distilled["components"]["R1"]["sheet_path"], "board.kicad_sch");
+    }
+
+    #[test]
+    fn native_distill_merges_units_of_one_reference() {
+        let unit = |pins: &[&str]| Placed {
+            lib_id: "Amplifier_Operational:TL072".to_string(),
+            value: "TL072".to_string(),
+            footprint: None,
+            properties: BTreeMap::new(),
+            x: 10.0,
+            y: 20.0,
+            pins: pins.iter().map(ToString::to_string).collect(),
+        };
+        let document = list(vec![
+            atom("kicad_sch"),
+            unit(&["1", "2", "3"]).to_sexp("U1"),
+            unit(&["5", "6", "7"]).to_sexp("U1"),
+        ]);
+
+        let distilled = native_distill(&document.to_string()).expect("well-formed");
+        let numbers: Vec<&str> = distilled["components"]["U1"]["pins"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|pin| pin["number"].as_str())
+            .collect();
+        assert_eq!(numbers, ["1", "2", "3", "5", "6", "7"]);
+    }
+}
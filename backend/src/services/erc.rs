@@ -0,0 +1,226 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::{error, info, warn};
+
+use crate::services::{distill, git};
+use crate::types::{CiErcViolation, DistillWarning};
+
+/// ERC results for a commit: violations from every schematic, plus files the
+/// checker couldn't load
+#[derive(Debug, Default)]
+pub struct ErcReport {
+    pub errors: Vec<CiErcViolation>,
+    pub warnings: Vec<CiErcViolation>,
+    pub skipped: Vec<DistillWarning>,
+}
+
+/// How a commit changed the ERC results of its base
+#[derive(Debug, Default)]
+pub struct ErcDiff {
+    /// Violations the commit introduced, and the commit's unreadable sheets
+    pub introduced: ErcReport,
+    /// Errors in the base that the commit no longer has
+    pub fixed_errors: Vec<CiErcViolation>,
+    /// Warnings in the base that the commit no longer has
+    pub fixed_warnings: Vec<CiErcViolation>,
+}
+
+/// A violation as printed by erc_report.py
+#[derive(Debug, Deserialize)]
+struct ScriptViolation {
+    violation_type: String,
+    severity: String,
+    message: String,
+    #[serde(default)]
+    component_refs: Vec<String>,
+    error_code: String,
+    net_name: Option<String>,
+}
+
+/// Get the path to the erc_report.py script.
+fn get_erc_script_path() -> PathBuf {
+    distill::get_distiller_path()
+        .join("examples")
+        .join("erc")
+        .join("erc_report.py")
+}
+
+/// Run erc_report.py on a directory and return its JSON output, shaped
+/// `{"files": {path: result}, "warnings": [...]}`
+async fn run_erc_script(directory: &Path) -> Result<Value> {
+    let python_path = distill::get_python_path();
+    let script_path = get_erc_script_path();
+
+    if !python_path.exists() {
+        anyhow::bail!(
+            "Python venv not found at {:?}. Run setup_venv.sh first.",
+            python_path
+        );
+    }
+
+    if !script_path.exists() {
+        anyhow::bail!("ERC script not found at {:?}", script_path);
+    }
+
+    let output = Command::new(&python_path)
+        .arg(&script_path)
+        .arg("--dir")
+        .arg(directory)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .context("Failed to execute ERC script")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        error!("ERC script failed: {}", stderr);
+        anyhow::bail!("ERC script failed: {}", stderr);
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    serde_json::from_str(&stdout).context("Failed to parse ERC script output as JSON")
+}
+
+/// Violations of one severity list ("errors", "warnings") in a file's result
+fn violations(file: &str, result: &Value, severity: &str) -> Vec<CiErcViolation> {
+    let list: Vec<ScriptViolation> = result
+        .get(severity)
+        .cloned()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+
+    list.into_iter()
+        .map(|v| CiErcViolation {
+            file: file.to_string(),
+            severity: v.severity,
+            error_code: v.error_code,
+            violation_type: v.violation_type,
+            message: v.message,
+            component_refs: v.component_refs,
+            net_name: v.net_name,
+        })
+        .collect()
+}
+
+/// Run the schematic-distiller electrical rules check on every schematic of a commit.
+/// Each sheet is checked on its own, so nets spanning sheets can be reported as
+/// unconnected by the checker.
+pub async fn check(repo_slug: &str, commit_hash: &str) -> Result<ErcReport> {
+    let files: Vec<_> = git::get_schematic_files(repo_slug, commit_hash)
+        .await
+        .context("Failed to fetch schematic files from repo")?
+        .into_iter()
+        .filter(|f| f.path.ends_with(".kicad_sch"))
+        .collect();
+    if files.is_empty() {
+        return Ok(ErcReport::default());
+    }
+
+    let temp_dir =
+        distill::write_schematic_files_to_temp(&files, "kicad-erc", repo_slug, commit_hash)
+            .await
+            .context("Failed to write schematic files to temp directory")?;
+    let output = run_erc_script(temp_dir.path()).await;
+    if let Err(e) = temp_dir.close() {
+        warn!("Failed to remove ERC temp directory: {}", e);
+    }
+    let output = output?;
+
+    let mut report = ErcReport {
+        skipped: distill::warnings(&output),
+        ..Default::default()
+    };
+    if let Some(results) = output.get("files").and_then(Value::as_object) {
+        for (file, result) in results {
+            report.errors.extend(violations(file, result, "errors"));
+            report.warnings.extend(violations(file, result, "warnings"));
+        }
+    }
+
+    info!(
+        "ERC for {}/{}: {} errors, {} warnings, {} file(s) skipped",
+        repo_slug,
+        commit_hash,
+        report.errors.len(),
+        report.warnings.len(),
+        report.skipped.len()
+    );
+    Ok(report)
+}
+
+/// What identifies a violation across revisions. Messages are left out since
+/// they can carry positions that move with unrelated edits.
+type ViolationKey = (String, String, String, Vec<String>, Option<String>);
+
+fn violation_key(v: &CiErcViolation) -> ViolationKey {
+    let mut refs = v.component_refs.clone();
+    refs.sort();
+    (
+        v.file.clone(),
+        v.error_code.clone(),
+        v.violation_type.clone(),
+        refs,
+        v.net_name.clone(),
+    )
+}
+
+/// Split `after` into violations `before` didn't have, and return the ones
+/// from `before` that `after` no longer has. Repeated violations are matched
+/// one for one.
+fn diff_violations(
+    before: Vec<CiErcViolation>,
+    after: Vec<CiErcViolation>,
+) -> (Vec<CiErcViolation>, Vec<CiErcViolation>) {
+    let mut remaining: HashMap<ViolationKey, Vec<CiErcViolation>> = HashMap::new();
+    for v in before {
+        remaining.entry(violation_key(&v)).or_default().push(v);
+    }
+
+    let introduced = after
+        .into_iter()
+        .filter(|v| {
+            remaining
+                .get_mut(&violation_key(v))
+                .and_then(Vec::pop)
+                .is_none()
+        })
+        .collect();
+
+    let mut fixed: Vec<CiErcViolation> = remaining.into_values().flatten().collect();
+    fixed.sort_by_key(violation_key);
+    (introduced, fixed)
+}
+
+/// Compare the ERC results of two revisions
+pub fn diff_reports(base: ErcReport, commit: ErcReport) -> ErcDiff {
+    let (errors, fixed_errors) = diff_violations(base.errors, commit.errors);
+    let (warnings, fixed_warnings) = diff_violations(base.warnings, commit.warnings);
+    ErcDiff {
+        introduced: ErcReport {
+            errors,
+            warnings,
+            skipped: commit.skipped,
+        },
+        fixed_errors,
+        fixed_warnings,
+    }
+}
+
+/// Run the ERC on a commit and on `base`, and report only the violations the
+/// commit introduced and those it fixed. Without a base every violation is new.
+pub async fn check_diff(repo_slug: &str, base: Option<&str>, commit_hash: &str) -> Result<ErcDiff> {
+    let commit = check(repo_slug, commit_hash).await?;
+    let base = match base {
+        Some(base) => check(repo_slug, base)
+            .await
+            .with_context(|| format!("Failed to check base {}", base))?,
+        None => ErcReport::default(),
+    };
+    Ok(diff_reports(base, commit))
+}
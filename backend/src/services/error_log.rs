@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+// How many errors are kept in memory
+const CAPACITY: usize = 100;
+
+/// An ERROR-level log event
+#[derive(Debug, Clone)]
+pub struct LoggedError {
+    pub at: DateTime<Utc>,
+    /// Module that logged the error
+    pub target: String,
+    pub message: String,
+    /// Id of the request being handled, when logged inside a request span
+    pub request_id: Option<String>,
+}
+
+static RECENT: Lazy<Mutex<VecDeque<LoggedError>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(CAPACITY)));
+
+/// Tracing layer that keeps the most recent ERROR events for the admin overview
+pub struct ErrorLog;
+
+/// `request_id` field of a request span, kept in the span's extensions
+struct SpanRequestId(String);
+
+impl<S> Layer<S> for ErrorLog
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = RequestIdVisitor::default();
+        attrs.record(&mut visitor);
+        if let (Some(request_id), Some(span)) = (visitor.0, ctx.span(id)) {
+            span.extensions_mut().insert(SpanRequestId(request_id));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if *event.metadata().level() != Level::ERROR {
+            return;
+        }
+
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let request_id = ctx.event_scope(event).and_then(|scope| {
+            scope.into_iter().find_map(|span| {
+                span.extensions()
+                    .get::<SpanRequestId>()
+                    .map(|id| id.0.clone())
+            })
+        });
+
+        let mut recent = RECENT.lock().unwrap();
+        if recent.len() == CAPACITY {
+            recent.pop_front();
+        }
+        recent.push_back(LoggedError {
+            at: Utc::now(),
+            target: event.metadata().target().to_string(),
+            message: visitor.0,
+            request_id,
+        });
+    }
+}
+
+/// Formats the event message followed by any other fields as `name=value`
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        } else {
+            let _ = write!(self.0, "{}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Picks the `request_id` field out of span attributes
+#[derive(Default)]
+struct RequestIdVisitor(Option<String>);
+
+impl Visit for RequestIdVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "request_id" {
+            self.0 = Some(value.to_string());
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "request_id" {
+            self.0 = Some(format!("{:?}", value));
+        }
+    }
+}
+
+/// Most recent errors logged by this process, newest first
+pub fn recent(limit: usize) -> Vec<LoggedError> {
+    RECENT
+        .lock()
+        .unwrap()
+        .iter()
+        .rev()
+        .take(limit)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,380 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tracing::{info, warn};
+
+use crate::services::bom::{self, BomComponent};
+use crate::services::distill;
+use crate::services::git::{self, FootprintFiles};
+use crate::services::github;
+use crate::services::lcsc::LcscClient;
+use crate::services::lib_table::{self, LibraryLocation};
+use crate::types::{
+    FootprintCheckResponse, FootprintIssue, FootprintLibraryIssue, PackageMismatch,
+};
+use kicad_db::{retrieve_parts, PgPool};
+
+/// Live LCSC package lookups per check, so large boards don't stall on rate limits
+const MAX_PACKAGE_LOOKUPS: usize = 50;
+
+/// Footprint libraries shipped with KiCad, available without being in the repository
+const KICAD_LIBRARIES: &[&str] = &[
+    "Audio_Module",
+    "Battery",
+    "Button_Switch_Keyboard",
+    "Button_Switch_SMD",
+    "Button_Switch_THT",
+    "Buzzer_Beeper",
+    "Calibration_Scale",
+    "Capacitor_SMD",
+    "Capacitor_THT",
+    "Capacitor_Tantalum_SMD",
+    "Converter_ACDC",
+    "Converter_DCDC",
+    "Crystal",
+    "Diode_SMD",
+    "Diode_THT",
+    "Display",
+    "Display_7Segment",
+    "Ferrite_THT",
+    "Fiducial",
+    "Filter",
+    "Fuse",
+    "Heatsink",
+    "Inductor_SMD",
+    "Inductor_THT",
+    "Jumper",
+    "LED_SMD",
+    "LED_THT",
+    "Module",
+    "MountingEquipment",
+    "MountingHole",
+    "NetTie",
+    "OptoDevice",
+    "Oscillator",
+    "PCM_Espressif",
+    "Potentiometer_SMD",
+    "Potentiometer_THT",
+    "Relay_SMD",
+    "Relay_THT",
+    "Resistor_SMD",
+    "Resistor_THT",
+    "RF",
+    "RF_Antenna",
+    "RF_Converter",
+    "RF_GPS",
+    "RF_Mini-Circuits",
+    "RF_Module",
+    "RF_Shielding",
+    "RF_WiFi",
+    "Sensor",
+    "Sensor_Audio",
+    "Sensor_Current",
+    "Sensor_Distance",
+    "Sensor_Humidity",
+    "Sensor_Motion",
+    "Sensor_Pressure",
+    "Sensor_Voltage",
+    "Socket",
+    "Symbol",
+    "TerminalBlock",
+    "TestPoint",
+    "Transformer_SMD",
+    "Transformer_THT",
+    "Transistor_Power_Module",
+    "Valve",
+    "Varistor",
+];
+
+/// Families of KiCad libraries with many members (Connector_JST, Package_SO, ...)
+const KICAD_LIBRARY_PREFIXES: &[&str] = &["Connector_", "Package_", "TerminalBlock_"];
+
+/// Extra libraries to treat as installed globally (e.g. a company library),
+/// from the comma-separated `FOOTPRINT_GLOBAL_LIBRARIES`
+static GLOBAL_LIBRARIES: Lazy<HashSet<String>> = Lazy::new(|| {
+    std::env::var("FOOTPRINT_GLOBAL_LIBRARIES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(ToString::to_string)
+        .collect()
+});
+
+fn is_global_library(library: &str) -> bool {
+    KICAD_LIBRARIES.contains(&library)
+        || KICAD_LIBRARY_PREFIXES
+            .iter()
+            .any(|p| library.starts_with(p))
+        || GLOBAL_LIBRARIES.contains(library)
+}
+
+/// Footprint libraries the repository provides or declares: nickname to the
+/// footprints found in the repository, or None when a library table points
+/// outside the repository.
+fn repo_libraries(files: &FootprintFiles) -> HashMap<String, Option<HashSet<String>>> {
+    let mut dirs: HashMap<&str, HashSet<String>> = HashMap::new();
+    for path in &files.footprints {
+        let Some((dir, file)) = path.rsplit_once('/') else {
+            continue;
+        };
+        if dir.ends_with(".pretty") {
+            let name = file.trim_end_matches(".kicad_mod").to_string();
+            dirs.entry(dir).or_default().insert(name);
+        }
+    }
+
+    let mut libraries: HashMap<String, Option<HashSet<String>>> = dirs
+        .iter()
+        .map(|(dir, footprints)| {
+            let nickname = dir.rsplit('/').next().unwrap_or(dir);
+            (
+                nickname.trim_end_matches(".pretty").to_string(),
+                Some(footprints.clone()),
+            )
+        })
+        .collect();
+
+    for (table_path, content) in &files.lib_tables {
+        for (nickname, uri) in lib_table::parse(content) {
+            match lib_table::locate(table_path, &uri) {
+                LibraryLocation::Project(dir) => {
+                    libraries.insert(nickname, dirs.get(dir.as_str()).cloned());
+                }
+                LibraryLocation::KiCad => {}
+                LibraryLocation::External => {
+                    libraries.entry(nickname).or_insert(None);
+                }
+            }
+        }
+    }
+
+    libraries
+}
+
+/// Comparable tokens for a distributor package description, e.g.
+/// "8-SOIC (0.154\", 3.90mm Width)" gives ["SOIC8"] and "0603 (1608 Metric)"
+/// gives ["0603", "1608METRIC"]
+fn package_keys(package: &str) -> Vec<String> {
+    package
+        .split([',', '(', ')', ';', '/'])
+        .map(str::trim)
+        .filter(|s| {
+            let lower = s.to_ascii_lowercase();
+            !s.contains('"') && !lower.contains("mm") && !lower.contains("width")
+        })
+        .filter_map(|segment| {
+            // DigiKey writes pin counts first ("8-SOIC"); footprints put them last
+            let segment = match segment.split_once('-') {
+                Some((pins, name))
+                    if !pins.is_empty()
+                        && pins.chars().all(|c| c.is_ascii_digit())
+                        && name.starts_with(|c: char| c.is_ascii_alphabetic()) =>
+                {
+                    format!("{}{}", name, pins)
+                }
+                _ => segment.to_string(),
+            };
+            let key: String = segment
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_ascii_uppercase();
+            (key.len() >= 3).then_some(key)
+        })
+        .collect()
+}
+
+/// Whether a footprint plausibly implements a distributor package. Unknown
+/// package descriptions count as a match so only clear conflicts are reported.
+fn footprint_matches_package(footprint: &str, package: &str) -> bool {
+    let keys = package_keys(package);
+    if keys.is_empty() {
+        return true;
+    }
+    let name: String = footprint
+        .rsplit(':')
+        .next()
+        .unwrap_or(footprint)
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .collect::<String>()
+        .to_ascii_uppercase();
+    keys.iter().any(|key| name.contains(key.as_str()))
+}
+
+fn issue(component: &BomComponent, raw: &Value) -> FootprintIssue {
+    let text = |key: &str| {
+        raw.get(key)
+            .and_then(Value::as_str)
+            .filter(|v| !v.is_empty())
+            .map(ToString::to_string)
+    };
+    FootprintIssue {
+        reference: component.reference.clone(),
+        value: component.value.clone(),
+        lib_id: text("lib_id"),
+        footprint: component.footprint.clone(),
+        sheet_path: text("sheet_path"),
+    }
+}
+
+/// Distilled components with their raw entries, skipping power symbols and other virtual parts
+fn components(distilled: &Value) -> Vec<(BomComponent, Value)> {
+    let raw: Vec<(String, Value)> = match distilled.get("components") {
+        Some(Value::Object(obj)) => obj.iter().map(|(r, c)| (r.clone(), c.clone())).collect(),
+        Some(Value::Array(arr)) => arr
+            .iter()
+            .filter_map(|c| Some((c.get("reference")?.as_str()?.to_string(), c.clone())))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut components: Vec<(BomComponent, Value)> = raw
+        .into_iter()
+        .filter(|(reference, _)| !reference.starts_with('#'))
+        .map(|(reference, comp)| (bom::to_bom_component(&reference, &comp), comp))
+        .collect();
+    components.sort_by(|a, b| a.0.reference.cmp(&b.0.reference));
+    components
+}
+
+/// Pre-layout footprint sanity check for a commit: unassigned footprints,
+/// footprint libraries missing from the repository, and footprints that
+/// disagree with the package distributors list for the part.
+///
+/// Packages come from stored DigiKey enrichment; with `lookup_packages` and
+/// LCSC lookups enabled, parts without one are looked up on LCSC.
+pub async fn check(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    lookup_packages: bool,
+) -> Result<FootprintCheckResponse> {
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+    let components = components(&distilled);
+    let libraries = repo_libraries(&git::get_footprint_files(repo, commit).await?);
+
+    let repo_url = github::repo_url(repo);
+    let stored_parts = retrieve_parts(pool, &repo_url, commit).await?;
+
+    let mut missing_footprints = Vec::new();
+    let mut missing_library_footprints = Vec::new();
+    let mut unknown_libraries: BTreeMap<String, FootprintLibraryIssue> = BTreeMap::new();
+    let mut package_mismatches = Vec::new();
+    let mut packages_checked = 0;
+
+    let lookup_packages = lookup_packages && LcscClient::is_configured();
+    let lcsc = LcscClient::new();
+    let mut lcsc_packages: HashMap<String, Option<String>> = HashMap::new();
+
+    for (component, raw) in &components {
+        let Some(footprint) = component.footprint.as_deref() else {
+            missing_footprints.push(issue(component, raw));
+            continue;
+        };
+
+        if let Some((library, name)) = footprint.split_once(':') {
+            match libraries.get(library) {
+                Some(Some(footprints)) if !footprints.contains(name) => {
+                    missing_library_footprints.push(issue(component, raw));
+                }
+                Some(Some(_)) => {}
+                Some(None) => unknown_libraries
+                    .entry(library.to_string())
+                    .or_insert_with(|| FootprintLibraryIssue {
+                        library: library.to_string(),
+                        declared_in_lib_table: true,
+                        references: Vec::new(),
+                    })
+                    .references
+                    .push(component.reference.clone()),
+                None if is_global_library(library) => {}
+                None => unknown_libraries
+                    .entry(library.to_string())
+                    .or_insert_with(|| FootprintLibraryIssue {
+                        library: library.to_string(),
+                        declared_in_lib_table: false,
+                        references: Vec::new(),
+                    })
+                    .references
+                    .push(component.reference.clone()),
+            }
+        }
+
+        let stored = stored_parts
+            .get(&bom::part_uuid_for_reference(&component.reference))
+            .and_then(|p| p.properties.pointer("/digikey/package"))
+            .and_then(Value::as_str)
+            .map(|p| (p.to_string(), "digikey"));
+        let part_number = component
+            .lcsc_part_number
+            .clone()
+            .or_else(|| component.mpn.clone());
+
+        let package = match (stored, &part_number) {
+            (Some(stored), _) => Some(stored),
+            (None, Some(part_number)) if lookup_packages => {
+                if !lcsc_packages.contains_key(part_number)
+                    && lcsc_packages.len() < MAX_PACKAGE_LOOKUPS
+                {
+                    let package = match lcsc.lookup(part_number).await {
+                        Ok(part) => part.and_then(|p| p.package),
+                        Err(e) => {
+                            warn!("LCSC package lookup failed for {}: {}", part_number, e);
+                            None
+                        }
+                    };
+                    lcsc_packages.insert(part_number.clone(), package);
+                }
+                lcsc_packages
+                    .get(part_number)
+                    .cloned()
+                    .flatten()
+                    .map(|p| (p, "lcsc"))
+            }
+            _ => None,
+        };
+
+        if let Some((package, source)) = package {
+            packages_checked += 1;
+            if !footprint_matches_package(footprint, &package) {
+                package_mismatches.push(PackageMismatch {
+                    reference: component.reference.clone(),
+                    footprint: footprint.to_string(),
+                    mpn: component.mpn.clone().or_else(|| part_number.clone()),
+                    package,
+                    source: source.to_string(),
+                });
+            }
+        }
+    }
+
+    let unknown_libraries: Vec<FootprintLibraryIssue> = unknown_libraries.into_values().collect();
+    let passed = missing_footprints.is_empty()
+        && missing_library_footprints.is_empty()
+        && unknown_libraries.is_empty()
+        && package_mismatches.is_empty();
+
+    info!(
+        "Footprint check for {}/{}: {} components, {} unassigned, {} unknown libraries, {} package mismatches",
+        repo,
+        commit,
+        components.len(),
+        missing_footprints.len(),
+        unknown_libraries.len(),
+        package_mismatches.len()
+    );
+
+    Ok(FootprintCheckResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        total_components: components.len(),
+        packages_checked,
+        passed,
+        missing_footprints,
+        unknown_libraries,
+        missing_library_footprints,
+        package_mismatches,
+    })
+}
@@ -1,26 +1,268 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, TimeZone, Utc};
-use git2::{build::RepoBuilder, ObjectType, Repository};
-use std::path::PathBuf;
+use git2::{
+    build::RepoBuilder, Cred, ErrorClass, ErrorCode, FetchOptions, ObjectType, ProxyOptions,
+    RemoteCallbacks, Repository,
+};
+use once_cell::sync::Lazy;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{info, warn};
 
+use crate::repo_policy::{self, RepoRejected};
+use crate::services::{disk, github, kicad_format, lfs, mirror, secret_settings};
 use crate::types::{CommitInfo, SchematicFile};
 
-/// Get the cache path for a repository
-fn get_cache_path(repo_slug: &str) -> PathBuf {
+/// "owner/repo" from a stored repository URL (`https://github.com/owner/repo.git`,
+/// or under `GITHUB_BASE_URL` on an Enterprise Server)
+pub fn repo_slug(repo_url: &str) -> Option<&str> {
+    repo_url
+        .strip_prefix(github::host().base_url.as_str())
+        .and_then(|rest| rest.strip_prefix('/'))
+        .map(|rest| rest.strip_suffix(".git").unwrap_or(rest))
+}
+
+/// Network settings for clones and fetches, read from the environment.
+///
+/// - `GIT_PROXY_URL`: proxy for git traffic (default: git config `http.proxy` or the
+///   `https_proxy` / `http_proxy` environment variables)
+/// - `GIT_USERNAME` / `GIT_PASSWORD`: credentials for private repositories; without them
+///   `GITHUB_TOKEN` (stored via the admin API, else from the environment) is used as an
+///   access token, read at each transfer so a new token applies without a restart
+/// - `GIT_FETCH_TIMEOUT_SECS`: abort a transfer that takes longer (default 300; 0 = no limit)
+/// - `GIT_FETCH_RETRIES`: retries after a transient network error (default 3), with
+///   the delay doubling from 1s
+/// - `GIT_MAX_CLONE_BYTES`: abort a transfer that receives more (default 2 GiB;
+///   0 = no limit)
+#[derive(Debug, Clone)]
+pub struct GitFetchSettings {
+    pub proxy_url: Option<String>,
+    pub timeout: Option<Duration>,
+    pub retries: u32,
+    pub max_bytes: Option<u64>,
+}
+
+impl GitFetchSettings {
+    pub fn from_env() -> Self {
+        let read = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let timeout = match read("GIT_FETCH_TIMEOUT_SECS").and_then(|v| v.parse::<u64>().ok()) {
+            Some(0) => None,
+            secs => Some(Duration::from_secs(secs.unwrap_or(300))),
+        };
+        Self {
+            proxy_url: read("GIT_PROXY_URL"),
+            timeout,
+            retries: read("GIT_FETCH_RETRIES")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            max_bytes: match read("GIT_MAX_CLONE_BYTES").and_then(|v| v.parse::<u64>().ok()) {
+                Some(0) => None,
+                bytes => Some(bytes.unwrap_or(2 * 1024 * 1024 * 1024)),
+            },
+        }
+    }
+
+    /// Fetch options for one transfer attempt. `too_large` is set when the
+    /// transfer is aborted for going over `max_bytes`.
+    fn fetch_options(&self, too_large: &Arc<AtomicBool>) -> FetchOptions<'static> {
+        let mut callbacks = RemoteCallbacks::new();
+
+        // libgit2 asks again after rejected credentials; offer them once so a bad
+        // token fails instead of looping
+        let credentials = credentials();
+        let mut offered = false;
+        callbacks.credentials(move |_url, _username, allowed| match &credentials {
+            Some((username, password))
+                if !offered && allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT) =>
+            {
+                offered = true;
+                Cred::userpass_plaintext(username, password)
+            }
+            _ => Err(git2::Error::from_str(
+                "No usable git credentials configured",
+            )),
+        });
+
+        // Returning false from the progress callback aborts the transfer
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        let max_bytes = self.max_bytes;
+        let too_large = Arc::clone(too_large);
+        callbacks.transfer_progress(move |progress| {
+            if max_bytes.is_some_and(|max| progress.received_bytes() as u64 > max) {
+                too_large.store(true, Ordering::Relaxed);
+                return false;
+            }
+            deadline.is_none_or(|deadline| Instant::now() < deadline)
+        });
+
+        let mut proxy = ProxyOptions::new();
+        match &self.proxy_url {
+            Some(url) => proxy.url(url),
+            None => proxy.auto(),
+        };
+
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks).proxy_options(proxy);
+        options
+    }
+}
+
+/// Credentials for clones, fetches and LFS downloads: GIT_USERNAME and
+/// GIT_PASSWORD, else GITHUB_TOKEN as an access token
+pub fn credentials() -> Option<(String, String)> {
+    let read = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    match (read("GIT_USERNAME"), read("GIT_PASSWORD")) {
+        (Some(username), Some(password)) => Some((username, password)),
+        _ => {
+            secret_settings::get("GITHUB_TOKEN").map(|token| ("x-access-token".to_string(), token))
+        }
+    }
+}
+
+static FETCH_SETTINGS: Lazy<GitFetchSettings> = Lazy::new(GitFetchSettings::from_env);
+
+/// Network failures worth retrying. Authentication, certificate and
+/// not-found errors fail the same way every time.
+fn is_transient(error: &git2::Error) -> bool {
+    if matches!(error.code(), ErrorCode::Auth | ErrorCode::Certificate) {
+        return false;
+    }
+    match error.class() {
+        ErrorClass::Net | ErrorClass::Os => true,
+        ErrorClass::Http => !["401", "403", "404"]
+            .iter()
+            .any(|status| error.message().contains(status)),
+        // A transfer aborted by the timeout callback
+        ErrorClass::Callback => error.code() == ErrorCode::User,
+        _ => false,
+    }
+}
+
+/// Run a clone or fetch, retrying transient failures with exponential backoff.
+/// Runs on a blocking thread, so the backoff sleeps it. A transfer over
+/// GIT_MAX_CLONE_BYTES fails with [`RepoRejected::TooLarge`] and isn't retried.
+fn with_retries<T>(
+    what: &str,
+    mut attempt: impl FnMut(FetchOptions<'static>) -> Result<T, git2::Error>,
+) -> Result<T> {
+    let settings = &*FETCH_SETTINGS;
+    let mut delay = Duration::from_secs(1);
+    let mut retry = 0;
+    loop {
+        let too_large = Arc::new(AtomicBool::new(false));
+        match attempt(settings.fetch_options(&too_large)) {
+            Err(_) if too_large.load(Ordering::Relaxed) => {
+                return Err(RepoRejected::TooLarge(format!(
+                    "{} stopped: the repository is over the {} byte limit (GIT_MAX_CLONE_BYTES)",
+                    what,
+                    settings.max_bytes.unwrap_or_default()
+                ))
+                .into());
+            }
+            Err(e) if retry < settings.retries && is_transient(&e) => {
+                retry += 1;
+                warn!(
+                    "{} failed ({}), retry {}/{} in {}s",
+                    what,
+                    e.message(),
+                    retry,
+                    settings.retries,
+                    delay.as_secs()
+                );
+                std::thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+/// Get the cache path for a repository: a bare clone, since files are only
+/// ever read from commit trees
+pub(crate) fn get_cache_path(repo_slug: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("kicad-cache-{}.git", repo_slug.replace('/', "-")))
+}
+
+/// Working-tree clone used as the cache before caches were bare
+fn get_legacy_cache_path(repo_slug: &str) -> PathBuf {
     std::env::temp_dir().join(format!("kicad-cache-{}", repo_slug.replace('/', "-")))
 }
 
+/// Turn a legacy working-tree cache into the bare cache without refetching:
+/// its `.git` directory becomes the bare repository and the checkout is dropped.
+fn migrate_legacy_cache(repo_slug: &str, cache_path: &Path) -> Result<()> {
+    let legacy_path = get_legacy_cache_path(repo_slug);
+    if !legacy_path.exists() {
+        return Ok(());
+    }
+
+    let git_dir = legacy_path.join(".git");
+    if !cache_path.exists() && git_dir.is_dir() {
+        std::fs::rename(&git_dir, cache_path).context("Failed to move legacy cache into place")?;
+        let migrated = Repository::open(cache_path)
+            .and_then(|repo| repo.config()?.set_bool("core.bare", true));
+        if let Err(e) = migrated {
+            // Start over with a fresh clone rather than keep a half-converted cache
+            let _ = std::fs::remove_dir_all(cache_path);
+            warn!("Failed to convert legacy cache for {}: {}", repo_slug, e);
+        } else {
+            info!(
+                "Converted cache for repo {} to a bare repository at {:?}",
+                repo_slug, cache_path
+            );
+        }
+    }
+    std::fs::remove_dir_all(&legacy_path).context("Failed to remove legacy cache")?;
+    Ok(())
+}
+
+/// Symbolic ref naming the remote's default branch in the cache
+const ORIGIN_HEAD: &str = "refs/remotes/origin/HEAD";
+
+/// Point `origin/HEAD` at the default branch the remote advertised on the
+/// last fetch. A fetch never moves it on its own, so without this a repository
+/// whose default branch changed (master to main) keeps resolving to the old
+/// branch. Returns the branch name when `origin/HEAD` moved.
+fn sync_origin_head(repo: &Repository, remote: &git2::Remote) -> Result<Option<String>> {
+    let advertised = remote.default_branch()?;
+    let advertised = advertised
+        .as_str()
+        .context("Default branch name isn't UTF-8")?;
+    let Some(branch) = advertised.strip_prefix("refs/heads/") else {
+        return Ok(None);
+    };
+
+    let target = format!("refs/remotes/origin/{}", branch);
+    let current = repo
+        .find_reference(ORIGIN_HEAD)
+        .ok()
+        .and_then(|r| r.symbolic_target().map(str::to_string));
+    if current.as_deref() == Some(target.as_str()) {
+        return Ok(None);
+    }
+    repo.reference_symbolic(ORIGIN_HEAD, &target, true, "remote default branch")?;
+    Ok(Some(branch.to_string()))
+}
+
 /// Invalidate (delete) the cache for a repository
 /// Call this when you know there are new commits (e.g., from a webhook)
 pub async fn invalidate_cache(repo_slug: &str) -> Result<()> {
-    let cache_path = get_cache_path(repo_slug);
-    if cache_path.exists() {
-        tokio::fs::remove_dir_all(&cache_path).await?;
-        info!(
-            "Invalidated cache for repo {} at {:?}",
-            repo_slug, cache_path
-        );
+    // The slug names directories under the temp dir
+    repo_policy::validate_slug(repo_slug)?;
+    if mirror::enabled() {
+        info!("Kept the uploaded copy of {} (mirror mode)", repo_slug);
+        return Ok(());
+    }
+    for cache_path in [get_cache_path(repo_slug), get_legacy_cache_path(repo_slug)] {
+        if cache_path.exists() {
+            tokio::fs::remove_dir_all(&cache_path).await?;
+            info!(
+                "Invalidated cache for repo {} at {:?}",
+                repo_slug, cache_path
+            );
+        }
     }
     Ok(())
 }
@@ -34,24 +276,44 @@ pub async fn get_repo(repo_slug: &str) -> Result<Repository> {
 /// Clone or fetch a repository with options
 /// If force_fresh is true, deletes any existing cache first
 pub async fn get_repo_with_options(repo_slug: &str, force_fresh: bool) -> Result<Repository> {
+    // Every clone and fetch passes here, whichever route or job asked for it
+    repo_policy::check(repo_slug)?;
+    disk::check_admission().await?;
     let repo_slug = repo_slug.to_string();
     let cache_path = get_cache_path(&repo_slug);
 
+    // Uploads are the only copy of a mirrored repository, so they're never
+    // dropped or fetched into
+    if mirror::enabled() {
+        return tokio::task::spawn_blocking(move || mirror::open(&repo_slug, &cache_path)).await?;
+    }
+
     // If force_fresh, delete the cache first
-    if force_fresh && cache_path.exists() {
-        tokio::fs::remove_dir_all(&cache_path).await?;
-        info!(
-            "Force-deleted cache for repo {} at {:?}",
-            repo_slug, cache_path
-        );
+    if force_fresh {
+        invalidate_cache(&repo_slug).await?;
     }
 
     tokio::task::spawn_blocking(move || -> Result<Repository> {
+        if let Err(e) = migrate_legacy_cache(&repo_slug, &cache_path) {
+            warn!("Failed to migrate legacy cache for {}: {:#}", repo_slug, e);
+        }
+
         if !cache_path.exists() {
-            let url = format!("https://github.com/{}.git", repo_slug);
-            let repo = RepoBuilder::new()
-                .clone(&url, &cache_path)
-                .context("Failed to clone repository")?;
+            let url = github::repo_url(&repo_slug);
+            let repo = with_retries(&format!("Clone of {}", repo_slug), |options| {
+                // A failed attempt can leave a partial clone behind
+                if cache_path.exists() {
+                    let _ = std::fs::remove_dir_all(&cache_path);
+                }
+                RepoBuilder::new()
+                    .bare(true)
+                    .fetch_options(options)
+                    .clone(&url, &cache_path)
+            })
+            .inspect_err(|_| {
+                let _ = std::fs::remove_dir_all(&cache_path);
+            })
+            .context("Failed to clone repository")?;
             info!("Cloned repo {} to {:?}", repo_slug, cache_path);
             Ok(repo)
         } else {
@@ -59,17 +321,33 @@ pub async fn get_repo_with_options(repo_slug: &str, force_fresh: bool) -> Result
             // Fetch updates
             {
                 let mut remote = repo.find_remote("origin").or_else(|_| {
-                    let url = format!("https://github.com/{}.git", repo_slug);
+                    let url = github::repo_url(&repo_slug);
                     repo.remote("origin", &url)
                 })?;
-                remote.fetch(&["refs/heads/*:refs/remotes/origin/*"], None, None)?;
+                with_retries(&format!("Fetch of {}", repo_slug), |mut options| {
+                    remote.fetch(
+                        &[
+                            "refs/heads/*:refs/remotes/origin/*",
+                            "+refs/tags/*:refs/tags/*",
+                        ],
+                        Some(&mut options),
+                        None,
+                    )
+                })
+                .context("Failed to fetch repository")?;
+
+                match sync_origin_head(&repo, &remote) {
+                    Ok(Some(branch)) => info!("Default branch of {} is {}", repo_slug, branch),
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to read the default branch of {}: {}", repo_slug, e),
+                }
             }
 
             // Update local HEAD to match remote's default branch
             // First, find the remote HEAD (origin/HEAD or origin/main or origin/master)
             let remote_commit_id = {
                 let remote_head = repo
-                    .find_reference("refs/remotes/origin/HEAD")
+                    .find_reference(ORIGIN_HEAD)
                     .or_else(|_| repo.find_reference("refs/remotes/origin/main"))
                     .or_else(|_| repo.find_reference("refs/remotes/origin/master"))
                     .context("Failed to find remote HEAD")?;
@@ -118,6 +396,7 @@ pub async fn get_all_commits(repo_slug: &str) -> Result<Vec<CommitInfo>> {
                 commit_date,
                 message: commit.summary().map(ToString::to_string),
                 has_schematic_changes: has_changes,
+                risk: None,
             });
         }
 
@@ -171,6 +450,148 @@ fn has_schematic_changes(repo: &Repository, commit: &git2::Commit) -> Result<boo
     }
 }
 
+/// Limits on the file content one call loads from the repository, read from
+/// the environment, so a commit with huge schematics fails cleanly instead of
+/// exhausting memory.
+///
+/// - `SCHEMATIC_MAX_FILE_BYTES`: largest single file (default 128 MiB)
+/// - `SCHEMATIC_MAX_REQUEST_BYTES`: all files of one call together (default 512 MiB)
+#[derive(Debug, Clone, Copy)]
+pub struct ContentLimits {
+    pub max_file_bytes: u64,
+    pub max_request_bytes: u64,
+}
+
+impl ContentLimits {
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            max_file_bytes: read("SCHEMATIC_MAX_FILE_BYTES", 128 * 1024 * 1024),
+            max_request_bytes: read("SCHEMATIC_MAX_REQUEST_BYTES", 512 * 1024 * 1024),
+        }
+    }
+}
+
+static CONTENT_LIMITS: Lazy<ContentLimits> = Lazy::new(ContentLimits::from_env);
+
+// Read size for streamed blobs
+const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Bytes left for the files of one call
+struct ContentBudget {
+    limits: ContentLimits,
+    used: u64,
+}
+
+impl ContentBudget {
+    fn new() -> Self {
+        Self {
+            limits: *CONTENT_LIMITS,
+            used: 0,
+        }
+    }
+
+    /// Reserve room for a file of `size` bytes, or explain which limit it breaks
+    fn reserve(&mut self, path: &str, size: u64) -> Result<()> {
+        if size > self.limits.max_file_bytes {
+            anyhow::bail!(
+                "{} is {} bytes, over the {} byte file limit (SCHEMATIC_MAX_FILE_BYTES)",
+                path,
+                size,
+                self.limits.max_file_bytes
+            );
+        }
+        if self.used + size > self.limits.max_request_bytes {
+            anyhow::bail!(
+                "Loading {} would take this commit's files past the {} byte limit (SCHEMATIC_MAX_REQUEST_BYTES)",
+                path,
+                self.limits.max_request_bytes
+            );
+        }
+        self.used += size;
+        Ok(())
+    }
+}
+
+/// Append `bytes` to `out` as UTF-8, replacing invalid sequences with U+FFFD.
+/// An incomplete sequence at the end stays in `bytes` for the next chunk
+/// unless this is the last one.
+fn push_utf8_lossy(out: &mut String, bytes: &mut Vec<u8>, last: bool) {
+    let mut start = 0;
+    loop {
+        match std::str::from_utf8(&bytes[start..]) {
+            Ok(valid) => {
+                out.push_str(valid);
+                start = bytes.len();
+                break;
+            }
+            Err(e) => {
+                let valid_end = start + e.valid_up_to();
+                // Validated by from_utf8 up to valid_end
+                out.push_str(std::str::from_utf8(&bytes[start..valid_end]).unwrap_or_default());
+                match e.error_len() {
+                    Some(len) => {
+                        out.push(char::REPLACEMENT_CHARACTER);
+                        start = valid_end + len;
+                    }
+                    None if last => {
+                        out.push(char::REPLACEMENT_CHARACTER);
+                        start = bytes.len();
+                        break;
+                    }
+                    None => {
+                        start = valid_end;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    bytes.drain(..start);
+}
+
+/// Text content of a blob, checked against the call's budget before anything
+/// is loaded. Loose objects are streamed in chunks and decoded as they arrive,
+/// so only the resulting string is held; packed objects (libgit2 can't stream
+/// those) are decompressed in one piece and decoded from there.
+fn read_blob_text(
+    repo: &Repository,
+    oid: git2::Oid,
+    path: &str,
+    budget: &mut ContentBudget,
+) -> Result<String> {
+    let odb = repo.odb()?;
+    let (size, _) = odb.read_header(oid)?;
+    budget.reserve(path, size as u64)?;
+
+    let mut content = String::with_capacity(size);
+    match odb.reader(oid) {
+        Ok((mut reader, _, _)) => {
+            let mut pending = Vec::with_capacity(STREAM_CHUNK_BYTES);
+            let mut chunk = vec![0; STREAM_CHUNK_BYTES];
+            loop {
+                let read = std::io::Read::read(&mut reader, &mut chunk)
+                    .with_context(|| format!("Failed to read {}", path))?;
+                pending.extend_from_slice(&chunk[..read]);
+                push_utf8_lossy(&mut content, &mut pending, read == 0);
+                if read == 0 {
+                    break;
+                }
+            }
+        }
+        Err(_) => {
+            let blob = repo.find_blob(oid)?;
+            content.push_str(&String::from_utf8_lossy(blob.content()));
+        }
+    }
+    Ok(content)
+}
+
 /// Check if a file is a KiCad file we need for distillation
 fn is_kicad_file(name: &str) -> bool {
     name.ends_with(".kicad_sch") || name.ends_with(".kicad_pro")
@@ -182,14 +603,16 @@ pub async fn get_schematic_files(repo_slug: &str, commit_hash: &str) -> Result<V
     let repo = get_repo(repo_slug).await?;
     let commit_hash = commit_hash.to_string();
 
-    tokio::task::spawn_blocking(move || -> Result<Vec<SchematicFile>> {
+    let mut files = tokio::task::spawn_blocking(move || -> Result<Vec<SchematicFile>> {
         let obj = repo.revparse_single(&commit_hash)?;
         let commit = obj.peel_to_commit()?;
         let tree = commit.tree()?;
 
         let mut files = Vec::new();
+        let mut budget = ContentBudget::new();
+        let mut failure = None;
 
-        tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+        let walked = tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
             if let Some(name) = entry.name() {
                 if is_kicad_file(name) && entry.kind() == Some(ObjectType::Blob) {
                     let path = if dir.is_empty() {
@@ -198,15 +621,96 @@ pub async fn get_schematic_files(repo_slug: &str, commit_hash: &str) -> Result<V
                         format!("{}{}", dir, name)
                     };
 
-                    if let Ok(obj) = entry.to_object(&repo) {
-                        if let Ok(blob) = obj.into_blob() {
-                            let content = String::from_utf8_lossy(blob.content()).to_string();
-                            files.push(SchematicFile { path, content });
+                    match read_blob_text(&repo, entry.id(), &path, &mut budget) {
+                        Ok(content) => {
+                            let format_version = if name.ends_with(".kicad_sch") {
+                                kicad_format::detect_version(&content)
+                            } else {
+                                None
+                            };
+                            let kicad_version = format_version
+                                .and_then(kicad_format::kicad_release)
+                                .map(ToString::to_string);
+                            files.push(SchematicFile {
+                                path,
+                                content,
+                                blob_oid: entry.id().to_string(),
+                                format_version,
+                                kicad_version,
+                            });
+                        }
+                        Err(e) => {
+                            failure = Some(e);
+                            return git2::TreeWalkResult::Abort;
                         }
                     }
                 }
             }
             git2::TreeWalkResult::Ok
+        });
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        walked?;
+
+        Ok(files)
+    })
+    .await??;
+
+    // Files stored with git LFS come out of the tree as pointer files
+    let resolved = lfs::resolve_pointers(
+        repo_slug,
+        files.iter_mut().map(|f| &mut f.content).collect(),
+    )
+    .await?;
+    if resolved > 0 {
+        for file in files.iter_mut().filter(|f| f.path.ends_with(".kicad_sch")) {
+            file.format_version = kicad_format::detect_version(&file.content);
+            file.kicad_version = file
+                .format_version
+                .and_then(kicad_format::kicad_release)
+                .map(ToString::to_string);
+        }
+    }
+
+    Ok(files)
+}
+
+/// Footprint library files at a commit
+#[derive(Debug, Default)]
+pub struct FootprintFiles {
+    /// (path, content) of every fp-lib-table
+    pub lib_tables: Vec<(String, String)>,
+    /// Paths of every .kicad_mod footprint
+    pub footprints: Vec<String>,
+}
+
+/// Get the footprint library tables and footprint files at a specific commit
+pub async fn get_footprint_files(repo_slug: &str, commit_hash: &str) -> Result<FootprintFiles> {
+    let repo = get_repo(repo_slug).await?;
+    let commit_hash = commit_hash.to_string();
+
+    tokio::task::spawn_blocking(move || -> Result<FootprintFiles> {
+        let tree = find_commit(&repo, &commit_hash)?.tree()?;
+        let mut files = FootprintFiles::default();
+
+        tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            let Some(name) = entry.name() else {
+                return git2::TreeWalkResult::Ok;
+            };
+            if entry.kind() != Some(ObjectType::Blob) {
+                return git2::TreeWalkResult::Ok;
+            }
+            let path = format!("{}{}", dir, name);
+            if name.ends_with(".kicad_mod") {
+                files.footprints.push(path);
+            } else if name == "fp-lib-table" {
+                if let Ok(blob) = entry.to_object(&repo).and_then(|o| o.peel_to_blob()) {
+                    let content = String::from_utf8_lossy(blob.content()).to_string();
+                    files.lib_tables.push((path, content));
+                }
+            }
+            git2::TreeWalkResult::Ok
         })?;
 
         Ok(files)
@@ -214,6 +718,114 @@ pub async fn get_schematic_files(repo_slug: &str, commit_hash: &str) -> Result<V
     .await?
 }
 
+/// Symbol library files at a commit
+#[derive(Debug, Default)]
+pub struct SymbolFiles {
+    /// (path, content) of every sym-lib-table
+    pub lib_tables: Vec<(String, String)>,
+    /// (path, content) of every .kicad_sym library
+    pub libraries: Vec<(String, String)>,
+}
+
+/// Get the symbol library tables and symbol libraries at a specific commit
+pub async fn get_symbol_files(repo_slug: &str, commit_hash: &str) -> Result<SymbolFiles> {
+    let repo = get_repo(repo_slug).await?;
+    let commit_hash = commit_hash.to_string();
+
+    let mut files = tokio::task::spawn_blocking(move || -> Result<SymbolFiles> {
+        let tree = find_commit(&repo, &commit_hash)?.tree()?;
+        let mut files = SymbolFiles::default();
+        let mut budget = ContentBudget::new();
+        let mut failure = None;
+
+        let walked = tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            let Some(name) = entry.name() else {
+                return git2::TreeWalkResult::Ok;
+            };
+            let target = if name.ends_with(".kicad_sym") {
+                &mut files.libraries
+            } else if name == "sym-lib-table" {
+                &mut files.lib_tables
+            } else {
+                return git2::TreeWalkResult::Ok;
+            };
+            if entry.kind() == Some(ObjectType::Blob) {
+                let path = format!("{}{}", dir, name);
+                match read_blob_text(&repo, entry.id(), &path, &mut budget) {
+                    Ok(content) => target.push((path, content)),
+                    Err(e) => {
+                        failure = Some(e);
+                        return git2::TreeWalkResult::Abort;
+                    }
+                }
+            }
+            git2::TreeWalkResult::Ok
+        });
+        if let Some(e) = failure {
+            return Err(e);
+        }
+        walked?;
+
+        Ok(files)
+    })
+    .await??;
+
+    lfs::resolve_pointers(
+        repo_slug,
+        files
+            .lib_tables
+            .iter_mut()
+            .chain(files.libraries.iter_mut())
+            .map(|(_, content)| content)
+            .collect(),
+    )
+    .await?;
+
+    Ok(files)
+}
+
+/// Changed .kicad_sch file paths in a commit, relative to its first parent
+fn changed_schematic_paths(repo: &Repository, commit: &git2::Commit) -> Result<Vec<String>> {
+    let mut changed_files = Vec::new();
+
+    if let Some(parent) = commit.parents().next() {
+        let tree1 = parent.tree()?;
+        let tree2 = commit.tree()?;
+        let diff = repo.diff_tree_to_tree(Some(&tree1), Some(&tree2), None)?;
+
+        for delta in diff.deltas() {
+            if let Some(path) = delta.new_file().path().and_then(|p| p.to_str()) {
+                if path.ends_with(".kicad_sch") {
+                    changed_files.push(path.to_string());
+                }
+            }
+            if let Some(path) = delta.old_file().path().and_then(|p| p.to_str()) {
+                if path.ends_with(".kicad_sch") && !changed_files.contains(&path.to_string()) {
+                    changed_files.push(path.to_string());
+                }
+            }
+        }
+    } else {
+        // Root commit - all files are "changed"
+        let tree = commit.tree()?;
+        tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            if let Some(name) = entry.name() {
+                if name.ends_with(".kicad_sch") && entry.kind() == Some(ObjectType::Blob) {
+                    let path = if dir.is_empty() {
+                        name.to_string()
+                    } else {
+                        format!("{}{}", dir, name)
+                    };
+                    changed_files.push(path);
+                }
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+    }
+
+    Ok(changed_files)
+}
+
 /// Get changed .kicad_sch file paths for a specific commit
 pub async fn get_changed_schematic_files(
     repo_slug: &str,
@@ -225,45 +837,126 @@ pub async fn get_changed_schematic_files(
     tokio::task::spawn_blocking(move || -> Result<Vec<String>> {
         let obj = repo.revparse_single(&commit_hash)?;
         let commit = obj.peel_to_commit()?;
+        changed_schematic_paths(&repo, &commit)
+    })
+    .await?
+}
 
-        let mut changed_files = Vec::new();
+/// A commit together with the schematic files it changed
+#[derive(Debug)]
+pub struct CommitChanges {
+    pub info: CommitInfo,
+    pub changed_files: Vec<String>,
+}
 
-        if let Some(parent) = commit.parents().next() {
-            let tree1 = parent.tree()?;
-            let tree2 = commit.tree()?;
-            let diff = repo.diff_tree_to_tree(Some(&tree1), Some(&tree2), None)?;
+/// Schematic commits in a range
+#[derive(Debug)]
+pub struct RangeChanges {
+    /// Full hash `from` resolved to
+    pub from_commit: String,
+    /// Full hash `to` resolved to
+    pub to_commit: String,
+    /// Commits reachable from `to` but not `from` that change schematics, oldest first
+    pub commits: Vec<CommitChanges>,
+}
 
-            for delta in diff.deltas() {
-                if let Some(path) = delta.new_file().path().and_then(|p| p.to_str()) {
-                    if path.ends_with(".kicad_sch") {
-                        changed_files.push(path.to_string());
-                    }
-                }
-                if let Some(path) = delta.old_file().path().and_then(|p| p.to_str()) {
-                    if path.ends_with(".kicad_sch") && !changed_files.contains(&path.to_string()) {
-                        changed_files.push(path.to_string());
-                    }
-                }
+/// A commit hash or tag that doesn't exist in the repository
+#[derive(Debug, Clone)]
+pub struct UnknownRevision(pub String);
+
+impl std::fmt::Display for UnknownRevision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unknown commit or tag: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownRevision {}
+
+/// Whether `rev` is already a full 40-character commit hash
+pub fn is_full_hash(rev: &str) -> bool {
+    rev.len() == 40 && rev.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Look up a revision in an open repository
+fn find_commit<'r>(repo: &'r Repository, rev: &str) -> Result<git2::Commit<'r>> {
+    repo.revparse_single(rev)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|_| UnknownRevision(rev.to_string()).into())
+}
+
+/// Resolve a commit hash, abbreviated hash or tag name to a full commit hash.
+///
+/// Full hashes are returned as-is without touching the repository, so callers
+/// can resolve every request without paying for a fetch.
+pub async fn resolve_commit(repo_slug: &str, rev: &str) -> Result<String> {
+    if is_full_hash(rev) {
+        return Ok(rev.to_ascii_lowercase());
+    }
+
+    let repo = get_repo(repo_slug).await?;
+    let rev = rev.to_string();
+
+    tokio::task::spawn_blocking(move || -> Result<String> {
+        Ok(find_commit(&repo, &rev)?.id().to_string())
+    })
+    .await?
+}
+
+/// First parent of a commit, or None for a root commit
+pub async fn get_parent_commit(repo_slug: &str, commit_hash: &str) -> Result<Option<String>> {
+    let repo = get_repo(repo_slug).await?;
+    let commit_hash = commit_hash.to_string();
+
+    tokio::task::spawn_blocking(move || -> Result<Option<String>> {
+        let commit = find_commit(&repo, &commit_hash)?;
+        Ok(commit.parent_ids().next().map(|id| id.to_string()))
+    })
+    .await?
+}
+
+/// Get the schematic commits between two revisions (commit hashes or tags), like `git log from..to`
+pub async fn get_schematic_changes_in_range(
+    repo_slug: &str,
+    from: &str,
+    to: &str,
+) -> Result<RangeChanges> {
+    let repo = get_repo(repo_slug).await?;
+    let from = from.to_string();
+    let to = to.to_string();
+
+    tokio::task::spawn_blocking(move || -> Result<RangeChanges> {
+        let from_oid = find_commit(&repo, &from)?.id();
+        let to_oid = find_commit(&repo, &to)?.id();
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME | git2::Sort::REVERSE)?;
+        revwalk.push(to_oid)?;
+        revwalk.hide(from_oid)?;
+
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            let changed_files = changed_schematic_paths(&repo, &commit)?;
+            if changed_files.is_empty() {
+                continue;
             }
-        } else {
-            // Root commit - all files are "changed"
-            let tree = commit.tree()?;
-            tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
-                if let Some(name) = entry.name() {
-                    if name.ends_with(".kicad_sch") && entry.kind() == Some(ObjectType::Blob) {
-                        let path = if dir.is_empty() {
-                            name.to_string()
-                        } else {
-                            format!("{}{}", dir, name)
-                        };
-                        changed_files.push(path);
-                    }
-                }
-                git2::TreeWalkResult::Ok
-            })?;
+            commits.push(CommitChanges {
+                info: CommitInfo {
+                    commit_hash: commit.id().to_string(),
+                    commit_date: Utc.timestamp_opt(commit.time().seconds(), 0).single(),
+                    message: commit.summary().map(ToString::to_string),
+                    has_schematic_changes: true,
+                    risk: None,
+                },
+                changed_files,
+            });
         }
 
-        Ok(changed_files)
+        Ok(RangeChanges {
+            from_commit: from_oid.to_string(),
+            to_commit: to_oid.to_string(),
+            commits,
+        })
     })
     .await?
 }
@@ -286,6 +979,7 @@ pub async fn get_commit_info(repo_slug: &str, commit_hash: &str) -> Result<Commi
             commit_date,
             message: commit.summary().map(ToString::to_string),
             has_schematic_changes: has_changes,
+            risk: None,
         })
     })
     .await?
@@ -302,3 +996,108 @@ pub async fn get_latest_commit(repo_slug: &str) -> Result<String> {
     })
     .await?
 }
+
+/// A repository's default branch and the commit at its tip
+#[derive(Debug, Clone)]
+pub struct DefaultBranch {
+    pub name: String,
+    pub head: String,
+}
+
+/// Fetch a repository and return its default branch as the remote advertises it
+pub async fn get_default_branch(repo_slug: &str) -> Result<DefaultBranch> {
+    let repo = get_repo(repo_slug).await?;
+
+    tokio::task::spawn_blocking(move || -> Result<DefaultBranch> {
+        let origin_head = repo
+            .find_reference(ORIGIN_HEAD)
+            .context("Remote default branch unknown")?;
+        let target = origin_head
+            .symbolic_target()
+            .context("Remote HEAD isn't a branch")?;
+        let name = target
+            .strip_prefix("refs/remotes/origin/")
+            .unwrap_or(target)
+            .to_string();
+        let head = origin_head.peel_to_commit()?.id().to_string();
+        Ok(DefaultBranch { name, head })
+    })
+    .await?
+}
+
+/// A git tag and the schematic work it contains
+#[derive(Debug)]
+pub struct TagInfo {
+    pub name: String,
+    pub commit_hash: String,
+    pub commit_date: Option<DateTime<Utc>>,
+    /// Annotated tag message, or the tagged commit's summary for lightweight tags
+    pub message: Option<String>,
+    /// The next older tag, if any
+    pub previous_tag: Option<String>,
+    /// Commits since `previous_tag` (or since the start of history) that change schematics
+    pub schematic_commits: usize,
+}
+
+/// List tags, newest first, with how many schematic commits each adds over the previous tag
+pub async fn get_tags(repo_slug: &str) -> Result<Vec<TagInfo>> {
+    let repo = get_repo(repo_slug).await?;
+
+    tokio::task::spawn_blocking(move || -> Result<Vec<TagInfo>> {
+        let mut tags = Vec::new();
+        for name in repo.tag_names(None)?.iter().flatten() {
+            let Ok(obj) = repo.revparse_single(&format!("refs/tags/{}", name)) else {
+                continue;
+            };
+            let Ok(commit) = obj.peel_to_commit() else {
+                // Tags of trees or blobs have no place in a release list
+                continue;
+            };
+            let message = obj
+                .as_tag()
+                .and_then(|t| t.message())
+                .map(|m| m.trim().to_string())
+                .filter(|m| !m.is_empty())
+                .or_else(|| commit.summary().map(ToString::to_string));
+            tags.push((
+                name.to_string(),
+                commit.id(),
+                commit.time().seconds(),
+                message,
+            ));
+        }
+        // Oldest first so each tag can be compared with the one before it
+        tags.sort_by_key(|(_, _, time, _)| *time);
+
+        let mut infos = Vec::with_capacity(tags.len());
+        let mut previous: Option<(String, git2::Oid)> = None;
+        for (name, oid, time, message) in tags {
+            let mut revwalk = repo.revwalk()?;
+            revwalk.push(oid)?;
+            if let Some((_, prev_oid)) = &previous {
+                revwalk.hide(*prev_oid)?;
+            }
+            let mut schematic_commits = 0;
+            for commit_oid in revwalk {
+                let commit = repo.find_commit(commit_oid?)?;
+                if has_schematic_changes(&repo, &commit)? {
+                    schematic_commits += 1;
+                }
+            }
+
+            infos.push(TagInfo {
+                name: name.clone(),
+                commit_hash: oid.to_string(),
+                commit_date: Utc.timestamp_opt(time, 0).single(),
+                message,
+                previous_tag: previous.as_ref().map(|(n, _)| n.clone()),
+                schematic_commits,
+            });
+            previous = Some((name, oid));
+        }
+
+        infos.reverse();
+        Ok(infos)
+    })
+    .await?
+}
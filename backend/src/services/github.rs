@@ -0,0 +1,488 @@
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use reqwest::{Client, RequestBuilder};
+use ring::hmac;
+use ring::rand::SystemRandom;
+use ring::signature::{RsaKeyPair, RSA_PKCS1_SHA256};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::services::secret_settings;
+
+const GITHUB_BASE_URL: &str = "https://github.com";
+const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// Installation tokens are renewed this long before they expire
+const TOKEN_RENEW_MARGIN_SECS: i64 = 300;
+
+// Installation tokens of the GitHub App by lowercased repository slug
+static INSTALLATION_TOKENS: Lazy<RwLock<HashMap<String, InstallationToken>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+static HOST: Lazy<GitHubHost> = Lazy::new(GitHubHost::from_env);
+
+static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(20))
+        .user_agent("grokicad")
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+/// The GitHub instance repositories are cloned from, github.com or a GitHub
+/// Enterprise Server, read from the environment.
+///
+/// - `GITHUB_BASE_URL`: web and clone origin (default https://github.com), e.g.
+///   https://ghe.example.com
+/// - `GITHUB_API_URL`: REST API root (default https://api.github.com, or
+///   `<GITHUB_BASE_URL>/api/v3` on an Enterprise Server)
+/// - `GITHUB_WEBHOOK_SECRET`: secret of the repositories' webhooks; deliveries
+///   without a matching `X-Hub-Signature-256` are refused (default: not verified).
+///   Read at each delivery, so one stored via the admin API applies without a restart
+///
+/// GITHUB_TOKEN and the GitHub App authenticate against this instance.
+#[derive(Debug, Clone)]
+pub struct GitHubHost {
+    pub base_url: String,
+    pub api_url: String,
+    /// Host name of `base_url`, stored as the `provider` of its repositories
+    pub host: String,
+}
+
+impl GitHubHost {
+    pub fn from_env() -> Self {
+        let base_url = env("GITHUB_BASE_URL").map_or_else(
+            || GITHUB_BASE_URL.to_string(),
+            |url| {
+                let url = url.trim_end_matches('/');
+                if url.contains("://") {
+                    url.to_string()
+                } else {
+                    format!("https://{}", url)
+                }
+            },
+        );
+        let host = base_url
+            .split_once("://")
+            .map_or(base_url.as_str(), |(_, rest)| rest)
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        let api_url = match env("GITHUB_API_URL") {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None if host == "github.com" => GITHUB_API_URL.to_string(),
+            None => format!("{}/api/v3", base_url),
+        };
+        Self {
+            base_url,
+            api_url,
+            host,
+        }
+    }
+
+    /// Whether this is a GitHub Enterprise Server rather than github.com
+    pub fn is_enterprise(&self) -> bool {
+        self.host != "github.com"
+    }
+}
+
+/// The configured GitHub instance
+pub fn host() -> &'static GitHubHost {
+    &HOST
+}
+
+/// Clone URL of a repository ("owner/repo"), also the `repo_url` its data is stored under
+pub fn repo_url(repo_slug: &str) -> String {
+    format!("{}/{}.git", HOST.base_url, repo_slug)
+}
+
+/// Web page of a commit
+pub fn commit_url(repo_slug: &str, commit_hash: &str) -> String {
+    format!("{}/{}/commit/{}", HOST.base_url, repo_slug, commit_hash)
+}
+
+/// Why a webhook delivery was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookRejection {
+    /// Sent by another GitHub instance than the configured one
+    WrongHost,
+    /// Missing or wrong `X-Hub-Signature-256` while a webhook secret is set
+    BadSignature,
+}
+
+impl std::fmt::Display for WebhookRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WebhookRejection::WrongHost => write!(f, "webhook sent by another GitHub instance"),
+            WebhookRejection::BadSignature => write!(f, "webhook signature doesn't match"),
+        }
+    }
+}
+
+/// Check a webhook delivery came from the configured instance. Enterprise
+/// Servers name themselves in `X-GitHub-Enterprise-Host`, which github.com
+/// never sends; with a webhook secret, the body must be signed with it.
+pub fn verify_webhook(
+    enterprise_host: Option<&str>,
+    signature: Option<&str>,
+    body: &[u8],
+) -> Result<(), WebhookRejection> {
+    let secret = secret_settings::get("GITHUB_WEBHOOK_SECRET");
+    check_webhook(&HOST, secret.as_deref(), enterprise_host, signature, body)
+}
+
+fn check_webhook(
+    host: &GitHubHost,
+    secret: Option<&str>,
+    enterprise_host: Option<&str>,
+    signature: Option<&str>,
+    body: &[u8],
+) -> Result<(), WebhookRejection> {
+    let sender = enterprise_host.map(|h| h.trim().to_lowercase());
+    let expected = host.is_enterprise().then_some(host.host.as_str());
+    if sender.as_deref() != expected {
+        return Err(WebhookRejection::WrongHost);
+    }
+
+    let Some(secret) = secret else {
+        return Ok(());
+    };
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let signature = signature
+        .and_then(|s| s.trim().strip_prefix("sha256="))
+        .and_then(|s| hex::decode(s).ok())
+        .ok_or(WebhookRejection::BadSignature)?;
+    hmac::verify(&key, body, &signature).map_err(|_| WebhookRejection::BadSignature)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct InstallationToken {
+    token: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Installation {
+    id: u64,
+}
+
+/// A commit status, shown next to the commit and on pull requests
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitStatus {
+    /// "pending", "success", "failure" or "error"
+    pub state: &'static str,
+    /// Short summary; GitHub truncates after 140 characters
+    pub description: String,
+    /// Page the status links to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_url: Option<String>,
+    /// Label telling this status apart from other integrations' statuses
+    pub context: &'static str,
+}
+
+/// The GitHub App configured by GITHUB_APP_ID and GITHUB_APP_PRIVATE_KEY_PATH
+struct AppCredentials {
+    app_id: String,
+    key_pair: RsaKeyPair,
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Whether GitHub App credentials are configured (not whether they work)
+pub fn app_configured() -> bool {
+    env("GITHUB_APP_ID").is_some() && env("GITHUB_APP_PRIVATE_KEY_PATH").is_some()
+}
+
+/// Load the App's private key: the PEM file GitHub generates (PKCS#1), or a
+/// PKCS#8 conversion of it
+fn app_credentials() -> Result<Option<AppCredentials>> {
+    let (Some(app_id), Some(key_path)) = (env("GITHUB_APP_ID"), env("GITHUB_APP_PRIVATE_KEY_PATH"))
+    else {
+        return Ok(None);
+    };
+    let pem = std::fs::read_to_string(&key_path)
+        .with_context(|| format!("Failed to read GitHub App private key {}", key_path))?;
+    let body: String = pem
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let der = BASE64
+        .decode(body)
+        .with_context(|| format!("{} is not a PEM file", key_path))?;
+    let key_pair = if pem.contains("BEGIN PRIVATE KEY") {
+        RsaKeyPair::from_pkcs8(&der)
+    } else {
+        RsaKeyPair::from_der(&der)
+    }
+    .map_err(|e| anyhow!("Invalid GitHub App private key {}: {}", key_path, e))?;
+    Ok(Some(AppCredentials { app_id, key_pair }))
+}
+
+/// A short-lived JWT authenticating as the App itself (RS256, 9 minutes;
+/// GitHub accepts at most 10). Issued a minute in the past against clock drift.
+fn app_jwt(app: &AppCredentials) -> Result<String> {
+    let now = Utc::now().timestamp();
+    // The App id is numeric; a client id ("Iv1...") is sent as a string
+    let issuer = app
+        .app_id
+        .parse::<u64>()
+        .map_or_else(|_| json!(app.app_id), |id| json!(id));
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#);
+    let claims = URL_SAFE_NO_PAD
+        .encode(json!({ "iat": now - 60, "exp": now + 540, "iss": issuer }).to_string());
+    let message = format!("{}.{}", header, claims);
+
+    let mut signature = vec![0; app.key_pair.public().modulus_len()];
+    app.key_pair
+        .sign(
+            &RSA_PKCS1_SHA256,
+            &SystemRandom::new(),
+            message.as_bytes(),
+            &mut signature,
+        )
+        .map_err(|_| anyhow!("Failed to sign the GitHub App JWT"))?;
+    Ok(format!("{}.{}", message, URL_SAFE_NO_PAD.encode(signature)))
+}
+
+async fn check_status(response: reqwest::Response, what: &str) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    anyhow::bail!("GitHub returned {} for {}: {}", status, what, body)
+}
+
+/// An installation token of the App for a repository, cached until shortly
+/// before it expires. Fails when the App isn't installed on the repository.
+async fn installation_token(app: &AppCredentials, repo_slug: &str) -> Result<String> {
+    let cache_key = repo_slug.to_lowercase();
+    if let Some(cached) = INSTALLATION_TOKENS.read().unwrap().get(&cache_key) {
+        if (cached.expires_at - Utc::now()).num_seconds() > TOKEN_RENEW_MARGIN_SECS {
+            return Ok(cached.token.clone());
+        }
+    }
+
+    let jwt = app_jwt(app)?;
+    let response = HTTP_CLIENT
+        .get(format!("{}/repos/{}/installation", HOST.api_url, repo_slug))
+        .header("Accept", "application/vnd.github+json")
+        .bearer_auth(&jwt)
+        .send()
+        .await
+        .context("GitHub installation request failed")?;
+    let installation: Installation =
+        check_status(response, &format!("the App installation on {}", repo_slug))
+            .await?
+            .json()
+            .await
+            .context("Failed to parse the GitHub App installation")?;
+
+    let response = HTTP_CLIENT
+        .post(format!(
+            "{}/app/installations/{}/access_tokens",
+            HOST.api_url, installation.id
+        ))
+        .header("Accept", "application/vnd.github+json")
+        .bearer_auth(&jwt)
+        .send()
+        .await
+        .context("GitHub installation token request failed")?;
+    let token: InstallationToken = check_status(
+        response,
+        &format!("an installation token for {}", repo_slug),
+    )
+    .await?
+    .json()
+    .await
+    .context("Failed to parse the GitHub installation token")?;
+
+    INSTALLATION_TOKENS
+        .write()
+        .unwrap()
+        .insert(cache_key, token.clone());
+    Ok(token.token)
+}
+
+/// Authenticate a request on a repository's behalf: with an installation
+/// token of the GitHub App when one is configured, else with GITHUB_TOKEN.
+/// Fails when neither is set.
+async fn authorize(request: RequestBuilder, repo_slug: &str) -> Result<RequestBuilder> {
+    if let Some(app) = app_credentials()? {
+        return Ok(request.bearer_auth(installation_token(&app, repo_slug).await?));
+    }
+    match secret_settings::get("GITHUB_TOKEN") {
+        Some(token) => Ok(request.bearer_auth(token)),
+        None => anyhow::bail!(
+            "No GitHub credentials: set GITHUB_APP_ID and GITHUB_APP_PRIVATE_KEY_PATH, or GITHUB_TOKEN"
+        ),
+    }
+}
+
+/// Post a status on a commit. Statuses with the same context replace each other.
+pub async fn create_commit_status(
+    repo_slug: &str,
+    commit_hash: &str,
+    status: &CommitStatus,
+) -> Result<()> {
+    let request = HTTP_CLIENT
+        .post(format!(
+            "{}/repos/{}/statuses/{}",
+            HOST.api_url, repo_slug, commit_hash
+        ))
+        .header("Accept", "application/vnd.github+json")
+        .json(status);
+    let response = authorize(request, repo_slug)
+        .await?
+        .send()
+        .await
+        .context("GitHub commit status request failed")?;
+    check_status(
+        response,
+        &format!("the status of {}@{}", repo_slug, commit_hash),
+    )
+    .await?;
+    Ok(())
+}
+
+/// A release from the GitHub releases API
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubRelease {
+    pub tag_name: String,
+    pub name: Option<String>,
+    pub html_url: String,
+    pub published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub prerelease: bool,
+}
+
+/// List a repository's GitHub releases (most recent 100).
+///
+/// Uses GITHUB_TOKEN when set; unauthenticated requests are limited to 60 per hour.
+pub async fn list_releases(repo_slug: &str) -> Result<Vec<GithubRelease>> {
+    let mut request = HTTP_CLIENT
+        .get(format!(
+            "{}/repos/{}/releases?per_page=100",
+            HOST.api_url, repo_slug
+        ))
+        .header("Accept", "application/vnd.github+json");
+    if let Some(token) = secret_settings::get("GITHUB_TOKEN") {
+        request = request.bearer_auth(token);
+    }
+
+    let response = request
+        .send()
+        .await
+        .context("GitHub releases request failed")?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        anyhow::bail!(
+            "GitHub returned {} for {} releases: {}",
+            status,
+            repo_slug,
+            body
+        );
+    }
+
+    response
+        .json::<Vec<GithubRelease>>()
+        .await
+        .context("Failed to parse GitHub releases")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(base_url: &str) -> GitHubHost {
+        let host = base_url.trim_start_matches("https://").to_string();
+        GitHubHost {
+            base_url: base_url.to_string(),
+            api_url: format!("{}/api/v3", base_url),
+            host,
+        }
+    }
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        format!("sha256={}", hex::encode(hmac::sign(&key, body).as_ref()))
+    }
+
+    #[test]
+    fn test_check_webhook_rejects_other_hosts() {
+        let body = br#"{"ref":"refs/heads/main"}"#;
+        let signature = sign("s3cret", body);
+        let signed = Some(signature.as_str());
+        let ghe = host("https://ghe.example.com");
+        let github = host("https://github.com");
+
+        assert_eq!(
+            check_webhook(&ghe, Some("s3cret"), Some("GHE.example.com "), signed, body),
+            Ok(())
+        );
+        // Correctly signed, but sent by another Enterprise Server or by github.com
+        assert_eq!(
+            check_webhook(&ghe, Some("s3cret"), Some("ghe.evil.com"), signed, body),
+            Err(WebhookRejection::WrongHost)
+        );
+        assert_eq!(
+            check_webhook(&ghe, Some("s3cret"), None, signed, body),
+            Err(WebhookRejection::WrongHost)
+        );
+        // github.com never names itself, so a delivery that does came from elsewhere
+        assert_eq!(
+            check_webhook(
+                &github,
+                Some("s3cret"),
+                Some("ghe.example.com"),
+                signed,
+                body
+            ),
+            Err(WebhookRejection::WrongHost)
+        );
+        assert_eq!(
+            check_webhook(&github, Some("s3cret"), None, signed, body),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_check_webhook_signature() {
+        let body = br#"{"ref":"refs/heads/main"}"#;
+        let github = host("https://github.com");
+
+        assert_eq!(check_webhook(&github, None, None, None, body), Ok(()));
+        for signature in [
+            None,
+            Some("sha256=00"),
+            Some("not-hex"),
+            Some(sign("other", body).as_str()),
+        ] {
+            assert_eq!(
+                check_webhook(&github, Some("s3cret"), None, signature, body),
+                Err(WebhookRejection::BadSignature)
+            );
+        }
+        let tampered = sign("s3cret", b"{}");
+        assert_eq!(
+            check_webhook(&github, Some("s3cret"), None, Some(&tampered), body),
+            Err(WebhookRejection::BadSignature)
+        );
+    }
+}
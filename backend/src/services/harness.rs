@@ -0,0 +1,212 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::services::commit_diff::is_generated_net;
+use crate::services::decoupling::base_name;
+use crate::services::pinmap::pin_order;
+use crate::services::{connectors, distill, git};
+use crate::types::{HarnessConnector, HarnessResponse, HarnessRow};
+use kicad_db::PgPool;
+
+/// A board of the repository: a KiCad project and the directory it's in
+#[derive(Debug)]
+struct Board {
+    name: String,
+    /// "boards/display/", or "" at the repository root
+    dir: String,
+}
+
+fn boards(project_paths: &[&str]) -> Vec<Board> {
+    project_paths
+        .iter()
+        .map(|path| {
+            let (dir, file) = match path.rfind('/') {
+                Some(i) => (&path[..=i], &path[i + 1..]),
+                None => ("", *path),
+            };
+            Board {
+                name: file.trim_end_matches(".kicad_pro").to_string(),
+                dir: dir.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Board whose project directory contains the sheet, the innermost one when
+/// projects are nested
+fn board_of<'a>(boards: &'a [Board], sheet: Option<&str>) -> Option<&'a str> {
+    let sheet = sheet?;
+    boards
+        .iter()
+        .filter(|b| sheet.starts_with(&b.dir))
+        .max_by_key(|b| b.dir.len())
+        .map(|b| b.name.as_str())
+}
+
+/// Where a net name says it goes: "MAIN_TO_DISP_SDA" -> "DISP", or
+/// "DISPLAY_SDA" -> "display" when another board is called "display"
+fn destination_hint(net: &str, own_board: Option<&str>, boards: &[Board]) -> Option<String> {
+    if is_generated_net(net) {
+        return None;
+    }
+    let name = base_name(net);
+    let tokens: Vec<&str> = name.split(['_', '-']).collect();
+    if let Some(i) = tokens.iter().position(|t| t.eq_ignore_ascii_case("TO")) {
+        if let Some(target) = tokens.get(i + 1).filter(|t| !t.is_empty()) {
+            return Some(target.to_string());
+        }
+    }
+    let first = tokens.first()?;
+    boards
+        .iter()
+        .map(|b| b.name.as_str())
+        .filter(|b| Some(*b) != own_board)
+        .find(|b| b.eq_ignore_ascii_case(first))
+        .map(ToString::to_string)
+}
+
+/// A connector pin: (board, reference, pin number)
+type PinRef<'a> = (Option<&'a str>, &'a str, String);
+
+/// Wiring table of every connector: each pin with its net, the pins of
+/// connectors on other boards sharing that net (cross-sheet nets are joined by
+/// name, so a harness shows up as one net spanning both boards) and, failing
+/// those, where the net name says it goes. Boards are the KiCad projects among
+/// `project_paths`; with fewer than two, nothing mates and only the hints from
+/// net names are given.
+pub fn table(
+    distilled: &Value,
+    project_paths: &[&str],
+) -> (Vec<HarnessConnector>, Vec<HarnessRow>) {
+    let Some(components) = distilled.get("components").and_then(Value::as_object) else {
+        return (Vec::new(), Vec::new());
+    };
+    let boards = boards(project_paths);
+    let field = |value: &Value, key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|v| !v.is_empty() && *v != "~")
+            .map(ToString::to_string)
+    };
+
+    // Connector -> board, and net -> connector pins on it
+    let mut connector_boards: BTreeMap<&str, Option<&str>> = BTreeMap::new();
+    let mut on_net: BTreeMap<String, Vec<PinRef>> = BTreeMap::new();
+    for (reference, component) in components {
+        if !connectors::is_connector(distilled, reference) {
+            continue;
+        }
+        let sheet = component.get("sheet_path").and_then(Value::as_str);
+        let board = board_of(&boards, sheet);
+        connector_boards.insert(reference, board);
+        for pin in component
+            .get("pins")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let (Some(number), Some(net)) = (field(pin, "number"), field(pin, "net")) {
+                on_net
+                    .entry(net)
+                    .or_default()
+                    .push((board, reference.as_str(), number));
+            }
+        }
+    }
+
+    let mut summaries = Vec::new();
+    let mut rows = Vec::new();
+    for (reference, board) in &connector_boards {
+        let component = &components[*reference];
+        let mut pins: Vec<&Value> = component
+            .get("pins")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .collect();
+        pins.sort_by_key(|pin| {
+            pin_order(
+                pin.get("number")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+            )
+        });
+
+        let mut mated_with: BTreeSet<String> = BTreeSet::new();
+        let mut connected = 0;
+        for pin in pins {
+            let Some(number) = field(pin, "number") else {
+                continue;
+            };
+            let net = field(pin, "net");
+            let mates: Vec<&PinRef> = net
+                .as_ref()
+                .and_then(|net| on_net.get(net))
+                .into_iter()
+                .flatten()
+                .filter(|(other_board, other, _)| {
+                    other != reference && board.is_some() && other_board != board
+                })
+                .collect();
+            let mate_boards: BTreeSet<&str> = mates.iter().filter_map(|(b, _, _)| *b).collect();
+            mated_with.extend(mates.iter().map(|(_, other, _)| other.to_string()));
+            connected += usize::from(net.is_some());
+
+            let destination = if mate_boards.is_empty() {
+                net.as_deref()
+                    .and_then(|net| destination_hint(net, *board, &boards))
+            } else {
+                Some(mate_boards.into_iter().collect::<Vec<_>>().join(", "))
+            };
+            rows.push(HarnessRow {
+                connector: reference.to_string(),
+                board: board.map(ToString::to_string),
+                pin: number,
+                pin_name: field(pin, "name"),
+                mates: mates
+                    .iter()
+                    .map(|(b, other, pin)| match b {
+                        Some(b) => format!("{} pin {} ({})", other, pin, b),
+                        None => format!("{} pin {}", other, pin),
+                    })
+                    .collect(),
+                destination,
+                net,
+            });
+        }
+
+        summaries.push(HarnessConnector {
+            reference: reference.to_string(),
+            board: board.map(ToString::to_string),
+            value: field(component, "value"),
+            footprint: field(component, "footprint"),
+            connected_pins: connected,
+            mated_with: mated_with.into_iter().collect(),
+        });
+    }
+    (summaries, rows)
+}
+
+/// Harness table of a commit
+pub async fn build(pool: &PgPool, repo: &str, commit: &str) -> Result<HarnessResponse> {
+    let files = git::get_schematic_files(repo, commit)
+        .await
+        .context("Failed to fetch schematic files from repo")?;
+    let project_paths: Vec<&str> = files
+        .iter()
+        .map(|f| f.path.as_str())
+        .filter(|p| p.ends_with(".kicad_pro"))
+        .collect();
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+    let (connectors, rows) = table(&distilled, &project_paths);
+
+    Ok(HarnessResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        boards: boards(&project_paths).into_iter().map(|b| b.name).collect(),
+        connectors,
+        rows,
+    })
+}
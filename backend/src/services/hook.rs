@@ -0,0 +1,348 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use tracing::{error, info, warn};
+
+use crate::services::{
+    audit, commit_status, distill, git, github, provenance, quarantine, repo_cards, retrieval,
+    risk, speech,
+};
+use crate::types::HookUpdateResponse;
+use kicad_db::provenance::{NewProvenance, ARTIFACT_SUMMARY};
+use kicad_db::summaries::{self, SummaryProvenance};
+use kicad_db::{retrieve_schematic_meta, store_schematic, PgPool};
+
+/// Generator recorded against stored blurbs/descriptions (no LLM call yet)
+pub const OVERVIEW_MODEL: &str = "template";
+/// Bump whenever the overview wording changes so feedback can be compared across versions
+pub const OVERVIEW_PROMPT_VERSION: &str = "overview-placeholder-v1";
+/// Commits per run that get a GitHub commit status, newest first, so
+/// processing a long history doesn't run the checks on every old commit
+const MAX_COMMIT_STATUSES: usize = 10;
+
+/// Provenance recorded for overviews generated by this build
+pub fn overview_provenance() -> SummaryProvenance<'static> {
+    SummaryProvenance {
+        model: OVERVIEW_MODEL,
+        prompt_version: OVERVIEW_PROMPT_VERSION,
+        distiller_version: distill::distiller_version(),
+    }
+}
+
+/// Generate overviews for every schematic commit in a repository that is missing one.
+/// `actor` is recorded in the audit log against each generated overview.
+pub async fn process_repo(pool: &PgPool, repo: &str, actor: &str) -> Result<HookUpdateResponse> {
+    let repo_url = github::repo_url(repo);
+
+    // Get all commits with schematic changes
+    let commits = git::get_schematic_commits(repo)
+        .await
+        .context("Failed to fetch commits")?;
+
+    info!(
+        "Found {} commits with schematic changes for repo: {}",
+        commits.len(),
+        repo
+    );
+    for (idx, commit) in commits.iter().enumerate() {
+        info!(
+            "  Commit {}: {} - {:?}",
+            idx + 1,
+            &commit.commit_hash[..8.min(commit.commit_hash.len())],
+            commit.message
+        );
+    }
+
+    let mut processed = 0;
+    let mut errors = Vec::new();
+    let mut quarantined =
+        quarantine::quarantined_commits(pool, &repo_url, quarantine::STAGE_OVERVIEW).await;
+    quarantined.retain(|hash| commits.iter().any(|c| &c.commit_hash == hash));
+
+    for commit_info in commits {
+        if quarantined.contains(&commit_info.commit_hash) {
+            continue;
+        }
+
+        // Check if we already have an overview for this commit
+        let existing = retrieve_schematic_meta(pool, &repo_url, &commit_info.commit_hash)
+            .await
+            .ok()
+            .flatten();
+
+        let needs_processing = existing
+            .as_ref()
+            .map(|s| s.generated_blurb.is_none() || s.generated_description.is_none())
+            .unwrap_or(true);
+
+        info!(
+            "Commit {} needs_processing={}, existing={:?}",
+            &commit_info.commit_hash[..8.min(commit_info.commit_hash.len())],
+            needs_processing,
+            existing.as_ref().map(|s| format!(
+                "blurb={}, desc={}",
+                s.generated_blurb.is_some(),
+                s.generated_description.is_some()
+            ))
+        );
+
+        if needs_processing {
+            match generate_and_store_overview(
+                pool,
+                actor,
+                repo,
+                &repo_url,
+                &commit_info.commit_hash,
+                commit_info.commit_date,
+                commit_info.message.as_deref(),
+            )
+            .await
+            {
+                Ok(_) => {
+                    processed += 1;
+                    quarantine::record_success(
+                        pool,
+                        &repo_url,
+                        &commit_info.commit_hash,
+                        quarantine::STAGE_OVERVIEW,
+                    )
+                    .await;
+                    info!(
+                        "Generated overview for {}/{}",
+                        repo, commit_info.commit_hash
+                    );
+                    if processed <= MAX_COMMIT_STATUSES {
+                        commit_status::post(pool, repo, &commit_info.commit_hash).await;
+                    }
+                }
+                Err(e) => {
+                    let err_msg = format!("Commit {}: {}", commit_info.commit_hash, e);
+                    // Check for rate limiting
+                    if e.to_string().contains("429")
+                        || e.to_string().to_lowercase().contains("rate")
+                    {
+                        error!(
+                            "RATE LIMITED while processing commit {}: {}",
+                            commit_info.commit_hash, e
+                        );
+                        warn!("XAI API rate limit hit! Stopping further processing.");
+                        errors.push(format!("RATE LIMITED: {}", err_msg));
+                        // Break out of the loop to avoid hitting more rate limits
+                        break;
+                    }
+                    error!("Failed to generate overview: {}", err_msg);
+                    errors.push(err_msg);
+                    quarantine::record_failure(
+                        pool,
+                        &repo_url,
+                        &commit_info.commit_hash,
+                        quarantine::STAGE_OVERVIEW,
+                        &e,
+                    )
+                    .await;
+                }
+            }
+        }
+    }
+
+    // Score commits indexed before risk scoring existed or by an older scorer
+    if let Err(e) = risk::backfill(pool, repo).await {
+        warn!("Failed to backfill risk scores for {}: {}", repo, e);
+    }
+
+    info!(
+        "Hook processing complete for {}: processed={}, errors={}, quarantined={}",
+        repo,
+        processed,
+        errors.len(),
+        quarantined.len()
+    );
+    if !errors.is_empty() {
+        warn!("Errors during processing: {:?}", errors);
+    }
+
+    Ok(HookUpdateResponse {
+        repo: repo.to_string(),
+        processed,
+        errors,
+        quarantined,
+        job_id: None,
+    })
+}
+
+/// A generated blurb and description, with the changed files they were built from
+struct Overview {
+    blurb: String,
+    description: String,
+    changed_files: Vec<String>,
+}
+
+/// Build a placeholder blurb and description from a commit's diff (TODO: integrate with Grok)
+async fn build_overview(
+    repo_slug: &str,
+    commit_hash: &str,
+    git_message: Option<&str>,
+) -> Result<Overview> {
+    // Get changed files for context
+    let changed_files = git::get_changed_schematic_files(repo_slug, commit_hash).await?;
+
+    let num_files = changed_files.len();
+    let blurb = if num_files > 0 {
+        format!(
+            "Schematic changes in {} file(s): {}",
+            num_files,
+            git_message
+                .unwrap_or("Update")
+                .split_whitespace()
+                .take(5)
+                .collect::<Vec<_>>()
+                .join(" ")
+        )
+    } else {
+        "Initial schematic commit".to_string()
+    };
+
+    let mut description = format!(
+        "Commit message: {}\nChanged files:\n",
+        git_message.unwrap_or("(no message)")
+    );
+    for path in &changed_files {
+        description.push_str(&format!("  - {}\n", path));
+    }
+
+    Ok(Overview {
+        blurb,
+        description,
+        changed_files,
+    })
+}
+
+/// Record what an overview was built from. The template has no prompt.
+async fn record_provenance(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    git_message: Option<&str>,
+    overview: &Overview,
+) {
+    let changed_files = overview.changed_files.join("\n");
+    provenance::record(
+        pool,
+        &NewProvenance {
+            artifact: ARTIFACT_SUMMARY,
+            artifact_ref: "blurb",
+            repo_url: Some(repo_url),
+            commit_hash: Some(commit_hash),
+            model: OVERVIEW_MODEL,
+            prompt_version: OVERVIEW_PROMPT_VERSION,
+            prompt: None,
+            tool_calls: &[],
+            inputs: &[
+                ("git_message", git_message.unwrap_or_default().as_bytes()),
+                ("changed_files", changed_files.as_bytes()),
+            ],
+            outputs: &[
+                ("blurb", overview.blurb.as_bytes()),
+                ("description", overview.description.as_bytes()),
+            ],
+        },
+    )
+    .await;
+}
+
+/// Generate a placeholder overview and store it in the database
+async fn generate_and_store_overview(
+    pool: &PgPool,
+    actor: &str,
+    repo_slug: &str,
+    repo_url: &str,
+    commit_hash: &str,
+    commit_date: Option<chrono::DateTime<chrono::Utc>>,
+    git_message: Option<&str>,
+) -> Result<()> {
+    let overview = build_overview(repo_slug, commit_hash, git_message).await?;
+    let (blurb, description) = (&overview.blurb, &overview.description);
+
+    let empty_parts = HashMap::new();
+    store_schematic(
+        pool,
+        repo_url,
+        commit_hash,
+        commit_date,
+        git_message,
+        None, // summary
+        None, // overview
+        Some(blurb),
+        Some(description),
+        empty_parts,
+    )
+    .await?;
+    summaries::set_provenance(pool, repo_url, commit_hash, &overview_provenance()).await?;
+    record_provenance(pool, repo_url, commit_hash, git_message, &overview).await;
+    speech::blurb(pool, repo_url, commit_hash, blurb).await;
+    record_generated(pool, actor, repo_url, commit_hash, false).await;
+    risk::record(pool, repo_slug, commit_hash).await;
+    retrieval::index_later(pool, repo_slug, commit_hash).await;
+    repo_cards::refresh_later(pool, repo_slug);
+
+    Ok(())
+}
+
+/// Regenerate the overview of an already-indexed commit from its diff,
+/// replacing only the blurb and description
+pub async fn regenerate_overview(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    actor: &str,
+) -> Result<()> {
+    let repo_url = github::repo_url(repo);
+    let existing = retrieve_schematic_meta(pool, &repo_url, commit)
+        .await?
+        .with_context(|| format!("No indexed commit {} in {}", commit, repo))?;
+
+    let git_message = existing.git_message.as_deref();
+    let overview = build_overview(repo, commit, git_message).await?;
+    summaries::update_summary(
+        pool,
+        &repo_url,
+        commit,
+        &overview.blurb,
+        &overview.description,
+        &overview_provenance(),
+    )
+    .await?;
+    record_provenance(pool, &repo_url, commit, git_message, &overview).await;
+    // An edited blurb is still the one read aloud
+    if !existing.blurb_edited {
+        speech::blurb(pool, &repo_url, commit, &overview.blurb).await;
+    }
+    record_generated(pool, actor, &repo_url, commit, true).await;
+    retrieval::index_later(pool, repo, commit).await;
+    repo_cards::refresh_later(pool, repo);
+
+    info!("Regenerated overview for {}/{}", repo, commit);
+    Ok(())
+}
+
+async fn record_generated(
+    pool: &PgPool,
+    actor: &str,
+    repo_url: &str,
+    commit: &str,
+    regenerated: bool,
+) {
+    audit::record(
+        pool,
+        audit::SUMMARY_GENERATED,
+        actor,
+        Some(repo_url),
+        Some(commit),
+        json!({
+            "model": OVERVIEW_MODEL,
+            "prompt_version": OVERVIEW_PROMPT_VERSION,
+            "regenerated": regenerated,
+        }),
+    )
+    .await;
+}
@@ -0,0 +1,205 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::services::commit_diff::{self, CommitDiff, NetMembers};
+use crate::services::git;
+use crate::types::{ImpactNetChange, ImpactResponse, ImpactedComponent};
+use kicad_db::PgPool;
+
+// Why a component is in the blast radius. The first five are direct changes.
+pub const REASON_ADDED: &str = "added";
+pub const REASON_REMOVED: &str = "removed";
+pub const REASON_EDITED: &str = "edited";
+pub const REASON_CONNECTED: &str = "connected";
+pub const REASON_DISCONNECTED: &str = "disconnected";
+pub const REASON_NET_MERGED: &str = "net_merged";
+pub const REASON_NET_SPLIT: &str = "net_split";
+pub const REASON_NET_REWIRED: &str = "net_rewired";
+
+const DIRECT_REASONS: &[&str] = &[
+    REASON_ADDED,
+    REASON_REMOVED,
+    REASON_EDITED,
+    REASON_CONNECTED,
+    REASON_DISCONNECTED,
+];
+
+/// (reference, pin)
+type Pin = (String, String);
+
+/// Net of every connected pin
+fn pin_nets(members: &NetMembers) -> BTreeMap<Pin, &str> {
+    members
+        .iter()
+        .flat_map(|(net, refs)| {
+            refs.iter().flat_map(move |(reference, pins)| {
+                pins.iter()
+                    .map(move |pin| ((reference.clone(), pin.clone()), net.as_str()))
+            })
+        })
+        .collect()
+}
+
+fn references(distilled: &Value) -> BTreeSet<&str> {
+    distilled
+        .get("components")
+        .and_then(Value::as_object)
+        .map(|c| c.keys().map(String::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// What a commit changed in the design's connectivity, and which components
+/// it reaches
+#[derive(Debug, Default)]
+pub struct Impact {
+    pub net_changes: Vec<ImpactNetChange>,
+    pub components: Vec<ImpactedComponent>,
+}
+
+impl Impact {
+    /// Components reached only through merged, split or rewired nets
+    pub fn indirect(&self) -> impl Iterator<Item = &ImpactedComponent> {
+        self.components.iter().filter(|c| !c.direct)
+    }
+}
+
+/// Components whose connectivity a commit changed, directly or through nets.
+///
+/// Nets before and after are matched by the pins they share, so renamed nets
+/// (including regenerated names) and nets that only gained or lost members
+/// stay the same net. Where several nets before share pins with one net after,
+/// the nets merged; the reverse is a split, and anything else a rewire. Every
+/// component on such nets is affected. Directly affected are components that
+/// were added, removed or edited, and pins that were connected or disconnected.
+pub fn analyze(diff: &CommitDiff) -> Impact {
+    let before = commit_diff::net_members(&diff.before);
+    let after = commit_diff::net_members(&diff.after);
+    let pins_before = pin_nets(&before);
+    let pins_after = pin_nets(&after);
+    let refs_before = references(&diff.before);
+    let refs_after = references(&diff.after);
+
+    let mut reasons: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    let mut reason = |reference: &str, why: &'static str| {
+        reasons
+            .entry(reference.to_string())
+            .or_default()
+            .insert(why);
+    };
+    for part in &diff.bom.added {
+        reason(&part.reference, REASON_ADDED);
+    }
+    for part in &diff.bom.removed {
+        reason(&part.reference, REASON_REMOVED);
+    }
+    for change in &diff.bom.changed {
+        reason(&change.reference, REASON_EDITED);
+    }
+    for (reference, _) in pins_after.keys().filter(|p| !pins_before.contains_key(*p)) {
+        if refs_before.contains(reference.as_str()) {
+            reason(reference, REASON_CONNECTED);
+        }
+    }
+    for (reference, _) in pins_before.keys().filter(|p| !pins_after.contains_key(*p)) {
+        if refs_after.contains(reference.as_str()) {
+            reason(reference, REASON_DISCONNECTED);
+        }
+    }
+
+    // Link each net before to the nets after that took over its pins
+    let mut links: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut back_links: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for (pin, was) in &pins_before {
+        if let Some(now) = pins_after.get(pin) {
+            links.entry(was).or_default().insert(now);
+            back_links.entry(now).or_default().insert(was);
+        }
+    }
+
+    // Clusters of linked nets: (nets before, nets after)
+    let mut seen: BTreeSet<&str> = BTreeSet::new();
+    let mut net_changes = Vec::new();
+    for start in links.keys() {
+        if seen.contains(start) {
+            continue;
+        }
+        let (mut nets_before, mut nets_after) = (BTreeSet::new(), BTreeSet::new());
+        let mut queue = vec![(true, *start)];
+        while let Some((is_before, net)) = queue.pop() {
+            let (set, next, edges) = if is_before {
+                (&mut nets_before, false, &links)
+            } else {
+                (&mut nets_after, true, &back_links)
+            };
+            if !set.insert(net) {
+                continue;
+            }
+            for linked in edges.get(net).into_iter().flatten() {
+                queue.push((next, *linked));
+            }
+        }
+        seen.extend(nets_before.iter().copied());
+
+        let (change, why) = match (nets_before.len(), nets_after.len()) {
+            (1, 1) => continue,
+            (_, 1) => ("merged", REASON_NET_MERGED),
+            (1, _) => ("split", REASON_NET_SPLIT),
+            _ => ("rewired", REASON_NET_REWIRED),
+        };
+        let components: BTreeSet<&String> = nets_before
+            .iter()
+            .filter_map(|net| before.get(*net))
+            .chain(nets_after.iter().filter_map(|net| after.get(*net)))
+            .flat_map(|refs| refs.keys())
+            .collect();
+        for reference in &components {
+            reason(reference, why);
+        }
+        net_changes.push(ImpactNetChange {
+            change: change.to_string(),
+            before: nets_before.into_iter().map(ToString::to_string).collect(),
+            after: nets_after.into_iter().map(ToString::to_string).collect(),
+            components: components.into_iter().cloned().collect(),
+        });
+    }
+
+    let components = reasons
+        .into_iter()
+        .map(|(reference, reasons)| ImpactedComponent {
+            direct: reasons.iter().any(|r| DIRECT_REASONS.contains(r)),
+            reasons: reasons.into_iter().map(ToString::to_string).collect(),
+            reference,
+        })
+        .collect();
+
+    Impact {
+        net_changes,
+        components,
+    }
+}
+
+/// Impact analysis of a commit against `base`, or its first parent
+pub async fn check(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    base: Option<String>,
+) -> Result<ImpactResponse> {
+    let base = match base {
+        Some(base) => Some(base),
+        None => git::get_parent_commit(repo, commit).await?,
+    };
+    let diff = commit_diff::compute_against(pool, repo, base.clone(), commit).await?;
+    let impact = analyze(&diff);
+
+    Ok(ImpactResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        base,
+        directly_affected: impact.components.iter().filter(|c| c.direct).count(),
+        indirectly_affected: impact.indirect().count(),
+        net_changes: impact.net_changes,
+        components: impact.components,
+    })
+}
@@ -0,0 +1,363 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use crate::quota;
+use crate::services::distiller::DistillTimeout;
+use crate::services::kicad_format::UnsupportedFormat;
+use crate::services::quarantine::Quarantined;
+use crate::services::{audit, backfill, distill, git, hook, retrieval, suggestions};
+use kicad_db::jobs::{self, FailedJobFilter, Job};
+use kicad_db::PgPool;
+
+// Attempts per job before it is marked failed
+const MAX_ATTEMPTS: i32 = 3;
+
+// Base delay before a failed job is retried (multiplied by the attempt number)
+const RETRY_DELAY_SECS: i64 = 30;
+
+// Running jobs older than this are assumed to belong to a dead worker
+const STALE_JOB_SECS: i64 = 30 * 60;
+
+// How often workers look for jobs abandoned by dead workers
+const REQUEUE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// How often an idle worker checks for new jobs
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// `locked_by` of jobs run by the API process itself
+const INLINE_WORKER_ID: &str = "api";
+
+// Set in grokicad-worker processes, see `mark_worker_process`
+static WORKER_PROCESS: AtomicBool = AtomicBool::new(false);
+
+/// Heavy work that can be handed to a `grokicad-worker` process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobRequest {
+    /// Distill a commit's schematics into the distilled JSON cache. The time
+    /// is charged to `api_key`, the key of the request that queued it.
+    Distill {
+        repo: String,
+        commit: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        api_key: Option<i64>,
+    },
+    /// Generate overviews for a repository's schematic commits (webhook processing).
+    /// With `refresh`, the cached clone is dropped first so new commits are picked up.
+    ProcessRepo { repo: String, refresh: bool },
+    /// Regenerate a stale commit overview with the current prompt and distiller
+    RegenerateSummary { repo: String, commit: String },
+    /// Distill and index parts for up to `limit` schematic commits before `head`
+    Backfill {
+        repo: String,
+        head: String,
+        limit: usize,
+    },
+    /// Build a commit's retrieval index for questions about the design
+    IndexRetrieval { repo: String, commit: String },
+    /// Generate the suggested questions of a newly onboarded repository
+    GenerateSuggestions { repo: String, commit: String },
+}
+
+impl JobRequest {
+    /// Short identifier stored in the `kind` column
+    pub fn kind(&self) -> &'static str {
+        match self {
+            JobRequest::Distill { .. } => "distill",
+            JobRequest::ProcessRepo { .. } => "process_repo",
+            JobRequest::RegenerateSummary { .. } => "regenerate_summary",
+            JobRequest::Backfill { .. } => "backfill",
+            JobRequest::IndexRetrieval { .. } => "index_retrieval",
+            JobRequest::GenerateSuggestions { .. } => "generate_suggestions",
+        }
+    }
+}
+
+/// Whether heavy processing should go through the job queue.
+///
+/// Set JOB_QUEUE=true when at least one `grokicad-worker` is running; otherwise
+/// the API keeps doing the work inline.
+pub fn queue_enabled() -> bool {
+    std::env::var("JOB_QUEUE")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Record that this process is a `grokicad-worker`. Work a job needs, such as
+/// distilling the commit it indexes, is then done inline instead of being
+/// queued behind it.
+pub fn mark_worker_process() {
+    WORKER_PROCESS.store(true, Ordering::Relaxed);
+}
+
+/// Whether work should be handed to the job queue from this process: the
+/// queue is enabled and this isn't a worker
+pub fn hand_off() -> bool {
+    queue_enabled() && !WORKER_PROCESS.load(Ordering::Relaxed)
+}
+
+/// The most recent job for this request, in any status. Fields the request
+/// leaves out of its payload, such as a Distill job's `api_key`, match any value.
+pub async fn latest(pool: &PgPool, request: &JobRequest) -> Result<Option<Job>> {
+    let payload = serde_json::to_value(request)?;
+    Ok(jobs::latest_job(pool, request.kind(), &payload).await?)
+}
+
+/// Add a job to the queue, returning its id
+pub async fn enqueue(pool: &PgPool, request: &JobRequest) -> Result<i64> {
+    let payload = serde_json::to_value(request)?;
+    let id = jobs::enqueue_job(pool, request.kind(), &payload, MAX_ATTEMPTS).await?;
+    Ok(id)
+}
+
+/// Queue a job that the caller doesn't wait for, returning its id.
+///
+/// Without the job queue no worker would pick the job up, so it is claimed
+/// and run in this process instead; its status and progress are still
+/// tracked in the queue.
+pub async fn start(pool: &PgPool, request: &JobRequest) -> Result<i64> {
+    if queue_enabled() {
+        return enqueue(pool, request).await;
+    }
+
+    // Nothing would pick up a retry, so in-process jobs get a single attempt
+    let payload = serde_json::to_value(request)?;
+    let id = jobs::enqueue_job(pool, request.kind(), &payload, 1).await?;
+    run_in_process(pool, id).await?;
+    Ok(id)
+}
+
+/// Claim queued job `id` and run it in the background of this process
+async fn run_in_process(pool: &PgPool, id: i64) -> Result<()> {
+    if let Some(job) = jobs::claim_job(pool, id, INLINE_WORKER_ID).await? {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_job(&pool, INLINE_WORKER_ID, job).await {
+                error!("Failed to run job {} in process: {}", id, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Put up to `limit` failed jobs matching `filter` back on the queue, with
+/// as many attempts as a new job. Without the job queue they are run in this
+/// process instead. Returns the retried jobs.
+pub async fn retry_failed(pool: &PgPool, filter: &FailedJobFilter, limit: i64) -> Result<Vec<Job>> {
+    let attempts = if queue_enabled() { MAX_ATTEMPTS } else { 1 };
+    let retried = jobs::retry_failed_jobs(pool, filter, attempts, limit).await?;
+    for job in &retried {
+        info!("Retrying failed {} job {}", job.kind, job.id);
+        if !queue_enabled() {
+            run_in_process(pool, job.id).await?;
+        }
+    }
+    Ok(retried)
+}
+
+/// Run job `id` in this process. Its actions are recorded in the audit log
+/// against the job.
+pub async fn execute(pool: &PgPool, id: i64, request: &JobRequest) -> Result<Value> {
+    let actor = &audit::job_actor(id);
+    match request {
+        JobRequest::Distill {
+            repo,
+            commit,
+            api_key,
+        } => {
+            quota::with_key(*api_key, async {
+                let started = Instant::now();
+                let (_, file_cache) = distill::distill_and_store(pool, repo, commit).await?;
+                let seconds = started.elapsed().as_secs_f64();
+                quota::charge_distill_seconds(pool, seconds).await;
+                Ok(serde_json::json!({
+                    "file_cache": file_cache,
+                    "distill_seconds": seconds,
+                }))
+            })
+            .await
+        }
+        JobRequest::ProcessRepo { repo, refresh } => {
+            if *refresh {
+                if let Err(e) = git::invalidate_cache(repo).await {
+                    warn!("Failed to invalidate cache for {}: {}", repo, e);
+                }
+            }
+            let summary = hook::process_repo(pool, repo, actor).await?;
+            Ok(serde_json::to_value(summary)?)
+        }
+        JobRequest::RegenerateSummary { repo, commit } => {
+            hook::regenerate_overview(pool, repo, commit, actor).await?;
+            Ok(Value::Null)
+        }
+        JobRequest::Backfill { repo, head, limit } => {
+            let summary =
+                backfill::backfill_repo(pool, repo, head, *limit, |done, total| async move {
+                    let progress = serde_json::json!({ "done": done, "total": total });
+                    if let Err(e) = jobs::set_progress(pool, id, &progress).await {
+                        warn!("Failed to record progress of job {}: {}", id, e);
+                    }
+                })
+                .await?;
+            Ok(serde_json::to_value(summary)?)
+        }
+        JobRequest::IndexRetrieval { repo, commit } => {
+            let documents = retrieval::build_index(pool, repo, commit).await?;
+            Ok(serde_json::json!({ "documents": documents }))
+        }
+        JobRequest::GenerateSuggestions { repo, commit } => {
+            let count = suggestions::generate(pool, repo, commit).await?;
+            Ok(serde_json::json!({ "suggestions": count }))
+        }
+    }
+}
+
+/// Claim and run a single job. Returns false when the queue was empty.
+async fn run_next(pool: &PgPool, worker_id: &str) -> Result<bool> {
+    let Some(job) = jobs::claim_next_job(pool, worker_id).await? else {
+        return Ok(false);
+    };
+    run_job(pool, worker_id, job).await?;
+    Ok(true)
+}
+
+/// Run a claimed job and record its outcome, on the job and in its run history
+async fn run_job(pool: &PgPool, worker_id: &str, job: Job) -> Result<()> {
+    // History is for operators; losing a row mustn't fail the job
+    let run_id = match jobs::start_run(pool, &job, worker_id).await {
+        Ok(run_id) => Some(run_id),
+        Err(e) => {
+            warn!("Failed to record run of job {}: {}", job.id, e);
+            None
+        }
+    };
+    let finish_run = |status: &'static str, error: Option<String>, artifacts: Option<Value>| async move {
+        let Some(run_id) = run_id else {
+            return;
+        };
+        if let Err(e) =
+            jobs::finish_run(pool, run_id, status, error.as_deref(), artifacts.as_ref()).await
+        {
+            warn!("Failed to record end of job run {}: {}", run_id, e);
+        }
+    };
+
+    let Job {
+        id,
+        kind,
+        attempts,
+        payload,
+        ..
+    } = job;
+    info!(
+        "{} running {} job {} (attempt {})",
+        worker_id, kind, id, attempts
+    );
+
+    let outcome = match serde_json::from_value::<JobRequest>(payload) {
+        Ok(request) => execute(pool, id, &request).await,
+        Err(e) => Err(anyhow::anyhow!("Invalid {} job payload: {}", kind, e)),
+    };
+
+    match outcome {
+        Ok(result) => {
+            jobs::complete_job(pool, id, &result).await?;
+            finish_run(jobs::STATUS_SUCCEEDED, None, Some(result)).await;
+            info!("{} finished {} job {}", worker_id, kind, id);
+        }
+        Err(e) => {
+            error!("{} failed {} job {}: {:#}", worker_id, kind, id, e);
+            finish_run(jobs::STATUS_FAILED, Some(format!("{:#}", e)), None).await;
+            if let Some(unsupported) = distill::cause(&e).downcast_ref::<UnsupportedFormat>() {
+                // The files won't change format on retry; keep the details
+                // so a request for the commit can answer with them
+                let result = serde_json::json!({
+                    "error": "unsupported_format",
+                    "details": unsupported.details(),
+                });
+                jobs::abandon_job(pool, id, &format!("{:#}", e), Some(&result)).await?;
+            } else if e.downcast_ref::<DistillTimeout>().is_some()
+                || e.downcast_ref::<Quarantined>().is_some()
+            {
+                // A commit that hung once will hang again, and a quarantined
+                // one stays refused until released; don't retry them
+                jobs::abandon_job(pool, id, &format!("{:#}", e), None).await?;
+            } else {
+                let delay = RETRY_DELAY_SECS * i64::from(attempts);
+                jobs::fail_job(pool, id, &format!("{:#}", e), delay).await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Worker loop: claim jobs from the queue until the process is stopped
+pub async fn run_worker(pool: PgPool, worker_id: String) {
+    info!("Worker {} started", worker_id);
+    loop {
+        match run_next(&pool, &worker_id).await {
+            Ok(true) => {}
+            Ok(false) => tokio::time::sleep(IDLE_POLL_INTERVAL).await,
+            Err(e) => {
+                error!("Worker {} queue error: {}", worker_id, e);
+                tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// Return jobs abandoned by crashed workers to the queue
+pub async fn requeue_stale(pool: &PgPool) -> Result<u64> {
+    Ok(jobs::requeue_stale_jobs(pool, STALE_JOB_SECS).await?)
+}
+
+/// Requeue stale jobs now and every few minutes after, so a worker that dies
+/// mid-job doesn't strand it until the next worker restart
+pub fn spawn_requeue(pool: PgPool) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(REQUEUE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            match requeue_stale(&pool).await {
+                Ok(0) => {}
+                Ok(n) => info!("Requeued {} job(s) abandoned by a stopped worker", n),
+                Err(e) => warn!("Failed to requeue stale jobs: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn distill_payload_names_the_key_only_when_there_is_one() {
+        let request = |api_key| JobRequest::Distill {
+            repo: "acme/board".to_string(),
+            commit: "abc123".to_string(),
+            api_key,
+        };
+        assert_eq!(
+            serde_json::to_value(request(None)).unwrap(),
+            json!({ "kind": "distill", "repo": "acme/board", "commit": "abc123" })
+        );
+        assert_eq!(
+            serde_json::to_value(request(Some(7))).unwrap()["api_key"],
+            json!(7)
+        );
+    }
+
+    #[test]
+    fn distill_payloads_queued_before_keys_still_parse() {
+        let payload = json!({ "kind": "distill", "repo": "acme/board", "commit": "abc123" });
+        let request: JobRequest = serde_json::from_value(payload).unwrap();
+        assert!(matches!(request, JobRequest::Distill { api_key: None, .. }));
+    }
+}
@@ -0,0 +1,125 @@
+use std::fmt;
+
+use serde_json::json;
+
+use crate::types::{DistillWarning, SchematicFile};
+
+/// Oldest S-expression schematic format the distiller understands (KiCad 6.0)
+pub const MIN_SUPPORTED_VERSION: u32 = 20211123;
+
+/// First format version written by each KiCad release, newest first
+const RELEASE_VERSIONS: &[(u32, &str)] = &[
+    (20250114, "9.0"),
+    (20231120, "8.0"),
+    (20230121, "7.0"),
+    (20211123, "6.0"),
+];
+
+/// A schematic file in a format the distiller can't parse
+#[derive(Debug, Clone)]
+pub struct UnsupportedFormat {
+    /// File path relative to repository root
+    pub path: String,
+    /// Detected format version (None for legacy or unrecognised files)
+    pub version: Option<u32>,
+}
+
+impl fmt::Display for UnsupportedFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(version) => write!(
+                f,
+                "{} uses schematic format version {} ({}); KiCad 6.0 (format {}) or newer is required",
+                self.path,
+                version,
+                describe_version(version),
+                MIN_SUPPORTED_VERSION
+            ),
+            None => write!(
+                f,
+                "{} is not a KiCad 6+ S-expression schematic (legacy KiCad 5 or unknown format); re-save it with KiCad 6.0 or newer",
+                self.path
+            ),
+        }
+    }
+}
+
+impl std::error::Error for UnsupportedFormat {}
+
+/// Read the `(version N)` header of a .kicad_sch file.
+///
+/// Only the start of the file is inspected; the version always directly
+/// follows the `(kicad_sch` token.
+pub fn detect_version(content: &str) -> Option<u32> {
+    let head = content.trim_start();
+    let rest = head.strip_prefix("(kicad_sch")?;
+    let rest = rest.trim_start().strip_prefix("(version")?;
+    let digits: String = rest
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+/// The KiCad release a format version belongs to, e.g. "8.0".
+/// Versions between releases come from nightly builds of the next one.
+pub fn kicad_release(version: u32) -> Option<&'static str> {
+    RELEASE_VERSIONS
+        .iter()
+        .find(|(first, _)| version >= *first)
+        .map(|(_, release)| *release)
+}
+
+fn describe_version(version: u32) -> String {
+    match kicad_release(version) {
+        Some(release) => format!("KiCad {}", release),
+        None => "pre-release KiCad 6".to_string(),
+    }
+}
+
+impl UnsupportedFormat {
+    /// Structured `details` of the 422 `unsupported_format` error
+    pub fn details(&self) -> serde_json::Value {
+        json!({
+            "file": self.path,
+            "format_version": self.version,
+            "kicad_release": self.version.and_then(kicad_release),
+            "min_supported_version": MIN_SUPPORTED_VERSION,
+        })
+    }
+
+    /// Read back the error from its `details`
+    pub fn from_details(details: &serde_json::Value) -> Option<Self> {
+        Some(Self {
+            path: details.get("file")?.as_str()?.to_string(),
+            version: details
+                .get("format_version")
+                .and_then(serde_json::Value::as_u64)
+                .and_then(|v| u32::try_from(v).ok()),
+        })
+    }
+}
+
+impl From<&UnsupportedFormat> for DistillWarning {
+    fn from(unsupported: &UnsupportedFormat) -> Self {
+        DistillWarning {
+            file: unsupported.path.clone(),
+            error: unsupported.to_string(),
+            format_version: unsupported.version,
+        }
+    }
+}
+
+/// Schematic files in a format the distiller doesn't support
+pub fn unsupported(files: &[SchematicFile]) -> Vec<UnsupportedFormat> {
+    files
+        .iter()
+        .filter(|f| f.path.ends_with(".kicad_sch"))
+        .filter(|f| f.format_version.is_none_or(|v| v < MIN_SUPPORTED_VERSION))
+        .map(|f| UnsupportedFormat {
+            path: f.path.clone(),
+            version: f.format_version,
+        })
+        .collect()
+}
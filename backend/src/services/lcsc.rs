@@ -0,0 +1,285 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, error, info};
+
+use crate::services::mpn;
+use crate::services::search_cache::{RateLimiter, SearchCache};
+use crate::types::{JlcPartType, LcscPartInfo};
+
+// JLCPCB's component search covers the LCSC catalogue and reports the
+// basic/extended library classification used for assembly pricing
+const JLCPCB_SEARCH_URL: &str =
+    "https://jlcpcb.com/api/overseas-pcb-order/v1/shoppingCart/smtGood/selectSmtComponentList";
+
+/// Currency JLCPCB quotes prices in
+pub const CURRENCY: &str = "USD";
+
+// How long search results stay cached, and how many queries are kept
+const SEARCH_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const SEARCH_CACHE_ENTRIES: usize = 2_000;
+
+// Minimum spacing between requests - the endpoint is unauthenticated, so be polite
+const MIN_REQUEST_INTERVAL: Duration = Duration::from_millis(1000);
+
+static SEARCH_CACHE: Lazy<SearchCache<Vec<LcscPartInfo>>> =
+    Lazy::new(|| SearchCache::new(SEARCH_CACHE_TTL, SEARCH_CACHE_ENTRIES));
+
+static RATE_LIMITER: RateLimiter = RateLimiter::new(MIN_REQUEST_INTERVAL);
+
+static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ComponentSearchRequest {
+    keyword: String,
+    current_page: i32,
+    page_size: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComponentSearchResponse {
+    code: Option<i32>,
+    message: Option<String>,
+    data: Option<ComponentSearchData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ComponentSearchData {
+    component_page_info: Option<ComponentPageInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ComponentPageInfo {
+    list: Option<Vec<JlcComponent>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JlcComponent {
+    component_code: Option<String>,
+    component_model_en: Option<String>,
+    component_brand_en: Option<String>,
+    component_specification_en: Option<String>,
+    describe: Option<String>,
+    stock_count: Option<i64>,
+    component_library_type: Option<String>,
+    component_prices: Option<Vec<ComponentPrice>>,
+    data_manual_url: Option<String>,
+    lcsc_goods_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ComponentPrice {
+    start_number: Option<i64>,
+    product_price: Option<f64>,
+}
+
+pub struct LcscClient;
+
+impl LcscClient {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// LCSC lookups go through JLCPCB's public, unauthenticated endpoint, so
+    /// they are off unless LCSC_ENABLED=true
+    pub fn is_configured() -> bool {
+        std::env::var("LCSC_ENABLED")
+            .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false)
+    }
+
+    /// Search the LCSC/JLCPCB catalogue by keyword, MPN or LCSC part number (e.g., "C25804")
+    pub async fn search(&self, query: &str) -> Result<Vec<LcscPartInfo>> {
+        if let Some(parts) = SEARCH_CACHE.get(query) {
+            debug!("Using cached LCSC results for: {}", query);
+            return Ok(parts);
+        }
+
+        RATE_LIMITER.wait().await;
+
+        debug!("Searching LCSC for: {}", query);
+
+        let response = HTTP_CLIENT
+            .post(JLCPCB_SEARCH_URL)
+            .json(&ComponentSearchRequest {
+                keyword: query.trim().to_string(),
+                current_page: 1,
+                page_size: 10,
+            })
+            .send()
+            .await
+            .context("Failed to send search request to JLCPCB")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("LCSC search failed: {} - {}", status, body);
+            anyhow::bail!("LCSC search failed: {} - {}", status, body);
+        }
+
+        let search_response: ComponentSearchResponse = response
+            .json()
+            .await
+            .context("Failed to parse JLCPCB search response")?;
+
+        if search_response.code.unwrap_or(200) != 200 {
+            anyhow::bail!(
+                "LCSC search failed: {}",
+                search_response.message.unwrap_or_default()
+            );
+        }
+
+        let parts: Vec<LcscPartInfo> = search_response
+            .data
+            .and_then(|d| d.component_page_info)
+            .and_then(|p| p.list)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(Self::convert_component)
+            .collect();
+
+        info!("LCSC search returned {} parts for {}", parts.len(), query);
+
+        SEARCH_CACHE.insert(query, parts.clone());
+
+        Ok(parts)
+    }
+
+    /// Find the part with exactly this MPN or LCSC part number.
+    /// MPNs are compared after [`mpn::normalize`]; search hits matching neither
+    /// number are ignored, so `None` means LCSC doesn't list the part.
+    pub async fn lookup(&self, part_number: &str) -> Result<Option<LcscPartInfo>> {
+        let parts = self.search(part_number).await?;
+        Ok(parts.into_iter().find(|p| {
+            p.lcsc_part_number.eq_ignore_ascii_case(part_number.trim())
+                || p.manufacturer_part_number
+                    .as_deref()
+                    .map(|m| mpn::same_part(m, part_number))
+                    .unwrap_or(false)
+        }))
+    }
+
+    /// Convert a JLCPCB component to our internal representation
+    fn convert_component(component: JlcComponent) -> Option<LcscPartInfo> {
+        let lcsc_part_number = component.component_code?;
+
+        let jlc_part_type = match component.component_library_type.as_deref() {
+            Some("base") => Some(JlcPartType::Basic),
+            Some("expand") => Some(JlcPartType::Extended),
+            _ => None,
+        };
+
+        // Unit price is the single-quantity price break
+        let unit_price = component.component_prices.and_then(|prices| {
+            prices
+                .into_iter()
+                .min_by_key(|p| p.start_number.unwrap_or(i64::MAX))
+                .and_then(|p| p.product_price)
+        });
+
+        Some(LcscPartInfo {
+            lcsc_part_number,
+            manufacturer_part_number: component.component_model_en,
+            manufacturer: component.component_brand_en,
+            description: component.describe,
+            package: component.component_specification_en,
+            quantity_available: component.stock_count,
+            unit_price,
+            jlc_part_type,
+            datasheet_url: component.data_manual_url,
+            product_url: component.lcsc_goods_url,
+        })
+    }
+}
+
+impl Default for LcscClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(json: serde_json::Value) -> JlcComponent {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn converts_a_jlcpcb_component() {
+        let part = LcscClient::convert_component(component(serde_json::json!({
+            "componentCode": "C25804",
+            "componentModelEn": "0603WAF1002T5E",
+            "componentBrandEn": "UNI-ROYAL",
+            "componentSpecificationEn": "0603",
+            "describe": "10kΩ ±1% 100mW",
+            "stockCount": 12000000,
+            "componentLibraryType": "base",
+            "componentPrices": [
+                {"startNumber": 100, "productPrice": 0.0009},
+                {"startNumber": 20, "productPrice": 0.0011}
+            ],
+            "dataManualUrl": "https://example.com/C25804.pdf",
+            "lcscGoodsUrl": "https://www.lcsc.com/product-detail/C25804.html"
+        })))
+        .unwrap();
+
+        assert_eq!(part.lcsc_part_number, "C25804");
+        assert_eq!(
+            part.manufacturer_part_number.as_deref(),
+            Some("0603WAF1002T5E")
+        );
+        assert_eq!(part.manufacturer.as_deref(), Some("UNI-ROYAL"));
+        assert_eq!(part.package.as_deref(), Some("0603"));
+        assert_eq!(part.quantity_available, Some(12_000_000));
+        assert_eq!(part.jlc_part_type, Some(JlcPartType::Basic));
+        // The price break with the smallest quantity
+        assert_eq!(part.unit_price, Some(0.0011));
+    }
+
+    #[test]
+    fn library_type_maps_to_basic_or_extended() {
+        let part_type = |library_type: &str| {
+            LcscClient::convert_component(component(serde_json::json!({
+                "componentCode": "C1",
+                "componentLibraryType": library_type
+            })))
+            .unwrap()
+            .jlc_part_type
+        };
+        assert_eq!(part_type("base"), Some(JlcPartType::Basic));
+        assert_eq!(part_type("expand"), Some(JlcPartType::Extended));
+        assert_eq!(part_type("other"), None);
+    }
+
+    #[test]
+    fn component_without_a_code_is_skipped() {
+        let part = LcscClient::convert_component(component(serde_json::json!({
+            "componentModelEn": "LM358DR2G"
+        })));
+        assert!(part.is_none());
+    }
+
+    #[test]
+    fn missing_prices_leave_the_unit_price_unset() {
+        let part = LcscClient::convert_component(component(serde_json::json!({
+            "componentCode": "C7950",
+            "componentPrices": []
+        })))
+        .unwrap();
+        assert_eq!(part.unit_price, None);
+        assert_eq!(part.jlc_part_type, None);
+    }
+}
@@ -0,0 +1,48 @@
+use once_cell::sync::Lazy;
+use sqlx::{Connection, PgConnection};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use kicad_db::jobs;
+use kicad_db::PgPool;
+
+// Advisory lock key held by the replica that runs the schedulers ("grok")
+const LEADER_LOCK_KEY: i64 = 0x6772_6f6b;
+
+// Connection holding the leader lock, kept out of the pool so it isn't reused
+static LEADER: Lazy<Mutex<Option<PgConnection>>> = Lazy::new(|| Mutex::new(None));
+
+/// Whether this process should run scheduled work (summary refresh, outbox
+/// relay, digests, branch checks). With several API replicas only the one
+/// holding a Postgres advisory lock does; the others check again on their
+/// next pass and take over if the leader goes away.
+pub async fn is_leader(pool: &PgPool) -> bool {
+    let mut leader = LEADER.lock().await;
+    if let Some(conn) = leader.as_mut() {
+        if conn.ping().await.is_ok() {
+            return true;
+        }
+        warn!("Lost the scheduler leader connection; the lock is released");
+        *leader = None;
+    }
+
+    let mut conn = match pool.acquire().await {
+        Ok(conn) => conn.detach(),
+        Err(e) => {
+            warn!("Failed to get a connection for the scheduler lock: {}", e);
+            return false;
+        }
+    };
+    match jobs::try_session_lock(&mut conn, LEADER_LOCK_KEY).await {
+        Ok(true) => {
+            info!("This process now runs the schedulers");
+            *leader = Some(conn);
+            true
+        }
+        Ok(false) => false,
+        Err(e) => {
+            warn!("Failed to take the scheduler lock: {}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,343 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::services::git;
+use crate::services::github;
+
+static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(300))
+        .user_agent("grokicad")
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+const LFS_MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+// Pointer files are small text files; anything larger is real content
+const MAX_POINTER_BYTES: usize = 1024;
+
+/// Largest LFS object downloaded (LFS_MAX_OBJECT_BYTES, default 200 MiB)
+fn max_object_bytes() -> u64 {
+    std::env::var("LFS_MAX_OBJECT_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(200 * 1024 * 1024)
+}
+
+/// A git LFS pointer file: the SHA-256 and size of the real content
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LfsPointer {
+    pub oid: String,
+    pub size: u64,
+}
+
+/// Parse a blob as an LFS pointer file
+pub fn parse_pointer(content: &[u8]) -> Option<LfsPointer> {
+    if content.len() > MAX_POINTER_BYTES {
+        return None;
+    }
+    let text = std::str::from_utf8(content).ok()?;
+    let mut lines = text.lines();
+    let version = lines.next()?.strip_prefix("version ")?;
+    if !version.starts_with("https://git-lfs.github.com/spec/")
+        && !version.starts_with("https://hawser.github.com/spec/")
+    {
+        return None;
+    }
+
+    let mut oid = None;
+    let mut size = None;
+    for line in lines {
+        if let Some(hash) = line.strip_prefix("oid sha256:") {
+            oid = Some(hash.trim().to_ascii_lowercase());
+        } else if let Some(bytes) = line.strip_prefix("size ") {
+            size = bytes.trim().parse().ok();
+        }
+    }
+    let oid = oid.filter(|o| o.len() == 64 && o.chars().all(|c| c.is_ascii_hexdigit()))?;
+    Some(LfsPointer { oid, size: size? })
+}
+
+/// Local store of downloaded objects, shared by every repository since
+/// objects are content-addressed
+fn cache_path(oid: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join("kicad-lfs-cache")
+        .join(&oid[..2])
+        .join(oid)
+}
+
+#[derive(Debug, Serialize)]
+struct BatchRequest<'a> {
+    operation: &'a str,
+    transfers: [&'a str; 1],
+    objects: &'a [LfsPointer],
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchResponse {
+    #[serde(default)]
+    objects: Vec<BatchObject>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchObject {
+    oid: String,
+    #[serde(default)]
+    actions: Option<BatchActions>,
+    #[serde(default)]
+    error: Option<BatchError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchActions {
+    download: Option<BatchAction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchAction {
+    href: String,
+    #[serde(default)]
+    header: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchError {
+    code: u16,
+    message: String,
+}
+
+/// Ask the repository's LFS server (GitHub's, per the cache's remote) where
+/// to download `pointers` from
+async fn batch_download_actions(
+    repo_slug: &str,
+    pointers: &[LfsPointer],
+) -> Result<HashMap<String, BatchAction>> {
+    let url = format!("{}/info/lfs/objects/batch", github::repo_url(repo_slug));
+    let mut request = HTTP_CLIENT
+        .post(&url)
+        .header("Accept", LFS_MEDIA_TYPE)
+        .header("Content-Type", LFS_MEDIA_TYPE)
+        .json(&BatchRequest {
+            operation: "download",
+            transfers: ["basic"],
+            objects: pointers,
+        });
+    if let Some((username, password)) = git::credentials() {
+        request = request.basic_auth(username, Some(password));
+    }
+
+    let response = request.send().await.context("LFS batch request failed")?;
+    if !response.status().is_success() {
+        anyhow::bail!("LFS batch request returned {}", response.status());
+    }
+    let batch: BatchResponse = response
+        .json()
+        .await
+        .context("Invalid LFS batch response")?;
+
+    let mut actions = HashMap::new();
+    for object in batch.objects {
+        if let Some(error) = object.error {
+            anyhow::bail!(
+                "LFS object {} unavailable ({}): {}",
+                object.oid,
+                error.code,
+                error.message
+            );
+        }
+        if let Some(download) = object.actions.and_then(|a| a.download) {
+            actions.insert(object.oid, download);
+        }
+    }
+    Ok(actions)
+}
+
+/// Download one object and check it against its pointer. The body is read
+/// in chunks and refused as soon as it runs past the pointer's size.
+async fn download(pointer: &LfsPointer, action: &BatchAction) -> Result<Vec<u8>> {
+    let mut request = HTTP_CLIENT.get(&action.href);
+    for (name, value) in &action.header {
+        request = request.header(name, value);
+    }
+    let mut response = request.send().await.context("LFS download failed")?;
+    if !response.status().is_success() {
+        anyhow::bail!("LFS download returned {}", response.status());
+    }
+    let too_large = |received: u64| {
+        anyhow::anyhow!(
+            "LFS object {} has at least {} bytes, expected {}",
+            pointer.oid,
+            received,
+            pointer.size
+        )
+    };
+    if let Some(length) = response.content_length().filter(|l| *l > pointer.size) {
+        return Err(too_large(length));
+    }
+
+    let mut bytes = Vec::with_capacity(pointer.size as usize);
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = response.chunk().await.context("LFS download failed")? {
+        let received = (bytes.len() + chunk.len()) as u64;
+        if received > pointer.size {
+            return Err(too_large(received));
+        }
+        hasher.update(&chunk);
+        bytes.extend_from_slice(&chunk);
+    }
+
+    if bytes.len() as u64 != pointer.size {
+        anyhow::bail!(
+            "LFS object {} has {} bytes, expected {}",
+            pointer.oid,
+            bytes.len(),
+            pointer.size
+        );
+    }
+    if hex::encode(hasher.finalize()) != pointer.oid {
+        anyhow::bail!("LFS object {} failed its checksum", pointer.oid);
+    }
+    Ok(bytes)
+}
+
+/// Content of LFS objects by oid: from the local cache, otherwise downloaded
+/// through the batch API and cached
+pub async fn fetch_objects(
+    repo_slug: &str,
+    pointers: &[LfsPointer],
+) -> Result<HashMap<String, Vec<u8>>> {
+    let mut objects = HashMap::new();
+    let mut missing: Vec<LfsPointer> = Vec::new();
+
+    for pointer in pointers {
+        if objects.contains_key(&pointer.oid) || missing.contains(pointer) {
+            continue;
+        }
+        if pointer.size > max_object_bytes() {
+            anyhow::bail!(
+                "LFS object {} is {} bytes, over the {} byte limit (LFS_MAX_OBJECT_BYTES)",
+                pointer.oid,
+                pointer.size,
+                max_object_bytes()
+            );
+        }
+        match tokio::fs::read(cache_path(&pointer.oid)).await {
+            Ok(content) if content.len() as u64 == pointer.size => {
+                objects.insert(pointer.oid.clone(), content);
+            }
+            _ => missing.push(pointer.clone()),
+        }
+    }
+    if missing.is_empty() {
+        return Ok(objects);
+    }
+
+    let actions = batch_download_actions(repo_slug, &missing).await?;
+    for pointer in &missing {
+        let action = actions
+            .get(&pointer.oid)
+            .with_context(|| format!("LFS server has no download for {}", pointer.oid))?;
+        let content = download(pointer, action).await?;
+
+        // Write then rename, so readers never see a partial object
+        let path = cache_path(&pointer.oid);
+        let stored = async {
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            let partial = path.with_extension("partial");
+            tokio::fs::write(&partial, &content).await?;
+            tokio::fs::rename(&partial, &path).await
+        };
+        if let Err(e) = stored.await {
+            warn!("Failed to cache LFS object {}: {}", pointer.oid, e);
+        }
+        objects.insert(pointer.oid.clone(), content);
+    }
+
+    info!("Fetched {} LFS object(s) for {}", missing.len(), repo_slug);
+    Ok(objects)
+}
+
+/// Replace LFS pointer files among `contents` with the real file content.
+/// Returns how many files were pointers.
+pub async fn resolve_pointers(repo_slug: &str, contents: Vec<&mut String>) -> Result<usize> {
+    let pointers: Vec<(&mut String, LfsPointer)> = contents
+        .into_iter()
+        .filter_map(|content| parse_pointer(content.as_bytes()).map(|p| (content, p)))
+        .collect();
+    if pointers.is_empty() {
+        return Ok(0);
+    }
+
+    let wanted: Vec<LfsPointer> = pointers.iter().map(|(_, p)| p.clone()).collect();
+    let objects = fetch_objects(repo_slug, &wanted)
+        .await
+        .with_context(|| format!("Failed to fetch git LFS content for {}", repo_slug))?;
+
+    let count = pointers.len();
+    for (content, pointer) in pointers {
+        if let Some(object) = objects.get(&pointer.oid) {
+            *content = String::from_utf8_lossy(object).to_string();
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OID: &str = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    fn pointer_text(oid: &str, size: &str) -> String {
+        format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize {}\n",
+            oid, size
+        )
+    }
+
+    #[test]
+    fn parses_a_valid_pointer() {
+        let pointer = parse_pointer(pointer_text(OID, "12345").as_bytes());
+        assert_eq!(
+            pointer,
+            Some(LfsPointer {
+                oid: OID.to_string(),
+                size: 12345,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_a_non_hex_oid() {
+        let oid = format!("{}zz", &OID[..62]);
+        assert_eq!(parse_pointer(pointer_text(&oid, "12345").as_bytes()), None);
+    }
+
+    #[test]
+    fn rejects_an_oversized_pointer() {
+        let mut text = pointer_text(OID, "12345");
+        text.push_str(&"x".repeat(MAX_POINTER_BYTES));
+        assert_eq!(parse_pointer(text.as_bytes()), None);
+    }
+
+    #[test]
+    fn rejects_a_pointer_without_a_size() {
+        let text = format!(
+            "version https://git-lfs.github.com/spec/v1\noid sha256:{}\n",
+            OID
+        );
+        assert_eq!(parse_pointer(text.as_bytes()), None);
+        assert_eq!(parse_pointer(pointer_text(OID, "many").as_bytes()), None);
+    }
+}
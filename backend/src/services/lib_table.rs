@@ -0,0 +1,58 @@
+/// The value of `(field ...)` in an s-expression fragment, quoted or bare
+fn sexpr_field(fragment: &str, field: &str) -> Option<String> {
+    let start = fragment.find(&format!("({} ", field))? + field.len() + 2;
+    let rest = fragment[start..].trim_start();
+    if let Some(quoted) = rest.strip_prefix('"') {
+        quoted.find('"').map(|end| quoted[..end].to_string())
+    } else {
+        let end = rest.find([')', ' ']).unwrap_or(rest.len());
+        Some(rest[..end].to_string())
+    }
+}
+
+/// (nickname, uri) for every library in an fp-lib-table or sym-lib-table
+pub fn parse(content: &str) -> Vec<(String, String)> {
+    content
+        .split("(lib ")
+        .skip(1)
+        .filter_map(|entry| Some((sexpr_field(entry, "name")?, sexpr_field(entry, "uri")?)))
+        .collect()
+}
+
+/// Join a relative path onto a directory, resolving "." and ".."
+fn join_path(dir: &str, relative: &str) -> String {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in relative.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Where a library table entry points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LibraryLocation {
+    /// A path inside the repository (`${KIPRJMOD}/...`), relative to the repository root
+    Project(String),
+    /// One of KiCad's bundled library directories
+    KiCad,
+    /// Anywhere else (absolute paths, user environment variables)
+    External,
+}
+
+/// Resolve a library table uri, given the path of the table within the repository
+pub fn locate(table_path: &str, uri: &str) -> LibraryLocation {
+    let table_dir = table_path.rsplit_once('/').map(|(d, _)| d).unwrap_or("");
+    if let Some(relative) = uri.strip_prefix("${KIPRJMOD}") {
+        LibraryLocation::Project(join_path(table_dir, relative))
+    } else if uri.contains("_FOOTPRINT_DIR}") || uri.contains("_SYMBOL_DIR}") {
+        LibraryLocation::KiCad
+    } else {
+        LibraryLocation::External
+    }
+}
@@ -0,0 +1,153 @@
+use std::sync::Arc;
+use tracing::warn;
+
+use kicad_db::llm_usage::record_usage;
+use kicad_db::messages::ChatCompletionRequest;
+use kicad_db::xai_client::{ResponsesUsage, StreamUsage, Usage};
+use kicad_db::PgPool;
+
+use crate::quota;
+
+// Default xAI prices in USD per million tokens, overridable with
+// LLM_PROMPT_PRICE_PER_MTOK / LLM_COMPLETION_PRICE_PER_MTOK
+const DEFAULT_PROMPT_PRICE_PER_MTOK: f64 = 0.20;
+const DEFAULT_COMPLETION_PRICE_PER_MTOK: f64 = 0.50;
+
+// Rough bytes per token, for estimating streams that report no usage
+const BYTES_PER_TOKEN: usize = 4;
+
+fn price(var: &str, default: f64) -> f64 {
+    std::env::var(var)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Estimated cost of a call in USD
+pub fn estimate_cost(prompt_tokens: i64, completion_tokens: i64) -> f64 {
+    let prompt = price("LLM_PROMPT_PRICE_PER_MTOK", DEFAULT_PROMPT_PRICE_PER_MTOK);
+    let completion = price(
+        "LLM_COMPLETION_PRICE_PER_MTOK",
+        DEFAULT_COMPLETION_PRICE_PER_MTOK,
+    );
+    (prompt_tokens as f64 * prompt + completion_tokens as f64 * completion) / 1_000_000.0
+}
+
+/// Record the token usage reported for an LLM call.
+///
+/// Failures are logged rather than returned; spend tracking never fails a request.
+pub async fn record(pool: &PgPool, feature: &str, model: &str, usage: Option<&ResponsesUsage>) {
+    if let Some(usage) = usage {
+        record_tokens(
+            pool,
+            feature,
+            model,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+        )
+        .await;
+    }
+}
+
+/// Record the token usage reported for a chat completion call
+pub async fn record_chat(pool: &PgPool, feature: &str, model: &str, usage: Option<&Usage>) {
+    if let Some(usage) = usage {
+        record_tokens(
+            pool,
+            feature,
+            model,
+            usage.prompt_tokens,
+            usage.completion_tokens,
+        )
+        .await;
+    }
+}
+
+async fn record_tokens(
+    pool: &PgPool,
+    feature: &str,
+    model: &str,
+    prompt_tokens: Option<u32>,
+    completion_tokens: Option<u32>,
+) {
+    let prompt_tokens = i64::from(prompt_tokens.unwrap_or(0));
+    let completion_tokens = i64::from(completion_tokens.unwrap_or(0));
+    let cost = estimate_cost(prompt_tokens, completion_tokens);
+
+    if let Err(e) = record_usage(pool, feature, model, prompt_tokens, completion_tokens, cost).await
+    {
+        warn!("Failed to record LLM usage for {}: {}", feature, e);
+    }
+    quota::charge_llm_tokens(pool, prompt_tokens + completion_tokens).await;
+}
+
+fn estimate_tokens(bytes: usize) -> u32 {
+    u32::try_from(bytes.div_ceil(BYTES_PER_TOKEN)).unwrap_or(u32::MAX)
+}
+
+/// Meters a streamed chat completion. When dropped, at the end of the stream or
+/// when the client goes away, it records the usage the final chunk reported,
+/// or an estimate from the prompt and the streamed text when there was none,
+/// and charges it to the API key of the request that started the stream.
+pub struct StreamMeter {
+    pool: Arc<PgPool>,
+    key: Option<i64>,
+    feature: &'static str,
+    model: String,
+    prompt_bytes: usize,
+    completion_bytes: usize,
+    usage: StreamUsage,
+}
+
+impl StreamMeter {
+    pub fn new(
+        pool: Arc<PgPool>,
+        feature: &'static str,
+        request: &ChatCompletionRequest,
+        usage: StreamUsage,
+    ) -> Self {
+        Self {
+            pool,
+            key: quota::current_key(),
+            feature,
+            model: request.model.clone(),
+            prompt_bytes: request.messages.iter().map(|m| m.content.len()).sum(),
+            completion_bytes: 0,
+            usage,
+        }
+    }
+
+    /// Count streamed content towards the estimate
+    pub fn add(&mut self, content: &str) {
+        self.completion_bytes += content.len();
+    }
+}
+
+impl Drop for StreamMeter {
+    fn drop(&mut self) {
+        let usage = self.usage.take().unwrap_or_else(|| {
+            let (prompt, completion) = (
+                estimate_tokens(self.prompt_bytes),
+                estimate_tokens(self.completion_bytes),
+            );
+            Usage {
+                prompt_tokens: Some(prompt),
+                completion_tokens: Some(completion),
+                total_tokens: Some(prompt.saturating_add(completion)),
+            }
+        });
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            warn!("No runtime to record LLM usage for {}", self.feature);
+            return;
+        };
+        let (pool, key, feature, model) = (
+            self.pool.clone(),
+            self.key,
+            self.feature,
+            std::mem::take(&mut self.model),
+        );
+        runtime.spawn(quota::with_key(key, async move {
+            record_chat(&pool, feature, &model, Some(&usage)).await;
+        }));
+    }
+}
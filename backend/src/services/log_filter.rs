@@ -0,0 +1,58 @@
+use anyhow::{Context, Result};
+use std::sync::OnceLock;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+/// Filter used when LOG_FILTER and RUST_LOG are unset
+const DEFAULT_FILTER: &str = "info";
+
+static HANDLE: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+static STARTUP_FILTER: OnceLock<String> = OnceLock::new();
+
+/// The filter the process started with: LOG_FILTER, else RUST_LOG, in
+/// `EnvFilter` syntax (e.g. "info,kicad_backend::services::git=debug").
+/// An invalid filter falls back to "info".
+fn startup_filter() -> &'static str {
+    STARTUP_FILTER.get_or_init(|| {
+        ["LOG_FILTER", "RUST_LOG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .map(|v| v.trim().to_string())
+            .find(|v| !v.is_empty() && EnvFilter::try_new(v).is_ok())
+            .unwrap_or_else(|| DEFAULT_FILTER.to_string())
+    })
+}
+
+/// Filter layer for the API's subscriber that `set` can replace while the
+/// process runs. Must sit directly on the registry.
+pub fn reloadable() -> reload::Layer<EnvFilter, Registry> {
+    let (layer, handle) = reload::Layer::new(EnvFilter::new(startup_filter()));
+    if HANDLE.set(handle).is_err() {
+        tracing::warn!("Log filter installed twice; only the first can be changed");
+    }
+    layer
+}
+
+/// The filter in effect, or None when the subscriber has no reloadable filter
+pub fn current() -> Option<String> {
+    HANDLE.get()?.with_current(|filter| filter.to_string()).ok()
+}
+
+/// Parse filter directives, e.g. "warn,kicad_backend=debug"
+pub fn parse(directives: &str) -> Result<EnvFilter> {
+    EnvFilter::try_new(directives.trim())
+        .with_context(|| format!("Invalid log filter {:?}", directives.trim()))
+}
+
+/// Replace the filter, or restore the startup filter with None. Returns the
+/// filter now in effect.
+pub fn set(filter: Option<EnvFilter>) -> Result<String> {
+    let handle = HANDLE
+        .get()
+        .context("Logging was not set up with a reloadable filter")?;
+    let filter = filter.unwrap_or_else(|| EnvFilter::new(startup_filter()));
+    let applied = filter.to_string();
+    handle
+        .reload(filter)
+        .context("Failed to replace the log filter")?;
+    Ok(applied)
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use tracing::{info, warn};
+
+use crate::services::bom;
+use crate::services::currency::{self, BASE_CURRENCY};
+use crate::services::github;
+use crate::types::DesignMetricsPoint;
+use kicad_db::metrics::{self, NewDesignMetrics};
+use kicad_db::PgPool;
+
+pub const DEFAULT_LIMIT: i64 = 1000;
+pub const MAX_LIMIT: i64 = 5000;
+
+/// Commits backfilled per history request, so old repositories catch up
+/// over a few requests instead of stalling one
+const BACKFILL_BATCH: i64 = 200;
+
+/// Compute design metrics from distilled data. `unit_prices` holds the
+/// enriched unit price of each reference; unpriced parts add nothing to the
+/// estimated cost.
+pub fn compute(distilled: &Value, unit_prices: &HashMap<String, f64>) -> NewDesignMetrics {
+    let components: Vec<(&String, &Value)> = match distilled.get("components") {
+        Some(Value::Object(obj)) => obj.iter().filter(|(r, _)| !r.starts_with('#')).collect(),
+        _ => Vec::new(),
+    };
+
+    let sheets: BTreeSet<&str> = components
+        .iter()
+        .map(|(_, comp)| comp.get("sheet_path").and_then(Value::as_str).unwrap_or(""))
+        .collect();
+    let mpns: BTreeSet<String> = components
+        .iter()
+        .filter_map(|(reference, comp)| bom::to_bom_component(reference, comp).mpn)
+        .collect();
+    let prices: Vec<f64> = components
+        .iter()
+        .filter_map(|(reference, _)| unit_prices.get(*reference).copied())
+        .collect();
+
+    NewDesignMetrics {
+        component_count: components.len() as i32,
+        net_count: distilled
+            .get("nets")
+            .and_then(Value::as_object)
+            .map_or(0, |nets| nets.len()) as i32,
+        sheet_count: sheets.len() as i32,
+        unique_mpns: mpns.len() as i32,
+        estimated_bom_cost: (!prices.is_empty()).then(|| prices.iter().sum()),
+        priced_components: prices.len() as i32,
+    }
+}
+
+/// Compute and store the metrics of a commit. Failures are logged rather than
+/// returned, since metrics never block processing. Costs are stored in
+/// `BASE_CURRENCY`; prices that can't be converted count as unpriced.
+pub async fn record(pool: &PgPool, repo_slug: &str, commit_hash: &str, distilled: &Value) {
+    let repo_url = github::repo_url(repo_slug);
+    let unit_prices = match metrics::part_prices(pool, &repo_url, commit_hash).await {
+        Ok(prices) => {
+            let rates = currency::current_rates(pool).await;
+            prices
+                .into_iter()
+                .filter_map(|(reference, price)| {
+                    rates
+                        .convert(price.unit_price, &price.currency, BASE_CURRENCY)
+                        .map(|p| (reference, p))
+                })
+                .collect()
+        }
+        Err(e) => {
+            warn!(
+                "Failed to load part prices for {}/{}: {}",
+                repo_slug, commit_hash, e
+            );
+            HashMap::new()
+        }
+    };
+
+    let computed = compute(distilled, &unit_prices);
+    if let Err(e) = metrics::upsert_metrics(pool, &repo_url, commit_hash, &computed).await {
+        warn!(
+            "Failed to store design metrics for {}/{}: {}",
+            repo_slug, commit_hash, e
+        );
+    }
+}
+
+/// Compute metrics for commits distilled before metrics were tracked.
+/// Returns the number of commits backfilled.
+pub async fn backfill(pool: &PgPool, repo_slug: &str) -> Result<usize> {
+    let repo_url = github::repo_url(repo_slug);
+    let pending = metrics::commits_without_metrics(pool, &repo_url, BACKFILL_BATCH).await?;
+
+    for (commit_hash, distilled) in &pending {
+        record(pool, repo_slug, commit_hash, distilled).await;
+    }
+    if !pending.is_empty() {
+        info!(
+            "Backfilled design metrics for {} commit(s) of {}",
+            pending.len(),
+            repo_slug
+        );
+    }
+    Ok(pending.len())
+}
+
+/// Metrics series of a repository, oldest commit first, with costs also
+/// converted to `display_currency`. Commits cached before metrics were
+/// tracked are backfilled first.
+pub async fn history(
+    pool: &PgPool,
+    repo_slug: &str,
+    limit: i64,
+    display_currency: &str,
+) -> Result<Vec<DesignMetricsPoint>> {
+    backfill(pool, repo_slug).await?;
+
+    let repo_url = github::repo_url(repo_slug);
+    let records = metrics::metrics_history(pool, &repo_url, limit).await?;
+    let rates = currency::current_rates(pool).await;
+    Ok(records
+        .into_iter()
+        .map(|r| DesignMetricsPoint {
+            commit: r.commit_hash,
+            commit_date: r.commit_date,
+            message: r.git_message,
+            component_count: r.component_count,
+            net_count: r.net_count,
+            sheet_count: r.sheet_count,
+            unique_mpns: r.unique_mpns,
+            estimated_bom_cost: r.estimated_bom_cost,
+            currency: BASE_CURRENCY.to_string(),
+            display_estimated_bom_cost: r
+                .estimated_bom_cost
+                .and_then(|cost| rates.convert(cost, BASE_CURRENCY, display_currency)),
+            priced_components: r.priced_components,
+        })
+        .collect())
+}
@@ -0,0 +1,627 @@
+use anyhow::{Context, Result};
+use axum::{body::Body, http::StatusCode};
+use futures_util::StreamExt;
+use git2::{BranchType, Repository};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use tempfile::NamedTempFile;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::errors::AppError;
+use crate::repo_policy::{self, RepoRejected};
+use crate::services::disk;
+use crate::services::git::{self, GitFetchSettings};
+use crate::types::ApiError;
+
+// First line of a git bundle, v2 or v3
+const BUNDLE_SIGNATURES: [&[u8]; 2] = [b"# v2 git bundle\n", b"# v3 git bundle\n"];
+
+// Directory levels below a tarball's root searched for the repository, so
+// `board/.git` or `board.git/` are found as well as a repository at the root
+const MAX_SEARCH_DEPTH: usize = 2;
+
+// Entries a tarball may unpack to; a working copy with loose objects stays well below
+const MAX_TARBALL_ENTRIES: u64 = 200_000;
+
+// Unpacked size of a tarball relative to GIT_MAX_CLONE_BYTES, leaving room for
+// a working copy's checkout next to its repository
+const UNPACKED_SIZE_FACTOR: u64 = 4;
+
+// Unpacked size limit when GIT_MAX_CLONE_BYTES is disabled (8 GiB)
+const DEFAULT_MAX_UNPACKED_BYTES: u64 = 8 << 30;
+
+// Largest pax or GNU long-name record read into memory
+const MAX_HEADER_RECORD_BYTES: u64 = 1 << 20;
+
+const TAR_BLOCK: usize = 512;
+
+/// Whether repositories come from uploads instead of the network, for
+/// air-gapped installs (MIRROR_MODE=true). Nothing is cloned or fetched: each
+/// repository is served as last uploaded through `/api/repo/upload`.
+pub fn enabled() -> bool {
+    std::env::var("MIRROR_MODE")
+        .map(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// A repository that was never uploaded to this mirror. Mapped to 404 `not_uploaded`.
+#[derive(Debug)]
+pub struct NotUploaded(pub String);
+
+impl std::fmt::Display for NotUploaded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Repository {} hasn't been uploaded; this instance doesn't clone (mirror mode)",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for NotUploaded {}
+
+impl From<&NotUploaded> for AppError {
+    fn from(missing: &NotUploaded) -> Self {
+        AppError::new(
+            StatusCode::NOT_FOUND,
+            ApiError::new("not_uploaded", missing.to_string()),
+        )
+    }
+}
+
+/// An upload that isn't a git bundle or a tarball of a git repository.
+/// Mapped to 400 `invalid_upload`.
+#[derive(Debug)]
+pub struct InvalidUpload(pub String);
+
+impl std::fmt::Display for InvalidUpload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidUpload {}
+
+impl From<&InvalidUpload> for AppError {
+    fn from(invalid: &InvalidUpload) -> Self {
+        AppError::new(
+            StatusCode::BAD_REQUEST,
+            ApiError::new("invalid_upload", invalid.0.clone()),
+        )
+    }
+}
+
+/// Open the uploaded copy of a repository
+pub fn open(repo_slug: &str, cache_path: &Path) -> Result<Repository> {
+    if !cache_path.exists() {
+        return Err(NotUploaded(repo_slug.to_string()).into());
+    }
+    Repository::open(cache_path).context("Failed to open uploaded repository")
+}
+
+/// Save an uploaded archive to a temporary file. One larger than
+/// GIT_MAX_CLONE_BYTES is refused, like a clone that receives more.
+pub async fn receive(body: Body) -> Result<NamedTempFile> {
+    let max_bytes = GitFetchSettings::from_env().max_bytes;
+    let file = tempfile::Builder::new()
+        .prefix("kicad-upload-")
+        .tempfile_in(disk::work_dir())
+        .context("Failed to create upload file")?;
+    let mut out = tokio::fs::File::from_std(file.reopen()?);
+
+    let mut received = 0u64;
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.context("Failed to read upload")?;
+        received += chunk.len() as u64;
+        if let Some(max) = max_bytes.filter(|max| received > *max) {
+            return Err(RepoRejected::TooLarge(format!(
+                "Upload is larger than the {} byte limit (GIT_MAX_CLONE_BYTES)",
+                max
+            ))
+            .into());
+        }
+        out.write_all(&chunk).await?;
+    }
+    out.flush().await?;
+    Ok(file)
+}
+
+/// What an upload turned out to contain
+#[derive(Debug, Clone)]
+pub struct Uploaded {
+    /// "bundle" or "tarball"
+    pub format: &'static str,
+    pub head: String,
+    pub branches: usize,
+}
+
+/// Make an uploaded archive the repository's copy. The archive is a git bundle
+/// (`git bundle create board.bundle --all`) or a tarball, compressed or not,
+/// of a bare repository or of a working copy with its `.git` directory.
+///
+/// A tarball is checked before it is unpacked, see `check_tarball`. The
+/// upload is unpacked next to the cache and only replaces the previous
+/// copy once it opens with a HEAD commit, so a bad upload changes nothing.
+pub async fn ingest(repo_slug: &str, archive: &Path) -> Result<Uploaded> {
+    repo_policy::check(repo_slug)?;
+    disk::check_admission().await?;
+    let (repo_slug, archive) = (repo_slug.to_string(), archive.to_path_buf());
+
+    tokio::task::spawn_blocking(move || -> Result<Uploaded> {
+        let staging = tempfile::Builder::new()
+            .prefix("kicad-upload-")
+            .tempdir_in(disk::work_dir())
+            .context("Failed to create upload staging directory")?;
+
+        let (format, root) = if is_bundle(&archive)? {
+            let root = staging.path().join("repo.git");
+            run(
+                Command::new("git")
+                    .args(["clone", "--bare", "--quiet"])
+                    .arg(&archive)
+                    .arg(&root),
+                "git clone of the bundle",
+            )?;
+            ("bundle", root)
+        } else {
+            check_tarball(&archive)?;
+            let unpacked = staging.path().join("unpacked");
+            std::fs::create_dir(&unpacked)?;
+            // tar recognises gzip, bzip2 and xz compression by itself
+            run(
+                Command::new("tar")
+                    .arg("--no-same-owner")
+                    .arg("-xf")
+                    .arg(&archive)
+                    .arg("-C")
+                    .arg(&unpacked),
+                "tar",
+            )?;
+            let root = find_repository(&unpacked, MAX_SEARCH_DEPTH).ok_or_else(|| {
+                InvalidUpload("No git repository found in the archive".to_string())
+            })?;
+            ("tarball", root)
+        };
+
+        let repo = Repository::open(&root)
+            .map_err(|e| InvalidUpload(format!("Not a git repository: {}", e.message())))?;
+        // A working copy's .git directory is kept without its checkout
+        repo.config()?.set_bool("core.bare", true)?;
+        let head = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(|e| InvalidUpload(format!("Repository has no HEAD commit: {}", e.message())))?
+            .id()
+            .to_string();
+        let branches = repo.branches(Some(BranchType::Local))?.count();
+        drop(repo);
+
+        let cache_path = git::get_cache_path(&repo_slug);
+        if cache_path.exists() {
+            std::fs::remove_dir_all(&cache_path).context("Failed to remove previous upload")?;
+        }
+        std::fs::rename(&root, &cache_path).context("Failed to move upload into place")?;
+        info!(
+            "Stored uploaded {} of {} at {:?}, HEAD at {}",
+            format,
+            repo_slug,
+            cache_path,
+            &head[..8]
+        );
+
+        Ok(Uploaded {
+            format,
+            head,
+            branches,
+        })
+    })
+    .await?
+}
+
+fn is_bundle(archive: &Path) -> Result<bool> {
+    let mut start = Vec::with_capacity(16);
+    std::fs::File::open(archive)
+        .context("Failed to open upload")?
+        .take(16)
+        .read_to_end(&mut start)?;
+    Ok(BUNDLE_SIGNATURES.iter().any(|sig| start.starts_with(sig)))
+}
+
+/// Check a tarball before unpacking it: only files and directories, with
+/// relative paths that stay inside the target, no more than
+/// MAX_TARBALL_ENTRIES of them and no more unpacked bytes than the limit
+fn check_tarball(archive: &Path) -> Result<()> {
+    let max_bytes = GitFetchSettings::from_env()
+        .max_bytes
+        .map_or(DEFAULT_MAX_UNPACKED_BYTES, |max| {
+            max.saturating_mul(UNPACKED_SIZE_FACTOR)
+        });
+
+    let mut start = [0u8; 6];
+    let read = std::fs::File::open(archive)
+        .context("Failed to open upload")?
+        .read(&mut start)?;
+    let decompressor = match &start[..read] {
+        [0x1f, 0x8b, ..] => Some("gzip"),
+        [b'B', b'Z', b'h', ..] => Some("bzip2"),
+        [0xfd, b'7', b'z', b'X', b'Z', 0] => Some("xz"),
+        [0x28, 0xb5, 0x2f, 0xfd, ..] => Some("zstd"),
+        _ => None,
+    };
+
+    let Some(decompressor) = decompressor else {
+        let file = std::fs::File::open(archive).context("Failed to open upload")?;
+        return check_tar_entries(file, MAX_TARBALL_ENTRIES, max_bytes);
+    };
+    let mut child = Command::new(decompressor)
+        .arg("-dc")
+        .arg(archive)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", decompressor))?;
+    let mut stdout = child.stdout.take().expect("piped stdout");
+    let checked = check_tar_entries(&mut stdout, MAX_TARBALL_ENTRIES, max_bytes);
+    if checked.is_err() {
+        // No need to decompress the rest
+        let _ = child.kill();
+        let _ = child.wait();
+        return checked;
+    }
+    std::io::copy(&mut stdout, &mut std::io::sink())?;
+    if !child.wait()?.success() {
+        return Err(
+            InvalidUpload(format!("{} failed to decompress the upload", decompressor)).into(),
+        );
+    }
+    Ok(())
+}
+
+/// Walk the headers of an uncompressed tar stream, checking every entry
+fn check_tar_entries(mut tar: impl Read, max_entries: u64, max_bytes: u64) -> Result<()> {
+    let invalid = |message: String| -> anyhow::Error { InvalidUpload(message).into() };
+    let (mut entries, mut bytes) = (0u64, 0u64);
+    // Path and size from a preceding GNU long-name or pax header
+    let (mut long_name, mut pax_path, mut pax_size) = (None::<String>, None::<String>, None::<u64>);
+    let mut header = [0u8; TAR_BLOCK];
+
+    loop {
+        if let Err(e) = tar.read_exact(&mut header) {
+            return Err(invalid(format!("Truncated or unreadable tarball: {}", e)));
+        }
+        if header.iter().all(|b| *b == 0) {
+            return Ok(());
+        }
+        if Some(tar_checksum(&header)) != tar_number(&header[148..156]) {
+            return Err(invalid("Not a tar archive".to_string()));
+        }
+        let header_size = tar_number(&header[124..136])
+            .ok_or_else(|| invalid("Invalid entry size in tarball".to_string()))?;
+        let typeflag = header[156];
+
+        // Headers describing the next entry carry their data in the archive
+        if matches!(typeflag, b'x' | b'g' | b'L' | b'K') {
+            if header_size > MAX_HEADER_RECORD_BYTES {
+                return Err(invalid("Oversized extended header in tarball".to_string()));
+            }
+            let mut data = vec![0u8; header_size as usize];
+            tar.read_exact(&mut data)?;
+            skip_padding(&mut tar, header_size)?;
+            match typeflag {
+                b'x' => {
+                    for (key, value) in pax_records(&data) {
+                        match key {
+                            "path" => pax_path = Some(value.to_string()),
+                            "size" => pax_size = value.parse().ok(),
+                            _ => {}
+                        }
+                    }
+                }
+                b'L' => {
+                    let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+                    long_name = Some(String::from_utf8_lossy(&data[..end]).into_owned());
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let name = long_name
+            .take()
+            .or(pax_path.take())
+            .unwrap_or_else(|| ustar_name(&header));
+        let size = pax_size.take().unwrap_or(header_size);
+        if name.starts_with('/') || name.split(['/', '\\']).any(|part| part == "..") {
+            return Err(invalid(format!(
+                "Tarball entry {:?} points outside the archive",
+                name
+            )));
+        }
+        match typeflag {
+            b'0' | 0 | b'7' | b'5' => {}
+            b'1' | b'2' => {
+                return Err(invalid(format!(
+                    "Tarball entry {:?} is a link; links aren't accepted",
+                    name
+                )))
+            }
+            other => {
+                return Err(invalid(format!(
+                    "Tarball entry {:?} has unsupported type {:?}",
+                    name, other as char
+                )))
+            }
+        }
+
+        entries += 1;
+        bytes = bytes.saturating_add(size);
+        if entries > max_entries {
+            return Err(RepoRejected::TooLarge(format!(
+                "Tarball has more than {} entries",
+                max_entries
+            ))
+            .into());
+        }
+        if bytes > max_bytes {
+            return Err(RepoRejected::TooLarge(format!(
+                "Tarball unpacks to more than the {} byte limit",
+                max_bytes
+            ))
+            .into());
+        }
+
+        // Only regular files have data; a directory's size field is ignored like tar does
+        let data_size = if typeflag == b'5' { 0 } else { size };
+        let skipped = std::io::copy(&mut (&mut tar).take(data_size), &mut std::io::sink())?;
+        if skipped < data_size {
+            return Err(invalid("Truncated tarball".to_string()));
+        }
+        skip_padding(&mut tar, data_size)?;
+    }
+}
+
+fn skip_padding(tar: &mut impl Read, size: u64) -> Result<()> {
+    let padding = (TAR_BLOCK as u64 - size % TAR_BLOCK as u64) % TAR_BLOCK as u64;
+    std::io::copy(&mut tar.take(padding), &mut std::io::sink())?;
+    Ok(())
+}
+
+/// Sum of the header bytes with the checksum field counted as spaces
+fn tar_checksum(header: &[u8; TAR_BLOCK]) -> u64 {
+    header
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if (148..156).contains(&i) {
+                32
+            } else {
+                u64::from(*b)
+            }
+        })
+        .sum()
+}
+
+/// A numeric header field: octal text, or base-256 when the high bit is set
+fn tar_number(field: &[u8]) -> Option<u64> {
+    if field.first().is_some_and(|b| b & 0x80 != 0) {
+        return field[1..]
+            .iter()
+            .try_fold(u64::from(field[0] & 0x7f), |n, b| {
+                n.checked_mul(256).map(|n| n + u64::from(*b))
+            });
+    }
+    let text = std::str::from_utf8(field).ok()?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(text, 8).ok()
+}
+
+/// An entry's name from its header, joined with the ustar prefix field
+fn ustar_name(header: &[u8; TAR_BLOCK]) -> String {
+    let field = |range: std::ops::Range<usize>| {
+        let bytes = &header[range];
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        String::from_utf8_lossy(&bytes[..end]).into_owned()
+    };
+    let name = field(0..100);
+    if &header[257..262] != b"ustar" {
+        return name;
+    }
+    match field(345..500) {
+        prefix if prefix.is_empty() => name,
+        prefix => format!("{}/{}", prefix, name),
+    }
+}
+
+/// The `<length> <key>=<value>\n` records of a pax extended header
+fn pax_records(data: &[u8]) -> Vec<(&str, &str)> {
+    let mut records = Vec::new();
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|b| *b == b' ') {
+        let Some(len) = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|len| *len > space && *len <= rest.len())
+        else {
+            break;
+        };
+        let record = std::str::from_utf8(&rest[space + 1..len]).unwrap_or_default();
+        if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+            records.push((key, value));
+        }
+        rest = &rest[len..];
+    }
+    records
+}
+
+/// The git directory at or below `dir`: a bare repository, or the `.git`
+/// directory of a working copy
+fn find_repository(dir: &Path, depth: usize) -> Option<PathBuf> {
+    if dir.join("HEAD").is_file() && dir.join("objects").is_dir() {
+        return Some(dir.to_path_buf());
+    }
+    let git_dir = dir.join(".git");
+    if git_dir.is_dir() {
+        return Some(git_dir);
+    }
+    if depth == 0 {
+        return None;
+    }
+
+    let mut children: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.path())
+        .collect();
+    children.sort();
+    children
+        .iter()
+        .find_map(|child| find_repository(child, depth - 1))
+}
+
+/// Run an unpacking command, refusing the upload when it fails
+fn run(command: &mut Command, what: &str) -> Result<()> {
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {}", what))?;
+    if !output.status.success() {
+        return Err(InvalidUpload(format!(
+            "{} failed: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A tarball of `paths`, created below a scratch directory by `setup`
+    fn tarball(setup: impl Fn(&Path), paths: &[&str], extra_args: &[&str]) -> Vec<u8> {
+        let dir = tempfile::tempdir().unwrap();
+        setup(dir.path());
+        let output = Command::new("tar")
+            .args(extra_args)
+            .arg("-cf")
+            .arg("-")
+            .arg("-C")
+            .arg(dir.path())
+            .args(paths)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        output.stdout
+    }
+
+    fn check(tar: &[u8]) -> Result<()> {
+        check_tar_entries(tar, 100, 1 << 20)
+    }
+
+    fn is_invalid(result: Result<()>) -> bool {
+        result.is_err_and(|e| e.downcast_ref::<InvalidUpload>().is_some())
+    }
+
+    fn is_too_large(result: Result<()>) -> bool {
+        result.is_err_and(|e| {
+            matches!(
+                e.downcast_ref::<RepoRejected>(),
+                Some(RepoRejected::TooLarge(_))
+            )
+        })
+    }
+
+    fn repo(dir: &Path) {
+        std::fs::create_dir_all(dir.join("board/.git/objects")).unwrap();
+        std::fs::write(dir.join("board/.git/HEAD"), "ref: refs/heads/main\n").unwrap();
+        std::fs::write(dir.join("board/board.kicad_sch"), "(kicad_sch)").unwrap();
+    }
+
+    #[test]
+    fn test_check_tar_accepts_repository() {
+        assert!(check(&tarball(repo, &["board"], &[])).is_ok());
+
+        // Long names go in GNU or pax extension headers
+        let long = format!("board/{}", "n".repeat(150));
+        let setup = |dir: &Path| {
+            repo(dir);
+            std::fs::write(dir.join(&long), "x").unwrap();
+        };
+        assert!(check(&tarball(setup, &["board"], &[])).is_ok());
+        assert!(check(&tarball(setup, &["board"], &["--format=pax"])).is_ok());
+    }
+
+    #[test]
+    fn test_check_tar_rejects_links_and_escaping_paths() {
+        let symlink = |dir: &Path| {
+            repo(dir);
+            std::os::unix::fs::symlink("/etc/passwd", dir.join("board/passwd")).unwrap();
+        };
+        assert!(is_invalid(check(&tarball(symlink, &["board"], &[]))));
+
+        let hardlink = |dir: &Path| {
+            repo(dir);
+            std::fs::hard_link(dir.join("board/.git/HEAD"), dir.join("board/HEAD")).unwrap();
+        };
+        assert!(is_invalid(check(&tarball(hardlink, &["board"], &[]))));
+
+        // -P keeps names tar would otherwise strip
+        let parent = tarball(repo, &["board/../board/.git/HEAD"], &["-P"]);
+        assert!(is_invalid(check(&parent)));
+        let dir = tempfile::tempdir().unwrap();
+        repo(dir.path());
+        let absolute = dir.path().join("board/.git/HEAD");
+        let absolute = tarball(|_| {}, &[absolute.to_str().unwrap()], &["-P"]);
+        assert!(is_invalid(check(&absolute)));
+    }
+
+    #[test]
+    fn test_check_tar_limits_entries_and_size() {
+        let many = |dir: &Path| {
+            for i in 0..150 {
+                std::fs::write(dir.join(format!("f{}", i)), "x").unwrap();
+            }
+        };
+        let names: Vec<String> = (0..150).map(|i| format!("f{}", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        assert!(is_too_large(check(&tarball(many, &names, &[]))));
+
+        let big = |dir: &Path| std::fs::write(dir.join("big"), vec![0u8; 2 << 20]).unwrap();
+        assert!(is_too_large(check(&tarball(big, &["big"], &[]))));
+    }
+
+    #[test]
+    fn test_check_tarball_decompresses() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let gzipped = |tar: &[u8]| {
+            let mut encoder = GzEncoder::new(NamedTempFile::new().unwrap(), Compression::fast());
+            encoder.write_all(tar).unwrap();
+            encoder.finish().unwrap()
+        };
+        let clean = gzipped(&tarball(repo, &["board"], &[]));
+        assert!(check_tarball(clean.path()).is_ok());
+
+        let symlink = |dir: &Path| {
+            repo(dir);
+            std::os::unix::fs::symlink("/etc/passwd", dir.join("board/passwd")).unwrap();
+        };
+        let linked = gzipped(&tarball(symlink, &["board"], &[]));
+        assert!(is_invalid(check_tarball(linked.path())));
+    }
+
+    #[test]
+    fn test_check_tar_rejects_garbage() {
+        assert!(is_invalid(check(&[0x42; 1024])));
+        assert!(is_invalid(check(&[])));
+    }
+}
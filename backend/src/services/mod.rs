@@ -1,5 +1,74 @@
+pub mod alternates;
+pub mod analysis_sessions;
+pub mod audit;
+pub mod backfill;
+pub mod blob_store;
+pub mod bom;
+pub mod bom_diff;
+pub mod comments;
+pub mod commit_diff;
+pub mod commit_status;
+pub mod connectors;
+pub mod component_notes;
+pub mod component_search;
+pub mod credentials;
+pub mod currency;
+pub mod design_export;
+pub mod design_rules;
+pub mod decoupling;
+pub mod default_branch;
+pub mod deterministic_summary;
+pub mod digests;
 pub mod digikey;
+pub mod disk;
 pub mod distill;
+pub mod distiller;
+pub mod erc;
+pub mod error_log;
+pub mod footprints;
 pub mod git;
+pub mod github;
+pub mod harness;
+pub mod hook;
+pub mod impact;
+pub mod jobs;
+pub mod kicad_format;
+pub mod lcsc;
+pub mod leader;
+pub mod lfs;
+pub mod lib_table;
+pub mod llm_usage;
+pub mod log_filter;
+pub mod metrics;
+pub mod mirror;
+pub mod mpn;
+pub mod net_explain;
+pub mod outbox;
+pub mod output_filter;
+pub mod parts;
+pub mod pinmap;
+pub mod prefetch;
+pub mod presence;
+pub mod provenance;
+pub mod quarantine;
+pub mod release_notes;
+pub mod repo_cards;
+pub mod retrieval;
+pub mod risk;
+pub mod runtime_config;
+pub mod search_cache;
+pub mod secret_settings;
+pub mod secret_store;
+pub mod speech;
+pub mod staged_summary;
+pub mod report;
+pub mod summaries;
+pub mod summary_cache;
+pub mod suggestions;
+pub mod symbols;
+pub mod test_points;
+pub mod value_changes;
+pub mod verdict;
+pub mod xref;
 
 pub use git::*;
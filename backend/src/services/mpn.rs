@@ -0,0 +1,132 @@
+use once_cell::sync::Lazy;
+use serde_json::{Map, Value};
+
+/// Property names that commonly hold a manufacturer part number, in priority order.
+/// Compared with [`normalize_key`], so "Manufacturer_Part_Number" also covers
+/// "Manufacturer Part Number" and "manufacturer-part-number".
+const DEFAULT_PROPERTY_KEYS: &[&str] = &[
+    "MPN",
+    "Manufacturer Part Number",
+    "Mfr Part Number",
+    "Mfr. Part Number",
+    "Manufacturer PN",
+    "MFR_PN",
+    "Part Number",
+    "PartNo",
+    "Part No",
+];
+
+/// Packaging and ordering suffixes that don't change the part itself: tape and
+/// reel, lead-free markers and DigiKey order codes pasted into the MPN field.
+const DEFAULT_STRIP_SUFFIXES: &[&str] = &[
+    "-ND", "#TRPBF", "#PBF", "-PBF", "-T/R", "-T&R", "/TR", "-TR", "-REEL7", "-REEL", "/REEL",
+];
+
+/// Values that mean "no part number"
+const PLACEHOLDERS: &[&str] = &["~", "-", "?", "N/A", "NA", "NONE", "TBD", "DNP", "NC"];
+
+/// MPN extraction settings.
+///
+/// - `MPN_PROPERTY_KEYS`: comma-separated property names to read MPNs from, in priority order
+/// - `MPN_STRIP_SUFFIXES`: comma-separated suffixes removed from MPNs (empty to keep MPNs as written)
+struct MpnSettings {
+    keys: Vec<String>,
+    strip_suffixes: Vec<String>,
+}
+
+fn env_list(name: &str) -> Option<Vec<String>> {
+    std::env::var(name).ok().map(|v| {
+        v.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(ToString::to_string)
+            .collect()
+    })
+}
+
+static SETTINGS: Lazy<MpnSettings> = Lazy::new(|| {
+    let keys = env_list("MPN_PROPERTY_KEYS")
+        .filter(|keys| !keys.is_empty())
+        .unwrap_or_else(|| {
+            DEFAULT_PROPERTY_KEYS
+                .iter()
+                .map(|k| k.to_string())
+                .collect()
+        });
+    let mut strip_suffixes: Vec<String> = env_list("MPN_STRIP_SUFFIXES")
+        .unwrap_or_else(|| {
+            DEFAULT_STRIP_SUFFIXES
+                .iter()
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .into_iter()
+        .map(|s| s.to_ascii_uppercase())
+        .collect();
+    // Longest first so "#TRPBF" wins over "#PBF"
+    strip_suffixes.sort_by_key(|s| std::cmp::Reverse(s.len()));
+
+    MpnSettings {
+        keys: keys.iter().map(|k| normalize_key(k)).collect(),
+        strip_suffixes,
+    }
+});
+
+/// Property name reduced to lowercase letters and digits
+pub fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Normalized property names that hold MPNs, in priority order
+pub fn property_keys() -> &'static [String] {
+    &SETTINGS.keys
+}
+
+/// Canonicalize a raw MPN: whitespace removed, uppercased and packaging
+/// suffixes stripped. Returns None for empty values and placeholders.
+pub fn normalize(raw: &str) -> Option<String> {
+    let mut mpn: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_uppercase();
+    if mpn.is_empty() || PLACEHOLDERS.contains(&mpn.as_str()) {
+        return None;
+    }
+
+    // Suffixes can stack ("-TR" after "#PBF"), so strip until nothing matches
+    while let Some(suffix) = SETTINGS
+        .strip_suffixes
+        .iter()
+        .find(|s| mpn.len() > s.len() && mpn.ends_with(s.as_str()))
+    {
+        mpn.truncate(mpn.len() - suffix.len());
+    }
+    Some(mpn)
+}
+
+/// Whether two raw MPNs name the same part once both are normalized
+pub fn same_part(a: &str, b: &str) -> bool {
+    match (normalize(a), normalize(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// The canonical MPN from a component's properties, trying keys in priority order
+pub fn extract(properties: &Map<String, Value>) -> Option<String> {
+    let normalized: Vec<(String, &Value)> = properties
+        .iter()
+        .map(|(k, v)| (normalize_key(k), v))
+        .collect();
+
+    property_keys().iter().find_map(|key| {
+        normalized
+            .iter()
+            .filter(|(k, _)| k == key)
+            .find_map(|(_, v)| v.as_str().and_then(normalize))
+    })
+}
@@ -0,0 +1,304 @@
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use crate::services::provenance;
+use crate::types::{GrokNetComponent, GrokNetContext, GrokNetPin};
+use kicad_db::{
+    canonical::content_hash,
+    net_explanations::{find_explanation, store_explanation, NetExplanation, NewNetExplanation},
+    provenance::{NewProvenance, ARTIFACT_NET_EXPLANATION},
+    PgPool,
+};
+
+/// Model used for net explanations
+pub const NET_EXPLAIN_MODEL: &str = "grok-4-1-fast";
+/// Bump whenever the net explanation prompt or context changes
+pub const NET_EXPLAIN_PROMPT_VERSION: &str = "explain-net-v1";
+
+// Ground and supply nets reach most of the board; the prompt lists this many members
+const MAX_PROMPT_COMPONENTS: usize = 60;
+const MAX_NEARBY: usize = 12;
+const PASSIVE_CATEGORIES: &[&str] = &["capacitor", "resistor", "inductor"];
+const PASSIVE_PREFIXES: &[&str] = &["R", "C", "L", "FB"];
+
+pub const SYSTEM_PROMPT: &str = r#"You explain the function of a single net in a KiCad schematic.
+You are given the components with pins on the net (value, category, sheet and which pins) and passives placed near them.
+Explain what the net most likely does: what drives it, what reads or is powered by it, and how the attached and nearby passives shape it (pull-ups, filtering, decoupling, termination, dividers).
+Start with one sentence on the net's role, then the details. Say when the data leaves the function uncertain.
+Only state what the data supports. Do not invent parts or connections."#;
+
+fn text(component: &Value, field: &str) -> Option<String> {
+    component
+        .get(field)
+        .and_then(Value::as_str)
+        .filter(|v| !v.is_empty())
+        .map(ToString::to_string)
+}
+
+fn is_passive(reference: &str, component: &Value) -> bool {
+    if let Some(category) = component.get("category").and_then(Value::as_str) {
+        return PASSIVE_CATEGORIES.contains(&category);
+    }
+    PASSIVE_PREFIXES.iter().any(|prefix| {
+        reference
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+    })
+}
+
+fn component_entry(
+    reference: &str,
+    component: &Value,
+    notes: &BTreeMap<String, String>,
+) -> GrokNetComponent {
+    GrokNetComponent {
+        reference: reference.to_string(),
+        value: text(component, "value"),
+        category: text(component, "category"),
+        footprint: text(component, "footprint"),
+        sheet: text(component, "sheet_path"),
+        pins: Vec::new(),
+        distance_mm: None,
+        note: notes.get(reference).cloned(),
+    }
+}
+
+/// Pins of `component` on `net`, falling back to the pin numbers the net lists
+fn pins_on(component: &Value, net: &str, listed: &Value) -> Vec<GrokNetPin> {
+    let pins: Vec<GrokNetPin> = component
+        .get("pins")
+        .and_then(Value::as_array)
+        .map(|pins| {
+            pins.iter()
+                .filter(|pin| pin.get("net").and_then(Value::as_str) == Some(net))
+                .filter_map(|pin| {
+                    Some(GrokNetPin {
+                        number: pin.get("number").and_then(Value::as_str)?.to_string(),
+                        name: text(pin, "name").filter(|n| n != "~"),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if !pins.is_empty() {
+        return pins;
+    }
+    listed
+        .as_array()
+        .map(|numbers| {
+            numbers
+                .iter()
+                .filter_map(|n| match n {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                })
+                .map(|number| GrokNetPin { number, name: None })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Components on a net and the passives near them, with the team's notes on
+/// them, or None when the commit has no net of that name
+pub fn context(
+    distilled: &Value,
+    repo: &str,
+    commit: &str,
+    net: &str,
+    notes: &BTreeMap<String, String>,
+) -> Option<GrokNetContext> {
+    let members = distilled.get("nets")?.get(net)?.as_object()?;
+    let components = distilled.get("components").and_then(Value::as_object);
+    let component = |reference: &str| {
+        components
+            .and_then(|c| c.get(reference))
+            .cloned()
+            .unwrap_or(Value::Null)
+    };
+
+    let on_net: Vec<GrokNetComponent> = members
+        .iter()
+        .map(|(reference, listed)| {
+            let data = component(reference);
+            GrokNetComponent {
+                pins: pins_on(&data, net, listed),
+                ..component_entry(reference, &data, notes)
+            }
+        })
+        .collect();
+
+    // Closest distance of each passive off the net to any component on it
+    let mut closest: BTreeMap<String, f64> = BTreeMap::new();
+    for edge in distilled
+        .get("proximities")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let (Some(a), Some(b)) = (
+            edge.get("ref_a").and_then(Value::as_str),
+            edge.get("ref_b").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        let distance = edge
+            .get("distance_mm")
+            .and_then(Value::as_f64)
+            .unwrap_or(f64::MAX);
+        let other = match (members.contains_key(a), members.contains_key(b)) {
+            (true, false) => b,
+            (false, true) => a,
+            _ => continue,
+        };
+        if !is_passive(other, &component(other)) {
+            continue;
+        }
+        let entry = closest.entry(other.to_string()).or_insert(distance);
+        *entry = entry.min(distance);
+    }
+    let mut nearby: Vec<(String, f64)> = closest.into_iter().collect();
+    nearby.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    let nearby = nearby
+        .into_iter()
+        .take(MAX_NEARBY)
+        .map(|(reference, distance)| GrokNetComponent {
+            distance_mm: (distance != f64::MAX).then_some(distance),
+            ..component_entry(&reference, &component(&reference), notes)
+        })
+        .collect();
+
+    Some(GrokNetContext {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        net: net.to_string(),
+        components: on_net,
+        nearby,
+        reused_from: None,
+        explained_at: None,
+    })
+}
+
+/// Hash of what an explanation is generated from; the same net with the same
+/// connections, surroundings and notes at another commit hashes the same
+pub fn context_hash(context: &GrokNetContext) -> String {
+    content_hash(&json!({
+        "net": context.net,
+        "components": serde_json::to_value(&context.components).unwrap_or_default(),
+        "nearby": serde_json::to_value(&context.nearby).unwrap_or_default(),
+    }))
+}
+
+fn describe(component: &GrokNetComponent) -> String {
+    let mut line = format!(
+        "{} ({}, {})",
+        component.reference,
+        component.value.as_deref().unwrap_or("?"),
+        component.category.as_deref().unwrap_or("other")
+    );
+    if let Some(footprint) = &component.footprint {
+        line.push_str(&format!(", footprint {}", footprint));
+    }
+    if let Some(sheet) = component.sheet.as_deref().filter(|s| *s != "/") {
+        line.push_str(&format!(", sheet {}", sheet));
+    }
+    if !component.pins.is_empty() {
+        let pins: Vec<String> = component
+            .pins
+            .iter()
+            .map(|pin| match &pin.name {
+                Some(name) => format!("{} ({})", pin.number, name),
+                None => pin.number.clone(),
+            })
+            .collect();
+        line.push_str(&format!(", pins {}", pins.join(", ")));
+    }
+    if let Some(distance) = component.distance_mm {
+        line.push_str(&format!(", {:.1} mm away", distance));
+    }
+    if let Some(note) = &component.note {
+        line.push_str(&format!("; team note: {}", note));
+    }
+    line
+}
+
+/// User prompt of an explanation
+pub fn prompt(context: &GrokNetContext) -> String {
+    let mut prompt = format!(
+        "Net: {}\n\n## Components on the net ({})\n",
+        context.net,
+        context.components.len()
+    );
+    for component in context.components.iter().take(MAX_PROMPT_COMPONENTS) {
+        prompt.push_str(&format!("- {}\n", describe(component)));
+    }
+    if context.components.len() > MAX_PROMPT_COMPONENTS {
+        prompt.push_str(&format!(
+            "- ... and {} more\n",
+            context.components.len() - MAX_PROMPT_COMPONENTS
+        ));
+    }
+    if !context.nearby.is_empty() {
+        prompt.push_str("\n## Nearby passives not on the net\n");
+        for component in &context.nearby {
+            prompt.push_str(&format!("- {}\n", describe(component)));
+        }
+    }
+    prompt
+}
+
+/// Stored explanation generated from the same context, if any
+pub async fn find(pool: &PgPool, repo_url: &str, net: &str, hash: &str) -> Option<NetExplanation> {
+    find_explanation(pool, repo_url, net, hash, NET_EXPLAIN_PROMPT_VERSION)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to look up explanation of net {}: {}", net, e);
+            None
+        })
+}
+
+/// Store a generated explanation for reuse and record how it was produced
+pub async fn store(
+    pool: &PgPool,
+    repo_url: &str,
+    context: &GrokNetContext,
+    hash: &str,
+    prompt: &str,
+    explanation: &str,
+) {
+    let new = NewNetExplanation {
+        repo_url,
+        commit_hash: &context.commit,
+        net_name: &context.net,
+        context_sha256: hash,
+        explanation,
+        model: NET_EXPLAIN_MODEL,
+        prompt_version: NET_EXPLAIN_PROMPT_VERSION,
+    };
+    if let Err(e) = store_explanation(pool, &new).await {
+        warn!(
+            "Failed to store explanation of net {} at {}: {}",
+            context.net, context.commit, e
+        );
+        return;
+    }
+
+    let full_prompt = format!("{}\n\n{}", SYSTEM_PROMPT, prompt);
+    provenance::record(
+        pool,
+        &NewProvenance {
+            artifact: ARTIFACT_NET_EXPLANATION,
+            artifact_ref: &context.net,
+            repo_url: Some(repo_url),
+            commit_hash: Some(&context.commit),
+            model: NET_EXPLAIN_MODEL,
+            prompt_version: NET_EXPLAIN_PROMPT_VERSION,
+            prompt: Some(&full_prompt),
+            tool_calls: &[],
+            inputs: &[("context", hash.as_bytes())],
+            outputs: &[("explanation", explanation.as_bytes())],
+        },
+    )
+    .await;
+}
@@ -0,0 +1,191 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use reqwest::Client;
+use ring::hmac;
+use serde_json::json;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::services::{leader, secret_settings};
+use kicad_db::outbox::{self, OutboxEvent};
+use kicad_db::PgPool;
+
+// Longest a single webhook delivery may take
+const DELIVERY_TIMEOUT_SECS: i64 = 20;
+
+static HTTP_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS as u64))
+        .user_agent("grokicad")
+        .build()
+        .expect("Failed to create HTTP client")
+});
+
+// Events claimed per relay pass
+const RELAY_BATCH: i64 = 20;
+
+// An event left delivering this long was claimed by a relay that died. Twice
+// the longest a pass can take delivering its whole batch, so the last events of
+// a slow but live pass aren't requeued and sent twice.
+const STALE_CLAIM_SECS: i64 = 2 * RELAY_BATCH * DELIVERY_TIMEOUT_SECS;
+
+// Retry delays double from the base up to the cap
+const RETRY_BASE_SECS: i64 = 30;
+const RETRY_MAX_SECS: i64 = 6 * 60 * 60;
+
+// Delivered and failed events are kept this long for inspection
+const RETENTION_SECS: i64 = 7 * 24 * 60 * 60;
+
+// Pruning runs on every Nth pass
+const PRUNE_EVERY_PASSES: u64 = 100;
+
+/// Delivery of outbox events to a notification webhook.
+///
+/// - `NOTIFICATION_WEBHOOK_URL`: endpoint events are POSTed to (unset: events are only pruned)
+/// - `NOTIFICATION_WEBHOOK_SECRET`: signs each body as `X-Grokicad-Signature: sha256=<hex HMAC>`;
+///   read at each delivery, so one stored via the admin API applies without a restart
+/// - `OUTBOX_RELAY_INTERVAL_SECS`: time between relay passes (default 10; 0 disables the relay)
+#[derive(Clone)]
+pub struct RelaySettings {
+    pub webhook_url: Option<String>,
+    pub interval: Option<Duration>,
+}
+
+impl RelaySettings {
+    pub fn from_env() -> Self {
+        let read = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let interval = match read("OUTBOX_RELAY_INTERVAL_SECS")
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(10)
+        {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        Self {
+            webhook_url: read("NOTIFICATION_WEBHOOK_URL"),
+            interval,
+        }
+    }
+}
+
+/// Delay before the next delivery attempt of an event that has failed `attempts` times
+fn retry_delay_secs(attempts: i32) -> i64 {
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    RETRY_BASE_SECS
+        .saturating_mul(1 << exponent)
+        .min(RETRY_MAX_SECS)
+}
+
+/// POST one event to the webhook. Any non-2xx response is a failed delivery.
+async fn deliver(url: &str, event: &OutboxEvent) -> Result<()> {
+    let body = serde_json::to_vec(&json!({
+        "id": event.id,
+        "type": event.event_type,
+        "created_at": event.created_at,
+        "payload": event.payload,
+    }))?;
+
+    let mut request = HTTP_CLIENT
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header("X-Grokicad-Event", &event.event_type)
+        .header("X-Grokicad-Delivery", event.id.to_string());
+    if let Some(secret) = secret_settings::get("NOTIFICATION_WEBHOOK_SECRET") {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let signature = hmac::sign(&key, &body);
+        request = request.header(
+            "X-Grokicad-Signature",
+            format!("sha256={}", hex::encode(signature.as_ref())),
+        );
+    }
+
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .context("Webhook request failed")?;
+    if !response.status().is_success() {
+        anyhow::bail!("Webhook responded with {}", response.status());
+    }
+    Ok(())
+}
+
+/// Deliver one batch of pending events. Returns (delivered, failed).
+pub async fn relay_pending(pool: &PgPool, settings: &RelaySettings) -> Result<(usize, usize)> {
+    let Some(url) = &settings.webhook_url else {
+        return Ok((0, 0));
+    };
+
+    let requeued = outbox::requeue_stale_events(pool, STALE_CLAIM_SECS).await?;
+    if requeued > 0 {
+        warn!("Requeued {} outbox event(s) from a stalled relay", requeued);
+    }
+
+    let mut delivered = 0;
+    let mut failed = 0;
+    for event in outbox::claim_events(pool, RELAY_BATCH).await? {
+        match deliver(url, &event).await {
+            Ok(()) => {
+                outbox::mark_delivered(pool, event.id).await?;
+                delivered += 1;
+            }
+            Err(e) => {
+                let delay = retry_delay_secs(event.attempts);
+                if event.attempts >= event.max_attempts {
+                    error!(
+                        "Giving up on outbox event {} ({}) after {} attempts: {:#}",
+                        event.id, event.event_type, event.attempts, e
+                    );
+                } else {
+                    warn!(
+                        "Outbox event {} ({}) failed, retrying in {}s: {:#}",
+                        event.id, event.event_type, delay, e
+                    );
+                }
+                outbox::mark_failed(pool, event.id, &format!("{:#}", e), delay).await?;
+                failed += 1;
+            }
+        }
+    }
+    Ok((delivered, failed))
+}
+
+/// Periodically deliver outbox events in the background, pruning old ones.
+/// Without a webhook URL, events are kept for the retention period and then dropped.
+pub fn spawn_relay(pool: PgPool, settings: RelaySettings) {
+    let Some(interval) = settings.interval else {
+        info!("Outbox relay disabled (OUTBOX_RELAY_INTERVAL_SECS=0)");
+        return;
+    };
+    if settings.webhook_url.is_none() {
+        info!("NOTIFICATION_WEBHOOK_URL is not set; outbox events will not be delivered");
+    }
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        let mut passes: u64 = 0;
+        loop {
+            ticker.tick().await;
+            if !leader::is_leader(&pool).await {
+                continue;
+            }
+            match relay_pending(&pool, &settings).await {
+                Ok((delivered, failed)) if delivered + failed > 0 => {
+                    info!("Outbox relay: delivered={}, failed={}", delivered, failed)
+                }
+                Ok(_) => {}
+                Err(e) => error!("Outbox relay failed: {:#}", e),
+            }
+
+            if passes.is_multiple_of(PRUNE_EVERY_PASSES) {
+                let include_pending = settings.webhook_url.is_none();
+                match outbox::prune_events(&pool, RETENTION_SECS, include_pending).await {
+                    Ok(pruned) if pruned > 0 => info!("Pruned {} old outbox event(s)", pruned),
+                    Ok(_) => {}
+                    Err(e) => error!("Outbox prune failed: {:#}", e),
+                }
+            }
+            passes = passes.wrapping_add(1);
+        }
+    });
+}
@@ -0,0 +1,497 @@
+use once_cell::sync::Lazy;
+use tracing::{info, warn};
+
+use crate::services::runtime_config;
+
+/// Filters run when LLM_OUTPUT_FILTERS is unset
+const DEFAULT_FILTERS: &str = "redact";
+const DEFAULT_MAX_CHARS: usize = 20_000;
+
+/// Appended where `max_length` cut the output short
+const TRUNCATED_MARKER: &str = "\n\n[Output truncated]";
+const REDACTED_EMAIL: &str = "[redacted email]";
+const REDACTED_KEY: &str = "[redacted key]";
+const REDACTED_SECRET: &str = "[redacted]";
+
+// Configured secrets shorter than this are too likely to occur in ordinary text
+const MIN_SECRET_LEN: usize = 8;
+
+// A streamed chunk held back waiting for a word or line to end is let through
+// past this size, so a run without whitespace can't hold up the stream
+const MAX_PENDING: usize = 4096;
+
+/// Prefixes of API keys and tokens, each followed by at least 16 key characters
+const KEY_PREFIXES: &[&str] = &[
+    "xai-",
+    "sk-",
+    "sk_live_",
+    "rk_live_",
+    "ghp_",
+    "gho_",
+    "ghu_",
+    "ghs_",
+    "github_pat_",
+    "glpat-",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+];
+
+/// Words masked by `profanity`, matched as whole words ignoring case
+const BLOCKED_WORDS: &[&str] = &[
+    "asshole",
+    "bastard",
+    "bitch",
+    "bullshit",
+    "cunt",
+    "dickhead",
+    "fuck",
+    "fucked",
+    "fucker",
+    "fucking",
+    "motherfucker",
+    "shit",
+    "shitty",
+];
+
+static CHAIN: Lazy<FilterChain> = Lazy::new(|| {
+    let chain = FilterChain::from_env();
+    info!("LLM output filters: {:?}", chain.names());
+    chain
+});
+
+/// One step of the filter chain run on LLM output
+pub trait OutputFilter: Send + Sync {
+    /// Name in LLM_OUTPUT_FILTERS
+    fn name(&self) -> &'static str;
+
+    /// Filter `text`. When streaming, `text` continues output of which this
+    /// step already returned `emitted` characters; a whole text starts at 0.
+    fn apply(&self, text: &str, emitted: usize) -> String;
+
+    /// Whether the step must see whole lines; otherwise whole words will do
+    fn needs_lines(&self) -> bool {
+        false
+    }
+}
+
+/// Filters run in order on everything an LLM produces, before it is stored
+/// or sent to a client. Configured per deployment:
+///
+/// - `LLM_OUTPUT_FILTERS`: comma-separated filters, in the order they run
+///   (default "redact"; empty for none): `strip_markdown`, `redact`,
+///   `max_length`, `profanity`
+/// - `LLM_OUTPUT_MAX_CHARS`: characters `max_length` lets through (default 20000)
+/// - `LLM_OUTPUT_BLOCKED_WORDS`: comma-separated words `profanity` masks on
+///   top of its own list
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn OutputFilter>>,
+}
+
+impl FilterChain {
+    /// A chain that lets everything through
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// This chain with `filter` run last
+    pub fn with(mut self, filter: impl OutputFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn from_env() -> Self {
+        let names =
+            std::env::var("LLM_OUTPUT_FILTERS").unwrap_or_else(|_| DEFAULT_FILTERS.to_string());
+        let max_chars = std::env::var("LLM_OUTPUT_MAX_CHARS")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(DEFAULT_MAX_CHARS);
+        let blocked_words: Vec<String> = std::env::var("LLM_OUTPUT_BLOCKED_WORDS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|w| w.trim().to_lowercase())
+            .filter(|w| !w.is_empty())
+            .collect();
+
+        let mut chain = Self::new();
+        for name in names.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            chain = match name {
+                "strip_markdown" => chain.with(StripMarkdown),
+                "redact" => chain.with(Redact::new(runtime_config::secret_values())),
+                "max_length" => chain.with(MaxLength { max_chars }),
+                "profanity" => chain.with(Profanity::new(&blocked_words)),
+                other => {
+                    warn!("Ignoring unknown LLM output filter {:?}", other);
+                    chain
+                }
+            };
+        }
+        chain
+    }
+
+    /// Names of the filters, in the order they run
+    pub fn names(&self) -> Vec<&'static str> {
+        self.filters.iter().map(|f| f.name()).collect()
+    }
+
+    /// Filter a whole text
+    pub fn apply(&self, text: &str) -> String {
+        self.filters
+            .iter()
+            .fold(text.to_string(), |text, filter| filter.apply(&text, 0))
+    }
+
+    /// Filter a text arriving in chunks
+    pub fn stream(&self) -> StreamFilter<'_> {
+        StreamFilter {
+            chain: self,
+            pending: String::new(),
+            emitted: vec![0; self.filters.len()],
+            lines: self.filters.iter().any(|f| f.needs_lines()),
+        }
+    }
+}
+
+/// Filters streamed output. Chunks are held back until a word (or, for
+/// filters that need them, a line) is complete, so nothing is split between
+/// chunks where a filter can't see it whole.
+pub struct StreamFilter<'a> {
+    chain: &'a FilterChain,
+    pending: String,
+    // Characters each filter returned so far
+    emitted: Vec<usize>,
+    lines: bool,
+}
+
+impl StreamFilter<'_> {
+    /// Add a chunk, returning the filtered output that is ready (may be empty).
+    /// Reasoning chunks (`<thinking>…</thinking>`, as the xAI client streams
+    /// them) are filtered on their own and returned straight away.
+    pub fn push(&mut self, chunk: &str) -> String {
+        if self.chain.filters.is_empty() {
+            return chunk.to_string();
+        }
+        if let Some(thinking) = chunk
+            .strip_prefix("<thinking>")
+            .and_then(|c| c.strip_suffix("</thinking>"))
+        {
+            return format!("<thinking>{}</thinking>", self.chain.apply(thinking));
+        }
+        self.pending.push_str(chunk);
+
+        let boundary = if self.lines {
+            self.pending.rfind('\n').map(|i| i + 1)
+        } else {
+            self.pending
+                .char_indices()
+                .rev()
+                .find(|(_, c)| c.is_whitespace())
+                .map(|(i, c)| i + c.len_utf8())
+        };
+        let cut = match boundary {
+            Some(cut) => cut,
+            None if self.pending.len() > MAX_PENDING => self.pending.len(),
+            None => return String::new(),
+        };
+        let ready: String = self.pending.drain(..cut).collect();
+        self.run(&ready)
+    }
+
+    /// The filtered rest of the output, once the stream has ended
+    pub fn finish(&mut self) -> String {
+        let rest = std::mem::take(&mut self.pending);
+        if rest.is_empty() {
+            return rest;
+        }
+        self.run(&rest)
+    }
+
+    fn run(&mut self, text: &str) -> String {
+        let mut text = text.to_string();
+        for (filter, emitted) in self.chain.filters.iter().zip(self.emitted.iter_mut()) {
+            text = filter.apply(&text, *emitted);
+            *emitted += text.chars().count();
+        }
+        text
+    }
+}
+
+/// Filter a whole LLM output with the configured chain
+pub fn apply(text: &str) -> String {
+    CHAIN.apply(text)
+}
+
+/// Filter a streamed LLM output with the configured chain
+pub fn stream() -> StreamFilter<'static> {
+    CHAIN.stream()
+}
+
+/// Markdown turned into plain text: headings, quotes, emphasis, code fences
+/// and rules are dropped, links keep their text and URL, images their alt text
+pub struct StripMarkdown;
+
+impl OutputFilter for StripMarkdown {
+    fn name(&self) -> &'static str {
+        "strip_markdown"
+    }
+
+    fn apply(&self, text: &str, _emitted: usize) -> String {
+        text.split_inclusive('\n')
+            .filter_map(|line| {
+                let (content, newline) = match line.strip_suffix('\n') {
+                    Some(content) => (content, "\n"),
+                    None => (line, ""),
+                };
+                let trimmed = content.trim();
+                let rule = !trimmed.is_empty() && trimmed.chars().all(|c| "-*_=|: ".contains(c));
+                if trimmed.starts_with("```") || trimmed.starts_with("~~~") || rule {
+                    return None;
+                }
+
+                let indent = &content[..content.len() - content.trim_start().len()];
+                let heading = trimmed.trim_start_matches('#');
+                let body = if heading.len() != trimmed.len() {
+                    heading.trim_start()
+                } else {
+                    trimmed.trim_start_matches('>').trim_start()
+                };
+                let body = ["* ", "+ "]
+                    .iter()
+                    .find_map(|marker| body.strip_prefix(marker))
+                    .map(|item| format!("- {}", item))
+                    .unwrap_or_else(|| body.to_string());
+                let body = if body.starts_with('|') {
+                    body.trim_matches('|').trim().to_string()
+                } else {
+                    body
+                };
+                Some(format!("{}{}{}", indent, strip_inline(&body), newline))
+            })
+            .collect()
+    }
+
+    fn needs_lines(&self) -> bool {
+        true
+    }
+}
+
+/// Links and images replaced by their text, and emphasis and code markers
+/// dropped. A lone `*` or `_` is an emphasis marker when a word is on one
+/// side of it only, so `2 * 3` and `VCC_3V3` are kept.
+fn strip_inline(line: &str) -> String {
+    let mut linked = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let (before, from) = rest.split_at(start);
+        let image = before.ends_with('!');
+        linked.push_str(before.strip_suffix('!').unwrap_or(before));
+        let link = from.find("](").and_then(|mid| {
+            let end = from[mid..].find(')')? + mid;
+            Some((&from[1..mid], &from[mid + 2..end], end))
+        });
+        match link {
+            Some((text, url, end)) => {
+                linked.push_str(text);
+                if !image && !url.is_empty() && url != text {
+                    linked.push_str(&format!(" ({})", url));
+                }
+                rest = &from[end + 1..];
+            }
+            None => {
+                linked.push('[');
+                rest = &from[1..];
+            }
+        }
+    }
+    linked.push_str(rest);
+
+    let unmarked = linked
+        .replace("**", "")
+        .replace("__", "")
+        .replace("~~", "")
+        .replace('`', "");
+    let chars: Vec<char> = unmarked.chars().collect();
+    let is_word = |i: Option<usize>| {
+        i.and_then(|i| chars.get(i))
+            .is_some_and(|c| c.is_alphanumeric())
+    };
+    chars
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| {
+            !(matches!(c, '*' | '_') && is_word(i.checked_sub(1)) != is_word(Some(i + 1)))
+        })
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Email addresses and API keys replaced, along with the values of this
+/// deployment's own secret settings, should the model echo any of them
+pub struct Redact {
+    secrets: Vec<String>,
+}
+
+impl Redact {
+    pub fn new(secrets: Vec<String>) -> Self {
+        let mut secrets: Vec<String> = secrets
+            .into_iter()
+            .filter(|s| s.chars().count() >= MIN_SECRET_LEN)
+            .collect();
+        // Longest first, so one secret containing another is replaced whole
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+        Self { secrets }
+    }
+}
+
+impl OutputFilter for Redact {
+    fn name(&self) -> &'static str {
+        "redact"
+    }
+
+    fn apply(&self, text: &str, _emitted: usize) -> String {
+        let mut text = text.to_string();
+        for secret in &self.secrets {
+            if text.contains(secret.as_str()) {
+                text = text.replace(secret.as_str(), REDACTED_SECRET);
+            }
+        }
+
+        let mut out = String::with_capacity(text.len());
+        let mut word_start = None;
+        for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+            match (c.is_whitespace(), word_start) {
+                (false, None) => word_start = Some(i),
+                (true, Some(start)) => {
+                    out.push_str(&redact_word(&text[start..i]));
+                    word_start = None;
+                }
+                _ => {}
+            }
+            if c.is_whitespace() && i < text.len() {
+                out.push(c);
+            }
+        }
+        out
+    }
+}
+
+/// A whitespace-delimited word with its email address or key replaced;
+/// surrounding punctuation is kept
+fn redact_word(word: &str) -> String {
+    let core = word.trim_matches(|c: char| "\"'()[]<>{},;:.!?*`".contains(c));
+    if core.is_empty() {
+        return word.to_string();
+    }
+    let replacement = if is_email(core.strip_prefix("mailto:").unwrap_or(core)) {
+        REDACTED_EMAIL
+    } else if is_key(core) {
+        REDACTED_KEY
+    } else {
+        return word.to_string();
+    };
+    word.replacen(core, replacement, 1)
+}
+
+fn is_email(s: &str) -> bool {
+    let Some((local, domain)) = s.split_once('@') else {
+        return false;
+    };
+    let labels: Vec<&str> = domain.split('.').collect();
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "._%+-".contains(c))
+        && labels.len() >= 2
+        && labels
+            .iter()
+            .all(|l| !l.is_empty() && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+/// A token with a known key prefix, or a long random-looking one: mixed case
+/// letters and digits. Part numbers (upper case) and hashes (hex) aren't keys.
+fn is_key(s: &str) -> bool {
+    let key_chars = |s: &str| {
+        s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_+=".contains(c))
+    };
+    if !key_chars(s) {
+        return false;
+    }
+    let prefixed = KEY_PREFIXES.iter().any(|prefix| {
+        s.strip_prefix(prefix)
+            .is_some_and(|rest| rest.len() >= 16 && rest.chars().any(|c| c.is_ascii_digit()))
+    });
+    prefixed
+        || (s.len() >= 32
+            && s.chars().any(|c| c.is_ascii_uppercase())
+            && s.chars().any(|c| c.is_ascii_lowercase())
+            && s.chars().any(|c| c.is_ascii_digit())
+            && !s.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Output cut off after a number of characters
+pub struct MaxLength {
+    pub max_chars: usize,
+}
+
+impl OutputFilter for MaxLength {
+    fn name(&self) -> &'static str {
+        "max_length"
+    }
+
+    fn apply(&self, text: &str, emitted: usize) -> String {
+        // Past the limit once the marker went out
+        let Some(room) = self.max_chars.checked_sub(emitted) else {
+            return String::new();
+        };
+        match text.char_indices().nth(room) {
+            Some((cut, _)) => format!("{}{}", &text[..cut], TRUNCATED_MARKER),
+            None => text.to_string(),
+        }
+    }
+}
+
+/// Blocked words masked with asterisks
+pub struct Profanity {
+    words: Vec<String>,
+}
+
+impl Profanity {
+    /// The built-in list and `extra` words (lowercase)
+    pub fn new(extra: &[String]) -> Self {
+        let mut words: Vec<String> = BLOCKED_WORDS.iter().map(|w| w.to_string()).collect();
+        words.extend(extra.iter().cloned());
+        Self { words }
+    }
+}
+
+impl OutputFilter for Profanity {
+    fn name(&self) -> &'static str {
+        "profanity"
+    }
+
+    fn apply(&self, text: &str, _emitted: usize) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut word = String::new();
+        for c in text.chars().map(Some).chain([None]) {
+            match c {
+                Some(c) if c.is_alphabetic() => word.push(c),
+                _ => {
+                    if self.words.contains(&word.to_lowercase()) {
+                        out.extend(std::iter::repeat_n('*', word.chars().count()));
+                    } else {
+                        out.push_str(&word);
+                    }
+                    word.clear();
+                    out.extend(c);
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use tracing::warn;
+
+use crate::services::digikey::{self, DigiKeyClient};
+use crate::services::lcsc::{self, LcscClient};
+use crate::types::PartOffer;
+
+/// A distributor that can be queried for live part availability
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartsProvider {
+    DigiKey,
+    Lcsc,
+}
+
+impl PartsProvider {
+    /// All known providers, in lookup priority order
+    pub const ALL: &'static [PartsProvider] = &[PartsProvider::DigiKey, PartsProvider::Lcsc];
+
+    /// Short identifier used in API responses
+    pub fn name(&self) -> &'static str {
+        match self {
+            PartsProvider::DigiKey => "digikey",
+            PartsProvider::Lcsc => "lcsc",
+        }
+    }
+
+    /// Parse a provider from its short identifier
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.name() == name)
+    }
+
+    /// Whether the provider authenticates with OAuth client credentials
+    pub fn uses_client_credentials(&self) -> bool {
+        match self {
+            PartsProvider::DigiKey => true,
+            PartsProvider::Lcsc => false,
+        }
+    }
+
+    /// Whether the provider has the credentials it needs
+    pub fn is_configured(&self) -> bool {
+        match self {
+            PartsProvider::DigiKey => DigiKeyClient::is_configured(),
+            PartsProvider::Lcsc => LcscClient::is_configured(),
+        }
+    }
+
+    /// Providers that are ready to be queried
+    pub fn configured() -> Vec<PartsProvider> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(|p| p.is_configured())
+            .collect()
+    }
+
+    /// Look up a manufacturer part number, returning only an exact match
+    pub async fn lookup_mpn(&self, mpn: &str) -> Result<Option<PartOffer>> {
+        match self {
+            PartsProvider::DigiKey => {
+                let part = DigiKeyClient::new().lookup_mpn(mpn).await?;
+                Ok(part.map(|p| PartOffer {
+                    provider: self.name().to_string(),
+                    distributor_part_number: p.digikey_part_number,
+                    manufacturer_part_number: p.manufacturer_part_number,
+                    manufacturer: p.manufacturer,
+                    unit_price: p.unit_price,
+                    currency: digikey::CURRENCY.to_string(),
+                    quantity_available: p.quantity_available,
+                    is_obsolete: p.is_obsolete,
+                    product_url: p.product_url,
+                }))
+            }
+            PartsProvider::Lcsc => {
+                let part = LcscClient::new().lookup(mpn).await?;
+                Ok(part.map(|p| PartOffer {
+                    provider: self.name().to_string(),
+                    distributor_part_number: Some(p.lcsc_part_number),
+                    manufacturer_part_number: p.manufacturer_part_number,
+                    manufacturer: p.manufacturer,
+                    unit_price: p.unit_price,
+                    currency: lcsc::CURRENCY.to_string(),
+                    quantity_available: p.quantity_available,
+                    is_obsolete: false,
+                    product_url: p.product_url,
+                }))
+            }
+        }
+    }
+}
+
+impl PartOffer {
+    /// Whether the part can actually be bought right now
+    pub fn is_purchasable(&self) -> bool {
+        !self.is_obsolete && self.quantity_available.unwrap_or(0) > 0
+    }
+}
+
+/// Offers for a part number from every configured provider
+#[derive(Debug, Default)]
+pub struct OfferSearch {
+    pub offers: Vec<PartOffer>,
+    /// Providers whose lookup failed, which may list the part after all
+    pub failed: Vec<PartsProvider>,
+}
+
+/// Query every configured provider for a part number.
+///
+/// Provider errors are logged and skipped so one failing distributor doesn't
+/// hide results from the others; they are listed in `failed`.
+pub async fn find_offers(mpn: &str) -> OfferSearch {
+    let mut search = OfferSearch::default();
+    for provider in PartsProvider::configured() {
+        match provider.lookup_mpn(mpn).await {
+            Ok(Some(offer)) => search.offers.push(offer),
+            Ok(None) => {}
+            Err(e) => {
+                warn!("{} lookup failed for {}: {}", provider.name(), mpn, e);
+                search.failed.push(provider);
+            }
+        }
+    }
+    search
+}
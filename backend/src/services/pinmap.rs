@@ -0,0 +1,311 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::services::commit_diff::is_generated_net;
+use crate::services::decoupling::{base_name, is_ground_net};
+use crate::services::{distill, risk};
+use crate::types::{PinMapMcu, PinMapPin, PinMapResponse};
+use kicad_db::PgPool;
+
+pub const KIND_SIGNAL: &str = "signal";
+pub const KIND_POWER: &str = "power";
+pub const KIND_GROUND: &str = "ground";
+pub const KIND_UNCONNECTED: &str = "unconnected";
+
+// Symbol name and value prefixes of common microcontroller families
+const MCU_FAMILIES: &[&str] = &[
+    "STM32", "STM8", "ATMEGA", "ATTINY", "ATSAM", "ATXMEGA", "NRF5", "ESP32", "ESP8266", "RP2040",
+    "RP2350", "PIC1", "PIC32", "DSPIC", "MSP430", "EFM32", "EFR32", "GD32", "CH32", "LPC",
+    "MIMXRT", "MK2", "MKL", "SAMD", "SAME", "CC13", "CC26",
+];
+
+/// ICs with at least this many GPIO-style pin names count as MCUs even when
+/// their family isn't known
+const MIN_GPIO_PINS: usize = 4;
+
+/// GPIO-style pin name: PA5, PB12, P0.13, P1_4, GPIO21, IO4
+fn is_gpio_name(name: &str) -> bool {
+    let name = name.split('/').next().unwrap_or(name).to_ascii_uppercase();
+    let digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if let Some(rest) = name
+        .strip_prefix("GPIO")
+        .or_else(|| name.strip_prefix("IO"))
+    {
+        return digits(rest);
+    }
+    let Some(rest) = name.strip_prefix('P') else {
+        return false;
+    };
+    let mut chars = rest.chars();
+    match chars.next() {
+        Some(port) if port.is_ascii_uppercase() => digits(chars.as_str()),
+        Some(port) if port.is_ascii_digit() => {
+            chars.as_str().strip_prefix(['.', '_']).is_some_and(digits)
+        }
+        _ => false,
+    }
+}
+
+fn field<'a>(component: &'a Value, key: &str) -> Option<&'a str> {
+    component.get(key).and_then(Value::as_str)
+}
+
+/// Pin name as written on the symbol, without KiCad's overbar markup
+/// ("~{RESET}" -> "RESET"); None for unnamed pins
+fn pin_name(pin: &Value) -> Option<String> {
+    let name = field(pin, "name").filter(|n| !n.is_empty() && *n != "~")?;
+    Some(name.replace("~{", "").replace('}', ""))
+}
+
+/// Whether a distilled component is a microcontroller: an IC from a KiCad MCU
+/// library or a known family, or with several GPIO-style pins
+pub fn is_mcu(reference: &str, component: &Value) -> bool {
+    let is_ic = field(component, "category") == Some("ic") || reference.starts_with('U');
+    if !is_ic {
+        return false;
+    }
+    let lib_id = field(component, "lib_id")
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    let (library, symbol) = lib_id.split_once(':').unwrap_or(("", &lib_id));
+    let value = field(component, "value")
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    if library.starts_with("MCU_")
+        || MCU_FAMILIES
+            .iter()
+            .any(|f| symbol.starts_with(f) || value.starts_with(f))
+    {
+        return true;
+    }
+    let gpio_pins = component
+        .get("pins")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(pin_name)
+        .filter(|name| is_gpio_name(name))
+        .count();
+    gpio_pins >= MIN_GPIO_PINS
+}
+
+/// Constant name for a labelled net: "/sensors/I2C_SDA" -> "I2C_SDA",
+/// "~{LED_EN}" -> "LED_EN_N". None for unlabelled nets.
+fn identifier(net: &str) -> Option<String> {
+    if is_generated_net(net) {
+        return None;
+    }
+    let name = base_name(net);
+    let (name, active_low) = match name.strip_prefix("~{") {
+        Some(rest) => (rest.trim_end_matches('}'), true),
+        None => (name, false),
+    };
+    let mut ident: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    ident = ident.trim_matches('_').to_string();
+    if ident.is_empty() {
+        return None;
+    }
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if active_low {
+        ident.push_str("_N");
+    }
+    Some(ident)
+}
+
+fn kind(net: Option<&str>) -> &'static str {
+    match net {
+        None => KIND_UNCONNECTED,
+        Some(net) if is_ground_net(net) => KIND_GROUND,
+        Some(net) if risk::is_power_net(base_name(net)) => KIND_POWER,
+        Some(_) => KIND_SIGNAL,
+    }
+}
+
+/// Numeric pins in numeric order, then the rest (BGA balls) alphabetically
+pub fn pin_order(number: &str) -> (bool, u64, String) {
+    match number.parse::<u64>() {
+        Ok(n) => (false, n, String::new()),
+        Err(_) => (true, 0, number.to_string()),
+    }
+}
+
+fn pins(component: &Value) -> Vec<PinMapPin> {
+    let mut pins: Vec<PinMapPin> = component
+        .get("pins")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|pin| {
+            let number = field(pin, "number")?;
+            let net = field(pin, "net").filter(|n| !n.is_empty());
+            let kind = kind(net);
+            Some(PinMapPin {
+                number: number.to_string(),
+                name: pin_name(pin),
+                net: net.map(ToString::to_string),
+                kind: kind.to_string(),
+                identifier: net.filter(|_| kind == KIND_SIGNAL).and_then(identifier),
+            })
+        })
+        .collect();
+    pins.sort_by_key(|p| pin_order(&p.number));
+
+    // Two pins on one net get distinct constants
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    for pin in &mut pins {
+        if let Some(ident) = &mut pin.identifier {
+            let count = seen.entry(ident.clone()).or_default();
+            *count += 1;
+            if *count > 1 {
+                ident.push_str(&format!("_{}", count));
+            }
+        }
+    }
+    pins
+}
+
+/// Pin maps of the MCUs in distilled data, or of the given references. Unknown
+/// references are left out.
+pub fn extract(distilled: &Value, references: Option<&[String]>) -> Vec<PinMapMcu> {
+    let Some(components) = distilled.get("components").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+    components
+        .iter()
+        .filter(|(reference, component)| match references {
+            Some(references) => references.contains(reference),
+            None => is_mcu(reference, component),
+        })
+        .map(|(reference, component)| PinMapMcu {
+            reference: reference.clone(),
+            value: field(component, "value").map(ToString::to_string),
+            lib_id: field(component, "lib_id").map(ToString::to_string),
+            sheet: field(component, "sheet_path").map(ToString::to_string),
+            pins: pins(component),
+        })
+        .collect()
+}
+
+/// Pin map of a commit
+pub async fn build(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    references: Option<&[String]>,
+) -> Result<PinMapResponse> {
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+    Ok(PinMapResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        mcus: extract(&distilled, references),
+    })
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..8.min(commit.len())]
+}
+
+/// Module or macro prefix of an MCU: "U1" -> "U1", "U$3" -> "U_3"
+fn mcu_identifier(reference: &str) -> String {
+    identifier(reference).unwrap_or_else(|| "MCU".to_string())
+}
+
+fn c_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn comment(text: &str) -> String {
+    text.replace("*/", "* /").replace('\n', " ")
+}
+
+fn describe(mcu: &PinMapMcu) -> String {
+    match &mcu.value {
+        Some(value) => format!("{} ({})", mcu.reference, value),
+        None => mcu.reference.clone(),
+    }
+}
+
+/// C header with a pad and a pin name macro per labelled signal pin:
+/// `U1_LED_R_PAD "21"`, `U1_LED_R_PIN "PA5"`
+pub fn to_c_header(pinmap: &PinMapResponse) -> String {
+    let mut out = format!(
+        "/* Pin map of {} at {}, generated from the schematic. Do not edit. */\n\n\
+         #ifndef PINMAP_H\n#define PINMAP_H\n",
+        comment(&pinmap.repo),
+        short(&pinmap.commit)
+    );
+    for mcu in &pinmap.mcus {
+        let prefix = mcu_identifier(&mcu.reference);
+        out.push_str(&format!("\n/* {} */\n", comment(&describe(mcu))));
+        for pin in &mcu.pins {
+            let (Some(ident), Some(net)) = (&pin.identifier, &pin.net) else {
+                continue;
+            };
+            out.push_str(&format!("/* {} */\n", comment(net)));
+            out.push_str(&format!(
+                "#define {}_{}_PAD {}\n",
+                prefix,
+                ident,
+                c_string(&pin.number)
+            ));
+            if let Some(name) = &pin.name {
+                out.push_str(&format!(
+                    "#define {}_{}_PIN {}\n",
+                    prefix,
+                    ident,
+                    c_string(name)
+                ));
+            }
+        }
+    }
+    out.push_str("\n#endif /* PINMAP_H */\n");
+    out
+}
+
+/// Rust module with a `Pin` constant per labelled signal pin, in a submodule
+/// per MCU: `u1::LED_R`
+pub fn to_rust_module(pinmap: &PinMapResponse) -> String {
+    let mut out = format!(
+        "//! Pin map of {} at {}, generated from the schematic. Do not edit.\n\n\
+         #[derive(Debug, Clone, Copy, PartialEq, Eq)]\n\
+         pub struct Pin {{\n    \
+             /// Package pad or ball\n    \
+             pub pad: &'static str,\n    \
+             /// Pin name on the symbol, e.g. \"PA5\"\n    \
+             pub name: Option<&'static str>,\n    \
+             pub net: &'static str,\n\
+         }}\n",
+        pinmap.repo.replace('\n', " "),
+        short(&pinmap.commit)
+    );
+    for mcu in &pinmap.mcus {
+        out.push_str(&format!(
+            "\n/// {}\n#[allow(dead_code)]\npub mod {} {{\n    use super::Pin;\n",
+            describe(mcu).replace('\n', " "),
+            mcu_identifier(&mcu.reference).to_ascii_lowercase()
+        ));
+        for pin in &mcu.pins {
+            let (Some(ident), Some(net)) = (&pin.identifier, &pin.net) else {
+                continue;
+            };
+            out.push_str(&format!(
+                "\n    pub const {}: Pin = Pin {{\n        pad: {:?},\n        name: {:?},\n        net: {:?},\n    }};\n",
+                ident, pin.number, pin.name, net
+            ));
+        }
+        out.push_str("}\n");
+    }
+    out
+}
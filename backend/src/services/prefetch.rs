@@ -0,0 +1,167 @@
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::{info, warn};
+
+use crate::demo;
+use crate::services::{distill, git, github, jobs};
+use kicad_db::jobs::{count_by_status, STATUS_QUEUED};
+use kicad_db::{read_pool, retrieve_distilled_json, PgPool};
+
+// A commit whose neighbours were prefetched isn't looked at again for this long
+const RECENT_TTL: Duration = Duration::from_secs(10 * 60);
+
+// How often a prefetch checks whether other work has finished
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+static SETTINGS: Lazy<PrefetchSettings> = Lazy::new(PrefetchSettings::from_env);
+
+// Prefetches run one at a time; ones asked for meanwhile are dropped
+static RUNNING: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(1));
+
+// Viewed commits whose neighbours were prefetched, by (lowercased repo, commit)
+static RECENT: Lazy<Mutex<HashMap<(String, String), Instant>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Speculative distillation of the schematic commits next to a viewed one.
+///
+/// - `PREFETCH_NEIGHBOURS`: commits prefetched on each side of a viewed one (default 1; 0 disables)
+/// - `PREFETCH_IDLE_WAIT_SECS`: how long a prefetch waits for other distillations to finish
+///   before giving up (default 120)
+#[derive(Debug, Clone, Copy)]
+pub struct PrefetchSettings {
+    pub neighbours: usize,
+    pub idle_wait: Duration,
+}
+
+impl PrefetchSettings {
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: u64| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .unwrap_or(default)
+        };
+        Self {
+            neighbours: read("PREFETCH_NEIGHBOURS", 1) as usize,
+            idle_wait: Duration::from_secs(read("PREFETCH_IDLE_WAIT_SECS", 120)),
+        }
+    }
+}
+
+/// Note that a commit was viewed: its neighbouring schematic commits are
+/// distilled in the background, so stepping to them is instant. Prefetching
+/// is low priority: it starts nothing while other distillations run or jobs
+/// are queued, isn't charged to the caller's quota, and is skipped when
+/// another prefetch is busy or on demo instances.
+pub fn after_view(pool: &PgPool, repo: &str, commit: &str) {
+    if SETTINGS.neighbours == 0 || demo::check_distill().is_err() {
+        return;
+    }
+    let (pool, repo, commit) = (pool.clone(), repo.to_string(), commit.to_string());
+    tokio::spawn(async move {
+        if let Err(e) = prefetch(&pool, &repo, &commit).await {
+            warn!(
+                "Failed to prefetch commits around {}/{}: {:#}",
+                repo, commit, e
+            );
+        }
+    });
+}
+
+/// Record a viewed commit, returning false if its neighbours were prefetched recently
+fn mark_recent(repo: &str, commit: &str) -> bool {
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    recent.retain(|_, at| now.duration_since(*at) < RECENT_TTL);
+    recent
+        .insert((repo.to_lowercase(), commit.to_string()), now)
+        .is_none()
+}
+
+/// Whether nothing else is being distilled: no distillation running in this
+/// process and, with the job queue, no jobs waiting for a worker
+async fn idle(pool: &PgPool) -> bool {
+    if distill::in_flight() > 0 {
+        return false;
+    }
+    if !jobs::queue_enabled() {
+        return true;
+    }
+    match count_by_status(pool).await {
+        Ok(counts) => !counts
+            .iter()
+            .any(|(status, count)| status == STATUS_QUEUED && *count > 0),
+        Err(e) => {
+            warn!("Failed to check the job queue before prefetching: {}", e);
+            false
+        }
+    }
+}
+
+/// Wait until [`idle`], for at most `PREFETCH_IDLE_WAIT_SECS`
+async fn wait_until_idle(pool: &PgPool) -> bool {
+    let started = Instant::now();
+    while !idle(pool).await {
+        if started.elapsed() > SETTINGS.idle_wait {
+            return false;
+        }
+        tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+    }
+    true
+}
+
+async fn prefetch(pool: &PgPool, repo: &str, commit: &str) -> Result<()> {
+    let Ok(_running) = RUNNING.try_acquire() else {
+        return Ok(());
+    };
+    if !mark_recent(repo, commit) {
+        return Ok(());
+    }
+
+    // Newest first; a commit that didn't touch the schematic has no place in it
+    let commits = git::get_schematic_commits(repo)
+        .await
+        .context("Failed to list schematic commits")?;
+    let Some(at) = commits.iter().position(|c| c.commit_hash == commit) else {
+        return Ok(());
+    };
+    // Nearest first, the newer side before the older
+    let neighbours: Vec<&str> = (1..=SETTINGS.neighbours)
+        .flat_map(|distance| [at.checked_sub(distance), Some(at + distance)])
+        .flatten()
+        .filter_map(|i| commits.get(i))
+        .map(|c| c.commit_hash.as_str())
+        .collect();
+
+    let repo_url = github::repo_url(repo);
+    for neighbour in neighbours {
+        if retrieve_distilled_json(read_pool(pool), &repo_url, neighbour)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+        if !wait_until_idle(pool).await {
+            info!(
+                "Stopped prefetching around {}/{}: still busy after {}s",
+                repo,
+                commit,
+                SETTINGS.idle_wait.as_secs()
+            );
+            return Ok(());
+        }
+        // Spawned requests carry no API key, so this isn't charged to anyone
+        match distill::distill_commit(pool, repo, neighbour).await {
+            Ok(_) => info!("Prefetched {}/{} next to {}", repo, neighbour, commit),
+            Err(e) if e.is::<distill::DistillPending>() => {
+                info!("Queued prefetch of {}/{} next to {}", repo, neighbour, commit)
+            }
+            Err(e) => info!("Failed to prefetch {}/{}: {:#}", repo, neighbour, e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::services::comments::ValidComment;
+use crate::types::{CommitCommentEntry, PresenceEvent, PresenceViewer};
+
+/// Viewers per commit; further connections are refused
+pub const MAX_VIEWERS_PER_ROOM: usize = 50;
+/// Component references kept from one selection
+pub const MAX_SELECTION: usize = 500;
+/// Characters kept from a display name
+pub const MAX_NAME_CHARS: usize = 64;
+
+// Events buffered per room for slow viewers; a viewer further behind gets a snapshot
+const CHANNEL_CAPACITY: usize = 64;
+
+/// The people viewing one commit and the channel their events go out on
+struct Room {
+    events: broadcast::Sender<PresenceEvent>,
+    viewers: Vec<PresenceViewer>,
+}
+
+// In memory only: presence is lost on restart, and each API instance has its own
+static ROOMS: Lazy<Mutex<HashMap<RoomKey, Room>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// A commit of a repository; slugs compare ignoring case, as on GitHub
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RoomKey {
+    repo: String,
+    commit: String,
+}
+
+impl RoomKey {
+    pub fn new(repo: &str, commit: &str) -> Self {
+        Self {
+            repo: repo.trim().to_lowercase(),
+            commit: commit.trim().to_lowercase(),
+        }
+    }
+}
+
+/// Every viewer of the commit was already taken
+#[derive(Debug)]
+pub struct RoomFull;
+
+/// A viewer's place in a room. Dropping it leaves the room.
+pub struct Session {
+    key: RoomKey,
+    pub id: String,
+    /// Display name the viewer joined with
+    pub name: Option<String>,
+    pub events: broadcast::Receiver<PresenceEvent>,
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        leave(&self.key, &self.id);
+    }
+}
+
+impl PresenceEvent {
+    /// Whether the event reports a change the viewer made itself, which isn't
+    /// sent back to it. Comments are, so their author sees the stored id.
+    pub fn is_echo(&self, session: &str) -> bool {
+        match self {
+            PresenceEvent::Leave { session: s, .. }
+            | PresenceEvent::SelectionChanged { session: s, .. } => s == session,
+            PresenceEvent::Join { viewer, .. } => viewer.session == session,
+            PresenceEvent::Snapshot { .. }
+            | PresenceEvent::Comment { .. }
+            | PresenceEvent::Error { .. } => false,
+        }
+    }
+}
+
+/// Enter a commit's room. Everyone already there is told about the new viewer;
+/// the new viewer's own join isn't sent back to it.
+pub fn join(key: RoomKey, name: Option<&str>) -> Result<Session, RoomFull> {
+    let name = name
+        .map(|n| n.trim().chars().take(MAX_NAME_CHARS).collect::<String>())
+        .filter(|n| !n.is_empty());
+    let viewer = PresenceViewer {
+        session: Uuid::new_v4().to_string(),
+        name,
+        selection: Vec::new(),
+        joined_at: Utc::now(),
+    };
+
+    let mut rooms = ROOMS.lock().unwrap();
+    let room = rooms.entry(key.clone()).or_insert_with(|| Room {
+        events: broadcast::channel(CHANNEL_CAPACITY).0,
+        viewers: Vec::new(),
+    });
+    if room.viewers.len() >= MAX_VIEWERS_PER_ROOM {
+        return Err(RoomFull);
+    }
+    room.viewers.push(viewer.clone());
+    // Nobody may be listening yet, which isn't an error
+    let _ = room.events.send(PresenceEvent::Join {
+        viewer: viewer.clone(),
+        count: room.viewers.len(),
+    });
+
+    Ok(Session {
+        key,
+        id: viewer.session,
+        name: viewer.name,
+        events: room.events.subscribe(),
+    })
+}
+
+fn leave(key: &RoomKey, session: &str) {
+    let mut rooms = ROOMS.lock().unwrap();
+    let Some(room) = rooms.get_mut(key) else {
+        return;
+    };
+    room.viewers.retain(|v| v.session != session);
+    if room.viewers.is_empty() {
+        rooms.remove(key);
+        return;
+    }
+    let _ = room.events.send(PresenceEvent::Leave {
+        session: session.to_string(),
+        count: room.viewers.len(),
+    });
+}
+
+/// Replace a viewer's selection and tell the room. References are trimmed,
+/// empty ones dropped and at most MAX_SELECTION kept.
+pub fn select(session: &Session, selection: Vec<String>) {
+    let selection: Vec<String> = selection
+        .into_iter()
+        .map(|r| r.trim().to_string())
+        .filter(|r| !r.is_empty())
+        .take(MAX_SELECTION)
+        .collect();
+
+    let mut rooms = ROOMS.lock().unwrap();
+    let Some(room) = rooms.get_mut(&session.key) else {
+        return;
+    };
+    let Some(viewer) = room.viewers.iter_mut().find(|v| v.session == session.id) else {
+        return;
+    };
+    if viewer.selection == selection {
+        return;
+    }
+    viewer.selection = selection.clone();
+    let _ = room.events.send(PresenceEvent::SelectionChanged {
+        session: session.id.clone(),
+        selection,
+    });
+}
+
+fn send(key: &RoomKey, event: PresenceEvent) {
+    if let Some(room) = ROOMS.lock().unwrap().get(key) {
+        let _ = room.events.send(event);
+    }
+}
+
+/// Send a viewer's comment to the room without storing it
+pub fn relay_comment(session: &Session, comment: ValidComment<'_>, parent_id: Option<i64>) {
+    send(
+        &session.key,
+        PresenceEvent::Comment {
+            session: Some(session.id.clone()),
+            id: None,
+            parent_id,
+            author: comment.author.to_string(),
+            body: comment.body.to_string(),
+            anchor_kind: comment.anchor_kind.map(str::to_string),
+            anchor: comment.anchor.map(str::to_string),
+            created_at: Utc::now(),
+        },
+    );
+}
+
+/// Send a stored comment to everyone viewing its commit; `session` is the
+/// viewer who pinned it, if it came over the presence channel
+pub fn broadcast_comment(key: &RoomKey, session: Option<&str>, comment: &CommitCommentEntry) {
+    send(
+        key,
+        PresenceEvent::Comment {
+            session: session.map(str::to_string),
+            id: Some(comment.id),
+            parent_id: comment.parent_id,
+            author: comment.author.clone(),
+            body: comment.body.clone(),
+            anchor_kind: comment.anchor_kind.clone(),
+            anchor: comment.anchor.clone(),
+            created_at: comment.created_at,
+        },
+    );
+}
+
+/// Repository and commit of the viewer's room, as given when joining
+pub fn room_of(session: &Session) -> &RoomKey {
+    &session.key
+}
+
+/// Everyone viewing a commit, earliest first
+pub fn viewers(key: &RoomKey) -> Vec<PresenceViewer> {
+    ROOMS
+        .lock()
+        .unwrap()
+        .get(key)
+        .map(|room| room.viewers.clone())
+        .unwrap_or_default()
+}
+
+/// The room as the given viewer should see it now
+pub fn snapshot(session: &Session) -> PresenceEvent {
+    PresenceEvent::Snapshot {
+        session: session.id.clone(),
+        viewers: viewers(&session.key),
+    }
+}
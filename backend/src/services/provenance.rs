@@ -0,0 +1,78 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::warn;
+
+use crate::types::{ProvenanceEntry, ProvenanceToolCall};
+use kicad_db::provenance::{record_provenance, NewProvenance, ProvenanceRecord, ToolCall};
+use kicad_db::xai_client::ResponsesOutput;
+use kicad_db::PgPool;
+
+/// Record how a stored AI artifact was produced, returning the record's id.
+/// Failures are logged, never surfaced: the artifact is already stored.
+pub async fn record(pool: &PgPool, new: &NewProvenance<'_>) -> Option<i64> {
+    match record_provenance(pool, new).await {
+        Ok(record) => Some(record.id),
+        Err(e) => {
+            warn!(
+                "Failed to record provenance of {} {}: {}",
+                new.artifact, new.artifact_ref, e
+            );
+            None
+        }
+    }
+}
+
+/// Tool calls among the output items of a responses API call
+pub fn tool_calls(output: Option<&[ResponsesOutput]>) -> Vec<ToolCall> {
+    output
+        .unwrap_or_default()
+        .iter()
+        .filter(|item| {
+            item.name.is_some()
+                || item
+                    .output_type
+                    .as_deref()
+                    .is_some_and(|t| t.ends_with("_call"))
+        })
+        .map(|item| ToolCall {
+            name: item
+                .name
+                .clone()
+                .or_else(|| item.output_type.clone())
+                .unwrap_or_default(),
+            call_id: item.call_id.clone().or_else(|| item.id.clone()),
+            status: item.status.clone(),
+            input: item.input.clone(),
+        })
+        .collect()
+}
+
+/// API representation of a provenance record, verified against its digest
+pub fn to_entry(record: ProvenanceRecord) -> ProvenanceEntry {
+    let hashes = |value: Value| serde_json::from_value::<BTreeMap<String, String>>(value);
+    ProvenanceEntry {
+        verified: record.verify(),
+        id: record.id,
+        artifact: record.artifact,
+        artifact_ref: record.artifact_ref,
+        repo_url: record.repo_url,
+        commit: record.commit_hash,
+        model: record.model,
+        prompt_version: record.prompt_version,
+        prompt_sha256: record.prompt_sha256,
+        tool_calls: serde_json::from_value::<Vec<ToolCall>>(record.tool_calls)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|call| ProvenanceToolCall {
+                name: call.name,
+                call_id: call.call_id,
+                status: call.status,
+                input: call.input,
+            })
+            .collect(),
+        input_hashes: hashes(record.input_hashes).unwrap_or_default(),
+        output_hashes: hashes(record.output_hashes).unwrap_or_default(),
+        created_at: record.created_at,
+        record_sha256: record.record_sha256,
+    }
+}
@@ -0,0 +1,134 @@
+use axum::http::StatusCode;
+use tracing::{error, info, warn};
+
+use crate::demo::DemoLimit;
+use crate::errors::AppError;
+use crate::repo_policy::RepoRejected;
+use crate::services::disk::DiskFull;
+use crate::services::kicad_format::UnsupportedFormat;
+use crate::types::ApiError;
+use kicad_db::{quarantine, PgPool};
+
+// Stages whose failures are counted per commit
+pub const STAGE_DISTILL: &str = "distill";
+pub const STAGE_OVERVIEW: &str = "overview";
+
+/// Failures of a stage on one commit before it's quarantined, from
+/// QUARANTINE_AFTER_FAILURES (default 3; 0 never quarantines)
+pub fn quarantine_after() -> i32 {
+    std::env::var("QUARANTINE_AFTER_FAILURES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(3)
+}
+
+/// A commit that failed a stage too often and is skipped until an operator
+/// releases it. Mapped to 422 `commit_quarantined`.
+#[derive(Debug)]
+pub struct Quarantined(pub String);
+
+impl std::fmt::Display for Quarantined {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Quarantined {}
+
+impl From<&Quarantined> for AppError {
+    fn from(quarantined: &Quarantined) -> Self {
+        AppError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::new("commit_quarantined", quarantined.0.clone()),
+        )
+    }
+}
+
+/// Refuse a stage on a quarantined commit. Lets the work through when the
+/// quarantine can't be read.
+pub async fn check(
+    pool: &PgPool,
+    repo_url: &str,
+    commit: &str,
+    stage: &str,
+) -> Result<(), Quarantined> {
+    match quarantine::get_quarantined(pool, repo_url, commit, stage).await {
+        Ok(None) => Ok(()),
+        Ok(Some(entry)) => Err(Quarantined(format!(
+            "Commit {} is quarantined after failing {} {} time(s); last error: {}",
+            commit,
+            stage,
+            entry.failures,
+            entry.last_error.as_deref().unwrap_or("unknown")
+        ))),
+        Err(e) => {
+            warn!("Failed to read quarantine of {}: {}", commit, e);
+            Ok(())
+        }
+    }
+}
+
+/// Whether a failure says something about the commit itself. Refusals,
+/// full disks, clone and database errors would fail any commit.
+fn counts(e: &anyhow::Error) -> bool {
+    !(e.is::<DemoLimit>()
+        || e.is::<DiskFull>()
+        || e.is::<RepoRejected>()
+        || e.is::<UnsupportedFormat>()
+        || e.is::<Quarantined>()
+        || e.chain()
+            .any(|cause| cause.is::<git2::Error>() || cause.is::<sqlx::Error>()))
+}
+
+/// Count a failure of a stage on a commit, quarantining it once it failed
+/// QUARANTINE_AFTER_FAILURES times in a row
+pub async fn record_failure(
+    pool: &PgPool,
+    repo_url: &str,
+    commit: &str,
+    stage: &str,
+    e: &anyhow::Error,
+) {
+    if !counts(e) {
+        return;
+    }
+    let limit = quarantine_after();
+    match quarantine::record_failure(pool, repo_url, commit, stage, &format!("{:#}", e), limit)
+        .await
+    {
+        Ok(entry) if entry.failures == limit && entry.quarantined_at.is_some() => error!(
+            "Quarantined {} at {} after {} failed {} attempts",
+            repo_url, commit, entry.failures, stage
+        ),
+        Ok(_) => {}
+        Err(e) => warn!("Failed to record {} failure of {}: {}", stage, commit, e),
+    }
+}
+
+/// Forget earlier failures of a stage on a commit that just succeeded
+pub async fn record_success(pool: &PgPool, repo_url: &str, commit: &str, stage: &str) {
+    if let Err(e) = quarantine::clear_failures(pool, repo_url, commit, stage).await {
+        warn!("Failed to clear {} failures of {}: {}", stage, commit, e);
+    }
+}
+
+/// Commits of a repository quarantined for a stage; empty when they can't be read
+pub async fn quarantined_commits(pool: &PgPool, repo_url: &str, stage: &str) -> Vec<String> {
+    match quarantine::quarantined_commits(pool, repo_url, stage).await {
+        Ok(commits) => {
+            if !commits.is_empty() {
+                info!(
+                    "Skipping {} quarantined commit(s) of {} for {}",
+                    commits.len(),
+                    repo_url,
+                    stage
+                );
+            }
+            commits
+        }
+        Err(e) => {
+            warn!("Failed to read quarantined commits of {}: {}", repo_url, e);
+            Vec::new()
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::info;
+
+use crate::demo;
+use crate::services::bom::{self, BomComponent};
+use crate::services::git::RangeChanges;
+use crate::services::{distill, git, github, llm_usage, output_filter, provenance};
+use crate::types::{
+    BomDelta, BomDeltaChange, BomDeltaPart, ReleaseNotesCommit, ReleaseNotesResponse,
+    SheetMetaEntry,
+};
+use kicad_db::{
+    canonical,
+    messages::{ChatCompletionRequest, Message},
+    provenance::{NewProvenance, ARTIFACT_RELEASE_NOTES},
+    release_notes::{self, NewReleaseNotes, ReleaseNotesRecord},
+    utilities::load_environment_file::load_environment_file,
+    xai_client::XaiClient,
+    PgPool,
+};
+
+/// Model used for release notes
+const RELEASE_NOTES_MODEL: &str = "grok-4-1-fast";
+/// Bump whenever the release notes prompt changes
+pub const RELEASE_NOTES_PROMPT_VERSION: &str = "release-notes-v2";
+
+// Keep the prompt bounded for long ranges; the stored commit list and BOM delta stay complete
+const MAX_PROMPT_COMMITS: usize = 150;
+const MAX_PROMPT_BOM_LINES: usize = 300;
+
+const RELEASE_NOTES_SYSTEM_PROMPT: &str = r#"You write hardware release notes for KiCad projects.
+You are given the schematic commits between two revisions and the component (BOM) changes between them.
+Write concise Markdown release notes for engineers and purchasing with these sections:
+## Summary - two or three sentences on what this release changes in the hardware
+## Changes - grouped by subsystem where the references make that apparent; say what changed and why it matters (function, compatibility, rework, testing)
+## BOM changes - parts added, removed or substituted, calling out anything that affects sourcing or cost
+## Risks and follow-ups - only if something warrants it
+When sheet owners are given, name the owner of each affected block so the right people review it.
+Only state what the data supports. Do not invent part numbers or reasons that are not in the commits."#;
+
+/// Components of a commit keyed by reference, empty when the commit has no schematics
+pub async fn components_at(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+) -> Result<BTreeMap<String, BomComponent>> {
+    let files = git::get_schematic_files(repo, commit).await?;
+    if !files.iter().any(|f| f.path.ends_with(".kicad_sch")) {
+        return Ok(BTreeMap::new());
+    }
+
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+    Ok(components_of(&distilled))
+}
+
+/// Components of distilled data keyed by reference, without power symbols
+pub fn components_of(distilled: &Value) -> BTreeMap<String, BomComponent> {
+    bom::extract_components(distilled)
+        .into_iter()
+        .filter(|c| !c.reference.starts_with('#'))
+        .map(|c| (c.reference.clone(), c))
+        .collect()
+}
+
+fn delta_part(component: &BomComponent) -> BomDeltaPart {
+    BomDeltaPart {
+        reference: component.reference.clone(),
+        value: component.value.clone(),
+        footprint: component.footprint.clone(),
+        mpn: component.mpn.clone(),
+    }
+}
+
+/// Component additions, removals and value/footprint/MPN changes between two revisions
+pub fn bom_delta(
+    before: &BTreeMap<String, BomComponent>,
+    after: &BTreeMap<String, BomComponent>,
+) -> BomDelta {
+    let mut delta = BomDelta::default();
+
+    for (reference, old) in before {
+        let Some(new) = after.get(reference) else {
+            delta.removed.push(delta_part(old));
+            continue;
+        };
+        let fields = [
+            ("value", &old.value, &new.value),
+            ("footprint", &old.footprint, &new.footprint),
+            ("mpn", &old.mpn, &new.mpn),
+        ];
+        for (field, was, now) in fields {
+            if was != now {
+                delta.changed.push(BomDeltaChange {
+                    reference: reference.clone(),
+                    field: field.to_string(),
+                    before: was.clone(),
+                    after: now.clone(),
+                });
+            }
+        }
+    }
+    delta.added = after
+        .iter()
+        .filter(|(reference, _)| !before.contains_key(*reference))
+        .map(|(_, c)| delta_part(c))
+        .collect();
+
+    delta
+}
+
+/// One-line description of a part: reference, value, MPN and footprint
+pub fn describe_part(part: &BomDeltaPart) -> String {
+    let mut line = format!(
+        "{} {}",
+        part.reference,
+        part.value.as_deref().unwrap_or("(no value)")
+    );
+    if let Some(mpn) = &part.mpn {
+        line.push_str(&format!(" [{}]", mpn));
+    }
+    if let Some(footprint) = &part.footprint {
+        line.push_str(&format!(" ({})", footprint));
+    }
+    line
+}
+
+fn build_prompt(
+    repo: &str,
+    from: &str,
+    to: &str,
+    commits: &[ReleaseNotesCommit],
+    delta: &BomDelta,
+    sheet_meta: &[SheetMetaEntry],
+) -> String {
+    let mut prompt = format!(
+        "Repository: {}\nRange: {} .. {}\n\nSchematic commits ({}, oldest first):\n",
+        repo,
+        from,
+        to,
+        commits.len()
+    );
+    for commit in commits.iter().take(MAX_PROMPT_COMMITS) {
+        prompt.push_str(&format!(
+            "- {} {}: {} (files: {})\n",
+            &commit.commit[..8.min(commit.commit.len())],
+            commit
+                .commit_date
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            commit.message.as_deref().unwrap_or("(no message)"),
+            commit.changed_files.join(", ")
+        ));
+    }
+    if commits.len() > MAX_PROMPT_COMMITS {
+        prompt.push_str(&format!(
+            "- ... and {} more commits\n",
+            commits.len() - MAX_PROMPT_COMMITS
+        ));
+    }
+
+    // Ownership of the sheets the range touched
+    let touched: Vec<&SheetMetaEntry> = sheet_meta
+        .iter()
+        .filter(|m| commits.iter().any(|c| c.changed_files.contains(&m.sheet)))
+        .collect();
+    if !touched.is_empty() {
+        prompt.push_str("\nSheet owners:\n");
+        for meta in touched {
+            prompt.push_str(&format!(
+                "- {}: owner {}, subsystem {}, review status {}\n",
+                meta.sheet,
+                meta.owner.as_deref().unwrap_or("(none)"),
+                meta.subsystem.as_deref().unwrap_or("(none)"),
+                meta.review_status.as_deref().unwrap_or("(none)")
+            ));
+        }
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    lines.extend(
+        delta
+            .added
+            .iter()
+            .map(|p| format!("+ added {}", describe_part(p))),
+    );
+    lines.extend(
+        delta
+            .removed
+            .iter()
+            .map(|p| format!("- removed {}", describe_part(p))),
+    );
+    lines.extend(delta.changed.iter().map(|c| {
+        format!(
+            "~ {} {}: {} -> {}",
+            c.reference,
+            c.field,
+            c.before.as_deref().unwrap_or("(none)"),
+            c.after.as_deref().unwrap_or("(none)")
+        )
+    }));
+
+    prompt.push_str(&format!("\nBOM changes ({}):\n", lines.len()));
+    if lines.is_empty() {
+        prompt.push_str("(no component changes)\n");
+    }
+    let total = lines.len();
+    for line in lines.into_iter().take(MAX_PROMPT_BOM_LINES) {
+        prompt.push_str(&line);
+        prompt.push('\n');
+    }
+    if total > MAX_PROMPT_BOM_LINES {
+        prompt.push_str(&format!(
+            "... and {} more changes\n",
+            total - MAX_PROMPT_BOM_LINES
+        ));
+    }
+
+    prompt
+}
+
+/// Generate release notes for the schematic changes in `range` and store them
+pub async fn generate(
+    pool: &PgPool,
+    repo: &str,
+    from: &str,
+    to: &str,
+    range: &RangeChanges,
+) -> Result<ReleaseNotesRecord> {
+    let before = components_at(pool, repo, &range.from_commit)
+        .await
+        .with_context(|| format!("Failed to read components at {}", from))?;
+    let after = components_at(pool, repo, &range.to_commit)
+        .await
+        .with_context(|| format!("Failed to read components at {}", to))?;
+    let delta = bom_delta(&before, &after);
+
+    let commits: Vec<ReleaseNotesCommit> = range
+        .commits
+        .iter()
+        .map(|c| ReleaseNotesCommit {
+            commit: c.info.commit_hash.clone(),
+            commit_date: c.info.commit_date,
+            message: c.info.message.clone(),
+            changed_files: c.changed_files.clone(),
+        })
+        .collect();
+
+    let sheet_meta = distill::sheet_meta(pool, repo).await;
+
+    demo::check_llm_budget(pool).await?;
+    load_environment_file(None)
+        .map_err(|e| anyhow::anyhow!("Failed to load environment: {}", e))?;
+    let xai_client =
+        XaiClient::new().map_err(|e| anyhow::anyhow!("Failed to initialize XAI client: {}", e))?;
+
+    let prompt = build_prompt(repo, from, to, &commits, &delta, &sheet_meta);
+    let messages = vec![
+        Message::system(RELEASE_NOTES_SYSTEM_PROMPT.to_string()),
+        Message::user(prompt.clone()),
+    ];
+    let request = ChatCompletionRequest::new(messages, RELEASE_NOTES_MODEL.to_string());
+    let response = xai_client
+        .chat_completion(&request)
+        .await
+        .map_err(|e| anyhow::anyhow!("XAI API call failed: {}", e))?;
+
+    llm_usage::record_chat(
+        pool,
+        "release_notes",
+        RELEASE_NOTES_MODEL,
+        response.usage.as_ref(),
+    )
+    .await;
+
+    let notes = response
+        .choices
+        .iter()
+        .find_map(|c| c.message.as_ref().and_then(|m| m.content.clone()))
+        .filter(|n| !n.trim().is_empty())
+        .context("The model returned no release notes")?;
+    let notes = output_filter::apply(&notes);
+
+    let repo_url = github::repo_url(repo);
+    let (commits_json, delta_json) = (
+        serde_json::to_value(&commits)?,
+        serde_json::to_value(&delta)?,
+    );
+    let record = release_notes::upsert_release_notes(
+        pool,
+        &NewReleaseNotes {
+            repo_url: &repo_url,
+            from_ref: from,
+            to_ref: to,
+            from_commit: &range.from_commit,
+            to_commit: &range.to_commit,
+            commits: &commits_json,
+            bom_delta: &delta_json,
+            notes: &notes,
+            model: RELEASE_NOTES_MODEL,
+            prompt_version: RELEASE_NOTES_PROMPT_VERSION,
+        },
+    )
+    .await?;
+
+    let full_prompt = format!("{}\n\n{}", RELEASE_NOTES_SYSTEM_PROMPT, prompt);
+    provenance::record(
+        pool,
+        &NewProvenance {
+            artifact: ARTIFACT_RELEASE_NOTES,
+            artifact_ref: &record.id.to_string(),
+            repo_url: Some(&repo_url),
+            commit_hash: Some(&range.to_commit),
+            model: RELEASE_NOTES_MODEL,
+            prompt_version: RELEASE_NOTES_PROMPT_VERSION,
+            prompt: Some(&full_prompt),
+            tool_calls: &[],
+            inputs: &[
+                ("commits", &canonical::to_canonical_vec(&commits_json)),
+                ("bom_delta", &canonical::to_canonical_vec(&delta_json)),
+            ],
+            outputs: &[("notes", notes.as_bytes())],
+        },
+    )
+    .await;
+
+    info!(
+        "Generated release notes {} for {} {}..{} ({} commits)",
+        record.id,
+        repo,
+        from,
+        to,
+        commits.len()
+    );
+    Ok(record)
+}
+
+/// API representation of stored release notes
+pub fn to_response(record: ReleaseNotesRecord, repo: &str, cached: bool) -> ReleaseNotesResponse {
+    ReleaseNotesResponse {
+        id: record.id,
+        repo: repo.to_string(),
+        from: record.from_ref,
+        to: record.to_ref,
+        from_commit: record.from_commit,
+        to_commit: record.to_commit,
+        commits: serde_json::from_value(record.commits).unwrap_or_default(),
+        bom_delta: serde_json::from_value(record.bom_delta).unwrap_or_default(),
+        notes: record.notes,
+        model: record.model,
+        prompt_version: record.prompt_version,
+        cached,
+        created_at: record.created_at,
+    }
+}
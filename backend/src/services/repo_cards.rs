@@ -0,0 +1,61 @@
+use anyhow::Result;
+use tracing::warn;
+
+use crate::services::github;
+use crate::types::RepoSummaryCardResponse;
+use crate::versioning;
+use kicad_db::{read_pool, repo_cards, PgPool};
+
+/// Recompute a repository's summary card in the background, after one of its
+/// commits was stored, distilled, summarised or commented on. The card is a
+/// handful of aggregates, so this runs in this process rather than as a job.
+pub fn refresh_later(pool: &PgPool, repo: &str) {
+    let (pool, repo) = (pool.clone(), repo.to_string());
+    tokio::spawn(async move {
+        let repo_url = github::repo_url(&repo);
+        if let Err(e) = repo_cards::refresh_card(&pool, &repo_url).await {
+            warn!("Failed to refresh the summary card of {}: {}", repo, e);
+        }
+    });
+}
+
+/// A repository's summary card as last refreshed. A repository that has
+/// none yet gets one computed now; None when nothing was ever stored for it.
+pub async fn card(pool: &PgPool, repo: &str) -> Result<Option<RepoSummaryCardResponse>> {
+    let repo_url = github::repo_url(repo);
+    let card = match repo_cards::get_card(read_pool(pool), &repo_url).await? {
+        Some(card) => Some(card),
+        None => {
+            repo_cards::refresh_card(pool, &repo_url).await?;
+            repo_cards::get_card(pool, &repo_url).await?
+        }
+    };
+    let Some(card) = card else {
+        return Ok(None);
+    };
+
+    // Slugs and commit hashes need no escaping in a query string
+    let thumbnail_url = card.thumbnail_commit_hash.as_ref().map(|commit| {
+        format!(
+            "{}/repo/commit/image?repo={}&commit={}",
+            versioning::CURRENT_PREFIX,
+            repo,
+            commit
+        )
+    });
+    Ok(Some(RepoSummaryCardResponse {
+        repo: repo.to_string(),
+        last_commit: card.last_commit_hash,
+        last_commit_date: card.last_commit_date,
+        last_commit_message: card.last_commit_message,
+        commit_count: card.commit_count,
+        distilled_count: card.distilled_count,
+        part_count: card.part_count,
+        comment_count: card.comment_count,
+        blurb: card.blurb,
+        blurb_commit: card.blurb_commit_hash,
+        thumbnail_commit: card.thumbnail_commit_hash,
+        thumbnail_url,
+        refreshed_at: card.refreshed_at,
+    }))
+}
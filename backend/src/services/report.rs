@@ -0,0 +1,564 @@
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+use crate::services::bom::{self, BomComponent};
+use crate::services::{
+    blob_store, commit_diff, connectors, design_rules, distill, erc, git, github, release_notes,
+    test_points,
+};
+use crate::types::{
+    BomDelta, CiErcViolation, ConnectorPinoutChange, DesignRuleViolation, TestPointCoverageResponse,
+};
+use kicad_db::summaries::REVIEW_APPROVED;
+use kicad_db::{read_pool, retrieve_schematic_meta, PgPool};
+
+/// Stored images larger than this are left out of the report rather than inlined
+const MAX_IMAGE_BYTES: i64 = 20 * 1024 * 1024;
+/// Nets without a test point listed by name before the rest are only counted
+const MAX_UNCOVERED_NETS_LISTED: usize = 100;
+
+/// Command that converts the HTML report to PDF (REPORT_PDF_COMMAND, e.g. "wkhtmltopdf").
+/// It is run as `<command> - -`: HTML on stdin, PDF on stdout. None disables PDF reports.
+pub fn pdf_command() -> Option<String> {
+    std::env::var("REPORT_PDF_COMMAND")
+        .ok()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+}
+
+/// One BOM line whose quantity changed between two revisions
+#[derive(Debug)]
+struct BomLineChange {
+    value: Option<String>,
+    footprint: Option<String>,
+    mpn: Option<String>,
+    before: usize,
+    after: usize,
+}
+
+/// Everything shown in a commit report
+#[derive(Debug)]
+pub struct CommitReport {
+    pub repo: String,
+    pub commit: String,
+    /// Commit the diff is against; None for a root commit
+    pub base: Option<String>,
+    message: Option<String>,
+    commit_date: Option<String>,
+    blurb: Option<String>,
+    description: Option<String>,
+    changed_files: Vec<String>,
+    bom_delta: BomDelta,
+    bom_lines: Vec<BomLineChange>,
+    /// Connectors whose pinout changed, shown ahead of everything else
+    connector_pinout_changes: Vec<ConnectorPinoutChange>,
+    erc_errors: Vec<CiErcViolation>,
+    erc_warnings: Vec<CiErcViolation>,
+    /// Why the ERC section is missing or incomplete
+    erc_notes: Vec<String>,
+    /// Enabled design rules of the repository; None when they couldn't be loaded
+    rules_checked: Option<usize>,
+    rule_violations: Vec<DesignRuleViolation>,
+    test_point_coverage: TestPointCoverageResponse,
+    /// Stored schematic image as a data URI
+    image: Option<String>,
+}
+
+/// BOM lines (value, footprint, MPN) whose quantity differs between two revisions
+fn bom_line_changes(
+    before: &BTreeMap<String, BomComponent>,
+    after: &BTreeMap<String, BomComponent>,
+) -> Vec<BomLineChange> {
+    type LineKey = (Option<String>, Option<String>, Option<String>);
+    let mut lines: BTreeMap<LineKey, (usize, usize)> = BTreeMap::new();
+
+    let before: Vec<BomComponent> = before.values().cloned().collect();
+    for group in bom::group_components(&before) {
+        lines
+            .entry((group.value, group.footprint, group.mpn))
+            .or_default()
+            .0 = group.references.len();
+    }
+    let after: Vec<BomComponent> = after.values().cloned().collect();
+    for group in bom::group_components(&after) {
+        lines
+            .entry((group.value, group.footprint, group.mpn))
+            .or_default()
+            .1 = group.references.len();
+    }
+
+    lines
+        .into_iter()
+        .filter(|(_, (before, after))| before != after)
+        .map(|((value, footprint, mpn), (before, after))| BomLineChange {
+            value,
+            footprint,
+            mpn,
+            before,
+            after,
+        })
+        .collect()
+}
+
+/// Gather the report for a commit: stored AI summary, changed sheets, component
+/// changes and BOM lines against `base` (or the first parent), ERC findings,
+/// design rule violations, test point coverage and the stored schematic image. An ERC that can't run
+/// or rules that can't be loaded are noted in the report instead of failing it. With `approved_only`, the blurb and description are
+/// left out unless they were approved.
+pub async fn build(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    base: Option<String>,
+    approved_only: bool,
+) -> Result<CommitReport> {
+    let base = match base {
+        Some(base) => Some(base),
+        None => git::get_parent_commit(repo, commit).await?,
+    };
+    let info = git::get_commit_info(repo, commit).await?;
+
+    let diff = commit_diff::compute_against(pool, repo, base.clone(), commit)
+        .await
+        .with_context(|| format!("Failed to diff {}", commit))?;
+    let before = release_notes::components_of(&diff.before);
+    let after = release_notes::components_of(&diff.after);
+    let connector_pinout_changes = connectors::pinout_changes(&diff);
+
+    let repo_url = github::repo_url(repo);
+    let meta = retrieve_schematic_meta(read_pool(pool), &repo_url, commit).await?;
+
+    let mut erc_notes = Vec::new();
+    let (erc_errors, erc_warnings) = match erc::check(repo, commit).await {
+        Ok(report) => {
+            erc_notes.extend(
+                report
+                    .skipped
+                    .iter()
+                    .map(|w| format!("{} could not be checked: {}", w.file, w.error)),
+            );
+            (report.errors, report.warnings)
+        }
+        Err(e) => {
+            warn!("ERC failed for {}/{}: {}", repo, commit, e);
+            erc_notes.push(format!("ERC could not run: {}", e));
+            (Vec::new(), Vec::new())
+        }
+    };
+
+    let (rules_checked, rule_violations) = match design_rules::enabled_rules(pool, repo).await {
+        Ok(rules) => (
+            Some(rules.len()),
+            design_rules::evaluate(&rules, &diff.after),
+        ),
+        Err(e) => {
+            warn!("Failed to load design rules of {}: {}", repo, e);
+            (None, Vec::new())
+        }
+    };
+
+    let sheet_meta = distill::sheet_meta(pool, repo).await;
+    let test_point_coverage = test_points::report(repo, commit, &diff.after, &sheet_meta);
+
+    let image = match blob_store::schematic_image(read_pool(pool), &repo_url, commit).await? {
+        Some(record) if record.size_bytes <= MAX_IMAGE_BYTES => {
+            blob_store::read(&record).await?.map(|data| {
+                format!(
+                    "data:{};base64,{}",
+                    record.content_type,
+                    BASE64.encode(&data)
+                )
+            })
+        }
+        Some(record) => {
+            info!(
+                "Leaving {} byte schematic image of {}/{} out of the report",
+                record.size_bytes, repo, commit
+            );
+            None
+        }
+        None => None,
+    };
+
+    let (blurb, description) = match meta {
+        Some(meta) if !approved_only || meta.summary_review_status == REVIEW_APPROVED => {
+            (meta.blurb, meta.description)
+        }
+        _ => (None, None),
+    };
+
+    Ok(CommitReport {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        base,
+        message: info.message,
+        commit_date: info.commit_date.map(|d| d.to_rfc3339()),
+        blurb,
+        description,
+        bom_lines: bom_line_changes(&before, &after),
+        connector_pinout_changes,
+        changed_files: diff.changed_files,
+        bom_delta: diff.bom,
+        erc_errors,
+        erc_warnings,
+        erc_notes,
+        rules_checked,
+        rule_violations,
+        test_point_coverage,
+        image,
+    })
+}
+
+/// Content type of an image from its first bytes
+pub fn image_content_type(head: &[u8]) -> &'static str {
+    if head.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "image/jpeg"
+    } else if head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP") {
+        "image/webp"
+    } else if head.starts_with(b"<svg") || head.starts_with(b"<?xml") {
+        "image/svg+xml"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..8.min(commit.len())]
+}
+
+fn cell(value: Option<&str>) -> String {
+    format!("<td>{}</td>", escape(value.unwrap_or("—")))
+}
+
+fn erc_table(html: &mut String, title: &str, violations: &[CiErcViolation]) {
+    html.push_str(&format!("<h3>{} ({})</h3>\n", title, violations.len()));
+    if violations.is_empty() {
+        return;
+    }
+    html.push_str(
+        "<table>\n<tr><th>Code</th><th>Sheet</th><th>Message</th><th>Components</th><th>Net</th></tr>\n",
+    );
+    for v in violations {
+        html.push_str(&format!(
+            "<tr>{}{}{}{}{}</tr>\n",
+            cell(Some(&v.error_code)),
+            cell(Some(&v.file)),
+            cell(Some(&v.message)),
+            cell(Some(v.component_refs.join(", ").as_str()).filter(|r| !r.is_empty())),
+            cell(v.net_name.as_deref())
+        ));
+    }
+    html.push_str("</table>\n");
+}
+
+fn connector_alert(html: &mut String, changes: &[ConnectorPinoutChange]) {
+    if changes.is_empty() {
+        return;
+    }
+    html.push_str(&format!(
+        "<div class=\"alert\">\n<h2>Connector pinout changed ({})</h2>\n\
+         <p>Check mating connectors, cable harnesses and enclosure labels before release.</p>\n",
+        changes.len()
+    ));
+    html.push_str(
+        "<table>\n<tr><th>Connector</th><th>Pin</th><th>Name</th><th>Net before</th><th>Net after</th></tr>\n",
+    );
+    for change in changes {
+        let connector = match &change.value {
+            Some(value) => format!("{} {}", change.reference, value),
+            None => change.reference.clone(),
+        };
+        for pin in &change.pins {
+            html.push_str(&format!(
+                "<tr>{}{}{}{}{}</tr>\n",
+                cell(Some(&connector)),
+                cell(Some(&pin.pin)),
+                cell(pin.name.as_deref()),
+                cell(pin.before.as_deref()),
+                cell(pin.after.as_deref())
+            ));
+        }
+    }
+    html.push_str("</table>\n</div>\n");
+}
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin:0.5em 0 1em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:left;vertical-align:top}\
+th{background:#f3f3f3}\
+.muted{color:#777}\
+img{max-width:100%;border:1px solid #ccc}\
+pre{white-space:pre-wrap}\
+.alert{border:2px solid #b00020;padding:0.5em 1em;margin:1em 0}\
+.alert h2{color:#b00020;margin-top:0.3em}";
+
+/// Render the report as a standalone HTML document (styles and image inlined)
+pub fn render_html(report: &CommitReport) -> String {
+    let mut html = String::new();
+    let title = format!("{} @ {}", report.repo, short(&report.commit));
+    html.push_str(&format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>Commit report: {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape(&title),
+        STYLE
+    ));
+    html.push_str(&format!("<h1>Commit report: {}</h1>\n", escape(&title)));
+    html.push_str(&format!(
+        "<p><strong>{}</strong></p>\n<p class=\"muted\">Commit {}{}<br>Compared against {}</p>\n",
+        escape(report.message.as_deref().unwrap_or("(no commit message)")),
+        escape(&report.commit),
+        report
+            .commit_date
+            .as_deref()
+            .map(|d| format!(", {}", escape(d)))
+            .unwrap_or_default(),
+        report
+            .base
+            .as_deref()
+            .map_or("the start of history".to_string(), escape)
+    ));
+
+    connector_alert(&mut html, &report.connector_pinout_changes);
+
+    html.push_str("<h2>Summary</h2>\n");
+    match (&report.blurb, &report.description) {
+        (None, None) => html
+            .push_str("<p class=\"muted\">No AI summary has been generated for this commit.</p>\n"),
+        (blurb, description) => {
+            if let Some(blurb) = blurb {
+                html.push_str(&format!("<p><strong>{}</strong></p>\n", escape(blurb)));
+            }
+            if let Some(description) = description {
+                html.push_str(&format!("<pre>{}</pre>\n", escape(description)));
+            }
+        }
+    }
+
+    html.push_str(&format!(
+        "<h2>Changed sheets ({})</h2>\n",
+        report.changed_files.len()
+    ));
+    if !report.changed_files.is_empty() {
+        html.push_str("<ul>\n");
+        for file in &report.changed_files {
+            html.push_str(&format!("<li>{}</li>\n", escape(file)));
+        }
+        html.push_str("</ul>\n");
+    }
+
+    let delta = &report.bom_delta;
+    html.push_str(&format!(
+        "<h2>Component changes</h2>\n<p>{} added, {} removed, {} changed</p>\n",
+        delta.added.len(),
+        delta.removed.len(),
+        delta.changed.len()
+    ));
+    if !delta.added.is_empty() || !delta.removed.is_empty() {
+        html.push_str(
+            "<table>\n<tr><th></th><th>Reference</th><th>Value</th><th>Footprint</th><th>MPN</th></tr>\n",
+        );
+        let parts = delta
+            .added
+            .iter()
+            .map(|p| ("Added", p))
+            .chain(delta.removed.iter().map(|p| ("Removed", p)));
+        for (change, part) in parts {
+            html.push_str(&format!(
+                "<tr><td>{}</td>{}{}{}{}</tr>\n",
+                change,
+                cell(Some(&part.reference)),
+                cell(part.value.as_deref()),
+                cell(part.footprint.as_deref()),
+                cell(part.mpn.as_deref())
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    if !delta.changed.is_empty() {
+        html.push_str(
+            "<table>\n<tr><th>Reference</th><th>Field</th><th>Before</th><th>After</th></tr>\n",
+        );
+        for change in &delta.changed {
+            html.push_str(&format!(
+                "<tr>{}{}{}{}</tr>\n",
+                cell(Some(&change.reference)),
+                cell(Some(&change.field)),
+                cell(change.before.as_deref()),
+                cell(change.after.as_deref())
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str(&format!(
+        "<h2>BOM delta ({} line(s))</h2>\n",
+        report.bom_lines.len()
+    ));
+    if !report.bom_lines.is_empty() {
+        html.push_str(
+            "<table>\n<tr><th>Value</th><th>Footprint</th><th>MPN</th><th>Qty before</th><th>Qty after</th></tr>\n",
+        );
+        for line in &report.bom_lines {
+            html.push_str(&format!(
+                "<tr>{}{}{}<td>{}</td><td>{}</td></tr>\n",
+                cell(line.value.as_deref()),
+                cell(line.footprint.as_deref()),
+                cell(line.mpn.as_deref()),
+                line.before,
+                line.after
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Electrical rules check</h2>\n");
+    for note in &report.erc_notes {
+        html.push_str(&format!("<p class=\"muted\">{}</p>\n", escape(note)));
+    }
+    erc_table(&mut html, "Errors", &report.erc_errors);
+    erc_table(&mut html, "Warnings", &report.erc_warnings);
+
+    html.push_str("<h2>Design rules</h2>\n");
+    match report.rules_checked {
+        None => html.push_str("<p class=\"muted\">Design rules could not be loaded.</p>\n"),
+        Some(0) => html
+            .push_str("<p class=\"muted\">No design rules are defined for this repository.</p>\n"),
+        Some(checked) => {
+            html.push_str(&format!(
+                "<p>{} rule(s) checked, {} violation(s)</p>\n",
+                checked,
+                report.rule_violations.len()
+            ));
+            if !report.rule_violations.is_empty() {
+                html.push_str("<table>\n<tr><th>Rule</th><th>Severity</th><th>Component</th><th>Sheet</th><th>Message</th></tr>\n");
+                for v in &report.rule_violations {
+                    html.push_str(&format!(
+                        "<tr>{}{}{}{}{}</tr>\n",
+                        cell(Some(&v.rule)),
+                        cell(Some(&v.severity)),
+                        cell(Some(&v.reference)),
+                        cell(v.sheet.as_deref()),
+                        cell(Some(&v.message))
+                    ));
+                }
+                html.push_str("</table>\n");
+            }
+        }
+    }
+
+    let coverage = &report.test_point_coverage;
+    html.push_str(&format!(
+        "<h2>Test point coverage</h2>\n<p>{} of {} nets have a test point ({}%), {} test point(s)</p>\n",
+        coverage.covered,
+        coverage.nets,
+        coverage.coverage_percent,
+        coverage.test_points.len()
+    ));
+    if coverage.subsystems.iter().any(|s| s.subsystem.is_some()) {
+        html.push_str(
+            "<table>\n<tr><th>Subsystem</th><th>Nets</th><th>Covered</th><th>Coverage</th></tr>\n",
+        );
+        for subsystem in &coverage.subsystems {
+            html.push_str(&format!(
+                "<tr>{}<td>{}</td><td>{}</td><td>{}%</td></tr>\n",
+                cell(Some(
+                    subsystem.subsystem.as_deref().unwrap_or("(no subsystem)")
+                )),
+                subsystem.nets,
+                subsystem.covered,
+                subsystem.coverage_percent
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    if !coverage.uncovered_nets.is_empty() {
+        let mut names: Vec<String> = coverage
+            .uncovered_nets
+            .iter()
+            .take(MAX_UNCOVERED_NETS_LISTED)
+            .map(|n| escape(&n.net))
+            .collect();
+        if coverage.uncovered_nets.len() > MAX_UNCOVERED_NETS_LISTED {
+            names.push(format!(
+                "and {} more",
+                coverage.uncovered_nets.len() - MAX_UNCOVERED_NETS_LISTED
+            ));
+        }
+        html.push_str(&format!(
+            "<p class=\"muted\">Without a test point: {}</p>\n",
+            names.join(", ")
+        ));
+    }
+
+    html.push_str("<h2>Schematic</h2>\n");
+    match &report.image {
+        Some(image) => html.push_str(&format!(
+            "<img src=\"{}\" alt=\"Schematic at {}\">\n",
+            image,
+            escape(short(&report.commit))
+        )),
+        None => html
+            .push_str("<p class=\"muted\">No rendered schematic is stored for this commit.</p>\n"),
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Convert an HTML report to PDF with REPORT_PDF_COMMAND
+pub async fn render_pdf(command: &str, html: &str) -> Result<Vec<u8>> {
+    let mut child = Command::new(command)
+        .args(["-", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to run PDF converter {}", command))?;
+
+    // Write from a separate task so a converter streaming output early can't deadlock
+    let mut stdin = child.stdin.take().context("PDF converter has no stdin")?;
+    let input = html.as_bytes().to_vec();
+    let writer = tokio::spawn(async move { stdin.write_all(&input).await });
+
+    let output = child
+        .wait_with_output()
+        .await
+        .context("PDF converter failed")?;
+    writer
+        .await?
+        .context("Failed to send the report to the PDF converter")?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "PDF converter exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    if !output.stdout.starts_with(b"%PDF") {
+        anyhow::bail!("PDF converter did not produce a PDF");
+    }
+    Ok(output.stdout)
+}
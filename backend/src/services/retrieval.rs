@@ -0,0 +1,627 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::{info, warn};
+
+use crate::services::jobs::{self, JobRequest};
+use crate::services::{comments, commit_diff, distill, github};
+use crate::types::{CommitCommentEntry, GrokCitation, SheetMetaEntry};
+use kicad_db::retrieval::{self as index, DocumentFilter, NewDocument};
+use kicad_db::{read_pool, retrieve_schematic_meta, PgPool, SchematicMeta};
+
+/// Bump whenever chunking or embedding changes, so indexes are built again
+pub const INDEX_VERSION: &str = "retrieval-v1";
+
+/// Categories documents are indexed under and queries can be limited to
+pub const CATEGORIES: &[&str] = &["component", "net", "sheet", "summary", "comment"];
+
+/// Characters of excerpts sent with a question, about 6k tokens
+pub const CONTEXT_BUDGET_CHARS: usize = 24_000;
+/// Excerpts sent with a question at most, however short
+pub const MAX_EXCERPTS: usize = 40;
+
+// BM25 term saturation and length normalisation
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+// Weight of a question word that only shares a stem prefix with a chunk word ("bat" for "battery")
+const PREFIX_WEIGHT: f64 = 0.5;
+
+// Length of the hashed embeddings
+const EMBEDDING_DIMENSIONS: usize = 256;
+// Share of a document's score from embedding similarity; the rest is BM25
+const EMBEDDING_WEIGHT: f64 = 0.3;
+// Weight of a word's character trigrams in its embedding, against 1 for the word
+const TRIGRAM_WEIGHT: f32 = 0.25;
+// Similarity below which a document sharing no word with the question is
+// taken for hash collisions rather than a match
+const MIN_SIMILARITY: f64 = 0.1;
+
+/// Words that say nothing about where in a design to look
+const STOPWORDS: &[&str] = &[
+    "a",
+    "about",
+    "all",
+    "an",
+    "and",
+    "any",
+    "are",
+    "as",
+    "at",
+    "be",
+    "board",
+    "by",
+    "can",
+    "design",
+    "do",
+    "does",
+    "done",
+    "for",
+    "from",
+    "handled",
+    "how",
+    "i",
+    "in",
+    "is",
+    "it",
+    "its",
+    "me",
+    "of",
+    "on",
+    "or",
+    "project",
+    "schematic",
+    "should",
+    "that",
+    "the",
+    "there",
+    "this",
+    "to",
+    "used",
+    "what",
+    "when",
+    "where",
+    "which",
+    "who",
+    "why",
+    "with",
+];
+
+/// A retrievable piece of a design: one component, net, sheet, summary
+/// paragraph or comment
+#[derive(Debug, Clone)]
+pub struct Chunk {
+    /// One of `CATEGORIES`
+    pub kind: String,
+    /// Reference, net name, sheet path, summary part or comment id
+    pub key: String,
+    /// Sheet a component is on
+    pub sheet: Option<String>,
+    pub text: String,
+}
+
+/// A chunk as indexed, with its embedding
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub chunk: Chunk,
+    pub embedding: Vec<f32>,
+}
+
+/// What a question is asked about
+#[derive(Debug, Clone, Default)]
+pub struct RetrievalQuery<'a> {
+    /// GitHub repository in "owner/repo" format
+    pub repo: &'a str,
+    /// Resolved commit hash
+    pub commit: &'a str,
+    pub question: &'a str,
+    /// Only documents of this sheet
+    pub sheet: Option<&'a str>,
+    /// Only documents of these categories; all when empty
+    pub categories: &'a [&'a str],
+}
+
+/// Excerpts picked for a question, numbered as the answer cites them
+#[derive(Debug)]
+pub struct Retrieved {
+    /// The excerpts as sent to the model, each headed by its [n]
+    pub context: String,
+    pub citations: Vec<GrokCitation>,
+    /// Chunks the excerpts were picked from
+    pub searched: usize,
+}
+
+fn text<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|v| !v.is_empty())
+}
+
+fn component_chunk(reference: &str, component: &Value) -> Chunk {
+    let mut text_parts = vec![format!(
+        "Component {} ({})",
+        reference,
+        text(component, "category").unwrap_or("other")
+    )];
+    for (label, key) in [
+        ("value", "value"),
+        ("symbol", "lib_id"),
+        ("footprint", "footprint"),
+        ("description", "description"),
+    ] {
+        if let Some(v) = text(component, key) {
+            text_parts.push(format!("{}: {}", label, v));
+        }
+    }
+    let sheet = text(component, "sheet_path").map(str::to_string);
+    if let Some(sheet) = sheet.as_deref().filter(|s| *s != "/") {
+        text_parts.push(format!("sheet: {}", sheet));
+    }
+    if let Some(props) = component.get("properties").and_then(Value::as_object) {
+        let props: Vec<String> = props
+            .iter()
+            // Datasheet links and KiCad's own fields only get in the way of matching
+            .filter(|(k, _)| !k.starts_with("ki_"))
+            .filter_map(|(k, v)| Some((k, v.as_str().filter(|v| !v.is_empty())?)))
+            .filter(|(_, v)| !v.starts_with("http://") && !v.starts_with("https://"))
+            .map(|(k, v)| format!("{}: {}", k, v))
+            .collect();
+        if !props.is_empty() {
+            text_parts.push(format!("properties: {}", props.join(", ")));
+        }
+    }
+    if let Some(pins) = component.get("pins").and_then(Value::as_array) {
+        let pins: Vec<String> = pins
+            .iter()
+            .filter_map(|pin| {
+                let number = text(pin, "number")?;
+                let net = text(pin, "net").unwrap_or("NC");
+                Some(match text(pin, "name") {
+                    Some(name) => format!("{} ({}) → {}", number, name, net),
+                    None => format!("{} → {}", number, net),
+                })
+            })
+            .collect();
+        if !pins.is_empty() {
+            text_parts.push(format!("pins: {}", pins.join(", ")));
+        }
+    }
+    Chunk {
+        kind: "component".to_string(),
+        key: reference.to_string(),
+        sheet,
+        text: text_parts.join("; "),
+    }
+}
+
+/// Every component, named net and sheet of distilled data as a retrievable
+/// chunk. Power symbols are left out; their nets carry the same information.
+pub fn chunks(distilled: &Value, sheet_meta: &[SheetMetaEntry]) -> Vec<Chunk> {
+    // Components are keyed by reference, or listed with a reference field
+    let components: Vec<(String, &Value)> = match distilled.get("components") {
+        Some(Value::Object(map)) => map.iter().map(|(r, c)| (r.clone(), c)).collect(),
+        Some(Value::Array(list)) => list
+            .iter()
+            .filter_map(|c| Some((text(c, "reference")?.to_string(), c)))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let mut chunks: Vec<Chunk> = components
+        .iter()
+        .filter(|(reference, _)| !reference.starts_with('#'))
+        .map(|(reference, component)| component_chunk(reference, component))
+        .collect();
+
+    let mut sheets: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for chunk in &chunks {
+        if let Some(sheet) = &chunk.sheet {
+            let label = match components
+                .iter()
+                .find(|(r, _)| *r == chunk.key)
+                .and_then(|(_, c)| text(c, "value"))
+            {
+                Some(value) => format!("{} ({})", chunk.key, value),
+                None => chunk.key.clone(),
+            };
+            sheets.entry(sheet.clone()).or_default().push(label);
+        }
+    }
+
+    // Generated names like "Net-(U1-Pad57)" would match questions about the
+    // reference; the component chunks already list those connections
+    for (name, members) in commit_diff::net_members(distilled) {
+        if commit_diff::is_generated_net(&name) {
+            continue;
+        }
+        let members: Vec<String> = members
+            .iter()
+            .filter(|(reference, _)| !reference.starts_with('#'))
+            .map(|(reference, pins)| {
+                let pins: Vec<&str> = pins.iter().map(String::as_str).collect();
+                format!("{} pin {}", reference, pins.join("/"))
+            })
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        chunks.push(Chunk {
+            kind: "net".to_string(),
+            text: format!("Net {} connects {}", name, members.join(", ")),
+            key: name,
+            sheet: None,
+        });
+    }
+
+    for (sheet, members) in sheets {
+        let mut text = format!("Sheet {}", sheet);
+        if let Some(meta) = sheet_meta.iter().find(|m| m.sheet == sheet) {
+            for (label, value) in [("subsystem", &meta.subsystem), ("owner", &meta.owner)] {
+                if let Some(value) = value {
+                    text.push_str(&format!("; {}: {}", label, value));
+                }
+            }
+        }
+        text.push_str(&format!("; holds {}", members.join(", ")));
+        chunks.push(Chunk {
+            kind: "sheet".to_string(),
+            key: sheet.clone(),
+            sheet: Some(sheet),
+            text,
+        });
+    }
+    chunks
+}
+
+/// The paragraphs of a commit's summaries (edits where there are any), each
+/// keyed by the summary it is from and its number, e.g. "description 2"
+pub fn summary_chunks(meta: &SchematicMeta) -> Vec<Chunk> {
+    let summaries = [
+        ("blurb", &meta.blurb),
+        ("description", &meta.description),
+        ("overview", &meta.project_overview),
+        ("changes", &meta.change_summary),
+    ];
+    let mut chunks = Vec::new();
+    for (name, summary) in summaries {
+        let Some(summary) = summary else {
+            continue;
+        };
+        let paragraphs = summary
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|p| p.chars().any(char::is_alphanumeric));
+        for (n, paragraph) in paragraphs.enumerate() {
+            chunks.push(Chunk {
+                kind: "summary".to_string(),
+                key: format!("{} {}", name, n + 1),
+                sheet: None,
+                text: format!("Summary ({}): {}", name, paragraph),
+            });
+        }
+    }
+    chunks
+}
+
+/// Each comment on a commit, with what it is anchored to
+pub fn comment_chunks(comments: &[CommitCommentEntry]) -> Vec<Chunk> {
+    comments
+        .iter()
+        .map(|comment| {
+            let anchor = match (&comment.anchor_kind, &comment.anchor) {
+                (Some(kind), Some(anchor)) => format!(" on {} {}", kind, anchor),
+                _ => String::new(),
+            };
+            Chunk {
+                kind: "comment".to_string(),
+                key: comment.id.to_string(),
+                sheet: None,
+                text: format!("Comment by {}{}: {}", comment.author, anchor, comment.body),
+            }
+        })
+        .collect()
+}
+
+/// Lowercase word with common suffixes removed, so "charging" and "charger" meet
+fn stem(word: &str) -> String {
+    let word = word.to_lowercase();
+    for suffix in ["ing", "ers", "er", "es", "ed", "s"] {
+        if let Some(stem) = word.strip_suffix(suffix) {
+            if stem.chars().count() >= 3 {
+                return stem.to_string();
+            }
+        }
+    }
+    word
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(stem)
+        .collect()
+}
+
+/// Stems of the words of a question that say where to look
+fn question_terms(question: &str) -> Vec<String> {
+    let mut terms: Vec<String> = question
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|w| !w.is_empty() && !STOPWORDS.contains(&w.as_str()))
+        .map(|w| stem(&w))
+        .collect();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+/// FNV-1a, which unlike the std hasher is the same on every build, so stored
+/// embeddings stay comparable
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Embedding of stemmed words: each word and, more lightly, its character
+/// trigrams are hashed into a fixed-length vector, normalised to unit
+/// length. The trigrams make "reg" and "regulator" similar where BM25
+/// would only see different words. Deterministic, so no embedding model is
+/// needed to index or query.
+fn embed_words(words: &[String]) -> Vec<f32> {
+    let mut vector = vec![0f32; EMBEDDING_DIMENSIONS];
+    let mut add = |feature: &str, weight: f32| {
+        let hash = fnv1a(feature.as_bytes());
+        // The top bit picks the sign, so collisions cancel out rather than pile up
+        let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        vector[(hash % EMBEDDING_DIMENSIONS as u64) as usize] += sign * weight;
+    };
+    for word in words {
+        add(word, 1.0);
+        let chars: Vec<char> = format!("<{}>", word).chars().collect();
+        for trigram in chars.windows(3) {
+            add(&trigram.iter().collect::<String>(), TRIGRAM_WEIGHT);
+        }
+    }
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+/// Embedding of a chunk's text
+pub fn embed(text: &str) -> Vec<f32> {
+    embed_words(&words(text))
+}
+
+/// Cosine similarity of two unit-length embeddings; 0 when their lengths differ
+fn similarity(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    a.iter().zip(b).map(|(a, b)| f64::from(a * b)).sum()
+}
+
+/// How well a chunk word matches a question word: 1 when equal, less when
+/// one is a prefix of the other ("bat" and "battery")
+fn match_weight(question: &str, word: &str) -> f64 {
+    if question == word {
+        1.0
+    } else if question.len() >= 3
+        && word.len() >= 3
+        && (question.starts_with(word) || word.starts_with(question))
+    {
+        PREFIX_WEIGHT
+    } else {
+        0.0
+    }
+}
+
+/// Documents ranked against a question, best first: BM25 scaled to the best
+/// match, blended with embedding similarity. Documents matching the question
+/// in neither are left out.
+pub fn rank<'a>(indexed: &'a [Document], question: &str) -> Vec<(&'a Chunk, f64)> {
+    let terms = question_terms(question);
+    if terms.is_empty() || indexed.is_empty() {
+        return Vec::new();
+    }
+    let chunks: Vec<&Chunk> = indexed.iter().map(|d| &d.chunk).collect();
+    let question_embedding = embed_words(&terms);
+
+    let documents: Vec<Vec<String>> = chunks.iter().map(|c| words(&c.text)).collect();
+    let average_len = documents.iter().map(Vec::len).sum::<usize>() as f64 / documents.len() as f64;
+    // Per chunk and term: the summed match weights of the chunk's words
+    let frequencies: Vec<Vec<f64>> = documents
+        .iter()
+        .map(|words| {
+            terms
+                .iter()
+                .map(|term| words.iter().map(|w| match_weight(term, w)).sum())
+                .collect()
+        })
+        .collect();
+
+    let n = indexed.len() as f64;
+    let idf: Vec<f64> = (0..terms.len())
+        .map(|t| {
+            let containing = frequencies.iter().filter(|f| f[t] > 0.0).count() as f64;
+            ((n - containing + 0.5) / (containing + 0.5) + 1.0).ln()
+        })
+        .collect();
+
+    let bm25: Vec<f64> = documents
+        .iter()
+        .zip(&frequencies)
+        .map(|(words, frequency)| {
+            let norm = K1 * (1.0 - B + B * words.len() as f64 / average_len.max(1.0));
+            frequency
+                .iter()
+                .zip(&idf)
+                .map(|(tf, idf)| idf * tf * (K1 + 1.0) / (tf + norm))
+                .sum::<f64>()
+        })
+        .collect();
+    let best = bm25.iter().copied().fold(0.0, f64::max);
+
+    let mut ranked: Vec<(&Chunk, f64)> = indexed
+        .iter()
+        .zip(bm25)
+        .filter_map(|(document, bm25)| {
+            let similarity = similarity(&document.embedding, &question_embedding).max(0.0);
+            if bm25 <= 0.0 && similarity < MIN_SIMILARITY {
+                return None;
+            }
+            let lexical = if best > 0.0 { bm25 / best } else { 0.0 };
+            let score = (1.0 - EMBEDDING_WEIGHT) * lexical + EMBEDDING_WEIGHT * similarity;
+            Some((&document.chunk, score))
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.key.cmp(&b.0.key)));
+    ranked
+}
+
+/// The best-ranked documents that fit `CONTEXT_BUDGET_CHARS`, numbered from 1.
+/// A document too long for what is left of the budget is passed over for
+/// shorter ones further down.
+pub fn retrieve(documents: &[Document], question: &str) -> Retrieved {
+    let mut context = String::new();
+    let mut citations = Vec::new();
+    for (chunk, _) in rank(documents, question) {
+        if citations.len() == MAX_EXCERPTS {
+            break;
+        }
+        let excerpt = format!("[{}] {}\n", citations.len() + 1, chunk.text);
+        if context.len() + excerpt.len() > CONTEXT_BUDGET_CHARS {
+            continue;
+        }
+        context.push_str(&excerpt);
+        citations.push(GrokCitation {
+            id: citations.len() + 1,
+            kind: chunk.kind.clone(),
+            key: chunk.key.clone(),
+            sheet: chunk.sheet.clone(),
+        });
+    }
+    Retrieved {
+        context,
+        citations,
+        searched: documents.len(),
+    }
+}
+
+/// Version an index is built under: the chunking rules and the distiller
+/// the design data came from
+fn index_version() -> String {
+    format!("{}+{}", INDEX_VERSION, distill::distiller_version())
+}
+
+/// Chunk and embed a commit's distilled data, summaries, sheet metadata and
+/// comments, replacing its indexed documents. Returns how many were indexed.
+pub async fn build_index(pool: &PgPool, repo: &str, commit: &str) -> Result<usize> {
+    let repo_url = github::repo_url(repo);
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+    let sheet_meta = distill::sheet_meta(pool, repo).await;
+
+    let mut all = chunks(&distilled, &sheet_meta);
+    match retrieve_schematic_meta(read_pool(pool), &repo_url, commit).await {
+        Ok(Some(meta)) => all.extend(summary_chunks(&meta)),
+        Ok(None) => {}
+        Err(e) => warn!(
+            "Failed to load summaries of {}@{} to index: {}",
+            repo, commit, e
+        ),
+    }
+    all.extend(comment_chunks(
+        &comments::for_commit(pool, repo, commit).await,
+    ));
+
+    let documents: Vec<Document> = all
+        .into_iter()
+        .map(|chunk| Document {
+            embedding: embed(&chunk.text),
+            chunk,
+        })
+        .collect();
+    let new_documents: Vec<NewDocument> = documents
+        .iter()
+        .map(|d| NewDocument {
+            category: &d.chunk.kind,
+            key: &d.chunk.key,
+            sheet: d.chunk.sheet.as_deref(),
+            text: &d.chunk.text,
+            embedding: &d.embedding,
+        })
+        .collect();
+    let stored =
+        index::replace_documents(pool, &repo_url, commit, &index_version(), &new_documents)
+            .await
+            .context("Failed to store retrieval documents")?;
+    anyhow::ensure!(stored, "Invalid repository: {}", repo);
+
+    info!(
+        "Indexed {} documents of {}@{}",
+        documents.len(),
+        repo,
+        commit
+    );
+    Ok(documents.len())
+}
+
+/// Reindex a commit after its summaries or comments changed: on a worker
+/// with the job queue, otherwise in the background of this process. Failures
+/// are logged rather than returned; a query builds a missing index itself.
+pub async fn index_later(pool: &PgPool, repo: &str, commit: &str) {
+    if jobs::queue_enabled() {
+        let request = JobRequest::IndexRetrieval {
+            repo: repo.to_string(),
+            commit: commit.to_string(),
+        };
+        if let Err(e) = jobs::enqueue(pool, &request).await {
+            warn!(
+                "Failed to queue retrieval indexing of {}@{}: {}",
+                repo, commit, e
+            );
+        }
+        return;
+    }
+
+    let (pool, repo, commit) = (pool.clone(), repo.to_string(), commit.to_string());
+    tokio::spawn(async move {
+        if let Err(e) = build_index(&pool, &repo, &commit).await {
+            warn!("Failed to index {}@{} for retrieval: {:#}", repo, commit, e);
+        }
+    });
+}
+
+/// Excerpts of a commit's index for a question, within the query's sheet and
+/// categories. A commit that isn't indexed, or was indexed under other rules
+/// or another distiller, is indexed first.
+pub async fn query(pool: &PgPool, query: &RetrievalQuery<'_>) -> Result<Retrieved> {
+    let repo_url = github::repo_url(query.repo);
+    let current = index::get_index(read_pool(pool), &repo_url, query.commit)
+        .await?
+        .is_some_and(|i| i.version == index_version());
+    if !current {
+        build_index(pool, query.repo, query.commit).await?;
+    }
+
+    let filter = DocumentFilter {
+        sheet: query.sheet,
+        categories: query.categories,
+    };
+    // Read back from the primary: a replica may not have the index just built
+    let documents: Vec<Document> = index::list_documents(pool, &repo_url, query.commit, &filter)
+        .await?
+        .into_iter()
+        .map(|d| Document {
+            chunk: Chunk {
+                kind: d.category,
+                key: d.key,
+                sheet: d.sheet,
+                text: d.text,
+            },
+            embedding: d.embedding,
+        })
+        .collect();
+    Ok(retrieve(&documents, query.question))
+}
@@ -0,0 +1,228 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use tracing::{info, warn};
+
+use crate::services::commit_diff::{self, CommitDiff};
+use crate::services::{connectors, github, impact};
+use crate::types::{CommitRisk, RiskFactor};
+use kicad_db::{risk, PgPool};
+
+/// Bump whenever the checks or weights change; older scores are recomputed
+pub const SCORER_VERSION: &str = "risk-v3";
+
+// Points per finding
+const POWER_NET_WEIGHT: i32 = 5;
+const CONNECTOR_PINOUT_WEIGHT: i32 = 8;
+const HIGH_PIN_COUNT_SWAP_WEIGHT: i32 = 6;
+const NET_RENAME_WEIGHT: i32 = 2;
+const BLAST_RADIUS_WEIGHT: i32 = 1;
+
+/// Components with at least this many pins count as high pin count
+const HIGH_PIN_COUNT: usize = 8;
+/// Commits scored per hook run, so a large repository catches up gradually
+const BACKFILL_BATCH: i64 = 50;
+
+// Power net names: rails like +3V3 or -12V, and ground/supply names
+const POWER_NET_PREFIXES: &[&str] = &[
+    "GND", "AGND", "DGND", "PGND", "VCC", "VDD", "VSS", "VEE", "VBUS", "VBAT", "VIN", "VSYS",
+];
+pub fn is_power_net(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    if upper.starts_with('+') || upper.starts_with('-') {
+        return upper[1..].starts_with(|c: char| c.is_ascii_digit());
+    }
+    // 3V3, 5V, 12V
+    if upper.starts_with(|c: char| c.is_ascii_digit())
+        && upper.contains('V')
+        && upper
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == 'V' || c == '.')
+    {
+        return true;
+    }
+    POWER_NET_PREFIXES.iter().any(|p| upper.starts_with(p))
+}
+
+fn component<'a>(distilled: &'a Value, reference: &str) -> Option<&'a Value> {
+    distilled.get("components")?.get(reference)
+}
+
+fn pins<'a>(distilled: &'a Value, reference: &str) -> &'a [Value] {
+    component(distilled, reference)
+        .and_then(|c| c.get("pins"))
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn factor(kind: &str, weight: i32, items: Vec<String>) -> Option<RiskFactor> {
+    if items.is_empty() {
+        return None;
+    }
+    let count = items.len() as i32;
+    Some(RiskFactor {
+        kind: kind.to_string(),
+        weight,
+        count,
+        score: weight * count,
+        items,
+    })
+}
+
+/// Score a commit's changes:
+///
+/// - `power_net_change`: power nets added, removed or rewired (renames count as renames)
+/// - `connector_pinout_change`: connectors whose pinout changed (see `connectors::pinout_changes`)
+/// - `high_pin_count_swap`: value, footprint or MPN changes on parts with many pins
+/// - `net_rename`: labelled nets renamed without changing their connections
+/// - `blast_radius`: components the commit didn't touch that sit on nets it
+///   merged, split or rewired (see `impact::analyze`)
+pub fn score(diff: &CommitDiff) -> CommitRisk {
+    let renames = diff.net_renames();
+    let renamed: BTreeSet<&str> = renames
+        .iter()
+        .flat_map(|(old, new)| [old.as_str(), new.as_str()])
+        .collect();
+
+    let power_nets: BTreeSet<String> = diff
+        .nets_added
+        .iter()
+        .chain(&diff.nets_removed)
+        .chain(&diff.nets_changed)
+        .filter(|name| !renamed.contains(name.as_str()) && is_power_net(name))
+        .cloned()
+        .collect();
+
+    let connectors: Vec<String> = connectors::pinout_changes(diff)
+        .into_iter()
+        .map(|c| c.reference)
+        .collect();
+
+    let swapped: Vec<String> = diff
+        .bom
+        .changed
+        .iter()
+        .map(|c| c.reference.as_str())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|reference| {
+            pins(&diff.before, reference)
+                .len()
+                .max(pins(&diff.after, reference).len())
+                >= HIGH_PIN_COUNT
+        })
+        .map(ToString::to_string)
+        .collect();
+
+    let reached: Vec<String> = impact::analyze(diff)
+        .indirect()
+        .map(|c| c.reference.clone())
+        .collect();
+
+    let mut factors: Vec<RiskFactor> = [
+        factor(
+            "power_net_change",
+            POWER_NET_WEIGHT,
+            power_nets.into_iter().collect(),
+        ),
+        factor(
+            "connector_pinout_change",
+            CONNECTOR_PINOUT_WEIGHT,
+            connectors,
+        ),
+        factor("high_pin_count_swap", HIGH_PIN_COUNT_SWAP_WEIGHT, swapped),
+        factor(
+            "net_rename",
+            NET_RENAME_WEIGHT,
+            renames
+                .iter()
+                .map(|(old, new)| format!("{} -> {}", old, new))
+                .collect(),
+        ),
+        factor("blast_radius", BLAST_RADIUS_WEIGHT, reached),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    factors.sort_by_key(|f| std::cmp::Reverse(f.score));
+
+    CommitRisk {
+        score: factors.iter().map(|f| f.score).sum(),
+        factors,
+        scorer_version: SCORER_VERSION.to_string(),
+    }
+}
+
+/// Score a commit against its first parent and store the result. Failures are
+/// logged rather than returned, since scoring never blocks processing.
+pub async fn record(pool: &PgPool, repo_slug: &str, commit_hash: &str) {
+    let diff = match commit_diff::compute(pool, repo_slug, commit_hash).await {
+        Ok(diff) => diff,
+        Err(e) => {
+            warn!(
+                "Failed to diff {}/{} for risk scoring: {:#}",
+                repo_slug, commit_hash, e
+            );
+            return;
+        }
+    };
+    let risk = score(&diff);
+
+    let repo_url = github::repo_url(repo_slug);
+    let factors = serde_json::to_value(&risk.factors).unwrap_or_default();
+    if let Err(e) = risk::upsert_risk(
+        pool,
+        &repo_url,
+        commit_hash,
+        risk.score,
+        &factors,
+        SCORER_VERSION,
+    )
+    .await
+    {
+        warn!(
+            "Failed to store risk score for {}/{}: {}",
+            repo_slug, commit_hash, e
+        );
+    }
+}
+
+/// Score indexed commits that have no score from this scorer version yet.
+/// Returns the number of commits scored.
+pub async fn backfill(pool: &PgPool, repo_slug: &str) -> Result<usize> {
+    let repo_url = github::repo_url(repo_slug);
+    let pending =
+        risk::commits_without_risk(pool, &repo_url, SCORER_VERSION, BACKFILL_BATCH).await?;
+
+    for commit_hash in &pending {
+        record(pool, repo_slug, commit_hash).await;
+    }
+    if !pending.is_empty() {
+        info!(
+            "Scored risk for {} commit(s) of {}",
+            pending.len(),
+            repo_slug
+        );
+    }
+    Ok(pending.len())
+}
+
+/// Stored risk scores of a repository's commits, keyed by commit hash
+pub async fn scores(pool: &PgPool, repo_slug: &str) -> Result<HashMap<String, CommitRisk>> {
+    let repo_url = github::repo_url(repo_slug);
+    Ok(risk::risk_scores(pool, &repo_url)
+        .await?
+        .into_iter()
+        .map(|r| {
+            let factors = serde_json::from_value(r.factors).unwrap_or_default();
+            (
+                r.commit_hash,
+                CommitRisk {
+                    score: r.score,
+                    factors,
+                    scorer_version: r.scorer_version,
+                },
+            )
+        })
+        .collect())
+}
@@ -0,0 +1,142 @@
+use reqwest::Url;
+
+use crate::services::secret_settings;
+use crate::types::AdminConfigEntry;
+
+/// The documented settings; every `NAME=` line, commented out or not, is one
+const ENV_EXAMPLE: &str = include_str!("../../.env.example");
+
+/// Name segments marking a variable that holds a secret, singular or plural
+const SECRET_SEGMENTS: &[&str] = &["KEY", "SECRET", "TOKEN", "PASSWORD", "PASSWD", "CREDENTIAL"];
+
+/// Settings named like secrets that only hold flags, counts or property names
+const NOT_SECRET: &[&str] = &[
+    "API_KEYS_REQUIRED",
+    "DEMO_LLM_DAILY_TOKENS",
+    "MPN_PROPERTY_KEYS",
+];
+
+const REDACTED: &str = "<redacted>";
+
+/// Names of the settings documented in .env.example, in file order
+pub fn documented_names() -> Vec<&'static str> {
+    let mut names: Vec<&str> = Vec::new();
+    for line in ENV_EXAMPLE.lines() {
+        let line = line.trim_start_matches('#').trim();
+        let Some((name, _)) = line.split_once('=') else {
+            continue;
+        };
+        let valid = name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+        if valid && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+fn is_secret(name: &str) -> bool {
+    !NOT_SECRET.contains(&name)
+        && name.split('_').any(|segment| {
+            let singular = segment.strip_suffix('S').unwrap_or(segment);
+            SECRET_SEGMENTS.contains(&segment) || SECRET_SEGMENTS.contains(&singular)
+        })
+}
+
+/// A setting's value as safe to show: secrets are replaced, and so are
+/// passwords inside URLs (e.g. DATABASE_URL). Returns (value, redacted).
+pub fn redact(name: &str, value: &str) -> (String, bool) {
+    if is_secret(name) {
+        return (REDACTED.to_string(), true);
+    }
+    match Url::parse(value) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some(REDACTED));
+            (url.to_string(), true)
+        }
+        _ => (value.to_string(), false),
+    }
+}
+
+/// Values of the secret settings set in this process, and passwords inside
+/// URL settings: what must never appear in output
+pub fn secret_values() -> Vec<String> {
+    documented_names()
+        .into_iter()
+        .filter_map(|name| {
+            let value = secret_settings::get(name)?;
+            if is_secret(name) {
+                return Some(value.trim().to_string());
+            }
+            let url = Url::parse(&value).ok()?;
+            url.password().map(str::to_string)
+        })
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+/// Every documented setting with its value in this process; unset settings
+/// use their defaults
+pub fn entries() -> Vec<AdminConfigEntry> {
+    documented_names()
+        .into_iter()
+        .map(|name| {
+            let value = secret_settings::get(name).filter(|v| !v.is_empty());
+            let (value, redacted) = match value {
+                Some(value) => {
+                    let (value, redacted) = redact(name, &value);
+                    (Some(value), redacted)
+                }
+                None => (None, false),
+            };
+            AdminConfigEntry {
+                name: name.to_string(),
+                set: value.is_some(),
+                value,
+                redacted,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_secret() {
+        for name in [
+            "ADMIN_TOKEN",
+            "XAI_API_KEY",
+            "S3_SECRET_ACCESS_KEY",
+            "SECRETS_ENCRYPTION_KEY",
+            "SECRETS_PREVIOUS_KEYS",
+            "GIT_PASSWORD",
+            "GITHUB_WEBHOOK_SECRET",
+        ] {
+            assert!(is_secret(name), "{} should be redacted", name);
+        }
+        for name in ["DATABASE_URL", "API_KEYS_REQUIRED", "PORT", "TRUST_PROXY"] {
+            assert!(!is_secret(name), "{} should be shown", name);
+        }
+    }
+
+    #[test]
+    fn test_redact_documented_secrets() {
+        for name in documented_names() {
+            if name.contains("SECRET") && !NOT_SECRET.contains(&name) {
+                assert_eq!(
+                    redact(name, "hunter2"),
+                    (REDACTED.to_string(), true),
+                    "{}",
+                    name
+                );
+            }
+        }
+        let (url, redacted) = redact("DATABASE_URL", "postgres://user:hunter2@db/kicad");
+        assert!(redacted);
+        assert!(!url.contains("hunter2"));
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Search results of a part provider by query, shared by every caller.
+///
+/// Queries compare trimmed and ignoring case. Entries expire after `ttl`, and
+/// at most `capacity` are kept: storing one more first drops the expired
+/// entries, then the least recently used.
+pub struct SearchCache<T> {
+    ttl: Duration,
+    capacity: usize,
+    state: Mutex<CacheState<T>>,
+}
+
+struct CacheState<T> {
+    entries: HashMap<String, CacheEntry<T>>,
+    // Incremented on every read and write; an entry's stamp orders it by use
+    clock: u64,
+}
+
+struct CacheEntry<T> {
+    value: T,
+    fetched_at: Instant,
+    used: u64,
+}
+
+fn cache_key(query: &str) -> String {
+    query.trim().to_uppercase()
+}
+
+impl<T: Clone> SearchCache<T> {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            state: Mutex::new(CacheState {
+                entries: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    /// Results stored for the query, unless they have expired
+    pub fn get(&self, query: &str) -> Option<T> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.clock += 1;
+        let clock = state.clock;
+        let key = cache_key(query);
+        let entry = state.entries.get_mut(&key)?;
+        if entry.fetched_at.elapsed() >= self.ttl {
+            state.entries.remove(&key);
+            return None;
+        }
+        entry.used = clock;
+        Some(entry.value.clone())
+    }
+
+    /// Store the results of a query, evicting to stay within the capacity
+    pub fn insert(&self, query: &str, value: T) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.clock += 1;
+        let clock = state.clock;
+        let key = cache_key(query);
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.capacity {
+            let ttl = self.ttl;
+            state.entries.retain(|_, e| e.fetched_at.elapsed() < ttl);
+            if state.entries.len() >= self.capacity {
+                let oldest = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, e)| e.used)
+                    .map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    state.entries.remove(&oldest);
+                }
+            }
+        }
+        state.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                fetched_at: Instant::now(),
+                used: clock,
+            },
+        );
+    }
+
+    /// Number of entries held, expired ones included
+    pub fn len(&self) -> usize {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entries
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Spaces outgoing requests to a provider at least `interval` apart.
+///
+/// Each caller reserves the next free slot and then sleeps until it without
+/// holding the lock, so a queue of waiting callers doesn't block the others
+/// from reserving.
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub const fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    /// Wait until this caller may send a request
+    pub async fn wait(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + self.interval);
+            slot
+        };
+        tokio::time::sleep_until(slot.into()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    #[test]
+    fn queries_compare_trimmed_and_ignoring_case() {
+        let cache = SearchCache::new(HOUR, 10);
+        cache.insert(" esp32-wroom-32e ", vec![1]);
+        assert_eq!(cache.get("ESP32-WROOM-32E"), Some(vec![1]));
+        assert_eq!(cache.get("ESP32-WROOM-32D"), None);
+    }
+
+    #[test]
+    fn expired_entries_are_not_served() {
+        let cache = SearchCache::new(Duration::ZERO, 10);
+        cache.insert("LM358", 1);
+        assert_eq!(cache.get("LM358"), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn least_recently_used_entry_is_evicted_at_capacity() {
+        let cache = SearchCache::new(HOUR, 2);
+        cache.insert("A", 1);
+        cache.insert("B", 2);
+        // Reading A makes B the least recently used
+        assert_eq!(cache.get("A"), Some(1));
+        cache.insert("C", 3);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("A"), Some(1));
+        assert_eq!(cache.get("B"), None);
+        assert_eq!(cache.get("C"), Some(3));
+    }
+
+    #[test]
+    fn replacing_an_entry_evicts_nothing() {
+        let cache = SearchCache::new(HOUR, 2);
+        cache.insert("A", 1);
+        cache.insert("B", 2);
+        cache.insert("a", 3);
+        assert_eq!(cache.get("A"), Some(3));
+        assert_eq!(cache.get("B"), Some(2));
+    }
+
+    #[tokio::test]
+    async fn requests_are_spaced_by_the_interval() {
+        let interval = Duration::from_millis(50);
+        let limiter = RateLimiter::new(interval);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.wait().await;
+        }
+        assert!(start.elapsed() >= interval * 2);
+    }
+}
@@ -0,0 +1,53 @@
+use anyhow::Result;
+use tracing::{info, warn};
+
+use crate::services::secret_store;
+use kicad_db::secrets::{self, Keyring, SecretError};
+use kicad_db::PgPool;
+
+/// Secret settings that can be kept encrypted in the database, set through the
+/// admin API, instead of in the environment. A stored value takes precedence
+/// over the environment variable of the same name.
+pub const MANAGED: &[&str] = &[
+    "GITHUB_TOKEN",
+    "GITHUB_WEBHOOK_SECRET",
+    "NOTIFICATION_WEBHOOK_SECRET",
+];
+
+/// Whether `name` can be stored
+pub fn is_managed(name: &str) -> bool {
+    MANAGED.contains(&name)
+}
+
+/// A setting's value: the stored one, else the environment variable
+pub fn get(name: &str) -> Option<String> {
+    if let Some(value) = secret_store::open(name) {
+        return Some(value);
+    }
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+/// Whether the setting's value comes from the database
+pub fn is_stored(name: &str) -> bool {
+    secret_store::contains(name)
+}
+
+/// Encrypt and store a setting, replacing any stored value. Takes effect in
+/// this process immediately and in others once notified of the change.
+pub async fn set(pool: &PgPool, name: &str, value: &str) -> Result<(), SecretError> {
+    secrets::put_secret(pool, &Keyring::from_env()?, name, value).await?;
+    if let Err(e) = secret_store::reload(pool).await {
+        warn!("Failed to reload stored secrets: {}", e);
+    }
+    info!("Stored setting {}", name);
+    Ok(())
+}
+
+/// Delete a stored setting, falling back to the environment
+pub async fn remove(pool: &PgPool, name: &str) -> Result<bool> {
+    let removed = secrets::delete_secret(pool, name).await?;
+    if let Err(e) = secret_store::reload(pool).await {
+        warn!("Failed to reload stored secrets: {}", e);
+    }
+    Ok(removed)
+}
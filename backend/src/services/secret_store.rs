@@ -0,0 +1,100 @@
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use sqlx::postgres::PgListener;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::services::credentials;
+use kicad_db::secrets::{self, Keyring, SealedSecret, CHANGED_CHANNEL};
+use kicad_db::PgPool;
+
+// Reload this often even without a notification, in case one was missed
+const REFRESH_INTERVAL: Duration = Duration::from_secs(15);
+// Wait before connecting again after the listener's connection fails
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Rows of the secrets table as last loaded, still sealed, keyed by name. Values
+// are only opened when read, so none is kept in the clear.
+static SEALED: Lazy<RwLock<HashMap<String, SealedSecret>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// A stored secret's value, opened with the configured keys. None when it
+/// isn't stored or doesn't open; `reload` warns about the latter.
+pub fn open(name: &str) -> Option<String> {
+    sealed(name)?.open(&Keyring::from_env().ok()?).ok()
+}
+
+/// A stored secret, still sealed
+pub fn sealed(name: &str) -> Option<SealedSecret> {
+    SEALED.read().unwrap().get(name).cloned()
+}
+
+/// Whether a secret of that name is stored
+pub fn contains(name: &str) -> bool {
+    SEALED.read().unwrap().contains_key(name)
+}
+
+/// Load the sealed rows of the secrets table, replacing what was loaded before.
+/// Secrets that don't open with the configured keys are kept but warned about,
+/// and providers whose credentials changed drop their access tokens.
+pub async fn reload(pool: &PgPool) -> Result<usize> {
+    let loaded: HashMap<String, SealedSecret> = secrets::list_sealed(pool)
+        .await?
+        .into_iter()
+        .map(|s| (s.name.clone(), s))
+        .collect();
+
+    if !loaded.is_empty() {
+        match Keyring::from_env() {
+            Ok(keyring) => {
+                for secret in loaded.values() {
+                    if let Err(e) = secret.open(&keyring) {
+                        warn!("Stored secret {} can't be opened: {}", secret.name, e);
+                    }
+                }
+            }
+            Err(e) => warn!("Stored secrets can't be opened: {}", e),
+        }
+    }
+
+    let count = loaded.len();
+    let previous = std::mem::replace(&mut *SEALED.write().unwrap(), loaded);
+    let current = SEALED.read().unwrap();
+    for name in previous.keys().chain(current.keys()) {
+        if previous.get(name) != current.get(name) {
+            credentials::secret_changed(name);
+        }
+    }
+    Ok(count)
+}
+
+/// Keep the loaded secrets current: reload whenever the secrets table changes,
+/// as notified on CHANGED_CHANNEL, and every REFRESH_INTERVAL
+pub fn spawn_listener(pool: PgPool) {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = listen(&pool).await {
+                warn!("Listening for changed secrets failed: {}", e);
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+}
+
+async fn listen(pool: &PgPool) -> Result<()> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANGED_CHANNEL).await?;
+    info!("Listening for changed secrets on {}", CHANGED_CHANNEL);
+    loop {
+        // Changes made before listening, or while the connection was being
+        // re-established (None), weren't notified
+        if let Err(e) = reload(pool).await {
+            warn!("Failed to reload stored secrets: {}", e);
+        }
+        if let Ok(received) = tokio::time::timeout(REFRESH_INTERVAL, listener.try_recv()).await {
+            received?;
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use tracing::warn;
+
+use kicad_db::{summaries, PgPool};
+
+/// Bump whenever the rules change, so stored read-aloud blurbs are made again
+pub const VERSION: &str = "speech-v1";
+
+// Sentences kept: the read-aloud text is a short blurb, not the full analysis
+const MAX_SENTENCES: usize = 3;
+
+/// Words spoken in full, matched as whole words (abbreviations ignoring case)
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("e.g.", "for example"),
+    ("i.e.", "that is"),
+    ("etc.", "and so on"),
+    ("vs.", "versus"),
+    ("vs", "versus"),
+    ("approx.", "approximately"),
+    ("w/", "with"),
+    ("w/o", "without"),
+    ("&", "and"),
+    ("->", "to"),
+    ("→", "to"),
+    ("ref", "reference"),
+    ("refs", "references"),
+    ("qty", "quantity"),
+    ("pkg", "package"),
+];
+
+/// Acronyms spoken in full, matched as whole words with their case
+const ACRONYMS: &[(&str, &str)] = &[
+    ("BOM", "bill of materials"),
+    ("DRC", "design rule check"),
+    ("ERC", "electrical rule check"),
+    ("ESD", "electrostatic discharge"),
+    ("GND", "ground"),
+    ("LDO", "low-dropout regulator"),
+    ("MCU", "microcontroller"),
+    ("MPN", "manufacturer part number"),
+    ("PCB", "circuit board"),
+    ("PSU", "power supply"),
+];
+
+/// Units after a number, with their singular and plural names
+const UNITS: &[(&str, &str, &str)] = &[
+    ("kΩ", "kilohm", "kilohms"),
+    ("kohm", "kilohm", "kilohms"),
+    ("MΩ", "megohm", "megohms"),
+    ("Ω", "ohm", "ohms"),
+    ("ohm", "ohm", "ohms"),
+    ("pF", "picofarad", "picofarads"),
+    ("nF", "nanofarad", "nanofarads"),
+    ("uF", "microfarad", "microfarads"),
+    ("µF", "microfarad", "microfarads"),
+    ("nH", "nanohenry", "nanohenries"),
+    ("uH", "microhenry", "microhenries"),
+    ("µH", "microhenry", "microhenries"),
+    ("mH", "millihenry", "millihenries"),
+    ("uA", "microamp", "microamps"),
+    ("µA", "microamp", "microamps"),
+    ("mA", "milliamp", "milliamps"),
+    ("A", "amp", "amps"),
+    ("mV", "millivolt", "millivolts"),
+    ("kV", "kilovolt", "kilovolts"),
+    ("V", "volt", "volts"),
+    ("mW", "milliwatt", "milliwatts"),
+    ("W", "watt", "watts"),
+    ("kHz", "kilohertz", "kilohertz"),
+    ("MHz", "megahertz", "megahertz"),
+    ("GHz", "gigahertz", "gigahertz"),
+    ("Hz", "hertz", "hertz"),
+    ("mm", "millimeter", "millimeters"),
+];
+
+/// Multipliers written in place of the decimal point of a value, e.g. "4k7"
+const VALUE_MULTIPLIERS: &[(char, &str)] = &[('R', "ohms"), ('k', "kilohms"), ('M', "megohms")];
+
+/// Format a summary is returned in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryFormat {
+    Standard,
+    /// Plain spoken text for reading aloud
+    Tts,
+}
+
+impl SummaryFormat {
+    /// "standard" (default) or "tts"
+    pub fn parse(format: Option<&str>) -> Result<Self, String> {
+        match format.unwrap_or("standard") {
+            "standard" => Ok(Self::Standard),
+            "tts" => Ok(Self::Tts),
+            other => Err(format!(
+                "Unknown summary format: {} (expected standard or tts)",
+                other
+            )),
+        }
+    }
+
+    /// A generated summary in this format
+    pub fn apply(self, summary: String) -> String {
+        match self {
+            Self::Standard => summary,
+            Self::Tts => from_text(&summary),
+        }
+    }
+}
+
+/// Inline markdown removed: links and images keep their text, code spans
+/// their content, and emphasis markers and bare URLs are dropped
+fn strip_inline(line: &str) -> String {
+    let mut out = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find('[') {
+        let (before, from) = rest.split_at(start);
+        out.push_str(before.strip_suffix('!').unwrap_or(before));
+        let link = from.find("](").and_then(|mid| {
+            let end = from[mid..].find(')')? + mid;
+            Some((&from[1..mid], end))
+        });
+        match link {
+            Some((text, end)) => {
+                out.push_str(text);
+                rest = &from[end + 1..];
+            }
+            None => {
+                out.push('[');
+                rest = &from[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+
+    out.replace("**", "")
+        .replace("__", "")
+        .replace("~~", "")
+        .replace(['*', '`'], "")
+        .split_whitespace()
+        .filter(|word| !word.starts_with("http://") && !word.starts_with("https://"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A value in resistor notation ("4k7", "10k", "0R1") spoken as a number
+fn spoken_value(word: &str) -> Option<String> {
+    let (at, multiplier) = word.char_indices().find_map(|(i, c)| {
+        VALUE_MULTIPLIERS
+            .iter()
+            .find(|(m, _)| *m == c)
+            .map(|(_, name)| (i, *name))
+    })?;
+    let (whole, fraction) = (&word[..at], &word[at + 1..]);
+    let digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if whole.is_empty() || !digits(whole) || !digits(fraction) {
+        return None;
+    }
+    Some(match fraction {
+        "" => format!("{} {}", whole, multiplier),
+        _ => format!("{} point {} {}", whole, fraction, multiplier),
+    })
+}
+
+/// A number with a unit ("10uF", "3.3V") spoken with the unit's name
+fn spoken_quantity(word: &str) -> Option<String> {
+    let split = word.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (number, unit) = word.split_at(split);
+    if number.is_empty() || !number.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let (_, singular, plural) = UNITS.iter().find(|(u, _, _)| *u == unit)?;
+    let name = if number == "1" { singular } else { plural };
+    Some(format!("{} {}", number.replace('.', " point "), name))
+}
+
+fn spoken_word(word: &str) -> String {
+    let word = word.replace("(s)", "s");
+    let start = word
+        .find(|c: char| c.is_alphanumeric() || "&→-".contains(c))
+        .unwrap_or(word.len());
+    let end = word
+        .rfind(|c: char| c.is_alphanumeric() || "&→>/Ωµ".contains(c))
+        .map_or(start, |i| {
+            i + word[i..].chars().next().map_or(1, char::len_utf8)
+        });
+    if start >= end {
+        return word.to_string();
+    }
+    // Punctuation around the word stays, except the dot of "e.g." and the like
+    let (lead, mut core, mut trail) = (&word[..start], &word[start..end], &word[end..]);
+    let with_dot = &word[start..(end + 1).min(word.len())];
+    if trail.starts_with('.')
+        && ABBREVIATIONS
+            .iter()
+            .any(|(a, _)| *a == with_dot.to_lowercase())
+    {
+        (core, trail) = (with_dot, &trail[1..]);
+    }
+    let core = core.to_string();
+    let lower = core.to_lowercase();
+
+    let spoken = ABBREVIATIONS
+        .iter()
+        .find(|(a, _)| *a == lower)
+        .or_else(|| ACRONYMS.iter().find(|(a, _)| *a == core))
+        .map(|(_, spoken)| spoken.to_string())
+        .or_else(|| spoken_quantity(&core))
+        .or_else(|| spoken_value(&core))
+        .unwrap_or_else(|| {
+            // "blurb/description" reads as "blurb or description"
+            match core.split_once('/') {
+                Some((a, b))
+                    if a.chars().all(char::is_alphabetic)
+                        && b.chars().all(char::is_alphabetic)
+                        && !a.is_empty()
+                        && !b.is_empty() =>
+                {
+                    format!("{} or {}", a, b)
+                }
+                _ => core,
+            }
+        });
+    format!("{}{}{}", lead, spoken, trail)
+}
+
+fn end_sentence(text: &mut String) {
+    let trimmed = text.trim_end().len();
+    text.truncate(trimmed);
+    if !text.is_empty() && !text.ends_with(['.', '!', '?', ':']) {
+        text.push('.');
+    }
+}
+
+/// A short, plain-spoken variant of a summary for reading aloud: markdown
+/// and URLs are removed, headings and list items become sentences,
+/// abbreviations, acronyms and units are spelled out, and only the first
+/// few sentences are kept.
+pub fn from_text(text: &str) -> String {
+    let mut plain = String::new();
+    let mut in_code_block = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block || line.chars().all(|c| "-*_=|: ".contains(c)) {
+            // Code, rules and table separators aren't read out
+            if line.is_empty() {
+                end_sentence(&mut plain);
+            }
+            continue;
+        }
+
+        let heading = line.trim_start_matches('#');
+        let quoted = heading.trim_start_matches('>').trim_start();
+        let item = ["- ", "* ", "+ "]
+            .iter()
+            .find_map(|marker| quoted.strip_prefix(marker))
+            .or_else(|| {
+                let digits = quoted.find(|c: char| !c.is_ascii_digit())?;
+                let rest = &quoted[digits..];
+                (digits > 0)
+                    .then(|| rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")))
+                    .flatten()
+            });
+        let standalone = item.is_some() || heading.len() != line.len() || line.starts_with('|');
+        let content = item
+            .unwrap_or(quoted)
+            .trim_matches(|c: char| c == '|' || c.is_whitespace())
+            .replace(" | ", ", ");
+
+        if standalone {
+            end_sentence(&mut plain);
+        }
+        if !plain.is_empty() {
+            plain.push(' ');
+        }
+        plain.push_str(&strip_inline(&content));
+        if standalone {
+            end_sentence(&mut plain);
+        }
+    }
+
+    let spoken: Vec<String> = plain.split_whitespace().map(spoken_word).collect();
+    let mut sentences = 0;
+    let mut out = String::new();
+    for word in spoken {
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(&word);
+        if word.ends_with(['.', '!', '?']) {
+            sentences += 1;
+            if sentences == MAX_SENTENCES {
+                break;
+            }
+        }
+    }
+    end_sentence(&mut out);
+    out
+}
+
+/// The read-aloud variant of a commit's blurb, made from `blurb` (the edit
+/// when there is one) and stored alongside it. A variant made from other
+/// text or older rules is made again.
+pub async fn blurb(pool: &PgPool, repo_url: &str, commit_hash: &str, blurb: &str) -> String {
+    match summaries::cached_speech_blurb(pool, repo_url, commit_hash, blurb, VERSION).await {
+        Ok(Some(speech)) => return speech,
+        Ok(None) => {}
+        Err(e) => warn!(
+            "Failed to load read-aloud blurb for {}@{}: {}",
+            repo_url, commit_hash, e
+        ),
+    }
+    let speech = from_text(blurb);
+    if let Err(e) =
+        summaries::store_speech_blurb(pool, repo_url, commit_hash, blurb, VERSION, &speech).await
+    {
+        warn!(
+            "Failed to store read-aloud blurb for {}@{}: {}",
+            repo_url, commit_hash, e
+        );
+    }
+    speech
+}
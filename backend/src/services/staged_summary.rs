@@ -0,0 +1,277 @@
+use anyhow::{Context, Result};
+use futures_util::{stream, StreamExt, TryStreamExt};
+use serde_json::json;
+use tracing::{info, warn};
+
+use crate::services::commit_diff::{self, CommitDiff};
+use crate::services::{
+    deterministic_summary, distill, git, github, llm_usage, output_filter, provenance,
+};
+use kicad_db::{
+    file_summaries::store_file_summaries,
+    messages::{ChatCompletionRequest, Message},
+    provenance::{NewProvenance, ARTIFACT_SUMMARY},
+    xai_client::XaiClient,
+    PgPool,
+};
+
+/// Model used for both stages
+pub const STAGED_SUMMARY_MODEL: &str = "grok-4-1-fast";
+/// Bump whenever the per-file or the commit prompt changes
+pub const STAGED_SUMMARY_PROMPT_VERSION: &str = "staged-summary-v1";
+
+// A single sheet can still add hundreds of parts; keep each file's prompt bounded
+const MAX_FILE_PROMPT_CHARS: usize = 12_000;
+
+const FILE_SYSTEM_PROMPT: &str = r#"You summarize the changes one commit made to a single KiCad schematic sheet.
+You are given the components and nets the commit added, removed or changed on that sheet.
+Reply with at most three plain sentences on what changed and what it is for, naming references where it helps.
+Only state what the data supports. Do not invent part numbers or reasons."#;
+
+const COMMIT_SYSTEM_PROMPT: &str = r#"You summarize a KiCad schematic commit that touched many sheets.
+You are given the commit message, overall component and net counts, and a summary of the changes on each sheet.
+Reply with a short paragraph on what the commit changes in the hardware as a whole, grouping related sheets,
+followed by the most significant changes as a bulleted list.
+Only state what the per-sheet summaries support."#;
+
+/// When a commit is summarized per file before the commit as a whole.
+///
+/// - `SUMMARY_STAGED_MIN_FILES`: files with component or net changes from
+///   which a commit is summarized in stages (default 8)
+/// - `SUMMARY_STAGED_CONCURRENCY`: files summarized at once (default 4)
+#[derive(Debug, Clone, Copy)]
+pub struct StagedSettings {
+    pub min_files: usize,
+    pub concurrency: usize,
+}
+
+impl StagedSettings {
+    pub fn from_env() -> Self {
+        let read = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+        };
+        Self {
+            min_files: read("SUMMARY_STAGED_MIN_FILES").unwrap_or(8).max(1),
+            concurrency: read("SUMMARY_STAGED_CONCURRENCY").unwrap_or(4).max(1),
+        }
+    }
+}
+
+/// One changed file's summary
+#[derive(Debug, Clone)]
+pub struct FileSummary {
+    pub path: String,
+    pub summary: String,
+}
+
+/// A commit summarized in stages
+#[derive(Debug, Clone)]
+pub struct StagedSummary {
+    pub summary: String,
+    /// The per-file summaries, one section per file
+    pub details: String,
+    pub files: Vec<FileSummary>,
+    /// Full prompt of the commit-level stage, system prompt included
+    pub prompt: String,
+}
+
+/// The part of a commit's diff that falls on one sheet
+pub fn file_diff(diff: &CommitDiff, path: &str) -> CommitDiff {
+    let side = |distilled| distill::sheet_distilled(distilled, path).unwrap_or_else(|| json!({}));
+    commit_diff::diff(
+        diff.parent.clone(),
+        vec![path.to_string()],
+        side(&diff.before),
+        side(&diff.after),
+    )
+}
+
+/// Per-file diffs of the changed files with component or net changes, in path order
+pub fn split(diff: &CommitDiff) -> Vec<(String, CommitDiff)> {
+    diff.changed_files
+        .iter()
+        .map(|path| (path.clone(), file_diff(diff, path)))
+        .filter(|(_, file)| !file.is_empty())
+        .collect()
+}
+
+fn file_prompt(path: &str, file: &CommitDiff) -> String {
+    let (blurb, description) = deterministic_summary::commit_summary(file);
+    let mut prompt = format!("Sheet: {}\n{}\n\n{}", path, blurb, description);
+    if prompt.len() > MAX_FILE_PROMPT_CHARS {
+        let mut end = MAX_FILE_PROMPT_CHARS;
+        while !prompt.is_char_boundary(end) {
+            end -= 1;
+        }
+        prompt.truncate(end);
+        prompt.push_str("\n(truncated)");
+    }
+    prompt
+}
+
+fn commit_prompt(message: Option<&str>, diff: &CommitDiff, files: &[FileSummary]) -> String {
+    let (blurb, _) = deterministic_summary::commit_summary(diff);
+    let mut prompt = format!(
+        "Commit message: {}\nOverall: {}\n\nPer-sheet summaries:\n",
+        message.unwrap_or("(no message)"),
+        blurb
+    );
+    for file in files {
+        prompt.push_str(&format!("\n## {}\n{}\n", file.path, file.summary));
+    }
+    prompt
+}
+
+async fn complete(
+    pool: &PgPool,
+    client: &XaiClient,
+    feature: &str,
+    system: &str,
+    prompt: &str,
+) -> Result<String> {
+    let messages = vec![
+        Message::system(system.to_string()),
+        Message::user(prompt.to_string()),
+    ];
+    let request = ChatCompletionRequest::new(messages, STAGED_SUMMARY_MODEL.to_string());
+    let response = client
+        .chat_completion(&request)
+        .await
+        .map_err(|e| anyhow::anyhow!("XAI API call failed: {}", e))?;
+    llm_usage::record_chat(pool, feature, STAGED_SUMMARY_MODEL, response.usage.as_ref()).await;
+
+    let text = response
+        .choices
+        .iter()
+        .find_map(|c| c.message.as_ref().and_then(|m| m.content.clone()))
+        .filter(|t| !t.trim().is_empty())
+        .context("The model returned no summary")?;
+    Ok(output_filter::apply(&text))
+}
+
+/// Summarize one file's part of a commit and record how
+async fn summarize_file(
+    pool: &PgPool,
+    client: &XaiClient,
+    repo_url: &str,
+    commit: &str,
+    path: &str,
+    file: &CommitDiff,
+) -> Result<FileSummary> {
+    let prompt = file_prompt(path, file);
+    let summary = complete(
+        pool,
+        client,
+        "commit_summary_file",
+        FILE_SYSTEM_PROMPT,
+        &prompt,
+    )
+    .await
+    .with_context(|| format!("Failed to summarize {}", path))?;
+
+    let full_prompt = format!("{}\n\n{}", FILE_SYSTEM_PROMPT, prompt);
+    provenance::record(
+        pool,
+        &NewProvenance {
+            artifact: ARTIFACT_SUMMARY,
+            artifact_ref: path,
+            repo_url: Some(repo_url),
+            commit_hash: Some(commit),
+            model: STAGED_SUMMARY_MODEL,
+            prompt_version: STAGED_SUMMARY_PROMPT_VERSION,
+            prompt: Some(&full_prompt),
+            tool_calls: &[],
+            inputs: &[("diff", file.content_hash().as_bytes())],
+            outputs: &[("summary", summary.as_bytes())],
+        },
+    )
+    .await;
+
+    Ok(FileSummary {
+        path: path.to_string(),
+        summary,
+    })
+}
+
+/// Summarize each changed file of `files` (from `split`) independently, at most
+/// `concurrency` at a time, then the commit from those summaries. The per-file
+/// summaries are stored; the commit-level one is left to the caller.
+pub async fn summarize(
+    pool: &PgPool,
+    client: &XaiClient,
+    repo: &str,
+    commit: &str,
+    diff: &CommitDiff,
+    files: &[(String, CommitDiff)],
+    concurrency: usize,
+) -> Result<StagedSummary> {
+    let repo_url = github::repo_url(repo);
+    info!(
+        "Summarizing {}/{} in stages ({} files)",
+        repo,
+        commit,
+        files.len()
+    );
+
+    // Collected first: a stream over a mapping closure isn't Send for the handler
+    let tasks: Vec<_> = files
+        .iter()
+        .map(|(path, file)| summarize_file(pool, client, &repo_url, commit, path, file))
+        .collect();
+    let summaries: Vec<FileSummary> = stream::iter(tasks)
+        .buffered(concurrency)
+        .try_collect()
+        .await?;
+
+    let pairs: Vec<(String, String)> = summaries
+        .iter()
+        .map(|f| (f.path.clone(), f.summary.clone()))
+        .collect();
+    if let Err(e) = store_file_summaries(
+        pool,
+        &repo_url,
+        commit,
+        STAGED_SUMMARY_MODEL,
+        STAGED_SUMMARY_PROMPT_VERSION,
+        &pairs,
+    )
+    .await
+    {
+        warn!(
+            "Failed to store file summaries of {}/{}: {}",
+            repo, commit, e
+        );
+    }
+
+    let message = match git::get_commit_info(repo, commit).await {
+        Ok(info) => info.message,
+        Err(e) => {
+            warn!("Failed to read the message of {}/{}: {}", repo, commit, e);
+            None
+        }
+    };
+    let prompt = commit_prompt(message.as_deref(), diff, &summaries);
+    let summary = complete(
+        pool,
+        client,
+        "commit_summary_reduce",
+        COMMIT_SYSTEM_PROMPT,
+        &prompt,
+    )
+    .await
+    .context("Failed to summarize the commit from its file summaries")?;
+
+    let details = summaries
+        .iter()
+        .map(|f| format!("{}:\n{}", f.path, f.summary))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(StagedSummary {
+        summary,
+        details,
+        files: summaries,
+        prompt: format!("{}\n\n{}", COMMIT_SYSTEM_PROMPT, prompt),
+    })
+}
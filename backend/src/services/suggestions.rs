@@ -0,0 +1,460 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use tracing::{info, warn};
+
+use crate::demo;
+use crate::services::jobs::{self, JobRequest};
+use crate::services::{
+    deterministic_summary, distill, github, llm_usage, output_filter, provenance,
+};
+use crate::types::{RepoSuggestion, RepoSuggestionsResponse};
+use kicad_db::{
+    canonical,
+    messages::{ChatCompletionRequest, Message},
+    provenance::{NewProvenance, ARTIFACT_SUGGESTIONS},
+    read_pool,
+    repo_suggestions::{self, NewRepoSuggestions},
+    utilities::load_environment_file::load_environment_file,
+    xai_client::XaiClient,
+    PgPool,
+};
+
+/// Model used for suggestions
+const SUGGESTIONS_MODEL: &str = "grok-4-1-fast";
+/// Bump whenever the suggestions prompt or outline changes
+pub const SUGGESTIONS_PROMPT_VERSION: &str = "suggestions-v1";
+/// Version of the suggestions built without the model
+pub const TEMPLATE_VERSION: &str = "suggestions-template-v1";
+
+const MAX_SUGGESTIONS: usize = 8;
+// Keep the outline bounded for large designs; the rest is summarized as a count
+const MAX_OUTLINE_PARTS: usize = 80;
+const MAX_OUTLINE_NETS: usize = 60;
+// Parts worth asking about; passives are described by the nets and ICs they serve
+const NOTABLE_CATEGORIES: &[&str] = &["ic", "connector", "transistor"];
+const POWER_NET_PREFIXES: &[&str] = &["VCC", "VDD", "VBUS", "VIN", "VBAT", "VSYS", "VSUP"];
+
+const SYSTEM_PROMPT: &str = r#"You suggest questions a hardware engineer new to a KiCad project would ask to understand it.
+You are given an outline of the design: its sheets, its ICs, connectors and transistors, and its named nets.
+Suggest up to 8 specific questions about the design's subsystems and how they work, e.g. "How is the USB-PD contract negotiated?" or "What protects the battery input?".
+Prefer questions about the most distinctive circuits over generic ones, and cover different parts of the design.
+Reply with only a JSON array of objects with the fields "question", "topic" (a label of two or three words), "sheet" (the sheet path the question is mostly about, or null) and "references" (the component references involved).
+Only ask about what the outline shows. Do not invent parts."#;
+
+/// A distilled component's non-empty text field
+fn text<'a>(component: &'a Value, field: &str) -> Option<&'a str> {
+    component
+        .get(field)
+        .and_then(Value::as_str)
+        .filter(|v| !v.is_empty())
+}
+
+/// Last segment of a hierarchical net name
+fn local_name(net: &str) -> &str {
+    net.rsplit('/').next().unwrap_or(net)
+}
+
+/// Whether a net was named in the schematic rather than by KiCad
+fn is_labelled(net: &str) -> bool {
+    let name = local_name(net);
+    !name.is_empty()
+        && !name.starts_with("Net-(")
+        && !name.starts_with("unconnected-")
+        && name != "unnamed"
+}
+
+fn is_power_net(net: &str) -> bool {
+    let name = local_name(net).to_ascii_uppercase();
+    (name.starts_with('+') || POWER_NET_PREFIXES.iter().any(|p| name.starts_with(p)))
+        && !name.contains("GND")
+}
+
+/// Sheet stem for display: "power" for "hw/power.kicad_sch"
+fn sheet_label(path: &str) -> &str {
+    let file = path.rsplit('/').next().unwrap_or(path);
+    file.strip_suffix(".kicad_sch").unwrap_or(file)
+}
+
+/// What suggestions are generated from
+struct Outline<'a> {
+    /// Sheets by path, with their component counts
+    sheets: Vec<(String, usize)>,
+    /// Notable components: reference, value, category, sheet
+    parts: Vec<(&'a str, Option<&'a str>, &'a str, Option<&'a str>)>,
+    /// Labelled nets with their member counts, most connected first
+    nets: Vec<(&'a str, usize)>,
+}
+
+fn outline(distilled: &Value) -> Outline<'_> {
+    let mut sheets: Vec<(String, usize)> = distill::sheet_manifest(distilled, &[])
+        .into_iter()
+        .map(|s| (s.path, s.component_count))
+        .collect();
+    sheets.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let parts = distilled
+        .get("components")
+        .and_then(Value::as_object)
+        .map(|components| {
+            components
+                .iter()
+                .filter_map(|(reference, component)| {
+                    let category = text(component, "category")?;
+                    NOTABLE_CATEGORIES.contains(&category).then(|| {
+                        (
+                            reference.as_str(),
+                            text(component, "value"),
+                            category,
+                            text(component, "sheet_path"),
+                        )
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut nets: Vec<(&str, usize)> = distilled
+        .get("nets")
+        .and_then(Value::as_object)
+        .map(|nets| {
+            nets.iter()
+                .filter(|(name, _)| is_labelled(name))
+                .map(|(name, members)| {
+                    let count = members.as_object().map_or(0, |m| m.len());
+                    (name.as_str(), count)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    nets.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    Outline {
+        sheets,
+        parts,
+        nets,
+    }
+}
+
+fn prompt(repo: &str, outline: &Outline) -> String {
+    let mut prompt = format!("Repository: {}\n\n## Sheets (components)\n", repo);
+    for (path, count) in &outline.sheets {
+        prompt.push_str(&format!("- {} ({})\n", path, count));
+    }
+
+    prompt.push_str(&format!(
+        "\n## ICs, connectors and transistors ({})\n",
+        outline.parts.len()
+    ));
+    for (reference, value, category, sheet) in outline.parts.iter().take(MAX_OUTLINE_PARTS) {
+        prompt.push_str(&format!(
+            "- {} ({}, {}), sheet {}\n",
+            reference,
+            value.unwrap_or("?"),
+            category,
+            sheet.unwrap_or("/")
+        ));
+    }
+    if outline.parts.len() > MAX_OUTLINE_PARTS {
+        prompt.push_str(&format!(
+            "- ... and {} more\n",
+            outline.parts.len() - MAX_OUTLINE_PARTS
+        ));
+    }
+
+    prompt.push_str(&format!(
+        "\n## Named nets (pins) ({})\n",
+        outline.nets.len()
+    ));
+    for (name, count) in outline.nets.iter().take(MAX_OUTLINE_NETS) {
+        prompt.push_str(&format!("- {} ({})\n", name, count));
+    }
+    if outline.nets.len() > MAX_OUTLINE_NETS {
+        prompt.push_str(&format!(
+            "- ... and {} more\n",
+            outline.nets.len() - MAX_OUTLINE_NETS
+        ));
+    }
+    prompt
+}
+
+/// Suggestions from the model's reply, keeping only sheets and references the
+/// design has
+fn parse(reply: &str, distilled: &Value, outline: &Outline) -> Vec<RepoSuggestion> {
+    let Some(array) = reply
+        .find('[')
+        .zip(reply.rfind(']'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &reply[start..=end])
+    else {
+        return Vec::new();
+    };
+    let Ok(suggestions) = serde_json::from_str::<Vec<RepoSuggestion>>(array) else {
+        return Vec::new();
+    };
+
+    let components = distilled.get("components").and_then(Value::as_object);
+    suggestions
+        .into_iter()
+        .filter_map(|s| {
+            let question = output_filter::apply(s.question.trim());
+            if question.is_empty() {
+                return None;
+            }
+            Some(RepoSuggestion {
+                question,
+                topic: s
+                    .topic
+                    .map(|t| output_filter::apply(t.trim()))
+                    .filter(|t| !t.is_empty()),
+                sheet: s
+                    .sheet
+                    .filter(|sheet| outline.sheets.iter().any(|(path, _)| path == sheet)),
+                references: s
+                    .references
+                    .into_iter()
+                    .filter(|r| components.is_some_and(|c| c.contains_key(r)))
+                    .collect(),
+            })
+        })
+        .take(MAX_SUGGESTIONS)
+        .collect()
+}
+
+/// Suggestions built from the outline alone: the largest sheets, an IC of
+/// each sheet, the busiest supply net and the connectors
+fn template(outline: &Outline) -> Vec<RepoSuggestion> {
+    let mut suggestions = Vec::new();
+
+    // The root sheet of a flat design is the whole design, not an area
+    let hierarchical = outline.sheets.len() > 1;
+    for (path, _) in outline.sheets.iter().filter(|_| hierarchical).take(3) {
+        suggestions.push(RepoSuggestion {
+            question: format!("What does the {} sheet do?", sheet_label(path)),
+            topic: Some(sheet_label(path).to_string()),
+            sheet: Some(path.clone()),
+            references: Vec::new(),
+        });
+    }
+
+    let ics = outline
+        .parts
+        .iter()
+        .filter(|(_, value, category, _)| *category == "ic" && value.is_some());
+    let mut ic_sheets: BTreeSet<Option<&str>> = BTreeSet::new();
+    for (reference, value, _, sheet) in ics {
+        // One IC per sheet, leaving room for the supply net and connectors
+        if suggestions.len() >= MAX_SUGGESTIONS - 3 {
+            break;
+        }
+        if !ic_sheets.insert(*sheet) {
+            continue;
+        }
+        suggestions.push(RepoSuggestion {
+            question: format!(
+                "What is {} ({}) used for, and what connects to it?",
+                reference,
+                value.unwrap_or_default()
+            ),
+            topic: value.map(ToString::to_string),
+            sheet: sheet.map(ToString::to_string),
+            references: vec![reference.to_string()],
+        });
+    }
+
+    if let Some((net, _)) = outline.nets.iter().find(|(name, _)| is_power_net(name)) {
+        suggestions.push(RepoSuggestion {
+            question: format!(
+                "How is {} generated and what does it supply?",
+                local_name(net)
+            ),
+            topic: Some(local_name(net).to_string()),
+            sheet: None,
+            references: Vec::new(),
+        });
+    }
+
+    let connectors = outline
+        .parts
+        .iter()
+        .filter(|(_, _, category, _)| *category == "connector");
+    for (reference, value, _, sheet) in connectors.take(2) {
+        let question = match value {
+            Some(value) => format!("What goes through {} ({})?", reference, value),
+            None => format!("What goes through {}?", reference),
+        };
+        suggestions.push(RepoSuggestion {
+            question,
+            topic: Some(reference.to_string()),
+            sheet: sheet.map(ToString::to_string),
+            references: vec![reference.to_string()],
+        });
+    }
+
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Ask the model for suggestions. Returns them with the full prompt.
+async fn generate_llm(
+    pool: &PgPool,
+    repo: &str,
+    distilled: &Value,
+    outline: &Outline<'_>,
+) -> Result<(Vec<RepoSuggestion>, String)> {
+    demo::check_llm_budget(pool).await?;
+    load_environment_file(None)
+        .map_err(|e| anyhow::anyhow!("Failed to load environment: {}", e))?;
+    let xai_client =
+        XaiClient::new().map_err(|e| anyhow::anyhow!("Failed to initialize XAI client: {}", e))?;
+
+    let prompt = prompt(repo, outline);
+    let messages = vec![
+        Message::system(SYSTEM_PROMPT.to_string()),
+        Message::user(prompt.clone()),
+    ];
+    let request = ChatCompletionRequest::new(messages, SUGGESTIONS_MODEL.to_string());
+    let response = xai_client
+        .chat_completion(&request)
+        .await
+        .map_err(|e| anyhow::anyhow!("XAI API call failed: {}", e))?;
+    llm_usage::record_chat(
+        pool,
+        "suggestions",
+        SUGGESTIONS_MODEL,
+        response.usage.as_ref(),
+    )
+    .await;
+
+    let reply = response
+        .choices
+        .iter()
+        .find_map(|c| c.message.as_ref().and_then(|m| m.content.clone()))
+        .unwrap_or_default();
+    let suggestions = parse(&reply, distilled, outline);
+    anyhow::ensure!(!suggestions.is_empty(), "The model returned no suggestions");
+    Ok((suggestions, format!("{}\n\n{}", SYSTEM_PROMPT, prompt)))
+}
+
+/// Generate and store the suggested questions of a repository from a commit's
+/// distilled data. The model writes them when it can; otherwise they are
+/// built from templates. Returns the number stored.
+pub async fn generate(pool: &PgPool, repo: &str, commit: &str) -> Result<usize> {
+    let distilled = distill::get_or_distill(pool, repo, commit)
+        .await
+        .with_context(|| format!("Failed to distill {}@{}", repo, commit))?;
+    let outline = outline(&distilled);
+
+    let (suggestions, model, prompt_version, prompt) =
+        match generate_llm(pool, repo, &distilled, &outline).await {
+            Ok((suggestions, prompt)) => (
+                suggestions,
+                SUGGESTIONS_MODEL,
+                SUGGESTIONS_PROMPT_VERSION,
+                Some(prompt),
+            ),
+            Err(e) => {
+                warn!(
+                    "Using template suggestions for {}@{}: {:#}",
+                    repo, commit, e
+                );
+                (
+                    template(&outline),
+                    deterministic_summary::MODEL,
+                    TEMPLATE_VERSION,
+                    None,
+                )
+            }
+        };
+
+    let repo_url = github::repo_url(repo);
+    let suggestions_json = serde_json::to_value(&suggestions)?;
+    let stored = repo_suggestions::store_suggestions(
+        pool,
+        &repo_url,
+        &NewRepoSuggestions {
+            commit_hash: commit,
+            suggestions: &suggestions_json,
+            model,
+            prompt_version,
+        },
+    )
+    .await
+    .context("Failed to store suggestions")?;
+    anyhow::ensure!(stored, "Invalid repository: {}", repo);
+
+    if let Some(prompt) = &prompt {
+        provenance::record(
+            pool,
+            &NewProvenance {
+                artifact: ARTIFACT_SUGGESTIONS,
+                artifact_ref: "onboarding",
+                repo_url: Some(&repo_url),
+                commit_hash: Some(commit),
+                model,
+                prompt_version,
+                prompt: Some(prompt),
+                tool_calls: &[],
+                inputs: &[("distilled", canonical::content_hash(&distilled).as_bytes())],
+                outputs: &[(
+                    "suggestions",
+                    &canonical::to_canonical_vec(&suggestions_json),
+                )],
+            },
+        )
+        .await;
+    }
+
+    info!(
+        "Stored {} suggestions for {}@{} ({})",
+        suggestions.len(),
+        repo,
+        commit,
+        model
+    );
+    Ok(suggestions.len())
+}
+
+/// Generate a newly onboarded repository's suggestions: on a worker with the
+/// job queue, otherwise in the background of this process. Failures are
+/// logged rather than returned.
+pub async fn generate_later(pool: &PgPool, repo: &str, commit: &str) {
+    if jobs::queue_enabled() {
+        let request = JobRequest::GenerateSuggestions {
+            repo: repo.to_string(),
+            commit: commit.to_string(),
+        };
+        if let Err(e) = jobs::enqueue(pool, &request).await {
+            warn!("Failed to queue suggestions for {}@{}: {}", repo, commit, e);
+        }
+        return;
+    }
+
+    let (pool, repo, commit) = (pool.clone(), repo.to_string(), commit.to_string());
+    tokio::spawn(async move {
+        if let Err(e) = generate(&pool, &repo, &commit).await {
+            warn!(
+                "Failed to generate suggestions for {}@{}: {:#}",
+                repo, commit, e
+            );
+        }
+    });
+}
+
+/// A repository's stored suggestions, if any were generated
+pub async fn get(pool: &PgPool, repo: &str) -> Result<Option<RepoSuggestionsResponse>> {
+    let repo_url = github::repo_url(repo);
+    let Some(stored) = repo_suggestions::get_suggestions(read_pool(pool), &repo_url).await? else {
+        return Ok(None);
+    };
+    let suggestions =
+        serde_json::from_value(stored.suggestions).context("Stored suggestions are malformed")?;
+    Ok(Some(RepoSuggestionsResponse {
+        repo: repo.to_string(),
+        commit: stored.commit_hash,
+        suggestions,
+        model: stored.model,
+        prompt_version: stored.prompt_version,
+        generated_at: stored.generated_at,
+    }))
+}
@@ -0,0 +1,138 @@
+use anyhow::Result;
+use serde_json::json;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::services::jobs::{self, JobRequest};
+use crate::services::{audit, distill, git, hook, leader};
+use kicad_db::{summaries, PgPool};
+
+// A claimed summary that hasn't been regenerated is handed out again after this long
+const CLAIM_RETRY_SECS: i64 = 60 * 60;
+
+/// Background regeneration of stale summaries.
+///
+/// - `SUMMARY_REFRESH_BATCH`: summaries regenerated per pass (default 10)
+/// - `SUMMARY_REFRESH_INTERVAL_SECS`: time between passes (default 300; 0 disables the background loop)
+#[derive(Debug, Clone, Copy)]
+pub struct RefreshSettings {
+    pub batch: i64,
+    pub interval: Option<Duration>,
+}
+
+impl RefreshSettings {
+    pub fn from_env() -> Self {
+        let read = |name: &str| std::env::var(name).ok().and_then(|v| v.parse::<u64>().ok());
+        let batch = read("SUMMARY_REFRESH_BATCH").unwrap_or(10).max(1) as i64;
+        let interval = match read("SUMMARY_REFRESH_INTERVAL_SECS").unwrap_or(300) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        Self { batch, interval }
+    }
+}
+
+/// What one refresh pass did
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RefreshOutcome {
+    /// Summaries newly flagged stale by this pass
+    pub marked_stale: u64,
+    /// Regeneration jobs handed to workers
+    pub queued: usize,
+    /// Summaries regenerated in this process (job queue disabled)
+    pub regenerated: usize,
+    pub failed: usize,
+    /// Summaries still flagged stale afterwards
+    pub remaining: i64,
+}
+
+/// Flag summaries produced by an older prompt or distiller, then regenerate up to
+/// `batch` of them: through the job queue when enabled, otherwise inline.
+/// `actor` is recorded in the audit log against each queued or regenerated summary.
+pub async fn refresh_stale(pool: &PgPool, batch: i64, actor: &str) -> Result<RefreshOutcome> {
+    let mut outcome = RefreshOutcome {
+        marked_stale: summaries::mark_stale(
+            pool,
+            hook::OVERVIEW_PROMPT_VERSION,
+            distill::distiller_version(),
+        )
+        .await?,
+        ..Default::default()
+    };
+
+    for stale in summaries::claim_stale(pool, batch, CLAIM_RETRY_SECS).await? {
+        let Some(repo) = git::repo_slug(&stale.repo_url) else {
+            warn!(
+                "Cannot regenerate summary for unrecognised repo URL {}",
+                stale.repo_url
+            );
+            outcome.failed += 1;
+            continue;
+        };
+        let request = JobRequest::RegenerateSummary {
+            repo: repo.to_string(),
+            commit: stale.commit_hash.clone(),
+        };
+
+        let result = if jobs::queue_enabled() {
+            match jobs::enqueue(pool, &request).await {
+                Ok(job_id) => {
+                    outcome.queued += 1;
+                    audit::record(
+                        pool,
+                        audit::SUMMARY_QUEUED,
+                        actor,
+                        Some(&stale.repo_url),
+                        Some(&stale.commit_hash),
+                        json!({ "job_id": job_id }),
+                    )
+                    .await;
+                    Ok(())
+                }
+                Err(e) => Err(e),
+            }
+        } else {
+            hook::regenerate_overview(pool, repo, &stale.commit_hash, actor)
+                .await
+                .map(|_| {
+                    outcome.regenerated += 1;
+                })
+        };
+        if let Err(e) = result {
+            error!(
+                "Failed to regenerate summary for {}/{}: {:#}",
+                repo, stale.commit_hash, e
+            );
+            outcome.failed += 1;
+        }
+    }
+
+    outcome.remaining = summaries::count_stale(pool).await?;
+    Ok(outcome)
+}
+
+/// Periodically regenerate stale summaries in the background
+pub fn spawn_refresh_loop(pool: PgPool, settings: RefreshSettings) {
+    let Some(interval) = settings.interval else {
+        info!("Stale summary refresh disabled (SUMMARY_REFRESH_INTERVAL_SECS=0)");
+        return;
+    };
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if !leader::is_leader(&pool).await {
+                continue;
+            }
+            match refresh_stale(&pool, settings.batch, audit::ACTOR_SCHEDULER).await {
+                Ok(outcome) if outcome.queued + outcome.regenerated + outcome.failed > 0 => info!(
+                    "Stale summary refresh: queued={}, regenerated={}, failed={}, remaining={}",
+                    outcome.queued, outcome.regenerated, outcome.failed, outcome.remaining
+                ),
+                Ok(_) => {}
+                Err(e) => error!("Stale summary refresh failed: {:#}", e),
+            }
+        }
+    });
+}
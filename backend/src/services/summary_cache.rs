@@ -0,0 +1,130 @@
+use tracing::{info, warn};
+
+use crate::services::commit_diff::CommitDiff;
+use crate::services::provenance;
+use kicad_db::provenance::{NewProvenance, ToolCall, ARTIFACT_SUMMARY, ARTIFACT_SUMMARY_REUSE};
+use kicad_db::summary_cache::{reuse_summary, store_summary, CachedSummary, NewCachedSummary};
+use kicad_db::PgPool;
+
+/// What a commit summary was generated for and how
+pub struct Generation<'a> {
+    pub repo_url: &'a str,
+    pub commit: &'a str,
+    pub model: &'a str,
+    pub prompt_version: &'a str,
+    pub prompt: &'a str,
+    pub tool_calls: &'a [ToolCall],
+}
+
+/// Cache key of a commit's structured diff, or None when the commit changed
+/// no components or nets: every such diff looks alike
+pub fn diff_hash(diff: &CommitDiff) -> Option<String> {
+    (!diff.is_empty()).then(|| diff.content_hash())
+}
+
+/// Summary already generated for an identical diff in the same repository. A
+/// reuse for another commit is recorded in the provenance log, pointing at the
+/// original generation.
+pub async fn reuse(
+    pool: &PgPool,
+    diff_sha256: &str,
+    generation: &Generation<'_>,
+) -> Option<CachedSummary> {
+    let cached = match reuse_summary(
+        pool,
+        generation.repo_url,
+        diff_sha256,
+        generation.model,
+        generation.prompt_version,
+    )
+    .await
+    {
+        Ok(cached) => cached?,
+        Err(e) => {
+            warn!("Failed to look up summary of diff {}: {}", diff_sha256, e);
+            return None;
+        }
+    };
+
+    let same_commit = cached.commit_hash == generation.commit
+        && cached.repo_url.eq_ignore_ascii_case(generation.repo_url);
+    if !same_commit {
+        info!(
+            "Reusing summary of {}@{} for {}@{} (identical diff)",
+            cached.repo_url, cached.commit_hash, generation.repo_url, generation.commit
+        );
+        let source = format!("{}@{}", cached.repo_url, cached.commit_hash);
+        let artifact_ref = cached
+            .provenance_id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| source.clone());
+        provenance::record(
+            pool,
+            &NewProvenance {
+                artifact: ARTIFACT_SUMMARY_REUSE,
+                artifact_ref: &artifact_ref,
+                repo_url: Some(generation.repo_url),
+                commit_hash: Some(generation.commit),
+                model: &cached.model,
+                prompt_version: &cached.prompt_version,
+                prompt: None,
+                tool_calls: &[],
+                inputs: &[
+                    ("diff", diff_sha256.as_bytes()),
+                    ("reused_from", source.as_bytes()),
+                ],
+                outputs: &[
+                    ("summary", cached.summary.as_bytes()),
+                    ("details", cached.details.as_bytes()),
+                ],
+            },
+        )
+        .await;
+    }
+    Some(cached)
+}
+
+/// Record a freshly generated summary and keep it for later commits with the
+/// same diff. Returns the provenance record's id.
+pub async fn store(
+    pool: &PgPool,
+    diff_sha256: &str,
+    generation: &Generation<'_>,
+    summary: &str,
+    details: &str,
+) -> Option<i64> {
+    let provenance_id = provenance::record(
+        pool,
+        &NewProvenance {
+            artifact: ARTIFACT_SUMMARY,
+            artifact_ref: "commit_summary",
+            repo_url: Some(generation.repo_url),
+            commit_hash: Some(generation.commit),
+            model: generation.model,
+            prompt_version: generation.prompt_version,
+            prompt: Some(generation.prompt),
+            tool_calls: generation.tool_calls,
+            inputs: &[("diff", diff_sha256.as_bytes())],
+            outputs: &[
+                ("summary", summary.as_bytes()),
+                ("details", details.as_bytes()),
+            ],
+        },
+    )
+    .await;
+
+    let new = NewCachedSummary {
+        diff_sha256,
+        model: generation.model,
+        prompt_version: generation.prompt_version,
+        summary,
+        details,
+        repo_url: generation.repo_url,
+        commit_hash: generation.commit,
+        provenance_id,
+    };
+    if let Err(e) = store_summary(pool, &new).await {
+        warn!("Failed to cache summary of diff {}: {}", diff_sha256, e);
+    }
+    provenance_id
+}
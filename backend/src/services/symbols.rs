@@ -0,0 +1,470 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use tracing::{info, warn};
+
+use crate::services::distill;
+use crate::services::git::{self, SymbolFiles};
+use crate::services::lib_table::{self, LibraryLocation};
+use crate::types::{SymbolCheckResponse, SymbolPinIssue};
+use kicad_db::PgPool;
+
+/// Derived symbols (`extends`) are followed at most this deep
+const MAX_EXTENDS_DEPTH: usize = 8;
+
+/// A parsed S-expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sexp {
+    List(Vec<Sexp>),
+    Atom(String),
+}
+
+impl Sexp {
+    pub fn atom(&self) -> Option<&str> {
+        match self {
+            Sexp::Atom(a) => Some(a),
+            Sexp::List(_) => None,
+        }
+    }
+}
+
+/// Writes the expression back in a form [`parse_sexp_children`] reads as the
+/// same tree: atoms are quoted when they're empty or hold whitespace, parens,
+/// quotes or backslashes.
+impl std::fmt::Display for Sexp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sexp::Atom(atom) => {
+                let bare = !atom.is_empty()
+                    && !atom
+                        .chars()
+                        .any(|c| c.is_whitespace() || matches!(c, '(' | ')' | '"' | '\\'));
+                if bare {
+                    return f.write_str(atom);
+                }
+                f.write_str("\"")?;
+                for c in atom.chars() {
+                    if matches!(c, '"' | '\\') {
+                        f.write_str("\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                f.write_str("\"")
+            }
+            Sexp::List(items) => {
+                f.write_str("(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// Parse a KiCad S-expression document incrementally: each child of the root
+/// list is handed to `each` as soon as it is complete and then dropped, so only
+/// one top-level entry (e.g. one library symbol) is held as a tree at a time.
+/// Iterative, so deeply nested files can't overflow the stack.
+///
+/// Returns false if the document isn't a single well-formed list.
+pub fn parse_sexp_children(input: &str, mut each: impl FnMut(Sexp)) -> bool {
+    let mut stack: Vec<Vec<Sexp>> = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    // Completed nodes directly under the root go to `each` instead of the stack
+    let mut emit = |stack: &mut Vec<Vec<Sexp>>, node: Sexp| match stack.len() {
+        0 => false,
+        1 => {
+            each(node);
+            true
+        }
+        _ => {
+            if let Some(parent) = stack.last_mut() {
+                parent.push(node);
+            }
+            true
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => stack.push(Vec::new()),
+            ')' => {
+                let Some(items) = stack.pop() else {
+                    return false;
+                };
+                if stack.is_empty() {
+                    return true;
+                }
+                emit(&mut stack, Sexp::List(items));
+            }
+            '"' => {
+                let mut atom = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => atom.extend(chars.next()),
+                        '"' => break,
+                        c => atom.push(c),
+                    }
+                }
+                if !emit(&mut stack, Sexp::Atom(atom)) {
+                    return false;
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut atom = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' {
+                        break;
+                    }
+                    atom.push(next);
+                    chars.next();
+                }
+                if !emit(&mut stack, Sexp::Atom(atom)) {
+                    return false;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Pins of a library symbol, by unit. Unit 0 holds pins shared by every unit.
+#[derive(Debug, Default)]
+struct SymbolDef {
+    extends: Option<String>,
+    units: BTreeMap<u32, BTreeSet<String>>,
+}
+
+/// Pin numbers of the `(pin ... (number "1" ...))` entries among `items`
+fn pin_numbers(items: &[Sexp]) -> impl Iterator<Item = String> + '_ {
+    items.iter().filter_map(|item| {
+        let Sexp::List(pin) = item else {
+            return None;
+        };
+        if pin.first().and_then(Sexp::atom) != Some("pin") {
+            return None;
+        }
+        pin.iter().find_map(|field| match field {
+            Sexp::List(number) if number.first().and_then(Sexp::atom) == Some("number") => {
+                number.get(1).and_then(Sexp::atom).map(ToString::to_string)
+            }
+            _ => None,
+        })
+    })
+}
+
+/// Name and pins of a `(symbol "name" ...)` library entry
+fn parse_symbol(symbol: &[Sexp]) -> Option<(String, SymbolDef)> {
+    let name = symbol.get(1).and_then(Sexp::atom)?;
+    let mut def = SymbolDef::default();
+
+    for item in &symbol[2..] {
+        let Sexp::List(list) = item else {
+            continue;
+        };
+        match list.first().and_then(Sexp::atom) {
+            Some("extends") => {
+                def.extends = list.get(1).and_then(Sexp::atom).map(ToString::to_string);
+            }
+            // Units are named "<symbol>_<unit>_<body style>"
+            Some("symbol") => {
+                let unit = list
+                    .get(1)
+                    .and_then(Sexp::atom)
+                    .and_then(|n| n.rsplit('_').nth(1))
+                    .and_then(|u| u.parse::<u32>().ok())
+                    .unwrap_or(0);
+                def.units
+                    .entry(unit)
+                    .or_default()
+                    .extend(pin_numbers(&list[2..]));
+            }
+            _ => {}
+        }
+    }
+    def.units
+        .entry(0)
+        .or_default()
+        .extend(pin_numbers(&symbol[2..]));
+    def.units.retain(|_, pins| !pins.is_empty());
+
+    Some((name.to_string(), def))
+}
+
+/// Symbols of a .kicad_sym library by name, parsed one symbol at a time.
+/// A malformed library yields no symbols.
+fn parse_library(content: &str) -> HashMap<String, SymbolDef> {
+    let mut symbols = HashMap::new();
+    let complete = parse_sexp_children(content, |item| {
+        let Sexp::List(list) = item else {
+            return;
+        };
+        if list.first().and_then(Sexp::atom) == Some("symbol") {
+            if let Some((name, def)) = parse_symbol(&list) {
+                symbols.insert(name, def);
+            }
+        }
+    });
+    if !complete {
+        return HashMap::new();
+    }
+    symbols
+}
+
+/// A symbol's pins by unit, following `extends` to the parent symbol
+fn resolve_units<'a>(
+    library: &'a HashMap<String, SymbolDef>,
+    name: &str,
+) -> Option<&'a BTreeMap<u32, BTreeSet<String>>> {
+    let mut def = library.get(name)?;
+    for _ in 0..MAX_EXTENDS_DEPTH {
+        match &def.extends {
+            Some(parent) if def.units.is_empty() => def = library.get(parent)?,
+            _ => break,
+        }
+    }
+    Some(&def.units)
+}
+
+/// Symbol libraries in the repository by nickname; None for libraries a
+/// sym-lib-table declares outside the repository
+fn repo_libraries(files: &SymbolFiles) -> HashMap<String, Option<HashMap<String, SymbolDef>>> {
+    let mut by_path: HashMap<&str, &str> = files
+        .libraries
+        .iter()
+        .map(|(path, content)| (path.as_str(), content.as_str()))
+        .collect();
+
+    let mut libraries: HashMap<String, Option<HashMap<String, SymbolDef>>> = HashMap::new();
+    for (table_path, table) in &files.lib_tables {
+        for (nickname, uri) in lib_table::parse(table) {
+            match lib_table::locate(table_path, &uri) {
+                LibraryLocation::Project(path) => {
+                    let library = by_path.remove(path.as_str()).map(parse_library);
+                    libraries.insert(nickname, library);
+                }
+                LibraryLocation::KiCad => {}
+                LibraryLocation::External => {
+                    libraries.entry(nickname).or_insert(None);
+                }
+            }
+        }
+    }
+
+    // Libraries not listed in any table are known by their file name
+    for (path, content) in by_path {
+        let stem = path
+            .rsplit('/')
+            .next()
+            .unwrap_or(path)
+            .trim_end_matches(".kicad_sym");
+        libraries
+            .entry(stem.to_string())
+            .or_insert_with(|| Some(parse_library(content)));
+    }
+
+    libraries
+}
+
+/// Order pin numbers numerically where possible ("2" before "10")
+fn sorted_pins(pins: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut pins: Vec<String> = pins.into_iter().collect();
+    pins.sort_by_key(|p| (p.parse::<u64>().map_or((1, 0), |n| (0, n)), p.clone()));
+    pins
+}
+
+/// Compare an instance's pins with its library symbol. Only units the instance
+/// has pins from are expected to be complete, so partly placed multi-unit parts
+/// aren't flagged; shared (unit 0) pins are always expected.
+fn compare_pins(
+    units: &BTreeMap<u32, BTreeSet<String>>,
+    instance: &BTreeSet<String>,
+) -> (Vec<String>, Vec<String>, usize) {
+    let mut covered: Vec<u32> = units
+        .iter()
+        .filter(|(unit, pins)| **unit != 0 && !pins.is_disjoint(instance))
+        .map(|(unit, _)| *unit)
+        .collect();
+    if covered.is_empty() {
+        covered.extend(units.keys().copied().find(|u| *u != 0));
+    }
+
+    let expected: BTreeSet<String> = units
+        .iter()
+        .filter(|(unit, _)| **unit == 0 || covered.contains(unit))
+        .flat_map(|(_, pins)| pins.iter().cloned())
+        .collect();
+    let all: BTreeSet<&String> = units.values().flatten().collect();
+
+    let missing = sorted_pins(expected.difference(instance).cloned());
+    let extra = sorted_pins(instance.iter().filter(|p| !all.contains(p)).cloned());
+    (missing, extra, expected.len())
+}
+
+/// Check every symbol instance against the repository's symbol libraries,
+/// flagging instances whose pins no longer match the library definition
+/// (typically a library edit that wasn't pulled into the schematic).
+/// Symbols from libraries outside the repository are skipped.
+pub async fn check(pool: &PgPool, repo: &str, commit: &str) -> Result<SymbolCheckResponse> {
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+    let libraries = repo_libraries(&git::get_symbol_files(repo, commit).await?);
+
+    let components: Vec<(&String, &Value)> = match distilled.get("components") {
+        Some(Value::Object(obj)) => obj.iter().collect(),
+        _ => Vec::new(),
+    };
+
+    let mut issues = Vec::new();
+    let mut checked = 0;
+    let mut skipped = 0;
+
+    for (reference, comp) in components {
+        if reference.starts_with('#') {
+            continue;
+        }
+        let Some(lib_id) = comp.get("lib_id").and_then(Value::as_str) else {
+            continue;
+        };
+        let Some((nickname, name)) = lib_id.split_once(':') else {
+            skipped += 1;
+            continue;
+        };
+        let Some(Some(library)) = libraries.get(nickname) else {
+            skipped += 1;
+            continue;
+        };
+        checked += 1;
+
+        let sheet_path = comp
+            .get("sheet_path")
+            .and_then(Value::as_str)
+            .map(ToString::to_string);
+        let instance: BTreeSet<String> = comp
+            .get("pins")
+            .and_then(Value::as_array)
+            .map(|pins| {
+                pins.iter()
+                    .filter_map(|p| p.get("number").and_then(Value::as_str))
+                    .map(ToString::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let Some(units) = resolve_units(library, name) else {
+            issues.push(SymbolPinIssue {
+                reference: reference.clone(),
+                lib_id: lib_id.to_string(),
+                sheet_path,
+                kind: "missing_symbol".to_string(),
+                library_pins: 0,
+                instance_pins: instance.len(),
+                missing_pins: Vec::new(),
+                extra_pins: Vec::new(),
+            });
+            continue;
+        };
+
+        let (missing_pins, extra_pins, library_pins) = compare_pins(units, &instance);
+        if !missing_pins.is_empty() || !extra_pins.is_empty() {
+            issues.push(SymbolPinIssue {
+                reference: reference.clone(),
+                lib_id: lib_id.to_string(),
+                sheet_path,
+                kind: "pin_mismatch".to_string(),
+                library_pins,
+                instance_pins: instance.len(),
+                missing_pins,
+                extra_pins,
+            });
+        }
+    }
+
+    let mut symbol_libraries: Vec<String> = libraries
+        .iter()
+        .filter(|(_, library)| library.is_some())
+        .map(|(nickname, _)| nickname.clone())
+        .collect();
+    symbol_libraries.sort();
+    if symbol_libraries.is_empty() {
+        warn!(
+            "No symbol libraries in {}/{}; every symbol was skipped",
+            repo, commit
+        );
+    }
+
+    info!(
+        "Symbol check for {}/{}: {} checked, {} skipped, {} issues",
+        repo,
+        commit,
+        checked,
+        skipped,
+        issues.len()
+    );
+
+    Ok(SymbolCheckResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        symbol_libraries,
+        checked,
+        skipped,
+        passed: issues.is_empty(),
+        issues,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn tree(atom: impl Strategy<Value = String> + 'static) -> impl Strategy<Value = Sexp> {
+        atom.prop_map(Sexp::Atom).prop_recursive(6, 64, 8, |inner| {
+            prop::collection::vec(inner, 0..8).prop_map(Sexp::List)
+        })
+    }
+
+    /// Any atom, including ones that need quoting and escaping
+    fn sexp() -> impl Strategy<Value = Sexp> {
+        tree(prop_oneof!["[a-z_]{1,12}", "-?[0-9]{1,4}(\\.[0-9]{1,4})?", any::<String>()])
+    }
+
+    /// Atoms written bare, like KiCad keywords and numbers
+    fn bare_sexp() -> impl Strategy<Value = Sexp> {
+        tree(prop_oneof!["[a-z_]{1,12}", "-?[0-9]{1,4}(\\.[0-9]{1,4})?"])
+    }
+
+    fn parse(input: &str) -> Option<Vec<Sexp>> {
+        let mut children = Vec::new();
+        parse_sexp_children(input, |node| children.push(node)).then_some(children)
+    }
+
+    proptest! {
+        #[test]
+        fn parse_inverts_display(children in prop::collection::vec(sexp(), 0..8)) {
+            let document = Sexp::List(children.clone()).to_string();
+            prop_assert_eq!(parse(&document), Some(children));
+        }
+
+        #[test]
+        fn parse_ignores_extra_whitespace(children in prop::collection::vec(bare_sexp(), 0..8)) {
+            let document = Sexp::List(children.clone())
+                .to_string()
+                .replace('(', "\n\t( ")
+                .replace(')', " )\n");
+            prop_assert_eq!(parse(&document), Some(children));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("(kicad_sch (symbol"), None);
+        assert_eq!(parse("kicad_sch"), None);
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::services::distill;
+use crate::types::{SheetMetaEntry, SubsystemCoverage, TestPointCoverageResponse, UncoveredNet};
+use kicad_db::PgPool;
+
+/// Component category the distiller assigns to test points
+pub const TEST_POINT_CATEGORY: &str = "test_point";
+
+/// Whether a distilled component is a test point: by category, or for results
+/// distilled before the category existed, by a TP reference or a test point
+/// symbol or footprint
+pub fn is_test_point(reference: &str, component: &Value) -> bool {
+    let field = |key: &str| {
+        component
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+    };
+    component.get("category").and_then(Value::as_str) == Some(TEST_POINT_CATEGORY)
+        || reference
+            .strip_prefix("TP")
+            .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        || field("lib_id").contains("testpoint")
+        || field("footprint").contains("testpoint")
+}
+
+fn percent(covered: usize, nets: usize) -> f64 {
+    if nets == 0 {
+        100.0
+    } else {
+        (covered as f64 * 1000.0 / nets as f64).round() / 10.0
+    }
+}
+
+/// Which nets have a test point on them, overall and per subsystem. Nets with
+/// fewer than two component pins (no-connects, dangling labels) aren't counted.
+/// A net belongs to the subsystems of the sheets its components are on, from
+/// the repository's sheet metadata; sheets without a subsystem count as None.
+pub fn report(
+    repo: &str,
+    commit: &str,
+    distilled: &Value,
+    sheet_meta: &[SheetMetaEntry],
+) -> TestPointCoverageResponse {
+    let components = distilled.get("components").and_then(Value::as_object);
+
+    let test_points: BTreeSet<String> = components
+        .into_iter()
+        .flatten()
+        .filter(|(reference, comp)| is_test_point(reference, comp))
+        .map(|(reference, _)| reference.clone())
+        .collect();
+
+    let subsystem_of = |reference: &str| -> Option<String> {
+        let sheet = components?
+            .get(reference)?
+            .get("sheet_path")
+            .and_then(Value::as_str)?;
+        sheet_meta
+            .iter()
+            .find(|m| m.sheet == sheet)
+            .and_then(|m| m.subsystem.clone())
+    };
+
+    // Subsystem -> (nets, covered)
+    let mut subsystems: BTreeMap<Option<String>, (usize, usize)> = BTreeMap::new();
+    let (mut nets, mut covered) = (0, 0);
+    let mut uncovered_nets = Vec::new();
+
+    let all_nets = distilled.get("nets").and_then(Value::as_object);
+    for (name, members) in all_nets.into_iter().flatten() {
+        let Some(members) = members.as_object() else {
+            continue;
+        };
+        let parts: Vec<&String> = members
+            .keys()
+            .filter(|reference| !test_points.contains(*reference))
+            .collect();
+        let pins: usize = members
+            .values()
+            .map(|pins| pins.as_array().map_or(1, Vec::len))
+            .sum();
+        if parts.is_empty() || pins < 2 {
+            continue;
+        }
+
+        let has_test_point = members.keys().any(|r| test_points.contains(r));
+        nets += 1;
+        covered += usize::from(has_test_point);
+        let net_subsystems: BTreeSet<Option<String>> =
+            parts.iter().map(|r| subsystem_of(r)).collect();
+        for subsystem in &net_subsystems {
+            let entry = subsystems.entry(subsystem.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += usize::from(has_test_point);
+        }
+        if !has_test_point {
+            uncovered_nets.push(UncoveredNet {
+                net: name.clone(),
+                subsystems: net_subsystems.into_iter().flatten().collect(),
+                components: parts.into_iter().cloned().collect(),
+            });
+        }
+    }
+
+    TestPointCoverageResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        test_points: test_points.into_iter().collect(),
+        nets,
+        covered,
+        coverage_percent: percent(covered, nets),
+        subsystems: subsystems
+            .into_iter()
+            .map(|(subsystem, (nets, covered))| SubsystemCoverage {
+                subsystem,
+                nets,
+                covered,
+                coverage_percent: percent(covered, nets),
+            })
+            .collect(),
+        uncovered_nets,
+    }
+}
+
+/// Test point coverage of a commit, for design-for-test reviews
+pub async fn check(pool: &PgPool, repo: &str, commit: &str) -> Result<TestPointCoverageResponse> {
+    let distilled = distill::get_or_distill(pool, repo, commit).await?;
+    let sheet_meta = distill::sheet_meta(pool, repo).await;
+    Ok(report(repo, commit, &distilled, &sheet_meta))
+}
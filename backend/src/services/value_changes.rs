@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::info;
+
+use crate::services::bom::BomComponent;
+use crate::services::release_notes;
+use crate::types::{ValueChangeGroup, ValueCompareResponse};
+use kicad_db::PgPool;
+
+/// Groups smaller than this are left out unless the request asks for them
+pub const DEFAULT_MIN_COUNT: usize = 2;
+
+fn field<'a>(component: &'a BomComponent, name: &str) -> &'a Option<String> {
+    match name {
+        "mpn" => &component.mpn,
+        _ => &component.value,
+    }
+}
+
+/// Field name, old value, new value
+type ChangeKey<'a> = (&'a str, Option<String>, Option<String>);
+
+/// Value and MPN changes on components present in both revisions, grouped by
+/// field and old → new value, largest group first. Each group also counts the
+/// components that still carry the old value afterwards, which points at a
+/// global edit that missed some parts (or hit parts it shouldn't have).
+pub fn group_changes(
+    before: &BTreeMap<String, BomComponent>,
+    after: &BTreeMap<String, BomComponent>,
+    min_count: usize,
+) -> (Vec<ValueChangeGroup>, usize) {
+    let mut groups: BTreeMap<ChangeKey, Vec<String>> = BTreeMap::new();
+    let mut changed: BTreeSet<&str> = BTreeSet::new();
+
+    for (reference, old) in before {
+        let Some(new) = after.get(reference) else {
+            continue;
+        };
+        for name in ["value", "mpn"] {
+            let (was, now) = (field(old, name), field(new, name));
+            if was != now {
+                changed.insert(reference);
+                groups
+                    .entry((name, was.clone(), now.clone()))
+                    .or_default()
+                    .push(reference.clone());
+            }
+        }
+    }
+
+    let mut groups: Vec<ValueChangeGroup> = groups
+        .into_iter()
+        .filter(|(_, references)| references.len() >= min_count)
+        .map(|((name, was, now), references)| {
+            let unchanged = match &was {
+                Some(_) => after.values().filter(|c| field(c, name) == &was).count(),
+                None => 0,
+            };
+            ValueChangeGroup {
+                field: name.to_string(),
+                before: was,
+                after: now,
+                count: references.len(),
+                references,
+                unchanged,
+            }
+        })
+        .collect();
+    groups.sort_by_key(|g| std::cmp::Reverse(g.count));
+
+    (groups, changed.len())
+}
+
+/// Compare component values and MPNs between two resolved commits
+pub async fn compare(
+    pool: &PgPool,
+    repo: &str,
+    from_commit: &str,
+    to_commit: &str,
+    min_count: usize,
+) -> Result<ValueCompareResponse> {
+    let before = release_notes::components_at(pool, repo, from_commit)
+        .await
+        .with_context(|| format!("Failed to read components at {}", from_commit))?;
+    let after = release_notes::components_at(pool, repo, to_commit)
+        .await
+        .with_context(|| format!("Failed to read components at {}", to_commit))?;
+
+    let compared = before.keys().filter(|r| after.contains_key(*r)).count();
+    let (groups, changed_components) = group_changes(&before, &after, min_count);
+
+    info!(
+        "Value compare for {} {}..{}: {} of {} component(s) changed, {} group(s)",
+        repo,
+        from_commit,
+        to_commit,
+        changed_components,
+        compared,
+        groups.len()
+    );
+
+    Ok(ValueCompareResponse {
+        repo: repo.to_string(),
+        from_commit: from_commit.to_string(),
+        to_commit: to_commit.to_string(),
+        compared,
+        changed_components,
+        groups,
+    })
+}
@@ -0,0 +1,385 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use tracing::{info, warn};
+
+use crate::services::bom::{self, BomComponent};
+use crate::services::digikey::DigiKeyClient;
+use crate::services::{
+    commit_diff, connectors, decoupling, design_rules, erc, git, github, release_notes,
+};
+use crate::types::{CiCheck, CiObsoletePart, CiVerdictResponse};
+use kicad_db::{retrieve_parts, PgPool};
+
+/// Live DigiKey lifecycle lookups per verdict, so large boards don't stall on rate limits
+const MAX_LIFECYCLE_LOOKUPS: usize = 50;
+
+/// Outcome of a check, ordered from best to worst
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    fn as_str(self) -> &'static str {
+        match self {
+            Status::Pass => "pass",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        }
+    }
+}
+
+fn check(name: &str, status: Status, summary: String) -> CiCheck {
+    CiCheck {
+        name: name.to_string(),
+        status: status.as_str().to_string(),
+        summary,
+    }
+}
+
+/// Obsolete and discontinued parts fail; not-recommended and last-time-buy parts warn
+fn lifecycle_severity(lifecycle: &str, is_obsolete: bool) -> Status {
+    let lifecycle = lifecycle.to_lowercase();
+    if lifecycle.contains("obsolete") || lifecycle.contains("discontinued") {
+        Status::Fail
+    } else if is_obsolete
+        || lifecycle.contains("not for new designs")
+        || lifecycle.contains("nrnd")
+        || lifecycle.contains("last time buy")
+    {
+        Status::Warn
+    } else {
+        Status::Pass
+    }
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..8.min(commit.len())]
+}
+
+/// Lifecycle of each part from stored DigiKey enrichment, or a live lookup when
+/// `lookup_parts` is set. Returns the flagged parts and how many parts with an
+/// MPN had no lifecycle data.
+async fn obsolete_parts(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    components: &BTreeMap<String, BomComponent>,
+    lookup_parts: bool,
+) -> Result<(Vec<CiObsoletePart>, usize)> {
+    let repo_url = github::repo_url(repo);
+    let stored_parts = retrieve_parts(pool, &repo_url, commit).await?;
+
+    let lookup = lookup_parts && DigiKeyClient::is_configured();
+    let client = DigiKeyClient::new();
+    let mut looked_up: HashMap<String, Option<(String, bool)>> = HashMap::new();
+
+    let mut flagged = Vec::new();
+    let mut without_lifecycle = 0;
+
+    for component in components.values() {
+        let Some(mpn) = &component.mpn else {
+            continue;
+        };
+
+        let stored = stored_parts
+            .get(&bom::part_uuid_for_reference(&component.reference))
+            .and_then(|p| p.properties.get("digikey"))
+            .and_then(|digikey| {
+                let is_obsolete = digikey.get("is_obsolete").and_then(Value::as_bool)?;
+                let lifecycle = digikey
+                    .get("lifecycle_status")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                Some((lifecycle.to_string(), is_obsolete, "digikey"))
+            });
+
+        let lifecycle = match stored {
+            Some(stored) => Some(stored),
+            None if lookup => {
+                if !looked_up.contains_key(mpn) && looked_up.len() < MAX_LIFECYCLE_LOOKUPS {
+                    let part = match client.lookup_mpn(mpn).await {
+                        Ok(part) => {
+                            part.map(|p| (p.lifecycle_status.unwrap_or_default(), p.is_obsolete))
+                        }
+                        Err(e) => {
+                            warn!("DigiKey lifecycle lookup failed for {}: {}", mpn, e);
+                            None
+                        }
+                    };
+                    looked_up.insert(mpn.clone(), part);
+                }
+                looked_up
+                    .get(mpn)
+                    .cloned()
+                    .flatten()
+                    .map(|(lifecycle, is_obsolete)| (lifecycle, is_obsolete, "digikey_lookup"))
+            }
+            None => None,
+        };
+
+        let Some((lifecycle, is_obsolete, source)) = lifecycle else {
+            without_lifecycle += 1;
+            continue;
+        };
+        let severity = lifecycle_severity(&lifecycle, is_obsolete);
+        if severity != Status::Pass {
+            flagged.push(CiObsoletePart {
+                reference: component.reference.clone(),
+                mpn: mpn.clone(),
+                lifecycle_status: (!lifecycle.is_empty()).then_some(lifecycle),
+                status: severity.as_str().to_string(),
+                source: source.to_string(),
+            });
+        }
+    }
+
+    Ok((flagged, without_lifecycle))
+}
+
+/// Run the CI checks for a commit and combine them into one verdict:
+///
+/// - `diff`: component changes against `base` (or the first parent); informational, always passes
+/// - `connector_pinout`: any connector whose pins moved to other nets fails
+/// - `erc`: electrical rules check; errors fail, warnings and unreadable sheets warn.
+///   With `differential_erc` only violations the commit introduced against the base count.
+/// - `obsolescence`: obsolete or discontinued parts fail, not-recommended parts warn
+/// - `design_rules`: violations of the repository's enabled design rules fail or
+///   warn by the rule's severity
+/// - `decoupling`: IC supply nets without a decoupling capacitor nearby warn
+///
+/// The verdict is the worst check status. An ERC that can't run warns rather
+/// than failing the request, so infrastructure problems don't block merges; the
+/// same goes for design rules that can't be loaded.
+pub async fn evaluate(
+    pool: &PgPool,
+    repo: &str,
+    commit: &str,
+    base: Option<String>,
+    lookup_parts: bool,
+    differential_erc: bool,
+) -> Result<CiVerdictResponse> {
+    let base = match base {
+        Some(base) => Some(base),
+        None => git::get_parent_commit(repo, commit).await?,
+    };
+
+    let mut diff = commit_diff::compute_against(pool, repo, base.clone(), commit)
+        .await
+        .with_context(|| format!("Failed to diff {}", commit))?;
+    let after = release_notes::components_of(&diff.after);
+    let connector_pinout_changes = connectors::pinout_changes(&diff);
+    let bom_delta = std::mem::take(&mut diff.bom);
+
+    let mut checks = vec![check(
+        "diff",
+        Status::Pass,
+        format!(
+            "{} added, {} removed, {} changed since {}",
+            bom_delta.added.len(),
+            bom_delta.removed.len(),
+            bom_delta.changed.len(),
+            base.as_deref().map_or("the start of history", short)
+        ),
+    )];
+    let mut verdict = Status::Pass;
+
+    let connector_status = if connector_pinout_changes.is_empty() {
+        Status::Pass
+    } else {
+        Status::Fail
+    };
+    verdict = verdict.max(connector_status);
+    checks.push(check(
+        "connector_pinout",
+        connector_status,
+        if connector_pinout_changes.is_empty() {
+            "No connector pinout changes".to_string()
+        } else {
+            format!(
+                "Pinout changed on {}; check mating connectors and cable harnesses",
+                connector_pinout_changes
+                    .iter()
+                    .map(|c| format!("{} ({} pin(s))", c.reference, c.pins.len()))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        },
+    ));
+
+    let erc_result = if differential_erc {
+        erc::check_diff(repo, base.as_deref(), commit).await
+    } else {
+        erc::check(repo, commit).await.map(|report| erc::ErcDiff {
+            introduced: report,
+            ..Default::default()
+        })
+    };
+    let (erc_status, erc_summary, erc_diff) = match erc_result {
+        Ok(diff) => {
+            let report = &diff.introduced;
+            let status = if !report.errors.is_empty() {
+                Status::Fail
+            } else if !report.warnings.is_empty() || !report.skipped.is_empty() {
+                Status::Warn
+            } else {
+                Status::Pass
+            };
+            let mut summary = if differential_erc {
+                format!(
+                    "{} new errors, {} new warnings, {} fixed since {}",
+                    report.errors.len(),
+                    report.warnings.len(),
+                    diff.fixed_errors.len() + diff.fixed_warnings.len(),
+                    base.as_deref().map_or("the start of history", short)
+                )
+            } else {
+                format!(
+                    "{} errors, {} warnings",
+                    report.errors.len(),
+                    report.warnings.len()
+                )
+            };
+            if !report.skipped.is_empty() {
+                summary.push_str(&format!(
+                    ", {} sheet(s) could not be checked",
+                    report.skipped.len()
+                ));
+            }
+            (status, summary, diff)
+        }
+        Err(e) => {
+            warn!("ERC failed for {}/{}: {}", repo, commit, e);
+            (
+                Status::Warn,
+                format!("ERC could not run: {:#}", e),
+                erc::ErcDiff::default(),
+            )
+        }
+    };
+    verdict = verdict.max(erc_status);
+    checks.push(check("erc", erc_status, erc_summary));
+
+    let (obsolete_parts, parts_without_lifecycle) =
+        obsolete_parts(pool, repo, commit, &after, lookup_parts).await?;
+    let obsolescence_status = obsolete_parts
+        .iter()
+        .map(|p| {
+            if p.status == "fail" {
+                Status::Fail
+            } else {
+                Status::Warn
+            }
+        })
+        .max()
+        .unwrap_or(Status::Pass);
+    verdict = verdict.max(obsolescence_status);
+    checks.push(check(
+        "obsolescence",
+        obsolescence_status,
+        format!(
+            "{} obsolete or discontinued, {} not recommended, {} part(s) without lifecycle data",
+            obsolete_parts.iter().filter(|p| p.status == "fail").count(),
+            obsolete_parts.iter().filter(|p| p.status == "warn").count(),
+            parts_without_lifecycle
+        ),
+    ));
+
+    let (rules_status, rules_summary, rule_violations) =
+        match design_rules::enabled_rules(pool, repo).await {
+            Ok(rules) if rules.is_empty() => (
+                Status::Pass,
+                "No design rules defined".to_string(),
+                Vec::new(),
+            ),
+            Ok(rules) => {
+                let violations = design_rules::evaluate(&rules, &diff.after);
+                let errors = violations.iter().filter(|v| v.severity == "error").count();
+                let warnings = violations.len() - errors;
+                let status = if errors > 0 {
+                    Status::Fail
+                } else if warnings > 0 {
+                    Status::Warn
+                } else {
+                    Status::Pass
+                };
+                (
+                    status,
+                    format!(
+                        "{} rule(s) checked: {} errors, {} warnings",
+                        rules.len(),
+                        errors,
+                        warnings
+                    ),
+                    violations,
+                )
+            }
+            Err(e) => {
+                warn!("Failed to load design rules of {}: {}", repo, e);
+                (
+                    Status::Warn,
+                    format!("Design rules could not be loaded: {:#}", e),
+                    Vec::new(),
+                )
+            }
+        };
+    verdict = verdict.max(rules_status);
+    checks.push(check("design_rules", rules_status, rules_summary));
+
+    let decoupling = decoupling::analyze(&diff.after);
+    let decoupling_issues: Vec<_> = decoupling.issues().cloned().collect();
+    let decoupling_status = if decoupling_issues.is_empty() {
+        Status::Pass
+    } else {
+        Status::Warn
+    };
+    let count = |status: &str| {
+        decoupling
+            .entries
+            .iter()
+            .filter(|e| e.status == status)
+            .count()
+    };
+    verdict = verdict.max(decoupling_status);
+    checks.push(check(
+        "decoupling",
+        decoupling_status,
+        format!(
+            "{} IC supply net(s): {} without decoupling, {} with decoupling only far away, {} with fewer capacitors than pins, {} shared capacitor(s)",
+            decoupling.entries.len(),
+            count(decoupling::STATUS_MISSING),
+            count(decoupling::STATUS_DISTANT),
+            count(decoupling::STATUS_INSUFFICIENT),
+            decoupling.shared_capacitors.len()
+        ),
+    ));
+
+    info!("CI verdict for {}/{}: {}", repo, commit, verdict.as_str());
+    let erc_mode = if differential_erc {
+        "differential"
+    } else {
+        "full"
+    };
+
+    Ok(CiVerdictResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        base,
+        verdict: verdict.as_str().to_string(),
+        checks,
+        bom_delta,
+        erc_mode: erc_mode.to_string(),
+        erc_errors: erc_diff.introduced.errors,
+        erc_warnings: erc_diff.introduced.warnings,
+        erc_fixed_errors: erc_diff.fixed_errors,
+        erc_fixed_warnings: erc_diff.fixed_warnings,
+        obsolete_parts,
+        connector_pinout_changes,
+        rule_violations,
+        decoupling_issues,
+        parts_without_lifecycle,
+    })
+}
@@ -0,0 +1,235 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::services::design_export::{resolve_sheet_path, sheet_property};
+use crate::services::git;
+use crate::services::symbols::{parse_sexp_children, Sexp};
+use crate::types::{SchematicFile, XrefEntry, XrefResponse};
+
+pub const KIND_GLOBAL_LABEL: &str = "global_label";
+pub const KIND_HIERARCHICAL_LABEL: &str = "hierarchical_label";
+pub const KIND_POWER: &str = "power";
+
+/// A global label used on only one sheet of a multi-sheet design
+pub const FLAG_SINGLE_SHEET: &str = "single_sheet";
+/// A hierarchical label with no pin of that name on the sheet symbols using its sheet
+pub const FLAG_NO_SHEET_PIN: &str = "no_sheet_pin";
+/// A sheet pin with no hierarchical label of that name in the sheet it points to
+pub const FLAG_NO_HIERARCHICAL_LABEL: &str = "no_hierarchical_label";
+/// Another name of the same kind differs from this one only in case
+pub const FLAG_CASE_MISMATCH: &str = "case_mismatch";
+
+/// Name -> sheet -> uses
+type Uses = BTreeMap<String, BTreeMap<String, usize>>;
+
+/// Labels, power symbols and sheet pins found in one schematic file
+#[derive(Debug, Default)]
+struct FileLabels {
+    global: BTreeMap<String, usize>,
+    hierarchical: BTreeMap<String, usize>,
+    power: BTreeMap<String, usize>,
+    /// Sheets this file instantiates: (child file, pin names)
+    sheet_pins: Vec<(String, BTreeSet<String>)>,
+}
+
+/// Value of a `(<key> "<value>")` entry among `items`
+fn list_value<'a>(items: &'a [Sexp], key: &str) -> Option<&'a str> {
+    items.iter().find_map(|item| match item {
+        Sexp::List(list) if list.first().and_then(Sexp::atom) == Some(key) => {
+            list.get(1).and_then(Sexp::atom)
+        }
+        _ => None,
+    })
+}
+
+/// Net name of a placed power symbol; None for other symbols and power flags
+fn power_net(items: &[Sexp]) -> Option<&str> {
+    let lib_id = list_value(items, "lib_id").unwrap_or_default();
+    let reference = sheet_property(items, &["Reference"]).unwrap_or_default();
+    let is_power =
+        lib_id.to_ascii_lowercase().starts_with("power:") || reference.starts_with("#PWR");
+    let value = sheet_property(items, &["Value"])?;
+    (is_power && !reference.starts_with("#FLG") && value != "PWR_FLAG").then_some(value)
+}
+
+fn scan(file: &SchematicFile) -> FileLabels {
+    let mut labels = FileLabels::default();
+    parse_sexp_children(&file.content, |node| {
+        let Sexp::List(items) = node else {
+            return;
+        };
+        let name = items.get(1).and_then(Sexp::atom).map(ToString::to_string);
+        match (items.first().and_then(Sexp::atom), name) {
+            (Some("global_label"), Some(name)) => *labels.global.entry(name).or_default() += 1,
+            (Some("hierarchical_label"), Some(name)) => {
+                *labels.hierarchical.entry(name).or_default() += 1
+            }
+            (Some("symbol"), _) => {
+                if let Some(net) = power_net(&items) {
+                    *labels.power.entry(net.to_string()).or_default() += 1;
+                }
+            }
+            (Some("sheet"), _) => {
+                // KiCad 6 wrote "Sheet file", later versions drop the space
+                if let Some(child) = sheet_property(&items, &["Sheetfile", "Sheet file"]) {
+                    let pins = items
+                        .iter()
+                        .filter_map(|item| match item {
+                            Sexp::List(pin) if pin.first().and_then(Sexp::atom) == Some("pin") => {
+                                pin.get(1).and_then(Sexp::atom).map(ToString::to_string)
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    labels
+                        .sheet_pins
+                        .push((resolve_sheet_path(&file.path, child), pins));
+                }
+            }
+            _ => {}
+        }
+    });
+    labels
+}
+
+fn add_uses(uses: &mut Uses, sheet: &str, names: &BTreeMap<String, usize>) {
+    for (name, count) in names {
+        *uses
+            .entry(name.clone())
+            .or_default()
+            .entry(sheet.to_string())
+            .or_default() += count;
+    }
+}
+
+/// Names of a kind that collide with another name when case is ignored
+fn case_collisions(uses: &Uses) -> BTreeSet<String> {
+    let mut by_lowercase: BTreeMap<String, Vec<&String>> = BTreeMap::new();
+    for name in uses.keys() {
+        by_lowercase
+            .entry(name.to_lowercase())
+            .or_default()
+            .push(name);
+    }
+    by_lowercase
+        .into_values()
+        .filter(|names| names.len() > 1)
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+fn entries(kind: &str, uses: Uses, flags: &BTreeMap<String, BTreeSet<&str>>) -> Vec<XrefEntry> {
+    let collisions = case_collisions(&uses);
+    uses.into_iter()
+        .map(|(name, sheets)| {
+            let mut entry_flags: BTreeSet<&str> = flags.get(&name).cloned().unwrap_or_default();
+            if collisions.contains(&name) {
+                entry_flags.insert(FLAG_CASE_MISMATCH);
+            }
+            XrefEntry {
+                kind: kind.to_string(),
+                count: sheets.values().sum(),
+                sheets: sheets.into_keys().collect(),
+                flags: entry_flags.into_iter().map(ToString::to_string).collect(),
+                name,
+            }
+        })
+        .collect()
+}
+
+/// Cross-reference of the global labels, hierarchical labels and power
+/// symbols of a set of schematic files: which sheets use each name, with
+/// likely mistakes flagged
+pub fn cross_reference(files: &[SchematicFile]) -> Vec<XrefEntry> {
+    let scanned: Vec<(&str, FileLabels)> = files
+        .iter()
+        .filter(|f| f.path.ends_with(".kicad_sch"))
+        .map(|f| (f.path.as_str(), scan(f)))
+        .collect();
+    let multi_sheet = scanned.len() > 1;
+
+    let (mut global, mut hierarchical, mut power) = (Uses::new(), Uses::new(), Uses::new());
+    for (sheet, labels) in &scanned {
+        add_uses(&mut global, sheet, &labels.global);
+        add_uses(&mut hierarchical, sheet, &labels.hierarchical);
+        add_uses(&mut power, sheet, &labels.power);
+    }
+
+    let global_flags: BTreeMap<String, BTreeSet<&str>> = global
+        .iter()
+        .filter(|(_, sheets)| multi_sheet && sheets.len() == 1)
+        .map(|(name, _)| (name.clone(), BTreeSet::from([FLAG_SINGLE_SHEET])))
+        .collect();
+
+    // Hierarchical labels pair with the pins of sheet symbols pointing at their file
+    let labels_of: BTreeMap<&str, &BTreeMap<String, usize>> = scanned
+        .iter()
+        .map(|(sheet, labels)| (*sheet, &labels.hierarchical))
+        .collect();
+    let mut pins_of: BTreeMap<&str, BTreeSet<&String>> = BTreeMap::new();
+    let mut hierarchical_flags: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    for (parent, labels) in &scanned {
+        for (child, pins) in &labels.sheet_pins {
+            pins_of.entry(child.as_str()).or_default().extend(pins);
+            for pin in pins {
+                *hierarchical
+                    .entry(pin.clone())
+                    .or_default()
+                    .entry(parent.to_string())
+                    .or_default() += 1;
+                let labelled = labels_of
+                    .get(child.as_str())
+                    .is_none_or(|labels| labels.contains_key(pin));
+                if !labelled {
+                    hierarchical_flags
+                        .entry(pin.clone())
+                        .or_default()
+                        .insert(FLAG_NO_HIERARCHICAL_LABEL);
+                }
+            }
+        }
+    }
+    for (sheet, labels) in &scanned {
+        // Labels of a sheet nothing instantiates (e.g. the root) can't be checked
+        let Some(pins) = pins_of.get(sheet) else {
+            continue;
+        };
+        for name in labels.hierarchical.keys().filter(|n| !pins.contains(n)) {
+            hierarchical_flags
+                .entry(name.clone())
+                .or_default()
+                .insert(FLAG_NO_SHEET_PIN);
+        }
+    }
+
+    let mut entries: Vec<XrefEntry> = [
+        entries(KIND_GLOBAL_LABEL, global, &global_flags),
+        entries(KIND_HIERARCHICAL_LABEL, hierarchical, &hierarchical_flags),
+        entries(KIND_POWER, power, &BTreeMap::new()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    entries.sort_by(|a, b| (&a.name, &a.kind).cmp(&(&b.name, &b.kind)));
+    entries
+}
+
+/// Cross-reference index of a commit
+pub async fn build(repo: &str, commit: &str) -> Result<XrefResponse> {
+    let files = git::get_schematic_files(repo, commit)
+        .await
+        .context("Failed to fetch schematic files from repo")?;
+    let entries = cross_reference(&files);
+
+    Ok(XrefResponse {
+        repo: repo.to_string(),
+        commit: commit.to_string(),
+        sheet_count: files
+            .iter()
+            .filter(|f| f.path.ends_with(".kicad_sch"))
+            .count(),
+        flagged: entries.iter().filter(|e| !e.flags.is_empty()).count(),
+        entries,
+    })
+}
@@ -1,6 +1,7 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
+use std::collections::BTreeMap;
+use utoipa::{IntoParams, ToSchema};
 
 // ============================================================================
 // DigiKey API Types
@@ -56,6 +57,28 @@ pub struct DigiKeyParameter {
     pub value: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct PartOffer {
+    /// Provider that returned this offer (e.g., "digikey")
+    pub provider: String,
+    /// Distributor's own part number
+    pub distributor_part_number: Option<String>,
+    /// Manufacturer part number
+    pub manufacturer_part_number: Option<String>,
+    /// Manufacturer name
+    pub manufacturer: Option<String>,
+    /// Unit price, in `currency`
+    pub unit_price: Option<f64>,
+    /// ISO 4217 code of the currency the distributor quoted
+    pub currency: String,
+    /// Quantity available
+    pub quantity_available: Option<i64>,
+    /// Whether the part is obsolete/deprecated
+    pub is_obsolete: bool,
+    /// Product URL on the distributor's site
+    pub product_url: Option<String>,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
 pub struct DigiKeySearchResponse {
     /// The search query used
@@ -70,6 +93,259 @@ pub struct DigiKeySearchResponse {
     pub total_count: usize,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DigiKeyEnrichRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DigiKeyEnrichedPart {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    /// Manufacturer part number from the schematic
+    pub mpn: String,
+    /// Matched DigiKey part number
+    pub digikey_part_number: Option<String>,
+    /// Lifecycle status description
+    pub lifecycle_status: Option<String>,
+    /// Whether the part is obsolete/deprecated
+    pub is_obsolete: bool,
+    /// Unit price (USD)
+    pub unit_price: Option<f64>,
+    /// Datasheet URL
+    pub datasheet_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DigiKeyMissingPart {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    /// Manufacturer part number from the schematic
+    pub mpn: String,
+    /// Why the part could not be enriched
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DigiKeyEnrichResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Total number of components in the schematic
+    pub total_components: usize,
+    /// Number of components without a manufacturer part number
+    pub skipped_without_mpn: usize,
+    /// Components that were matched on DigiKey and stored
+    pub enriched: Vec<DigiKeyEnrichedPart>,
+    /// Components with an MPN that could not be matched
+    pub missing: Vec<DigiKeyMissingPart>,
+}
+
+// ============================================================================
+// LCSC / JLCPCB Types
+// ============================================================================
+
+/// JLCPCB assembly library classification
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JlcPartType {
+    /// Basic part - pre-loaded on JLCPCB's pick-and-place machines, no setup fee
+    Basic,
+    /// Extended part - loaded on demand, incurs a per-part setup fee
+    Extended,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, Clone)]
+pub struct LcscPartInfo {
+    /// LCSC part number (e.g., "C25804"), also used as the JLCPCB assembly part number
+    pub lcsc_part_number: String,
+    /// Manufacturer part number
+    pub manufacturer_part_number: Option<String>,
+    /// Manufacturer name
+    pub manufacturer: Option<String>,
+    /// Part description
+    pub description: Option<String>,
+    /// Package/case (e.g., "0603")
+    pub package: Option<String>,
+    /// Quantity available
+    pub quantity_available: Option<i64>,
+    /// Unit price at quantity 1 (USD)
+    pub unit_price: Option<f64>,
+    /// JLCPCB assembly library classification (None if not in the assembly library)
+    pub jlc_part_type: Option<JlcPartType>,
+    /// Datasheet URL
+    pub datasheet_url: Option<String>,
+    /// Product URL on LCSC
+    pub product_url: Option<String>,
+}
+
+// ============================================================================
+// BOM Endpoint Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BomRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Look up each line on LCSC/JLCPCB to report assembly availability (needs LCSC_ENABLED)
+    #[serde(default)]
+    pub include_lcsc: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BomLine {
+    /// Reference designators on this line (e.g., ["C1", "C2"])
+    pub references: Vec<String>,
+    /// Number of components on this line
+    pub quantity: usize,
+    /// Component value
+    pub value: Option<String>,
+    /// Footprint library ID
+    pub footprint: Option<String>,
+    /// Manufacturer part number
+    pub mpn: Option<String>,
+    /// Manufacturer name
+    pub manufacturer: Option<String>,
+    /// LCSC part number (from the schematic, or matched when `include_lcsc` is set)
+    pub lcsc_part_number: Option<String>,
+    /// JLCPCB assembly library classification
+    pub jlc_part_type: Option<JlcPartType>,
+    /// Whether JLCPCB can assemble this line (None if not checked or LCSC has no exact match). True when
+    /// the part or one of its approved alternates can be assembled.
+    pub jlc_assemblable: Option<bool>,
+    /// Approved alternates of this line's MPN
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alternates: Vec<String>,
+    /// Alternate that made the line assemblable when the part itself isn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub assemblable_alternate: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BomResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Total number of components
+    pub total_components: usize,
+    /// Grouped BOM lines
+    pub lines: Vec<BomLine>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PartAlternateRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// MPN of the BOM line
+    pub mpn: String,
+    /// MPN approved in its place
+    pub alternate_mpn: String,
+    /// Manufacturer of the alternate
+    pub manufacturer: Option<String>,
+    /// Why the alternate is approved, or what to watch out for
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PartAlternateRemoveRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    pub mpn: String,
+    pub alternate_mpn: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PartAlternatesRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PartAlternateEntry {
+    /// Normalized MPN of the BOM line
+    pub mpn: String,
+    /// Normalized MPN of the alternate
+    pub alternate_mpn: String,
+    pub manufacturer: Option<String>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PartAlternatesResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Approved alternates, by MPN and alternate MPN
+    pub alternates: Vec<PartAlternateEntry>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BomDiffRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Earlier revision (commit hash or tag)
+    pub from: String,
+    /// Later revision (commit hash or tag)
+    pub to: String,
+    /// ISO 4217 code to show costs in (default DISPLAY_CURRENCY)
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BomDiffLine {
+    /// One of "added", "removed", "quantity_changed"
+    pub change: String,
+    pub value: Option<String>,
+    pub footprint: Option<String>,
+    pub mpn: Option<String>,
+    pub manufacturer: Option<String>,
+    pub before_quantity: usize,
+    pub after_quantity: usize,
+    /// References on this line at `to` but not at `from`
+    pub added_references: Vec<String>,
+    /// References on this line at `from` but not at `to`
+    pub removed_references: Vec<String>,
+    /// Cached distributor unit price, if the line was ever enriched
+    pub unit_price: Option<f64>,
+    /// ISO 4217 code of `unit_price` and `cost_delta`
+    pub currency: Option<String>,
+    /// Unit price times the quantity change; null when unpriced
+    pub cost_delta: Option<f64>,
+    /// `unit_price` in the display currency; null when it can't be converted
+    pub display_unit_price: Option<f64>,
+    /// `cost_delta` in the display currency
+    pub display_cost_delta: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BomDiffResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash `from` resolved to
+    pub from_commit: String,
+    /// Full commit hash `to` resolved to
+    pub to_commit: String,
+    /// Lines added, removed or changed in quantity
+    pub lines: Vec<BomDiffLine>,
+    /// ISO 4217 code of the `display_*` amounts and `net_cost_delta`
+    pub display_currency: String,
+    /// Day the exchange rates used were fetched; null when none were available
+    pub exchange_rates_date: Option<NaiveDate>,
+    /// Sum of the priced lines' cost deltas in the display currency, per board
+    pub net_cost_delta: f64,
+    /// Changed lines without a cached price, or whose price couldn't be
+    /// converted; left out of `net_cost_delta`
+    pub unpriced_lines: usize,
+}
+
 // ============================================================================
 // Repo Endpoint Types
 // ============================================================================
@@ -78,6 +354,8 @@ pub struct DigiKeySearchResponse {
 pub struct RepoCommitsRequest {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
+    /// "date" (default, history order) or "risk" (riskiest first, unscored commits last)
+    pub sort: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -90,6 +368,33 @@ pub struct CommitInfo {
     pub message: Option<String>,
     /// Whether this commit modified .kicad_sch files
     pub has_schematic_changes: bool,
+    /// Heuristic risk of the commit's changes; None until the commit is indexed and scored
+    pub risk: Option<CommitRisk>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CommitRisk {
+    /// Sum of the factor scores; 0 when nothing risky changed
+    pub score: i32,
+    /// What contributed to the score, highest first
+    pub factors: Vec<RiskFactor>,
+    /// Version of the heuristics that produced the score
+    pub scorer_version: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RiskFactor {
+    /// "power_net_change", "connector_pinout_change", "high_pin_count_swap", "net_rename"
+    /// or "blast_radius"
+    pub kind: String,
+    /// Points per finding
+    pub weight: i32,
+    /// Number of findings
+    pub count: i32,
+    /// weight * count
+    pub score: i32,
+    /// The findings: net names, references, or "OLD -> NEW" for renames
+    pub items: Vec<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -104,16 +409,23 @@ pub struct RepoCommitsResponse {
 pub struct CommitFilesRequest {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash
+    /// Commit hash or tag name
     pub commit: String,
 }
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct SchematicFile {
     /// File path relative to repository root
     pub path: String,
     /// Raw file content
     pub content: String,
+    /// Git blob OID of the file content
+    pub blob_oid: String,
+    /// S-expression format version from the file header (e.g., 20231120 for KiCad 8).
+    /// None for non-schematic files and legacy/unrecognised formats.
+    pub format_version: Option<u32>,
+    /// KiCad release the format version belongs to (e.g., "8.0")
+    pub kicad_version: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -130,8 +442,14 @@ pub struct CommitFilesResponse {
 pub struct CommitInfoRequest {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash
+    /// Commit hash or tag name
     pub commit: String,
+    /// Leave out the blurb and description unless a reviewer approved them
+    #[serde(default)]
+    pub approved_only: bool,
+    /// "standard" (default) or "tts": `blurb` is a short plain-spoken
+    /// variant without markdown or abbreviations, for reading aloud
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -144,240 +462,3040 @@ pub struct CommitInfoResponse {
     pub commit_date: Option<DateTime<Utc>>,
     /// Commit message summary
     pub message: Option<String>,
-    /// Short AI-generated summary
+    /// Short summary: a person's edit if there is one, else AI-generated
     pub blurb: Option<String>,
-    /// Detailed AI-generated description
+    /// Detailed description: a person's edit if there is one, else AI-generated
     pub description: Option<String>,
+    /// AI-generated blurb, kept when the blurb is edited
+    pub generated_blurb: Option<String>,
+    /// AI-generated description, kept when the description is edited
+    pub generated_description: Option<String>,
+    /// `blurb` is a person's edit
+    pub blurb_edited: bool,
+    /// `description` is a person's edit
+    pub description_edited: bool,
+    pub edited_by: Option<String>,
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Review state of the blurb/description ("draft", "approved" or
+    /// "rejected"); null when nothing is stored for the commit
+    pub review_status: Option<String>,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
     /// List of changed .kicad_sch file paths
     pub changed_files: Vec<String>,
+    /// Human comments on the commit, oldest first
+    pub comments: Vec<CommitCommentEntry>,
 }
 
-// ============================================================================
-// Hook Endpoint Types
-// ============================================================================
-
-#[derive(Debug, Serialize, ToSchema)]
-pub struct HookUpdateResponse {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CommitCommentRequest {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Number of commits successfully processed
-    pub processed: usize,
-    /// List of errors encountered during processing
-    pub errors: Vec<String>,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Name of the person commenting
+    pub author: String,
+    /// Comment text (Markdown)
+    pub body: String,
+    /// Comment being replied to; must be on the same commit
+    pub parent_id: Option<i64>,
+    /// What the comment is about: "component" or "net" (requires `anchor`)
+    pub anchor_kind: Option<String>,
+    /// Reference designator or net name the comment is about
+    pub anchor: Option<String>,
 }
 
-// ============================================================================
-// Grok Endpoint Types
-// ============================================================================
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CommitCommentEntry {
+    pub id: i64,
+    /// Comment this one replies to (None for the start of a thread)
+    pub parent_id: Option<i64>,
+    pub author: String,
+    pub body: String,
+    pub anchor_kind: Option<String>,
+    pub anchor: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
 
 #[derive(Debug, Deserialize, ToSchema)]
-pub struct GrokCommitSummaryRequest {
+pub struct CommitCommentsRequest {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash
+    /// Commit hash or tag name
     pub commit: String,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
-pub struct GrokCommitSummaryResponse {
+pub struct CommitCommentsResponse {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
     /// Full commit hash
     pub commit: String,
-    /// Short AI-generated summary
-    pub summary: String,
-    /// Detailed AI-generated analysis
-    pub details: String,
+    /// Comments oldest first; replies reference their thread via `parent_id`
+    pub comments: Vec<CommitCommentEntry>,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
-pub struct GrokSelectionSummaryRequest {
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SchematicImageQuery {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash
+    /// Commit hash or tag name
     pub commit: String,
-    /// List of component IDs to analyze
-    pub component_ids: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, ToSchema)]
-pub struct GrokSelectionStreamRequest {
+pub struct StoredCommitsRequest {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
+    /// Maximum number of commits to return (default 100, max 1000)
+    pub limit: Option<i64>,
+    /// Number of commits to skip, newest first
+    #[serde(default)]
+    pub offset: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StoredCommit {
     /// Full commit hash
     pub commit: String,
-    /// List of component IDs (references) to analyze
-    pub component_ids: Vec<String>,
-    /// User's query about the components
-    pub query: String,
-    /// Pre-distilled schematic data (optional - will fetch if not provided)
-    pub distilled: Option<serde_json::Value>,
-    /// Enable thinking/reasoning mode (shows chain-of-thought)
-    #[serde(default)]
-    pub thinking_mode: bool,
+    pub commit_date: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+    pub has_blurb: bool,
+    pub has_description: bool,
+    /// Review state of the blurb/description: "draft", "approved" or "rejected"
+    pub review_status: String,
+    /// Distilled schematic data is cached
+    pub has_distilled: bool,
+    pub has_image: bool,
+    /// Stored (enriched) parts
+    pub part_count: i64,
+    /// When the commit was first stored
+    pub stored_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
-pub struct GrokSelectionSummaryResponse {
+pub struct StoredCommitsResponse {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash
-    pub commit: String,
-    /// List of component IDs that were analyzed
-    pub component_ids: Vec<String>,
-    /// Short AI-generated summary
-    pub summary: String,
-    /// Detailed AI-generated analysis
-    pub details: String,
+    /// Stored commits for the repository, across all pages
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+    /// Stored commits, newest first
+    pub commits: Vec<StoredCommit>,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
-pub struct GrokRepoSummaryRequest {
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RepoSummaryCardQuery {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
-pub struct GrokRepoSummaryResponse {
+pub struct RepoSummaryCardResponse {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Short AI-generated summary
-    pub summary: String,
-    /// Detailed AI-generated analysis
-    pub details: String,
+    /// Newest stored schematic commit
+    pub last_commit: Option<String>,
+    pub last_commit_date: Option<DateTime<Utc>>,
+    pub last_commit_message: Option<String>,
+    /// Stored schematic commits
+    pub commit_count: i32,
+    /// Stored commits with distilled data
+    pub distilled_count: i32,
+    /// Parts of the newest stored commit
+    pub part_count: i32,
+    /// Comments across all commits
+    pub comment_count: i32,
+    /// Blurb of the newest commit that has one not rejected in review
+    pub blurb: Option<String>,
+    /// Commit the blurb belongs to
+    pub blurb_commit: Option<String>,
+    /// Newest commit with a stored schematic image
+    pub thumbnail_commit: Option<String>,
+    /// Path of that image (`/api/v1/repo/commit/image`)
+    pub thumbnail_url: Option<String>,
+    /// When the card was last recomputed
+    pub refreshed_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
-pub struct GrokObsoleteReplacementRequest {
-    /// Manufacturer part number of the obsolete part
-    pub manufacturer_part_number: String,
-    /// Manufacturer name
-    pub manufacturer: Option<String>,
-    /// Part description
-    pub description: Option<String>,
-    /// Product category
-    pub category: Option<String>,
-    /// Datasheet URL (PDF link for Grok to analyze)
-    pub datasheet_url: Option<String>,
-    /// DigiKey product page URL
-    pub product_url: Option<String>,
-    /// Key parameters/specifications
-    pub parameters: Vec<DigiKeyParameter>,
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RepoSuggestionsQuery {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
 }
 
-#[derive(Debug, Serialize, ToSchema)]
-pub struct GrokObsoleteReplacementResponse {
-    /// The original obsolete part number
-    pub original_part: String,
-    /// AI-generated analysis and replacement recommendations
-    pub analysis: String,
-    /// Whether the search was successful
-    pub success: bool,
-    /// Error message if failed
-    pub error: Option<String>,
+/// A question worth asking about a repository's design
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RepoSuggestion {
+    /// Ready to send as a chat message, e.g. "How is the USB-PD contract negotiated?"
+    pub question: String,
+    /// Short label of the area for a chip or button, e.g. "USB-PD"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// Schematic file the question is mostly about
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sheet: Option<String>,
+    /// Components the question is about
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
 }
 
-// ============================================================================
-// Distill Endpoint Types
-// ============================================================================
-
-#[derive(Debug, Deserialize, ToSchema)]
-pub struct DistillRequest {
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RepoSuggestionsResponse {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash
+    /// Commit the suggestions were generated from
     pub commit: String,
+    pub suggestions: Vec<RepoSuggestion>,
+    /// Model that wrote them, or "deterministic" for template suggestions
+    pub model: String,
+    pub prompt_version: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct RepoUploadQuery {
+    /// GitHub repository in "owner/repo" format the upload is served as
+    pub repo: String,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
-pub struct DistillResponse {
+pub struct RepoUploadResponse {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash
-    pub commit: String,
-    /// Whether the result was served from cache
-    pub cached: bool,
-    /// Distilled schematic data (JSON object with components, nets, proximities)
-    pub distilled: serde_json::Value,
+    /// "bundle" or "tarball"
+    pub format: String,
+    /// Commit HEAD of the upload points at, the repository's latest commit
+    pub head_commit: String,
+    /// Local branches in the upload
+    pub branches: usize,
+    /// Job generating overviews for the uploaded schematic commits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub job_id: Option<i64>,
 }
 
-// ============================================================================
-// Repo Initialization Types
-// ============================================================================
-
 #[derive(Debug, Deserialize, ToSchema)]
-pub struct RepoInitRequest {
+pub struct CommitOverviewEditRequest {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash (optional - uses latest if not provided)
-    pub commit: Option<String>,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// New blurb; omit to keep it, or send "" to go back to the generated one
+    pub blurb: Option<String>,
+    /// New description; omit to keep it, or send "" to go back to the generated one
+    pub description: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
-pub struct RepoInitResponse {
+pub struct CommitOverviewResponse {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash that was initialized
+    /// Full commit hash
     pub commit: String,
-    /// Whether the distilled data was already cached
-    pub cached: bool,
-    /// Number of components found in the schematic
+    /// Blurb as shown: the edit if there is one, else the generated blurb
+    pub blurb: Option<String>,
+    /// Description as shown: the edit if there is one, else the generated description
+    pub description: Option<String>,
+    pub generated_blurb: Option<String>,
+    pub generated_description: Option<String>,
+    pub blurb_edited: bool,
+    pub description_edited: bool,
+    pub edited_by: Option<String>,
+    pub edited_at: Option<DateTime<Utc>>,
+    /// Review state; edits go back to "draft"
+    pub review_status: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SummaryReviewRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// "approved" or "rejected"
+    pub status: String,
+    /// Corrected blurb, stored as a human edit; omit to keep the current one
+    pub blurb: Option<String>,
+    /// Corrected description, stored as a human edit; omit to keep the current one
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SummaryReviewResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    pub blurb: Option<String>,
+    pub description: Option<String>,
+    /// "approved" or "rejected"
+    pub review_status: String,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+// ============================================================================
+// Hook Endpoint Types
+// ============================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HookUpdateResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Number of commits successfully processed
+    pub processed: usize,
+    /// List of errors encountered during processing
+    pub errors: Vec<String>,
+    /// Commits skipped because they're quarantined after failing repeatedly;
+    /// release them with POST /api/admin/quarantine/release
+    pub quarantined: Vec<String>,
+    /// Background job id when processing was queued for a worker instead of run inline
+    pub job_id: Option<i64>,
+}
+
+// ============================================================================
+// Grok Endpoint Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokCommitSummaryRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// "llm" (default) or "deterministic": a template-based summary of the
+    /// structured diff that needs no API key and is reproducible for CI
+    pub mode: Option<String>,
+    /// "standard" (default) or "tts": a short plain-spoken summary without
+    /// markdown or abbreviations, for reading aloud
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokCommitSummaryResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Short AI-generated summary
+    pub summary: String,
+    /// Detailed AI-generated analysis
+    pub details: String,
+    /// Model that generated the summary (send back with feedback)
+    pub model: String,
+    /// Prompt version that generated the summary (send back with feedback)
+    pub prompt_version: String,
+    /// Human comments on the commit, oldest first
+    pub comments: Vec<CommitCommentEntry>,
+    /// Commit of the same repository whose identical diff the summary was
+    /// generated for, when it was reused instead of generated for this commit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reused_from: Option<GrokSummarySource>,
+    /// Per-file summaries the summary was synthesized from, for commits
+    /// touching too many files to summarize in one prompt
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub file_summaries: Vec<GrokFileSummary>,
+}
+
+/// Summary of one changed file of a commit
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokFileSummary {
+    /// Schematic file path
+    pub path: String,
+    pub summary: String,
+}
+
+/// Where a reused commit summary came from
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokSummarySource {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Provenance record of the original generation
+    pub provenance_id: Option<i64>,
+    pub generated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokSelectionSummaryRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// List of component IDs to analyze
+    pub component_ids: Vec<String>,
+    /// "llm" (default) or "deterministic": a template-based summary of the
+    /// distilled data that needs no API key and is reproducible for CI
+    pub mode: Option<String>,
+    /// "standard" (default) or "tts": a short plain-spoken summary without
+    /// markdown or abbreviations, for reading aloud
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokSelectionStreamRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// List of component IDs (references) to analyze
+    pub component_ids: Vec<String>,
+    /// User's query about the components
+    pub query: String,
+    /// Pre-distilled schematic data (optional - will fetch if not provided)
+    pub distilled: Option<serde_json::Value>,
+    /// Enable thinking/reasoning mode (shows chain-of-thought)
+    #[serde(default)]
+    pub thinking_mode: bool,
+    /// Session from /api/grok/session for the same repo and commit. Its
+    /// context and earlier turns are reused, so `distilled` needn't be resent.
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokAskRepoRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name; the default branch's latest commit when omitted
+    pub commit: Option<String>,
+    /// Free-form question about the project, e.g. "where is battery charging handled?"
+    pub question: String,
+    /// Only search this sheet, e.g. "/power/"
+    pub sheet: Option<String>,
+    /// Only search these kinds of excerpt: "component", "net", "sheet", "summary" or
+    /// "comment"; all when omitted
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Enable thinking/reasoning mode (shows chain-of-thought)
+    #[serde(default)]
+    pub thinking_mode: bool,
+}
+
+/// A design excerpt sent with a repository question, which the answer cites as [id]
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GrokCitation {
+    pub id: usize,
+    /// "component", "net" or "sheet"
+    pub kind: String,
+    /// Reference, net name or sheet path
+    pub key: String,
+    /// Sheet a component is on
+    pub sheet: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokExplainNetRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Net name as in the distilled data, e.g. "+3V3" or "/mcu/SWDIO"
+    pub net: String,
+    /// Generate a new explanation even if one is stored for the same context
+    #[serde(default)]
+    pub regenerate: bool,
+}
+
+/// A pin of a component on the explained net
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GrokNetPin {
+    pub number: String,
+    pub name: Option<String>,
+}
+
+/// A component on or near the explained net
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GrokNetComponent {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub value: Option<String>,
+    /// Distiller category: "ic", "capacitor", "resistor", ...
+    pub category: Option<String>,
+    pub footprint: Option<String>,
+    pub sheet: Option<String>,
+    /// Pins on the net; empty for nearby components
+    pub pins: Vec<GrokNetPin>,
+    /// Distance to the closest component on the net, for nearby components
+    pub distance_mm: Option<f64>,
+    /// The team's note on the component
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// What a net explanation is generated from, sent as the `net` event
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct GrokNetContext {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    pub net: String,
+    /// Components with pins on the net
+    pub components: Vec<GrokNetComponent>,
+    /// Passives close to a component on the net but not on it themselves
+    pub nearby: Vec<GrokNetComponent>,
+    /// Commit the explanation was generated at, when a stored one is replayed
+    pub reused_from: Option<String>,
+    /// When the replayed explanation was generated
+    pub explained_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokSessionRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Pre-distilled schematic data, kept for the session's questions
+    /// (optional - will fetch if not provided)
+    pub distilled: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokSessionResponse {
+    /// Session id (a UUID) to send as `session_id` with selection streams
+    pub session_id: String,
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Size of the system prompt the session reuses
+    pub context_chars: usize,
+    /// The session closes after this long without a question
+    pub idle_timeout_secs: i64,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct GrokSessionExportQuery {
+    /// "markdown" (default) or "json"
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokSessionExportTurn {
+    pub question: String,
+    /// References of the components selected for the question
+    pub components: Vec<String>,
+    pub answer: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokSessionExport {
+    pub session_id: String,
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    /// Questions and answers, oldest first; sessions keep their latest 20
+    pub turns: Vec<GrokSessionExportTurn>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokSelectionSummaryResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// List of component IDs that were analyzed
+    pub component_ids: Vec<String>,
+    /// Short AI-generated summary
+    pub summary: String,
+    /// Detailed AI-generated analysis
+    pub details: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokRepoSummaryRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// "standard" (default) or "tts": a short plain-spoken summary without
+    /// markdown or abbreviations, for reading aloud
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokRepoSummaryResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Short AI-generated summary
+    pub summary: String,
+    /// Detailed AI-generated analysis
+    pub details: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokObsoleteReplacementRequest {
+    /// Manufacturer part number of the obsolete part
+    pub manufacturer_part_number: String,
+    /// Manufacturer name
+    pub manufacturer: Option<String>,
+    /// Part description
+    pub description: Option<String>,
+    /// Product category
+    pub category: Option<String>,
+    /// Datasheet URL (PDF link for Grok to analyze)
+    pub datasheet_url: Option<String>,
+    /// DigiKey product page URL
+    pub product_url: Option<String>,
+    /// Key parameters/specifications
+    pub parameters: Vec<DigiKeyParameter>,
+    /// Only return suggestions that are in stock at a configured distributor
+    #[serde(default)]
+    pub in_stock_only: bool,
+    /// Look for second sources of a part that stays in the design, and approve
+    /// the in-stock ones as alternates of its BOM line in `repo`
+    #[serde(default)]
+    pub add_alternate: bool,
+    /// GitHub repository in "owner/repo" format (required with `add_alternate`)
+    pub repo: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokReplacementSuggestion {
+    /// Manufacturer part number of the suggested replacement
+    pub manufacturer_part_number: String,
+    /// Manufacturer name
+    pub manufacturer: Option<String>,
+    /// Why Grok considers this a good replacement
+    pub reason: Option<String>,
+    /// Live distributor offers with stock on hand
+    pub offers: Vec<PartOffer>,
+    /// False when no offer was found but a distributor lookup failed, so stock
+    /// couldn't be checked; such suggestions are kept rather than dropped
+    pub stock_verified: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GrokObsoleteReplacementResponse {
+    /// The original obsolete part number
+    pub original_part: String,
+    /// AI-generated analysis and replacement recommendations. After a stock
+    /// check, the sections about dropped suggestions are left out.
+    pub analysis: String,
+    /// Stock-checked suggestions (only populated when `verified` is true)
+    pub suggestions: Vec<GrokReplacementSuggestion>,
+    /// Suggested part numbers dropped because no distributor had them in stock
+    pub dropped_suggestions: Vec<String>,
+    /// Whether `suggestions` were checked against distributor stock. False when
+    /// no stock check was asked for, or the reply had no parseable part list
+    /// (the analysis is then unfiltered).
+    pub verified: bool,
+    /// Part numbers approved as alternates (only with `add_alternate`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub added_alternates: Vec<String>,
+    /// Provenance record of the approved alternates (see /api/provenance)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance_id: Option<i64>,
+    /// Whether the search was successful
+    pub success: bool,
+    /// Error message if failed
+    pub error: Option<String>,
+}
+
+// ============================================================================
+// Distill Endpoint Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DistillRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// "python", "native" or "compare" (runs both and reports differences).
+    /// Defaults to DISTILLER_BACKEND; other backends bypass the cache.
+    pub backend: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DistillResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Whether the result was served from cache
+    pub cached: bool,
+    /// Distilled schematic data (JSON object with components, nets, proximities)
+    pub distilled: serde_json::Value,
+    /// Schematic files that were skipped because they could not be parsed or
+    /// are in an unsupported KiCad format
+    pub warnings: Vec<DistillWarning>,
+    /// Parse cache statistics (absent when the whole commit was served from cache)
+    pub file_cache: Option<DistillCacheStats>,
+    /// Owner, subsystem and review status of annotated sheets
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sheet_meta: Vec<SheetMetaEntry>,
+    /// Differences between the backends (compare mode only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<DistillComparison>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DistillComparison {
+    /// Backend whose output is returned
+    pub primary: String,
+    /// Backend being validated against it
+    pub candidate: String,
+    /// Total number of differences
+    pub difference_count: usize,
+    /// The first differences, in file and pointer order
+    pub differences: Vec<DistillDifference>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DistillDifference {
+    /// Schematic file path relative to repository root
+    pub file: String,
+    /// JSON pointer into the file's distilled data, e.g. "/components/R1/value".
+    /// Pins are addressed by number.
+    pub pointer: String,
+    /// Value in the primary output; absent when only the candidate has it
+    pub primary: Option<serde_json::Value>,
+    /// Value in the candidate output; absent when only the primary has it
+    pub candidate: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DistillSheet {
+    /// Schematic file path relative to repository root
+    pub path: String,
+    /// Components placed on this sheet
     pub component_count: usize,
-    /// Number of nets found in the schematic
+    /// Nets with at least one pin on this sheet
     pub net_count: usize,
-    /// List of schematic files found
-    pub schematic_files: Vec<String>,
-    /// Distilled schematic data
-    pub distilled: serde_json::Value,
 }
 
-#[derive(Debug, Deserialize, ToSchema)]
-pub struct RepoClearCacheRequest {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DistillSheetRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Schematic file path relative to repository root, as listed in the sheet manifest
+    pub sheet: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DistillSheetResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Schematic file path relative to repository root
+    pub sheet: String,
+    /// The sheet's distilled data (components, nets limited to this sheet's pins, proximities)
+    pub distilled: serde_json::Value,
+    /// Owner, subsystem and review status assigned to the sheet, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sheet_meta: Option<SheetMetaEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SheetMetaEntry {
+    /// Schematic file path relative to repository root
+    pub sheet: String,
+    pub owner: Option<String>,
+    /// Subsystem or functional block the sheet belongs to (e.g. "Power")
+    pub subsystem: Option<String>,
+    /// "unreviewed", "in_review", "changes_requested" or "approved"
+    pub review_status: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SheetMetaRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Schematic file path relative to repository root
+    pub sheet: String,
+    /// Replaces the stored owner; omit to clear
+    pub owner: Option<String>,
+    /// Replaces the stored subsystem; omit to clear
+    pub subsystem: Option<String>,
+    /// Replaces the stored review status; omit to clear
+    pub review_status: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ComponentNoteEntry {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub note: String,
+    pub author: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ComponentNoteRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    /// Replaces the stored note; omit or send "" to remove it
+    pub note: Option<String>,
+    /// Who wrote the note
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentNoteResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    pub reference: String,
+    /// The stored note; null once removed
+    pub note: Option<ComponentNoteEntry>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ComponentNotesQuery {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentNotesResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Notes by reference
+    pub notes: Vec<ComponentNoteEntry>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct DistillCacheStats {
+    /// Schematic files whose parse was reused from the parse cache
+    pub hits: usize,
+    /// Schematic files that had to be parsed
+    pub misses: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DistillWarning {
+    /// File path relative to repository root
+    pub file: String,
+    /// Parse error reported for the file
+    pub error: String,
+    /// Detected schematic format version, for files skipped as too old
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format_version: Option<u32>,
+}
+
+// ============================================================================
+// Repo Initialization Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RepoInitRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name (optional - uses latest if not provided)
+    pub commit: Option<String>,
+    /// Return counts and a sheet manifest instead of the full distilled data;
+    /// fetch sheets with `POST /api/distill/sheet` as needed
+    #[serde(default)]
+    pub summary_only: bool,
+    /// Also distill and index parts for up to this many earlier schematic
+    /// commits (at most 50), in a background job
+    pub backfill: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RepoInitResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash that was initialized
+    pub commit: String,
+    /// Whether the distilled data was already cached
+    pub cached: bool,
+    /// Number of components found in the schematic
+    pub component_count: usize,
+    /// Number of nets found in the schematic
+    pub net_count: usize,
+    /// List of schematic files found
+    pub schematic_files: Vec<String>,
+    /// Distilled schematic data (omitted with `summary_only`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distilled: Option<serde_json::Value>,
+    /// Per-sheet counts (only with `summary_only`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sheets: Option<Vec<DistillSheet>>,
+    /// Owner, subsystem and review status of annotated sheets
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sheet_meta: Vec<SheetMetaEntry>,
+    /// Job backfilling earlier commits; follow it with `GET /api/jobs/{id}`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backfill_job_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RepoClearCacheRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name (optional - clears all commits if not provided)
+    pub commit: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RepoClearCacheResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Whether the cache was cleared
+    pub cleared: bool,
+    /// Message describing what was cleared
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RepoReleasesRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct GithubReleaseInfo {
+    /// Release title
+    pub name: Option<String>,
+    /// Release page on GitHub
+    pub url: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub draft: bool,
+    pub prerelease: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RepoRelease {
+    /// Tag name, usable anywhere a commit hash is accepted
+    pub tag: String,
+    /// Tagged commit hash (null for GitHub releases whose tag isn't in the repository)
+    pub commit: Option<String>,
+    pub commit_date: Option<DateTime<Utc>>,
+    /// Annotated tag message or the tagged commit's summary
+    pub message: Option<String>,
+    /// The next older tag, for building release-notes ranges
+    pub previous_tag: Option<String>,
+    /// Commits since the previous tag that change schematics
+    pub schematic_commits: usize,
+    /// Whether the release changes any schematics
+    pub touches_schematics: bool,
+    /// The matching GitHub release, if one was published for this tag
+    pub github_release: Option<GithubReleaseInfo>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RepoReleasesResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// False when the GitHub releases API could not be reached; only git tags are listed then
+    pub github_releases_available: bool,
+    /// Releases, newest first
+    pub releases: Vec<RepoRelease>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ComponentSearchRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Pattern matched against reference, value, lib_id and MPN
+    pub query: Option<String>,
+    /// Pattern matched against the component value (e.g. "LM317")
+    pub value: Option<String>,
+    /// Pattern matched against the manufacturer part number
+    pub mpn: Option<String>,
+    /// Pattern matched against the symbol library ID (e.g. "Regulator_Linear:*")
+    pub lib_id: Option<String>,
+    /// Maximum number of matches to return (default 500, max 5000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentSearchMatch {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub value: Option<String>,
+    pub lib_id: Option<String>,
+    pub footprint: Option<String>,
+    /// MPN from the symbol, or from stored part enrichment
+    pub mpn: Option<String>,
+    /// Schematic sheet the component is placed on
+    pub sheet_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentSearchCommit {
+    /// Full commit hash
+    pub commit: String,
+    pub commit_date: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+    pub matches: Vec<ComponentSearchMatch>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ComponentSearchResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Number of matching components across all commits
+    pub total_matches: usize,
+    /// Whether more matches exist than `limit` allowed
+    pub truncated: bool,
+    /// Commits with matching components, newest first
+    pub commits: Vec<ComponentSearchCommit>,
+}
+
+// ============================================================================
+// Footprint Check Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FootprintCheckRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Look up packages on LCSC for parts without stored distributor data (default false; needs
+    /// LCSC_ENABLED)
+    #[serde(default)]
+    pub lookup_packages: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FootprintIssue {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub value: Option<String>,
+    pub lib_id: Option<String>,
+    pub footprint: Option<String>,
+    /// Schematic sheet the component is placed on
+    pub sheet_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FootprintLibraryIssue {
+    /// Footprint library nickname
+    pub library: String,
+    /// Whether an fp-lib-table declares the library (pointing outside the repository)
+    pub declared_in_lib_table: bool,
+    /// Components using footprints from the library
+    pub references: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PackageMismatch {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub footprint: String,
+    pub mpn: Option<String>,
+    /// Package as listed by the distributor (e.g., "8-SOIC")
+    pub package: String,
+    /// Where the package came from ("digikey" or "lcsc")
+    pub source: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FootprintCheckResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Components checked (power symbols excluded)
+    pub total_components: usize,
+    /// Components that had a distributor package to compare against
+    pub packages_checked: usize,
+    /// Whether no issues were found
+    pub passed: bool,
+    /// Components without a footprint assignment
+    pub missing_footprints: Vec<FootprintIssue>,
+    /// Footprint libraries that are neither in the repository nor shipped with KiCad
+    pub unknown_libraries: Vec<FootprintLibraryIssue>,
+    /// Footprints missing from a library that is in the repository
+    pub missing_library_footprints: Vec<FootprintIssue>,
+    /// Footprints that don't look like the distributor's package
+    pub package_mismatches: Vec<PackageMismatch>,
+}
+
+// ============================================================================
+// Symbol Check Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SymbolCheckRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SymbolPinIssue {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub lib_id: String,
+    /// Schematic sheet the component is placed on
+    pub sheet_path: Option<String>,
+    /// "missing_symbol" (no longer in its library) or "pin_mismatch"
+    pub kind: String,
+    /// Pins the library defines for the placed units
+    pub library_pins: usize,
+    /// Pins on the schematic instance
+    pub instance_pins: usize,
+    /// Library pins the instance doesn't have
+    pub missing_pins: Vec<String>,
+    /// Instance pins the library no longer defines
+    pub extra_pins: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SymbolCheckResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Symbol libraries found in the repository
+    pub symbol_libraries: Vec<String>,
+    /// Symbol instances compared with a repository library
+    pub checked: usize,
+    /// Symbol instances from libraries outside the repository (not checked)
+    pub skipped: usize,
+    /// Whether every checked instance matches its library symbol
+    pub passed: bool,
+    pub issues: Vec<SymbolPinIssue>,
+}
+
+// ============================================================================
+// Decoupling Check Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DecouplingCheckRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DecouplingCapacitor {
+    /// Reference designator (e.g., "C4")
+    pub reference: String,
+    pub value: Option<String>,
+    /// Distance from the IC on the schematic sheet
+    pub distance_mm: f64,
+}
+
+/// Decoupling of one IC's supply pins on one net
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DecouplingEntry {
+    /// Reference designator of the IC
+    pub reference: String,
+    pub value: Option<String>,
+    /// Supply net
+    pub net: String,
+    /// Pins of the IC on the net, e.g. "7 (VDD)"
+    pub pins: Vec<String>,
+    pub sheet: Option<String>,
+    /// Capacitors between the net and ground near the IC, closest first
+    pub capacitors: Vec<DecouplingCapacitor>,
+    /// "ok", "insufficient" (fewer capacitors than pins), "distant" (decoupling
+    /// on the net, none near the IC) or "missing" (none on the net at all)
+    pub status: String,
+}
+
+/// A capacitor near more than one IC on the same supply net
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SharedDecouplingCapacitor {
+    pub reference: String,
+    pub value: Option<String>,
+    pub net: String,
+    /// ICs the capacitor is counted for
+    pub components: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DecouplingCheckResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Capacitors further than this from an IC don't count as its decoupling
+    pub radius_mm: f64,
+    /// One entry per IC and supply net
+    pub entries: Vec<DecouplingEntry>,
+    pub shared_capacitors: Vec<SharedDecouplingCapacitor>,
+}
+
+// ============================================================================
+// Impact Analysis Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImpactRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Commit hash or tag to compare against; defaults to the commit's first parent
+    pub base: Option<String>,
+}
+
+/// Nets that merged, split or swapped pins between them
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImpactNetChange {
+    /// "merged" (several nets became one), "split" (one net became several)
+    /// or "rewired" (pins moved between nets)
+    pub change: String,
+    /// Nets involved before the commit
+    pub before: Vec<String>,
+    /// Nets involved after the commit
+    pub after: Vec<String>,
+    /// Components on any of these nets, before or after
+    pub components: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImpactedComponent {
+    /// Reference designator (e.g., "U3")
+    pub reference: String,
+    /// Any of "added", "removed", "edited", "connected" or "disconnected" (a pin
+    /// gained or lost its net), "net_merged", "net_split", "net_rewired"
+    pub reasons: Vec<String>,
+    /// Whether the commit changed the component or its pins itself, rather than
+    /// only the nets it sits on
+    pub direct: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ImpactResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Commit compared against; null for a root commit
+    pub base: Option<String>,
+    pub directly_affected: usize,
+    /// Components reached only through merged, split or rewired nets
+    pub indirectly_affected: usize,
+    pub net_changes: Vec<ImpactNetChange>,
+    /// Every affected component, by reference
+    pub components: Vec<ImpactedComponent>,
+}
+
+// ============================================================================
+// Pin Map Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PinMapRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// "json" (default), "c" for a C header or "rust" for a Rust module
+    pub format: Option<String>,
+    /// References to map (e.g. ["U1"]); defaults to the detected microcontrollers
+    pub components: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PinMapPin {
+    /// Pad or ball (e.g., "21", "A7")
+    pub number: String,
+    /// Pin name on the symbol (e.g., "PA5"), without overbar markup
+    pub name: Option<String>,
+    /// Null for unconnected pins
+    pub net: Option<String>,
+    /// "signal", "power", "ground" or "unconnected"
+    pub kind: String,
+    /// Constant name in the generated code, for signal pins on labelled nets
+    pub identifier: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PinMapMcu {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub value: Option<String>,
+    pub lib_id: Option<String>,
+    pub sheet: Option<String>,
+    /// Pins in pad order
+    pub pins: Vec<PinMapPin>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PinMapResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    pub mcus: Vec<PinMapMcu>,
+}
+
+// ============================================================================
+// Harness Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct HarnessRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HarnessConnector {
+    /// Reference designator (e.g., "J2")
+    pub reference: String,
+    /// KiCad project the connector belongs to; null when the repository has none
+    pub board: Option<String>,
+    pub value: Option<String>,
+    pub footprint: Option<String>,
+    pub connected_pins: usize,
+    /// Connectors on other boards sharing nets with this one
+    pub mated_with: Vec<String>,
+}
+
+/// One connector pin of the wiring table
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HarnessRow {
+    pub connector: String,
+    pub board: Option<String>,
+    pub pin: String,
+    pub pin_name: Option<String>,
+    /// Null for unconnected pins
+    pub net: Option<String>,
+    /// Connector pins on other boards on the same net, e.g. "J1 pin 3 (display)"
+    pub mates: Vec<String>,
+    /// Boards of the mates, or else where the net name says it goes
+    /// (e.g. "DISP" for "MAIN_TO_DISP_SDA")
+    pub destination: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct HarnessResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// KiCad projects in the repository
+    pub boards: Vec<String>,
+    pub connectors: Vec<HarnessConnector>,
+    /// Every connector pin, by connector and pin
+    pub rows: Vec<HarnessRow>,
+}
+
+// ============================================================================
+// Test Point Coverage Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct TestPointCoverageRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SubsystemCoverage {
+    /// Subsystem from the sheet metadata; null for sheets without one
+    pub subsystem: Option<String>,
+    /// Nets with a component on the subsystem's sheets
+    pub nets: usize,
+    /// Of those, nets with a test point
+    pub covered: usize,
+    pub coverage_percent: f64,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct UncoveredNet {
+    pub net: String,
+    /// Subsystems the net's components belong to
+    pub subsystems: Vec<String>,
+    /// Components on the net
+    pub components: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TestPointCoverageResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Reference designators of the test points found
+    pub test_points: Vec<String>,
+    /// Nets connecting at least two component pins
+    pub nets: usize,
+    /// Of those, nets with a test point
+    pub covered: usize,
+    pub coverage_percent: f64,
+    pub subsystems: Vec<SubsystemCoverage>,
+    /// Nets without a test point, by name
+    pub uncovered_nets: Vec<UncoveredNet>,
+}
+
+// ============================================================================
+// Cross-Reference Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct XrefRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct XrefEntry {
+    /// Label or power net name
+    pub name: String,
+    /// "global_label", "hierarchical_label" (labels and the sheet pins they
+    /// connect to) or "power"
+    pub kind: String,
+    /// Schematic files using the name, relative to repository root
+    pub sheets: Vec<String>,
+    /// Placements across all sheets
+    pub count: usize,
+    /// Likely mistakes: "single_sheet" (global label on one sheet only),
+    /// "no_sheet_pin" (hierarchical label without a matching sheet pin),
+    /// "no_hierarchical_label" (sheet pin without a matching label) and
+    /// "case_mismatch" (another name differs only in case)
+    pub flags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct XrefResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Schematic files scanned
+    pub sheet_count: usize,
+    /// Entries with at least one flag
+    pub flagged: usize,
+    /// One entry per name and kind, by name
+    pub entries: Vec<XrefEntry>,
+}
+
+// ============================================================================
+// Value Compare Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ValueCompareRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Earlier revision (commit hash or tag)
+    pub from: String,
+    /// Later revision (commit hash or tag)
+    pub to: String,
+    /// Smallest group reported (default 2, so only bulk changes; 1 lists every change)
+    pub min_count: Option<usize>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValueChangeGroup {
+    /// Which field changed: "value" or "mpn"
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+    /// Components that made this change
+    pub count: usize,
+    /// Reference designators of those components
+    pub references: Vec<String>,
+    /// Components that still have the old value at `to`; non-zero on a bulk
+    /// change can mean a global edit missed some parts
+    pub unchanged: usize,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ValueCompareResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash `from` resolved to
+    pub from_commit: String,
+    /// Full commit hash `to` resolved to
+    pub to_commit: String,
+    /// Components present in both revisions
+    pub compared: usize,
+    /// Components whose value or MPN changed
+    pub changed_components: usize,
+    /// Changes grouped by field and old → new value, largest first
+    pub groups: Vec<ValueChangeGroup>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MetricsHistoryRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Maximum number of commits to return, newest kept (default 1000, max 5000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DesignMetricsPoint {
+    /// Full commit hash
+    pub commit: String,
+    pub commit_date: Option<DateTime<Utc>>,
+    pub message: Option<String>,
+    /// Components, excluding power symbols and other virtual parts
+    pub component_count: i32,
+    pub net_count: i32,
+    pub sheet_count: i32,
+    /// Distinct normalized manufacturer part numbers
+    pub unique_mpns: i32,
+    /// Sum of enriched unit prices for one board; null when no part is priced
+    pub estimated_bom_cost: Option<f64>,
+    /// ISO 4217 code of `estimated_bom_cost`
+    pub currency: String,
+    /// `estimated_bom_cost` in the display currency; null when it can't be converted
+    pub display_estimated_bom_cost: Option<f64>,
+    /// Components with a known unit price
+    pub priced_components: i32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MetricsHistoryResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// ISO 4217 code of each point's `display_estimated_bom_cost`
+    pub display_currency: String,
+    /// Per-commit metrics, oldest first
+    pub points: Vec<DesignMetricsPoint>,
+}
+
+// ============================================================================
+// Admin Endpoint Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ProviderCredentialsRequest {
+    /// OAuth client ID
+    pub client_id: String,
+    /// OAuth client secret (stored encrypted, never returned)
+    pub client_secret: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProviderCredentialStatus {
+    /// Provider name (e.g., "digikey")
+    pub provider: String,
+    /// Whether credentials are available
+    pub configured: bool,
+    /// Where the active credentials come from ("database" or "environment")
+    pub source: Option<String>,
+    /// Last four characters of the client ID, for identification
+    pub client_id_hint: Option<String>,
+    /// When the stored credentials were last rotated
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Expiry of the currently cached access token, if any
+    pub token_expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProviderCredentialsResponse {
+    /// Whether SECRETS_ENCRYPTION_KEY (or the legacy CREDENTIALS_ENCRYPTION_KEY)
+    /// is set, allowing credentials to be stored
+    pub encryption_configured: bool,
+    /// Credential status for each provider that uses OAuth client credentials
+    pub providers: Vec<ProviderCredentialStatus>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminStoredSecret {
+    /// "secret" or "credential"
+    pub kind: String,
+    /// Secret name, or provider name of a credential
+    pub name: String,
+    /// Id of the key it is sealed with; null for credentials stored before keys had ids
+    pub key_id: Option<String>,
+    /// Whether it is sealed with the active key
+    pub on_active_key: bool,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminSecretsResponse {
+    /// Id of the key new secrets are sealed with; null when none is configured
+    pub active_key_id: Option<String>,
+    /// Ids of the keys in SECRETS_PREVIOUS_KEYS
+    pub previous_key_ids: Vec<String>,
+    /// Why the keys can't be used, when they can't
+    pub error: Option<String>,
+    /// Stored secrets and provider credentials, without their values
+    pub secrets: Vec<AdminStoredSecret>,
+    /// How many of them aren't sealed with the active key yet
+    pub pending_rotation: usize,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminSecretRequest {
+    /// Value to store (stored encrypted, never returned)
+    pub value: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminSecretsRotateResponse {
+    /// Secrets resealed with the active key
+    pub secrets: u64,
+    /// Provider credentials resealed with the active key
+    pub credentials: u64,
+    /// Ones that opened with no configured key and were left as they were,
+    /// as "secret:<name>" or "credential:<provider>"
+    pub failed: Vec<String>,
+    /// Id of the key they were resealed with
+    pub active_key_id: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminIndexStats {
+    /// Repositories with at least one indexed commit
+    pub repos_tracked: i64,
+    /// Commits with a stored schematic record
+    pub commits_indexed: i64,
+    /// Commits with cached distilled JSON
+    pub commits_distilled: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminDistillCacheStats {
+    /// Commits in the distilled JSON cache
+    pub commit_entries: i64,
+    /// Distinct distilled results stored for those commits; commits that
+    /// distil to the same result share one copy
+    pub commit_contents: i64,
+    /// Stored size of the per-commit cache in bytes
+    pub commit_bytes: i64,
+    /// Schematic file parses in the parse cache, keyed by blob OID and distiller version
+    pub file_entries: i64,
+    /// Stored size of the parse cache in bytes
+    pub file_bytes: i64,
+    /// Per-commit cache hits since `counters_since`
+    pub commit_hits: u64,
+    /// Per-commit cache misses since `counters_since`
+    pub commit_misses: u64,
+    /// commit_hits / (commit_hits + commit_misses), if there were any lookups
+    pub commit_hit_rate: Option<f64>,
+    /// Parse cache hits since `counters_since`
+    pub file_hits: u64,
+    /// Parse cache misses since `counters_since`
+    pub file_misses: u64,
+    /// file_hits / (file_hits + file_misses), if any files were looked up
+    pub file_hit_rate: Option<f64>,
+    /// Distill requests since `counters_since` that waited on a distillation of
+    /// the same commit already running instead of starting their own
+    pub coalesced_requests: u64,
+    /// When this API process started counting (hit/miss counters reset on restart)
+    pub counters_since: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminJobStats {
+    /// Jobs waiting for a worker (including retries)
+    pub queued: i64,
+    /// Jobs currently being run
+    pub running: i64,
+    /// Jobs that finished successfully
+    pub succeeded: i64,
+    /// Jobs that exhausted their attempts
+    pub failed: i64,
+    /// When the longest-waiting queued job was created
+    pub oldest_queued_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminDiskStats {
+    /// Directory holding repository caches and distiller scratch files
+    pub path: String,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    pub available_percent: f64,
+    /// "ok", "low" (under the warning threshold) or "critical" (clones and
+    /// distillation are refused)
+    pub status: String,
+    pub min_free_bytes: u64,
+    pub min_free_percent: f64,
+    pub warn_free_percent: f64,
+    /// Requests refused for lack of space since this process started
+    pub rejected: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminLlmSpend {
+    /// Start of the current calendar month (UTC)
+    pub since: DateTime<Utc>,
+    /// LLM calls that reported token usage
+    pub requests: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    /// Estimated cost in USD, from LLM_PROMPT_PRICE_PER_MTOK / LLM_COMPLETION_PRICE_PER_MTOK
+    pub cost_usd: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminRecentError {
+    /// When the error happened
+    pub at: DateTime<Utc>,
+    /// "api" for errors logged by this process, "job" for failed background jobs
+    pub source: String,
+    /// Module that logged the error, or the job kind
+    pub context: String,
+    pub message: String,
+    /// Request being handled when the error was logged (matches `ApiError.request_id`)
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminOverviewResponse {
+    /// When these numbers were collected
+    pub generated_at: DateTime<Utc>,
+    pub index: AdminIndexStats,
+    pub distill_cache: AdminDistillCacheStats,
+    pub jobs: AdminJobStats,
+    /// LLM usage for the current month
+    pub llm_spend: AdminLlmSpend,
+    /// Free space where repositories are cloned; None when it can't be measured
+    pub disk: Option<AdminDiskStats>,
+    /// Most recent errors, newest first
+    pub recent_errors: Vec<AdminRecentError>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminRefreshSummariesQuery {
+    /// Maximum number of summaries to regenerate (default SUMMARY_REFRESH_BATCH)
+    pub batch: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminRefreshSummariesResponse {
+    /// Current overview prompt version
+    pub prompt_version: String,
+    /// Current schematic-distiller version
+    pub distiller_version: String,
+    /// Summaries newly flagged stale by this request
+    pub marked_stale: u64,
+    /// Regeneration jobs handed to workers
+    pub queued: usize,
+    /// Summaries regenerated inline (job queue disabled)
+    pub regenerated: usize,
+    /// Summaries that could not be queued or regenerated
+    pub failed: usize,
+    /// Summaries still flagged stale, including ones just queued
+    pub remaining_stale: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminRepo {
+    /// Repository id
+    pub id: i32,
+    /// Provider host, e.g. "github.com"
+    pub provider: String,
+    /// Owner/name path
+    pub slug: String,
+    /// Canonical clone URL
+    pub repo_url: String,
+    /// Stored commits
+    pub commit_count: i64,
+    /// Date of the newest stored commit
+    pub last_commit_date: Option<DateTime<Utc>>,
+    /// Whether processed commits get a GitHub commit status
+    pub commit_status: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminReposResponse {
+    /// Tracked repositories, most recently active first
+    pub repos: Vec<AdminRepo>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminRenameRepoRequest {
+    /// Current repository URL or "owner/name" slug on GitHub
+    pub from: String,
+    /// New repository URL or "owner/name" slug on GitHub
+    pub to: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminRepoCommitStatusRequest {
+    /// Repository URL or "owner/name" slug on GitHub
+    pub repo: String,
+    /// Post a GitHub commit status for each processed commit
+    pub enabled: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminAuditQuery {
+    /// Only this action, e.g. "cache.cleared"
+    pub action: Option<String>,
+    /// Only this actor: "api", "admin", "webhook", "scheduler" or "job:<id>"
+    pub actor: Option<String>,
+    /// Only this repository: a full URL, or an "owner/name" slug on GitHub
+    pub repo: Option<String>,
+    /// Only entries recorded at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only entries recorded before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Maximum entries to return (default 100, at most 1000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminFailedJobsQuery {
+    /// Only jobs of this kind, e.g. "distill" or "regenerate_summary"
+    pub kind: Option<String>,
+    /// Only jobs that failed at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Maximum jobs to return (default 100, at most 1000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminFailedJob {
+    pub id: i64,
+    pub kind: String,
+    /// What the job was asked to do, e.g. `{"kind": "distill", "repo": ..., "commit": ...}`
+    pub payload: serde_json::Value,
+    pub attempts: i32,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When the job finally failed
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Every attempt at the job, oldest first
+    pub runs: Vec<JobRunEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminFailedJobsResponse {
+    /// Failed jobs, most recently failed first
+    pub jobs: Vec<AdminFailedJob>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminRetryJobsRequest {
+    /// Only these jobs
+    pub ids: Option<Vec<i64>>,
+    /// Only jobs of this kind
+    pub kind: Option<String>,
+    /// Only jobs that failed at or after this time, e.g. the start of an outage
+    pub since: Option<DateTime<Utc>>,
+    /// Maximum jobs to retry (default 100, at most 1000), most recently failed first
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminRetryJobsResponse {
+    /// Jobs put back on the queue (or run in the API process without JOB_QUEUE)
+    pub retried: Vec<i64>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminQuarantineQuery {
+    /// Only this repository: a full URL, or an "owner/name" slug on GitHub
+    pub repo: Option<String>,
+    /// Only this stage: "distill" or "overview"
+    pub stage: Option<String>,
+    /// Maximum commits to return (default 100, at most 1000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminQuarantinedCommit {
+    pub repo_url: String,
+    pub commit: String,
+    /// Stage the commit kept failing: "distill" or "overview"
+    pub stage: String,
+    /// Failures since the stage last succeeded on the commit
+    pub failures: i32,
+    pub last_error: Option<String>,
+    pub first_failed_at: DateTime<Utc>,
+    pub last_failed_at: DateTime<Utc>,
+    pub quarantined_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminQuarantineResponse {
+    /// Quarantined commits, most recently quarantined first
+    pub commits: Vec<AdminQuarantinedCommit>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminQuarantineReleaseRequest {
+    /// A full URL, or an "owner/name" slug on GitHub
+    pub repo: String,
+    pub commit: String,
+    /// Only release this stage; every stage when omitted
+    pub stage: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminQuarantineReleaseResponse {
+    /// Entries released; empty when the commit wasn't quarantined
+    pub released: Vec<AdminQuarantinedCommit>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminLogFilterRequest {
+    /// Filter directives, e.g. "info,kicad_backend::services::git=debug"; omit
+    /// to restore the filter the process started with
+    pub filter: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminLogFilterResponse {
+    /// Filter in effect
+    pub filter: String,
+    /// Filter it replaced, when changed by this request
+    pub previous: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminConfigEntry {
+    /// Environment variable, as documented in .env.example
+    pub name: String,
+    /// False when unset and its default applies
+    pub set: bool,
+    pub value: Option<String>,
+    /// Whether `value` hides a secret
+    pub redacted: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminConfigResponse {
+    /// Version of this backend build
+    pub version: String,
+    /// Log filter in effect
+    pub log_filter: Option<String>,
+    /// Distiller backend used by default: "python", "native" or "compare"
+    pub distiller_backend: String,
+    pub distiller_version: String,
+    /// Whether heavy work goes to grokicad-worker processes
+    pub job_queue: bool,
+    pub demo_mode: bool,
+    /// "local" or "s3"
+    pub blob_store: String,
+    pub settings: Vec<AdminConfigEntry>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct AdminStorageQuery {
+    /// Only this repository: a full URL, or an "owner/name" slug on GitHub
+    pub repo: Option<String>,
+    /// Maximum repositories to return, largest first (default 100, at most 1000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminBlobUsage {
+    /// "schematic_image", "render", "datasheet" or "export"
+    pub kind: String,
+    pub blobs: i64,
+    /// Stored bytes; blobs sharing their content count once
+    pub bytes: i64,
+}
+
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct AdminRepoStorage {
+    pub repo_url: String,
+    /// Stored commits
+    pub schematics: i64,
+    pub parts: i64,
+    /// Commits with distilled JSON
+    pub distilled_commits: i64,
+    /// On-disk size of that distilled JSON. Contents shared with other
+    /// repositories count for each of them.
+    pub distilled_bytes: i64,
+    /// Blob store usage by kind, schematic images included
+    pub blobs: Vec<AdminBlobUsage>,
+    pub blob_bytes: i64,
+    /// distilled_bytes + blob_bytes
+    pub total_bytes: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminStorageTotals {
+    /// Size of the whole Postgres database, indexes and other tables included
+    pub database_bytes: i64,
+    /// Distilled JSON; contents shared by several commits count once
+    pub distilled_bytes: i64,
+    /// Schematic parse cache, shared by all repositories
+    pub file_cache_bytes: i64,
+    /// Blob store (schematic images included), with shared contents counted once
+    pub blob_bytes: i64,
+    /// "local" or "s3"
+    pub blob_store: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminStorageResponse {
+    pub generated_at: DateTime<Utc>,
+    pub totals: AdminStorageTotals,
+    /// Repositories using the most storage first
+    pub repos: Vec<AdminRepoStorage>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminAuditEntry {
+    pub id: i64,
+    /// What happened, e.g. "repo.onboarded", "cache.invalidated", "summary.generated"
+    pub action: String,
+    /// Who triggered it
+    pub actor: String,
+    /// Repository URL at the time of the action
+    pub repo_url: Option<String>,
+    pub commit: Option<String>,
+    /// Action-specific parameters
+    pub params: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminAuditResponse {
+    /// Matching entries, newest first
+    pub entries: Vec<AdminAuditEntry>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminApiKeyRequest {
+    /// Who the key is for
+    pub name: String,
+    #[serde(flatten)]
+    pub quotas: AdminApiKeyQuotasRequest,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AdminApiKeyQuotasRequest {
+    /// Requests per month; unlimited when omitted
+    pub request_quota: Option<i64>,
+    /// LLM tokens (prompt and completion) per month; unlimited when omitted
+    pub llm_token_quota: Option<i64>,
+    /// Seconds of distillation per month; unlimited when omitted
+    pub cpu_seconds_quota: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminApiKey {
+    pub id: i64,
+    pub name: String,
+    /// First characters of the key, to tell keys apart
+    pub key_prefix: String,
+    pub request_quota: Option<i64>,
+    pub llm_token_quota: Option<i64>,
+    pub cpu_seconds_quota: Option<f64>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminApiKeyCreateResponse {
+    pub key: AdminApiKey,
+    /// The key to send in `x-api-key`. Only shown once.
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AdminApiKeysResponse {
+    /// All keys, revoked ones included
+    pub keys: Vec<AdminApiKey>,
+}
+
+// ============================================================================
+// Error Types
+// ============================================================================
+
+/// Body of every API error. Sent as RFC 7807 problem details (`detail` for
+/// `message`, plus `type`, `title`, `status` and `instance`) to clients that
+/// accept `application/problem+json`, see `errors::envelope`.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiError {
+    /// Error code
+    pub error: String,
+    /// Human-readable error message
+    pub message: String,
+    /// Id of the failed request (also in the `x-request-id` response header);
+    /// filled in by the request id middleware. Quote it when reporting an error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Machine-readable specifics of the error, e.g. the file and detected
+    /// format version of an `unsupported_format` error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+}
+
+impl ApiError {
+    pub fn new(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            error: error.into(),
+            message: message.into(),
+            request_id: None,
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new("not_found", message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new("internal_error", message)
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new("bad_request", message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new("unauthorized", message)
+    }
+
+    pub fn unsupported_format(message: impl Into<String>) -> Self {
+        Self::new("unsupported_format", message)
+    }
+}
+
+// ============================================================================
+// Job Queue Types
+// ============================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobStatusResponse {
+    /// Job id
+    pub id: i64,
+    /// Job type (e.g., "distill", "process_repo")
+    pub kind: String,
+    /// One of "queued", "running", "succeeded", "failed"
+    pub status: String,
+    /// Number of times a worker has picked up the job
+    pub attempts: i32,
+    /// Job output, once succeeded
+    pub result: Option<serde_json::Value>,
+    /// Last progress reported while running (e.g. `{"done": 3, "total": 20}`)
+    pub progress: Option<serde_json::Value>,
+    /// Error from the most recent failed attempt
+    pub last_error: Option<String>,
+    /// When the job was queued
+    pub created_at: DateTime<Utc>,
+    /// When the latest attempt started
+    pub started_at: Option<DateTime<Utc>>,
+    /// When the job succeeded or finally failed
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Every attempt at the job, oldest first
+    pub runs: Vec<JobRunEntry>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JobRunEntry {
+    /// Attempt number, from 1; retried jobs continue counting
+    pub attempt: i32,
+    /// Worker that ran the attempt ("api" for jobs run in the API process)
+    pub worker: String,
+    /// One of "running", "succeeded", "failed"
+    pub status: String,
+    pub error: Option<String>,
+    /// What the attempt produced, when it succeeded
+    pub artifacts: Option<serde_json::Value>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+}
+
+// ============================================================================
+// Blob Store Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BlobListRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Only return blobs of this kind ("schematic_image", "render", "datasheet", "export")
+    pub kind: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlobInfo {
+    /// Storage key, e.g. "render/owner/repo/<commit>/root.svg"
+    pub key: String,
+    /// One of "schematic_image", "render", "datasheet", "export"
+    pub kind: String,
+    /// MIME type of the stored file
+    pub content_type: String,
+    /// File size in bytes
+    pub size_bytes: i64,
+    /// Hex-encoded SHA-256 of the file contents
+    pub sha256: String,
+    /// Signed link to download the file
+    pub url: String,
+    /// When `url` stops working
+    pub url_expires_at: DateTime<Utc>,
+    /// When the file was stored
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BlobListResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Stored files for the commit
+    pub blobs: Vec<BlobInfo>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct BlobFileQuery {
+    /// Unix timestamp after which the link is rejected
+    pub expires: i64,
+    /// Hex-encoded HMAC from the signed link
+    pub signature: String,
+}
+
+// ============================================================================
+// Feedback Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SummaryFeedbackRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// What is being rated: "blurb", "description" or "commit_summary"
+    pub target: String,
+    /// 1 for thumbs up, -1 for thumbs down
+    pub rating: i16,
+    /// Optional free-text comment
+    pub comment: Option<String>,
+    /// The rated text; required for "commit_summary", ignored otherwise (the stored text is used)
+    pub summary_text: Option<String>,
+    /// Model from the summary response; required for "commit_summary"
+    pub model: Option<String>,
+    /// Prompt version from the summary response; required for "commit_summary"
+    pub prompt_version: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SummaryFeedbackResponse {
+    /// ID of the stored rating
+    pub id: i64,
+    /// Model the rating was attributed to
+    pub model: Option<String>,
+    /// Prompt version the rating was attributed to
+    pub prompt_version: Option<String>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct FeedbackExportQuery {
+    /// Only include this target ("blurb", "description" or "commit_summary")
+    pub target: Option<String>,
+    /// Only include ratings of this prompt version
+    pub prompt_version: Option<String>,
+    /// Only include ratings at or after this time (RFC 3339)
+    pub since: Option<DateTime<Utc>>,
+    /// Maximum number of entries to return (default 1000, max 10000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeedbackEntry {
+    pub id: i64,
+    /// Repository URL
+    pub repo_url: String,
+    /// Full commit hash
+    pub commit: String,
+    /// "blurb", "description" or "commit_summary"
+    pub target: String,
+    /// 1 for thumbs up, -1 for thumbs down
+    pub rating: i16,
+    pub comment: Option<String>,
+    /// The text that was rated
+    pub summary_text: Option<String>,
+    pub model: Option<String>,
+    pub prompt_version: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeedbackTotal {
+    pub target: String,
+    pub model: Option<String>,
+    pub prompt_version: Option<String>,
+    /// Thumbs up count
+    pub up: i64,
+    /// Thumbs down count
+    pub down: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeedbackExportResponse {
+    /// Vote counts per target/model/prompt version across all matching ratings
+    pub totals: Vec<FeedbackTotal>,
+    /// Matching ratings, newest first
+    pub entries: Vec<FeedbackEntry>,
+}
+
+// ============================================================================
+// Release Notes Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GrokReleaseNotesRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Start of the range (commit hash or tag), exclusive
+    pub from: String,
+    /// End of the range (commit hash or tag), inclusive
+    pub to: String,
+    /// Generate new notes even if notes for this range are already stored
+    #[serde(default)]
+    pub regenerate: bool,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReleaseNotesListRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Maximum number of entries to return (default 20, max 100)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReleaseNotesCommit {
+    /// Full commit hash
+    pub commit: String,
+    /// Timestamp of the commit
+    pub commit_date: Option<DateTime<Utc>>,
+    /// Commit message summary
+    pub message: Option<String>,
+    /// Schematic files changed by the commit
+    pub changed_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BomDeltaPart {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub value: Option<String>,
+    pub footprint: Option<String>,
+    /// Manufacturer part number
+    pub mpn: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BomDeltaChange {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    /// Which field changed: "value", "footprint" or "mpn"
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct BomDelta {
+    /// Components present at the end of the range but not the start
+    pub added: Vec<BomDeltaPart>,
+    /// Components present at the start of the range but not the end
+    pub removed: Vec<BomDeltaPart>,
+    /// Field changes on components present at both ends
+    pub changed: Vec<BomDeltaChange>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReleaseNotesResponse {
+    /// ID for retrieving these notes later
+    pub id: i64,
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Full commit hash (optional - clears all commits if not provided)
-    pub commit: Option<String>,
+    /// Start of the range as requested (commit hash or tag)
+    pub from: String,
+    /// End of the range as requested (commit hash or tag)
+    pub to: String,
+    /// Full commit hash `from` resolved to
+    pub from_commit: String,
+    /// Full commit hash `to` resolved to
+    pub to_commit: String,
+    /// Schematic commits in the range, oldest first
+    pub commits: Vec<ReleaseNotesCommit>,
+    /// Component changes between the start and end of the range
+    pub bom_delta: BomDelta,
+    /// Generated release notes (Markdown)
+    pub notes: String,
+    pub model: String,
+    pub prompt_version: String,
+    /// Whether these notes were already stored rather than generated for this request
+    pub cached: bool,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
-pub struct RepoClearCacheResponse {
+pub struct ReleaseNotesListResponse {
     /// GitHub repository in "owner/repo" format
     pub repo: String,
-    /// Whether the cache was cleared
-    pub cleared: bool,
-    /// Message describing what was cleared
+    /// Stored release notes, newest first
+    pub release_notes: Vec<ReleaseNotesResponse>,
+}
+
+// ============================================================================
+// CI Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CiVerdictRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name to judge
+    pub commit: String,
+    /// Commit hash or tag to diff against (e.g. the PR base); defaults to the commit's first parent
+    pub base: Option<String>,
+    /// Look up lifecycle status on DigiKey for parts without stored enrichment
+    #[serde(default)]
+    pub lookup_parts: bool,
+    /// "full" (default) reports every ERC violation; "differential" also checks the base
+    /// and reports only the violations the commit introduced, plus the ones it fixed
+    pub erc_mode: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CiCheck {
+    /// "diff", "connector_pinout", "erc" or "obsolescence"
+    pub name: String,
+    /// "pass", "warn" or "fail"
+    pub status: String,
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CiErcViolation {
+    /// Schematic file the violation was found in
+    pub file: String,
+    /// "error" or "warning"
+    pub severity: String,
+    /// Checker error code (e.g. "E001")
+    pub error_code: String,
+    /// Violation category (e.g. "pin_conflict")
+    pub violation_type: String,
     pub message: String,
+    pub component_refs: Vec<String>,
+    pub net_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CiObsoletePart {
+    /// Reference designator (e.g., "U1")
+    pub reference: String,
+    pub mpn: String,
+    /// Lifecycle status reported by the distributor
+    pub lifecycle_status: Option<String>,
+    /// "fail" for obsolete or discontinued parts, "warn" for not-recommended ones
+    pub status: String,
+    /// "digikey" for stored enrichment, "digikey_lookup" for a live lookup
+    pub source: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConnectorPinChange {
+    /// Pin number
+    pub pin: String,
+    /// Pin name from the symbol, if it has one
+    pub name: Option<String>,
+    /// Net before the change; null when the pin was unconnected or didn't exist
+    pub before: Option<String>,
+    /// Net after the change; null when the pin is unconnected or no longer exists
+    pub after: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConnectorPinoutChange {
+    /// Reference designator (e.g., "J1")
+    pub reference: String,
+    pub value: Option<String>,
+    /// Pins whose connection changed, by pin number
+    pub pins: Vec<ConnectorPinChange>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CiVerdictResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Full commit hash the diff is against; null for a root commit
+    pub base: Option<String>,
+    /// Worst status of the checks: "pass", "warn" or "fail"
+    pub verdict: String,
+    pub checks: Vec<CiCheck>,
+    /// Component changes against the base
+    pub bom_delta: BomDelta,
+    /// "full" or "differential"
+    pub erc_mode: String,
+    /// ERC errors; in differential mode only those the commit introduced
+    pub erc_errors: Vec<CiErcViolation>,
+    /// ERC warnings; in differential mode only those the commit introduced
+    pub erc_warnings: Vec<CiErcViolation>,
+    /// Errors in the base that the commit fixed (differential mode only)
+    pub erc_fixed_errors: Vec<CiErcViolation>,
+    /// Warnings in the base that the commit fixed (differential mode only)
+    pub erc_fixed_warnings: Vec<CiErcViolation>,
+    /// Parts flagged by the obsolescence check
+    pub obsolete_parts: Vec<CiObsoletePart>,
+    /// Connectors whose pinout changed against the base
+    pub connector_pinout_changes: Vec<ConnectorPinoutChange>,
+    /// Violations of the repository's design rules
+    pub rule_violations: Vec<DesignRuleViolation>,
+    /// IC supply nets whose decoupling is missing or not near the IC
+    pub decoupling_issues: Vec<DecouplingEntry>,
+    /// Parts with an MPN but no lifecycle data (not enriched and not looked up)
+    pub parts_without_lifecycle: usize,
 }
 
 // ============================================================================
-// Error Types
+// Design Rule Types
 // ============================================================================
 
+/// Components a design rule applies to or requires. Each pattern is a
+/// case-insensitive glob (`*`, `?`) and may list alternatives separated by `|`;
+/// every pattern given must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ComponentSelector {
+    /// Reference designator, e.g. "U*"
+    pub reference: Option<String>,
+    /// Category assigned by the distiller, e.g. "ic", "capacitor", "connector"
+    pub category: Option<String>,
+    /// Library symbol, e.g. "MCU_ST_STM32*"
+    pub lib_id: Option<String>,
+    /// Value, e.g. "100n*|0.1u*"
+    pub value: Option<String>,
+    pub footprint: Option<String>,
+}
+
+/// A declarative design policy: every component matching `applies_to` needs
+/// at least `min_count` components matching `require` connected to it or
+/// placed near it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DesignRuleSpec {
+    /// What the rule enforces, shown with its violations
+    pub description: Option<String>,
+    /// "error" (default) or "warning"
+    pub severity: Option<String>,
+    pub applies_to: ComponentSelector,
+    /// Check each pin whose name or number matches this glob on its own, e.g.
+    /// "VDD*"; components without such pins are skipped. Omit to check the
+    /// component as a whole.
+    pub pins: Option<String>,
+    pub require: ComponentSelector,
+    /// "same_net" (default): required parts share a net with the checked pin
+    /// (or with any pin of the component); "nearby": required parts are within
+    /// `max_distance_mm` on the same sheet
+    pub relation: Option<String>,
+    /// Distance limit in schematic millimetres; required for "nearby" and
+    /// narrows "same_net" when given
+    pub max_distance_mm: Option<f64>,
+    /// Required parts must be on the same sheet
+    #[serde(default)]
+    pub same_sheet: bool,
+    /// Required parts needed per checked component or pin (default 1)
+    pub min_count: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DesignRuleRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Rule name, unique per repository; storing an existing name replaces it
+    pub name: String,
+    pub rule: DesignRuleSpec,
+    /// Disabled rules are kept but not evaluated (default true)
+    pub enabled: Option<bool>,
+    /// Who changed the rule
+    pub updated_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DesignRuleEntry {
+    pub name: String,
+    pub rule: DesignRuleSpec,
+    pub enabled: bool,
+    pub updated_by: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DesignRuleListRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+}
+
 #[derive(Debug, Serialize, ToSchema)]
-pub struct ApiError {
-    /// Error code
-    pub error: String,
-    /// Human-readable error message
+pub struct DesignRuleListResponse {
+    pub repo: String,
+    /// Rules by name, disabled ones included
+    pub rules: Vec<DesignRuleEntry>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DesignRuleDeleteRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DesignRuleCheckRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DesignRuleViolation {
+    /// Name of the rule
+    pub rule: String,
+    /// "error" or "warning"
+    pub severity: String,
     pub message: String,
+    /// Component the rule was checked on
+    pub reference: String,
+    /// Pin checked, for rules with a pin pattern
+    pub pin: Option<String>,
+    /// Net of the checked pin
+    pub net: Option<String>,
+    pub sheet: Option<String>,
 }
 
-impl ApiError {
-    pub fn new(error: impl Into<String>, message: impl Into<String>) -> Self {
-        Self {
-            error: error.into(),
-            message: message.into(),
-        }
-    }
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DesignRuleCheckResponse {
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    /// Enabled rules evaluated
+    pub rules_checked: usize,
+    pub violations: Vec<DesignRuleViolation>,
+}
 
-    pub fn not_found(message: impl Into<String>) -> Self {
-        Self::new("not_found", message)
-    }
+// ============================================================================
+// Saved View Types
+// ============================================================================
 
-    pub fn internal(message: impl Into<String>) -> Self {
-        Self::new("internal_error", message)
-    }
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SavedViewZoom {
+    /// Left edge of the visible region, in schematic units
+    pub x: f64,
+    /// Top edge of the visible region, in schematic units
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
 
-    pub fn bad_request(message: impl Into<String>) -> Self {
-        Self::new("bad_request", message)
-    }
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SavedViewRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// Display name of the view
+    pub name: String,
+    /// Schematic file shown, relative to repository root
+    pub sheet: Option<String>,
+    /// Reference designators of the selected components
+    #[serde(default)]
+    pub selected_components: Vec<String>,
+    /// Visible region of the sheet
+    pub zoom: Option<SavedViewZoom>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SavedViewEntry {
+    /// View id (a UUID), used in share links
+    pub id: String,
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Full commit hash
+    pub commit: String,
+    pub name: String,
+    pub sheet: Option<String>,
+    pub selected_components: Vec<String>,
+    pub zoom: Option<SavedViewZoom>,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SavedViewCreateResponse {
+    pub view: SavedViewEntry,
+    /// Secret needed to delete the view (send as `x-view-token`); only returned here
+    pub edit_token: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SavedViewListRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Only views at this commit hash or tag
+    pub commit: Option<String>,
+    /// Maximum number of views (default 100, max 1000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SavedViewListResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Saved views, newest first
+    pub views: Vec<SavedViewEntry>,
+}
+
+// ============================================================================
+// Report Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CommitReportRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name to report on
+    pub commit: String,
+    /// Commit hash or tag to diff against; defaults to the commit's first parent
+    pub base: Option<String>,
+    /// "html" (default) or "pdf"; PDF needs REPORT_PDF_COMMAND on the server
+    pub format: Option<String>,
+    /// Leave out the blurb and description unless a reviewer approved them
+    #[serde(default)]
+    pub approved_only: bool,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct CommitReportQuery {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name to report on
+    pub commit: String,
+    /// Commit hash or tag to diff against; defaults to the commit's first parent
+    pub base: Option<String>,
+    /// Leave out the blurb and description unless a reviewer approved them
+    #[serde(default)]
+    pub approved_only: bool,
+}
+
+// ============================================================================
+// Digest Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DigestSubscribeRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Address the digest is sent to
+    pub email: String,
+    /// "daily", "weekly" (default) or "off" to pause without unsubscribing
+    pub frequency: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DigestConfirmationResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    pub email: String,
+    pub frequency: String,
+    /// Hours the emailed confirmation link stays valid
+    pub expires_in_hours: i32,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DigestConfirmQuery {
+    /// Token from the confirmation email
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DigestSubscriptionResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    pub email: String,
+    pub frequency: String,
+    /// End of the last digested period; null until the first digest
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DigestUnsubscribeRequest {
+    /// Unsubscribe token of the subscription
+    pub token: String,
+}
+
+// ============================================================================
+// Design Export Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DesignExportRequest {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit hash or tag name
+    pub commit: String,
+    /// "json" (default) or "xml"
+    pub format: Option<String>,
+}
+
+// ============================================================================
+// Usage Types
+// ============================================================================
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsageCount {
+    pub used: i64,
+    /// Monthly quota; null when unlimited
+    pub limit: Option<i64>,
+    pub remaining: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsageSeconds {
+    pub used: f64,
+    /// Monthly quota; null when unlimited
+    pub limit: Option<f64>,
+    pub remaining: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UsageResponse {
+    /// Name the key was issued under
+    pub key_name: String,
+    pub key_prefix: String,
+    /// Start of the current quota period (the calendar month, UTC)
+    pub period_start: DateTime<Utc>,
+    /// When usage resets
+    pub resets_at: DateTime<Utc>,
+    pub requests: UsageCount,
+    /// Prompt and completion tokens of LLM calls
+    pub llm_tokens: UsageCount,
+    /// Time spent distilling schematics
+    pub cpu_seconds: UsageSeconds,
+}
+
+// ============================================================================
+// Presence Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct PresenceQuery {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Commit being viewed
+    pub commit: String,
+    /// Display name shown to the other viewers
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PresenceViewer {
+    /// Id of the viewer's connection
+    pub session: String,
+    pub name: Option<String>,
+    /// Selected component references
+    pub selection: Vec<String>,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PresenceResponse {
+    pub repo: String,
+    pub commit: String,
+    /// Everyone viewing the commit, earliest first
+    pub viewers: Vec<PresenceViewer>,
+}
+
+/// Event sent to viewers over the presence socket, as JSON text with a "type" field
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PresenceEvent {
+    /// Everyone viewing the commit; sent on connecting (with the viewer's own
+    /// session id) and again whenever the viewer fell behind and missed events
+    Snapshot {
+        session: String,
+        viewers: Vec<PresenceViewer>,
+    },
+    Join {
+        viewer: PresenceViewer,
+        /// Viewers now in the room
+        count: usize,
+    },
+    Leave {
+        session: String,
+        count: usize,
+    },
+    SelectionChanged {
+        session: String,
+        selection: Vec<String>,
+    },
+    /// A comment on the commit, sent to every viewer including its author
+    Comment {
+        /// Viewer who sent it; null for comments posted through the comments API
+        session: Option<String>,
+        /// Id of the stored comment; null when it was only broadcast, not pinned
+        id: Option<i64>,
+        parent_id: Option<i64>,
+        author: String,
+        body: String,
+        anchor_kind: Option<String>,
+        anchor: Option<String>,
+        created_at: DateTime<Utc>,
+    },
+    /// A message of this viewer was refused; sent to that viewer only
+    Error {
+        message: String,
+    },
+}
+
+/// Message a viewer sends over the presence socket, as JSON text with a "type" field
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PresenceMessage {
+    /// Replace the viewer's selected component references
+    Selection { selection: Vec<String> },
+    /// Comment on the commit as the viewer's name (connect with `name` to
+    /// comment). With `pin` the comment is also stored with the commit's
+    /// comments, and may reply to one of them (`parent_id`).
+    Comment {
+        body: String,
+        /// "component" or "net" (requires `anchor`)
+        anchor_kind: Option<String>,
+        /// Reference designator or net name the comment is about
+        anchor: Option<String>,
+        parent_id: Option<i64>,
+        #[serde(default)]
+        pin: bool,
+    },
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SyncChangesQuery {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// `next_cursor` of the previous page; omit to start from the beginning
+    pub cursor: Option<String>,
+    /// Maximum number of changes to return (default 500, max 5000)
+    pub limit: Option<i64>,
+}
+
+/// An entity changed since the cursor, as it is now, with a "kind" field.
+/// Absent fields are null.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyncChange {
+    /// A stored commit with its summary
+    Commit {
+        commit: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        commit_date: Option<DateTime<Utc>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        /// Blurb as shown: the edit if there is one, else the generated blurb
+        #[serde(skip_serializing_if = "Option::is_none")]
+        blurb: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        /// draft, approved or rejected
+        review_status: String,
+        has_distilled: bool,
+        /// Changes whenever the distilled data does; refetch it when it differs
+        distilled_version: i32,
+        has_image: bool,
+        stored_at: DateTime<Utc>,
+    },
+    /// A part of a stored commit
+    Part {
+        commit: String,
+        part_uuid: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        blurb: Option<String>,
+        properties: serde_json::Value,
+    },
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncChangesResponse {
+    /// GitHub repository in "owner/repo" format
+    pub repo: String,
+    /// Changed entities, oldest change first. An entity changed several times
+    /// since the cursor appears once.
+    pub changes: Vec<SyncChange>,
+    /// Cursor to send for the next page, or later to poll for new changes
+    pub next_cursor: String,
+    /// Whether more changes are waiting past `next_cursor`
+    pub has_more: bool,
+}
+
+// ============================================================================
+// Provenance Types
+// ============================================================================
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct ProvenanceQuery {
+    /// Only this GitHub repository, in "owner/repo" format
+    pub repo: Option<String>,
+    /// Only artifacts of this commit
+    pub commit: Option<String>,
+    /// Only this kind of artifact: "summary", "release_notes" or "alternate"
+    pub artifact: Option<String>,
+    /// Only this artifact: a release notes id, or the MPN alternates were found for
+    pub artifact_ref: Option<String>,
+    /// Maximum records to return (default 100, at most 1000)
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProvenanceToolCall {
+    /// Tool name, e.g. "web_search"
+    pub name: String,
+    pub call_id: Option<String>,
+    pub status: Option<String>,
+    /// Arguments as the model sent them
+    pub input: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProvenanceEntry {
+    pub id: i64,
+    /// "summary", "release_notes" or "alternate"
+    pub artifact: String,
+    /// Which artifact: "blurb" for summaries, the release notes id, or the
+    /// MPN alternates were found for
+    pub artifact_ref: String,
+    /// Repository URL as recorded; not rewritten when a repository is renamed
+    pub repo_url: Option<String>,
+    pub commit: Option<String>,
+    pub model: String,
+    pub prompt_version: String,
+    /// SHA-256 of the full prompt as sent; null for generators without a prompt
+    pub prompt_sha256: Option<String>,
+    /// Tools the model called, in order
+    pub tool_calls: Vec<ProvenanceToolCall>,
+    /// SHA-256 of each input, by name
+    pub input_hashes: BTreeMap<String, String>,
+    /// SHA-256 of each stored output, by name
+    pub output_hashes: BTreeMap<String, String>,
+    pub created_at: DateTime<Utc>,
+    /// SHA-256 over the rest of the record, taken when it was written
+    pub record_sha256: String,
+    /// Whether the record still matches `record_sha256`
+    pub verified: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProvenanceListResponse {
+    /// Matching records, newest first
+    pub records: Vec<ProvenanceEntry>,
 }
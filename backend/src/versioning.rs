@@ -0,0 +1,84 @@
+use axum::{
+    extract::{OriginalUri, Request},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use tracing::warn;
+
+/// Prefix of the current API version
+pub const CURRENT_PREFIX: &str = "/api/v1";
+
+/// Prefix of the unversioned paths served before versioning, now deprecated
+pub const LEGACY_PREFIX: &str = "/api";
+
+// When the unversioned paths were deprecated (2026-10-17), as a Unix time
+const DEPRECATED_AT: i64 = 1_792_195_200;
+
+// Date the unversioned paths are removed unless LEGACY_API_SUNSET says otherwise
+const DEFAULT_SUNSET: &str = "2027-04-17";
+
+/// The `Sunset` header: LEGACY_API_SUNSET (YYYY-MM-DD) as an HTTP date
+static SUNSET: Lazy<String> = Lazy::new(|| {
+    let configured = std::env::var("LEGACY_API_SUNSET").ok();
+    let date = configured
+        .as_deref()
+        .and_then(|v| match NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d") {
+            Ok(date) => Some(date),
+            Err(e) => {
+                warn!("Ignoring LEGACY_API_SUNSET {:?}: {}", v, e);
+                None
+            }
+        })
+        .or_else(|| NaiveDate::parse_from_str(DEFAULT_SUNSET, "%Y-%m-%d").ok())
+        .unwrap_or_default();
+    date.format("%a, %d %b %Y 00:00:00 GMT").to_string()
+});
+
+/// A request path as the unversioned route it is served by, so checks on
+/// path prefixes treat "/api/v1/repo/init" and "/api/repo/init" alike
+pub fn unversioned(path: &str) -> String {
+    match path.strip_prefix(CURRENT_PREFIX) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", LEGACY_PREFIX, rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// An unversioned path as the same route under the current version
+pub fn current(path: &str) -> String {
+    match path.strip_prefix(LEGACY_PREFIX) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", CURRENT_PREFIX, rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Middleware on the unversioned routes: marks responses with `Deprecation`
+/// and `Sunset` headers and links the same route under the current version
+pub async fn deprecate_legacy(request: Request, next: Next) -> Response {
+    // Nested routes see their path without the prefix; the link needs all of it
+    let path = match request.extensions().get::<OriginalUri>() {
+        Some(OriginalUri(uri)) => uri.path().to_string(),
+        None => request.uri().path().to_string(),
+    };
+    let successor = current(&path);
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    if let Ok(value) = HeaderValue::from_str(&format!("@{}", DEPRECATED_AT)) {
+        headers.insert("deprecation", value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&SUNSET) {
+        headers.insert("sunset", value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor))
+    {
+        headers.append(header::LINK, value);
+    }
+    response
+}
@@ -0,0 +1,137 @@
+//! Compares storing parts row by row (the previous `store_schematic`) with the
+//! batched insert. Needs the database up (`./database-up.sh`):
+//!
+//!     cargo bench --bench store_schematic
+
+use kicad_db::{create_pool, store_schematic, PgPool};
+use serde_json::{json, Value};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const REPO_URL: &str = "https://github.com/bench/store-schematic.git";
+const PART_COUNTS: &[usize] = &[100, 1_000, 5_000];
+const ITERATIONS: u32 = 5;
+
+fn make_parts(count: usize) -> HashMap<Uuid, (Option<String>, Value)> {
+    (0..count)
+        .map(|i| {
+            let reference = format!("R{}", i + 1);
+            let properties = json!({ "reference": reference, "value": "10k", "footprint": "Resistor_SMD:R_0603_1608Metric" });
+            (Uuid::new_v4(), (Some(format!("Resistor {}", reference)), properties))
+        })
+        .collect()
+}
+
+/// The previous implementation: one INSERT per part inside a transaction
+async fn store_row_by_row(
+    pool: &PgPool,
+    commit_hash: &str,
+    parts: &HashMap<Uuid, (Option<String>, Value)>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let schematic_id: i32 = sqlx::query(
+        r#"
+        INSERT INTO schematics (repo_url, commit_hash)
+        VALUES ($1, $2)
+        ON CONFLICT (repo_url, commit_hash) DO UPDATE SET repo_url = EXCLUDED.repo_url
+        RETURNING id
+        "#,
+    )
+    .bind(REPO_URL)
+    .bind(commit_hash)
+    .fetch_one(&mut *tx)
+    .await?
+    .try_get("id")?;
+
+    for (part_uuid, (blurb, properties)) in parts {
+        sqlx::query(
+            r#"
+            INSERT INTO parts (schematic_id, part_uuid, blurb, properties)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (schematic_id, part_uuid) DO UPDATE SET
+                blurb = EXCLUDED.blurb,
+                properties = EXCLUDED.properties
+            "#,
+        )
+        .bind(schematic_id)
+        .bind(part_uuid)
+        .bind(blurb)
+        .bind(properties)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await
+}
+
+async fn clean_up(pool: &PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(REPO_URL)
+        .execute(pool)
+        .await
+        .map(|_| ())
+}
+
+fn per_iteration(total: Duration) -> f64 {
+    total.as_secs_f64() * 1000.0 / f64::from(ITERATIONS)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!(
+                "Could not connect to DB ({}). Run `./database-up.sh` first.",
+                e
+            );
+            return Ok(());
+        }
+    };
+
+    println!(
+        "{:>8}  {:>14}  {:>14}  {:>8}",
+        "parts", "row by row", "batched", "speedup"
+    );
+    for &count in PART_COUNTS {
+        let parts = make_parts(count);
+        let mut row_by_row = Duration::ZERO;
+        let mut batched = Duration::ZERO;
+
+        for i in 0..ITERATIONS {
+            clean_up(&pool).await?;
+            let start = Instant::now();
+            store_row_by_row(&pool, &format!("row-{}", i), &parts).await?;
+            row_by_row += start.elapsed();
+
+            clean_up(&pool).await?;
+            let start = Instant::now();
+            store_schematic(
+                &pool,
+                REPO_URL,
+                &format!("batch-{}", i),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                parts.clone(),
+            )
+            .await?;
+            batched += start.elapsed();
+        }
+
+        println!(
+            "{:>8}  {:>11.1} ms  {:>11.1} ms  {:>7.1}x",
+            count,
+            per_iteration(row_by_row),
+            per_iteration(batched),
+            row_by_row.as_secs_f64() / batched.as_secs_f64()
+        );
+    }
+
+    clean_up(&pool).await?;
+    Ok(())
+}
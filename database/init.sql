@@ -5,7 +5,6 @@ CREATE TABLE IF NOT EXISTS schematics (
     commit_date TIMESTAMPTZ,
     git_message TEXT,
     UNIQUE(repo_url, commit_hash),
-    schematic_image BYTEA,
     change_summary TEXT,
     project_overview TEXT,
     blurb TEXT,
@@ -22,3 +21,676 @@ CREATE TABLE IF NOT EXISTS parts (
     properties JSONB DEFAULT '{}',
     UNIQUE(schematic_id, part_uuid)
 );
+
+-- Supplier API credentials as first stored; they now live in secrets, and the backend
+-- moves any rows left here at startup
+CREATE TABLE IF NOT EXISTS provider_credentials (
+    provider TEXT PRIMARY KEY,
+    client_id TEXT NOT NULL,
+    secret_nonce BYTEA NOT NULL,
+    secret_ciphertext BYTEA NOT NULL,
+    updated_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Per-file distill results were merged across sheets by net name, which broke
+-- hierarchical connectivity; only the parse step is cached now
+DROP TABLE IF EXISTS distill_file_cache;
+
+-- Parsed S-expression tree of a schematic file, keyed by git blob OID and the
+-- distiller version that parsed it (content-addressed, shared across repos/commits)
+CREATE TABLE IF NOT EXISTS schematic_parse_cache (
+    blob_oid TEXT NOT NULL,
+    distiller_version TEXT NOT NULL,
+    parsed BYTEA NOT NULL,
+    created_at TIMESTAMPTZ DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (blob_oid, distiller_version)
+);
+
+-- Background job queue shared by the API and grokicad-worker processes
+CREATE TABLE IF NOT EXISTS jobs (
+    id BIGSERIAL PRIMARY KEY,
+    kind TEXT NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL DEFAULT 'queued', -- queued | running | succeeded | failed
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 3,
+    result JSONB,
+    last_error TEXT,
+    locked_by TEXT,
+    run_after TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    started_at TIMESTAMPTZ,
+    finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS jobs_queued_idx ON jobs (run_after, id) WHERE status = 'queued';
+-- Finding the job already queued for the same work (e.g. distilling one commit). Payloads
+-- are matched by containment, so a job carrying more fields, such as the API key its
+-- time is charged to, is still found
+DROP INDEX IF EXISTS jobs_payload_idx;
+CREATE INDEX IF NOT EXISTS jobs_payload_contains_idx ON jobs USING GIN (payload jsonb_path_ops);
+
+-- Metadata for files kept in the blob store (schematic images, renders, datasheets, export archives).
+-- The bytes live on the filesystem or in S3-compatible storage, not in Postgres.
+CREATE TABLE IF NOT EXISTS blobs (
+    key TEXT PRIMARY KEY,
+    kind TEXT NOT NULL, -- schematic_image | render | datasheet | export
+    repo_url TEXT,
+    commit_hash TEXT,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    sha256 TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS blobs_commit_idx ON blobs (repo_url, commit_hash);
+-- Schematic images used to live in schematics.schematic_image. The backend
+-- copies any left there into the blob store at startup, then drops the column.
+
+-- One row per LLM API call that reported token usage, for spend tracking
+CREATE TABLE IF NOT EXISTS llm_usage (
+    id BIGSERIAL PRIMARY KEY,
+    feature TEXT NOT NULL, -- e.g. commit_summary, obsolete_replacement
+    model TEXT NOT NULL,
+    prompt_tokens BIGINT NOT NULL DEFAULT 0,
+    completion_tokens BIGINT NOT NULL DEFAULT 0,
+    cost_usd DOUBLE PRECISION NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS llm_usage_created_idx ON llm_usage (created_at);
+
+-- Which generator produced a commit's blurb/description, so feedback can be traced to a prompt
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_model TEXT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_prompt_version TEXT;
+
+-- User ratings of generated summaries, used to iterate on prompts
+CREATE TABLE IF NOT EXISTS feedback (
+    id BIGSERIAL PRIMARY KEY,
+    schematic_id INTEGER REFERENCES schematics(id) ON DELETE SET NULL,
+    repo_url TEXT NOT NULL,
+    commit_hash TEXT NOT NULL,
+    target TEXT NOT NULL, -- blurb | description | commit_summary
+    rating SMALLINT NOT NULL CHECK (rating IN (-1, 1)),
+    comment TEXT,
+    summary_text TEXT, -- the text that was rated, as shown to the user
+    model TEXT,
+    prompt_version TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS feedback_created_idx ON feedback (created_at);
+CREATE INDEX IF NOT EXISTS feedback_prompt_idx ON feedback (target, prompt_version);
+
+-- Summary staleness: summaries generated by an older distiller or prompt are regenerated in the background
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_distiller_version TEXT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_generated_at TIMESTAMPTZ;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_stale BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_requeued_at TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS schematics_stale_idx ON schematics (summary_requeued_at) WHERE summary_stale;
+
+-- Generated hardware release notes for a commit range
+CREATE TABLE IF NOT EXISTS release_notes (
+    id BIGSERIAL PRIMARY KEY,
+    repo_url TEXT NOT NULL,
+    from_ref TEXT NOT NULL, -- commit hash or tag as requested
+    to_ref TEXT NOT NULL,
+    from_commit TEXT NOT NULL,
+    to_commit TEXT NOT NULL,
+    commits JSONB NOT NULL, -- schematic commits in the range with their changed files
+    bom_delta JSONB NOT NULL,
+    notes TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_version TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(repo_url, from_commit, to_commit)
+);
+
+CREATE INDEX IF NOT EXISTS release_notes_repo_idx ON release_notes (repo_url, created_at);
+
+-- Per-commit design complexity metrics, for charting growth over time
+CREATE TABLE IF NOT EXISTS design_metrics (
+    schematic_id INTEGER PRIMARY KEY REFERENCES schematics(id) ON DELETE CASCADE,
+    component_count INTEGER NOT NULL,
+    net_count INTEGER NOT NULL,
+    sheet_count INTEGER NOT NULL,
+    unique_mpns INTEGER NOT NULL,
+    estimated_bom_cost DOUBLE PRECISION, -- sum of known unit prices for one board; NULL when no part is priced
+    priced_components INTEGER NOT NULL DEFAULT 0,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Transactional outbox: events are written in the same transaction as the state
+-- change they describe, then delivered by a relay with retries
+CREATE TABLE IF NOT EXISTS outbox_events (
+    id BIGSERIAL PRIMARY KEY,
+    event_type TEXT NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL DEFAULT 'pending', -- pending | delivering | delivered | failed
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL DEFAULT 10,
+    last_error TEXT,
+    deliver_after TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    claimed_at TIMESTAMPTZ,
+    delivered_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS outbox_events_pending_idx ON outbox_events (deliver_after, id) WHERE status = 'pending';
+
+-- Repositories by provider host and owner/name slug. Schematics reference their
+-- repo by id, so a rename or provider move is a single row update (see repos::rename_repo)
+CREATE TABLE IF NOT EXISTS repos (
+    id SERIAL PRIMARY KEY,
+    provider TEXT NOT NULL,
+    slug TEXT NOT NULL,
+    repo_url TEXT NOT NULL, -- canonical https://<provider>/<slug>.git
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS repos_provider_slug_idx ON repos (provider, (LOWER(slug)));
+
+-- Existing rows are attached by repos::backfill_repo_ids at startup
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS repo_id INTEGER REFERENCES repos(id) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS schematics_repo_id_idx ON schematics (repo_id, commit_date);
+
+-- Bumped on every distilled_json write or clear, for compare-and-swap updates
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS distilled_version INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS distilled_updated_at TIMESTAMPTZ;
+
+-- User-assigned metadata per schematic sheet (owner, subsystem, review status),
+-- included in distill responses and review prompts
+CREATE TABLE IF NOT EXISTS sheet_meta (
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    sheet_path TEXT NOT NULL, -- schematic file path relative to the repository root
+    owner TEXT,
+    subsystem TEXT,
+    review_status TEXT, -- unreviewed | in_review | changes_requested | approved
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (repo_id, sheet_path)
+);
+
+-- Named snapshots of the schematic viewer (commit, sheet, selection, zoom),
+-- shared by link. Anyone with the link can open a view; deleting it takes the
+-- edit token returned at creation, stored here as its SHA-256
+CREATE TABLE IF NOT EXISTS saved_views (
+    id UUID PRIMARY KEY,
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    commit_hash TEXT NOT NULL,
+    name TEXT NOT NULL,
+    sheet_path TEXT,
+    selected_components TEXT[] NOT NULL DEFAULT '{}',
+    zoom JSONB, -- {"x", "y", "width", "height"} of the visible region
+    notes TEXT,
+    owner TEXT, -- reserved for per-user views once requests are authenticated
+    edit_token_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS saved_views_repo_idx ON saved_views (repo_id, created_at);
+
+-- Human comments on a commit, threaded by parent and optionally anchored to a
+-- component reference or net name. Returned next to the AI summaries
+CREATE TABLE IF NOT EXISTS commit_comments (
+    id BIGSERIAL PRIMARY KEY,
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    commit_hash TEXT NOT NULL,
+    parent_id BIGINT REFERENCES commit_comments(id) ON DELETE CASCADE,
+    author TEXT NOT NULL,
+    body TEXT NOT NULL,
+    anchor_kind TEXT, -- component | net
+    anchor TEXT, -- reference designator or net name
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS commit_comments_commit_idx ON commit_comments (repo_id, commit_hash, created_at);
+
+-- Digest email subscriptions, one per subscriber and watched repository. Due
+-- digests are compiled by the backend's digest scheduler and handed to the
+-- notification outbox as `digest.email` events
+CREATE TABLE IF NOT EXISTS digest_subscriptions (
+    id BIGSERIAL PRIMARY KEY,
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    frequency TEXT NOT NULL DEFAULT 'weekly', -- daily | weekly | off
+    unsubscribe_token TEXT NOT NULL UNIQUE, -- included in every digest for one-click unsubscribe
+    last_sent_at TIMESTAMPTZ, -- end of the last digested period; NULL until the first digest
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS digest_subscriptions_email_idx ON digest_subscriptions (repo_id, (LOWER(email)));
+
+-- Subscription requests awaiting confirmation. Subscribing emails a link with the
+-- token; following it within 48 hours creates the subscription or changes its frequency
+CREATE TABLE IF NOT EXISTS digest_confirmations (
+    token TEXT PRIMARY KEY,
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    email TEXT NOT NULL,
+    frequency TEXT NOT NULL, -- daily | weekly | off
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS digest_confirmations_email_idx ON digest_confirmations (repo_id, (LOWER(email)));
+
+-- Audit trail of processing actions: onboarding, cache clears and invalidations,
+-- summary generation and admin changes, with who triggered them and how
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    action TEXT NOT NULL, -- e.g. repo.onboarded, cache.cleared, summary.generated
+    actor TEXT NOT NULL, -- api | admin | webhook | scheduler | job:<id>
+    repo_url TEXT, -- as recorded; not rewritten when a repository is renamed
+    commit_hash TEXT,
+    params JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_created_idx ON audit_log (created_at);
+CREATE INDEX IF NOT EXISTS audit_log_repo_idx ON audit_log (repo_url, created_at);
+
+-- API keys of the hosted multi-tenant service. Quotas apply per calendar month
+-- (UTC); NULL means unlimited
+CREATE TABLE IF NOT EXISTS api_keys (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE, -- SHA-256 hex of the key; the key itself is only shown at creation
+    key_prefix TEXT NOT NULL, -- first characters of the key, to tell keys apart
+    request_quota BIGINT,
+    llm_token_quota BIGINT,
+    cpu_seconds_quota DOUBLE PRECISION, -- seconds spent distilling
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Usage of each API key per month, counted against its quotas
+CREATE TABLE IF NOT EXISTS api_key_usage (
+    key_id BIGINT NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    period_start DATE NOT NULL, -- first day of the month
+    requests BIGINT NOT NULL DEFAULT 0,
+    llm_tokens BIGINT NOT NULL DEFAULT 0,
+    cpu_seconds DOUBLE PRECISION NOT NULL DEFAULT 0,
+    PRIMARY KEY (key_id, period_start)
+);
+
+-- Shared key metering requests sent without an x-api-key header. Its hash can't
+-- match a real key's; quotas are set like any key's, and revoking it refuses
+-- requests without a key.
+INSERT INTO api_keys (name, key_hash, key_prefix)
+VALUES ('anonymous', 'anonymous', '')
+ON CONFLICT (key_hash) DO NOTHING;
+
+-- Progress of long-running jobs (e.g. {"done": 3, "total": 20} for a backfill)
+ALTER TABLE jobs ADD COLUMN IF NOT EXISTS progress JSONB;
+
+-- Approved alternates (second sources) for a part, per repository. `mpn` and
+-- `alternate_mpn` are normalized MPNs; a BOM line with `mpn` may be built
+-- with any of its alternates
+CREATE TABLE IF NOT EXISTS part_alternates (
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    mpn TEXT NOT NULL,
+    alternate_mpn TEXT NOT NULL,
+    manufacturer TEXT,
+    note TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (repo_id, mpn, alternate_mpn)
+);
+
+-- Daily exchange rates: units of each currency per one unit of `base`
+CREATE TABLE IF NOT EXISTS exchange_rates (
+    base TEXT NOT NULL,
+    fetched_on DATE NOT NULL,
+    rates JSONB NOT NULL, -- {"EUR": 0.92, ...}
+    PRIMARY KEY (base, fetched_on)
+);
+
+-- Review of a commit's generated blurb/description: 'draft' until someone approves or
+-- rejects it. New text from the generator puts it back to draft.
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_review_status TEXT NOT NULL DEFAULT 'draft'
+    CHECK (summary_review_status IN ('draft', 'approved', 'rejected'));
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_reviewed_by TEXT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_reviewed_at TIMESTAMPTZ;
+
+-- Human edits of a commit's blurb/description. The generated text stays in blurb/description;
+-- reads show the edit when there is one.
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS blurb_override TEXT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS description_override TEXT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_edited_by TEXT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS summary_edited_at TIMESTAMPTZ;
+
+-- Chat sessions over one commit's schematic. The schematic context is built once and every
+-- question reuses it with the turns so far, so follow-ups share a prompt prefix the provider caches
+CREATE TABLE IF NOT EXISTS analysis_sessions (
+    id UUID PRIMARY KEY,
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    commit_hash TEXT NOT NULL,
+    context TEXT NOT NULL, -- system prompt with the schematic summary
+    distilled_json JSONB, -- distilled data sent by the client, if any
+    turns JSONB NOT NULL DEFAULT '[]', -- [{"user": ..., "assistant": ...}], oldest first
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS analysis_sessions_last_used_idx ON analysis_sessions (last_used_at);
+
+-- Heuristic risk score of a commit's changes against its first parent. factors lists the
+-- weighted findings: [{"kind": ..., "weight": ..., "count": ..., "score": ..., "items": [...]}]
+CREATE TABLE IF NOT EXISTS commit_risk (
+    schematic_id INTEGER PRIMARY KEY REFERENCES schematics(id) ON DELETE CASCADE,
+    score INTEGER NOT NULL,
+    factors JSONB NOT NULL DEFAULT '[]',
+    scorer_version TEXT NOT NULL, -- rescored when the heuristics change
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Team-defined design policy rules of a repository, evaluated against each commit's
+-- distilled data. definition holds the rule: which components it applies to, which must be
+-- connected or placed near them, and how severe a miss is
+CREATE TABLE IF NOT EXISTS design_rules (
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    definition JSONB NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_by TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (repo_id, name)
+);
+
+-- One row per attempt at a job, kept after the job finishes so operators can see what failed,
+-- where and for how long. artifacts holds what a successful run produced (the job's result)
+CREATE TABLE IF NOT EXISTS job_runs (
+    id BIGSERIAL PRIMARY KEY,
+    job_id BIGINT NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    worker TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'running', -- running | succeeded | failed
+    error TEXT,
+    artifacts JSONB,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at TIMESTAMPTZ,
+    duration_ms BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS job_runs_job_idx ON job_runs (job_id, id);
+CREATE INDEX IF NOT EXISTS jobs_failed_idx ON jobs (finished_at) WHERE status = 'failed';
+
+-- Consecutive failures of one processing stage (distill, overview) on one commit. Once
+-- failures reach the configured limit the commit is quarantined: the stage skips it until an
+-- operator releases it, so a poison commit can't block the webhook loop or the job queue.
+-- A success deletes the row
+CREATE TABLE IF NOT EXISTS commit_failures (
+    repo_url TEXT NOT NULL,
+    commit_hash TEXT NOT NULL,
+    stage TEXT NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    first_failed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_failed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    quarantined_at TIMESTAMPTZ,
+    PRIMARY KEY (repo_url, commit_hash, stage)
+);
+
+CREATE INDEX IF NOT EXISTS commit_failures_quarantined_idx ON commit_failures (quarantined_at)
+    WHERE quarantined_at IS NOT NULL;
+
+-- Distilled JSON stored once per distinct result, keyed by the SHA-256 of its canonical
+-- serialization. Commits that don't change the design point at the same row through
+-- schematics.distilled_sha256; rows written before this keep their JSON inline in
+-- schematics.distilled_json, and readers take whichever is set
+CREATE TABLE IF NOT EXISTS distilled_contents (
+    sha256 TEXT PRIMARY KEY,
+    distilled_json JSONB NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS distilled_sha256 TEXT REFERENCES distilled_contents(sha256);
+CREATE INDEX IF NOT EXISTS schematics_distilled_sha256_idx ON schematics (distilled_sha256);
+
+-- Where a blob's bytes live in the store when they're shared by content hash
+-- (content/<sha256>); NULL means at the blob's own key
+ALTER TABLE blobs ADD COLUMN IF NOT EXISTS content_key TEXT;
+CREATE INDEX IF NOT EXISTS blobs_content_key_idx ON blobs (content_key);
+
+-- One row per shared blob content. Writers lock it while they record a blob
+-- using it, so the content can't be deleted from under them (see blobs::release_content)
+CREATE TABLE IF NOT EXISTS blob_contents (
+    content_key TEXT PRIMARY KEY,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+INSERT INTO blob_contents (content_key, size_bytes)
+SELECT content_key, MAX(size_bytes) FROM blobs WHERE content_key IS NOT NULL GROUP BY content_key
+ON CONFLICT (content_key) DO NOTHING;
+
+-- Post a GitHub commit status for each processed commit (see services::commit_status)
+ALTER TABLE repos ADD COLUMN IF NOT EXISTS commit_status BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Read-aloud variant of a commit's blurb (the edit when there is one), with the blurb it was
+-- made from and the rules version, so edits, regenerations and rule changes make it again
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS speech_blurb TEXT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS speech_blurb_source TEXT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS speech_blurb_version TEXT;
+
+-- Retrieval index for questions about a design (see services::retrieval): each commit's
+-- components, nets, sheets, summaries and comments as documents with an embedding and the
+-- metadata queries filter on. A commit's documents are replaced together, and the version
+-- records the chunking rules and distiller they were built with
+CREATE TABLE IF NOT EXISTS retrieval_indexes (
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    commit_hash TEXT NOT NULL,
+    version TEXT NOT NULL,
+    documents INTEGER NOT NULL DEFAULT 0,
+    indexed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (repo_id, commit_hash)
+);
+
+CREATE TABLE IF NOT EXISTS retrieval_documents (
+    id BIGSERIAL PRIMARY KEY,
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    commit_hash TEXT NOT NULL,
+    category TEXT NOT NULL,
+    key TEXT NOT NULL,
+    sheet TEXT,
+    text TEXT NOT NULL,
+    embedding REAL[] NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS retrieval_documents_commit_idx
+    ON retrieval_documents (repo_id, commit_hash, category);
+
+-- Summary card of each repository for the frontend's repo list: its latest schematic commit,
+-- counts, latest blurb and the commit whose image is the thumbnail. Rewritten from the
+-- repository's rows when its commits are processed (repo_cards::refresh_card), not on read
+CREATE TABLE IF NOT EXISTS repo_cards (
+    repo_id INTEGER PRIMARY KEY REFERENCES repos(id) ON DELETE CASCADE,
+    last_commit_hash TEXT,
+    last_commit_date TIMESTAMPTZ,
+    last_commit_message TEXT,
+    commit_count INTEGER NOT NULL DEFAULT 0,
+    distilled_count INTEGER NOT NULL DEFAULT 0,
+    part_count INTEGER NOT NULL DEFAULT 0,
+    comment_count INTEGER NOT NULL DEFAULT 0,
+    blurb TEXT,
+    blurb_commit_hash TEXT,
+    thumbnail_commit_hash TEXT,
+    refreshed_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Change tracking for delta sync (see kicad_db::sync). Every insert or update of a schematic or
+-- part is stamped with its transaction id and a position from sync_seq, so offline clients can
+-- ask for what changed after the last stamp they saw. Rows only leave with their repository,
+-- so deletions aren't tracked
+CREATE SEQUENCE IF NOT EXISTS sync_seq;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS change_txid BIGINT;
+ALTER TABLE schematics ADD COLUMN IF NOT EXISTS change_seq BIGINT;
+ALTER TABLE parts ADD COLUMN IF NOT EXISTS change_txid BIGINT;
+ALTER TABLE parts ADD COLUMN IF NOT EXISTS change_seq BIGINT;
+CREATE INDEX IF NOT EXISTS schematics_change_idx ON schematics (repo_id, change_txid, change_seq);
+CREATE INDEX IF NOT EXISTS parts_change_idx ON parts (change_txid, change_seq);
+
+CREATE OR REPLACE FUNCTION stamp_change() RETURNS trigger AS $$
+BEGIN
+    NEW.change_txid := pg_current_xact_id()::TEXT::BIGINT;
+    NEW.change_seq := nextval('sync_seq');
+    RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER schematics_stamp_change BEFORE INSERT OR UPDATE ON schematics
+    FOR EACH ROW EXECUTE FUNCTION stamp_change();
+CREATE OR REPLACE TRIGGER parts_stamp_change BEFORE INSERT OR UPDATE ON parts
+    FOR EACH ROW EXECUTE FUNCTION stamp_change();
+
+-- Rows from before change tracking get stamped by the trigger
+UPDATE schematics SET change_seq = 0 WHERE change_seq IS NULL;
+UPDATE parts SET change_seq = 0 WHERE change_seq IS NULL;
+
+-- Secrets kept in the database (access tokens, webhook secrets), AES-256-GCM encrypted by
+-- kicad_db::secrets. key_id names the key a value was sealed with, so values sealed under a
+-- previous key still open after the active key changes, until secrets::rotate reseals them
+CREATE TABLE IF NOT EXISTS secrets (
+    name TEXT PRIMARY KEY,
+    key_id TEXT NOT NULL,
+    nonce BYTEA NOT NULL,
+    ciphertext BYTEA NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Processes keep the sealed rows of secrets in memory and LISTEN on secrets_changed to
+-- reload them, so a value set through one replica takes effect in every other
+CREATE OR REPLACE FUNCTION notify_secrets_changed() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('secrets_changed', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER secrets_notify_change AFTER INSERT OR UPDATE OR DELETE ON secrets
+    FOR EACH STATEMENT EXECUTE FUNCTION notify_secrets_changed();
+
+-- Key the provider secret was sealed with; NULL for secrets stored before keys had ids
+ALTER TABLE provider_credentials ADD COLUMN IF NOT EXISTS secret_key_id TEXT;
+
+-- How each stored AI artifact (summary, release notes, suggested alternate) was produced:
+-- model, prompt, tool calls, and SHA-256 hashes of what went in and came out. Rows are
+-- never changed or removed; record_sha256 covers every other column, so an edited row
+-- no longer verifies
+CREATE TABLE IF NOT EXISTS ai_provenance (
+    id BIGSERIAL PRIMARY KEY,
+    artifact TEXT NOT NULL, -- summary | summary_reuse | release_notes | alternate | net_explanation | suggestions
+    artifact_ref TEXT NOT NULL, -- which one: release notes id, replaced MPN, ...
+    repo_url TEXT, -- as recorded; not rewritten when a repository is renamed
+    commit_hash TEXT,
+    model TEXT NOT NULL,
+    prompt_version TEXT NOT NULL,
+    prompt_sha256 TEXT, -- NULL for generators without a prompt
+    tool_calls JSONB NOT NULL DEFAULT '[]', -- [{"name", "call_id", "status", "input"}]
+    input_hashes JSONB NOT NULL DEFAULT '{}', -- input name -> SHA-256 hex
+    output_hashes JSONB NOT NULL DEFAULT '{}', -- output name -> SHA-256 hex
+    created_at TIMESTAMPTZ NOT NULL,
+    record_sha256 TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS ai_provenance_commit_idx ON ai_provenance (repo_url, commit_hash, created_at);
+CREATE INDEX IF NOT EXISTS ai_provenance_artifact_idx ON ai_provenance (artifact, artifact_ref, created_at);
+
+CREATE OR REPLACE FUNCTION reject_change() RETURNS trigger AS $$
+BEGIN
+    RAISE EXCEPTION '% is append-only', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER ai_provenance_append_only BEFORE UPDATE OR DELETE ON ai_provenance
+    FOR EACH ROW EXECUTE FUNCTION reject_change();
+
+-- LLM commit summaries keyed by a hash of the structured schematic diff, so a reverted or
+-- cherry-picked commit with an identical diff reuses the summary instead of paying for a
+-- new one. The first commit summarized for a diff is kept as the source. Summaries are
+-- only reused within the repository they were generated for
+CREATE TABLE IF NOT EXISTS summary_cache (
+    diff_sha256 TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_version TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    details TEXT NOT NULL,
+    repo_url TEXT NOT NULL, -- commit the summary was generated for
+    commit_hash TEXT NOT NULL,
+    provenance_id BIGINT, -- ai_provenance record of the generation
+    hits INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (repo_url, diff_sha256, model, prompt_version)
+);
+-- Databases created before summaries were scoped to a repository
+ALTER TABLE summary_cache DROP CONSTRAINT IF EXISTS summary_cache_pkey;
+ALTER TABLE summary_cache ADD PRIMARY KEY (repo_url, diff_sha256, model, prompt_version);
+
+-- Per-file summaries of commits too large to summarize in one prompt; the commit-level
+-- summary synthesized from them is stored in summary_cache
+CREATE TABLE IF NOT EXISTS file_summaries (
+    repo_url TEXT NOT NULL,
+    commit_hash TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_version TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (repo_url, commit_hash, file_path)
+);
+
+-- AI explanations of what a net does. context_sha256 hashes everything the explanation was
+-- generated from (pins, attached and nearby components), so a net left untouched by later
+-- commits reuses its explanation
+CREATE TABLE IF NOT EXISTS net_explanations (
+    repo_url TEXT NOT NULL,
+    commit_hash TEXT NOT NULL,
+    net_name TEXT NOT NULL,
+    context_sha256 TEXT NOT NULL,
+    explanation TEXT NOT NULL,
+    model TEXT NOT NULL,
+    prompt_version TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (repo_url, commit_hash, net_name)
+);
+
+CREATE INDEX IF NOT EXISTS net_explanations_context_idx ON net_explanations (repo_url, net_name, context_sha256);
+
+-- Suggested questions for a newly onboarded repository, shown as prompts in the frontend chat.
+-- One set per repository, from the commit it was onboarded at; suggestions is
+-- [{"question", "topic", "sheet", "references"}]
+CREATE TABLE IF NOT EXISTS repo_suggestions (
+    repo_id INTEGER PRIMARY KEY REFERENCES repos(id) ON DELETE CASCADE,
+    commit_hash TEXT NOT NULL,
+    suggestions JSONB NOT NULL,
+    model TEXT NOT NULL,
+    prompt_version TEXT NOT NULL,
+    generated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Notes users attach to components, kept per repository and reference so they follow the
+-- component across commits. Given to Grok with any prompt that includes the component
+CREATE TABLE IF NOT EXISTS component_notes (
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    reference TEXT NOT NULL,
+    note TEXT NOT NULL,
+    author TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (repo_id, reference)
+);
+
+-- Default branch each repository last advertised, checked on a schedule (see services::default_branch).
+-- NULL until the first check
+ALTER TABLE repos ADD COLUMN IF NOT EXISTS default_branch TEXT;
+
+-- Changes of a repository's default branch (master to main); the artifacts built from the
+-- latest commit are regenerated from the new branch's head when one is recorded
+CREATE TABLE IF NOT EXISTS default_branch_changes (
+    id BIGSERIAL PRIMARY KEY,
+    repo_id INTEGER NOT NULL REFERENCES repos(id) ON DELETE CASCADE,
+    old_branch TEXT NOT NULL,
+    new_branch TEXT NOT NULL,
+    head_commit TEXT NOT NULL,
+    detected_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS default_branch_changes_repo_idx ON default_branch_changes (repo_id, detected_at);
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+use crate::repos::{ensure_repo_id, RepoRef};
+
+/// An approved alternate for a part of a repository
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct PartAlternate {
+    pub mpn: String,
+    pub alternate_mpn: String,
+    pub manufacturer: Option<String>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Approve `alternate_mpn` for `mpn`, replacing the manufacturer and note if it
+/// was already approved. Returns None when the repository URL can't be parsed.
+pub async fn add_alternate(
+    pool: &PgPool,
+    repo_url: &str,
+    mpn: &str,
+    alternate_mpn: &str,
+    manufacturer: Option<&str>,
+    note: Option<&str>,
+) -> Result<Option<PartAlternate>, Error> {
+    let Some(repo_id) = ensure_repo_id(pool, repo_url).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, PartAlternate>(
+        r#"
+        INSERT INTO part_alternates (repo_id, mpn, alternate_mpn, manufacturer, note)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (repo_id, mpn, alternate_mpn) DO UPDATE SET
+            manufacturer = EXCLUDED.manufacturer,
+            note = EXCLUDED.note
+        RETURNING mpn, alternate_mpn, manufacturer, note, created_at
+        "#,
+    )
+    .bind(repo_id)
+    .bind(mpn)
+    .bind(alternate_mpn)
+    .bind(manufacturer)
+    .bind(note)
+    .fetch_one(pool)
+    .await
+    .map(Some)
+}
+
+/// Withdraw an alternate. Returns whether one was removed.
+pub async fn remove_alternate(
+    pool: &PgPool,
+    repo_url: &str,
+    mpn: &str,
+    alternate_mpn: &str,
+) -> Result<bool, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(false);
+    };
+
+    let result = sqlx::query(
+        r#"
+        DELETE FROM part_alternates a
+        USING repos r
+        WHERE r.id = a.repo_id AND r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND a.mpn = $3 AND a.alternate_mpn = $4
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(mpn)
+    .bind(alternate_mpn)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every approved alternate of a repository, by part and alternate MPN.
+/// Replica-safe.
+pub async fn list_alternates(pool: &PgPool, repo_url: &str) -> Result<Vec<PartAlternate>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, PartAlternate>(
+        r#"
+        SELECT a.mpn, a.alternate_mpn, a.manufacturer, a.note, a.created_at
+        FROM part_alternates a
+        JOIN repos r ON r.id = a.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+        ORDER BY a.mpn, a.alternate_mpn
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::repos::ensure_repo_id;
+
+/// A chat session over one commit's schematic
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct AnalysisSession {
+    pub id: Uuid,
+    pub repo_url: String,
+    pub commit_hash: String,
+    /// System prompt with the schematic summary, built when the session started
+    pub context: String,
+    /// Distilled data the client sent when starting the session
+    pub distilled_json: Option<Value>,
+    /// Turns so far, oldest first: `[{"user": ..., "assistant": ...}]`, see `SessionTurn`
+    pub turns: Value,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+}
+
+/// A question and answer of a session
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionTurn {
+    /// Message sent to the model: the selected components' context and the question
+    pub user: String,
+    pub assistant: String,
+    /// The question as asked; None for turns stored before it was kept apart
+    #[serde(default)]
+    pub question: Option<String>,
+    /// References of the components selected for the question
+    #[serde(default)]
+    pub components: Vec<String>,
+}
+
+/// Fields of a finished turn
+#[derive(Debug, Clone)]
+pub struct NewTurn<'a> {
+    pub user: &'a str,
+    pub assistant: &'a str,
+    pub question: &'a str,
+    pub components: &'a [String],
+}
+
+impl AnalysisSession {
+    /// Each stored turn, oldest first
+    pub fn turn_list(&self) -> Vec<SessionTurn> {
+        self.turns
+            .as_array()
+            .map(|turns| {
+                turns
+                    .iter()
+                    .filter_map(|turn| serde_json::from_value(turn.clone()).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// (user, assistant) messages of each turn, oldest first
+    pub fn turn_messages(&self) -> Vec<(String, String)> {
+        self.turn_list()
+            .into_iter()
+            .map(|turn| (turn.user, turn.assistant))
+            .collect()
+    }
+}
+
+const SESSION_COLUMNS: &str = r#"
+    s.id, r.repo_url, s.commit_hash, s.context, s.distilled_json, s.turns,
+    s.created_at, s.last_used_at
+"#;
+
+/// Start a session under a new random id. Returns None when the repository
+/// URL can't be parsed.
+pub async fn create_session(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    context: &str,
+    distilled: Option<&Value>,
+) -> Result<Option<AnalysisSession>, Error> {
+    let Some(repo_id) = ensure_repo_id(pool, repo_url).await? else {
+        return Ok(None);
+    };
+
+    let id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO analysis_sessions (id, repo_id, commit_hash, context, distilled_json)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(repo_id)
+    .bind(commit_hash)
+    .bind(context)
+    .bind(distilled)
+    .fetch_one(pool)
+    .await?;
+
+    fetch_session(pool, id, None).await
+}
+
+/// Look up a session whether or not it is still open, e.g. to export it
+pub async fn find_session(pool: &PgPool, id: Uuid) -> Result<Option<AnalysisSession>, Error> {
+    fetch_session(pool, id, None).await
+}
+
+/// Look up a session used within the last `idle_secs`
+pub async fn get_session(
+    pool: &PgPool,
+    id: Uuid,
+    idle_secs: i64,
+) -> Result<Option<AnalysisSession>, Error> {
+    fetch_session(pool, id, Some(idle_secs)).await
+}
+
+async fn fetch_session(
+    pool: &PgPool,
+    id: Uuid,
+    idle_secs: Option<i64>,
+) -> Result<Option<AnalysisSession>, Error> {
+    sqlx::query_as::<_, AnalysisSession>(&format!(
+        r#"
+        SELECT {}
+        FROM analysis_sessions s
+        JOIN repos r ON r.id = s.repo_id
+        WHERE s.id = $1
+          AND ($2::FLOAT8 IS NULL
+               OR s.last_used_at >= CURRENT_TIMESTAMP - make_interval(secs => $2))
+        "#,
+        SESSION_COLUMNS
+    ))
+    .bind(id)
+    .bind(idle_secs.map(|secs| secs as f64))
+    .fetch_optional(pool)
+    .await
+}
+
+/// Append a turn to a session, keeping its latest `max_turns` turns.
+/// Returns whether the session exists.
+pub async fn append_turn(
+    pool: &PgPool,
+    id: Uuid,
+    turn: &NewTurn<'_>,
+    max_turns: i64,
+) -> Result<bool, Error> {
+    let turn = serde_json::to_value(SessionTurn {
+        user: turn.user.to_string(),
+        assistant: turn.assistant.to_string(),
+        question: Some(turn.question.to_string()),
+        components: turn.components.to_vec(),
+    })
+    .unwrap_or_default();
+
+    let result = sqlx::query(
+        r#"
+        UPDATE analysis_sessions SET
+            turns = (
+                SELECT COALESCE(jsonb_agg(t.turn ORDER BY t.idx), '[]'::jsonb)
+                FROM jsonb_array_elements(
+                    turns || jsonb_build_array($2::JSONB)
+                ) WITH ORDINALITY AS t(turn, idx)
+                WHERE t.idx > jsonb_array_length(turns) + 1 - $3
+            ),
+            last_used_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(turn)
+    .bind(max_turns)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete a session. Returns whether it existed.
+pub async fn delete_session(pool: &PgPool, id: Uuid) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM analysis_sessions WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete sessions unused for `idle_secs`. Returns how many were deleted.
+pub async fn delete_idle_sessions(pool: &PgPool, idle_secs: i64) -> Result<u64, Error> {
+    let result = sqlx::query(
+        "DELETE FROM analysis_sessions WHERE last_used_at < CURRENT_TIMESTAMP - make_interval(secs => $1)",
+    )
+    .bind(idle_secs as f64)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
@@ -0,0 +1,188 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// An API key, without its secret
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub key_prefix: String,
+    pub request_quota: Option<i64>,
+    pub llm_token_quota: Option<i64>,
+    pub cpu_seconds_quota: Option<f64>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Monthly limits of a key; None is unlimited
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct KeyQuotas {
+    pub request_quota: Option<i64>,
+    pub llm_token_quota: Option<i64>,
+    pub cpu_seconds_quota: Option<f64>,
+}
+
+/// What a key used in one period
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, sqlx::FromRow)]
+pub struct KeyUsage {
+    pub requests: i64,
+    pub llm_tokens: i64,
+    pub cpu_seconds: f64,
+}
+
+/// `key_hash` of the shared key that meters requests sent without one
+pub const ANONYMOUS_KEY_HASH: &str = "anonymous";
+
+const KEY_COLUMNS: &str = r#"
+    id, name, key_prefix, request_quota, llm_token_quota, cpu_seconds_quota, revoked_at, created_at
+"#;
+
+/// Store a new key. Only the hash of the secret is kept.
+pub async fn create_key(
+    pool: &PgPool,
+    name: &str,
+    key_hash: &str,
+    key_prefix: &str,
+    quotas: &KeyQuotas,
+) -> Result<ApiKey, Error> {
+    sqlx::query_as::<_, ApiKey>(&format!(
+        r#"
+        INSERT INTO api_keys (name, key_hash, key_prefix, request_quota, llm_token_quota, cpu_seconds_quota)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING {}
+        "#,
+        KEY_COLUMNS
+    ))
+    .bind(name)
+    .bind(key_hash)
+    .bind(key_prefix)
+    .bind(quotas.request_quota)
+    .bind(quotas.llm_token_quota)
+    .bind(quotas.cpu_seconds_quota)
+    .fetch_one(pool)
+    .await
+}
+
+/// The unrevoked key with this hash
+pub async fn find_active_key(pool: &PgPool, key_hash: &str) -> Result<Option<ApiKey>, Error> {
+    sqlx::query_as::<_, ApiKey>(&format!(
+        "SELECT {} FROM api_keys WHERE key_hash = $1 AND revoked_at IS NULL",
+        KEY_COLUMNS
+    ))
+    .bind(key_hash)
+    .fetch_optional(pool)
+    .await
+}
+
+/// All keys, revoked ones included, oldest first
+pub async fn list_keys(pool: &PgPool) -> Result<Vec<ApiKey>, Error> {
+    sqlx::query_as::<_, ApiKey>(&format!("SELECT {} FROM api_keys ORDER BY id", KEY_COLUMNS))
+        .fetch_all(pool)
+        .await
+}
+
+/// Replace a key's quotas. Returns None when no key has this id.
+pub async fn set_quotas(
+    pool: &PgPool,
+    id: i64,
+    quotas: &KeyQuotas,
+) -> Result<Option<ApiKey>, Error> {
+    sqlx::query_as::<_, ApiKey>(&format!(
+        r#"
+        UPDATE api_keys SET request_quota = $2, llm_token_quota = $3, cpu_seconds_quota = $4
+        WHERE id = $1
+        RETURNING {}
+        "#,
+        KEY_COLUMNS
+    ))
+    .bind(id)
+    .bind(quotas.request_quota)
+    .bind(quotas.llm_token_quota)
+    .bind(quotas.cpu_seconds_quota)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Revoke a key. Returns whether an active key was revoked.
+pub async fn revoke_key(pool: &PgPool, id: i64) -> Result<bool, Error> {
+    let result =
+        sqlx::query("UPDATE api_keys SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL")
+            .bind(id)
+            .execute(pool)
+            .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// A key's usage in the period starting `period_start`
+pub async fn usage(pool: &PgPool, key_id: i64, period_start: NaiveDate) -> Result<KeyUsage, Error> {
+    let usage = sqlx::query_as::<_, KeyUsage>(
+        r#"
+        SELECT requests, llm_tokens, cpu_seconds
+        FROM api_key_usage
+        WHERE key_id = $1 AND period_start = $2
+        "#,
+    )
+    .bind(key_id)
+    .bind(period_start)
+    .fetch_optional(pool)
+    .await?;
+    Ok(usage.unwrap_or_default())
+}
+
+/// Count one request against a key, unless it already made `request_quota`
+/// requests this period. Returns the usage including this request, or None
+/// when the quota is used up.
+pub async fn charge_request(
+    pool: &PgPool,
+    key_id: i64,
+    period_start: NaiveDate,
+    request_quota: Option<i64>,
+) -> Result<Option<KeyUsage>, Error> {
+    if request_quota.is_some_and(|quota| quota <= 0) {
+        return Ok(None);
+    }
+
+    sqlx::query_as::<_, KeyUsage>(
+        r#"
+        INSERT INTO api_key_usage (key_id, period_start, requests)
+        VALUES ($1, $2, 1)
+        ON CONFLICT (key_id, period_start) DO UPDATE SET
+            requests = api_key_usage.requests + 1
+        WHERE $3::BIGINT IS NULL OR api_key_usage.requests < $3
+        RETURNING requests, llm_tokens, cpu_seconds
+        "#,
+    )
+    .bind(key_id)
+    .bind(period_start)
+    .bind(request_quota)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Add LLM tokens and distillation seconds to a key's usage
+pub async fn add_usage(
+    pool: &PgPool,
+    key_id: i64,
+    period_start: NaiveDate,
+    llm_tokens: i64,
+    cpu_seconds: f64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO api_key_usage (key_id, period_start, llm_tokens, cpu_seconds)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (key_id, period_start) DO UPDATE SET
+            llm_tokens = api_key_usage.llm_tokens + EXCLUDED.llm_tokens,
+            cpu_seconds = api_key_usage.cpu_seconds + EXCLUDED.cpu_seconds
+        "#,
+    )
+    .bind(key_id)
+    .bind(period_start)
+    .bind(llm_tokens)
+    .bind(cpu_seconds)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
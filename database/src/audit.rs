@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+
+/// One recorded action
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub action: String,
+    pub actor: String,
+    pub repo_url: Option<String>,
+    pub commit_hash: Option<String>,
+    pub params: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Narrows `list_entries`; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub repo_url: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Record one action
+pub async fn record_action(
+    pool: &PgPool,
+    action: &str,
+    actor: &str,
+    repo_url: Option<&str>,
+    commit_hash: Option<&str>,
+    params: &Value,
+) -> Result<i64, Error> {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO audit_log (action, actor, repo_url, commit_hash, params)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(action)
+    .bind(actor)
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(params)
+    .fetch_one(pool)
+    .await
+}
+
+/// Recorded actions matching `filter`, newest first. Repository URLs match
+/// ignoring case; `since` is inclusive and `until` exclusive. Replica-safe.
+pub async fn list_entries(
+    pool: &PgPool,
+    filter: &AuditFilter,
+    limit: i64,
+) -> Result<Vec<AuditEntry>, Error> {
+    sqlx::query_as::<_, AuditEntry>(
+        r#"
+        SELECT id, action, actor, repo_url, commit_hash, params, created_at
+        FROM audit_log
+        WHERE ($1::TEXT IS NULL OR action = $1)
+          AND ($2::TEXT IS NULL OR actor = $2)
+          AND ($3::TEXT IS NULL OR LOWER(repo_url) = LOWER($3))
+          AND ($4::TIMESTAMPTZ IS NULL OR created_at >= $4)
+          AND ($5::TIMESTAMPTZ IS NULL OR created_at < $5)
+        ORDER BY created_at DESC, id DESC
+        LIMIT $6
+        "#,
+    )
+    .bind(filter.action.as_deref())
+    .bind(filter.actor.as_deref())
+    .bind(filter.repo_url.as_deref())
+    .bind(filter.since)
+    .bind(filter.until)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,284 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgConnection, PgPool};
+
+/// Metadata for a file held in the blob store.
+/// The bytes themselves live outside Postgres; `key` locates them in the store.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct BlobRecord {
+    pub key: String,
+    pub kind: String,
+    pub repo_url: Option<String>,
+    pub commit_hash: Option<String>,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub sha256: String,
+    /// Where the bytes live when they're shared with other blobs of the same
+    /// content; None when they're at `key`
+    pub content_key: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl BlobRecord {
+    /// The store key holding this blob's bytes
+    pub fn location(&self) -> &str {
+        self.content_key.as_deref().unwrap_or(&self.key)
+    }
+}
+
+/// Metadata for a blob that has just been written to the store
+#[derive(Debug, Clone)]
+pub struct NewBlob<'a> {
+    pub key: &'a str,
+    pub kind: &'a str,
+    pub repo_url: Option<&'a str>,
+    pub commit_hash: Option<&'a str>,
+    pub content_type: &'a str,
+    pub size_bytes: i64,
+    pub sha256: &'a str,
+    pub content_key: Option<&'a str>,
+}
+
+/// Kind of the blob holding a commit's schematic image
+pub const KIND_SCHEMATIC_IMAGE: &str = "schematic_image";
+
+/// Re-stamp a commit's schematic row so sync clients see its `has_image` change
+async fn touch_schematic(
+    conn: &mut PgConnection,
+    repo_url: &str,
+    commit_hash: &str,
+) -> Result<(), Error> {
+    sqlx::query(
+        "UPDATE schematics SET change_seq = change_seq WHERE repo_url = $1 AND commit_hash = $2",
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Record a stored blob, replacing any previous metadata for the same key
+pub async fn upsert_blob(pool: &PgPool, blob: &NewBlob<'_>) -> Result<BlobRecord, Error> {
+    let mut tx = pool.begin().await?;
+    let (record, _) = record_blob(&mut tx, blob).await?;
+    tx.commit().await?;
+    Ok(record)
+}
+
+/// Record a blob as part of a transaction. Also returns whether its shared
+/// content is new, i.e. whether the caller has to write the bytes before
+/// committing.
+///
+/// The shared content's row stays locked until the transaction ends, so
+/// `release_content` can't delete the bytes this blob is about to rely on.
+pub async fn record_blob(
+    conn: &mut PgConnection,
+    blob: &NewBlob<'_>,
+) -> Result<(BlobRecord, bool), Error> {
+    let new_content = match blob.content_key {
+        Some(content_key) => {
+            sqlx::query_scalar(
+                r#"
+                INSERT INTO blob_contents (content_key, size_bytes)
+                VALUES ($1, $2)
+                ON CONFLICT (content_key) DO UPDATE SET content_key = EXCLUDED.content_key
+                RETURNING xmax = 0
+                "#,
+            )
+            .bind(content_key)
+            .bind(blob.size_bytes)
+            .fetch_one(&mut *conn)
+            .await?
+        }
+        None => true,
+    };
+
+    let record = sqlx::query_as::<_, BlobRecord>(
+        r#"
+        INSERT INTO blobs (key, kind, repo_url, commit_hash, content_type, size_bytes, sha256, content_key)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (key)
+        DO UPDATE SET
+            kind = EXCLUDED.kind,
+            repo_url = EXCLUDED.repo_url,
+            commit_hash = EXCLUDED.commit_hash,
+            content_type = EXCLUDED.content_type,
+            size_bytes = EXCLUDED.size_bytes,
+            sha256 = EXCLUDED.sha256,
+            content_key = EXCLUDED.content_key,
+            created_at = CURRENT_TIMESTAMP
+        RETURNING *
+        "#,
+    )
+    .bind(blob.key)
+    .bind(blob.kind)
+    .bind(blob.repo_url)
+    .bind(blob.commit_hash)
+    .bind(blob.content_type)
+    .bind(blob.size_bytes)
+    .bind(blob.sha256)
+    .bind(blob.content_key)
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if let (KIND_SCHEMATIC_IMAGE, Some(repo_url), Some(commit_hash)) =
+        (blob.kind, blob.repo_url, blob.commit_hash)
+    {
+        touch_schematic(conn, repo_url, commit_hash).await?;
+    }
+    Ok((record, new_content))
+}
+
+/// Look up a blob's metadata by key
+pub async fn get_blob(pool: &PgPool, key: &str) -> Result<Option<BlobRecord>, Error> {
+    sqlx::query_as::<_, BlobRecord>("SELECT * FROM blobs WHERE key = $1")
+        .bind(key)
+        .fetch_optional(pool)
+        .await
+}
+
+/// All blobs attached to a commit, optionally filtered by kind. Replica-safe.
+pub async fn list_blobs(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    kind: Option<&str>,
+) -> Result<Vec<BlobRecord>, Error> {
+    sqlx::query_as::<_, BlobRecord>(
+        r#"
+        SELECT * FROM blobs
+        WHERE repo_url = $1 AND commit_hash = $2 AND ($3::TEXT IS NULL OR kind = $3)
+        ORDER BY kind, key
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(kind)
+    .fetch_all(pool)
+    .await
+}
+
+/// Remove a blob's metadata. Returns whether a row was deleted.
+pub async fn delete_blob(pool: &PgPool, key: &str) -> Result<bool, Error> {
+    let mut tx = pool.begin().await?;
+    let deleted = sqlx::query_as::<_, BlobRecord>("DELETE FROM blobs WHERE key = $1 RETURNING *")
+        .bind(key)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+    if let Some(BlobRecord {
+        kind,
+        repo_url: Some(repo_url),
+        commit_hash: Some(commit_hash),
+        ..
+    }) = &deleted
+    {
+        if kind == KIND_SCHEMATIC_IMAGE {
+            touch_schematic(&mut tx, repo_url, commit_hash).await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(deleted.is_some())
+}
+
+/// Forget a shared content no blob references any more, as part of a
+/// transaction. Returns whether it was forgotten; the caller then deletes the
+/// bytes before committing. Content a concurrent `record_blob` is using is left alone.
+pub async fn release_content(conn: &mut PgConnection, content_key: &str) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM blob_contents WHERE content_key IN (
+            SELECT c.content_key FROM blob_contents c
+            WHERE c.content_key = $1
+              AND NOT EXISTS (SELECT 1 FROM blobs b WHERE b.content_key = c.content_key)
+            FOR UPDATE SKIP LOCKED
+        )
+        "#,
+    )
+    .bind(content_key)
+    .execute(conn)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Number of blobs whose bytes are kept at a shared content key
+pub async fn content_references(pool: &PgPool, content_key: &str) -> Result<i64, Error> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM blobs WHERE content_key = $1")
+        .bind(content_key)
+        .fetch_one(pool)
+        .await
+}
+
+/// A schematic image still held in the `schematics.schematic_image` column,
+/// from before images moved to the blob store
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct LegacyImage {
+    pub id: i32,
+    pub repo_url: String,
+    pub commit_hash: String,
+    pub image: Vec<u8>,
+}
+
+/// Whether the database still has the `schematics.schematic_image` column
+pub async fn has_legacy_image_column(pool: &PgPool) -> Result<bool, Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM information_schema.columns
+            WHERE table_schema = current_schema()
+              AND table_name = 'schematics' AND column_name = 'schematic_image'
+        )
+        "#,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Up to `limit` images left in the legacy column. Only call when
+/// `has_legacy_image_column` is true.
+pub async fn legacy_images(pool: &PgPool, limit: i64) -> Result<Vec<LegacyImage>, Error> {
+    sqlx::query_as::<_, LegacyImage>(
+        r#"
+        SELECT id, repo_url, commit_hash, schematic_image AS image
+        FROM schematics
+        WHERE schematic_image IS NOT NULL
+        ORDER BY id
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Clear a legacy image once it has been copied to the blob store
+pub async fn clear_legacy_image(pool: &PgPool, schematic_id: i32) -> Result<(), Error> {
+    sqlx::query("UPDATE schematics SET schematic_image = NULL WHERE id = $1")
+        .bind(schematic_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Drop the legacy image column, unless an image was written to it since
+/// the last `legacy_images` pass. Returns whether it was dropped.
+pub async fn drop_legacy_image_column(pool: &PgPool) -> Result<bool, Error> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("LOCK TABLE schematics IN ACCESS EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await?;
+    let remaining: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM schematics WHERE schematic_image IS NOT NULL)",
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+    if remaining {
+        return Ok(false);
+    }
+    sqlx::query("ALTER TABLE schematics DROP COLUMN IF EXISTS schematic_image")
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(true)
+}
@@ -0,0 +1,175 @@
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+
+/// Fields identifying an element of a distilled array, in order of
+/// preference. An array is sorted by the first set every element has.
+const IDENTITY_KEYS: &[&[&str]] = &[
+    &["uuid"],
+    &["reference"],
+    &["ref_a", "ref_b"],
+    &["number"],
+    &["Pin"],
+    &["file"],
+];
+
+fn identity_key(element: &Value, keys: &[&str]) -> Vec<String> {
+    keys.iter()
+        .map(|key| match element.get(key) {
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+            None => String::new(),
+        })
+        .collect()
+}
+
+/// Compare strings with embedded numbers by value, so pin "2" sorts before "10"
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(ca), Some(cb)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        if ca.is_ascii_digit() && cb.is_ascii_digit() {
+            let da = a.find(|c: char| !c.is_ascii_digit()).unwrap_or(a.len());
+            let db = b.find(|c: char| !c.is_ascii_digit()).unwrap_or(b.len());
+            let (na, nb) = (
+                a[..da].trim_start_matches('0'),
+                b[..db].trim_start_matches('0'),
+            );
+            let ordering = na.len().cmp(&nb.len()).then_with(|| na.cmp(nb));
+            if ordering != Ordering::Equal {
+                return ordering;
+            }
+            (a, b) = (&a[da..], &b[db..]);
+        } else {
+            if ca != cb {
+                return ca.cmp(&cb);
+            }
+            (a, b) = (&a[ca.len_utf8()..], &b[cb.len_utf8()..]);
+        }
+    }
+}
+
+fn sort_elements(elements: &mut [Value]) {
+    let Some(keys) = IDENTITY_KEYS.iter().find(|keys| {
+        !elements.is_empty()
+            && elements
+                .iter()
+                .all(|e| e.is_object() && keys.iter().all(|key| e.get(key).is_some()))
+    }) else {
+        return;
+    };
+    elements.sort_by(|a, b| {
+        identity_key(a, keys)
+            .iter()
+            .zip(identity_key(b, keys).iter())
+            .map(|(a, b)| natural_cmp(a, b))
+            .find(|ordering| ordering.is_ne())
+            // Ties (e.g. a pin listed twice) fall back to the serialized element
+            .unwrap_or_else(|| a.to_string().cmp(&b.to_string()))
+    });
+}
+
+/// Put distilled data into canonical form, so the same design always
+/// serializes to the same bytes whatever order the distiller produced:
+/// object keys are sorted, and arrays of records (components, pins, net
+/// members, proximities, warnings) are ordered by their identifying fields.
+/// Other arrays, e.g. coordinates, keep their order.
+pub fn canonicalize(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(String, Value)> = std::mem::take(map).into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            for (_, child) in &mut entries {
+                canonicalize(child);
+            }
+            *map = entries.into_iter().collect::<Map<String, Value>>();
+        }
+        Value::Array(elements) => {
+            for element in elements.iter_mut() {
+                canonicalize(element);
+            }
+            sort_elements(elements);
+        }
+        _ => {}
+    }
+}
+
+/// The canonical serialization of distilled data, for storing and hashing
+pub fn to_canonical_vec(value: &Value) -> Vec<u8> {
+    let mut value = value.clone();
+    canonicalize(&mut value);
+    serde_json::to_vec(&value).unwrap_or_default()
+}
+
+/// SHA-256 (hex) of the canonical serialization: equal for equal designs,
+/// whatever order their data came in
+pub fn content_hash(value: &Value) -> String {
+    hex::encode(Sha256::digest(to_canonical_vec(value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_canonicalize_sorts_records() {
+        let mut a = json!({
+            "nets": { "GND": { "U1": [{ "Pin": "10" }, { "Pin": "2" }], "C1": [{ "Pin": "1" }] } },
+            "components": { "U1": { "pins": [{ "number": "10" }, { "number": "2" }] } },
+            "proximities": [
+                { "ref_a": "R1", "ref_b": "C2", "score": 0.5 },
+                { "ref_a": "C1", "ref_b": "U1", "score": 0.2 }
+            ],
+            "outline": [[2, 1], [0, 0]]
+        });
+        let mut b = json!({
+            "outline": [[2, 1], [0, 0]],
+            "proximities": [
+                { "ref_a": "C1", "ref_b": "U1", "score": 0.2 },
+                { "ref_a": "R1", "ref_b": "C2", "score": 0.5 }
+            ],
+            "components": { "U1": { "pins": [{ "number": "2" }, { "number": "10" }] } },
+            "nets": { "GND": { "C1": [{ "Pin": "1" }], "U1": [{ "Pin": "2" }, { "Pin": "10" }] } }
+        });
+        canonicalize(&mut a);
+        canonicalize(&mut b);
+        assert_eq!(
+            serde_json::to_string(&a).unwrap(),
+            serde_json::to_string(&b).unwrap()
+        );
+        assert_eq!(a["components"]["U1"]["pins"][0]["number"], "2");
+        assert_eq!(a["proximities"][0]["ref_a"], "C1");
+        // Arrays without identifying fields keep their order
+        assert_eq!(a["outline"], json!([[2, 1], [0, 0]]));
+    }
+
+    #[test]
+    fn test_canonicalize_prefers_uuid() {
+        let mut value = json!([
+            { "uuid": "b", "reference": "R1" },
+            { "uuid": "a", "reference": "R2" }
+        ]);
+        canonicalize(&mut value);
+        assert_eq!(value[0]["reference"], "R2");
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let a = json!({ "b": [{ "Pin": "2" }, { "Pin": "1" }], "a": 1 });
+        let b = json!({ "a": 1, "b": [{ "Pin": "1" }, { "Pin": "2" }] });
+        assert_eq!(content_hash(&a), content_hash(&b));
+        assert_eq!(content_hash(&a).len(), 64);
+        assert_ne!(content_hash(&a), content_hash(&json!({ "a": 2 })));
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("2", "10"), Ordering::Less);
+        assert_eq!(natural_cmp("A10", "A9"), Ordering::Greater);
+        assert_eq!(natural_cmp("C2", "C10"), Ordering::Less);
+        assert_eq!(natural_cmp("GND", "GND"), Ordering::Equal);
+    }
+}
@@ -0,0 +1,110 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+use crate::repos::{ensure_repo_id, RepoRef};
+
+/// Accepted `anchor_kind` values
+pub const ANCHOR_KINDS: &[&str] = &["component", "net"];
+
+/// A comment on a commit
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct CommitComment {
+    pub id: i64,
+    pub repo_url: String,
+    pub commit_hash: String,
+    /// The comment this one replies to
+    pub parent_id: Option<i64>,
+    pub author: String,
+    pub body: String,
+    pub anchor_kind: Option<String>,
+    pub anchor: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fields for a new comment
+#[derive(Debug, Clone)]
+pub struct NewComment<'a> {
+    pub repo_url: &'a str,
+    pub commit_hash: &'a str,
+    pub parent_id: Option<i64>,
+    pub author: &'a str,
+    pub body: &'a str,
+    pub anchor_kind: Option<&'a str>,
+    pub anchor: Option<&'a str>,
+}
+
+const COMMENT_COLUMNS: &str = r#"
+    c.id, r.repo_url, c.commit_hash, c.parent_id, c.author, c.body,
+    c.anchor_kind, c.anchor, c.created_at
+"#;
+
+/// Store a comment. Returns None when the repository URL can't be parsed.
+/// The caller checks that a parent belongs to the same commit.
+pub async fn insert_comment(
+    pool: &PgPool,
+    comment: &NewComment<'_>,
+) -> Result<Option<CommitComment>, Error> {
+    let Some(repo_id) = ensure_repo_id(pool, comment.repo_url).await? else {
+        return Ok(None);
+    };
+
+    let id: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO commit_comments
+            (repo_id, commit_hash, parent_id, author, body, anchor_kind, anchor)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id
+        "#,
+    )
+    .bind(repo_id)
+    .bind(comment.commit_hash)
+    .bind(comment.parent_id)
+    .bind(comment.author)
+    .bind(comment.body)
+    .bind(comment.anchor_kind)
+    .bind(comment.anchor)
+    .fetch_one(pool)
+    .await?;
+
+    get_comment(pool, id).await
+}
+
+/// Look up a comment by id
+pub async fn get_comment(pool: &PgPool, id: i64) -> Result<Option<CommitComment>, Error> {
+    sqlx::query_as::<_, CommitComment>(&format!(
+        "SELECT {} FROM commit_comments c JOIN repos r ON r.id = c.repo_id WHERE c.id = $1",
+        COMMENT_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Every comment on a commit, oldest first, so replies follow their parents.
+/// Replica-safe.
+pub async fn list_comments(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+) -> Result<Vec<CommitComment>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, CommitComment>(&format!(
+        r#"
+        SELECT {}
+        FROM commit_comments c
+        JOIN repos r ON r.id = c.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2) AND c.commit_hash = $3
+        ORDER BY c.created_at, c.id
+        "#,
+        COMMENT_COLUMNS
+    ))
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(commit_hash)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+use crate::repos::{ensure_repo_id, RepoRef};
+
+/// A note attached to one component of a repository
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ComponentNote {
+    pub reference: String,
+    pub note: String,
+    pub author: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Set a component's note, replacing what was stored. Notes are kept per
+/// repository rather than per commit, so they follow the component across
+/// history. Returns None when the repository URL can't be parsed.
+pub async fn upsert_note(
+    pool: &PgPool,
+    repo_url: &str,
+    reference: &str,
+    note: &str,
+    author: Option<&str>,
+) -> Result<Option<ComponentNote>, Error> {
+    let Some(repo_id) = ensure_repo_id(pool, repo_url).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, ComponentNote>(
+        r#"
+        INSERT INTO component_notes (repo_id, reference, note, author)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (repo_id, reference) DO UPDATE SET
+            note = EXCLUDED.note,
+            author = EXCLUDED.author,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING reference, note, author, updated_at
+        "#,
+    )
+    .bind(repo_id)
+    .bind(reference)
+    .bind(note)
+    .bind(author)
+    .fetch_one(pool)
+    .await
+    .map(Some)
+}
+
+/// Remove a component's note. Returns whether there was one.
+pub async fn delete_note(pool: &PgPool, repo_url: &str, reference: &str) -> Result<bool, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(false);
+    };
+
+    let result = sqlx::query(
+        r#"
+        DELETE FROM component_notes n
+        USING repos r
+        WHERE r.id = n.repo_id AND r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND n.reference = $3
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(reference)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Notes of every annotated component of a repository, by reference. Replica-safe.
+pub async fn list_notes(pool: &PgPool, repo_url: &str) -> Result<Vec<ComponentNote>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, ComponentNote>(
+        r#"
+        SELECT n.reference, n.note, n.author, n.updated_at
+        FROM component_notes n
+        JOIN repos r ON r.id = n.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+        ORDER BY n.reference
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .fetch_all(pool)
+    .await
+}
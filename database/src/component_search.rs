@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+
+/// Filters for a component search. Patterns are ILIKE patterns; unset filters
+/// match everything, set ones must all match.
+#[derive(Debug, Clone, Default)]
+pub struct ComponentSearch<'a> {
+    /// Matches the component value (e.g. "LM317%")
+    pub value: Option<&'a str>,
+    /// Matches the symbol library ID (e.g. "Regulator_Linear:%")
+    pub lib_id: Option<&'a str>,
+    /// Matches the MPN from symbol properties or stored part enrichment
+    pub mpn: Option<&'a str>,
+    /// Matches any of reference, value, lib_id or MPN
+    pub any: Option<&'a str>,
+    /// Symbol property names that hold an MPN, reduced to lowercase letters and digits
+    pub mpn_keys: Vec<String>,
+    pub limit: i64,
+}
+
+/// A distilled component matching a search, with its stored part properties
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ComponentMatch {
+    pub commit_hash: String,
+    pub commit_date: Option<DateTime<Utc>>,
+    pub git_message: Option<String>,
+    pub reference: String,
+    /// The component entry from distilled_json
+    pub component: Value,
+    /// Properties from the parts table (e.g. DigiKey enrichment), if any
+    pub part_properties: Option<Value>,
+}
+
+/// SQL condition: the component's MPN matches the pattern in parameter `param`
+fn mpn_condition(param: u8) -> String {
+    format!(
+        r#"(
+            p.properties->>'mpn' ILIKE ${param}
+            OR p.properties->'digikey'->>'manufacturer_part_number' ILIKE ${param}
+            OR EXISTS (
+                SELECT 1
+                FROM jsonb_each_text(CASE WHEN jsonb_typeof(c.value->'properties') = 'object'
+                                          THEN c.value->'properties' ELSE '{{}}'::jsonb END) prop
+                WHERE regexp_replace(lower(prop.key), '[^a-z0-9]', '', 'g') = ANY($5) AND prop.value ILIKE ${param}
+            )
+        )"#
+    )
+}
+
+/// Search the distilled components of every indexed commit of a repository,
+/// newest commit first. Power symbols and other virtual parts are skipped.
+/// Replica-safe (see `read_pool`).
+pub async fn search_components(
+    pool: &PgPool,
+    repo_url: &str,
+    search: &ComponentSearch<'_>,
+) -> Result<Vec<ComponentMatch>, Error> {
+    sqlx::query_as::<_, ComponentMatch>(&format!(
+        r#"
+        SELECT s.commit_hash, s.commit_date, s.git_message,
+               c.key AS reference, c.value AS component, p.properties AS part_properties
+        FROM schematics s
+        LEFT JOIN distilled_contents dc ON dc.sha256 = s.distilled_sha256
+        CROSS JOIN LATERAL jsonb_each(
+            CASE WHEN jsonb_typeof(COALESCE(dc.distilled_json, s.distilled_json)->'components') = 'object'
+                 THEN COALESCE(dc.distilled_json, s.distilled_json)->'components' ELSE '{{}}'::jsonb END
+        ) c
+        LEFT JOIN parts p ON p.schematic_id = s.id AND p.properties->>'reference' = c.key
+        WHERE s.repo_url = $1
+          AND (s.distilled_json IS NOT NULL OR s.distilled_sha256 IS NOT NULL)
+          AND c.key NOT LIKE '#%'
+          AND ($2::text IS NULL OR c.value->>'value' ILIKE $2)
+          AND ($3::text IS NULL OR c.value->>'lib_id' ILIKE $3)
+          AND ($4::text IS NULL OR {mpn})
+          AND ($6::text IS NULL OR c.key ILIKE $6 OR c.value->>'value' ILIKE $6
+               OR c.value->>'lib_id' ILIKE $6 OR {any_mpn})
+        ORDER BY s.commit_date DESC NULLS LAST, s.commit_hash, c.key
+        LIMIT $7
+        "#,
+        mpn = mpn_condition(4),
+        any_mpn = mpn_condition(6),
+    ))
+    .bind(repo_url)
+    .bind(search.value)
+    .bind(search.lib_id)
+    .bind(search.mpn)
+    .bind(&search.mpn_keys)
+    .bind(search.any)
+    .bind(search.limit)
+    .fetch_all(pool)
+    .await
+}
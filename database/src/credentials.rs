@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+use crate::secrets::{self, Keyring, Sealed, SealedSecret, SecretError};
+
+/// Prefix of the `secrets` names supplier API credentials are kept under
+pub const SECRET_PREFIX: &str = "provider/";
+
+/// OAuth client credentials of a supplier API, sealed together as one secret
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ClientCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// Name of the secret holding a provider's credentials
+pub fn secret_name(provider: &str) -> String {
+    format!("{}{}", SECRET_PREFIX, provider)
+}
+
+/// Provider whose credentials a secret holds, if it holds any
+pub fn provider_of(secret_name: &str) -> Option<&str> {
+    secret_name.strip_prefix(SECRET_PREFIX)
+}
+
+/// Seal and store a provider's credentials in the `secrets` table, replacing
+/// any stored before, including ones in the legacy `provider_credentials` table
+pub async fn put(
+    pool: &PgPool,
+    keyring: &Keyring,
+    provider: &str,
+    credentials: &ClientCredentials,
+) -> Result<(), SecretError> {
+    let value = serde_json::to_string(credentials)
+        .map_err(|_| SecretError::Crypto("Failed to serialize credentials"))?;
+    secrets::put_secret(pool, keyring, &secret_name(provider), &value).await?;
+    delete_credential(pool, provider).await?;
+    Ok(())
+}
+
+/// A provider's stored credentials, if there are any
+pub async fn get(
+    pool: &PgPool,
+    keyring: &Keyring,
+    provider: &str,
+) -> Result<Option<ClientCredentials>, SecretError> {
+    secrets::get_secret(pool, keyring, &secret_name(provider))
+        .await?
+        .map(|value| parse(&value))
+        .transpose()
+}
+
+/// Open credentials sealed in a secret, as listed by `secrets::list_sealed`
+pub fn open(secret: &SealedSecret, keyring: &Keyring) -> Result<ClientCredentials, SecretError> {
+    parse(&secret.open(keyring)?)
+}
+
+fn parse(value: &str) -> Result<ClientCredentials, SecretError> {
+    serde_json::from_str(value)
+        .map_err(|_| SecretError::Crypto("Stored credentials are not valid JSON"))
+}
+
+/// Remove a provider's stored credentials from both tables
+pub async fn remove(pool: &PgPool, provider: &str) -> Result<bool, Error> {
+    let removed = secrets::delete_secret(pool, &secret_name(provider)).await?;
+    Ok(delete_credential(pool, provider).await? || removed)
+}
+
+/// Move credentials from the legacy `provider_credentials` table into
+/// `secrets`. Returns the providers moved; rows that don't open with the
+/// keyring are left where they are.
+pub async fn migrate_legacy(pool: &PgPool, keyring: &Keyring) -> Result<Vec<String>, SecretError> {
+    let mut moved = Vec::new();
+    for row in list_credentials(pool).await? {
+        // Legacy secrets are bound to the bare provider name
+        let Ok(client_secret) = keyring.open(
+            &row.provider,
+            row.secret_key_id.as_deref(),
+            &row.secret_nonce,
+            &row.secret_ciphertext,
+        ) else {
+            continue;
+        };
+        let credentials = ClientCredentials {
+            client_id: row.client_id,
+            client_secret,
+        };
+        put(pool, keyring, &row.provider, &credentials).await?;
+        moved.push(row.provider);
+    }
+    Ok(moved)
+}
+
+/// Supplier API credentials as stored in the legacy `provider_credentials`
+/// table, which `migrate_legacy` empties into `secrets`. The client secret is
+/// sealed by the caller with `secrets::Keyring`, bound to the provider name;
+/// this module only moves bytes.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct StoredCredential {
+    pub provider: String,
+    pub client_id: String,
+    pub secret_nonce: Vec<u8>,
+    pub secret_ciphertext: Vec<u8>,
+    /// Key the secret was sealed with; None for secrets stored before keys had ids
+    pub secret_key_id: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Insert or replace the credentials for a provider
+pub async fn upsert_credential(
+    pool: &PgPool,
+    provider: &str,
+    client_id: &str,
+    secret: &Sealed,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO provider_credentials (provider, client_id, secret_nonce, secret_ciphertext, secret_key_id, updated_at)
+        VALUES ($1, $2, $3, $4, $5, CURRENT_TIMESTAMP)
+        ON CONFLICT (provider) DO UPDATE SET
+            client_id = EXCLUDED.client_id,
+            secret_nonce = EXCLUDED.secret_nonce,
+            secret_ciphertext = EXCLUDED.secret_ciphertext,
+            secret_key_id = EXCLUDED.secret_key_id,
+            updated_at = EXCLUDED.updated_at
+        "#,
+    )
+    .bind(provider)
+    .bind(client_id)
+    .bind(&secret.nonce)
+    .bind(&secret.ciphertext)
+    .bind(&secret.key_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Load credentials for every provider
+pub async fn list_credentials(pool: &PgPool) -> Result<Vec<StoredCredential>, Error> {
+    sqlx::query_as::<_, StoredCredential>(
+        "SELECT provider, client_id, secret_nonce, secret_ciphertext, secret_key_id, updated_at FROM provider_credentials ORDER BY provider",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Remove the stored credentials for a provider
+pub async fn delete_credential(pool: &PgPool, provider: &str) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM provider_credentials WHERE provider = $1")
+        .bind(provider)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,109 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+use crate::repos::{find_repo, RepoRef};
+
+/// A recorded change of a repository's default branch
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct DefaultBranchChange {
+    pub id: i64,
+    pub old_branch: String,
+    pub new_branch: String,
+    /// Head of the new branch when the change was detected
+    pub head_commit: String,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Store the default branch a repository advertises. When it differs from the
+/// one stored before, the change is recorded in the same transaction and
+/// returned; the first branch seen for a repository is not a change.
+/// Returns None for an unknown repository too.
+pub async fn record_default_branch(
+    pool: &PgPool,
+    repo_url: &str,
+    branch: &str,
+    head_commit: &str,
+) -> Result<Option<DefaultBranchChange>, Error> {
+    let Some(repo) = find_repo(pool, repo_url).await? else {
+        return Ok(None);
+    };
+
+    let mut tx = pool.begin().await?;
+    let previous: Option<String> =
+        sqlx::query_scalar("SELECT default_branch FROM repos WHERE id = $1 FOR UPDATE")
+            .bind(repo.id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    if previous.as_deref() == Some(branch) {
+        return Ok(None);
+    }
+    sqlx::query("UPDATE repos SET default_branch = $2 WHERE id = $1")
+        .bind(repo.id)
+        .bind(branch)
+        .execute(&mut *tx)
+        .await?;
+
+    let change = match previous {
+        Some(old_branch) => Some(
+            sqlx::query_as::<_, DefaultBranchChange>(
+                r#"
+                INSERT INTO default_branch_changes (repo_id, old_branch, new_branch, head_commit)
+                VALUES ($1, $2, $3, $4)
+                RETURNING id, old_branch, new_branch, head_commit, detected_at
+                "#,
+            )
+            .bind(repo.id)
+            .bind(&old_branch)
+            .bind(branch)
+            .bind(head_commit)
+            .fetch_one(&mut *tx)
+            .await?,
+        ),
+        None => None,
+    };
+
+    tx.commit().await?;
+    Ok(change)
+}
+
+/// Default branch stored for a repository, if it was checked. Replica-safe.
+pub async fn get_default_branch(pool: &PgPool, repo_url: &str) -> Result<Option<String>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(None);
+    };
+
+    sqlx::query_scalar::<_, Option<String>>(
+        "SELECT default_branch FROM repos WHERE provider = $1 AND LOWER(slug) = LOWER($2)",
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .fetch_optional(pool)
+    .await
+    .map(Option::flatten)
+}
+
+/// A repository's default branch changes, newest first. Replica-safe.
+pub async fn list_changes(
+    pool: &PgPool,
+    repo_url: &str,
+) -> Result<Vec<DefaultBranchChange>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, DefaultBranchChange>(
+        r#"
+        SELECT c.id, c.old_branch, c.new_branch, c.head_commit, c.detected_at
+        FROM default_branch_changes c
+        JOIN repos r ON r.id = c.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+        ORDER BY c.detected_at DESC, c.id DESC
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+
+use crate::repos::{ensure_repo_id, RepoRef};
+
+/// A named design rule of a repository
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct DesignRuleRecord {
+    pub name: String,
+    /// Rule definition as given by the API; validated before it's stored
+    pub definition: Value,
+    pub enabled: bool,
+    pub updated_by: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Create or replace a rule. Returns None when the repository URL can't be parsed.
+pub async fn upsert_rule(
+    pool: &PgPool,
+    repo_url: &str,
+    name: &str,
+    definition: &Value,
+    enabled: bool,
+    updated_by: Option<&str>,
+) -> Result<Option<DesignRuleRecord>, Error> {
+    let Some(repo_id) = ensure_repo_id(pool, repo_url).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, DesignRuleRecord>(
+        r#"
+        INSERT INTO design_rules (repo_id, name, definition, enabled, updated_by)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (repo_id, name) DO UPDATE SET
+            definition = EXCLUDED.definition,
+            enabled = EXCLUDED.enabled,
+            updated_by = EXCLUDED.updated_by,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING name, definition, enabled, updated_by, updated_at
+        "#,
+    )
+    .bind(repo_id)
+    .bind(name)
+    .bind(definition)
+    .bind(enabled)
+    .bind(updated_by)
+    .fetch_one(pool)
+    .await
+    .map(Some)
+}
+
+/// Rules of a repository by name, optionally only the enabled ones. Replica-safe.
+pub async fn list_rules(
+    pool: &PgPool,
+    repo_url: &str,
+    enabled_only: bool,
+) -> Result<Vec<DesignRuleRecord>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, DesignRuleRecord>(
+        r#"
+        SELECT d.name, d.definition, d.enabled, d.updated_by, d.updated_at
+        FROM design_rules d
+        JOIN repos r ON r.id = d.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND (d.enabled OR NOT $3)
+        ORDER BY d.name
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(enabled_only)
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete a rule; false when there was none by that name
+pub async fn delete_rule(pool: &PgPool, repo_url: &str, name: &str) -> Result<bool, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(false);
+    };
+
+    let result = sqlx::query(
+        r#"
+        DELETE FROM design_rules d
+        USING repos r
+        WHERE r.id = d.repo_id
+          AND r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND d.name = $3
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(name)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, Executor, PgConnection, PgPool, Postgres};
+use uuid::Uuid;
+
+use crate::repos::{ensure_repo_id, RepoRef};
+
+/// Accepted values of `digest_subscriptions.frequency`
+pub const FREQUENCIES: &[&str] = &["daily", "weekly", "off"];
+
+/// A subscriber's digest preference for one repository
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct DigestSubscription {
+    pub id: i64,
+    pub repo_url: String,
+    pub email: String,
+    pub frequency: String,
+    pub unsubscribe_token: String,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DigestSubscription {
+    /// Start of the period the next digest covers
+    pub fn period_start(&self) -> DateTime<Utc> {
+        self.last_sent_at.unwrap_or(self.created_at)
+    }
+}
+
+/// An indexed schematic commit with its summary and design metrics
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct DigestCommit {
+    pub commit_hash: String,
+    pub commit_date: Option<DateTime<Utc>>,
+    pub git_message: Option<String>,
+    pub blurb: Option<String>,
+    pub component_count: Option<i32>,
+    pub estimated_bom_cost: Option<f64>,
+}
+
+const SUBSCRIPTION_COLUMNS: &str = r#"
+    d.id, r.repo_url, d.email, d.frequency, d.unsubscribe_token, d.last_sent_at, d.created_at
+"#;
+
+/// How long a confirmation link stays valid
+pub const CONFIRMATION_TTL_HOURS: i32 = 48;
+
+// Minimum time before a confirmation for the same address and repository is resent
+const CONFIRMATION_RESEND_MINUTES: i32 = 10;
+
+/// Outcome of `request_confirmation`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfirmationRequest {
+    /// A new pending confirmation; its token goes in the confirmation link
+    Created { token: String },
+    /// A confirmation was sent to this address recently; nothing was changed
+    Throttled,
+    /// The repository URL can't be parsed
+    InvalidRepo,
+}
+
+/// Record a pending subscription (or frequency change) that takes effect once
+/// the address follows its confirmation link. Existing subscriptions are left
+/// untouched until then. A pending request for the same address and repository
+/// is replaced, unless it was made within the last few minutes.
+pub async fn request_confirmation(
+    conn: &mut PgConnection,
+    repo_url: &str,
+    email: &str,
+    frequency: &str,
+) -> Result<ConfirmationRequest, Error> {
+    let Some(repo_id) = ensure_repo_id(&mut *conn, repo_url).await? else {
+        return Ok(ConfirmationRequest::InvalidRepo);
+    };
+
+    let token = Uuid::new_v4().simple().to_string();
+    let stored: Option<String> = sqlx::query_scalar(
+        r#"
+        INSERT INTO digest_confirmations (token, repo_id, email, frequency)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (repo_id, (LOWER(email))) DO UPDATE SET
+            token = EXCLUDED.token,
+            email = EXCLUDED.email,
+            frequency = EXCLUDED.frequency,
+            created_at = CURRENT_TIMESTAMP
+        WHERE digest_confirmations.created_at
+              <= CURRENT_TIMESTAMP - make_interval(mins => $5)
+        RETURNING token
+        "#,
+    )
+    .bind(&token)
+    .bind(repo_id)
+    .bind(email)
+    .bind(frequency)
+    .bind(CONFIRMATION_RESEND_MINUTES)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(match stored {
+        Some(token) => ConfirmationRequest::Created { token },
+        None => ConfirmationRequest::Throttled,
+    })
+}
+
+/// Apply the pending request with this confirmation token: subscribe the
+/// address, or change the frequency of its existing subscription (addresses
+/// match ignoring case). Returns None for an unknown or expired token.
+pub async fn confirm(pool: &PgPool, token: &str) -> Result<Option<DigestSubscription>, Error> {
+    let mut tx = pool.begin().await?;
+    let pending: Option<(i32, String, String)> = sqlx::query_as(
+        r#"
+        DELETE FROM digest_confirmations
+        WHERE token = $1 AND created_at > CURRENT_TIMESTAMP - make_interval(hours => $2)
+        RETURNING repo_id, email, frequency
+        "#,
+    )
+    .bind(token)
+    .bind(CONFIRMATION_TTL_HOURS)
+    .fetch_optional(&mut *tx)
+    .await?;
+    let Some((repo_id, email, frequency)) = pending else {
+        return Ok(None);
+    };
+
+    let id: i64 = sqlx::query_scalar(
+        r#"
+        INSERT INTO digest_subscriptions (repo_id, email, frequency, unsubscribe_token)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (repo_id, (LOWER(email))) DO UPDATE SET
+            frequency = EXCLUDED.frequency
+        RETURNING id
+        "#,
+    )
+    .bind(repo_id)
+    .bind(&email)
+    .bind(&frequency)
+    .bind(Uuid::new_v4().simple().to_string())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let subscription = sqlx::query_as::<_, DigestSubscription>(&format!(
+        "SELECT {} FROM digest_subscriptions d JOIN repos r ON r.id = d.repo_id WHERE d.id = $1",
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(subscription)
+}
+
+/// Delete pending confirmations whose link has expired. Returns how many were deleted.
+pub async fn purge_expired_confirmations(pool: &PgPool) -> Result<u64, Error> {
+    let result = sqlx::query(
+        "DELETE FROM digest_confirmations WHERE created_at <= CURRENT_TIMESTAMP - make_interval(hours => $1)",
+    )
+    .bind(CONFIRMATION_TTL_HOURS)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Remove the subscription with this unsubscribe token. Returns whether one was removed.
+pub async fn unsubscribe(pool: &PgPool, unsubscribe_token: &str) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM digest_subscriptions WHERE unsubscribe_token = $1")
+        .bind(unsubscribe_token)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Subscriptions whose period has ended, longest overdue first
+pub async fn due_subscriptions(
+    pool: &PgPool,
+    limit: i64,
+) -> Result<Vec<DigestSubscription>, Error> {
+    sqlx::query_as::<_, DigestSubscription>(&format!(
+        r#"
+        SELECT {}
+        FROM digest_subscriptions d
+        JOIN repos r ON r.id = d.repo_id
+        WHERE d.frequency <> 'off'
+          AND COALESCE(d.last_sent_at, d.created_at)
+              + CASE d.frequency WHEN 'daily' THEN INTERVAL '1 day' ELSE INTERVAL '7 days' END
+              <= NOW()
+        ORDER BY COALESCE(d.last_sent_at, d.created_at)
+        LIMIT $1
+        "#,
+        SUBSCRIPTION_COLUMNS
+    ))
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Record that a subscription's digest covers everything up to `sent_at`.
+///
+/// A compare-and-swap on `last_sent_at`: returns false when another scheduler
+/// already handled this period, so the caller can roll back its digest.
+pub async fn mark_sent<'e, E>(
+    executor: E,
+    subscription: &DigestSubscription,
+    sent_at: DateTime<Utc>,
+) -> Result<bool, Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let result = sqlx::query(
+        r#"
+        UPDATE digest_subscriptions SET last_sent_at = $3
+        WHERE id = $1 AND last_sent_at IS NOT DISTINCT FROM $2
+        "#,
+    )
+    .bind(subscription.id)
+    .bind(subscription.last_sent_at)
+    .bind(sent_at)
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+const COMMIT_COLUMNS: &str = r#"
+    s.commit_hash, s.commit_date, s.git_message, COALESCE(s.blurb_override, s.blurb) AS blurb,
+    m.component_count, m.estimated_bom_cost
+"#;
+
+/// Indexed commits of a repository dated within (`since`, `until`], oldest first. Replica-safe.
+pub async fn commits_between(
+    pool: &PgPool,
+    repo_url: &str,
+    since: DateTime<Utc>,
+    until: DateTime<Utc>,
+) -> Result<Vec<DigestCommit>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, DigestCommit>(&format!(
+        r#"
+        SELECT {}
+        FROM schematics s
+        JOIN repos r ON r.id = s.repo_id
+        LEFT JOIN design_metrics m ON m.schematic_id = s.id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND s.commit_date > $3 AND s.commit_date <= $4
+        ORDER BY s.commit_date, s.id
+        "#,
+        COMMIT_COLUMNS
+    ))
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(since)
+    .bind(until)
+    .fetch_all(pool)
+    .await
+}
+
+/// The newest indexed commit with metrics dated at or before `at`, the
+/// baseline for a period's deltas. Replica-safe.
+pub async fn baseline_commit(
+    pool: &PgPool,
+    repo_url: &str,
+    at: DateTime<Utc>,
+) -> Result<Option<DigestCommit>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, DigestCommit>(&format!(
+        r#"
+        SELECT {}
+        FROM schematics s
+        JOIN repos r ON r.id = s.repo_id
+        JOIN design_metrics m ON m.schematic_id = s.id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND s.commit_date <= $3
+        ORDER BY s.commit_date DESC, s.id DESC
+        LIMIT 1
+        "#,
+        COMMIT_COLUMNS
+    ))
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(at)
+    .fetch_optional(pool)
+    .await
+}
@@ -0,0 +1,67 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+use std::collections::HashMap;
+
+/// Exchange rates fetched on one day: units of each currency per unit of `base`
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ExchangeRates {
+    pub base: String,
+    pub fetched_on: NaiveDate,
+    pub rates: Value,
+}
+
+impl ExchangeRates {
+    /// Rates by currency code; entries that aren't numbers are skipped
+    pub fn by_currency(&self) -> HashMap<String, f64> {
+        self.rates
+            .as_object()
+            .map(|rates| {
+                rates
+                    .iter()
+                    .filter_map(|(code, rate)| Some((code.clone(), rate.as_f64()?)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Store the rates fetched on a day, replacing any stored earlier that day
+pub async fn store_rates(
+    pool: &PgPool,
+    base: &str,
+    fetched_on: NaiveDate,
+    rates: &HashMap<String, f64>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO exchange_rates (base, fetched_on, rates)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (base, fetched_on) DO UPDATE SET rates = EXCLUDED.rates
+        "#,
+    )
+    .bind(base)
+    .bind(fetched_on)
+    .bind(serde_json::json!(rates))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The most recently fetched rates for `base`
+pub async fn latest_rates(pool: &PgPool, base: &str) -> Result<Option<ExchangeRates>, Error> {
+    sqlx::query_as::<_, ExchangeRates>(
+        r#"
+        SELECT base, fetched_on, rates
+        FROM exchange_rates
+        WHERE base = $1
+        ORDER BY fetched_on DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(base)
+    .fetch_optional(pool)
+    .await
+}
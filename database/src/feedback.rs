@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// A stored rating of a generated summary
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct FeedbackRecord {
+    pub id: i64,
+    pub repo_url: String,
+    pub commit_hash: String,
+    pub target: String,
+    pub rating: i16,
+    pub comment: Option<String>,
+    pub summary_text: Option<String>,
+    pub model: Option<String>,
+    pub prompt_version: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fields for a new rating
+#[derive(Debug, Clone)]
+pub struct NewFeedback<'a> {
+    pub schematic_id: Option<i32>,
+    pub repo_url: &'a str,
+    pub commit_hash: &'a str,
+    pub target: &'a str,
+    pub rating: i16,
+    pub comment: Option<&'a str>,
+    pub summary_text: Option<&'a str>,
+    pub model: Option<&'a str>,
+    pub prompt_version: Option<&'a str>,
+}
+
+/// The stored blurb/description of a commit and what generated them
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct StoredSummary {
+    pub schematic_id: i32,
+    pub blurb: Option<String>,
+    pub description: Option<String>,
+    pub summary_model: Option<String>,
+    pub summary_prompt_version: Option<String>,
+}
+
+/// Up/down vote counts for one target/model/prompt version
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct FeedbackTotals {
+    pub target: String,
+    pub model: Option<String>,
+    pub prompt_version: Option<String>,
+    pub up: i64,
+    pub down: i64,
+}
+
+/// Filters for exporting feedback; `None` matches everything
+#[derive(Debug, Clone, Default)]
+pub struct FeedbackFilter<'a> {
+    pub target: Option<&'a str>,
+    pub prompt_version: Option<&'a str>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: i64,
+}
+
+/// Look up the stored blurb/description for a commit
+pub async fn stored_summary(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+) -> Result<Option<StoredSummary>, Error> {
+    sqlx::query_as::<_, StoredSummary>(
+        r#"
+        SELECT id AS schematic_id, blurb, description, summary_model, summary_prompt_version
+        FROM schematics
+        WHERE repo_url = $1 AND commit_hash = $2
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Store a rating
+pub async fn insert_feedback(
+    pool: &PgPool,
+    feedback: &NewFeedback<'_>,
+) -> Result<FeedbackRecord, Error> {
+    sqlx::query_as::<_, FeedbackRecord>(
+        r#"
+        INSERT INTO feedback
+            (schematic_id, repo_url, commit_hash, target, rating, comment, summary_text, model, prompt_version)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        RETURNING id, repo_url, commit_hash, target, rating, comment, summary_text, model,
+                  prompt_version, created_at
+        "#,
+    )
+    .bind(feedback.schematic_id)
+    .bind(feedback.repo_url)
+    .bind(feedback.commit_hash)
+    .bind(feedback.target)
+    .bind(feedback.rating)
+    .bind(feedback.comment)
+    .bind(feedback.summary_text)
+    .bind(feedback.model)
+    .bind(feedback.prompt_version)
+    .fetch_one(pool)
+    .await
+}
+
+/// Ratings matching `filter`, newest first. Replica-safe.
+pub async fn export_feedback(
+    pool: &PgPool,
+    filter: &FeedbackFilter<'_>,
+) -> Result<Vec<FeedbackRecord>, Error> {
+    sqlx::query_as::<_, FeedbackRecord>(
+        r#"
+        SELECT id, repo_url, commit_hash, target, rating, comment, summary_text, model,
+               prompt_version, created_at
+        FROM feedback
+        WHERE ($1::TEXT IS NULL OR target = $1)
+          AND ($2::TEXT IS NULL OR prompt_version = $2)
+          AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+        ORDER BY created_at DESC, id DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(filter.target)
+    .bind(filter.prompt_version)
+    .bind(filter.since)
+    .bind(filter.limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Vote counts per target/model/prompt version over ratings matching `filter` (ignoring its limit)
+pub async fn feedback_totals(
+    pool: &PgPool,
+    filter: &FeedbackFilter<'_>,
+) -> Result<Vec<FeedbackTotals>, Error> {
+    sqlx::query_as::<_, FeedbackTotals>(
+        r#"
+        SELECT target, model, prompt_version,
+               COUNT(*) FILTER (WHERE rating > 0) AS up,
+               COUNT(*) FILTER (WHERE rating < 0) AS down
+        FROM feedback
+        WHERE ($1::TEXT IS NULL OR target = $1)
+          AND ($2::TEXT IS NULL OR prompt_version = $2)
+          AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+        GROUP BY target, model, prompt_version
+        ORDER BY target, prompt_version, model
+        "#,
+    )
+    .bind(filter.target)
+    .bind(filter.prompt_version)
+    .bind(filter.since)
+    .fetch_all(pool)
+    .await
+}
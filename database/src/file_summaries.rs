@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// Summary of one changed file of a commit
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct FileSummary {
+    pub repo_url: String,
+    pub commit_hash: String,
+    pub file_path: String,
+    pub summary: String,
+    pub model: String,
+    pub prompt_version: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Store the per-file summaries of a commit, replacing earlier ones of the same files
+pub async fn store_file_summaries(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    model: &str,
+    prompt_version: &str,
+    summaries: &[(String, String)],
+) -> Result<(), Error> {
+    let (paths, texts): (Vec<&str>, Vec<&str>) = summaries
+        .iter()
+        .map(|(path, summary)| (path.as_str(), summary.as_str()))
+        .unzip();
+    sqlx::query(
+        r#"
+        INSERT INTO file_summaries (repo_url, commit_hash, file_path, summary, model, prompt_version)
+        SELECT $1, $2, file_path, summary, $3, $4
+        FROM UNNEST($5::TEXT[], $6::TEXT[]) AS f(file_path, summary)
+        ON CONFLICT (repo_url, commit_hash, file_path) DO UPDATE SET
+            summary = EXCLUDED.summary,
+            model = EXCLUDED.model,
+            prompt_version = EXCLUDED.prompt_version,
+            created_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(model)
+    .bind(prompt_version)
+    .bind(&paths)
+    .bind(&texts)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Per-file summaries of a commit, by path
+pub async fn list_file_summaries(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+) -> Result<Vec<FileSummary>, Error> {
+    sqlx::query_as::<_, FileSummary>(
+        r#"
+        SELECT repo_url, commit_hash, file_path, summary, model, prompt_version, created_at
+        FROM file_summaries
+        WHERE repo_url = $1 AND commit_hash = $2
+        ORDER BY file_path
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,428 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgConnection, PgPool};
+
+/// A row in the background job queue.
+/// `kind` and `payload` are interpreted by the backend; this module only schedules them.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct Job {
+    pub id: i64,
+    pub kind: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub result: Option<Value>,
+    /// Last progress reported by the running job, if it reports any
+    pub progress: Option<Value>,
+    pub last_error: Option<String>,
+    pub locked_by: Option<String>,
+    pub run_after: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+
+/// One attempt at a job. `status` is "running", "succeeded" or "failed".
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct JobRun {
+    pub id: i64,
+    pub job_id: i64,
+    pub kind: String,
+    pub attempt: i32,
+    pub worker: String,
+    pub status: String,
+    pub error: Option<String>,
+    /// What a successful run produced
+    pub artifacts: Option<Value>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<i64>,
+}
+
+/// Narrows `list_failed_jobs` and `retry_failed_jobs`; unset fields match
+/// every failed job
+#[derive(Debug, Clone, Default)]
+pub struct FailedJobFilter {
+    pub ids: Option<Vec<i64>>,
+    pub kind: Option<String>,
+    /// Only jobs that failed at or after this time
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Add a job to the queue, returning its id
+pub async fn enqueue_job(
+    pool: &PgPool,
+    kind: &str,
+    payload: &Value,
+    max_attempts: i32,
+) -> Result<i64, Error> {
+    sqlx::query_scalar(
+        "INSERT INTO jobs (kind, payload, max_attempts) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(kind)
+    .bind(payload)
+    .bind(max_attempts)
+    .fetch_one(pool)
+    .await
+}
+
+/// Claim the oldest runnable job for a worker.
+///
+/// Uses `FOR UPDATE SKIP LOCKED` so any number of workers can poll the same
+/// table without handing out a job twice.
+pub async fn claim_next_job(pool: &PgPool, worker_id: &str) -> Result<Option<Job>, Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        UPDATE jobs SET
+            status = 'running',
+            attempts = attempts + 1,
+            locked_by = $1,
+            started_at = CURRENT_TIMESTAMP
+        WHERE id = (
+            SELECT id FROM jobs
+            WHERE status = 'queued' AND run_after <= CURRENT_TIMESTAMP
+            ORDER BY run_after, id
+            FOR UPDATE SKIP LOCKED
+            LIMIT 1
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(worker_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Claim a specific queued job, for running it outside the worker pool.
+/// Returns None when the job is not queued (e.g. a worker got to it first).
+pub async fn claim_job(pool: &PgPool, id: i64, worker_id: &str) -> Result<Option<Job>, Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        UPDATE jobs SET
+            status = 'running',
+            attempts = attempts + 1,
+            locked_by = $2,
+            started_at = CURRENT_TIMESTAMP
+        WHERE id = $1 AND status = 'queued'
+        RETURNING *
+        "#,
+    )
+    .bind(id)
+    .bind(worker_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Record a running job's progress, replacing what it reported before
+pub async fn set_progress(pool: &PgPool, id: i64, progress: &Value) -> Result<(), Error> {
+    sqlx::query("UPDATE jobs SET progress = $2 WHERE id = $1")
+        .bind(id)
+        .bind(progress)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Mark a job as finished successfully
+pub async fn complete_job(pool: &PgPool, id: i64, result: &Value) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE jobs SET
+            status = 'succeeded',
+            result = $2,
+            last_error = NULL,
+            locked_by = NULL,
+            finished_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(result)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a job failure.
+///
+/// The job goes back on the queue after `retry_delay_secs` while it has
+/// attempts left, otherwise it is marked failed for good.
+pub async fn fail_job(
+    pool: &PgPool,
+    id: i64,
+    error: &str,
+    retry_delay_secs: i64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE jobs SET
+            status = CASE WHEN attempts < max_attempts THEN 'queued' ELSE 'failed' END,
+            run_after = CURRENT_TIMESTAMP + make_interval(secs => $3),
+            last_error = $2,
+            locked_by = NULL,
+            finished_at = CASE WHEN attempts < max_attempts THEN NULL ELSE CURRENT_TIMESTAMP END
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(error)
+    .bind(retry_delay_secs as f64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Mark a job failed for good, whatever attempts it has left. For failures
+/// that would only repeat on retry, such as a distillation timeout. `result`
+/// can describe the failure for clients polling the job.
+pub async fn abandon_job(
+    pool: &PgPool,
+    id: i64,
+    error: &str,
+    result: Option<&Value>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE jobs SET
+            status = 'failed',
+            last_error = $2,
+            result = $3,
+            locked_by = NULL,
+            finished_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(error)
+    .bind(result)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Put jobs left running by a crashed worker back on the queue. Their open
+/// runs are closed as failed.
+pub async fn requeue_stale_jobs(pool: &PgPool, stale_after_secs: i64) -> Result<u64, Error> {
+    sqlx::query(
+        r#"
+        UPDATE job_runs SET
+            status = 'failed',
+            error = 'Worker stopped responding',
+            finished_at = CURRENT_TIMESTAMP,
+            duration_ms = (EXTRACT(EPOCH FROM CURRENT_TIMESTAMP - started_at) * 1000)::BIGINT
+        WHERE status = 'running'
+            AND job_id IN (
+                SELECT id FROM jobs
+                WHERE status = 'running'
+                    AND started_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+            )
+        "#,
+    )
+    .bind(stale_after_secs as f64)
+    .execute(pool)
+    .await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE jobs SET
+            status = 'queued',
+            locked_by = NULL
+        WHERE status = 'running'
+            AND started_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+        "#,
+    )
+    .bind(stale_after_secs as f64)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// The most recently queued job of `kind` whose payload has every field of
+/// `payload`, in any status
+pub async fn latest_job(pool: &PgPool, kind: &str, payload: &Value) -> Result<Option<Job>, Error> {
+    sqlx::query_as::<_, Job>(
+        "SELECT * FROM jobs WHERE kind = $1 AND payload @> $2 ORDER BY id DESC LIMIT 1",
+    )
+    .bind(kind)
+    .bind(payload)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Take the session-level advisory lock `key` on `conn` if no other session
+/// holds it. It is held until taken back or the connection closes.
+pub async fn try_session_lock(conn: &mut PgConnection, key: i64) -> Result<bool, Error> {
+    sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+        .bind(key)
+        .fetch_one(conn)
+        .await
+}
+
+/// Look up a job by id
+pub async fn get_job(pool: &PgPool, id: i64) -> Result<Option<Job>, Error> {
+    sqlx::query_as::<_, Job>("SELECT * FROM jobs WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Number of jobs in each status
+pub async fn count_by_status(pool: &PgPool) -> Result<Vec<(String, i64)>, Error> {
+    sqlx::query_as("SELECT status, COUNT(*) FROM jobs GROUP BY status ORDER BY status")
+        .fetch_all(pool)
+        .await
+}
+
+/// When the longest-waiting runnable job was queued
+pub async fn oldest_queued_at(pool: &PgPool) -> Result<Option<DateTime<Utc>>, Error> {
+    sqlx::query_scalar("SELECT MIN(created_at) FROM jobs WHERE status = 'queued'")
+        .fetch_one(pool)
+        .await
+}
+
+/// Jobs with a recorded error, most recent first.
+/// Includes jobs that are queued for a retry as well as permanently failed ones.
+pub async fn recent_failures(pool: &PgPool, limit: i64) -> Result<Vec<Job>, Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        SELECT * FROM jobs
+        WHERE last_error IS NOT NULL
+        ORDER BY COALESCE(finished_at, started_at, created_at) DESC
+        LIMIT $1
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Record the start of an attempt at a claimed job, returning the run's id
+pub async fn start_run(pool: &PgPool, job: &Job, worker_id: &str) -> Result<i64, Error> {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO job_runs (job_id, kind, attempt, worker)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id
+        "#,
+    )
+    .bind(job.id)
+    .bind(&job.kind)
+    .bind(job.attempts)
+    .bind(worker_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Record how a run ended: STATUS_SUCCEEDED with what it produced, or
+/// STATUS_FAILED with its error
+pub async fn finish_run(
+    pool: &PgPool,
+    run_id: i64,
+    status: &str,
+    error: Option<&str>,
+    artifacts: Option<&Value>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE job_runs SET
+            status = $2,
+            error = $3,
+            artifacts = $4,
+            finished_at = CURRENT_TIMESTAMP,
+            duration_ms = (EXTRACT(EPOCH FROM CURRENT_TIMESTAMP - started_at) * 1000)::BIGINT
+        WHERE id = $1
+        "#,
+    )
+    .bind(run_id)
+    .bind(status)
+    .bind(error)
+    .bind(artifacts)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Runs of the given jobs, oldest first
+pub async fn runs_for_jobs(pool: &PgPool, job_ids: &[i64]) -> Result<Vec<JobRun>, Error> {
+    sqlx::query_as::<_, JobRun>("SELECT * FROM job_runs WHERE job_id = ANY($1) ORDER BY id")
+        .bind(job_ids)
+        .fetch_all(pool)
+        .await
+}
+
+/// Failed jobs matching `filter`, most recently failed first
+pub async fn list_failed_jobs(
+    pool: &PgPool,
+    filter: &FailedJobFilter,
+    limit: i64,
+) -> Result<Vec<Job>, Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        SELECT * FROM jobs
+        WHERE status = 'failed'
+            AND ($1::BIGINT[] IS NULL OR id = ANY($1))
+            AND ($2::TEXT IS NULL OR kind = $2)
+            AND ($3::TIMESTAMPTZ IS NULL OR finished_at >= $3)
+        ORDER BY finished_at DESC NULLS LAST, id DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(&filter.ids)
+    .bind(&filter.kind)
+    .bind(filter.since)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Put up to `limit` failed jobs matching `filter` back on the queue with
+/// `extra_attempts` more attempts, returning them. Their earlier runs and
+/// last error are kept.
+pub async fn retry_failed_jobs(
+    pool: &PgPool,
+    filter: &FailedJobFilter,
+    extra_attempts: i32,
+    limit: i64,
+) -> Result<Vec<Job>, Error> {
+    sqlx::query_as::<_, Job>(
+        r#"
+        UPDATE jobs SET
+            status = 'queued',
+            max_attempts = attempts + $5,
+            run_after = CURRENT_TIMESTAMP,
+            locked_by = NULL,
+            finished_at = NULL
+        WHERE id IN (
+            SELECT id FROM jobs
+            WHERE status = 'failed'
+                AND ($1::BIGINT[] IS NULL OR id = ANY($1))
+                AND ($2::TEXT IS NULL OR kind = $2)
+                AND ($3::TIMESTAMPTZ IS NULL OR finished_at >= $3)
+            ORDER BY finished_at DESC NULLS LAST, id DESC
+            LIMIT $4
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(&filter.ids)
+    .bind(&filter.kind)
+    .bind(filter.since)
+    .bind(limit)
+    .bind(extra_attempts)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,56 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// Totals over a set of LLM calls
+#[derive(Serialize, Deserialize, Debug, Clone, Default, sqlx::FromRow)]
+pub struct UsageSummary {
+    pub requests: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: f64,
+}
+
+/// Record one LLM call
+pub async fn record_usage(
+    pool: &PgPool,
+    feature: &str,
+    model: &str,
+    prompt_tokens: i64,
+    completion_tokens: i64,
+    cost_usd: f64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO llm_usage (feature, model, prompt_tokens, completion_tokens, cost_usd)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(feature)
+    .bind(model)
+    .bind(prompt_tokens)
+    .bind(completion_tokens)
+    .bind(cost_usd)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Usage totals for calls made at or after `since`
+pub async fn usage_since(pool: &PgPool, since: DateTime<Utc>) -> Result<UsageSummary, Error> {
+    sqlx::query_as::<_, UsageSummary>(
+        r#"
+        SELECT
+            COUNT(*) AS requests,
+            COALESCE(SUM(prompt_tokens), 0)::BIGINT AS prompt_tokens,
+            COALESCE(SUM(completion_tokens), 0)::BIGINT AS completion_tokens,
+            COALESCE(SUM(cost_usd), 0) AS cost_usd
+        FROM llm_usage
+        WHERE created_at >= $1
+        "#,
+    )
+    .bind(since)
+    .fetch_one(pool)
+    .await
+}
@@ -20,7 +20,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "main",
         None, // commit_date
         Some("Test git message"), // git_message
-        None,  // image bytes
         Some("Initial commit"), // change_summary
         Some("Smartwatch project"), // project_overview
         Some("Brief blurb"), // blurb
@@ -72,6 +72,12 @@ pub struct ReasoningConfig {
     pub effort: ReasoningEffort,
 }
 
+/// Streaming options; `include_usage` asks for a final chunk carrying token usage
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StreamOptions {
+    pub include_usage: bool,
+}
+
 /// Chat completion request for XAI API
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ChatCompletionRequest {
@@ -80,6 +86,8 @@ pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub reasoning: Option<ReasoningConfig>,
 }
 
@@ -90,6 +98,7 @@ impl ChatCompletionRequest {
             messages,
             model,
             stream: None,
+            stream_options: None,
             reasoning: None,
         }
     }
@@ -100,6 +109,7 @@ impl ChatCompletionRequest {
             messages,
             model,
             stream: Some(stream),
+            stream_options: None,
             reasoning: None,
         }
     }
@@ -110,6 +120,7 @@ impl ChatCompletionRequest {
             messages,
             model,
             stream: Some(stream),
+            stream_options: None,
             reasoning: Some(ReasoningConfig { effort }),
         }
     }
@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool, Row};
+use std::collections::HashMap;
+
+/// Design metrics of one commit, with the commit's date and message
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct DesignMetricsRecord {
+    pub commit_hash: String,
+    pub commit_date: Option<DateTime<Utc>>,
+    pub git_message: Option<String>,
+    pub component_count: i32,
+    pub net_count: i32,
+    pub sheet_count: i32,
+    pub unique_mpns: i32,
+    pub estimated_bom_cost: Option<f64>,
+    pub priced_components: i32,
+    pub computed_at: DateTime<Utc>,
+}
+
+/// Metrics computed for a commit
+#[derive(Debug, Clone, Default)]
+pub struct NewDesignMetrics {
+    pub component_count: i32,
+    pub net_count: i32,
+    pub sheet_count: i32,
+    pub unique_mpns: i32,
+    pub estimated_bom_cost: Option<f64>,
+    pub priced_components: i32,
+}
+
+/// Store metrics for an indexed commit, replacing earlier ones.
+/// Returns false when the commit has no schematics row.
+pub async fn upsert_metrics(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    metrics: &NewDesignMetrics,
+) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO design_metrics
+            (schematic_id, component_count, net_count, sheet_count, unique_mpns, estimated_bom_cost, priced_components)
+        SELECT id, $3, $4, $5, $6, $7, $8
+        FROM schematics
+        WHERE repo_url = $1 AND commit_hash = $2
+        ON CONFLICT (schematic_id) DO UPDATE SET
+            component_count = EXCLUDED.component_count,
+            net_count = EXCLUDED.net_count,
+            sheet_count = EXCLUDED.sheet_count,
+            unique_mpns = EXCLUDED.unique_mpns,
+            estimated_bom_cost = EXCLUDED.estimated_bom_cost,
+            priced_components = EXCLUDED.priced_components,
+            computed_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(metrics.component_count)
+    .bind(metrics.net_count)
+    .bind(metrics.sheet_count)
+    .bind(metrics.unique_mpns)
+    .bind(metrics.estimated_bom_cost)
+    .bind(metrics.priced_components)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Metrics for a repository's commits, oldest first (at most `limit`, keeping the newest)
+pub async fn metrics_history(
+    pool: &PgPool,
+    repo_url: &str,
+    limit: i64,
+) -> Result<Vec<DesignMetricsRecord>, Error> {
+    sqlx::query_as::<_, DesignMetricsRecord>(
+        r#"
+        SELECT * FROM (
+            SELECT s.commit_hash, s.commit_date, s.git_message,
+                   m.component_count, m.net_count, m.sheet_count, m.unique_mpns,
+                   m.estimated_bom_cost, m.priced_components, m.computed_at
+            FROM design_metrics m
+            JOIN schematics s ON s.id = m.schematic_id
+            WHERE s.repo_url = $1
+            ORDER BY s.commit_date DESC NULLS LAST, s.id DESC
+            LIMIT $2
+        ) recent
+        ORDER BY commit_date ASC NULLS FIRST
+        "#,
+    )
+    .bind(repo_url)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Commits with cached distilled data but no metrics yet: (commit_hash, distilled_json)
+pub async fn commits_without_metrics(
+    pool: &PgPool,
+    repo_url: &str,
+    limit: i64,
+) -> Result<Vec<(String, Value)>, Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT s.commit_hash, COALESCE(c.distilled_json, s.distilled_json) AS distilled_json
+        FROM schematics s
+        LEFT JOIN distilled_contents c ON c.sha256 = s.distilled_sha256
+        LEFT JOIN design_metrics m ON m.schematic_id = s.id
+        WHERE s.repo_url = $1
+          AND (s.distilled_json IS NOT NULL OR s.distilled_sha256 IS NOT NULL)
+          AND m.schematic_id IS NULL
+        ORDER BY s.commit_date DESC NULLS LAST
+        LIMIT $2
+        "#,
+    )
+    .bind(repo_url)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| Ok((row.try_get("commit_hash")?, row.try_get("distilled_json")?)))
+        .collect()
+}
+
+/// A unit price stored by part enrichment, in the currency it was quoted in
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PartPrice {
+    pub unit_price: f64,
+    /// ISO 4217 code; prices stored before currencies were recorded are USD
+    pub currency: String,
+}
+
+/// Unit prices and their currencies stored by part enrichment for a commit,
+/// keyed by reference
+pub async fn part_prices(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+) -> Result<HashMap<String, PartPrice>, Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT p.properties->>'reference' AS reference,
+               (p.properties->'digikey'->>'unit_price')::DOUBLE PRECISION AS unit_price,
+               COALESCE(p.properties->'digikey'->>'currency', 'USD') AS currency
+        FROM parts p
+        JOIN schematics s ON s.id = p.schematic_id
+        WHERE s.repo_url = $1 AND s.commit_hash = $2
+          AND p.properties->>'reference' IS NOT NULL
+          AND jsonb_typeof(p.properties->'digikey'->'unit_price') = 'number'
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let price = PartPrice {
+                unit_price: row.try_get("unit_price")?,
+                currency: row.try_get("currency")?,
+            };
+            Ok((row.try_get("reference")?, price))
+        })
+        .collect()
+}
+
+/// Unit prices stored by part enrichment for a commit, keyed by reference
+pub async fn part_unit_prices(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+) -> Result<HashMap<String, f64>, Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT p.properties->>'reference' AS reference,
+               (p.properties->'digikey'->>'unit_price')::DOUBLE PRECISION AS unit_price
+        FROM parts p
+        JOIN schematics s ON s.id = p.schematic_id
+        WHERE s.repo_url = $1 AND s.commit_hash = $2
+          AND p.properties->>'reference' IS NOT NULL
+          AND jsonb_typeof(p.properties->'digikey'->'unit_price') = 'number'
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| Ok((row.try_get("reference")?, row.try_get("unit_price")?)))
+        .collect()
+}
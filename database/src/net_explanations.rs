@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// A stored explanation of one net at one commit
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct NetExplanation {
+    pub repo_url: String,
+    pub commit_hash: String,
+    pub net_name: String,
+    /// Hash of the context the explanation was generated from
+    pub context_sha256: String,
+    pub explanation: String,
+    pub model: String,
+    pub prompt_version: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A freshly generated explanation to store
+#[derive(Debug, Clone)]
+pub struct NewNetExplanation<'a> {
+    pub repo_url: &'a str,
+    pub commit_hash: &'a str,
+    pub net_name: &'a str,
+    pub context_sha256: &'a str,
+    pub explanation: &'a str,
+    pub model: &'a str,
+    pub prompt_version: &'a str,
+}
+
+/// Newest explanation of a net generated from the same context by the same
+/// prompt version, at this commit or any other of the repository
+pub async fn find_explanation(
+    pool: &PgPool,
+    repo_url: &str,
+    net_name: &str,
+    context_sha256: &str,
+    prompt_version: &str,
+) -> Result<Option<NetExplanation>, Error> {
+    sqlx::query_as::<_, NetExplanation>(
+        r#"
+        SELECT repo_url, commit_hash, net_name, context_sha256, explanation,
+               model, prompt_version, created_at
+        FROM net_explanations
+        WHERE repo_url = $1 AND net_name = $2 AND context_sha256 = $3 AND prompt_version = $4
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(repo_url)
+    .bind(net_name)
+    .bind(context_sha256)
+    .bind(prompt_version)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Store an explanation, replacing the one of the same net at the same commit
+pub async fn store_explanation(
+    pool: &PgPool,
+    new: &NewNetExplanation<'_>,
+) -> Result<NetExplanation, Error> {
+    sqlx::query_as::<_, NetExplanation>(
+        r#"
+        INSERT INTO net_explanations (
+            repo_url, commit_hash, net_name, context_sha256, explanation, model, prompt_version
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (repo_url, commit_hash, net_name) DO UPDATE SET
+            context_sha256 = EXCLUDED.context_sha256,
+            explanation = EXCLUDED.explanation,
+            model = EXCLUDED.model,
+            prompt_version = EXCLUDED.prompt_version,
+            created_at = CURRENT_TIMESTAMP
+        RETURNING repo_url, commit_hash, net_name, context_sha256, explanation,
+                  model, prompt_version, created_at
+        "#,
+    )
+    .bind(new.repo_url)
+    .bind(new.commit_hash)
+    .bind(new.net_name)
+    .bind(new.context_sha256)
+    .bind(new.explanation)
+    .bind(new.model)
+    .bind(new.prompt_version)
+    .fetch_one(pool)
+    .await
+}
@@ -0,0 +1,169 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, Executor, PgPool, Postgres};
+
+/// An event waiting in (or delivered from) the outbox.
+/// `event_type` and `payload` are interpreted by the relay's consumers.
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct OutboxEvent {
+    pub id: i64,
+    pub event_type: String,
+    pub payload: Value,
+    pub status: String,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+    pub deliver_after: DateTime<Utc>,
+    pub claimed_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Emitted by `store_schematic` when a commit is stored or re-stored
+pub const EVENT_SCHEMATIC_STORED: &str = "schematic.stored";
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_DELIVERING: &str = "delivering";
+pub const STATUS_DELIVERED: &str = "delivered";
+pub const STATUS_FAILED: &str = "failed";
+
+/// Add an event to the outbox, returning its id.
+///
+/// Pass the transaction that makes the state change (`&mut *tx`) so the event
+/// is stored if and only if the change commits.
+pub async fn enqueue_event<'e, E>(
+    executor: E,
+    event_type: &str,
+    payload: &Value,
+) -> Result<i64, Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    sqlx::query_scalar(
+        "INSERT INTO outbox_events (event_type, payload) VALUES ($1, $2) RETURNING id",
+    )
+    .bind(event_type)
+    .bind(payload)
+    .fetch_one(executor)
+    .await
+}
+
+/// Claim up to `limit` deliverable events, oldest first.
+///
+/// Uses `FOR UPDATE SKIP LOCKED` so several relays can poll the same table
+/// without delivering an event twice.
+pub async fn claim_events(pool: &PgPool, limit: i64) -> Result<Vec<OutboxEvent>, Error> {
+    sqlx::query_as::<_, OutboxEvent>(
+        r#"
+        UPDATE outbox_events SET
+            status = 'delivering',
+            attempts = attempts + 1,
+            claimed_at = CURRENT_TIMESTAMP
+        WHERE id IN (
+            SELECT id FROM outbox_events
+            WHERE status = 'pending' AND deliver_after <= CURRENT_TIMESTAMP
+            ORDER BY deliver_after, id
+            FOR UPDATE SKIP LOCKED
+            LIMIT $1
+        )
+        RETURNING *
+        "#,
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Mark an event as delivered
+pub async fn mark_delivered(pool: &PgPool, id: i64) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE outbox_events SET
+            status = 'delivered',
+            last_error = NULL,
+            delivered_at = CURRENT_TIMESTAMP
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Record a failed delivery.
+///
+/// The event is retried after `retry_delay_secs` while it has attempts left,
+/// otherwise it is marked failed for good.
+pub async fn mark_failed(
+    pool: &PgPool,
+    id: i64,
+    error: &str,
+    retry_delay_secs: i64,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE outbox_events SET
+            status = CASE WHEN attempts < max_attempts THEN 'pending' ELSE 'failed' END,
+            deliver_after = CURRENT_TIMESTAMP + make_interval(secs => $3),
+            last_error = $2
+        WHERE id = $1
+        "#,
+    )
+    .bind(id)
+    .bind(error)
+    .bind(retry_delay_secs as f64)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Put events left delivering by a crashed relay back in the outbox.
+/// They may reach consumers twice; delivery is at least once.
+pub async fn requeue_stale_events(pool: &PgPool, stale_after_secs: i64) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE outbox_events SET
+            status = 'pending'
+        WHERE status = 'delivering'
+            AND claimed_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+        "#,
+    )
+    .bind(stale_after_secs as f64)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Delete delivered and permanently failed events older than `older_than_secs`.
+/// With `include_pending`, undelivered events are dropped too (no relay configured).
+pub async fn prune_events(
+    pool: &PgPool,
+    older_than_secs: i64,
+    include_pending: bool,
+) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"
+        DELETE FROM outbox_events
+        WHERE created_at < CURRENT_TIMESTAMP - make_interval(secs => $1)
+            AND (status IN ('delivered', 'failed') OR $2)
+        "#,
+    )
+    .bind(older_than_secs as f64)
+    .bind(include_pending)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Number of events in each status
+pub async fn count_by_status(pool: &PgPool) -> Result<Vec<(String, i64)>, Error> {
+    sqlx::query_as("SELECT status, COUNT(*) FROM outbox_events GROUP BY status ORDER BY status")
+        .fetch_all(pool)
+        .await
+}
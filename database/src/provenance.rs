@@ -0,0 +1,246 @@
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{Error, PgPool};
+
+use crate::canonical::content_hash;
+
+/// A commit's generated blurb/description
+pub const ARTIFACT_SUMMARY: &str = "summary";
+/// Release notes of a commit range
+pub const ARTIFACT_RELEASE_NOTES: &str = "release_notes";
+/// Alternates of a part suggested by the model and approved after a stock check
+pub const ARTIFACT_ALTERNATE: &str = "alternate";
+/// A commit summary reused from another commit with an identical diff;
+/// `artifact_ref` is the id of the original generation's record, or its
+/// `repo_url@commit` when none was written
+pub const ARTIFACT_SUMMARY_REUSE: &str = "summary_reuse";
+/// An explanation of a net's function; `artifact_ref` is the net name
+pub const ARTIFACT_NET_EXPLANATION: &str = "net_explanation";
+/// Suggested questions of a newly onboarded repository; `artifact_ref` is "onboarding"
+pub const ARTIFACT_SUGGESTIONS: &str = "suggestions";
+
+/// A tool the model called while producing an artifact
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ToolCall {
+    pub name: String,
+    pub call_id: Option<String>,
+    pub status: Option<String>,
+    /// Arguments as the model sent them (e.g. a search query)
+    pub input: Option<String>,
+}
+
+/// What went into and came out of one AI artifact. Contents are hashed, not stored.
+#[derive(Debug, Clone)]
+pub struct NewProvenance<'a> {
+    pub artifact: &'a str,
+    pub artifact_ref: &'a str,
+    pub repo_url: Option<&'a str>,
+    pub commit_hash: Option<&'a str>,
+    pub model: &'a str,
+    pub prompt_version: &'a str,
+    /// Full prompt as sent, system prompt included; None for generators without one
+    pub prompt: Option<&'a str>,
+    pub tool_calls: &'a [ToolCall],
+    /// Named inputs (commit message, BOM delta, ...)
+    pub inputs: &'a [(&'a str, &'a [u8])],
+    /// Named outputs as stored
+    pub outputs: &'a [(&'a str, &'a [u8])],
+}
+
+/// One recorded provenance entry
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ProvenanceRecord {
+    pub id: i64,
+    pub artifact: String,
+    pub artifact_ref: String,
+    pub repo_url: Option<String>,
+    pub commit_hash: Option<String>,
+    pub model: String,
+    pub prompt_version: String,
+    pub prompt_sha256: Option<String>,
+    pub tool_calls: Value,
+    pub input_hashes: Value,
+    pub output_hashes: Value,
+    pub created_at: DateTime<Utc>,
+    pub record_sha256: String,
+}
+
+impl ProvenanceRecord {
+    /// SHA-256 over every field but the id and the digest itself
+    pub fn digest(&self) -> String {
+        let canonical = json!({
+            "artifact": self.artifact,
+            "artifact_ref": self.artifact_ref,
+            "repo_url": self.repo_url,
+            "commit_hash": self.commit_hash,
+            "model": self.model,
+            "prompt_version": self.prompt_version,
+            "prompt_sha256": self.prompt_sha256,
+            "tool_calls": self.tool_calls,
+            "input_hashes": self.input_hashes,
+            "output_hashes": self.output_hashes,
+            "created_at": self.created_at.timestamp_micros(),
+        });
+        content_hash(&canonical)
+    }
+
+    /// Whether the record is as it was written
+    pub fn verify(&self) -> bool {
+        self.digest() == self.record_sha256
+    }
+}
+
+/// Narrows `list_provenance`; unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct ProvenanceFilter {
+    pub artifact: Option<String>,
+    pub artifact_ref: Option<String>,
+    pub repo_url: Option<String>,
+    pub commit_hash: Option<String>,
+}
+
+/// SHA-256 (hex) of an input or output as hashed into a record
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn hashes(named: &[(&str, &[u8])]) -> Value {
+    Value::Object(
+        named
+            .iter()
+            .map(|(name, bytes)| (name.to_string(), Value::String(sha256_hex(bytes))))
+            .collect::<Map<_, _>>(),
+    )
+}
+
+const COLUMNS: &str = "id, artifact, artifact_ref, repo_url, commit_hash, model, prompt_version, prompt_sha256, tool_calls, input_hashes, output_hashes, created_at, record_sha256";
+
+/// Record how an artifact was produced
+pub async fn record_provenance(
+    pool: &PgPool,
+    new: &NewProvenance<'_>,
+) -> Result<ProvenanceRecord, Error> {
+    let mut record = ProvenanceRecord {
+        id: 0,
+        artifact: new.artifact.to_string(),
+        artifact_ref: new.artifact_ref.to_string(),
+        repo_url: new.repo_url.map(str::to_string),
+        commit_hash: new.commit_hash.map(str::to_string),
+        model: new.model.to_string(),
+        prompt_version: new.prompt_version.to_string(),
+        prompt_sha256: new.prompt.map(|p| sha256_hex(p.as_bytes())),
+        tool_calls: serde_json::to_value(new.tool_calls).unwrap_or_default(),
+        input_hashes: hashes(new.inputs),
+        output_hashes: hashes(new.outputs),
+        // Postgres keeps microseconds; the digest must survive the round trip
+        created_at: Utc::now().trunc_subsecs(6),
+        record_sha256: String::new(),
+    };
+    record.record_sha256 = record.digest();
+
+    sqlx::query_as::<_, ProvenanceRecord>(&format!(
+        r#"
+        INSERT INTO ai_provenance (
+            artifact, artifact_ref, repo_url, commit_hash, model, prompt_version,
+            prompt_sha256, tool_calls, input_hashes, output_hashes, created_at, record_sha256
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING {}
+        "#,
+        COLUMNS
+    ))
+    .bind(&record.artifact)
+    .bind(&record.artifact_ref)
+    .bind(&record.repo_url)
+    .bind(&record.commit_hash)
+    .bind(&record.model)
+    .bind(&record.prompt_version)
+    .bind(&record.prompt_sha256)
+    .bind(&record.tool_calls)
+    .bind(&record.input_hashes)
+    .bind(&record.output_hashes)
+    .bind(record.created_at)
+    .bind(&record.record_sha256)
+    .fetch_one(pool)
+    .await
+}
+
+/// A provenance record by id
+pub async fn get_provenance(pool: &PgPool, id: i64) -> Result<Option<ProvenanceRecord>, Error> {
+    sqlx::query_as::<_, ProvenanceRecord>(&format!(
+        "SELECT {} FROM ai_provenance WHERE id = $1",
+        COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Provenance records matching `filter`, newest first. Repository URLs match
+/// ignoring case. Replica-safe.
+pub async fn list_provenance(
+    pool: &PgPool,
+    filter: &ProvenanceFilter,
+    limit: i64,
+) -> Result<Vec<ProvenanceRecord>, Error> {
+    sqlx::query_as::<_, ProvenanceRecord>(&format!(
+        r#"
+        SELECT {}
+        FROM ai_provenance
+        WHERE ($1::TEXT IS NULL OR artifact = $1)
+          AND ($2::TEXT IS NULL OR artifact_ref = $2)
+          AND ($3::TEXT IS NULL OR LOWER(repo_url) = LOWER($3))
+          AND ($4::TEXT IS NULL OR commit_hash = $4)
+        ORDER BY created_at DESC, id DESC
+        LIMIT $5
+        "#,
+        COLUMNS
+    ))
+    .bind(filter.artifact.as_deref())
+    .bind(filter.artifact_ref.as_deref())
+    .bind(filter.repo_url.as_deref())
+    .bind(filter.commit_hash.as_deref())
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> ProvenanceRecord {
+        ProvenanceRecord {
+            id: 1,
+            artifact: ARTIFACT_RELEASE_NOTES.to_string(),
+            artifact_ref: "7".to_string(),
+            repo_url: Some("https://github.com/acme/board.git".to_string()),
+            commit_hash: None,
+            model: "grok".to_string(),
+            prompt_version: "v1".to_string(),
+            prompt_sha256: Some(sha256_hex(b"prompt")),
+            tool_calls: json!([]),
+            input_hashes: hashes(&[("commits", b"[]")]),
+            output_hashes: hashes(&[("notes", b"Notes")]),
+            created_at: Utc::now().trunc_subsecs(6),
+            record_sha256: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_digest_detects_edits() {
+        let mut record = record();
+        record.record_sha256 = record.digest();
+        assert!(record.verify());
+
+        let mut edited = record.clone();
+        edited.model = "other".to_string();
+        assert!(!edited.verify());
+
+        let mut edited = record.clone();
+        edited.output_hashes = hashes(&[("notes", b"Edited notes")]);
+        assert!(!edited.verify());
+    }
+}
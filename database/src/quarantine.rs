@@ -0,0 +1,158 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// Failures of one stage on one commit since its last success
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct CommitFailure {
+    pub repo_url: String,
+    pub commit_hash: String,
+    pub stage: String,
+    pub failures: i32,
+    pub last_error: Option<String>,
+    pub first_failed_at: DateTime<Utc>,
+    pub last_failed_at: DateTime<Utc>,
+    /// Set once `failures` reached the limit; the stage skips the commit from then on
+    pub quarantined_at: Option<DateTime<Utc>>,
+}
+
+/// Count a failure of `stage` on a commit, quarantining it once `failures`
+/// reaches `quarantine_after` (0 never quarantines). Returns the updated row.
+pub async fn record_failure(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    stage: &str,
+    error: &str,
+    quarantine_after: i32,
+) -> Result<CommitFailure, Error> {
+    sqlx::query_as::<_, CommitFailure>(
+        r#"
+        INSERT INTO commit_failures (repo_url, commit_hash, stage, failures, last_error, quarantined_at)
+        VALUES ($1, $2, $3, 1, $4, CASE WHEN $5 > 0 AND 1 >= $5 THEN CURRENT_TIMESTAMP END)
+        ON CONFLICT (repo_url, commit_hash, stage) DO UPDATE SET
+            failures = commit_failures.failures + 1,
+            last_error = EXCLUDED.last_error,
+            last_failed_at = CURRENT_TIMESTAMP,
+            quarantined_at = COALESCE(
+                commit_failures.quarantined_at,
+                CASE WHEN $5 > 0 AND commit_failures.failures + 1 >= $5 THEN CURRENT_TIMESTAMP END
+            )
+        RETURNING *
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(stage)
+    .bind(error)
+    .bind(quarantine_after)
+    .fetch_one(pool)
+    .await
+}
+
+/// Forget the failures of `stage` on a commit after it succeeded
+pub async fn clear_failures(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    stage: &str,
+) -> Result<(), Error> {
+    sqlx::query(
+        "DELETE FROM commit_failures WHERE repo_url = $1 AND commit_hash = $2 AND stage = $3",
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(stage)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// The quarantine entry of `stage` on a commit, if it is quarantined
+pub async fn get_quarantined(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    stage: &str,
+) -> Result<Option<CommitFailure>, Error> {
+    sqlx::query_as::<_, CommitFailure>(
+        r#"
+        SELECT * FROM commit_failures
+        WHERE repo_url = $1 AND commit_hash = $2 AND stage = $3
+            AND quarantined_at IS NOT NULL
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(stage)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Commits of a repository quarantined for `stage`
+pub async fn quarantined_commits(
+    pool: &PgPool,
+    repo_url: &str,
+    stage: &str,
+) -> Result<Vec<String>, Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT commit_hash FROM commit_failures
+        WHERE repo_url = $1 AND stage = $2 AND quarantined_at IS NOT NULL
+        "#,
+    )
+    .bind(repo_url)
+    .bind(stage)
+    .fetch_all(pool)
+    .await
+}
+
+/// Quarantined commits, most recently quarantined first, optionally only of
+/// one repository or stage
+pub async fn list_quarantined(
+    pool: &PgPool,
+    repo_url: Option<&str>,
+    stage: Option<&str>,
+    limit: i64,
+) -> Result<Vec<CommitFailure>, Error> {
+    sqlx::query_as::<_, CommitFailure>(
+        r#"
+        SELECT * FROM commit_failures
+        WHERE quarantined_at IS NOT NULL
+            AND ($1::TEXT IS NULL OR LOWER(repo_url) = LOWER($1))
+            AND ($2::TEXT IS NULL OR stage = $2)
+        ORDER BY quarantined_at DESC, repo_url, commit_hash, stage
+        LIMIT $3
+        "#,
+    )
+    .bind(repo_url)
+    .bind(stage)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Release a quarantined commit from `stage`, or from every stage, resetting
+/// its failure count. Returns the released entries.
+pub async fn release(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    stage: Option<&str>,
+) -> Result<Vec<CommitFailure>, Error> {
+    sqlx::query_as::<_, CommitFailure>(
+        r#"
+        DELETE FROM commit_failures
+        WHERE LOWER(repo_url) = LOWER($1) AND commit_hash = $2
+            AND ($3::TEXT IS NULL OR stage = $3)
+            AND quarantined_at IS NOT NULL
+        RETURNING *
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(stage)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+
+/// Stored release notes for a commit range
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ReleaseNotesRecord {
+    pub id: i64,
+    pub repo_url: String,
+    pub from_ref: String,
+    pub to_ref: String,
+    pub from_commit: String,
+    pub to_commit: String,
+    pub commits: Value,
+    pub bom_delta: Value,
+    pub notes: String,
+    pub model: String,
+    pub prompt_version: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fields for new release notes
+#[derive(Debug, Clone)]
+pub struct NewReleaseNotes<'a> {
+    pub repo_url: &'a str,
+    pub from_ref: &'a str,
+    pub to_ref: &'a str,
+    pub from_commit: &'a str,
+    pub to_commit: &'a str,
+    pub commits: &'a Value,
+    pub bom_delta: &'a Value,
+    pub notes: &'a str,
+    pub model: &'a str,
+    pub prompt_version: &'a str,
+}
+
+const COLUMNS: &str = "id, repo_url, from_ref, to_ref, from_commit, to_commit, commits, bom_delta, notes, model, prompt_version, created_at";
+
+/// Store release notes, replacing any earlier notes for the same range
+pub async fn upsert_release_notes(
+    pool: &PgPool,
+    notes: &NewReleaseNotes<'_>,
+) -> Result<ReleaseNotesRecord, Error> {
+    sqlx::query_as::<_, ReleaseNotesRecord>(&format!(
+        r#"
+        INSERT INTO release_notes
+            (repo_url, from_ref, to_ref, from_commit, to_commit, commits, bom_delta, notes, model, prompt_version)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ON CONFLICT (repo_url, from_commit, to_commit) DO UPDATE SET
+            from_ref = EXCLUDED.from_ref,
+            to_ref = EXCLUDED.to_ref,
+            commits = EXCLUDED.commits,
+            bom_delta = EXCLUDED.bom_delta,
+            notes = EXCLUDED.notes,
+            model = EXCLUDED.model,
+            prompt_version = EXCLUDED.prompt_version,
+            created_at = CURRENT_TIMESTAMP
+        RETURNING {}
+        "#,
+        COLUMNS
+    ))
+    .bind(notes.repo_url)
+    .bind(notes.from_ref)
+    .bind(notes.to_ref)
+    .bind(notes.from_commit)
+    .bind(notes.to_commit)
+    .bind(notes.commits)
+    .bind(notes.bom_delta)
+    .bind(notes.notes)
+    .bind(notes.model)
+    .bind(notes.prompt_version)
+    .fetch_one(pool)
+    .await
+}
+
+/// Release notes by id
+pub async fn get_release_notes(
+    pool: &PgPool,
+    id: i64,
+) -> Result<Option<ReleaseNotesRecord>, Error> {
+    sqlx::query_as::<_, ReleaseNotesRecord>(&format!(
+        "SELECT {} FROM release_notes WHERE id = $1",
+        COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Release notes already generated for a commit range
+pub async fn find_release_notes(
+    pool: &PgPool,
+    repo_url: &str,
+    from_commit: &str,
+    to_commit: &str,
+) -> Result<Option<ReleaseNotesRecord>, Error> {
+    sqlx::query_as::<_, ReleaseNotesRecord>(&format!(
+        "SELECT {} FROM release_notes WHERE repo_url = $1 AND from_commit = $2 AND to_commit = $3",
+        COLUMNS
+    ))
+    .bind(repo_url)
+    .bind(from_commit)
+    .bind(to_commit)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Release notes for a repository, newest first
+pub async fn list_release_notes(
+    pool: &PgPool,
+    repo_url: &str,
+    limit: i64,
+) -> Result<Vec<ReleaseNotesRecord>, Error> {
+    sqlx::query_as::<_, ReleaseNotesRecord>(&format!(
+        "SELECT {} FROM release_notes WHERE repo_url = $1 ORDER BY created_at DESC, id DESC LIMIT $2",
+        COLUMNS
+    ))
+    .bind(repo_url)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+use crate::repos::{find_repo, RepoRef};
+
+/// A repository at a glance, as last refreshed
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct RepoCard {
+    pub repo_url: String,
+    /// Newest stored commit by commit date
+    pub last_commit_hash: Option<String>,
+    pub last_commit_date: Option<DateTime<Utc>>,
+    pub last_commit_message: Option<String>,
+    pub commit_count: i32,
+    /// Stored commits with distilled data
+    pub distilled_count: i32,
+    /// Parts of the newest stored commit
+    pub part_count: i32,
+    pub comment_count: i32,
+    /// Blurb of the newest commit that has one not rejected in review,
+    /// the human edit if there is one
+    pub blurb: Option<String>,
+    pub blurb_commit_hash: Option<String>,
+    /// Newest commit with a stored schematic image
+    pub thumbnail_commit_hash: Option<String>,
+    pub refreshed_at: DateTime<Utc>,
+}
+
+/// Recompute a repository's card from its stored commits and comments.
+/// Returns false when no repository matches the URL.
+pub async fn refresh_card(pool: &PgPool, repo_url: &str) -> Result<bool, Error> {
+    let Some(repo) = find_repo(pool, repo_url).await? else {
+        return Ok(false);
+    };
+
+    sqlx::query(
+        r#"
+        WITH latest AS (
+            SELECT id, commit_hash, commit_date, git_message
+            FROM schematics
+            WHERE repo_id = $1
+            ORDER BY commit_date DESC NULLS LAST, id DESC
+            LIMIT 1
+        ), blurb AS (
+            SELECT commit_hash, COALESCE(blurb_override, blurb) AS blurb
+            FROM schematics
+            WHERE repo_id = $1
+              AND COALESCE(blurb_override, blurb) IS NOT NULL
+              AND summary_review_status <> 'rejected'
+            ORDER BY commit_date DESC NULLS LAST, id DESC
+            LIMIT 1
+        ), thumbnail AS (
+            SELECT commit_hash
+            FROM schematics s
+            WHERE repo_id = $1
+              AND EXISTS (
+                  SELECT 1 FROM blobs b
+                  WHERE b.repo_url = s.repo_url AND b.commit_hash = s.commit_hash
+                    AND b.kind = 'schematic_image'
+              )
+            ORDER BY commit_date DESC NULLS LAST, id DESC
+            LIMIT 1
+        )
+        INSERT INTO repo_cards (
+            repo_id, last_commit_hash, last_commit_date, last_commit_message,
+            commit_count, distilled_count, part_count, comment_count,
+            blurb, blurb_commit_hash, thumbnail_commit_hash
+        )
+        SELECT
+            $1,
+            (SELECT commit_hash FROM latest),
+            (SELECT commit_date FROM latest),
+            (SELECT git_message FROM latest),
+            (SELECT COUNT(*) FROM schematics WHERE repo_id = $1),
+            (SELECT COUNT(*) FROM schematics
+             WHERE repo_id = $1 AND (distilled_json IS NOT NULL OR distilled_sha256 IS NOT NULL)),
+            (SELECT COUNT(*) FROM parts p JOIN latest l ON p.schematic_id = l.id),
+            (SELECT COUNT(*) FROM commit_comments WHERE repo_id = $1),
+            (SELECT blurb FROM blurb),
+            (SELECT commit_hash FROM blurb),
+            (SELECT commit_hash FROM thumbnail)
+        ON CONFLICT (repo_id) DO UPDATE SET
+            last_commit_hash = EXCLUDED.last_commit_hash,
+            last_commit_date = EXCLUDED.last_commit_date,
+            last_commit_message = EXCLUDED.last_commit_message,
+            commit_count = EXCLUDED.commit_count,
+            distilled_count = EXCLUDED.distilled_count,
+            part_count = EXCLUDED.part_count,
+            comment_count = EXCLUDED.comment_count,
+            blurb = EXCLUDED.blurb,
+            blurb_commit_hash = EXCLUDED.blurb_commit_hash,
+            thumbnail_commit_hash = EXCLUDED.thumbnail_commit_hash,
+            refreshed_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(repo.id)
+    .execute(pool)
+    .await?;
+
+    Ok(true)
+}
+
+/// A repository's card, if it was ever refreshed. Replica-safe.
+pub async fn get_card(pool: &PgPool, repo_url: &str) -> Result<Option<RepoCard>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, RepoCard>(
+        r#"
+        SELECT r.repo_url, c.last_commit_hash, c.last_commit_date, c.last_commit_message,
+               c.commit_count, c.distilled_count, c.part_count, c.comment_count,
+               c.blurb, c.blurb_commit_hash, c.thumbnail_commit_hash, c.refreshed_at
+        FROM repo_cards c
+        JOIN repos r ON r.id = c.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .fetch_optional(pool)
+    .await
+}
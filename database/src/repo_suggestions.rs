@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+
+use crate::repos::{find_repo, RepoRef};
+
+/// Suggested questions of a repository, as stored
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct RepoSuggestions {
+    pub repo_url: String,
+    /// Commit the suggestions were generated from
+    pub commit_hash: String,
+    /// `[{"question", "topic", "sheet", "references"}]`
+    pub suggestions: Value,
+    pub model: String,
+    pub prompt_version: String,
+    pub generated_at: DateTime<Utc>,
+}
+
+/// Freshly generated suggestions to store
+#[derive(Debug, Clone)]
+pub struct NewRepoSuggestions<'a> {
+    pub commit_hash: &'a str,
+    pub suggestions: &'a Value,
+    pub model: &'a str,
+    pub prompt_version: &'a str,
+}
+
+/// Store a repository's suggestions, replacing earlier ones.
+/// Returns false when no repository matches the URL.
+pub async fn store_suggestions(
+    pool: &PgPool,
+    repo_url: &str,
+    new: &NewRepoSuggestions<'_>,
+) -> Result<bool, Error> {
+    let Some(repo) = find_repo(pool, repo_url).await? else {
+        return Ok(false);
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO repo_suggestions (repo_id, commit_hash, suggestions, model, prompt_version)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (repo_id) DO UPDATE SET
+            commit_hash = EXCLUDED.commit_hash,
+            suggestions = EXCLUDED.suggestions,
+            model = EXCLUDED.model,
+            prompt_version = EXCLUDED.prompt_version,
+            generated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(repo.id)
+    .bind(new.commit_hash)
+    .bind(new.suggestions)
+    .bind(new.model)
+    .bind(new.prompt_version)
+    .execute(pool)
+    .await?;
+
+    Ok(true)
+}
+
+/// A repository's suggestions, if any were generated. Replica-safe.
+pub async fn get_suggestions(
+    pool: &PgPool,
+    repo_url: &str,
+) -> Result<Option<RepoSuggestions>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, RepoSuggestions>(
+        r#"
+        SELECT r.repo_url, s.commit_hash, s.suggestions, s.model, s.prompt_version,
+               s.generated_at
+        FROM repo_suggestions s
+        JOIN repos r ON r.id = s.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .fetch_optional(pool)
+    .await
+}
@@ -0,0 +1,287 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, Executor, PgPool, Postgres};
+
+/// A repository identity parsed from a clone URL: the host and the owner/name
+/// path. Slugs keep their case but compare case-insensitively, as on GitHub.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoRef {
+    pub provider: String,
+    pub slug: String,
+}
+
+impl RepoRef {
+    /// Parse `https://github.com/owner/name(.git)`, `git@github.com:owner/name.git`,
+    /// `ssh://git@host:22/owner/name` and similar. Returns None for anything without
+    /// a host and at least an owner and a name.
+    pub fn parse(repo_url: &str) -> Option<Self> {
+        let url = repo_url.trim();
+        let (host, path) = match url.split_once("://") {
+            Some((_, rest)) => {
+                let (authority, path) = rest.split_once('/')?;
+                let host = authority.rsplit('@').next()?;
+                (host.split(':').next()?, path)
+            }
+            // scp-like syntax: user@host:owner/name
+            None => {
+                let (authority, path) = url.split_once(':')?;
+                (authority.rsplit('@').next()?, path)
+            }
+        };
+
+        let path = path.trim_matches('/');
+        let slug = path.strip_suffix(".git").unwrap_or(path);
+        let segments: Vec<&str> = slug.split('/').collect();
+        if host.is_empty() || segments.len() < 2 || segments.iter().any(|s| s.is_empty()) {
+            return None;
+        }
+
+        Some(Self {
+            provider: host.to_lowercase(),
+            slug: slug.to_string(),
+        })
+    }
+
+    /// Canonical clone URL, the form stored in `repo_url` columns
+    pub fn url(&self) -> String {
+        format!("https://{}/{}.git", self.provider, self.slug)
+    }
+}
+
+/// A tracked repository
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct Repo {
+    pub id: i32,
+    pub provider: String,
+    pub slug: String,
+    pub repo_url: String,
+    pub created_at: DateTime<Utc>,
+    /// Whether processed commits get a GitHub commit status
+    pub commit_status: bool,
+}
+
+/// A repository with counts of its stored commits
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct RepoSummary {
+    pub id: i32,
+    pub provider: String,
+    pub slug: String,
+    pub repo_url: String,
+    pub created_at: DateTime<Utc>,
+    pub commit_status: bool,
+    pub commit_count: i64,
+    pub last_commit_date: Option<DateTime<Utc>>,
+}
+
+/// Id of the repository a URL refers to, creating the row on first sight.
+/// None when the URL can't be parsed; such rows are left without a `repo_id`.
+///
+/// Takes any executor so writers can call it inside their own transaction.
+pub async fn ensure_repo_id<'e, E>(executor: E, repo_url: &str) -> Result<Option<i32>, Error>
+where
+    E: Executor<'e, Database = Postgres>,
+{
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(None);
+    };
+
+    // The no-op update makes RETURNING yield the existing row on conflict
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO repos (provider, slug, repo_url)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (provider, (LOWER(slug))) DO UPDATE SET
+            provider = EXCLUDED.provider
+        RETURNING id
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(repo.url())
+    .fetch_one(executor)
+    .await
+    .map(Some)
+}
+
+/// Look up a repository by any URL form that refers to it
+pub async fn find_repo(pool: &PgPool, repo_url: &str) -> Result<Option<Repo>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, Repo>(
+        r#"
+        SELECT id, provider, slug, repo_url, created_at, commit_status
+        FROM repos
+        WHERE provider = $1 AND LOWER(slug) = LOWER($2)
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .fetch_optional(pool)
+    .await
+}
+
+/// All repositories with their stored commit counts, most recently active first
+pub async fn list_repos(pool: &PgPool) -> Result<Vec<RepoSummary>, Error> {
+    sqlx::query_as::<_, RepoSummary>(
+        r#"
+        SELECT
+            r.id, r.provider, r.slug, r.repo_url, r.created_at, r.commit_status,
+            COUNT(s.id) AS commit_count,
+            MAX(s.commit_date) AS last_commit_date
+        FROM repos r
+        LEFT JOIN schematics s ON s.repo_id = r.id
+        GROUP BY r.id
+        ORDER BY MAX(s.commit_date) DESC NULLS LAST, r.slug
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Outcome of renaming a repository
+#[derive(Debug, Clone)]
+pub enum RenameOutcome {
+    Renamed(Repo),
+    /// No repository matches the old URL
+    NotFound,
+    /// The new URL is unparseable or already belongs to another repository
+    Conflict,
+}
+
+/// Move a repository to a new URL (a GitHub rename or transfer, or a move to
+/// another provider). The repo row is updated in place and every table keyed
+/// by URL is rewritten in the same transaction, so stored data stays attached.
+pub async fn rename_repo(
+    pool: &PgPool,
+    from_url: &str,
+    to_url: &str,
+) -> Result<RenameOutcome, Error> {
+    let Some(from) = find_repo(pool, from_url).await? else {
+        return Ok(RenameOutcome::NotFound);
+    };
+    let Some(to) = RepoRef::parse(to_url) else {
+        return Ok(RenameOutcome::Conflict);
+    };
+    if let Some(existing) = find_repo(pool, &to.url()).await? {
+        if existing.id != from.id {
+            return Ok(RenameOutcome::Conflict);
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let renamed = sqlx::query_as::<_, Repo>(
+        r#"
+        UPDATE repos SET provider = $2, slug = $3, repo_url = $4
+        WHERE id = $1
+        RETURNING id, provider, slug, repo_url, created_at, commit_status
+        "#,
+    )
+    .bind(from.id)
+    .bind(&to.provider)
+    .bind(&to.slug)
+    .bind(to.url())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE schematics SET repo_url = $2 WHERE repo_id = $1")
+        .bind(from.id)
+        .bind(&renamed.repo_url)
+        .execute(&mut *tx)
+        .await?;
+    for table in ["blobs", "feedback", "release_notes"] {
+        sqlx::query(&format!(
+            "UPDATE {} SET repo_url = $2 WHERE repo_url = $1",
+            table
+        ))
+        .bind(&from.repo_url)
+        .bind(&renamed.repo_url)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(RenameOutcome::Renamed(renamed))
+}
+
+/// Turn GitHub commit statuses on or off for a repository. Returns the
+/// updated repository, or None when it isn't tracked.
+pub async fn set_commit_status(
+    pool: &PgPool,
+    repo_url: &str,
+    enabled: bool,
+) -> Result<Option<Repo>, Error> {
+    let Some(repo) = find_repo(pool, repo_url).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, Repo>(
+        r#"
+        UPDATE repos SET commit_status = $2
+        WHERE id = $1
+        RETURNING id, provider, slug, repo_url, created_at, commit_status
+        "#,
+    )
+    .bind(repo.id)
+    .bind(enabled)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Attach schematics stored before `repos` existed to their repository.
+/// Returns the number of schematics updated; URLs that can't be parsed are skipped.
+pub async fn backfill_repo_ids(pool: &PgPool) -> Result<u64, Error> {
+    let urls: Vec<String> =
+        sqlx::query_scalar("SELECT DISTINCT repo_url FROM schematics WHERE repo_id IS NULL")
+            .fetch_all(pool)
+            .await?;
+
+    let mut updated = 0;
+    for repo_url in urls {
+        let Some(repo_id) = ensure_repo_id(pool, &repo_url).await? else {
+            continue;
+        };
+        updated += sqlx::query(
+            "UPDATE schematics SET repo_id = $2 WHERE repo_url = $1 AND repo_id IS NULL",
+        )
+        .bind(&repo_url)
+        .bind(repo_id)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_repo_urls() {
+        let expected = Some(RepoRef {
+            provider: "github.com".to_string(),
+            slug: "Owner/Board".to_string(),
+        });
+        assert_eq!(
+            RepoRef::parse("https://github.com/Owner/Board.git"),
+            expected
+        );
+        assert_eq!(RepoRef::parse("https://GitHub.com/Owner/Board/"), expected);
+        assert_eq!(RepoRef::parse("git@github.com:Owner/Board.git"), expected);
+        assert_eq!(
+            RepoRef::parse("ssh://git@github.com:22/Owner/Board"),
+            expected
+        );
+
+        let nested = RepoRef::parse("https://gitlab.com/group/sub/board.git").unwrap();
+        assert_eq!(nested.slug, "group/sub/board");
+        assert_eq!(nested.url(), "https://gitlab.com/group/sub/board.git");
+
+        assert_eq!(RepoRef::parse("https://github.com/owner"), None);
+        assert_eq!(RepoRef::parse("not a url"), None);
+        assert_eq!(RepoRef::parse(""), None);
+    }
+}
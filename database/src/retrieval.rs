@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+use crate::repos::{ensure_repo_id, RepoRef};
+
+/// A retrievable document of a commit's design
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct RetrievalDocument {
+    pub id: i64,
+    /// "component", "net", "sheet", "summary" or "comment"
+    pub category: String,
+    /// Reference, net name, sheet path, summary part or comment id
+    pub key: String,
+    pub sheet: Option<String>,
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+/// Fields of a document to index
+#[derive(Debug, Clone)]
+pub struct NewDocument<'a> {
+    pub category: &'a str,
+    pub key: &'a str,
+    pub sheet: Option<&'a str>,
+    pub text: &'a str,
+    pub embedding: &'a [f32],
+}
+
+/// When and how a commit was indexed
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct RetrievalIndex {
+    pub version: String,
+    pub documents: i32,
+    pub indexed_at: DateTime<Utc>,
+}
+
+/// Documents a query is limited to, besides its repository and commit
+#[derive(Debug, Clone, Default)]
+pub struct DocumentFilter<'a> {
+    /// Documents of this sheet only
+    pub sheet: Option<&'a str>,
+    /// Documents of these categories only; all when empty
+    pub categories: &'a [&'a str],
+}
+
+/// Replace a commit's documents with `documents`, built under `version`.
+/// Returns false when the repository URL can't be parsed.
+pub async fn replace_documents(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    version: &str,
+    documents: &[NewDocument<'_>],
+) -> Result<bool, Error> {
+    let mut tx = pool.begin().await?;
+    let Some(repo_id) = ensure_repo_id(&mut *tx, repo_url).await? else {
+        return Ok(false);
+    };
+
+    sqlx::query("DELETE FROM retrieval_documents WHERE repo_id = $1 AND commit_hash = $2")
+        .bind(repo_id)
+        .bind(commit_hash)
+        .execute(&mut *tx)
+        .await?;
+    for document in documents {
+        sqlx::query(
+            r#"
+            INSERT INTO retrieval_documents
+                (repo_id, commit_hash, category, key, sheet, text, embedding)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(repo_id)
+        .bind(commit_hash)
+        .bind(document.category)
+        .bind(document.key)
+        .bind(document.sheet)
+        .bind(document.text)
+        .bind(document.embedding)
+        .execute(&mut *tx)
+        .await?;
+    }
+    sqlx::query(
+        r#"
+        INSERT INTO retrieval_indexes (repo_id, commit_hash, version, documents)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (repo_id, commit_hash) DO UPDATE SET
+            version = EXCLUDED.version,
+            documents = EXCLUDED.documents,
+            indexed_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(repo_id)
+    .bind(commit_hash)
+    .bind(version)
+    .bind(documents.len() as i32)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// How a commit was indexed, if it was. Replica-safe.
+pub async fn get_index(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+) -> Result<Option<RetrievalIndex>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, RetrievalIndex>(
+        r#"
+        SELECT i.version, i.documents, i.indexed_at
+        FROM retrieval_indexes i
+        JOIN repos r ON r.id = i.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2) AND i.commit_hash = $3
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(commit_hash)
+    .fetch_optional(pool)
+    .await
+}
+
+/// A commit's documents matching `filter`, in the order they were indexed.
+/// Replica-safe.
+pub async fn list_documents(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    filter: &DocumentFilter<'_>,
+) -> Result<Vec<RetrievalDocument>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+    let categories: Vec<String> = filter.categories.iter().map(|c| c.to_string()).collect();
+
+    sqlx::query_as::<_, RetrievalDocument>(
+        r#"
+        SELECT d.id, d.category, d.key, d.sheet, d.text, d.embedding
+        FROM retrieval_documents d
+        JOIN repos r ON r.id = d.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2) AND d.commit_hash = $3
+          AND ($4::TEXT IS NULL OR d.sheet = $4)
+          AND (cardinality($5::TEXT[]) = 0 OR d.category = ANY($5))
+        ORDER BY d.id
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(commit_hash)
+    .bind(filter.sheet)
+    .bind(&categories)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+
+/// Risk score of one commit
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct CommitRiskRecord {
+    pub commit_hash: String,
+    pub score: i32,
+    pub factors: Value,
+    pub scorer_version: String,
+    pub computed_at: DateTime<Utc>,
+}
+
+/// Store the risk score of an indexed commit, replacing an earlier one.
+/// Returns false when the commit has no schematics row.
+pub async fn upsert_risk(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    score: i32,
+    factors: &Value,
+    scorer_version: &str,
+) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO commit_risk (schematic_id, score, factors, scorer_version)
+        SELECT id, $3, $4, $5
+        FROM schematics
+        WHERE repo_url = $1 AND commit_hash = $2
+        ON CONFLICT (schematic_id) DO UPDATE SET
+            score = EXCLUDED.score,
+            factors = EXCLUDED.factors,
+            scorer_version = EXCLUDED.scorer_version,
+            computed_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(score)
+    .bind(factors)
+    .bind(scorer_version)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Risk scores of a repository's commits, riskiest first
+pub async fn risk_scores(pool: &PgPool, repo_url: &str) -> Result<Vec<CommitRiskRecord>, Error> {
+    sqlx::query_as::<_, CommitRiskRecord>(
+        r#"
+        SELECT s.commit_hash, r.score, r.factors, r.scorer_version, r.computed_at
+        FROM commit_risk r
+        JOIN schematics s ON s.id = r.schematic_id
+        WHERE s.repo_url = $1
+        ORDER BY r.score DESC, s.commit_date DESC NULLS LAST
+        "#,
+    )
+    .bind(repo_url)
+    .fetch_all(pool)
+    .await
+}
+
+/// Indexed commits with no risk score from `scorer_version`, newest first
+pub async fn commits_without_risk(
+    pool: &PgPool,
+    repo_url: &str,
+    scorer_version: &str,
+    limit: i64,
+) -> Result<Vec<String>, Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT s.commit_hash
+        FROM schematics s
+        LEFT JOIN commit_risk r ON r.schematic_id = s.id
+        WHERE s.repo_url = $1 AND (r.schematic_id IS NULL OR r.scorer_version <> $2)
+        ORDER BY s.commit_date DESC NULLS LAST
+        LIMIT $3
+        "#,
+    )
+    .bind(repo_url)
+    .bind(scorer_version)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,177 @@
+use sqlx::{Error, PgPool};
+use std::collections::HashSet;
+
+/// The schema script the database is initialised and upgraded with
+pub const INIT_SQL: &str = include_str!("../init.sql");
+
+// Leading words of table constraints in a CREATE TABLE body
+const CONSTRAINT_KEYWORDS: &[&str] = &[
+    "PRIMARY",
+    "UNIQUE",
+    "FOREIGN",
+    "CONSTRAINT",
+    "CHECK",
+    "EXCLUDE",
+];
+
+/// Split on commas outside parentheses
+fn split_top_level(body: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    for (i, c) in body.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&body[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&body[start..]);
+    parts
+}
+
+/// Postgres folds unquoted names to lowercase
+fn identifier(word: &str) -> String {
+    match word.strip_prefix('"').and_then(|w| w.strip_suffix('"')) {
+        Some(quoted) => quoted.to_string(),
+        None => word.to_lowercase(),
+    }
+}
+
+/// (table, column) pairs created by a schema script: the columns of each
+/// `CREATE TABLE` and every `ALTER TABLE ... ADD COLUMN`
+pub fn expected_columns(sql: &str) -> Vec<(String, String)> {
+    let without_comments: String = sql
+        .lines()
+        .map(|line| line.split("--").next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut columns = Vec::new();
+    for statement in without_comments.split(';') {
+        let words: Vec<&str> = statement.split_whitespace().collect();
+        let upper: Vec<String> = words.iter().map(|w| w.to_uppercase()).collect();
+        let upper: Vec<&str> = upper.iter().map(String::as_str).collect();
+        match upper.as_slice() {
+            ["CREATE", "TABLE", ..] => {
+                let name_at = if upper.get(2..5) == Some(&["IF", "NOT", "EXISTS"]) {
+                    5
+                } else {
+                    2
+                };
+                let (Some(name), Some(open), Some(close)) = (
+                    words.get(name_at),
+                    statement.find('('),
+                    statement.rfind(')'),
+                ) else {
+                    continue;
+                };
+                let table = identifier(name.split('(').next().unwrap_or(name));
+                for definition in split_top_level(&statement[open + 1..close]) {
+                    let Some(first) = definition
+                        .split(|c: char| c.is_whitespace() || c == '(')
+                        .find(|w| !w.is_empty())
+                    else {
+                        continue;
+                    };
+                    if !CONSTRAINT_KEYWORDS.contains(&first.to_uppercase().as_str()) {
+                        columns.push((table.clone(), identifier(first)));
+                    }
+                }
+            }
+            ["ALTER", "TABLE", ..] => {
+                let Some(table) = words.get(2).map(|w| identifier(w)) else {
+                    continue;
+                };
+                for (i, window) in upper.windows(2).enumerate() {
+                    if window != ["ADD", "COLUMN"] {
+                        continue;
+                    }
+                    let name_at = if upper.get(i + 2..i + 5) == Some(&["IF", "NOT", "EXISTS"]) {
+                        i + 5
+                    } else {
+                        i + 2
+                    };
+                    if let Some(column) = words.get(name_at) {
+                        columns.push((table.clone(), identifier(column)));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    columns
+}
+
+/// Columns of `init.sql` missing from the database, as "table.column", i.e.
+/// schema changes that haven't been applied yet. Empty when it's up to date.
+pub async fn missing_columns(pool: &PgPool) -> Result<Vec<String>, Error> {
+    let existing: Vec<(String, String)> = sqlx::query_as(
+        r#"
+        SELECT table_name::TEXT, column_name::TEXT FROM information_schema.columns
+        WHERE table_schema = current_schema()
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+    let existing: HashSet<(String, String)> = existing.into_iter().collect();
+
+    let mut missing = Vec::new();
+    for (table, column) in expected_columns(INIT_SQL) {
+        let name = format!("{}.{}", table, column);
+        if !existing.contains(&(table, column)) && !missing.contains(&name) {
+            missing.push(name);
+        }
+    }
+    Ok(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expected_columns() {
+        let sql = r#"
+            -- Things; with a comment
+            CREATE TABLE IF NOT EXISTS things (
+                id SERIAL PRIMARY KEY,
+                "Name" TEXT NOT NULL CHECK (length("Name") > 0),
+                price NUMERIC(10, 2),
+                UNIQUE(id, "Name"),
+                PRIMARY KEY (id)
+            );
+            CREATE INDEX IF NOT EXISTS things_idx ON things (price);
+            ALTER TABLE things ADD COLUMN IF NOT EXISTS note TEXT DEFAULT 'a, b';
+            alter table things add column extra INTEGER;
+        "#;
+        let columns: Vec<String> = expected_columns(sql)
+            .into_iter()
+            .map(|(table, column)| format!("{}.{}", table, column))
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                "things.id",
+                "things.Name",
+                "things.price",
+                "things.note",
+                "things.extra"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_init_sql_parses() {
+        let columns = expected_columns(INIT_SQL);
+        assert!(columns.contains(&("schematics".to_string(), "distilled_json".to_string())));
+        assert!(columns.contains(&("jobs".to_string(), "progress".to_string())));
+        assert!(columns.contains(&("commit_failures".to_string(), "quarantined_at".to_string())));
+        assert!(!columns
+            .iter()
+            .any(|(_, column)| column == "primary" || column == "unique"));
+    }
+}
@@ -0,0 +1,511 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Error, PgPool};
+
+/// Base64-encoded 32-byte key new secrets are sealed with
+pub const KEY_VAR: &str = "SECRETS_ENCRYPTION_KEY";
+/// File holding the active key instead, as mounted by a KMS or secret manager
+pub const KEY_FILE_VAR: &str = "SECRETS_ENCRYPTION_KEY_FILE";
+/// Comma-separated base64 keys that still open secrets sealed before a rotation
+pub const PREVIOUS_KEYS_VAR: &str = "SECRETS_PREVIOUS_KEYS";
+/// The active key's variable before keys could rotate; still read when the others are unset
+pub const LEGACY_KEY_VAR: &str = "CREDENTIALS_ENCRYPTION_KEY";
+
+/// Failure to seal or open a secret
+#[derive(Debug)]
+pub enum SecretError {
+    /// No active key is configured
+    NotConfigured,
+    /// A configured key isn't 32 bytes of base64
+    InvalidKey(String),
+    /// Sealed under a key that isn't configured (any more)
+    UnknownKey(String),
+    /// Encrypting failed, or the ciphertext doesn't open with its key
+    Crypto(&'static str),
+    Database(Error),
+}
+
+impl std::fmt::Display for SecretError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretError::NotConfigured => write!(
+                f,
+                "No encryption key is configured; set {} to a base64-encoded 32-byte key",
+                KEY_VAR
+            ),
+            SecretError::InvalidKey(e) => write!(f, "{}", e),
+            SecretError::UnknownKey(id) => write!(
+                f,
+                "Sealed with key {}, which isn't configured; add it to {}",
+                id, PREVIOUS_KEYS_VAR
+            ),
+            SecretError::Crypto(e) => write!(f, "{}", e),
+            SecretError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for SecretError {}
+
+impl From<Error> for SecretError {
+    fn from(e: Error) -> Self {
+        SecretError::Database(e)
+    }
+}
+
+struct Key {
+    id: String,
+    key: LessSafeKey,
+    /// For deriving keys for other purposes from this one
+    derivation: hmac::Key,
+}
+
+impl Key {
+    fn new(bytes: &[u8]) -> Option<Self> {
+        let key = UnboundKey::new(&AES_256_GCM, bytes).ok()?;
+        Some(Self {
+            id: key_id(bytes),
+            key: LessSafeKey::new(key),
+            derivation: hmac::Key::new(hmac::HMAC_SHA256, bytes),
+        })
+    }
+
+    fn from_base64(name: &str, encoded: &str) -> Result<Self, SecretError> {
+        BASE64
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|bytes| Self::new(&bytes))
+            .ok_or_else(|| {
+                SecretError::InvalidKey(format!("{} must be 32 bytes, base64-encoded", name))
+            })
+    }
+}
+
+/// Id of a key: the first 8 bytes of its SHA-256, in hex. Derived rather than
+/// configured, so an id can't be given to the wrong key.
+fn key_id(bytes: &[u8]) -> String {
+    hex::encode(&Sha256::digest(bytes)[..8])
+}
+
+/// A secret sealed with AES-256-GCM
+#[derive(Debug, Clone)]
+pub struct Sealed {
+    pub key_id: String,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Encryption keys for secrets at rest. The active key seals new secrets;
+/// it and the previous keys open them. To rotate, make the new key active,
+/// list the old one as previous, run `rotate`, then drop the old key.
+pub struct Keyring {
+    active: Key,
+    previous: Vec<Key>,
+}
+
+impl Keyring {
+    /// A keyring from raw 32-byte keys
+    pub fn new(active: &[u8], previous: &[&[u8]]) -> Result<Self, SecretError> {
+        let invalid = || SecretError::InvalidKey("Encryption keys must be 32 bytes".to_string());
+        Ok(Self {
+            active: Key::new(active).ok_or_else(invalid)?,
+            previous: previous
+                .iter()
+                .map(|bytes| Key::new(bytes).ok_or_else(invalid))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// The keyring configured by `SECRETS_ENCRYPTION_KEY` (or
+    /// `SECRETS_ENCRYPTION_KEY_FILE`, or `CREDENTIALS_ENCRYPTION_KEY`) and
+    /// `SECRETS_PREVIOUS_KEYS`
+    pub fn from_env() -> Result<Self, SecretError> {
+        let set = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        let active = if let Some(encoded) = set(KEY_VAR) {
+            Key::from_base64(KEY_VAR, &encoded)?
+        } else if let Some(path) = set(KEY_FILE_VAR) {
+            let encoded = std::fs::read_to_string(path.trim()).map_err(|e| {
+                SecretError::InvalidKey(format!("Failed to read {}: {}", KEY_FILE_VAR, e))
+            })?;
+            Key::from_base64(KEY_FILE_VAR, &encoded)?
+        } else if let Some(encoded) = set(LEGACY_KEY_VAR) {
+            Key::from_base64(LEGACY_KEY_VAR, &encoded)?
+        } else {
+            return Err(SecretError::NotConfigured);
+        };
+
+        let previous = set(PREVIOUS_KEYS_VAR)
+            .unwrap_or_default()
+            .split(',')
+            .filter(|k| !k.trim().is_empty())
+            .map(|k| Key::from_base64(PREVIOUS_KEYS_VAR, k))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { active, previous })
+    }
+
+    /// Id of the key new secrets are sealed with
+    pub fn active_key_id(&self) -> &str {
+        &self.active.id
+    }
+
+    /// A 32-byte key for `purpose` derived from the active key, so other
+    /// features can share this key's configuration without reusing it directly.
+    /// Changes when the active key is rotated.
+    pub fn derive_key(&self, purpose: &str) -> Vec<u8> {
+        hmac::sign(&self.active.derivation, purpose.as_bytes())
+            .as_ref()
+            .to_vec()
+    }
+
+    /// Ids of the keys kept to open older secrets
+    pub fn previous_key_ids(&self) -> Vec<&str> {
+        self.previous.iter().map(|k| k.id.as_str()).collect()
+    }
+
+    /// Seal a secret with the active key. `context` (e.g. the secret's name)
+    /// is authenticated with it, so the ciphertext only opens under the same one.
+    pub fn seal(&self, context: &str, plaintext: &str) -> Result<Sealed, SecretError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| SecretError::Crypto("Failed to generate a nonce"))?;
+
+        let mut in_out = plaintext.as_bytes().to_vec();
+        self.active
+            .key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(context.as_bytes()),
+                &mut in_out,
+            )
+            .map_err(|_| SecretError::Crypto("Failed to encrypt secret"))?;
+
+        Ok(Sealed {
+            key_id: self.active.id.clone(),
+            nonce: nonce.to_vec(),
+            ciphertext: in_out,
+        })
+    }
+
+    /// Open a secret sealed with `context`. Without a key id (secrets stored
+    /// before keys had ids) every key is tried.
+    pub fn open(
+        &self,
+        context: &str,
+        key_id: Option<&str>,
+        nonce: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<String, SecretError> {
+        let keys: Vec<&Key> = std::iter::once(&self.active)
+            .chain(&self.previous)
+            .filter(|k| key_id.is_none_or(|id| k.id == id))
+            .collect();
+        if let (Some(id), []) = (key_id, keys.as_slice()) {
+            return Err(SecretError::UnknownKey(id.to_string()));
+        }
+
+        for key in keys {
+            let Ok(nonce) = Nonce::try_assume_unique_for_key(nonce) else {
+                return Err(SecretError::Crypto("Invalid nonce length"));
+            };
+            let mut in_out = ciphertext.to_vec();
+            if let Ok(plaintext) =
+                key.key
+                    .open_in_place(nonce, Aad::from(context.as_bytes()), &mut in_out)
+            {
+                return String::from_utf8(plaintext.to_vec())
+                    .map_err(|_| SecretError::Crypto("Decrypted secret is not valid UTF-8"));
+            }
+        }
+        Err(SecretError::Crypto("Failed to decrypt secret (wrong key?)"))
+    }
+}
+
+// Secrets of the `secrets` table are bound to their name, prefixed so they
+// can't be swapped with a provider credential of the same name
+fn secret_context(name: &str) -> String {
+    format!("secret/{}", name)
+}
+
+/// A stored secret, without its value
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct SecretInfo {
+    pub name: String,
+    pub key_id: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SealedRow {
+    name: String,
+    key_id: Option<String>,
+    nonce: Vec<u8>,
+    ciphertext: Vec<u8>,
+}
+
+/// Channel the `secrets_notify_change` trigger notifies after every change to
+/// the `secrets` table, so processes caching its rows know to reload them
+pub const CHANGED_CHANNEL: &str = "secrets_changed";
+
+/// A stored secret as it is kept in the table, still sealed
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct SealedSecret {
+    pub name: String,
+    pub key_id: Option<String>,
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SealedSecret {
+    /// The secret's value
+    pub fn open(&self, keyring: &Keyring) -> Result<String, SecretError> {
+        keyring.open(
+            &secret_context(&self.name),
+            self.key_id.as_deref(),
+            &self.nonce,
+            &self.ciphertext,
+        )
+    }
+}
+
+/// Seal and store a secret, replacing one of the same name
+pub async fn put_secret(
+    pool: &PgPool,
+    keyring: &Keyring,
+    name: &str,
+    value: &str,
+) -> Result<(), SecretError> {
+    let sealed = keyring.seal(&secret_context(name), value)?;
+    sqlx::query(
+        r#"
+        INSERT INTO secrets (name, key_id, nonce, ciphertext)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (name) DO UPDATE SET
+            key_id = EXCLUDED.key_id,
+            nonce = EXCLUDED.nonce,
+            ciphertext = EXCLUDED.ciphertext,
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(name)
+    .bind(&sealed.key_id)
+    .bind(&sealed.nonce)
+    .bind(&sealed.ciphertext)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// A stored secret's value, if there is one
+pub async fn get_secret(
+    pool: &PgPool,
+    keyring: &Keyring,
+    name: &str,
+) -> Result<Option<String>, SecretError> {
+    let row = sqlx::query_as::<_, SealedRow>(
+        "SELECT name, key_id, nonce, ciphertext FROM secrets WHERE name = $1",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+
+    row.map(|r| {
+        keyring.open(
+            &secret_context(&r.name),
+            r.key_id.as_deref(),
+            &r.nonce,
+            &r.ciphertext,
+        )
+    })
+    .transpose()
+}
+
+/// Remove a stored secret
+pub async fn delete_secret(pool: &PgPool, name: &str) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM secrets WHERE name = $1")
+        .bind(name)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Every stored secret, by name
+pub async fn list_secrets(pool: &PgPool) -> Result<Vec<SecretInfo>, Error> {
+    sqlx::query_as::<_, SecretInfo>("SELECT name, key_id, updated_at FROM secrets ORDER BY name")
+        .fetch_all(pool)
+        .await
+}
+
+/// Every stored secret without opening it, by name
+pub async fn list_sealed(pool: &PgPool) -> Result<Vec<SealedSecret>, Error> {
+    sqlx::query_as::<_, SealedSecret>(
+        "SELECT name, key_id, nonce, ciphertext, updated_at FROM secrets ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Outcome of `rotate`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rotation {
+    /// Secrets and provider credentials resealed under the active key
+    pub secrets: u64,
+    pub credentials: u64,
+    /// Ones that didn't open with any configured key and were left as they
+    /// were, as "secret:<name>" or "credential:<provider>"
+    pub failed: Vec<String>,
+}
+
+/// Reseal every stored secret and provider credential that isn't under the
+/// active key. Ones that don't open are reported rather than stopping the rest.
+pub async fn rotate(pool: &PgPool, keyring: &Keyring) -> Result<Rotation, SecretError> {
+    let active = keyring.active_key_id();
+    let mut tx = pool.begin().await?;
+    let mut rotation = Rotation::default();
+
+    let stale = sqlx::query_as::<_, SealedRow>(
+        "SELECT name, key_id, nonce, ciphertext FROM secrets WHERE key_id <> $1 FOR UPDATE",
+    )
+    .bind(active)
+    .fetch_all(&mut *tx)
+    .await?;
+    for row in stale {
+        let context = secret_context(&row.name);
+        let Ok(value) = keyring.open(&context, row.key_id.as_deref(), &row.nonce, &row.ciphertext)
+        else {
+            rotation.failed.push(format!("secret:{}", row.name));
+            continue;
+        };
+        let sealed = keyring.seal(&context, &value)?;
+        sqlx::query(
+            "UPDATE secrets SET key_id = $2, nonce = $3, ciphertext = $4, updated_at = CURRENT_TIMESTAMP WHERE name = $1",
+        )
+        .bind(&row.name)
+        .bind(&sealed.key_id)
+        .bind(&sealed.nonce)
+        .bind(&sealed.ciphertext)
+        .execute(&mut *tx)
+        .await?;
+        rotation.secrets += 1;
+    }
+
+    // Provider secrets are bound to the provider name (see credentials)
+    let stale = sqlx::query_as::<_, SealedRow>(
+        r#"
+        SELECT provider AS name, secret_key_id AS key_id, secret_nonce AS nonce,
+               secret_ciphertext AS ciphertext
+        FROM provider_credentials
+        WHERE secret_key_id IS DISTINCT FROM $1
+        FOR UPDATE
+        "#,
+    )
+    .bind(active)
+    .fetch_all(&mut *tx)
+    .await?;
+    for row in stale {
+        let Ok(value) = keyring.open(
+            &row.name,
+            row.key_id.as_deref(),
+            &row.nonce,
+            &row.ciphertext,
+        ) else {
+            rotation.failed.push(format!("credential:{}", row.name));
+            continue;
+        };
+        let sealed = keyring.seal(&row.name, &value)?;
+        sqlx::query(
+            r#"
+            UPDATE provider_credentials
+            SET secret_key_id = $2, secret_nonce = $3, secret_ciphertext = $4
+            WHERE provider = $1
+            "#,
+        )
+        .bind(&row.name)
+        .bind(&sealed.key_id)
+        .bind(&sealed.nonce)
+        .bind(&sealed.ciphertext)
+        .execute(&mut *tx)
+        .await?;
+        rotation.credentials += 1;
+    }
+
+    tx.commit().await?;
+    Ok(rotation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let keyring = Keyring::new(&[1; 32], &[]).unwrap();
+        let sealed = keyring.seal("digikey", "hunter2").unwrap();
+        assert_eq!(sealed.key_id, keyring.active_key_id());
+        assert_ne!(sealed.ciphertext, b"hunter2");
+
+        let open =
+            |context, key_id| keyring.open(context, key_id, &sealed.nonce, &sealed.ciphertext);
+        assert_eq!(
+            open("digikey", Some(sealed.key_id.as_str())).unwrap(),
+            "hunter2"
+        );
+        assert_eq!(open("digikey", None).unwrap(), "hunter2");
+        // Bound to its context
+        assert!(open("lcsc", None).is_err());
+        assert!(matches!(
+            open("digikey", Some("0000")),
+            Err(SecretError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_rotated_keyring_opens_old_secrets() {
+        let old = Keyring::new(&[1; 32], &[]).unwrap();
+        let sealed = old.seal("token", "s3cret").unwrap();
+
+        let rotated = Keyring::new(&[2; 32], &[&[1; 32]]).unwrap();
+        assert_ne!(rotated.active_key_id(), old.active_key_id());
+        assert_eq!(rotated.previous_key_ids(), [old.active_key_id()]);
+        let value = rotated
+            .open(
+                "token",
+                Some(&sealed.key_id),
+                &sealed.nonce,
+                &sealed.ciphertext,
+            )
+            .unwrap();
+        assert_eq!(value, "s3cret");
+
+        // Once the old key is dropped its secrets no longer open
+        let dropped = Keyring::new(&[2; 32], &[]).unwrap();
+        let result = dropped.open(
+            "token",
+            Some(&sealed.key_id),
+            &sealed.nonce,
+            &sealed.ciphertext,
+        );
+        assert!(matches!(result, Err(SecretError::UnknownKey(_))));
+        assert!(Keyring::new(&[2; 16], &[]).is_err());
+    }
+
+    #[test]
+    fn test_derive_key() {
+        let keyring = Keyring::new(&[1; 32], &[]).unwrap();
+        let signing = keyring.derive_key("blob-signing");
+        assert_eq!(signing.len(), 32);
+        assert_eq!(signing, keyring.derive_key("blob-signing"));
+        assert_ne!(signing, keyring.derive_key("other"));
+        assert_ne!(signing, vec![1; 32]);
+        let rotated = Keyring::new(&[2; 32], &[&[1; 32]]).unwrap();
+        assert_ne!(signing, rotated.derive_key("blob-signing"));
+    }
+}
@@ -0,0 +1,82 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+use crate::repos::{ensure_repo_id, RepoRef};
+
+/// Accepted `review_status` values
+pub const REVIEW_STATUSES: &[&str] = &["unreviewed", "in_review", "changes_requested", "approved"];
+
+/// Metadata attached to one schematic sheet of a repository
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct SheetMeta {
+    pub sheet_path: String,
+    pub owner: Option<String>,
+    pub subsystem: Option<String>,
+    pub review_status: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Fields to set on a sheet; `None` clears a field
+#[derive(Debug, Clone, Default)]
+pub struct SheetMetaUpdate<'a> {
+    pub owner: Option<&'a str>,
+    pub subsystem: Option<&'a str>,
+    pub review_status: Option<&'a str>,
+}
+
+/// Set a sheet's metadata, replacing what was stored. Metadata is kept per
+/// repository rather than per commit, so it follows the sheet across history.
+/// Returns None when the repository URL can't be parsed.
+pub async fn upsert_sheet_meta(
+    pool: &PgPool,
+    repo_url: &str,
+    sheet_path: &str,
+    update: &SheetMetaUpdate<'_>,
+) -> Result<Option<SheetMeta>, Error> {
+    let Some(repo_id) = ensure_repo_id(pool, repo_url).await? else {
+        return Ok(None);
+    };
+
+    sqlx::query_as::<_, SheetMeta>(
+        r#"
+        INSERT INTO sheet_meta (repo_id, sheet_path, owner, subsystem, review_status)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (repo_id, sheet_path) DO UPDATE SET
+            owner = EXCLUDED.owner,
+            subsystem = EXCLUDED.subsystem,
+            review_status = EXCLUDED.review_status,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING sheet_path, owner, subsystem, review_status, updated_at
+        "#,
+    )
+    .bind(repo_id)
+    .bind(sheet_path)
+    .bind(update.owner)
+    .bind(update.subsystem)
+    .bind(update.review_status)
+    .fetch_one(pool)
+    .await
+    .map(Some)
+}
+
+/// Metadata of every annotated sheet of a repository, by path. Replica-safe.
+pub async fn list_sheet_meta(pool: &PgPool, repo_url: &str) -> Result<Vec<SheetMeta>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, SheetMeta>(
+        r#"
+        SELECT m.sheet_path, m.owner, m.subsystem, m.review_status, m.updated_at
+        FROM sheet_meta m
+        JOIN repos r ON r.id = m.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+        ORDER BY m.sheet_path
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .fetch_all(pool)
+    .await
+}
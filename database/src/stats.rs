@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// Counts of tracked repositories and indexed commits
+#[derive(Serialize, Deserialize, Debug, Clone, Default, sqlx::FromRow)]
+pub struct IndexStats {
+    pub repos_tracked: i64,
+    pub commits_indexed: i64,
+    pub commits_distilled: i64,
+}
+
+/// Size of the two distill caches (per-commit JSON and per-file parse cache)
+#[derive(Serialize, Deserialize, Debug, Clone, Default, sqlx::FromRow)]
+pub struct DistillCacheSize {
+    pub commit_entries: i64,
+    /// Distinct results the commits share
+    pub commit_contents: i64,
+    pub commit_bytes: i64,
+    pub file_entries: i64,
+    pub file_bytes: i64,
+}
+
+/// Repository and commit counts. Replica-safe, as are the other statistics here.
+pub async fn index_stats(pool: &PgPool) -> Result<IndexStats, Error> {
+    sqlx::query_as::<_, IndexStats>(
+        r#"
+        SELECT
+            COUNT(DISTINCT repo_url) AS repos_tracked,
+            COUNT(*) AS commits_indexed,
+            COUNT(*) FILTER (
+                WHERE distilled_json IS NOT NULL OR distilled_sha256 IS NOT NULL
+            ) AS commits_distilled
+        FROM schematics
+        "#,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Entry counts and on-disk (possibly compressed) sizes of the distill
+/// caches. Shared distilled contents count once.
+pub async fn distill_cache_size(pool: &PgPool) -> Result<DistillCacheSize, Error> {
+    sqlx::query_as::<_, DistillCacheSize>(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM schematics
+             WHERE distilled_json IS NOT NULL OR distilled_sha256 IS NOT NULL) AS commit_entries,
+            (SELECT COUNT(*) FROM distilled_contents) AS commit_contents,
+            (SELECT COALESCE(SUM(pg_column_size(distilled_json)), 0)::BIGINT FROM schematics)
+              + (SELECT COALESCE(SUM(pg_column_size(distilled_json)), 0)::BIGINT FROM distilled_contents)
+              AS commit_bytes,
+            (SELECT COUNT(*) FROM schematic_parse_cache) AS file_entries,
+            (SELECT COALESCE(SUM(pg_column_size(parsed)), 0)::BIGINT FROM schematic_parse_cache) AS file_bytes
+        "#,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Postgres usage of one repository's commits
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct RepoStorage {
+    pub repo_url: String,
+    pub schematics: i64,
+    pub parts: i64,
+    pub distilled_commits: i64,
+    /// On-disk size of the distilled JSON the commits use. Contents shared with
+    /// other repositories count for each of them.
+    pub distilled_bytes: i64,
+}
+
+/// Blob store usage of one repository for one kind of blob
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct RepoBlobUsage {
+    pub repo_url: String,
+    pub kind: String,
+    pub blobs: i64,
+    /// Stored bytes; blobs sharing their content count once
+    pub bytes: i64,
+}
+
+/// Storage used across all repositories; shared contents count once
+#[derive(Serialize, Deserialize, Debug, Clone, Default, sqlx::FromRow)]
+pub struct StorageTotals {
+    pub database_bytes: i64,
+    pub distilled_bytes: i64,
+    pub file_cache_bytes: i64,
+    pub blob_bytes: i64,
+}
+
+/// Postgres usage per repository, optionally just one (matched case-insensitively)
+pub async fn repo_storage(
+    pool: &PgPool,
+    repo_url: Option<&str>,
+) -> Result<Vec<RepoStorage>, Error> {
+    sqlx::query_as::<_, RepoStorage>(
+        r#"
+        WITH commits AS (
+            SELECT repo_url,
+                   COUNT(*) AS schematics,
+                   COUNT(*) FILTER (
+                       WHERE distilled_json IS NOT NULL OR distilled_sha256 IS NOT NULL
+                   ) AS distilled_commits,
+                   COALESCE(SUM(pg_column_size(distilled_json)), 0)::BIGINT AS inline_bytes
+            FROM schematics
+            WHERE $1::TEXT IS NULL OR LOWER(repo_url) = LOWER($1)
+            GROUP BY repo_url
+        ),
+        shared AS (
+            SELECT d.repo_url, SUM(pg_column_size(c.distilled_json))::BIGINT AS bytes
+            FROM (
+                SELECT DISTINCT repo_url, distilled_sha256 FROM schematics
+                WHERE distilled_sha256 IS NOT NULL
+                  AND ($1::TEXT IS NULL OR LOWER(repo_url) = LOWER($1))
+            ) d
+            JOIN distilled_contents c ON c.sha256 = d.distilled_sha256
+            GROUP BY d.repo_url
+        ),
+        part_counts AS (
+            SELECT s.repo_url, COUNT(*) AS parts
+            FROM parts p
+            JOIN schematics s ON s.id = p.schematic_id
+            WHERE $1::TEXT IS NULL OR LOWER(s.repo_url) = LOWER($1)
+            GROUP BY s.repo_url
+        )
+        SELECT c.repo_url, c.schematics, COALESCE(p.parts, 0) AS parts, c.distilled_commits,
+               c.inline_bytes + COALESCE(sh.bytes, 0) AS distilled_bytes
+        FROM commits c
+        LEFT JOIN shared sh ON sh.repo_url = c.repo_url
+        LEFT JOIN part_counts p ON p.repo_url = c.repo_url
+        ORDER BY c.repo_url
+        "#,
+    )
+    .bind(repo_url)
+    .fetch_all(pool)
+    .await
+}
+
+/// Blob store usage per repository and kind. Blobs not attached to a
+/// repository are left out.
+pub async fn repo_blob_usage(
+    pool: &PgPool,
+    repo_url: Option<&str>,
+) -> Result<Vec<RepoBlobUsage>, Error> {
+    sqlx::query_as::<_, RepoBlobUsage>(
+        r#"
+        SELECT repo_url, kind, SUM(refs)::BIGINT AS blobs, SUM(size_bytes)::BIGINT AS bytes
+        FROM (
+            SELECT repo_url, kind, COUNT(*) AS refs, MAX(size_bytes) AS size_bytes
+            FROM blobs
+            WHERE repo_url IS NOT NULL
+              AND ($1::TEXT IS NULL OR LOWER(repo_url) = LOWER($1))
+            GROUP BY repo_url, kind, COALESCE(content_key, key)
+        ) locations
+        GROUP BY repo_url, kind
+        ORDER BY repo_url, kind
+        "#,
+    )
+    .bind(repo_url)
+    .fetch_all(pool)
+    .await
+}
+
+/// Size of the database and of each kind of stored data
+pub async fn storage_totals(pool: &PgPool) -> Result<StorageTotals, Error> {
+    sqlx::query_as::<_, StorageTotals>(
+        r#"
+        SELECT
+            pg_database_size(current_database()) AS database_bytes,
+            (SELECT COALESCE(SUM(pg_column_size(distilled_json)), 0)::BIGINT FROM schematics)
+              + (SELECT COALESCE(SUM(pg_column_size(distilled_json)), 0)::BIGINT FROM distilled_contents)
+              AS distilled_bytes,
+            (SELECT COALESCE(SUM(pg_column_size(parsed)), 0)::BIGINT FROM schematic_parse_cache) AS file_cache_bytes,
+            (SELECT COALESCE(SUM(size_bytes), 0)::BIGINT FROM (
+                SELECT MAX(size_bytes) AS size_bytes FROM blobs GROUP BY COALESCE(content_key, key)
+            ) locations) AS blob_bytes
+        "#,
+    )
+    .fetch_one(pool)
+    .await
+}
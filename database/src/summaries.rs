@@ -0,0 +1,325 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// Review state of generated text nobody has reviewed yet
+pub const REVIEW_DRAFT: &str = "draft";
+pub const REVIEW_APPROVED: &str = "approved";
+pub const REVIEW_REJECTED: &str = "rejected";
+/// States a reviewer can move a summary to
+pub const REVIEW_DECISIONS: &[&str] = &[REVIEW_APPROVED, REVIEW_REJECTED];
+
+/// What produced a commit's blurb/description
+#[derive(Debug, Clone, Copy)]
+pub struct SummaryProvenance<'a> {
+    pub model: &'a str,
+    pub prompt_version: &'a str,
+    pub distiller_version: &'a str,
+}
+
+/// Review state of a commit's blurb/description, with the reviewed text
+/// (human edits preferred over generated text)
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct SummaryReview {
+    pub blurb: Option<String>,
+    pub description: Option<String>,
+    pub review_status: String,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+/// A commit's generated blurb/description next to their human edits
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct SummaryOverride {
+    pub generated_blurb: Option<String>,
+    pub generated_description: Option<String>,
+    pub blurb_override: Option<String>,
+    pub description_override: Option<String>,
+    pub edited_by: Option<String>,
+    pub edited_at: Option<DateTime<Utc>>,
+    pub review_status: String,
+}
+
+/// A stale summary claimed for regeneration
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct StaleSummary {
+    pub repo_url: String,
+    pub commit_hash: String,
+}
+
+/// Record what produced a commit's blurb/description and clear its stale flag
+pub async fn set_provenance(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    provenance: &SummaryProvenance<'_>,
+) -> Result<(), Error> {
+    sqlx::query(
+        r#"
+        UPDATE schematics SET
+            summary_model = $3,
+            summary_prompt_version = $4,
+            summary_distiller_version = $5,
+            summary_generated_at = CURRENT_TIMESTAMP,
+            summary_stale = FALSE,
+            summary_requeued_at = NULL
+        WHERE repo_url = $1 AND commit_hash = $2
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(provenance.model)
+    .bind(provenance.prompt_version)
+    .bind(provenance.distiller_version)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Replace a commit's blurb/description, leaving the rest of the row untouched
+pub async fn update_summary(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    blurb: &str,
+    description: &str,
+    provenance: &SummaryProvenance<'_>,
+) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE schematics SET
+            blurb = $3,
+            description = $4,
+            summary_model = $5,
+            summary_prompt_version = $6,
+            summary_distiller_version = $7,
+            summary_generated_at = CURRENT_TIMESTAMP,
+            summary_stale = FALSE,
+            summary_requeued_at = NULL,
+            summary_review_status = CASE
+                WHEN blurb IS NOT DISTINCT FROM $3 AND description IS NOT DISTINCT FROM $4
+                THEN summary_review_status ELSE 'draft' END,
+            summary_reviewed_by = CASE
+                WHEN blurb IS NOT DISTINCT FROM $3 AND description IS NOT DISTINCT FROM $4
+                THEN summary_reviewed_by END,
+            summary_reviewed_at = CASE
+                WHEN blurb IS NOT DISTINCT FROM $3 AND description IS NOT DISTINCT FROM $4
+                THEN summary_reviewed_at END
+        WHERE repo_url = $1 AND commit_hash = $2
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(blurb)
+    .bind(description)
+    .bind(provenance.model)
+    .bind(provenance.prompt_version)
+    .bind(provenance.distiller_version)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Approve or reject a commit's blurb/description. A reviewer may correct the
+/// text while reviewing; corrections are stored as human edits and `None`
+/// keeps the current text. Returns None when the commit isn't stored.
+pub async fn review_summary(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    status: &str,
+    reviewer: &str,
+    blurb: Option<&str>,
+    description: Option<&str>,
+) -> Result<Option<SummaryReview>, Error> {
+    sqlx::query_as::<_, SummaryReview>(
+        r#"
+        UPDATE schematics SET
+            blurb_override = COALESCE($5, blurb_override),
+            description_override = COALESCE($6, description_override),
+            summary_edited_by = CASE
+                WHEN $5::TEXT IS NULL AND $6::TEXT IS NULL THEN summary_edited_by ELSE $4 END,
+            summary_edited_at = CASE
+                WHEN $5::TEXT IS NULL AND $6::TEXT IS NULL THEN summary_edited_at
+                ELSE CURRENT_TIMESTAMP END,
+            summary_review_status = $3,
+            summary_reviewed_by = $4,
+            summary_reviewed_at = CURRENT_TIMESTAMP
+        WHERE repo_url = $1 AND commit_hash = $2
+        RETURNING COALESCE(blurb_override, blurb) AS blurb,
+                  COALESCE(description_override, description) AS description,
+                  summary_review_status AS review_status,
+                  summary_reviewed_by AS reviewed_by,
+                  summary_reviewed_at AS reviewed_at
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(status)
+    .bind(reviewer)
+    .bind(blurb)
+    .bind(description)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Store human edits of a commit's blurb/description, keeping the generated
+/// text. `None` leaves a field as it is; an empty string drops its edit so the
+/// generated text shows again. Edited text needs review again, so the review
+/// state goes back to draft. Returns None when the commit isn't stored.
+pub async fn set_override(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    blurb: Option<&str>,
+    description: Option<&str>,
+    editor: &str,
+) -> Result<Option<SummaryOverride>, Error> {
+    sqlx::query_as::<_, SummaryOverride>(
+        r#"
+        UPDATE schematics SET
+            blurb_override = CASE
+                WHEN $3::TEXT IS NULL THEN blurb_override ELSE NULLIF($3, '') END,
+            description_override = CASE
+                WHEN $4::TEXT IS NULL THEN description_override ELSE NULLIF($4, '') END,
+            summary_edited_by = $5,
+            summary_edited_at = CURRENT_TIMESTAMP,
+            summary_review_status = 'draft',
+            summary_reviewed_by = NULL,
+            summary_reviewed_at = NULL
+        WHERE repo_url = $1 AND commit_hash = $2
+        RETURNING blurb AS generated_blurb, description AS generated_description,
+                  blurb_override, description_override,
+                  summary_edited_by AS edited_by, summary_edited_at AS edited_at,
+                  summary_review_status AS review_status
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(blurb)
+    .bind(description)
+    .bind(editor)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Flag summaries generated with a different prompt or distiller version than the current ones.
+/// Returns how many were newly flagged.
+pub async fn mark_stale(
+    pool: &PgPool,
+    prompt_version: &str,
+    distiller_version: &str,
+) -> Result<u64, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE schematics SET summary_stale = TRUE
+        WHERE NOT summary_stale
+          AND (blurb IS NOT NULL OR description IS NOT NULL)
+          AND (summary_prompt_version IS DISTINCT FROM $1
+               OR summary_distiller_version IS DISTINCT FROM $2)
+        "#,
+    )
+    .bind(prompt_version)
+    .bind(distiller_version)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Claim up to `limit` stale summaries for regeneration, oldest first.
+///
+/// Claimed rows are not handed out again for `retry_after_secs`, so several
+/// API processes can share the work and failed regenerations are retried later.
+pub async fn claim_stale(
+    pool: &PgPool,
+    limit: i64,
+    retry_after_secs: i64,
+) -> Result<Vec<StaleSummary>, Error> {
+    sqlx::query_as::<_, StaleSummary>(
+        r#"
+        UPDATE schematics SET summary_requeued_at = CURRENT_TIMESTAMP
+        WHERE id IN (
+            SELECT id FROM schematics
+            WHERE summary_stale
+              AND (summary_requeued_at IS NULL
+                   OR summary_requeued_at < CURRENT_TIMESTAMP - make_interval(secs => $2))
+            ORDER BY summary_generated_at NULLS FIRST, id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING repo_url, commit_hash
+        "#,
+    )
+    .bind(limit)
+    .bind(retry_after_secs as f64)
+    .fetch_all(pool)
+    .await
+}
+
+/// Number of summaries currently flagged stale
+pub async fn count_stale(pool: &PgPool) -> Result<i64, Error> {
+    let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM schematics WHERE summary_stale")
+        .fetch_one(pool)
+        .await?;
+    Ok(count)
+}
+
+/// A commit's stored read-aloud blurb, if it was made from `source` by the
+/// given rules version
+pub async fn cached_speech_blurb(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    source: &str,
+    version: &str,
+) -> Result<Option<String>, Error> {
+    let row: Option<(String,)> = sqlx::query_as(
+        r#"
+        SELECT speech_blurb FROM schematics
+        WHERE repo_url = $1 AND commit_hash = $2
+          AND speech_blurb IS NOT NULL
+          AND speech_blurb_source = $3 AND speech_blurb_version = $4
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(source)
+    .bind(version)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|(speech,)| speech))
+}
+
+/// Store a commit's read-aloud blurb with the blurb it was made from.
+/// Returns false when the commit isn't stored.
+pub async fn store_speech_blurb(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: &str,
+    source: &str,
+    version: &str,
+    speech: &str,
+) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE schematics SET
+            speech_blurb = $5,
+            speech_blurb_source = $3,
+            speech_blurb_version = $4
+        WHERE repo_url = $1 AND commit_hash = $2
+        "#,
+    )
+    .bind(repo_url)
+    .bind(commit_hash)
+    .bind(source)
+    .bind(version)
+    .bind(speech)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Error, PgPool};
+
+/// An LLM commit summary stored under the hash of the diff it summarized
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct CachedSummary {
+    pub diff_sha256: String,
+    pub model: String,
+    pub prompt_version: String,
+    pub summary: String,
+    pub details: String,
+    /// Commit the summary was generated for
+    pub repo_url: String,
+    pub commit_hash: String,
+    /// Provenance record of the generation, when one was written
+    pub provenance_id: Option<i64>,
+    /// Times the summary was served from the cache
+    pub hits: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Summary generated in the same repository for the same diff by the same model
+/// and prompt version, counting the lookup as a reuse. Summaries are never
+/// shared between repositories.
+pub async fn reuse_summary(
+    pool: &PgPool,
+    repo_url: &str,
+    diff_sha256: &str,
+    model: &str,
+    prompt_version: &str,
+) -> Result<Option<CachedSummary>, Error> {
+    sqlx::query_as::<_, CachedSummary>(
+        r#"
+        UPDATE summary_cache
+        SET hits = hits + 1
+        WHERE diff_sha256 = $1 AND model = $2 AND prompt_version = $3 AND repo_url = $4
+        RETURNING diff_sha256, model, prompt_version, summary, details,
+                  repo_url, commit_hash, provenance_id, hits, created_at
+        "#,
+    )
+    .bind(diff_sha256)
+    .bind(model)
+    .bind(prompt_version)
+    .bind(repo_url)
+    .fetch_optional(pool)
+    .await
+}
+
+/// A freshly generated summary to store
+#[derive(Debug, Clone)]
+pub struct NewCachedSummary<'a> {
+    pub diff_sha256: &'a str,
+    pub model: &'a str,
+    pub prompt_version: &'a str,
+    pub summary: &'a str,
+    pub details: &'a str,
+    pub repo_url: &'a str,
+    pub commit_hash: &'a str,
+    pub provenance_id: Option<i64>,
+}
+
+/// Store a freshly generated summary. The first summary stored for a diff in a
+/// repository is kept; returns false when one already was.
+pub async fn store_summary(pool: &PgPool, new: &NewCachedSummary<'_>) -> Result<bool, Error> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO summary_cache (
+            diff_sha256, model, prompt_version, summary, details,
+            repo_url, commit_hash, provenance_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (repo_url, diff_sha256, model, prompt_version) DO NOTHING
+        "#,
+    )
+    .bind(new.diff_sha256)
+    .bind(new.model)
+    .bind(new.prompt_version)
+    .bind(new.summary)
+    .bind(new.details)
+    .bind(new.repo_url)
+    .bind(new.commit_hash)
+    .bind(new.provenance_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,215 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::repos::RepoRef;
+
+/// Position in the change stream: the transaction id and sequence number a
+/// row was stamped with. Changes are ordered by both. The default is the
+/// start, before every change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SyncCursor {
+    pub txid: i64,
+    pub seq: i64,
+}
+
+impl fmt::Display for SyncCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.txid, self.seq)
+    }
+}
+
+impl FromStr for SyncCursor {
+    type Err = String;
+
+    /// Parse the "<txid>-<seq>" form `Display` writes
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid sync cursor {:?}", s);
+        let (txid, seq) = s.trim().split_once('-').ok_or_else(invalid)?;
+        Ok(Self {
+            txid: txid.parse().map_err(|_| invalid())?,
+            seq: seq.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// A commit as of its latest change, with its summary
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ChangedCommit {
+    pub commit_hash: String,
+    pub commit_date: Option<DateTime<Utc>>,
+    pub git_message: Option<String>,
+    /// Blurb and description as shown: the human edit if there is one,
+    /// else the generated text
+    pub blurb: Option<String>,
+    pub description: Option<String>,
+    pub summary_review_status: String,
+    pub has_distilled_json: bool,
+    /// Bumped on every distilled JSON write, so mirrors know to refetch it
+    pub distilled_version: i32,
+    pub has_image: bool,
+    pub created_at: DateTime<Utc>,
+    pub change_txid: i64,
+    pub change_seq: i64,
+}
+
+/// A part as of its latest change
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct ChangedPart {
+    pub commit_hash: String,
+    pub part_uuid: String,
+    pub blurb: Option<String>,
+    pub properties: Value,
+    pub change_txid: i64,
+    pub change_seq: i64,
+}
+
+/// A changed entity, at its position in the change stream
+#[derive(Debug, Clone)]
+pub enum Change {
+    Commit(ChangedCommit),
+    Part(ChangedPart),
+}
+
+impl Change {
+    pub fn cursor(&self) -> SyncCursor {
+        let (txid, seq) = match self {
+            Change::Commit(c) => (c.change_txid, c.change_seq),
+            Change::Part(p) => (p.change_txid, p.change_seq),
+        };
+        SyncCursor { txid, seq }
+    }
+}
+
+/// A page of changes
+#[derive(Debug, Clone)]
+pub struct ChangePage {
+    pub changes: Vec<Change>,
+    /// Where the next page starts: after the last change, or where the
+    /// request started when there were none
+    pub next: SyncCursor,
+    pub has_more: bool,
+}
+
+/// Oldest transaction still running; every transaction id below it is finished
+async fn horizon(pool: &PgPool) -> Result<i64, Error> {
+    sqlx::query_scalar("SELECT pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT")
+        .fetch_one(pool)
+        .await
+}
+
+/// Commits and parts of a repository changed after `after`, oldest change
+/// first, at most `limit` of them.
+///
+/// Only changes of transactions that finished before every running one are
+/// returned: sequence numbers are taken before commit, so a transaction still
+/// running may yet commit rows that sort before ones already visible, and a
+/// cursor past them would skip those rows. They are served once it finishes.
+///
+/// Reads the primary: transaction ids and snapshots are per server.
+pub async fn changes_since(
+    pool: &PgPool,
+    repo_url: &str,
+    after: SyncCursor,
+    limit: i64,
+) -> Result<ChangePage, Error> {
+    let empty = ChangePage {
+        changes: Vec::new(),
+        next: after,
+        has_more: false,
+    };
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(empty);
+    };
+    let horizon = horizon(pool).await?;
+
+    // One more than asked for of each, to tell whether there's another page
+    let commits = sqlx::query_as::<_, ChangedCommit>(
+        r#"
+        SELECT s.commit_hash, s.commit_date, s.git_message,
+               COALESCE(s.blurb_override, s.blurb) AS blurb,
+               COALESCE(s.description_override, s.description) AS description,
+               s.summary_review_status,
+               (s.distilled_json IS NOT NULL OR s.distilled_sha256 IS NOT NULL) AS has_distilled_json,
+               s.distilled_version,
+               EXISTS (
+                   SELECT 1 FROM blobs b
+                   WHERE b.repo_url = s.repo_url AND b.commit_hash = s.commit_hash
+                     AND b.kind = 'schematic_image'
+               ) AS has_image,
+               s.created_at, s.change_txid, s.change_seq
+        FROM schematics s
+        JOIN repos r ON r.id = s.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND (s.change_txid, s.change_seq) > ($3, $4)
+          AND s.change_txid < $5
+        ORDER BY s.change_txid, s.change_seq
+        LIMIT $6
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(after.txid)
+    .bind(after.seq)
+    .bind(horizon)
+    .bind(limit + 1)
+    .fetch_all(pool)
+    .await?;
+
+    let parts = sqlx::query_as::<_, ChangedPart>(
+        r#"
+        SELECT s.commit_hash, p.part_uuid::TEXT AS part_uuid, p.blurb, p.properties,
+               p.change_txid, p.change_seq
+        FROM parts p
+        JOIN schematics s ON s.id = p.schematic_id
+        JOIN repos r ON r.id = s.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND (p.change_txid, p.change_seq) > ($3, $4)
+          AND p.change_txid < $5
+        ORDER BY p.change_txid, p.change_seq
+        LIMIT $6
+        "#,
+    )
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(after.txid)
+    .bind(after.seq)
+    .bind(horizon)
+    .bind(limit + 1)
+    .fetch_all(pool)
+    .await?;
+
+    let mut changes: Vec<Change> = commits
+        .into_iter()
+        .map(Change::Commit)
+        .chain(parts.into_iter().map(Change::Part))
+        .collect();
+    changes.sort_by_key(Change::cursor);
+    let has_more = changes.len() as i64 > limit;
+    changes.truncate(limit.max(0) as usize);
+
+    let next = changes.last().map(Change::cursor).unwrap_or(after);
+    Ok(ChangePage {
+        changes,
+        next,
+        has_more,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = SyncCursor { txid: 2580, seq: 17 };
+        assert_eq!(cursor.to_string(), "2580-17");
+        assert_eq!("2580-17".parse::<SyncCursor>(), Ok(cursor));
+        assert_eq!(" 0-0 ".parse::<SyncCursor>(), Ok(SyncCursor::default()));
+        assert!("2580".parse::<SyncCursor>().is_err());
+        assert!("a-b".parse::<SyncCursor>().is_err());
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{Error, PgPool};
+use uuid::Uuid;
+
+use crate::repos::{ensure_repo_id, RepoRef};
+
+/// A saved view of the schematic viewer
+#[derive(Serialize, Deserialize, Debug, Clone, sqlx::FromRow)]
+pub struct SavedView {
+    pub id: Uuid,
+    pub repo_url: String,
+    pub commit_hash: String,
+    pub name: String,
+    pub sheet_path: Option<String>,
+    pub selected_components: Vec<String>,
+    pub zoom: Option<Value>,
+    pub notes: Option<String>,
+    pub owner: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fields for a new view
+#[derive(Debug, Clone)]
+pub struct NewSavedView<'a> {
+    pub repo_url: &'a str,
+    pub commit_hash: &'a str,
+    pub name: &'a str,
+    pub sheet_path: Option<&'a str>,
+    pub selected_components: &'a [String],
+    pub zoom: Option<&'a Value>,
+    pub notes: Option<&'a str>,
+    pub owner: Option<&'a str>,
+    /// SHA-256 (hex) of the token that allows deleting the view
+    pub edit_token_hash: &'a str,
+}
+
+const VIEW_COLUMNS: &str = r#"
+    v.id, r.repo_url, v.commit_hash, v.name, v.sheet_path, v.selected_components,
+    v.zoom, v.notes, v.owner, v.created_at
+"#;
+
+/// Store a view under a new random id. Returns None when the repository URL
+/// can't be parsed.
+pub async fn insert_view(
+    pool: &PgPool,
+    view: &NewSavedView<'_>,
+) -> Result<Option<SavedView>, Error> {
+    let Some(repo_id) = ensure_repo_id(pool, view.repo_url).await? else {
+        return Ok(None);
+    };
+
+    let id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO saved_views
+            (id, repo_id, commit_hash, name, sheet_path, selected_components, zoom, notes, owner, edit_token_hash)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        RETURNING id
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(repo_id)
+    .bind(view.commit_hash)
+    .bind(view.name)
+    .bind(view.sheet_path)
+    .bind(view.selected_components)
+    .bind(view.zoom)
+    .bind(view.notes)
+    .bind(view.owner)
+    .bind(view.edit_token_hash)
+    .fetch_one(pool)
+    .await?;
+
+    get_view(pool, id).await
+}
+
+/// Look up a view by id. Replica-safe.
+pub async fn get_view(pool: &PgPool, id: Uuid) -> Result<Option<SavedView>, Error> {
+    sqlx::query_as::<_, SavedView>(&format!(
+        "SELECT {} FROM saved_views v JOIN repos r ON r.id = v.repo_id WHERE v.id = $1",
+        VIEW_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Views of a repository, optionally at one commit, newest first. Replica-safe.
+pub async fn list_views(
+    pool: &PgPool,
+    repo_url: &str,
+    commit_hash: Option<&str>,
+    limit: i64,
+) -> Result<Vec<SavedView>, Error> {
+    let Some(repo) = RepoRef::parse(repo_url) else {
+        return Ok(Vec::new());
+    };
+
+    sqlx::query_as::<_, SavedView>(&format!(
+        r#"
+        SELECT {}
+        FROM saved_views v
+        JOIN repos r ON r.id = v.repo_id
+        WHERE r.provider = $1 AND LOWER(r.slug) = LOWER($2)
+          AND ($3::TEXT IS NULL OR v.commit_hash = $3)
+        ORDER BY v.created_at DESC
+        LIMIT $4
+        "#,
+        VIEW_COLUMNS
+    ))
+    .bind(&repo.provider)
+    .bind(&repo.slug)
+    .bind(commit_hash)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Delete a view if `edit_token_hash` matches the one it was created with.
+/// Returns whether a view was deleted.
+pub async fn delete_view(pool: &PgPool, id: Uuid, edit_token_hash: &str) -> Result<bool, Error> {
+    let result = sqlx::query("DELETE FROM saved_views WHERE id = $1 AND edit_token_hash = $2")
+        .bind(id)
+        .bind(edit_token_hash)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
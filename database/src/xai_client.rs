@@ -1,10 +1,11 @@
 // USAGE:
 // $ cargo test xai_client -- --nocapture
-use crate::messages::ChatCompletionRequest;
+use crate::messages::{ChatCompletionRequest, Message, MessageRole, StreamOptions};
 use crate::utilities::load_environment_file::get_environment_variable;
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::warn;
 
@@ -14,6 +15,9 @@ pub const DEFAULT_XAI_API_URL: &str = "https://api.x.ai/v1/chat/completions";
 /// Default XAI API responses endpoint URL
 pub const DEFAULT_XAI_RESPONSES_URL: &str = "https://api.x.ai/v1/responses";
 
+/// XAI API endpoint describing the calling API key
+pub const DEFAULT_XAI_API_KEY_URL: &str = "https://api.x.ai/v1/api-key";
+
 
 /// Default timeout in seconds (3600 seconds = 1 hour)
 pub const DEFAULT_TIMEOUT_SECONDS: u64 = 3600;
@@ -51,7 +55,7 @@ pub struct MessageResponse {
     pub content: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Usage {
     pub prompt_tokens: Option<u32>,
     pub completion_tokens: Option<u32>,
@@ -66,6 +70,9 @@ pub struct StreamChunk {
     pub created: Option<u64>,
     pub model: Option<String>,
     pub choices: Vec<StreamChoice>,
+    /// Only set on the final chunk, when the request asked for usage
+    #[serde(default)]
+    pub usage: Option<Usage>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -79,6 +86,22 @@ pub struct StreamChoice {
 pub type ChatCompletionStream = Pin<
     Box<dyn futures_util::Stream<Item = Result<String, Box<dyn std::error::Error + Send + Sync>>> + Send>,
 >;
+
+/// Token usage of a chat completion stream, filled in when the final chunk
+/// arrives. Stays empty if the stream ends early or the server reports none.
+#[derive(Clone, Default)]
+pub struct StreamUsage(Arc<Mutex<Option<Usage>>>);
+
+impl StreamUsage {
+    fn set(&self, usage: Usage) {
+        *self.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(usage);
+    }
+
+    /// Take the reported usage, if any
+    pub fn take(&self) -> Option<Usage> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+}
 /// Tool type for XAI responses API
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -191,36 +214,124 @@ pub struct ResponsesUsage {
     pub total_tokens: Option<u32>,
 }
 
+/// An on-prem OpenAI-compatible server (vLLM, llama.cpp, Ollama, ...) used in
+/// place of the xAI API, so the pipeline can run air-gapped:
+///
+/// - `LLM_BASE_URL`: API root, e.g. `http://llm.internal:8000/v1`
+/// - `LLM_API_KEY`: bearer token, if the server wants one
+/// - `LLM_MODEL`: model to request instead of the Grok models callers ask for
+#[derive(Debug, Clone)]
+pub struct LocalEndpoint {
+    pub base_url: String,
+    pub api_key: Option<String>,
+    pub model: Option<String>,
+}
+
+impl LocalEndpoint {
+    /// The endpoint configured by LLM_BASE_URL, if any
+    pub fn from_env() -> Option<Self> {
+        let read = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        Some(Self {
+            base_url: read("LLM_BASE_URL")?.trim_end_matches('/').to_string(),
+            api_key: read("LLM_API_KEY"),
+            model: read("LLM_MODEL"),
+        })
+    }
+}
+
 /// XAI API client for making chat completion requests
 #[derive(Debug, Clone)]
 pub struct XaiClient {
     api_key: String,
     base_url: String,
+    responses_url: String,
     timeout: Duration,
+    conversation_id: Option<String>,
+    local: Option<LocalEndpoint>,
 }
 
 impl XaiClient {
     /// Create a new XAI client with default settings
-    /// Loads API key from XAI_API_KEY environment variable
+    /// Loads API key from XAI_API_KEY environment variable, or talks to the
+    /// on-prem server of LLM_BASE_URL when one is configured
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
         Self::with_config(None, None)
     }
 
     /// Create a new XAI client with custom configuration
-    /// - base_url: Optional custom URL (defaults to DEFAULT_XAI_API_URL)
+    /// - base_url: Optional custom URL (defaults to DEFAULT_XAI_API_URL, or the
+    ///   chat completions endpoint of the LLM_BASE_URL server)
     /// - timeout_seconds: Optional timeout in seconds (defaults to DEFAULT_TIMEOUT_SECONDS)
     pub fn with_config(
         base_url: Option<String>,
         timeout_seconds: Option<u64>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
-        let api_key = get_environment_variable("XAI_API_KEY")?;
+        let local = LocalEndpoint::from_env();
+        let (api_key, default_url, responses_url) = match &local {
+            // Responses requests become chat completions, see `responses`
+            Some(local) => (
+                local.api_key.clone().unwrap_or_default(),
+                format!("{}/chat/completions", local.base_url),
+                DEFAULT_XAI_RESPONSES_URL.to_string(),
+            ),
+            None => (
+                get_environment_variable("XAI_API_KEY")?,
+                DEFAULT_XAI_API_URL.to_string(),
+                DEFAULT_XAI_RESPONSES_URL.to_string(),
+            ),
+        };
         Ok(Self {
             api_key,
-            base_url: base_url.unwrap_or_else(|| DEFAULT_XAI_API_URL.to_string()),
+            base_url: base_url.unwrap_or(default_url),
+            responses_url,
             timeout: Duration::from_secs(timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS)),
+            conversation_id: None,
+            local,
         })
     }
 
+    /// The on-prem server requests go to instead of the xAI API, if any
+    pub fn local_endpoint(&self) -> Option<&LocalEndpoint> {
+        self.local.as_ref()
+    }
+
+    // The model the on-prem server is asked for in place of the requested one
+    fn model_override(&self) -> Option<&str> {
+        self.local.as_ref().and_then(|local| local.model.as_deref())
+    }
+
+    // An on-prem server may need no key at all
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.api_key.is_empty() {
+            return request;
+        }
+        request.header("Authorization", format!("Bearer {}", self.api_key))
+    }
+
+    /// Send `x-grok-conv-id` with chat completion requests, so the requests of
+    /// one conversation reach the same server and reuse its prompt cache
+    pub fn with_conversation(mut self, id: impl Into<String>) -> Self {
+        self.conversation_id = Some(id.into());
+        self
+    }
+
+    fn post(&self, client: &reqwest::Client) -> reqwest::RequestBuilder {
+        let request = self.authorize(
+            client
+                .post(&self.base_url)
+                .header("Content-Type", "application/json"),
+        );
+        match &self.conversation_id {
+            Some(id) => request.header("x-grok-conv-id", id),
+            None => request,
+        }
+    }
+
     /// Make a chat completion request
     pub async fn chat_completion(
         &self,
@@ -228,13 +339,11 @@ impl XaiClient {
     ) -> Result<ChatCompletionResponse, Box<dyn std::error::Error>> {
         let client = reqwest::Client::builder().timeout(self.timeout).build()?;
 
-        let response = client
-            .post(&self.base_url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(request)
-            .send()
-            .await?;
+        let mut request = request.clone();
+        if let Some(model) = self.model_override() {
+            request.model = model.to_string();
+        }
+        let response = self.post(&client).json(&request).send().await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -265,18 +374,31 @@ impl XaiClient {
         Ok(completion_response)
     }
 
-    /// Make a responses request (with tools support)
+    /// Make a responses request (with tools support).
+    ///
+    /// An on-prem server gets a plain chat completion instead: the search tools
+    /// run on xAI's side, and OpenAI-compatible servers needn't serve
+    /// `/responses`. The answer comes back as a single message output.
     pub async fn responses(
         &self,
         request: &ResponsesRequest,
     ) -> Result<ResponsesResponse, Box<dyn std::error::Error>> {
+        if self.local.is_some() {
+            return self.responses_via_chat(request).await;
+        }
         let client = reqwest::Client::builder().timeout(self.timeout).build()?;
 
-        let response = client
-            .post(DEFAULT_XAI_RESPONSES_URL)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(request)
+        let mut request = request.clone();
+        if let Some(model) = self.model_override() {
+            request.model = model.to_string();
+        }
+        let response = self
+            .authorize(
+                client
+                    .post(&self.responses_url)
+                    .header("Content-Type", "application/json"),
+            )
+            .json(&request)
             .send()
             .await?;
 
@@ -317,6 +439,99 @@ impl XaiClient {
         Ok(responses_result)
     }
 
+    // A responses request as a tool-less chat completion, for on-prem servers
+    async fn responses_via_chat(
+        &self,
+        request: &ResponsesRequest,
+    ) -> Result<ResponsesResponse, Box<dyn std::error::Error>> {
+        let messages = request
+            .input
+            .iter()
+            .map(|input| {
+                let role = match input.role.as_str() {
+                    "system" | "developer" => MessageRole::System,
+                    "assistant" => MessageRole::Assistant,
+                    _ => MessageRole::User,
+                };
+                Message::new(role, input.content.clone())
+            })
+            .collect();
+        let completion = self
+            .chat_completion(&ChatCompletionRequest::new(messages, request.model.clone()))
+            .await?;
+
+        let text = completion
+            .choices
+            .into_iter()
+            .find_map(|choice| choice.message.and_then(|m| m.content))
+            .unwrap_or_default();
+        Ok(ResponsesResponse {
+            created_at: completion.created,
+            id: completion.id,
+            max_output_tokens: None,
+            model: completion.model,
+            object: Some("response".to_string()),
+            output: Some(vec![ResponsesOutput {
+                call_id: None,
+                input: None,
+                name: None,
+                output_type: Some("message".to_string()),
+                id: None,
+                status: Some("completed".to_string()),
+                result: None,
+                content: Some(serde_json::json!([{ "type": "output_text", "text": text }])),
+            }]),
+            usage: completion.usage.map(|usage| ResponsesUsage {
+                prompt_tokens: usage.prompt_tokens,
+                completion_tokens: usage.completion_tokens,
+                total_tokens: usage.total_tokens,
+            }),
+        })
+    }
+
+    /// Check the API key is accepted without spending tokens: asks the API
+    /// about the key itself and fails when it's unknown, disabled or blocked.
+    /// An on-prem server is asked for its models instead.
+    pub async fn verify_api_key(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout.min(Duration::from_secs(15)))
+            .build()?;
+
+        if let Some(local) = &self.local {
+            let url = format!("{}/models", local.base_url);
+            let response = self.authorize(client.get(&url)).send().await?;
+            if !response.status().is_success() {
+                return Err(format!("{} returned {}", url, response.status()).into());
+            }
+            return Ok(());
+        }
+
+        let response = client
+            .get(DEFAULT_XAI_API_KEY_URL)
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(
+                format!("API key check failed with status {}: {}", status, error_text).into(),
+            );
+        }
+
+        let info: serde_json::Value = response.json().await?;
+        for flag in ["api_key_disabled", "api_key_blocked", "team_blocked"] {
+            if info.get(flag).and_then(serde_json::Value::as_bool) == Some(true) {
+                return Err(format!("API key rejected: {}", flag).into());
+            }
+        }
+        Ok(())
+    }
+
     /// Get the timeout
     pub fn timeout(&self) -> Duration {
         self.timeout
@@ -328,24 +543,25 @@ impl XaiClient {
     }
 
     /// Make a streaming chat completion request
-    /// Returns a stream of content strings as they arrive
+    /// Returns a stream of content strings as they arrive, and a handle to the
+    /// token usage the server reports in the final chunk
     pub async fn chat_completion_stream(
         &self,
         request: &ChatCompletionRequest,
-    ) -> Result<ChatCompletionStream, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<(ChatCompletionStream, StreamUsage), Box<dyn std::error::Error + Send + Sync>> {
         let client = reqwest::Client::builder().timeout(self.timeout).build()?;
 
-        // Ensure stream is enabled
+        // Ensure stream is enabled, with usage reported at the end
         let mut stream_request = request.clone();
         stream_request.stream = Some(true);
+        stream_request.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
+        if let Some(model) = self.model_override() {
+            stream_request.model = model.to_string();
+        }
 
-        let response = client
-            .post(&self.base_url)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .json(&stream_request)
-            .send()
-            .await?;
+        let response = self.post(&client).json(&stream_request).send().await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -368,6 +584,8 @@ impl XaiClient {
         }
 
         let byte_stream = response.bytes_stream();
+        let usage = StreamUsage::default();
+        let reported = usage.clone();
 
         let stream = async_stream::stream! {
             let mut buffer = String::new();
@@ -397,6 +615,9 @@ impl XaiClient {
 
                                 match serde_json::from_str::<StreamChunk>(data) {
                                     Ok(chunk) => {
+                                        if let Some(usage) = chunk.usage {
+                                            reported.set(usage);
+                                        }
                                         if let Some(choice) = chunk.choices.first() {
                                             if let Some(delta) = &choice.delta {
                                                 // First check for reasoning_content (thinking mode)
@@ -426,7 +647,7 @@ impl XaiClient {
             }
         };
 
-        Ok(Box::pin(stream))
+        Ok((Box::pin(stream), usage))
     }
 }
 
@@ -1,4 +1,4 @@
-use kicad_db::{create_pool, store_schematic, retrieve_schematic};
+use kicad_db::{alternates, analysis_sessions, api_keys, audit, blobs, canonical, comments, component_notes, component_search, create_pool, credentials, default_branches, design_rules, digests, exchange_rates, feedback, file_summaries, jobs, llm_usage, metrics, net_explanations, outbox, provenance, quarantine, release_notes, repo_cards, repo_suggestions, repos, retrieval, risk, schema, secrets, sheet_meta, stats, summaries, summary_cache, sync, views, store_schematic, retrieve_schematic, list_schematics, Pagination, retrieve_schematic_meta, retrieve_parts, store_schematic_parse, retrieve_schematic_parses, store_distilled_json, retrieve_distilled_json, distilled_version, clear_distilled_json, prune_distilled_contents, StoreDistilledError, merge_part_properties, set_commit_info};
 use uuid::Uuid;
 use std::collections::HashMap;
 use serde_json::json;
@@ -7,6 +7,22 @@ use serde_json::json;
 // Note: Run with DB container up (database-up.sh)
 // cargo test --test integration
 
+/// Record a schematic image for a commit as the blob store would
+async fn record_test_image(pool: &sqlx::PgPool, repo_url: &str, commit_hash: &str, size_bytes: i64) -> Result<(), sqlx::Error> {
+    let key = format!("schematic_image/{}/{}/schematic.png", repo_url.replace("://", "/"), commit_hash);
+    blobs::upsert_blob(pool, &blobs::NewBlob {
+        key: &key,
+        kind: blobs::KIND_SCHEMATIC_IMAGE,
+        repo_url: Some(repo_url),
+        commit_hash: Some(commit_hash),
+        content_type: "image/png",
+        size_bytes,
+        sha256: "00",
+        content_key: None,
+    }).await?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_store_and_retrieve() -> Result<(), Box<dyn std::error::Error>> {
     let pool = match create_pool().await {
@@ -33,7 +49,6 @@ async fn test_store_and_retrieve() -> Result<(), Box<dyn std::error::Error>> {
         test_commit,
         None, // commit_date
         None, // git_message
-        Some(b"test image bytes".to_vec()), // image
         Some("test summary"), // change_summary
         Some("test overview"), // project_overview
         Some("test blurb"), // blurb
@@ -50,14 +65,29 @@ async fn test_store_and_retrieve() -> Result<(), Box<dyn std::error::Error>> {
     assert_eq!(sch.commit_hash, test_commit);
     assert_eq!(sch.commit_date, None);
     assert_eq!(sch.git_message, None);
-    assert_eq!(sch.schematic_image, Some(b"test image bytes".to_vec()));
     assert_eq!(sch.blurb, Some("test blurb".to_string()));
     assert_eq!(sch.description, Some("test description".to_string()));
     assert_eq!(sch.parts.len(), 1);
     let part = sch.parts.get(&test_uuid).unwrap();
     assert_eq!(part.blurb, Some("test blurb".to_string()));
 
+    // Metadata and parts; the image size comes from the blob store metadata
+    let meta = retrieve_schematic_meta(&pool, test_repo, test_commit).await?.unwrap();
+    assert_eq!(meta.blurb, Some("test blurb".to_string()));
+    assert_eq!(meta.image_size, None);
+    record_test_image(&pool, test_repo, test_commit, 16).await?;
+    let meta = retrieve_schematic_meta(&pool, test_repo, test_commit).await?.unwrap();
+    assert_eq!(meta.image_size, Some(16));
+    assert!(!meta.has_distilled_json);
+    let stored_parts = retrieve_parts(&pool, test_repo, test_commit).await?;
+    assert_eq!(stored_parts.len(), 1);
+    assert!(stored_parts.contains_key(&test_uuid));
+
     // Cleanup (optional for test)
+    sqlx::query("DELETE FROM blobs WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
     sqlx::query("DELETE FROM parts WHERE schematic_id IN (SELECT id FROM schematics WHERE commit_hash = $1)")
         .bind(test_commit)
         .execute(&pool)
@@ -70,4 +100,2323 @@ async fn test_store_and_retrieve() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-// Add more integration tests as needed
\ No newline at end of file
+#[tokio::test]
+async fn test_credential_upsert_and_delete() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let provider = "test-provider";
+    let sealed = |key_id: &str, nonce: &[u8], ciphertext: &[u8]| secrets::Sealed {
+        key_id: key_id.to_string(),
+        nonce: nonce.to_vec(),
+        ciphertext: ciphertext.to_vec(),
+    };
+    credentials::upsert_credential(&pool, provider, "id-1", &sealed("key-1", b"nonce-1", b"cipher-1")).await?;
+    credentials::upsert_credential(&pool, provider, "id-2", &sealed("key-2", b"nonce-2", b"cipher-2")).await?;
+
+    let stored = credentials::list_credentials(&pool).await?;
+    let row = stored.iter().find(|c| c.provider == provider).unwrap();
+    assert_eq!(row.client_id, "id-2");
+    assert_eq!(row.secret_nonce, b"nonce-2".to_vec());
+    assert_eq!(row.secret_ciphertext, b"cipher-2".to_vec());
+    assert_eq!(row.secret_key_id.as_deref(), Some("key-2"));
+
+    assert!(credentials::delete_credential(&pool, provider).await?);
+    assert!(!credentials::delete_credential(&pool, provider).await?);
+
+    Ok(())
+}
+
+// Add more integration tests as needed
+#[tokio::test]
+async fn test_schematic_parse_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let cached_oid = "test-blob-oid-cached".to_string();
+    let missing_oid = "test-blob-oid-missing".to_string();
+    let parsed = br#"{"format":1,"sexp":[{"s":"kicad_sch"}]}"#.to_vec();
+
+    store_schematic_parse(&pool, &cached_oid, "test-1.0", &parsed).await?;
+
+    let results =
+        retrieve_schematic_parses(&pool, "test-1.0", &[cached_oid.clone(), missing_oid.clone()])
+            .await?;
+    assert_eq!(results.get(&cached_oid), Some(&parsed));
+    assert!(!results.contains_key(&missing_oid));
+
+    // A parse made by another distiller version is never served
+    let other = retrieve_schematic_parses(&pool, "test-2.0", std::slice::from_ref(&cached_oid)).await?;
+    assert!(other.is_empty());
+
+    sqlx::query("DELETE FROM schematic_parse_cache WHERE blob_oid = $1")
+        .bind(&cached_oid)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_job_queue_lifecycle() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let kind = "integration_test";
+    let id = jobs::enqueue_job(&pool, kind, &json!({"n": 1}), 2).await?;
+
+    // Claim jobs until we get ours (other queued jobs may exist in a shared DB)
+    let claimed = loop {
+        match jobs::claim_next_job(&pool, "test-worker").await? {
+            Some(job) if job.id == id => break job,
+            Some(other) => jobs::fail_job(&pool, other.id, "released by test", 0).await?,
+            None => panic!("Enqueued job was not claimable"),
+        }
+    };
+    assert_eq!(claimed.status, jobs::STATUS_RUNNING);
+    assert_eq!(claimed.attempts, 1);
+    assert_eq!(claimed.locked_by.as_deref(), Some("test-worker"));
+
+    // First failure requeues, second exhausts max_attempts
+    jobs::fail_job(&pool, id, "boom", 0).await?;
+    let job = jobs::get_job(&pool, id).await?.expect("job exists");
+    assert_eq!(job.status, jobs::STATUS_QUEUED);
+    assert_eq!(job.last_error.as_deref(), Some("boom"));
+
+    sqlx::query("UPDATE jobs SET status = 'running', attempts = attempts + 1 WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    jobs::fail_job(&pool, id, "boom again", 0).await?;
+    let job = jobs::get_job(&pool, id).await?.expect("job exists");
+    assert_eq!(job.status, jobs::STATUS_FAILED);
+
+    let id = jobs::enqueue_job(&pool, kind, &json!({}), 1).await?;
+    sqlx::query("UPDATE jobs SET status = 'running', attempts = 1 WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    jobs::complete_job(&pool, id, &json!({"ok": true})).await?;
+    let job = jobs::get_job(&pool, id).await?.expect("job exists");
+    assert_eq!(job.status, jobs::STATUS_SUCCEEDED);
+    assert_eq!(job.result, Some(json!({"ok": true})));
+
+    // Specific jobs can be claimed directly and report progress
+    let id = jobs::enqueue_job(&pool, kind, &json!({}), 1).await?;
+    let claimed = jobs::claim_job(&pool, id, "api").await?.expect("queued job");
+    assert_eq!(claimed.status, jobs::STATUS_RUNNING);
+    assert!(claimed.progress.is_none());
+    assert!(jobs::claim_job(&pool, id, "api").await?.is_none());
+    jobs::set_progress(&pool, id, &json!({"done": 1, "total": 2})).await?;
+    let job = jobs::get_job(&pool, id).await?.expect("job exists");
+    assert_eq!(job.progress, Some(json!({"done": 1, "total": 2})));
+    jobs::complete_job(&pool, id, &json!(null)).await?;
+
+    // Abandoned jobs fail immediately, even with attempts left
+    let id = jobs::enqueue_job(&pool, kind, &json!({}), 3).await?;
+    sqlx::query("UPDATE jobs SET status = 'running', attempts = 1 WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await?;
+    jobs::abandon_job(&pool, id, "timed out", Some(&json!({"error": "timeout"}))).await?;
+    let job = jobs::get_job(&pool, id).await?.expect("job exists");
+    assert_eq!(job.status, jobs::STATUS_FAILED);
+    assert_eq!(job.last_error.as_deref(), Some("timed out"));
+    assert_eq!(job.result, Some(json!({"error": "timeout"})));
+    assert!(job.finished_at.is_some());
+    let latest = jobs::latest_job(&pool, kind, &json!({})).await?.expect("job exists");
+    assert_eq!(latest.id, id);
+
+    // Runs record each attempt
+    let run_id = jobs::start_run(&pool, &job, "test-worker").await?;
+    jobs::finish_run(&pool, run_id, jobs::STATUS_FAILED, Some("timed out"), None).await?;
+    let runs = jobs::runs_for_jobs(&pool, &[id]).await?;
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].attempt, 1);
+    assert_eq!(runs[0].status, jobs::STATUS_FAILED);
+    assert_eq!(runs[0].error.as_deref(), Some("timed out"));
+    assert!(runs[0].duration_ms.is_some());
+
+    // Failed jobs can be listed and retried with more attempts
+    let filter = jobs::FailedJobFilter {
+        ids: Some(vec![id]),
+        kind: Some(kind.to_string()),
+        since: None,
+    };
+    let failed = jobs::list_failed_jobs(&pool, &filter, 10).await?;
+    assert_eq!(failed.iter().map(|j| j.id).collect::<Vec<_>>(), vec![id]);
+    let retried = jobs::retry_failed_jobs(&pool, &filter, 2, 10).await?;
+    assert_eq!(retried.len(), 1);
+    assert_eq!(retried[0].status, jobs::STATUS_QUEUED);
+    assert_eq!(retried[0].max_attempts, 3);
+    assert!(retried[0].finished_at.is_none());
+    assert!(jobs::list_failed_jobs(&pool, &filter, 10).await?.is_empty());
+    assert!(jobs::retry_failed_jobs(&pool, &filter, 2, 10).await?.is_empty());
+
+    sqlx::query("DELETE FROM jobs WHERE kind = $1")
+        .bind(kind)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_blob_metadata() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = format!("https://example.com/blob-test-{}.git", Uuid::new_v4());
+    let commit_hash = "blob-test-commit";
+    let key = format!("render/{}/{}/root.svg", repo_url, commit_hash);
+
+    let mut blob = blobs::NewBlob {
+        key: &key,
+        kind: "render",
+        repo_url: Some(&repo_url),
+        commit_hash: Some(commit_hash),
+        content_type: "image/svg+xml",
+        size_bytes: 10,
+        sha256: "abc",
+        content_key: None,
+    };
+    blobs::upsert_blob(&pool, &blob).await?;
+
+    // Re-uploading replaces the metadata
+    blob.size_bytes = 20;
+    let stored = blobs::upsert_blob(&pool, &blob).await?;
+    assert_eq!(stored.size_bytes, 20);
+
+    let listed = blobs::list_blobs(&pool, &repo_url, commit_hash, None).await?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].key, key);
+    assert!(blobs::list_blobs(&pool, &repo_url, commit_hash, Some("datasheet")).await?.is_empty());
+
+    assert_eq!(stored.location(), key);
+
+    // Blobs with the same content share their bytes
+    let shared = format!("content/{}", Uuid::new_v4());
+    let other_key = format!("render/{}/other-commit/root.svg", repo_url);
+    blob.content_key = Some(&shared);
+    let stored = blobs::upsert_blob(&pool, &blob).await?;
+    assert_eq!(stored.location(), shared);
+    blob.key = &other_key;
+    blobs::upsert_blob(&pool, &blob).await?;
+    assert_eq!(blobs::content_references(&pool, &shared).await?, 2);
+    assert!(blobs::delete_blob(&pool, &other_key).await?);
+    assert_eq!(blobs::content_references(&pool, &shared).await?, 1);
+
+    assert!(blobs::get_blob(&pool, &key).await?.is_some());
+    assert!(blobs::delete_blob(&pool, &key).await?);
+    assert!(blobs::get_blob(&pool, &key).await?.is_none());
+    assert_eq!(blobs::content_references(&pool, &shared).await?, 0);
+
+    // Shared content is only new to its first user and released with its last
+    let mut tx = pool.begin().await?;
+    blob.key = &key;
+    let (_, new_content) = blobs::record_blob(&mut tx, &blob).await?;
+    assert!(!new_content, "content row kept until released");
+    assert!(!blobs::release_content(&mut tx, &shared).await?);
+    tx.commit().await?;
+    assert!(blobs::delete_blob(&pool, &key).await?);
+    let mut tx = pool.begin().await?;
+    assert!(blobs::release_content(&mut tx, &shared).await?);
+    tx.commit().await?;
+    let mut tx = pool.begin().await?;
+    let (_, new_content) = blobs::record_blob(&mut tx, &blob).await?;
+    assert!(new_content);
+    tx.rollback().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repo_storage() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = format!("https://github.com/test/storage-{}.git", Uuid::new_v4());
+    let distilled = json!({ "components": {}, "nonce": repo_url });
+    store_distilled_json(&pool, &repo_url, "a", &distilled, None).await?;
+    store_distilled_json(&pool, &repo_url, "b", &distilled, None).await?;
+    let content_key = format!("content/{}", Uuid::new_v4());
+    for commit in ["a", "b"] {
+        let key = format!("render/{}/{}/root.svg", repo_url, commit);
+        blobs::upsert_blob(
+            &pool,
+            &blobs::NewBlob {
+                key: &key,
+                kind: "render",
+                repo_url: Some(&repo_url),
+                commit_hash: Some(commit),
+                content_type: "image/svg+xml",
+                size_bytes: 100,
+                sha256: "abc",
+                content_key: Some(&content_key),
+            },
+        )
+        .await?;
+    }
+
+    // Matched case-insensitively; the shared distilled JSON counts once
+    let usage = stats::repo_storage(&pool, Some(&repo_url.to_uppercase())).await?;
+    assert_eq!(usage.len(), 1);
+    assert_eq!((usage[0].schematics, usage[0].distilled_commits), (2, 2));
+    let single: i64 = sqlx::query_scalar(
+        "SELECT pg_column_size(distilled_json)::BIGINT FROM distilled_contents WHERE sha256 = $1",
+    )
+    .bind(canonical::content_hash(&distilled))
+    .fetch_one(&pool)
+    .await?;
+    assert_eq!(usage[0].distilled_bytes, single);
+
+    let blob_usage = stats::repo_blob_usage(&pool, Some(&repo_url)).await?;
+    assert_eq!(blob_usage.len(), 1);
+    assert_eq!((blob_usage[0].kind.as_str(), blob_usage[0].blobs, blob_usage[0].bytes), ("render", 2, 100));
+    assert!(stats::storage_totals(&pool).await?.database_bytes > 0);
+
+    sqlx::query("DELETE FROM blobs WHERE repo_url = $1").bind(&repo_url).execute(&pool).await?;
+    clear_distilled_json(&pool, &repo_url, None).await?;
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1").bind(&repo_url).execute(&pool).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_llm_usage_and_stats() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let since = chrono::Utc::now() - chrono::Duration::seconds(1);
+    let before = llm_usage::usage_since(&pool, since).await?;
+    llm_usage::record_usage(&pool, "integration_test", "test-model", 100, 50, 0.25).await?;
+    let after = llm_usage::usage_since(&pool, since).await?;
+    assert!(after.requests > before.requests);
+    assert!(after.prompt_tokens >= before.prompt_tokens + 100);
+    assert!(after.cost_usd >= before.cost_usd + 0.25);
+
+    let index = stats::index_stats(&pool).await?;
+    assert!(index.commits_indexed >= index.commits_distilled);
+    assert!(index.commits_indexed >= index.repos_tracked);
+    let cache = stats::distill_cache_size(&pool).await?;
+    assert!(cache.commit_entries >= 0 && cache.file_entries >= 0);
+
+    sqlx::query("DELETE FROM llm_usage WHERE feature = 'integration_test'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_summary_feedback() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/feedback.git";
+    let test_commit = "feedback123";
+    store_schematic(
+        &pool,
+        test_repo,
+        test_commit,
+        None,
+        None,
+        None,
+        None,
+        Some("rated blurb"),
+        Some("rated description"),
+        HashMap::new(),
+    ).await?;
+    let provenance = summaries::SummaryProvenance {
+        model: "test-model",
+        prompt_version: "test-prompt-v1",
+        distiller_version: "test-distiller-1",
+    };
+    summaries::set_provenance(&pool, test_repo, test_commit, &provenance).await?;
+
+    let stored = feedback::stored_summary(&pool, test_repo, test_commit).await?.expect("schematic exists");
+    assert_eq!(stored.blurb.as_deref(), Some("rated blurb"));
+    assert_eq!(stored.summary_prompt_version.as_deref(), Some("test-prompt-v1"));
+
+    for rating in [1, -1, -1] {
+        feedback::insert_feedback(&pool, &feedback::NewFeedback {
+            schematic_id: Some(stored.schematic_id),
+            repo_url: test_repo,
+            commit_hash: test_commit,
+            target: "blurb",
+            rating,
+            comment: Some("integration test"),
+            summary_text: stored.blurb.as_deref(),
+            model: stored.summary_model.as_deref(),
+            prompt_version: stored.summary_prompt_version.as_deref(),
+        }).await?;
+    }
+
+    let filter = feedback::FeedbackFilter {
+        prompt_version: Some("test-prompt-v1"),
+        limit: 10,
+        ..Default::default()
+    };
+    let entries = feedback::export_feedback(&pool, &filter).await?;
+    assert!(entries.len() >= 3);
+    assert!(entries.iter().all(|e| e.prompt_version.as_deref() == Some("test-prompt-v1")));
+    let totals = feedback::feedback_totals(&pool, &filter).await?;
+    let blurb_totals = totals.iter().find(|t| t.target == "blurb").expect("blurb totals");
+    assert!(blurb_totals.up >= 1 && blurb_totals.down >= 2);
+
+    sqlx::query("DELETE FROM feedback WHERE prompt_version = 'test-prompt-v1'")
+        .execute(&pool)
+        .await?;
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_stale_summaries() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/stale-summaries.git";
+    let test_commit = "stale123";
+    store_schematic(
+        &pool,
+        test_repo,
+        test_commit,
+        None,
+        None,
+        None,
+        None,
+        Some("old blurb"),
+        Some("old description"),
+        HashMap::new(),
+    ).await?;
+    let old = summaries::SummaryProvenance {
+        model: "test-model",
+        prompt_version: "stale-test-v1",
+        distiller_version: "stale-test-distiller",
+    };
+    summaries::set_provenance(&pool, test_repo, test_commit, &old).await?;
+
+    // A new prompt version flags the summary once
+    assert!(summaries::mark_stale(&pool, "stale-test-v2", "stale-test-distiller").await? >= 1);
+    assert!(summaries::count_stale(&pool).await? >= 1);
+
+    // Claimed summaries are not handed out again until the retry window passes
+    let mut claimed = Vec::new();
+    loop {
+        let batch = summaries::claim_stale(&pool, 100, 3600).await?;
+        if batch.is_empty() {
+            break;
+        }
+        claimed.extend(batch);
+    }
+    assert!(claimed.iter().any(|s| s.repo_url == test_repo && s.commit_hash == test_commit));
+    assert!(summaries::claim_stale(&pool, 100, 3600).await?.is_empty());
+
+    let new = summaries::SummaryProvenance {
+        prompt_version: "stale-test-v2",
+        ..old
+    };
+    assert!(summaries::update_summary(&pool, test_repo, test_commit, "new blurb", "new description", &new).await?);
+    let stored = retrieve_schematic(&pool, test_repo, test_commit).await?.expect("schematic exists");
+    assert_eq!(stored.blurb.as_deref(), Some("new blurb"));
+    assert_eq!(summaries::mark_stale(&pool, "stale-test-v2", "stale-test-distiller").await?, 0);
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_release_notes() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/release-notes.git";
+    let commits = json!([{ "commit": "bbb", "changed_files": ["main.kicad_sch"] }]);
+    let bom_delta = json!({ "added": [], "removed": [], "changed": [] });
+    let mut new_notes = release_notes::NewReleaseNotes {
+        repo_url: test_repo,
+        from_ref: "v1.0",
+        to_ref: "v1.1",
+        from_commit: "aaa",
+        to_commit: "bbb",
+        commits: &commits,
+        bom_delta: &bom_delta,
+        notes: "first draft",
+        model: "test-model",
+        prompt_version: "test-v1",
+    };
+    let first = release_notes::upsert_release_notes(&pool, &new_notes).await?;
+
+    // Regenerating the same range replaces the notes in place
+    new_notes.notes = "second draft";
+    let second = release_notes::upsert_release_notes(&pool, &new_notes).await?;
+    assert_eq!(first.id, second.id);
+
+    let fetched = release_notes::get_release_notes(&pool, first.id).await?.expect("notes exist");
+    assert_eq!(fetched.notes, "second draft");
+    assert_eq!(fetched.commits, commits);
+    let found = release_notes::find_release_notes(&pool, test_repo, "aaa", "bbb").await?;
+    assert_eq!(found.map(|n| n.id), Some(first.id));
+    assert_eq!(release_notes::list_release_notes(&pool, test_repo, 10).await?.len(), 1);
+
+    sqlx::query("DELETE FROM release_notes WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_component_search() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/component-search.git";
+    let distilled = json!({
+        "components": {
+            "U1": { "lib_id": "Regulator_Linear:LM317_TO-220", "value": "LM317", "sheet_path": "/power.kicad_sch", "properties": {} },
+            "U2": { "lib_id": "MCU_ST:STM32F103C8Tx", "value": "STM32F103C8T6", "properties": { "Manufacturer_Part_Number": "STM32F103C8T6" } },
+            "R1": { "lib_id": "Device:R", "value": "240", "properties": {} },
+            "#PWR01": { "lib_id": "power:GND", "value": "GND", "properties": {} }
+        }
+    });
+    store_distilled_json(&pool, test_repo, "aaa", &distilled, None).await?;
+    // Enrichment stores the MPN on the part rather than the symbol
+    merge_part_properties(&pool, test_repo, "aaa", HashMap::from([
+        (Uuid::new_v4(), json!({ "reference": "R1", "mpn": "RC0603FR-07240RL" })),
+    ])).await?;
+
+    let mut search = component_search::ComponentSearch {
+        value: Some("lm317%"),
+        mpn_keys: vec!["mpn".to_string(), "manufacturerpartnumber".to_string()],
+        limit: 100,
+        ..Default::default()
+    };
+    let found = component_search::search_components(&pool, test_repo, &search).await?;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].reference, "U1");
+    assert_eq!(found[0].commit_hash, "aaa");
+
+    search.value = None;
+    search.mpn = Some("STM32%");
+    let found = component_search::search_components(&pool, test_repo, &search).await?;
+    assert_eq!(found.iter().map(|m| m.reference.as_str()).collect::<Vec<_>>(), vec!["U2"]);
+
+    search.mpn = None;
+    search.any = Some("%07240%");
+    let found = component_search::search_components(&pool, test_repo, &search).await?;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].reference, "R1");
+    assert!(found[0].part_properties.is_some());
+
+    // Virtual parts never match
+    search.any = Some("GND");
+    assert!(component_search::search_components(&pool, test_repo, &search).await?.is_empty());
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_design_metrics() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/design-metrics.git";
+    let distilled = json!({ "components": {}, "nets": {} });
+    store_distilled_json(&pool, test_repo, "old", &distilled, None).await?;
+    store_distilled_json(&pool, test_repo, "new", &distilled, None).await?;
+    sqlx::query("UPDATE schematics SET commit_date = NOW() - INTERVAL '1 day' WHERE repo_url = $1 AND commit_hash = 'old'")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+    sqlx::query("UPDATE schematics SET commit_date = NOW() WHERE repo_url = $1 AND commit_hash = 'new'")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    assert_eq!(metrics::commits_without_metrics(&pool, test_repo, 10).await?.len(), 2);
+
+    let stored = metrics::upsert_metrics(&pool, test_repo, "old", &metrics::NewDesignMetrics {
+        component_count: 10,
+        net_count: 8,
+        sheet_count: 1,
+        unique_mpns: 4,
+        ..Default::default()
+    }).await?;
+    assert!(stored);
+    metrics::upsert_metrics(&pool, test_repo, "new", &metrics::NewDesignMetrics {
+        component_count: 25,
+        net_count: 20,
+        sheet_count: 3,
+        unique_mpns: 9,
+        estimated_bom_cost: Some(12.5),
+        priced_components: 2,
+    }).await?;
+    // Unknown commits have nowhere to store metrics
+    assert!(!metrics::upsert_metrics(&pool, test_repo, "missing", &metrics::NewDesignMetrics::default()).await?);
+
+    let history = metrics::metrics_history(&pool, test_repo, 10).await?;
+    assert_eq!(history.iter().map(|m| m.commit_hash.as_str()).collect::<Vec<_>>(), vec!["old", "new"]);
+    assert_eq!(history[1].component_count, 25);
+    assert_eq!(history[1].estimated_bom_cost, Some(12.5));
+    assert!(history[0].estimated_bom_cost.is_none());
+
+    // The limit keeps the newest commits
+    let latest = metrics::metrics_history(&pool, test_repo, 1).await?;
+    assert_eq!(latest.len(), 1);
+    assert_eq!(latest[0].commit_hash, "new");
+    assert!(metrics::commits_without_metrics(&pool, test_repo, 10).await?.is_empty());
+
+    merge_part_properties(&pool, test_repo, "new", HashMap::from([
+        (Uuid::new_v4(), json!({ "reference": "U1", "digikey": { "unit_price": 2.5 } })),
+        (Uuid::new_v4(), json!({ "reference": "R1", "digikey": { "unit_price": null } })),
+    ])).await?;
+    let prices = metrics::part_unit_prices(&pool, test_repo, "new").await?;
+    assert_eq!(prices.len(), 1);
+    assert_eq!(prices.get("U1"), Some(&2.5));
+
+    merge_part_properties(&pool, test_repo, "new", HashMap::from([
+        (Uuid::new_v4(), json!({ "reference": "C1", "digikey": { "unit_price": 0.1, "currency": "EUR" } })),
+    ])).await?;
+    let prices = metrics::part_prices(&pool, test_repo, "new").await?;
+    assert_eq!(prices.get("U1"), Some(&metrics::PartPrice { unit_price: 2.5, currency: "USD".to_string() }));
+    assert_eq!(prices.get("C1").map(|p| p.currency.as_str()), Some("EUR"));
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_list_schematics() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/list-schematics.git";
+    let parts = HashMap::from([(Uuid::new_v4(), (None, json!({ "reference": "R1" })))]);
+    store_schematic(&pool, test_repo, "old", Some(chrono::Utc::now() - chrono::Duration::days(1)), Some("first"), None, None, Some("blurb"), None, parts).await?;
+    record_test_image(&pool, test_repo, "old", 3).await?;
+    store_distilled_json(&pool, test_repo, "new", &json!({ "components": {} }), None).await?;
+    sqlx::query("UPDATE schematics SET commit_date = NOW() WHERE repo_url = $1 AND commit_hash = 'new'")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    let (stored, total) = list_schematics(&pool, test_repo, Pagination { limit: 10, offset: 0 }).await?;
+    assert_eq!(total, 2);
+    assert_eq!(stored.iter().map(|s| s.commit_hash.as_str()).collect::<Vec<_>>(), vec!["new", "old"]);
+    assert!(stored[0].has_distilled_json && !stored[0].has_blurb && !stored[0].has_image);
+    assert!(stored[1].has_blurb && stored[1].has_image && !stored[1].has_description);
+    assert_eq!(stored[1].part_count, 1);
+
+    let (page, total) = list_schematics(&pool, test_repo, Pagination { limit: 1, offset: 1 }).await?;
+    assert_eq!(total, 2);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].commit_hash, "old");
+
+    sqlx::query("DELETE FROM blobs WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_outbox() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let event_type = format!("test.outbox.{}", Uuid::new_v4());
+
+    // An event enqueued in a rolled-back transaction never exists
+    let mut tx = pool.begin().await?;
+    let rolled_back = outbox::enqueue_event(&mut *tx, &event_type, &json!({ "n": 0 })).await?;
+    tx.rollback().await?;
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM outbox_events WHERE id = $1)")
+        .bind(rolled_back)
+        .fetch_one(&pool)
+        .await?;
+    assert!(!exists);
+
+    let id = outbox::enqueue_event(&pool, &event_type, &json!({ "n": 1 })).await?;
+
+    let claimed = outbox::claim_events(&pool, 1000).await?;
+    let event = claimed.iter().find(|e| e.id == id).expect("event claimed");
+    assert_eq!(event.status, outbox::STATUS_DELIVERING);
+    assert_eq!(event.attempts, 1);
+    assert_eq!(event.payload, json!({ "n": 1 }));
+    // Claimed events aren't handed out again
+    assert!(!outbox::claim_events(&pool, 1000).await?.iter().any(|e| e.id == id));
+
+    // A failed delivery with attempts left goes back to pending
+    outbox::mark_failed(&pool, id, "connection refused", 0).await?;
+    let retried = outbox::claim_events(&pool, 1000).await?;
+    let event = retried.iter().find(|e| e.id == id).expect("event retried");
+    assert_eq!(event.attempts, 2);
+    assert_eq!(event.last_error.as_deref(), Some("connection refused"));
+
+    outbox::mark_delivered(&pool, id).await?;
+    let status: String = sqlx::query_scalar("SELECT status FROM outbox_events WHERE id = $1")
+        .bind(id)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(status, outbox::STATUS_DELIVERED);
+
+    // Out of attempts: failed for good
+    let failing = outbox::enqueue_event(&pool, &event_type, &json!({ "n": 2 })).await?;
+    sqlx::query("UPDATE outbox_events SET max_attempts = 1 WHERE id = $1")
+        .bind(failing)
+        .execute(&pool)
+        .await?;
+    assert!(outbox::claim_events(&pool, 1000).await?.iter().any(|e| e.id == failing));
+    outbox::mark_failed(&pool, failing, "HTTP 500", 0).await?;
+    let status: String = sqlx::query_scalar("SELECT status FROM outbox_events WHERE id = $1")
+        .bind(failing)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(status, outbox::STATUS_FAILED);
+
+    // Storing a schematic emits an event in the same transaction
+    let test_repo = "https://github.com/test/outbox.git";
+    let schematic_id = store_schematic(&pool, test_repo, "abc", None, None, None, None, None, None, HashMap::new()).await?;
+    let stored: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM outbox_events WHERE event_type = $1 AND payload->>'schematic_id' = $2")
+        .bind(outbox::EVENT_SCHEMATIC_STORED)
+        .bind(schematic_id.to_string())
+        .fetch_one(&pool)
+        .await?;
+    assert!(stored >= 1);
+
+    sqlx::query("DELETE FROM outbox_events WHERE event_type = $1 OR (event_type = $2 AND payload->>'repo_url' = $3)")
+        .bind(&event_type)
+        .bind(outbox::EVENT_SCHEMATIC_STORED)
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repos() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let old_url = "https://github.com/test/repo-rename-old.git";
+    let new_url = "https://github.com/test/repo-rename-new.git";
+    store_distilled_json(&pool, old_url, "abc", &json!({ "components": {} }), None).await?;
+
+    // Any URL form finds the same repo
+    let repo = repos::find_repo(&pool, "git@github.com:Test/Repo-Rename-Old").await?.expect("repo created on store");
+    assert_eq!(repo.provider, "github.com");
+    assert_eq!(repo.repo_url, old_url);
+    let repo_id: Option<i32> = sqlx::query_scalar("SELECT repo_id FROM schematics WHERE repo_url = $1")
+        .bind(old_url)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(repo_id, Some(repo.id));
+    assert!(repos::list_repos(&pool).await?.iter().any(|r| r.id == repo.id && r.commit_count == 1));
+
+    // Rows stored before repos existed are attached by the backfill
+    sqlx::query("UPDATE schematics SET repo_id = NULL WHERE repo_url = $1")
+        .bind(old_url)
+        .execute(&pool)
+        .await?;
+    assert!(repos::backfill_repo_ids(&pool).await? >= 1);
+
+    match repos::rename_repo(&pool, old_url, new_url).await? {
+        repos::RenameOutcome::Renamed(renamed) => {
+            assert_eq!(renamed.id, repo.id);
+            assert_eq!(renamed.slug, "test/repo-rename-new");
+        }
+        other => panic!("unexpected rename outcome: {:?}", other),
+    }
+    assert!(retrieve_distilled_json(&pool, new_url, "abc").await?.is_some());
+    assert!(retrieve_distilled_json(&pool, old_url, "abc").await?.is_none());
+    assert!(matches!(repos::rename_repo(&pool, old_url, new_url).await?, repos::RenameOutcome::NotFound));
+
+    // Commit statuses are off until turned on
+    assert!(!repo.commit_status);
+    let enabled = repos::set_commit_status(&pool, new_url, true).await?.expect("repo tracked");
+    assert!(enabled.commit_status);
+    assert!(repos::find_repo(&pool, new_url).await?.unwrap().commit_status);
+    assert!(repos::set_commit_status(&pool, old_url, true).await?.is_none());
+
+    sqlx::query("DELETE FROM repos WHERE id = $1")
+        .bind(repo.id)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_distilled_json_compare_and_swap() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/distilled-cas.git";
+    assert_eq!(distilled_version(&pool, test_repo, "abc").await?, 0);
+
+    // Only version 0 may create the row
+    assert!(matches!(
+        store_distilled_json(&pool, test_repo, "abc", &json!({ "n": 0 }), Some(3)).await,
+        Err(StoreDistilledError::Conflict { current_version: 0 })
+    ));
+    assert_eq!(store_distilled_json(&pool, test_repo, "abc", &json!({ "n": 1 }), Some(0)).await?, 1);
+
+    // Two workers that both read version 1: the second one conflicts and the first write survives
+    assert_eq!(store_distilled_json(&pool, test_repo, "abc", &json!({ "n": 2 }), Some(1)).await?, 2);
+    assert!(matches!(
+        store_distilled_json(&pool, test_repo, "abc", &json!({ "n": 3 }), Some(1)).await,
+        Err(StoreDistilledError::Conflict { current_version: 2 })
+    ));
+    assert_eq!(retrieve_distilled_json(&pool, test_repo, "abc").await?, Some(json!({ "n": 2 })));
+
+    // Clearing bumps the version; unconditional writes always succeed
+    clear_distilled_json(&pool, test_repo, Some("abc")).await?;
+    assert_eq!(distilled_version(&pool, test_repo, "abc").await?, 3);
+    assert_eq!(store_distilled_json(&pool, test_repo, "abc", &json!({ "n": 4 }), None).await?, 4);
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_distilled_json_deduplicated() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = format!("https://github.com/test/distilled-dedup-{}.git", Uuid::new_v4());
+    let nonce = Uuid::new_v4().to_string();
+    let first = json!({ "nonce": nonce, "proximities": [{ "ref_a": "R2", "ref_b": "C1" }, { "ref_a": "R1", "ref_b": "C1" }] });
+    // Same design, listed in another order
+    let second = json!({ "proximities": [{ "ref_a": "R1", "ref_b": "C1" }, { "ref_a": "R2", "ref_b": "C1" }], "nonce": nonce });
+    let sha256 = canonical::content_hash(&first);
+    assert_eq!(sha256, canonical::content_hash(&second));
+
+    store_distilled_json(&pool, &test_repo, "a", &first, None).await?;
+    store_distilled_json(&pool, &test_repo, "b", &second, None).await?;
+    let contents: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM distilled_contents WHERE sha256 = $1")
+        .bind(&sha256)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(contents, 1);
+    let stored = retrieve_distilled_json(&pool, &test_repo, "b").await?.unwrap();
+    assert_eq!(stored["proximities"][0]["ref_a"], "R1");
+    assert_eq!(retrieve_distilled_json(&pool, &test_repo, "a").await?, Some(stored));
+    assert!(retrieve_schematic_meta(&pool, &test_repo, "a").await?.unwrap().has_distilled_json);
+
+    // Shared contents stay until the last commit lets go of them
+    clear_distilled_json(&pool, &test_repo, Some("a")).await?;
+    assert!(retrieve_distilled_json(&pool, &test_repo, "b").await?.is_some());
+    store_distilled_json(&pool, &test_repo, "b", &json!({ "nonce": nonce, "changed": true }), None).await?;
+    prune_distilled_contents(&pool).await?;
+    let contents: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM distilled_contents WHERE sha256 = $1")
+        .bind(&sha256)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(contents, 0);
+
+    clear_distilled_json(&pool, &test_repo, None).await?;
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(&test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sheet_meta() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = "https://github.com/test/sheet-meta.git";
+    let update = sheet_meta::SheetMetaUpdate {
+        owner: Some("alice"),
+        subsystem: Some("Power"),
+        review_status: Some("in_review"),
+    };
+    let stored = sheet_meta::upsert_sheet_meta(&pool, repo_url, "power.kicad_sch", &update).await?.expect("parseable url");
+    assert_eq!(stored.owner.as_deref(), Some("alice"));
+
+    // Replacing clears fields that aren't given
+    let update = sheet_meta::SheetMetaUpdate { owner: Some("bob"), ..Default::default() };
+    sheet_meta::upsert_sheet_meta(&pool, repo_url, "power.kicad_sch", &update).await?;
+
+    // Any URL form of the repo finds the metadata
+    let listed = sheet_meta::list_sheet_meta(&pool, "git@github.com:Test/Sheet-Meta.git").await?;
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].owner.as_deref(), Some("bob"));
+    assert_eq!(listed[0].subsystem, None);
+
+    assert!(sheet_meta::upsert_sheet_meta(&pool, "not a url", "a.kicad_sch", &update).await?.is_none());
+
+    sqlx::query("DELETE FROM repos WHERE slug = 'test/sheet-meta'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_saved_views() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = "https://github.com/test/saved-views.git";
+    let selected = vec!["U1".to_string(), "C3".to_string()];
+    let zoom = json!({ "x": 10.0, "y": 20.0, "width": 50.0, "height": 30.0 });
+    let view = views::insert_view(&pool, &views::NewSavedView {
+        repo_url,
+        commit_hash: "abc",
+        name: "Regulator",
+        sheet_path: Some("power.kicad_sch"),
+        selected_components: &selected,
+        zoom: Some(&zoom),
+        notes: Some("check C3 rating"),
+        owner: None,
+        edit_token_hash: "hash",
+    }).await?.expect("parseable url");
+    assert_eq!(view.repo_url, repo_url);
+    assert_eq!(view.selected_components, selected);
+
+    let fetched = views::get_view(&pool, view.id).await?.expect("stored view");
+    assert_eq!(fetched.zoom, Some(zoom));
+    assert_eq!(views::list_views(&pool, repo_url, Some("abc"), 10).await?.len(), 1);
+    assert!(views::list_views(&pool, repo_url, Some("def"), 10).await?.is_empty());
+
+    // Deleting needs the edit token the view was created with
+    assert!(!views::delete_view(&pool, view.id, "wrong").await?);
+    assert!(views::delete_view(&pool, view.id, "hash").await?);
+    assert!(views::get_view(&pool, view.id).await?.is_none());
+
+    sqlx::query("DELETE FROM repos WHERE slug = 'test/saved-views'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_commit_comments() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = "https://github.com/test/commit-comments.git";
+    let comment = comments::NewComment {
+        repo_url,
+        commit_hash: "abc",
+        parent_id: None,
+        author: "alice",
+        body: "Is C3 rated for 25V?",
+        anchor_kind: Some("component"),
+        anchor: Some("C3"),
+    };
+    let root = comments::insert_comment(&pool, &comment).await?.expect("parseable url");
+    assert_eq!(root.repo_url, repo_url);
+
+    let reply = comments::NewComment {
+        parent_id: Some(root.id),
+        author: "bob",
+        body: "Yes, 50V part",
+        anchor_kind: None,
+        anchor: None,
+        ..comment
+    };
+    let reply = comments::insert_comment(&pool, &reply).await?.expect("parseable url");
+    assert_eq!(comments::get_comment(&pool, reply.id).await?.and_then(|c| c.parent_id), Some(root.id));
+
+    let listed = comments::list_comments(&pool, repo_url, "abc").await?;
+    assert_eq!(listed.iter().map(|c| c.id).collect::<Vec<_>>(), vec![root.id, reply.id]);
+    assert!(comments::list_comments(&pool, repo_url, "def").await?.is_empty());
+
+    // Deleting the repo removes its comments
+    sqlx::query("DELETE FROM repos WHERE slug = 'test/commit-comments'")
+        .execute(&pool)
+        .await?;
+    assert!(comments::get_comment(&pool, root.id).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_digest_subscriptions() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = "https://github.com/test/digests.git";
+    let request = |email: &'static str, frequency: &'static str| {
+        let pool = pool.clone();
+        async move {
+            let mut conn = pool.acquire().await?;
+            digests::request_confirmation(&mut conn, repo_url, email, frequency).await
+        }
+    };
+    let digests::ConfirmationRequest::Created { token } = request("Eve@example.com", "weekly").await? else {
+        panic!("expected a pending confirmation");
+    };
+    // Nothing is subscribed until the link is followed, and a link works once
+    let subscription = digests::confirm(&pool, &token).await?.expect("confirmed subscription");
+    assert_eq!(subscription.frequency, "weekly");
+    assert!(subscription.last_sent_at.is_none());
+    assert!(digests::confirm(&pool, &token).await?.is_none());
+
+    // A frequency change also needs confirming, and keeps the same subscription
+    let digests::ConfirmationRequest::Created { token } = request("eve@example.com", "daily").await? else {
+        panic!("expected a pending confirmation");
+    };
+    assert_eq!(request("eve@example.com", "off").await?, digests::ConfirmationRequest::Throttled);
+    let updated = digests::confirm(&pool, &token).await?.expect("confirmed subscription");
+    assert_eq!(updated.id, subscription.id);
+    assert_eq!(updated.frequency, "daily");
+    assert_eq!(updated.unsubscribe_token, subscription.unsubscribe_token);
+    // Confirming clears the pending request, so a new one isn't throttled
+    assert!(matches!(request("eve@example.com", "weekly").await?, digests::ConfirmationRequest::Created { .. }));
+    sqlx::query("DELETE FROM digest_confirmations WHERE LOWER(email) = 'eve@example.com'")
+        .execute(&pool)
+        .await?;
+
+    let is_due = |due: &[digests::DigestSubscription]| due.iter().any(|d| d.id == subscription.id);
+    assert!(!is_due(&digests::due_subscriptions(&pool, 1000).await?));
+    sqlx::query("UPDATE digest_subscriptions SET created_at = NOW() - INTERVAL '2 days' WHERE id = $1")
+        .bind(subscription.id)
+        .execute(&pool)
+        .await?;
+    let due = digests::due_subscriptions(&pool, 1000).await?;
+    let due = due.into_iter().find(|d| d.id == subscription.id).expect("due subscription");
+
+    // Commits in the period, with their metrics, and the baseline before it
+    let distilled = json!({ "components": {}, "nets": {} });
+    store_distilled_json(&pool, repo_url, "before", &distilled, None).await?;
+    store_distilled_json(&pool, repo_url, "during", &distilled, None).await?;
+    sqlx::query("UPDATE schematics SET commit_date = NOW() - INTERVAL '3 days' WHERE repo_url = $1 AND commit_hash = 'before'")
+        .bind(repo_url)
+        .execute(&pool)
+        .await?;
+    sqlx::query("UPDATE schematics SET commit_date = NOW() - INTERVAL '1 day', blurb = 'Added LDO' WHERE repo_url = $1 AND commit_hash = 'during'")
+        .bind(repo_url)
+        .execute(&pool)
+        .await?;
+    metrics::upsert_metrics(&pool, repo_url, "before", &metrics::NewDesignMetrics {
+        component_count: 10,
+        ..Default::default()
+    }).await?;
+
+    let now = chrono::Utc::now();
+    let commits = digests::commits_between(&pool, repo_url, due.period_start(), now).await?;
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].commit_hash, "during");
+    assert_eq!(commits[0].blurb.as_deref(), Some("Added LDO"));
+    assert_eq!(commits[0].component_count, None);
+    let baseline = digests::baseline_commit(&pool, repo_url, due.period_start()).await?.expect("baseline");
+    assert_eq!(baseline.commit_hash, "before");
+    assert_eq!(baseline.component_count, Some(10));
+
+    // Only one scheduler can claim a period
+    assert!(digests::mark_sent(&pool, &due, now).await?);
+    assert!(!digests::mark_sent(&pool, &due, now).await?);
+    assert!(!is_due(&digests::due_subscriptions(&pool, 1000).await?));
+
+    assert!(digests::unsubscribe(&pool, &subscription.unsubscribe_token).await?);
+    assert!(!digests::unsubscribe(&pool, &subscription.unsubscribe_token).await?);
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(repo_url)
+        .execute(&pool)
+        .await?;
+    sqlx::query("DELETE FROM repos WHERE slug = 'test/digests'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_audit_log() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = format!("https://github.com/test/audit-{}.git", Uuid::new_v4().simple());
+    let cleared = audit::record_action(&pool, "cache.cleared", "api", Some(&repo_url), Some("abc123"), &json!({ "scope": "commit" })).await?;
+    let onboarded = audit::record_action(&pool, "repo.onboarded", "webhook", Some(&repo_url), None, &json!({})).await?;
+
+    // Newest first, scoped to the repository ignoring case
+    let filter = audit::AuditFilter { repo_url: Some(repo_url.to_uppercase()), ..Default::default() };
+    let entries = audit::list_entries(&pool, &filter, 10).await?;
+    assert_eq!(entries.iter().map(|e| e.id).collect::<Vec<_>>(), vec![onboarded, cleared]);
+    assert_eq!(entries[1].commit_hash.as_deref(), Some("abc123"));
+    assert_eq!(entries[1].params["scope"], "commit");
+
+    let filter = audit::AuditFilter { repo_url: Some(repo_url.clone()), actor: Some("api".into()), ..Default::default() };
+    let entries = audit::list_entries(&pool, &filter, 10).await?;
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].action, "cache.cleared");
+
+    let filter = audit::AuditFilter { repo_url: Some(repo_url.clone()), since: Some(chrono::Utc::now() + chrono::Duration::hours(1)), ..Default::default() };
+    assert!(audit::list_entries(&pool, &filter, 10).await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_key_quotas() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let hash = Uuid::new_v4().simple().to_string();
+    let quotas = api_keys::KeyQuotas { request_quota: Some(2), ..Default::default() };
+    let key = api_keys::create_key(&pool, "tenant", &hash, "gk_test", &quotas).await?;
+    assert_eq!(api_keys::find_active_key(&pool, &hash).await?.map(|k| k.id), Some(key.id));
+
+    // Requests are counted until the quota is reached, then refused without counting
+    let period = chrono::NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+    assert_eq!(api_keys::charge_request(&pool, key.id, period, key.request_quota).await?.map(|u| u.requests), Some(1));
+    assert_eq!(api_keys::charge_request(&pool, key.id, period, key.request_quota).await?.map(|u| u.requests), Some(2));
+    assert!(api_keys::charge_request(&pool, key.id, period, key.request_quota).await?.is_none());
+    assert_eq!(api_keys::usage(&pool, key.id, period).await?.requests, 2);
+
+    // Each period starts from zero
+    let next_period = chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+    assert_eq!(api_keys::usage(&pool, key.id, next_period).await?.requests, 0);
+
+    api_keys::add_usage(&pool, key.id, period, 150, 2.5).await?;
+    api_keys::add_usage(&pool, key.id, period, 50, 0.5).await?;
+    let usage = api_keys::usage(&pool, key.id, period).await?;
+    assert_eq!(usage.llm_tokens, 200);
+    assert!((usage.cpu_seconds - 3.0).abs() < 1e-9);
+
+    // Raising the quota lets requests through again
+    let raised = api_keys::KeyQuotas { request_quota: Some(3), llm_token_quota: Some(1000), cpu_seconds_quota: None };
+    let key = api_keys::set_quotas(&pool, key.id, &raised).await?.expect("key exists");
+    assert_eq!(key.llm_token_quota, Some(1000));
+    assert!(api_keys::charge_request(&pool, key.id, period, key.request_quota).await?.is_some());
+
+    assert!(api_keys::revoke_key(&pool, key.id).await?);
+    assert!(!api_keys::revoke_key(&pool, key.id).await?);
+    assert!(api_keys::find_active_key(&pool, &hash).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_set_commit_info() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/commit-info.git";
+    let date = chrono::Utc::now();
+    assert!(!set_commit_info(&pool, test_repo, "abc", Some(date), Some("first")).await?);
+
+    store_distilled_json(&pool, test_repo, "abc", &json!({"components": {}}), None).await?;
+    assert!(set_commit_info(&pool, test_repo, "abc", Some(date), Some("first")).await?);
+    // Existing values are kept
+    assert!(set_commit_info(&pool, test_repo, "abc", None, Some("second")).await?);
+    let meta = retrieve_schematic_meta(&pool, test_repo, "abc").await?.expect("stored");
+    assert_eq!(meta.git_message.as_deref(), Some("first"));
+    assert!(meta.commit_date.is_some());
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_part_alternates() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = "https://github.com/test/part-alternates.git";
+    let added = alternates::add_alternate(&pool, repo_url, "LM317T", "LM317TG", Some("onsemi"), None).await?.expect("parseable url");
+    assert_eq!(added.alternate_mpn, "LM317TG");
+    // Approving again replaces the details
+    alternates::add_alternate(&pool, repo_url, "LM317T", "LM317TG", None, Some("same die")).await?;
+    alternates::add_alternate(&pool, repo_url, "LM317T", "LM317AT", None, None).await?;
+
+    let listed = alternates::list_alternates(&pool, "git@github.com:Test/Part-Alternates.git").await?;
+    assert_eq!(listed.iter().map(|a| a.alternate_mpn.as_str()).collect::<Vec<_>>(), vec!["LM317AT", "LM317TG"]);
+    assert_eq!(listed[1].manufacturer, None);
+    assert_eq!(listed[1].note.as_deref(), Some("same die"));
+
+    assert!(alternates::remove_alternate(&pool, repo_url, "LM317T", "LM317AT").await?);
+    assert!(!alternates::remove_alternate(&pool, repo_url, "LM317T", "LM317AT").await?);
+    assert_eq!(alternates::list_alternates(&pool, repo_url).await?.len(), 1);
+    assert!(alternates::add_alternate(&pool, "not a url", "A", "B", None, None).await?.is_none());
+
+    sqlx::query("DELETE FROM repos WHERE slug = 'test/part-alternates'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_exchange_rates() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let base = "XTS"; // ISO 4217 code reserved for testing
+    let day = chrono::NaiveDate::from_ymd_opt(2024, 3, 1).expect("valid date");
+    assert!(exchange_rates::latest_rates(&pool, base).await?.is_none());
+
+    exchange_rates::store_rates(&pool, base, day, &HashMap::from([("EUR".to_string(), 0.9)])).await?;
+    // Storing the same day again replaces its rates
+    exchange_rates::store_rates(&pool, base, day, &HashMap::from([("EUR".to_string(), 0.92)])).await?;
+    exchange_rates::store_rates(&pool, base, day.pred_opt().expect("valid date"), &HashMap::from([("EUR".to_string(), 0.5)])).await?;
+
+    let latest = exchange_rates::latest_rates(&pool, base).await?.expect("stored");
+    assert_eq!(latest.fetched_on, day);
+    assert_eq!(latest.by_currency().get("EUR"), Some(&0.92));
+
+    sqlx::query("DELETE FROM exchange_rates WHERE base = $1")
+        .bind(base)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_summary_review() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/summary-review.git";
+    let test_commit = "review123";
+    assert!(summaries::review_summary(&pool, test_repo, test_commit, summaries::REVIEW_APPROVED, "alice", None, None).await?.is_none());
+
+    store_schematic(&pool, test_repo, test_commit, None, None, None, None, Some("ai blurb"), Some("ai description"), HashMap::new()).await?;
+    let meta = retrieve_schematic_meta(&pool, test_repo, test_commit).await?.expect("stored");
+    assert_eq!(meta.summary_review_status, summaries::REVIEW_DRAFT);
+
+    // Approving with an edit keeps the untouched field
+    let review = summaries::review_summary(&pool, test_repo, test_commit, summaries::REVIEW_APPROVED, "alice", Some("edited blurb"), None)
+        .await?
+        .expect("stored");
+    assert_eq!(review.review_status, summaries::REVIEW_APPROVED);
+    assert_eq!(review.reviewed_by.as_deref(), Some("alice"));
+    assert!(review.reviewed_at.is_some());
+    assert_eq!(review.blurb.as_deref(), Some("edited blurb"));
+    assert_eq!(review.description.as_deref(), Some("ai description"));
+
+    // Storing the same generated text again keeps the review and the edit; new text goes back to draft
+    store_schematic(&pool, test_repo, test_commit, None, None, None, None, Some("ai blurb"), Some("ai description"), HashMap::new()).await?;
+    let meta = retrieve_schematic_meta(&pool, test_repo, test_commit).await?.expect("stored");
+    assert_eq!(meta.summary_review_status, summaries::REVIEW_APPROVED);
+    assert_eq!(meta.blurb.as_deref(), Some("edited blurb"));
+    assert_eq!(meta.generated_blurb.as_deref(), Some("ai blurb"));
+    assert!(meta.blurb_edited && !meta.description_edited);
+    assert_eq!(meta.summary_edited_by.as_deref(), Some("alice"));
+    let provenance = summaries::SummaryProvenance {
+        model: "test-model",
+        prompt_version: "review-test-v1",
+        distiller_version: "review-test-distiller",
+    };
+    summaries::update_summary(&pool, test_repo, test_commit, "regenerated", "regenerated description", &provenance).await?;
+    let meta = retrieve_schematic_meta(&pool, test_repo, test_commit).await?.expect("stored");
+    assert_eq!(meta.summary_review_status, summaries::REVIEW_DRAFT);
+    assert!(meta.summary_reviewed_by.is_none());
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_summary_override() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/summary-override.git";
+    let test_commit = "override123";
+    assert!(summaries::set_override(&pool, test_repo, test_commit, Some("x"), None, "bob").await?.is_none());
+
+    store_schematic(&pool, test_repo, test_commit, None, None, None, None, Some("ai blurb"), Some("ai description"), HashMap::new()).await?;
+    summaries::review_summary(&pool, test_repo, test_commit, summaries::REVIEW_APPROVED, "alice", None, None).await?;
+
+    let edited = summaries::set_override(&pool, test_repo, test_commit, None, Some("human description"), "bob")
+        .await?
+        .expect("stored");
+    assert_eq!(edited.generated_description.as_deref(), Some("ai description"));
+    assert_eq!(edited.description_override.as_deref(), Some("human description"));
+    assert!(edited.blurb_override.is_none());
+    assert_eq!(edited.edited_by.as_deref(), Some("bob"));
+    assert_eq!(edited.review_status, summaries::REVIEW_DRAFT);
+
+    // Reads prefer the edit, and regeneration keeps it
+    let provenance = summaries::SummaryProvenance {
+        model: "test-model",
+        prompt_version: "override-test-v1",
+        distiller_version: "override-test-distiller",
+    };
+    summaries::update_summary(&pool, test_repo, test_commit, "new ai blurb", "new ai description", &provenance).await?;
+    let meta = retrieve_schematic_meta(&pool, test_repo, test_commit).await?.expect("stored");
+    assert_eq!(meta.blurb.as_deref(), Some("new ai blurb"));
+    assert_eq!(meta.description.as_deref(), Some("human description"));
+    assert_eq!(meta.generated_description.as_deref(), Some("new ai description"));
+    assert!(meta.description_edited && !meta.blurb_edited);
+
+    // An empty edit restores the generated text
+    summaries::set_override(&pool, test_repo, test_commit, None, Some(""), "bob").await?;
+    let meta = retrieve_schematic_meta(&pool, test_repo, test_commit).await?.expect("stored");
+    assert_eq!(meta.description.as_deref(), Some("new ai description"));
+    assert!(!meta.description_edited);
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_speech_blurb() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/speech-blurb.git";
+    let test_commit = "speech123";
+    assert!(!summaries::store_speech_blurb(&pool, test_repo, test_commit, "blurb", "v1", "spoken").await?);
+
+    sqlx::query("INSERT INTO schematics (repo_url, commit_hash, blurb) VALUES ($1, $2, 'blurb')")
+        .bind(test_repo)
+        .bind(test_commit)
+        .execute(&pool)
+        .await?;
+    assert!(summaries::cached_speech_blurb(&pool, test_repo, test_commit, "blurb", "v1").await?.is_none());
+
+    assert!(summaries::store_speech_blurb(&pool, test_repo, test_commit, "blurb", "v1", "spoken").await?);
+    assert_eq!(
+        summaries::cached_speech_blurb(&pool, test_repo, test_commit, "blurb", "v1").await?.as_deref(),
+        Some("spoken")
+    );
+    // Made from other text or by other rules, it isn't used
+    assert!(summaries::cached_speech_blurb(&pool, test_repo, test_commit, "edited blurb", "v1").await?.is_none());
+    assert!(summaries::cached_speech_blurb(&pool, test_repo, test_commit, "blurb", "v2").await?.is_none());
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retrieval_documents() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/retrieval-docs.git";
+    let test_commit = "retrieval123";
+    let document = |category, key, sheet, text| retrieval::NewDocument {
+        category,
+        key,
+        sheet,
+        text,
+        embedding: &[0.6, 0.8],
+    };
+    assert!(retrieval::get_index(&pool, test_repo, test_commit).await?.is_none());
+    assert!(!retrieval::replace_documents(&pool, "not a repo", test_commit, "v1", &[]).await?);
+
+    let documents = [
+        document("component", "U1", Some("/power/"), "Component U1 (power)"),
+        document("net", "VBUS", None, "Net VBUS connects U1 pin 1"),
+        document("comment", "7", None, "Comment by alice"),
+    ];
+    assert!(retrieval::replace_documents(&pool, test_repo, test_commit, "v1", &documents).await?);
+    let index = retrieval::get_index(&pool, test_repo, test_commit).await?.expect("indexed");
+    assert_eq!((index.version.as_str(), index.documents), ("v1", 3));
+
+    let all = retrieval::list_documents(&pool, test_repo, test_commit, &Default::default()).await?;
+    let keys: Vec<&str> = all.iter().map(|d| d.key.as_str()).collect();
+    assert_eq!(keys, ["U1", "VBUS", "7"]);
+    assert_eq!(all[0].embedding, vec![0.6, 0.8]);
+
+    let filter = retrieval::DocumentFilter { sheet: Some("/power/"), categories: &[] };
+    let on_sheet = retrieval::list_documents(&pool, test_repo, test_commit, &filter).await?;
+    assert_eq!(on_sheet.len(), 1);
+    let filter = retrieval::DocumentFilter { sheet: None, categories: &["net", "comment"] };
+    let categories = retrieval::list_documents(&pool, test_repo, test_commit, &filter).await?;
+    assert_eq!(categories.iter().map(|d| d.key.as_str()).collect::<Vec<_>>(), ["VBUS", "7"]);
+
+    // Reindexing replaces every document of the commit
+    let documents = [document("sheet", "/power/", Some("/power/"), "Sheet /power/")];
+    assert!(retrieval::replace_documents(&pool, test_repo, test_commit, "v2", &documents).await?);
+    let all = retrieval::list_documents(&pool, test_repo, test_commit, &Default::default()).await?;
+    assert_eq!(all.len(), 1);
+    assert_eq!(retrieval::get_index(&pool, test_repo, test_commit).await?.expect("indexed").version, "v2");
+    assert!(retrieval::list_documents(&pool, test_repo, "other", &Default::default()).await?.is_empty());
+
+    sqlx::query("DELETE FROM repos WHERE LOWER(slug) = 'test/retrieval-docs'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repo_cards() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/repo-cards.git";
+    assert!(!repo_cards::refresh_card(&pool, "not a repo").await?);
+    assert!(!repo_cards::refresh_card(&pool, test_repo).await?);
+    assert!(repo_cards::get_card(&pool, test_repo).await?.is_none());
+
+    // A repository without commits still gets an empty card
+    let repo_id = repos::ensure_repo_id(&pool, test_repo).await?.expect("repo id");
+    assert!(repo_cards::refresh_card(&pool, test_repo).await?);
+    let card = repo_cards::get_card(&pool, test_repo).await?.expect("refreshed");
+    assert_eq!((card.commit_count, card.last_commit_hash), (0, None));
+
+    for (commit, date, blurb, image, status) in [
+        ("card1", "2024-01-01T00:00:00Z", Some("First board"), true, "approved"),
+        ("card2", "2024-02-01T00:00:00Z", Some("Rejected blurb"), false, "rejected"),
+        ("card3", "2024-03-01T00:00:00Z", None, false, "draft"),
+    ] {
+        sqlx::query(
+            r#"
+            INSERT INTO schematics (repo_url, repo_id, commit_hash, commit_date, git_message, blurb, summary_review_status)
+            VALUES ($1, $2, $3, $4::TIMESTAMPTZ, $3, $5, $6)
+            "#,
+        )
+        .bind(test_repo)
+        .bind(repo_id)
+        .bind(commit)
+        .bind(date)
+        .bind(blurb)
+        .bind(status)
+        .execute(&pool)
+        .await?;
+        if image {
+            record_test_image(&pool, test_repo, commit, 3).await?;
+        }
+    }
+    sqlx::query("INSERT INTO parts (schematic_id, part_uuid) SELECT id, 'p1' FROM schematics WHERE repo_id = $1 AND commit_hash = 'card3'")
+        .bind(repo_id)
+        .execute(&pool)
+        .await?;
+    sqlx::query("INSERT INTO commit_comments (repo_id, commit_hash, author, body) VALUES ($1, 'card1', 'alice', 'Looks good')")
+        .bind(repo_id)
+        .execute(&pool)
+        .await?;
+
+    // The card is only as fresh as its last refresh
+    let stale = repo_cards::get_card(&pool, test_repo).await?.expect("refreshed");
+    assert_eq!(stale.commit_count, 0);
+
+    assert!(repo_cards::refresh_card(&pool, test_repo).await?);
+    let card = repo_cards::get_card(&pool, test_repo).await?.expect("refreshed");
+    assert_eq!(card.repo_url, test_repo);
+    assert_eq!(card.last_commit_hash.as_deref(), Some("card3"));
+    assert_eq!(card.last_commit_message.as_deref(), Some("card3"));
+    assert_eq!((card.commit_count, card.distilled_count, card.part_count, card.comment_count), (3, 0, 1, 1));
+    assert_eq!(card.blurb.as_deref(), Some("First board"));
+    assert_eq!(card.blurb_commit_hash.as_deref(), Some("card1"));
+    assert_eq!(card.thumbnail_commit_hash.as_deref(), Some("card1"));
+    assert!(card.refreshed_at >= stale.refreshed_at);
+
+    sqlx::query("DELETE FROM blobs WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+    sqlx::query("DELETE FROM repos WHERE LOWER(slug) = 'test/repo-cards'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sync_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/sync-changes.git";
+    let start = sync::SyncCursor::default();
+    assert!(sync::changes_since(&pool, test_repo, start, 10).await?.changes.is_empty());
+
+    let repo_id = repos::ensure_repo_id(&pool, test_repo).await?.expect("repo id");
+    let schematic_id: i32 = sqlx::query_scalar(
+        "INSERT INTO schematics (repo_url, repo_id, commit_hash, blurb) VALUES ($1, $2, 'sync1', 'First') RETURNING id",
+    )
+    .bind(test_repo)
+    .bind(repo_id)
+    .fetch_one(&pool)
+    .await?;
+    sqlx::query("INSERT INTO parts (schematic_id, part_uuid, properties) VALUES ($1, 'p1', '{\"Reference\": \"R1\"}')")
+        .bind(schematic_id)
+        .execute(&pool)
+        .await?;
+
+    // Changes show once no transaction that started before them is running;
+    // other tests may hold one open briefly
+    let mut page = sync::changes_since(&pool, test_repo, start, 10).await?;
+    for _ in 0..50 {
+        if page.changes.len() == 2 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        page = sync::changes_since(&pool, test_repo, start, 10).await?;
+    }
+    assert_eq!(page.changes.len(), 2);
+    assert!(!page.has_more);
+    match (&page.changes[0], &page.changes[1]) {
+        (sync::Change::Commit(commit), sync::Change::Part(part)) => {
+            assert_eq!((commit.commit_hash.as_str(), commit.blurb.as_deref()), ("sync1", Some("First")));
+            assert_eq!((part.commit_hash.as_str(), part.part_uuid.as_str()), ("sync1", "p1"));
+        }
+        other => panic!("unexpected changes {:?}", other),
+    }
+
+    // Pages follow on from the cursor
+    let first = sync::changes_since(&pool, test_repo, start, 1).await?;
+    assert!(first.has_more);
+    assert_eq!(first.changes.len(), 1);
+    let rest = sync::changes_since(&pool, test_repo, first.next, 1).await?;
+    assert!(!rest.has_more);
+    assert!(matches!(rest.changes.as_slice(), [sync::Change::Part(_)]));
+    let caught_up = sync::changes_since(&pool, test_repo, page.next, 10).await?;
+    assert!(caught_up.changes.is_empty());
+    assert_eq!(caught_up.next, page.next);
+
+    // An edit moves the commit past the cursor
+    sqlx::query("UPDATE schematics SET blurb_override = 'Edited' WHERE id = $1")
+        .bind(schematic_id)
+        .execute(&pool)
+        .await?;
+    let mut edited = sync::changes_since(&pool, test_repo, page.next, 10).await?;
+    for _ in 0..50 {
+        if !edited.changes.is_empty() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        edited = sync::changes_since(&pool, test_repo, page.next, 10).await?;
+    }
+    match edited.changes.as_slice() {
+        [sync::Change::Commit(commit)] => assert_eq!(commit.blurb.as_deref(), Some("Edited")),
+        other => panic!("unexpected changes {:?}", other),
+    }
+    assert!(edited.next > page.next);
+
+    sqlx::query("DELETE FROM repos WHERE LOWER(slug) = 'test/sync-changes'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_secrets_rotation() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let (name, provider) = ("test/github-token", "test-rotation-provider");
+    let old = secrets::Keyring::new(&[7; 32], &[])?;
+    secrets::put_secret(&pool, &old, name, "ghp_old").await?;
+    secrets::put_secret(&pool, &old, name, "ghp_new").await?;
+    assert_eq!(secrets::get_secret(&pool, &old, name).await?.as_deref(), Some("ghp_new"));
+    assert!(secrets::get_secret(&pool, &old, "test/missing").await?.is_none());
+    let stored: Vec<u8> = sqlx::query_scalar("SELECT ciphertext FROM secrets WHERE name = $1")
+        .bind(name)
+        .fetch_one(&pool)
+        .await?;
+    assert!(!stored.windows(7).any(|w| w == b"ghp_new"));
+    let sealed = secrets::list_sealed(&pool).await?.into_iter().find(|s| s.name == name).expect("listed");
+    assert_eq!(sealed.ciphertext, stored);
+    assert_eq!(sealed.open(&old)?, "ghp_new");
+    credentials::upsert_credential(&pool, provider, "client", &old.seal(provider, "client-secret")?).await?;
+
+    // A new active key still opens what the old one sealed, until rotation reseals it
+    let rotated = secrets::Keyring::new(&[8; 32], &[&[7; 32]])?;
+    assert_eq!(secrets::get_secret(&pool, &rotated, name).await?.as_deref(), Some("ghp_new"));
+    let rotation = secrets::rotate(&pool, &rotated).await?;
+    assert!(rotation.secrets >= 1 && rotation.credentials >= 1);
+    assert!(!rotation.failed.iter().any(|f| f.ends_with(name) || f.ends_with(provider)));
+    let info = secrets::list_secrets(&pool).await?;
+    let entry = info.iter().find(|s| s.name == name).expect("listed");
+    assert_eq!(entry.key_id, rotated.active_key_id());
+
+    let new_only = secrets::Keyring::new(&[8; 32], &[])?;
+    assert_eq!(secrets::get_secret(&pool, &new_only, name).await?.as_deref(), Some("ghp_new"));
+    let row = credentials::list_credentials(&pool).await?.into_iter().find(|c| c.provider == provider).expect("stored");
+    assert_eq!(row.secret_key_id.as_deref(), Some(rotated.active_key_id()));
+    let secret = new_only.open(provider, row.secret_key_id.as_deref(), &row.secret_nonce, &row.secret_ciphertext)?;
+    assert_eq!(secret, "client-secret");
+    assert!(secrets::get_secret(&pool, &old, name).await.is_err());
+
+    assert!(secrets::delete_secret(&pool, name).await?);
+    assert!(!secrets::delete_secret(&pool, name).await?);
+    credentials::delete_credential(&pool, provider).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_secrets_notify_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let name = "test/notified-secret";
+    let keyring = secrets::Keyring::new(&[9; 32], &[])?;
+    let mut listener = sqlx::postgres::PgListener::connect_with(&pool).await?;
+    listener.listen(secrets::CHANGED_CHANNEL).await?;
+    let wait = std::time::Duration::from_secs(5);
+
+    secrets::put_secret(&pool, &keyring, name, "first").await?;
+    assert_eq!(tokio::time::timeout(wait, listener.recv()).await??.channel(), secrets::CHANGED_CHANNEL);
+    secrets::put_secret(&pool, &keyring, name, "second").await?;
+    tokio::time::timeout(wait, listener.recv()).await??;
+    assert!(secrets::delete_secret(&pool, name).await?);
+    tokio::time::timeout(wait, listener.recv()).await??;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_analysis_sessions() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/analysis-sessions.git";
+    assert!(analysis_sessions::create_session(&pool, "not a url", "abc", "ctx", None).await?.is_none());
+
+    let distilled = json!({"components": {}});
+    let session = analysis_sessions::create_session(&pool, test_repo, "abc", "schematic context", Some(&distilled))
+        .await?
+        .expect("created");
+    assert_eq!(session.repo_url, test_repo);
+    assert_eq!(session.distilled_json, Some(distilled));
+    assert!(session.turn_messages().is_empty());
+
+    // Only the latest turns are kept
+    let components = vec!["U1".to_string()];
+    for n in 1..=3 {
+        let (user, assistant) = (format!("q{}", n), format!("a{}", n));
+        let turn = analysis_sessions::NewTurn { user: &user, assistant: &assistant, question: "why?", components: &components };
+        assert!(analysis_sessions::append_turn(&pool, session.id, &turn, 2).await?);
+    }
+    let stored = analysis_sessions::get_session(&pool, session.id, 3600).await?.expect("active");
+    assert_eq!(
+        stored.turn_messages(),
+        vec![("q2".to_string(), "a2".to_string()), ("q3".to_string(), "a3".to_string())]
+    );
+    let turns = stored.turn_list();
+    assert_eq!(turns[0].question.as_deref(), Some("why?"));
+    assert_eq!(turns[0].components, components);
+
+    // Turns stored before questions and components were kept still read
+    sqlx::query(r#"UPDATE analysis_sessions SET turns = '[{"user": "old", "assistant": "answer"}]' WHERE id = $1"#)
+        .bind(session.id)
+        .execute(&pool)
+        .await?;
+    let old = analysis_sessions::find_session(&pool, session.id).await?.expect("exists");
+    assert_eq!(old.turn_list()[0].question, None);
+    assert!(old.turn_list()[0].components.is_empty());
+
+    assert!(analysis_sessions::delete_session(&pool, session.id).await?);
+    assert!(analysis_sessions::get_session(&pool, session.id, 3600).await?.is_none());
+    let turn = analysis_sessions::NewTurn { user: "q", assistant: "a", question: "q", components: &[] };
+    assert!(!analysis_sessions::append_turn(&pool, session.id, &turn, 2).await?);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_commit_risk() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/commit-risk.git";
+    let distilled = json!({ "components": {}, "nets": {} });
+    store_distilled_json(&pool, test_repo, "calm", &distilled, None).await?;
+    store_distilled_json(&pool, test_repo, "risky", &distilled, None).await?;
+
+    assert_eq!(risk::commits_without_risk(&pool, test_repo, "v1", 10).await?.len(), 2);
+
+    let factors = json!([{ "kind": "power_net_change", "weight": 5, "count": 2, "score": 10, "items": ["+3V3", "GND"] }]);
+    assert!(risk::upsert_risk(&pool, test_repo, "risky", 10, &factors, "v1").await?);
+    risk::upsert_risk(&pool, test_repo, "calm", 0, &json!([]), "v1").await?;
+    // Unknown commits have nowhere to store a score
+    assert!(!risk::upsert_risk(&pool, test_repo, "missing", 1, &json!([]), "v1").await?);
+
+    let scores = risk::risk_scores(&pool, test_repo).await?;
+    assert_eq!(scores.iter().map(|r| r.commit_hash.as_str()).collect::<Vec<_>>(), vec!["risky", "calm"]);
+    assert_eq!(scores[0].factors, factors);
+    assert!(risk::commits_without_risk(&pool, test_repo, "v1", 10).await?.is_empty());
+
+    // A new scorer version rescores everything
+    assert_eq!(risk::commits_without_risk(&pool, test_repo, "v2", 10).await?.len(), 2);
+    risk::upsert_risk(&pool, test_repo, "calm", 3, &json!([]), "v2").await?;
+    assert_eq!(risk::commits_without_risk(&pool, test_repo, "v2", 10).await?, vec!["risky".to_string()]);
+
+    sqlx::query("DELETE FROM schematics WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_design_rules() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/design-rules.git";
+    let decoupling = json!({ "applies_to": { "category": "ic" }, "pins": "VDD*", "require": { "category": "capacitor" } });
+    let esd = json!({ "applies_to": { "category": "connector" }, "require": { "value": "*ESD*" }, "relation": "nearby", "max_distance_mm": 20.0 });
+
+    let stored = design_rules::upsert_rule(&pool, test_repo, "decoupling", &decoupling, true, Some("alice"))
+        .await?
+        .expect("repository URL parses");
+    assert_eq!(stored.definition, decoupling);
+    design_rules::upsert_rule(&pool, test_repo, "connector-esd", &esd, false, None).await?;
+
+    let all = design_rules::list_rules(&pool, test_repo, false).await?;
+    assert_eq!(all.iter().map(|r| r.name.as_str()).collect::<Vec<_>>(), vec!["connector-esd", "decoupling"]);
+    let enabled = design_rules::list_rules(&pool, test_repo, true).await?;
+    assert_eq!(enabled.len(), 1);
+    assert_eq!(enabled[0].updated_by.as_deref(), Some("alice"));
+
+    // Storing a rule again replaces it
+    design_rules::upsert_rule(&pool, test_repo, "connector-esd", &esd, true, Some("bob")).await?;
+    assert_eq!(design_rules::list_rules(&pool, test_repo, true).await?.len(), 2);
+
+    assert!(design_rules::delete_rule(&pool, test_repo, "decoupling").await?);
+    assert!(!design_rules::delete_rule(&pool, test_repo, "decoupling").await?);
+    assert_eq!(design_rules::list_rules(&pool, test_repo, false).await?.len(), 1);
+    assert!(design_rules::list_rules(&pool, "not a url", false).await?.is_empty());
+
+    sqlx::query("DELETE FROM repos WHERE slug = 'test/design-rules'")
+        .execute(&pool)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_commit_quarantine() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let test_repo = "https://github.com/test/quarantine.git";
+    let commit = "poison123";
+    sqlx::query("DELETE FROM commit_failures WHERE repo_url = $1")
+        .bind(test_repo)
+        .execute(&pool)
+        .await?;
+
+    // Quarantined on the third failure, and the count keeps going afterwards
+    for attempt in 1..=3 {
+        let failure = quarantine::record_failure(&pool, test_repo, commit, "distill", &format!("boom {}", attempt), 3).await?;
+        assert_eq!(failure.failures, attempt);
+        assert_eq!(failure.quarantined_at.is_some(), attempt == 3);
+    }
+    let failure = quarantine::record_failure(&pool, test_repo, commit, "distill", "boom 4", 3).await?;
+    assert_eq!(failure.failures, 4);
+    assert_eq!(failure.last_error.as_deref(), Some("boom 4"));
+    assert!(quarantine::get_quarantined(&pool, test_repo, commit, "distill").await?.is_some());
+    assert!(quarantine::get_quarantined(&pool, test_repo, commit, "overview").await?.is_none());
+    assert_eq!(quarantine::quarantined_commits(&pool, test_repo, "distill").await?, vec![commit.to_string()]);
+
+    // A limit of 0 never quarantines; a success forgets the failures
+    quarantine::record_failure(&pool, test_repo, "flaky456", "distill", "timeout", 0).await?;
+    assert!(quarantine::get_quarantined(&pool, test_repo, "flaky456", "distill").await?.is_none());
+    quarantine::clear_failures(&pool, test_repo, "flaky456", "distill").await?;
+
+    let listed = quarantine::list_quarantined(&pool, Some("HTTPS://github.com/test/quarantine.git"), None, 10).await?;
+    assert_eq!(listed.len(), 1);
+    assert!(quarantine::list_quarantined(&pool, Some(test_repo), Some("overview"), 10).await?.is_empty());
+
+    assert!(quarantine::release(&pool, test_repo, commit, Some("overview")).await?.is_empty());
+    let released = quarantine::release(&pool, test_repo, commit, None).await?;
+    assert_eq!(released.len(), 1);
+    assert!(quarantine::quarantined_commits(&pool, test_repo, "distill").await?.is_empty());
+
+    let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM commit_failures WHERE repo_url = $1")
+        .bind(test_repo)
+        .fetch_one(&pool)
+        .await?;
+    assert_eq!(remaining, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_schema_up_to_date() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    // The test database is initialised from init.sql
+    assert_eq!(schema::missing_columns(&pool).await?, Vec::<String>::new());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_provenance() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    // Records are append-only, so every run writes under a repository of its own
+    let repo_url = format!("https://github.com/test-provenance/{}.git", Uuid::new_v4());
+    let tool_calls = vec![provenance::ToolCall {
+        name: "web_search".to_string(),
+        call_id: Some("call-1".to_string()),
+        status: Some("completed".to_string()),
+        input: Some("LM317 replacement".to_string()),
+    }];
+    let record = provenance::record_provenance(&pool, &provenance::NewProvenance {
+        artifact: provenance::ARTIFACT_ALTERNATE,
+        artifact_ref: "LM317",
+        repo_url: Some(&repo_url),
+        commit_hash: None,
+        model: "grok-test",
+        prompt_version: "v1",
+        prompt: Some("Find a replacement for LM317"),
+        tool_calls: &tool_calls,
+        inputs: &[("mpn", b"LM317")],
+        outputs: &[("analysis", b"Use LM1117")],
+    }).await?;
+    assert!(record.verify());
+    assert_eq!(record.prompt_sha256.as_deref(), Some(provenance::sha256_hex(b"Find a replacement for LM317").as_str()));
+    assert_eq!(record.output_hashes["analysis"], provenance::sha256_hex(b"Use LM1117"));
+
+    let stored = provenance::get_provenance(&pool, record.id).await?.expect("stored");
+    assert!(stored.verify());
+    assert_eq!(serde_json::from_value::<Vec<provenance::ToolCall>>(stored.tool_calls)?, tool_calls);
+
+    let filter = provenance::ProvenanceFilter {
+        repo_url: Some(repo_url.to_uppercase()),
+        ..Default::default()
+    };
+    let listed = provenance::list_provenance(&pool, &filter, 10).await?;
+    assert_eq!(listed.iter().map(|r| r.id).collect::<Vec<_>>(), vec![record.id]);
+    let filter = provenance::ProvenanceFilter {
+        artifact: Some(provenance::ARTIFACT_SUMMARY.to_string()),
+        ..filter
+    };
+    assert!(provenance::list_provenance(&pool, &filter, 10).await?.is_empty());
+
+    // Neither changed nor removed
+    let updated = sqlx::query("UPDATE ai_provenance SET model = 'other' WHERE id = $1")
+        .bind(record.id)
+        .execute(&pool)
+        .await;
+    assert!(updated.is_err());
+    let deleted = sqlx::query("DELETE FROM ai_provenance WHERE id = $1")
+        .bind(record.id)
+        .execute(&pool)
+        .await;
+    assert!(deleted.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_summary_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let diff_sha256 = canonical::content_hash(&json!({"run": Uuid::new_v4().to_string()}));
+    let new = summary_cache::NewCachedSummary {
+        diff_sha256: &diff_sha256,
+        model: "grok-test",
+        prompt_version: "v1",
+        summary: "Adds a decoupling capacitor",
+        details: "C12 on +3V3",
+        repo_url: "https://github.com/test-summary-cache/board.git",
+        commit_hash: "abc123",
+        provenance_id: Some(42),
+    };
+    assert!(summary_cache::reuse_summary(&pool, new.repo_url, &diff_sha256, "grok-test", "v1").await?.is_none());
+    assert!(summary_cache::store_summary(&pool, &new).await?);
+
+    // A cherry-pick with the same diff keeps the first summary
+    let cherry_pick = summary_cache::NewCachedSummary {
+        summary: "Something else",
+        commit_hash: "def456",
+        ..new.clone()
+    };
+    assert!(!summary_cache::store_summary(&pool, &cherry_pick).await?);
+
+    let cached = summary_cache::reuse_summary(&pool, new.repo_url, &diff_sha256, "grok-test", "v1").await?.expect("cached");
+    assert_eq!(cached.summary, "Adds a decoupling capacitor");
+    assert_eq!(cached.commit_hash, "abc123");
+    assert_eq!(cached.provenance_id, Some(42));
+    assert_eq!(cached.hits, 1);
+
+    // Another model or prompt version generates its own
+    assert!(summary_cache::reuse_summary(&pool, new.repo_url, &diff_sha256, "grok-test", "v2").await?.is_none());
+    assert!(summary_cache::reuse_summary(&pool, new.repo_url, &diff_sha256, "other", "v1").await?.is_none());
+
+    // Another repository with the same diff doesn't see it, and stores its own
+    let other_repo = "https://github.com/test-summary-cache/other.git";
+    assert!(summary_cache::reuse_summary(&pool, other_repo, &diff_sha256, "grok-test", "v1").await?.is_none());
+    let other = summary_cache::NewCachedSummary {
+        summary: "Other repository's summary",
+        repo_url: other_repo,
+        ..new.clone()
+    };
+    assert!(summary_cache::store_summary(&pool, &other).await?);
+    let cached = summary_cache::reuse_summary(&pool, other_repo, &diff_sha256, "grok-test", "v1").await?.expect("cached");
+    assert_eq!(cached.summary, "Other repository's summary");
+
+    sqlx::query("DELETE FROM summary_cache WHERE diff_sha256 = $1")
+        .bind(&diff_sha256)
+        .execute(&pool)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_summaries() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = format!("https://github.com/test-file-summaries/{}.git", Uuid::new_v4());
+    let summaries = vec![
+        ("power.kicad_sch".to_string(), "Adds a buck converter".to_string()),
+        ("mcu.kicad_sch".to_string(), "Moves the MCU to 3V3".to_string()),
+    ];
+    file_summaries::store_file_summaries(&pool, &repo_url, "abc123", "grok-test", "v1", &summaries).await?;
+
+    let listed = file_summaries::list_file_summaries(&pool, &repo_url, "abc123").await?;
+    assert_eq!(listed.iter().map(|f| f.file_path.as_str()).collect::<Vec<_>>(), vec!["mcu.kicad_sch", "power.kicad_sch"]);
+    assert_eq!(listed[1].summary, "Adds a buck converter");
+
+    // Summarizing again replaces the file's summary
+    let again = vec![("power.kicad_sch".to_string(), "Adds a 5V buck converter".to_string())];
+    file_summaries::store_file_summaries(&pool, &repo_url, "abc123", "grok-test", "v2", &again).await?;
+    let listed = file_summaries::list_file_summaries(&pool, &repo_url, "abc123").await?;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[1].summary, "Adds a 5V buck converter");
+    assert_eq!(listed[1].prompt_version, "v2");
+    assert!(file_summaries::list_file_summaries(&pool, &repo_url, "def456").await?.is_empty());
+
+    sqlx::query("DELETE FROM file_summaries WHERE repo_url = $1")
+        .bind(&repo_url)
+        .execute(&pool)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_net_explanations() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = format!("https://github.com/test-net-explanations/{}.git", Uuid::new_v4());
+    let new = net_explanations::NewNetExplanation {
+        repo_url: &repo_url,
+        commit_hash: "abc123",
+        net_name: "NRST",
+        context_sha256: "ctx-1",
+        explanation: "Reset line with a 10k pull-up",
+        model: "grok-test",
+        prompt_version: "v1",
+    };
+    let stored = net_explanations::store_explanation(&pool, &new).await?;
+    assert_eq!(stored.explanation, "Reset line with a 10k pull-up");
+
+    // Found from another commit when the context is unchanged
+    let found = net_explanations::find_explanation(&pool, &repo_url, "NRST", "ctx-1", "v1").await?.expect("stored");
+    assert_eq!(found.commit_hash, "abc123");
+    assert!(net_explanations::find_explanation(&pool, &repo_url, "NRST", "ctx-2", "v1").await?.is_none());
+    assert!(net_explanations::find_explanation(&pool, &repo_url, "NRST", "ctx-1", "v2").await?.is_none());
+
+    // Regenerating at the same commit replaces the explanation
+    let regenerated = net_explanations::NewNetExplanation {
+        context_sha256: "ctx-2",
+        explanation: "Reset line, pulled up and filtered",
+        ..new.clone()
+    };
+    net_explanations::store_explanation(&pool, &regenerated).await?;
+    assert!(net_explanations::find_explanation(&pool, &repo_url, "NRST", "ctx-1", "v1").await?.is_none());
+    let found = net_explanations::find_explanation(&pool, &repo_url, "NRST", "ctx-2", "v1").await?.expect("replaced");
+    assert_eq!(found.explanation, "Reset line, pulled up and filtered");
+
+    sqlx::query("DELETE FROM net_explanations WHERE repo_url = $1")
+        .bind(&repo_url)
+        .execute(&pool)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repo_suggestions() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = format!("https://github.com/test/suggestions-{}.git", Uuid::new_v4());
+    let suggestions = serde_json::json!([
+        {"question": "How is the USB-PD contract negotiated?", "topic": "USB-PD", "sheet": "power.kicad_sch", "references": ["U3"]}
+    ]);
+    let new = repo_suggestions::NewRepoSuggestions {
+        commit_hash: "abc123",
+        suggestions: &suggestions,
+        model: "grok-4-1-fast",
+        prompt_version: "v1",
+    };
+
+    // Only for known repositories
+    assert!(!repo_suggestions::store_suggestions(&pool, &repo_url, &new).await?);
+    assert!(repo_suggestions::get_suggestions(&pool, &repo_url).await?.is_none());
+
+    let repo_id = repos::ensure_repo_id(&pool, &repo_url).await?.expect("repo id");
+    assert!(repo_suggestions::store_suggestions(&pool, &repo_url, &new).await?);
+    let stored = repo_suggestions::get_suggestions(&pool, &repo_url).await?.expect("stored");
+    assert_eq!(stored.repo_url, repo_url);
+    assert_eq!(stored.commit_hash, "abc123");
+    assert_eq!(stored.suggestions, suggestions);
+
+    // A later generation replaces the set
+    let empty = serde_json::json!([]);
+    let replaced = repo_suggestions::NewRepoSuggestions {
+        commit_hash: "def456",
+        suggestions: &empty,
+        model: "deterministic",
+        ..new.clone()
+    };
+    assert!(repo_suggestions::store_suggestions(&pool, &repo_url, &replaced).await?);
+    let stored = repo_suggestions::get_suggestions(&pool, &repo_url).await?.expect("stored");
+    assert_eq!((stored.commit_hash.as_str(), stored.model.as_str()), ("def456", "deterministic"));
+    assert_eq!(stored.suggestions, empty);
+
+    sqlx::query("DELETE FROM repos WHERE id = $1")
+        .bind(repo_id)
+        .execute(&pool)
+        .await?;
+    assert!(repo_suggestions::get_suggestions(&pool, &repo_url).await?.is_none());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_component_notes() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = "https://github.com/test/component-notes.git";
+    let stored = component_notes::upsert_note(&pool, repo_url, "U3", "Rev A needs the 5V bodge wire", Some("alice")).await?.expect("parseable url");
+    assert_eq!((stored.reference.as_str(), stored.author.as_deref()), ("U3", Some("alice")));
+    component_notes::upsert_note(&pool, repo_url, "R12", "Sets the charge current", None).await?;
+
+    // Replacing a note replaces its author too
+    component_notes::upsert_note(&pool, repo_url, "U3", "Fixed in rev B", None).await?;
+
+    // Any URL form of the repo finds the notes
+    let listed = component_notes::list_notes(&pool, "git@github.com:Test/Component-Notes.git").await?;
+    let listed: Vec<(&str, &str, Option<&str>)> = listed.iter().map(|n| (n.reference.as_str(), n.note.as_str(), n.author.as_deref())).collect();
+    assert_eq!(listed, vec![("R12", "Sets the charge current", None), ("U3", "Fixed in rev B", None)]);
+
+    assert!(component_notes::delete_note(&pool, repo_url, "R12").await?);
+    assert!(!component_notes::delete_note(&pool, repo_url, "R12").await?);
+    assert_eq!(component_notes::list_notes(&pool, repo_url).await?.len(), 1);
+
+    assert!(component_notes::upsert_note(&pool, "not a url", "U1", "note", None).await?.is_none());
+    assert!(!component_notes::delete_note(&pool, "not a url", "U1").await?);
+
+    sqlx::query("DELETE FROM repos WHERE slug = 'test/component-notes'")
+        .execute(&pool)
+        .await?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_default_branch_changes() -> Result<(), Box<dyn std::error::Error>> {
+    let pool = match create_pool().await {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Warning: Could not connect to DB ({}). Skipping integration test. Run `./database-up.sh` first.", e);
+            return Ok(());
+        }
+    };
+
+    let repo_url = "https://github.com/test/default-branch.git";
+    // Only tracked repositories are recorded
+    assert!(default_branches::record_default_branch(&pool, repo_url, "master", "aaa").await?.is_none());
+    assert_eq!(default_branches::get_default_branch(&pool, repo_url).await?, None);
+    repos::ensure_repo_id(&pool, repo_url).await?;
+
+    // The first branch seen, and seeing it again, aren't changes
+    assert!(default_branches::record_default_branch(&pool, repo_url, "master", "aaa").await?.is_none());
+    assert!(default_branches::record_default_branch(&pool, repo_url, "master", "bbb").await?.is_none());
+    assert_eq!(default_branches::get_default_branch(&pool, repo_url).await?.as_deref(), Some("master"));
+
+    let change = default_branches::record_default_branch(&pool, repo_url, "main", "ccc").await?.expect("branch changed");
+    assert_eq!((change.old_branch.as_str(), change.new_branch.as_str(), change.head_commit.as_str()), ("master", "main", "ccc"));
+    assert!(default_branches::record_default_branch(&pool, repo_url, "main", "ddd").await?.is_none());
+    default_branches::record_default_branch(&pool, repo_url, "develop", "eee").await?.expect("branch changed");
+
+    // Newest first, found by any URL form of the repo
+    let changes = default_branches::list_changes(&pool, "git@github.com:Test/Default-Branch.git").await?;
+    let changes: Vec<(&str, &str)> = changes.iter().map(|c| (c.old_branch.as_str(), c.new_branch.as_str())).collect();
+    assert_eq!(changes, vec![("main", "develop"), ("master", "main")]);
+    assert_eq!(default_branches::get_default_branch(&pool, repo_url).await?.as_deref(), Some("develop"));
+
+    sqlx::query("DELETE FROM repos WHERE slug = 'test/default-branch'")
+        .execute(&pool)
+        .await?;
+    Ok(())
+}
@@ -97,6 +97,27 @@ export interface CommitInfoResponse {
     changed_files: string[];
 }
 
+export type SummaryFeedbackTarget = "blurb" | "description" | "commit_summary";
+
+export interface SummaryFeedbackRequest {
+    repo: string;
+    commit: string;
+    target: SummaryFeedbackTarget;
+    /** 1 for thumbs up, -1 for thumbs down */
+    rating: 1 | -1;
+    comment?: string;
+    /** Required for commit_summary: the text, model and prompt version that were shown */
+    summary_text?: string;
+    model?: string;
+    prompt_version?: string;
+}
+
+export interface SummaryFeedbackResponse {
+    id: number;
+    model: string | null;
+    prompt_version: string | null;
+}
+
 export interface DistillResponse {
     repo: string;
     commit: string;
@@ -130,6 +151,53 @@ export interface RepoClearCacheResponse {
     message: string;
 }
 
+export interface GithubReleaseInfo {
+    name: string | null;
+    url: string;
+    published_at: string | null;
+    draft: boolean;
+    prerelease: boolean;
+}
+
+export interface RepoRelease {
+    /** Tag name, usable anywhere a commit hash is accepted */
+    tag: string;
+    /** Null for GitHub releases whose tag isn't in the repository */
+    commit: string | null;
+    commit_date: string | null;
+    message: string | null;
+    previous_tag: string | null;
+    schematic_commits: number;
+    touches_schematics: boolean;
+    github_release: GithubReleaseInfo | null;
+}
+
+export interface RepoReleasesResponse {
+    repo: string;
+    github_releases_available: boolean;
+    releases: RepoRelease[];
+}
+
+export interface StoredCommit {
+    commit: string;
+    commit_date: string | null;
+    message: string | null;
+    has_blurb: boolean;
+    has_description: boolean;
+    has_distilled: boolean;
+    has_image: boolean;
+    part_count: number;
+    stored_at: string;
+}
+
+export interface StoredCommitsResponse {
+    repo: string;
+    total: number;
+    limit: number;
+    offset: number;
+    commits: StoredCommit[];
+}
+
 export interface DistilledSchematic {
     components: DistilledComponent[];
     nets: Record<string, Record<string, { Pin: string }[]>>;
@@ -174,11 +242,74 @@ export interface GrokSelectionRequest {
 export class GrokiAPI {
     private static baseUrl = API_BASE_URL;
 
+    // Most recent ETag-tagged responses, keyed by endpoint and request body
+    private static etagCache = new Map<string, { etag: string; data: unknown }>();
+    private static readonly etagCacheSize = 20;
+
     /**
      * Set the API base URL (useful for testing or different environments)
      */
     static setBaseUrl(url: string): void {
         this.baseUrl = url;
+        this.etagCache.clear();
+    }
+
+    /**
+     * POST a JSON request to an endpoint that returns an ETag.
+     * A previous response is revalidated with If-None-Match and reused when
+     * the backend answers 304, so unchanged payloads aren't downloaded again.
+     */
+    private static async postWithETag<T>(
+        path: string,
+        body: unknown,
+        errorLabel: string,
+    ): Promise<T> {
+        const payload = JSON.stringify(body);
+        const key = `${path} ${payload}`;
+        const cached = this.etagCache.get(key);
+
+        const headers: Record<string, string> = {
+            "Content-Type": "application/json",
+        };
+        if (cached) {
+            headers["If-None-Match"] = cached.etag;
+        }
+
+        const response = await fetch(`${this.baseUrl}${path}`, {
+            method: "POST",
+            headers,
+            body: payload,
+        });
+
+        if (response.status === 304 && cached) {
+            // Refresh recency so frequently used entries survive eviction
+            this.etagCache.delete(key);
+            this.etagCache.set(key, cached);
+            return cached.data as T;
+        }
+
+        if (!response.ok) {
+            const errorText = await response.text().catch(() => "");
+            throw new Error(
+                `Failed to ${errorLabel}: ${response.status} ${
+                    response.statusText
+                }${errorText ? ` - ${errorText}` : ""}`,
+            );
+        }
+
+        const data: T = await response.json();
+        const etag = response.headers.get("ETag");
+        if (etag) {
+            this.etagCache.delete(key);
+            this.etagCache.set(key, { etag, data });
+            if (this.etagCache.size > this.etagCacheSize) {
+                const oldest = this.etagCache.keys().next().value;
+                if (oldest !== undefined) {
+                    this.etagCache.delete(oldest);
+                }
+            }
+        }
+        return data;
     }
 
     /**
@@ -216,32 +347,33 @@ export class GrokiAPI {
     }
 
     /**
-     * Get all .kicad_sch files at a specific commit
+     * List commits the backend has already processed, newest first, with flags
+     * for which artifacts (blurb, description, distilled data, image) exist
      */
-    static async getCommitFiles(
+    static async getStoredCommits(
         repo: string,
-        commit: string,
-    ): Promise<SchematicFile[]> {
+        limit?: number,
+        offset = 0,
+    ): Promise<StoredCommitsResponse> {
         try {
-            const response = await fetch(`${this.baseUrl}/repo/commit/files`, {
+            const response = await fetch(`${this.baseUrl}/repo/stored`, {
                 method: "POST",
                 headers: {
                     "Content-Type": "application/json",
                 },
-                body: JSON.stringify({ repo, commit }),
+                body: JSON.stringify({ repo, limit, offset }),
             });
 
             if (!response.ok) {
                 const errorText = await response.text().catch(() => "");
                 throw new Error(
-                    `Failed to fetch commit files: ${response.status} ${
+                    `Failed to fetch stored commits: ${response.status} ${
                         response.statusText
                     }${errorText ? ` - ${errorText}` : ""}`,
                 );
             }
 
-            const data: CommitFilesResponse = await response.json();
-            return data.files;
+            return await response.json();
         } catch (e) {
             if (e instanceof TypeError && e.message.includes("fetch")) {
                 throw new Error(
@@ -253,25 +385,22 @@ export class GrokiAPI {
     }
 
     /**
-     * Get detailed information about a specific commit
+     * Get tags and GitHub releases, newest first, flagging which change schematics
      */
-    static async getCommitInfo(
-        repo: string,
-        commit: string,
-    ): Promise<CommitInfoResponse> {
+    static async getReleases(repo: string): Promise<RepoReleasesResponse> {
         try {
-            const response = await fetch(`${this.baseUrl}/repo/commit/info`, {
+            const response = await fetch(`${this.baseUrl}/repo/releases`, {
                 method: "POST",
                 headers: {
                     "Content-Type": "application/json",
                 },
-                body: JSON.stringify({ repo, commit }),
+                body: JSON.stringify({ repo }),
             });
 
             if (!response.ok) {
                 const errorText = await response.text().catch(() => "");
                 throw new Error(
-                    `Failed to fetch commit info: ${response.status} ${
+                    `Failed to fetch releases: ${response.status} ${
                         response.statusText
                     }${errorText ? ` - ${errorText}` : ""}`,
                 );
@@ -289,14 +418,38 @@ export class GrokiAPI {
     }
 
     /**
-     * Get distilled schematic data for a specific commit
+     * Get all .kicad_sch files at a specific commit
      */
-    static async getDistilledSchematic(
+    static async getCommitFiles(
         repo: string,
         commit: string,
-    ): Promise<DistilledSchematic> {
+    ): Promise<SchematicFile[]> {
+        try {
+            const data = await this.postWithETag<CommitFilesResponse>(
+                "/repo/commit/files",
+                { repo, commit },
+                "fetch commit files",
+            );
+            return data.files;
+        } catch (e) {
+            if (e instanceof TypeError && e.message.includes("fetch")) {
+                throw new Error(
+                    `Cannot connect to API at ${this.baseUrl}. Is the backend running?`,
+                );
+            }
+            throw e;
+        }
+    }
+
+    /**
+     * Get detailed information about a specific commit
+     */
+    static async getCommitInfo(
+        repo: string,
+        commit: string,
+    ): Promise<CommitInfoResponse> {
         try {
-            const response = await fetch(`${this.baseUrl}/distill`, {
+            const response = await fetch(`${this.baseUrl}/repo/commit/info`, {
                 method: "POST",
                 headers: {
                     "Content-Type": "application/json",
@@ -307,13 +460,36 @@ export class GrokiAPI {
             if (!response.ok) {
                 const errorText = await response.text().catch(() => "");
                 throw new Error(
-                    `Failed to fetch distilled schematic: ${response.status} ${
+                    `Failed to fetch commit info: ${response.status} ${
                         response.statusText
                     }${errorText ? ` - ${errorText}` : ""}`,
                 );
             }
 
-            const data: DistillResponse = await response.json();
+            return await response.json();
+        } catch (e) {
+            if (e instanceof TypeError && e.message.includes("fetch")) {
+                throw new Error(
+                    `Cannot connect to API at ${this.baseUrl}. Is the backend running?`,
+                );
+            }
+            throw e;
+        }
+    }
+
+    /**
+     * Get distilled schematic data for a specific commit
+     */
+    static async getDistilledSchematic(
+        repo: string,
+        commit: string,
+    ): Promise<DistilledSchematic> {
+        try {
+            const data = await this.postWithETag<DistillResponse>(
+                "/distill",
+                { repo, commit },
+                "fetch distilled schematic",
+            );
             return data.distilled;
         } catch (e) {
             if (e instanceof TypeError && e.message.includes("fetch")) {
@@ -339,24 +515,11 @@ export class GrokiAPI {
         commit?: string,
     ): Promise<RepoInitResponse> {
         try {
-            const response = await fetch(`${this.baseUrl}/repo/init`, {
-                method: "POST",
-                headers: {
-                    "Content-Type": "application/json",
-                },
-                body: JSON.stringify({ repo, commit } satisfies RepoInitRequest),
-            });
-
-            if (!response.ok) {
-                const errorText = await response.text().catch(() => "");
-                throw new Error(
-                    `Failed to initialize repository: ${response.status} ${
-                        response.statusText
-                    }${errorText ? ` - ${errorText}` : ""}`,
-                );
-            }
-
-            const data: RepoInitResponse = await response.json();
+            const data = await this.postWithETag<RepoInitResponse>(
+                "/repo/init",
+                { repo, commit } satisfies RepoInitRequest,
+                "initialize repository",
+            );
             console.log(
                 `[API] Repository ${repo} initialized: ${data.component_count} components, ${data.net_count} nets, cached=${data.cached}`,
             );
@@ -432,6 +595,41 @@ export class GrokiAPI {
         }
     }
 
+    /**
+     * Rate a generated commit blurb, description or summary
+     */
+    static async submitSummaryFeedback(
+        feedback: SummaryFeedbackRequest,
+    ): Promise<SummaryFeedbackResponse> {
+        try {
+            const response = await fetch(`${this.baseUrl}/feedback/summary`, {
+                method: "POST",
+                headers: {
+                    "Content-Type": "application/json",
+                },
+                body: JSON.stringify(feedback),
+            });
+
+            if (!response.ok) {
+                const errorText = await response.text().catch(() => "");
+                throw new Error(
+                    `Failed to submit feedback: ${response.status} ${
+                        response.statusText
+                    }${errorText ? ` - ${errorText}` : ""}`,
+                );
+            }
+
+            return await response.json();
+        } catch (e) {
+            if (e instanceof TypeError && e.message.includes("fetch")) {
+                throw new Error(
+                    `Cannot connect to API at ${this.baseUrl}. Is the backend running?`,
+                );
+            }
+            throw e;
+        }
+    }
+
     /**
      * Extract repo identifier from a GitHub URL
      * e.g., "https://github.com/owner/repo" -> "owner/repo"
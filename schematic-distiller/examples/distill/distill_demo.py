@@ -4,18 +4,55 @@ Example: distill a KiCad schematic into LLM-friendly JSON.
 
 import argparse
 import json
+import sys
 from pathlib import Path
 from typing import Dict, Iterable, List, Optional, Set, Tuple
 
 import kicad_sch_api as ksa
 from kicad_sch_api.distill import DistillationConfig, distill_schematic
 from kicad_sch_api import load_schematic as parse_schematic
+from kicad_sch_api.core.parse_cache import ParseCache, get_parse_cache, set_parse_cache
 
 
 def _collect_schematics_from_dir(directory: Path) -> List[Path]:
     return sorted(p for p in directory.rglob("*.kicad_sch") if p.is_file())
 
 
+def _partition_loadable(paths: Iterable[Path]) -> Tuple[List[Path], List[Dict[str, str]]]:
+    """
+    Try to parse each schematic, separating loadable files from corrupt/truncated ones.
+
+    Returns (loadable paths, warnings) where each warning names the file and parse error.
+    """
+    loadable: List[Path] = []
+    warnings: List[Dict[str, str]] = []
+    for path in paths:
+        try:
+            parse_schematic(str(path))
+        except Exception as e:
+            warnings.append({"file": str(path), "error": str(e) or type(e).__name__})
+            continue
+        loadable.append(path)
+    return loadable, warnings
+
+
+def _relative_warning_paths(warnings: List[Dict[str, str]], directory: Optional[Path]) -> List[Dict[str, str]]:
+    """Report warning paths relative to the input directory so they match repository paths."""
+    if directory is None:
+        return warnings
+
+    base = directory.resolve()
+    relative: List[Dict[str, str]] = []
+    for warning in warnings:
+        path = Path(warning["file"]).resolve()
+        try:
+            file_name = str(path.relative_to(base))
+        except ValueError:
+            file_name = warning["file"]
+        relative.append({**warning, "file": file_name})
+    return relative
+
+
 def _find_root_from_project_file(directory: Path, schematics: List[Path]) -> Optional[Path]:
     """
     Look for a .kicad_pro file and use its name to identify the root schematic.
@@ -72,15 +109,9 @@ def _detect_root_schematic(schematics: Iterable[Path], directory: Optional[Path]
         data = getattr(schematic, "_data", {}) or {}
         
         # Check for sheets in the schematic - could be under different keys
+        # (`schematic.sheets` is a SheetManager, not a list, so read the parsed data)
         sheets_data = data.get("sheet", []) + data.get("sheets", [])
-        
-        # Also check if sheets is a list of sheet items in the schematic
-        if hasattr(schematic, 'sheets'):
-            for sheet in schematic.sheets:
-                if hasattr(sheet, 'filename'):
-                    child_path = (sch_path.parent / sheet.filename).resolve()
-                    children.add(child_path)
-        
+
         # Process data-based sheets
         for sheet in sheets_data:
             file_field = sheet.get("filename") or sheet.get("file")
@@ -104,10 +135,7 @@ def _detect_root_schematic(schematics: Iterable[Path], directory: Optional[Path]
     for sch_path in roots:
         schematic = parse_schematic(str(sch_path))
         data = getattr(schematic, "_data", {}) or {}
-        count = len(data.get("sheet", []))
-        if hasattr(schematic, 'sheets'):
-            count = max(count, len(schematic.sheets))
-        sheet_counts[sch_path] = count
+        sheet_counts[sch_path] = len(data.get("sheet", []) + data.get("sheets", []))
 
     max_count = max(sheet_counts.values())
     best_roots = [p for p, cnt in sheet_counts.items() if cnt == max_count]
@@ -126,6 +154,22 @@ def _detect_root_schematic(schematics: Iterable[Path], directory: Optional[Path]
     return best_roots_sorted[0]
 
 
+def _relative_sheet_files(data: Dict, directory: Optional[Path]) -> None:
+    """Report component sheet files relative to the input directory so they match repository paths."""
+    if directory is None:
+        return
+
+    base = directory.resolve()
+    for component in data.get("components", {}).values():
+        sheet_file = component.get("sheet_file")
+        if not sheet_file:
+            continue
+        try:
+            component["sheet_file"] = Path(sheet_file).resolve().relative_to(base).as_posix()
+        except ValueError:
+            pass
+
+
 def main() -> None:
     parser = argparse.ArgumentParser(description="Distill KiCad schematic for LLM input")
     group = parser.add_mutually_exclusive_group(required=True)
@@ -147,8 +191,15 @@ def main() -> None:
         action="store_true",
         help="Disable hierarchical connectivity traversal",
     )
+    parser.add_argument(
+        "--parse-cache",
+        help="Directory of parsed schematics keyed by git blob id; read before parsing, written after",
+    )
     args = parser.parse_args()
 
+    if args.parse_cache:
+        set_parse_cache(ParseCache(args.parse_cache))
+
     candidate_paths: List[Path] = []
     directory: Optional[Path] = None
     
@@ -166,13 +217,32 @@ def main() -> None:
         if not p.exists():
             raise SystemExit(f"Schematic not found: {p}")
 
+    # Skip files that fail to parse instead of failing the whole distillation
+    candidate_paths, warnings = _partition_loadable(candidate_paths)
+    for warning in warnings:
+        print(f"Warning: skipping {warning['file']}: {warning['error']}", file=sys.stderr)
+    if not candidate_paths:
+        raise SystemExit("No parseable schematic files found.")
+
     root_path = _detect_root_schematic(candidate_paths, directory) if len(candidate_paths) > 1 else candidate_paths[0]
 
     schematic = ksa.load_schematic(str(root_path))
     cfg = DistillationConfig(proximity_radius_mm=args.radius, hierarchical=not args.no_hierarchy)
     distilled = distill_schematic(schematic, cfg)
 
-    print(json.dumps(distilled.to_dict(), indent=2))
+    # Corrupt child sheets are already in the candidate warnings; don't report them twice
+    reported = {Path(w["file"]).resolve() for w in warnings}
+    distilled.warnings = warnings + [w for w in distilled.warnings if Path(w["file"]).resolve() not in reported]
+    distilled.warnings = _relative_warning_paths(distilled.warnings, directory)
+
+    output = distilled.to_dict()
+    _relative_sheet_files(output, directory)
+
+    cache = get_parse_cache()
+    if cache:
+        print(f"Parse cache: {cache.hits} hit(s), {cache.misses} miss(es)", file=sys.stderr)
+
+    print(json.dumps(output, indent=2))
 
 
 if __name__ == "__main__":
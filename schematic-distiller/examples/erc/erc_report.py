@@ -0,0 +1,47 @@
+"""
+Example: run the electrical rules check on KiCad schematics and print JSON results.
+"""
+
+import argparse
+import json
+from pathlib import Path
+from typing import Any, Dict, List
+
+import kicad_sch_api as ksa
+from kicad_sch_api.validation import ElectricalRulesChecker
+
+
+def _check_file(path: Path) -> Dict[str, Any]:
+    return ElectricalRulesChecker(ksa.load_schematic(str(path))).run_all_checks().to_dict()
+
+
+def main() -> None:
+    parser = argparse.ArgumentParser(description="Run ERC on KiCad schematics")
+    group = parser.add_mutually_exclusive_group(required=True)
+    group.add_argument("--schematic", help="Path to a .kicad_sch file")
+    group.add_argument("--dir", help="Directory containing .kicad_sch files")
+    args = parser.parse_args()
+
+    directory = Path(args.dir) if args.dir else None
+    if directory is not None:
+        paths = sorted(p for p in directory.rglob("*.kicad_sch") if p.is_file())
+    else:
+        paths = [Path(args.schematic)]
+    if not paths:
+        raise SystemExit("No schematic files found.")
+
+    # Each file is checked on its own; files that fail to load are reported, not fatal
+    files: Dict[str, Dict[str, Any]] = {}
+    warnings: List[Dict[str, str]] = []
+    for path in paths:
+        key = str(path.relative_to(directory)) if directory is not None else str(path)
+        try:
+            files[key] = _check_file(path)
+        except Exception as e:
+            warnings.append({"file": key, "error": str(e) or type(e).__name__})
+
+    print(json.dumps({"files": files, "warnings": warnings}, indent=2))
+
+
+if __name__ == "__main__":
+    main()
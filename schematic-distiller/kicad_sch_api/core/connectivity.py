@@ -102,6 +102,7 @@ class ConnectivityAnalyzer:
         self._label_name_to_nets: Dict[str, List[Net]] = defaultdict(list)
         self._sheet_links: List[Tuple[Any, Any, Dict[str, Any]]] = []  # (parent_sch, child_sch, sheet_data)
         self._schematic_contexts: List[Tuple[Any, str]] = []  # (schematic, hierarchy_path)
+        self._load_warnings: List[Dict[str, str]] = []  # child sheets that were skipped
 
         logger.info(f"Initialized ConnectivityAnalyzer (tolerance={tolerance}mm)")
 
@@ -548,6 +549,7 @@ class ConnectivityAnalyzer:
         # Reset tracking
         self._sheet_links = []
         self._schematic_contexts = []
+        self._load_warnings = []
 
         schematics: List[Any] = []
 
@@ -573,6 +575,9 @@ class ConnectivityAnalyzer:
 
                 if not child_path.exists():
                     logger.warning(f"Child schematic not found: {child_path}")
+                    self._load_warnings.append(
+                        {"file": str(child_path), "error": "Child schematic not found"}
+                    )
                     continue
 
                 try:
@@ -588,6 +593,7 @@ class ConnectivityAnalyzer:
                     recurse(child_schematic, child_path, child_h_path)
                 except Exception as e:
                     logger.warning(f"Could not load child schematic {sheet_filename}: {e}")
+                    self._load_warnings.append({"file": str(child_path), "error": str(e)})
 
         root_path = Path(root_schematic.file_path) if root_schematic.file_path else None
         recurse(root_schematic, root_path, "/")
@@ -709,3 +715,12 @@ class ConnectivityAnalyzer:
             List of (schematic, hierarchy_path) tuples
         """
         return list(self._schematic_contexts)
+
+    def get_load_warnings(self) -> List[Dict[str, str]]:
+        """
+        Get child sheets that could not be loaded during hierarchical analysis.
+
+        Returns:
+            List of {"file": path, "error": message} dicts
+        """
+        return list(self._load_warnings)
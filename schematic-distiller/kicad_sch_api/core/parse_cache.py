@@ -0,0 +1,114 @@
+"""
+On-disk cache of parsed S-expressions, keyed by git blob id.
+
+Parsing the S-expression text is the slow part of loading a large schematic,
+and most files are unchanged between commits. When a cache is installed with
+`set_parse_cache`, `SExpressionParser.parse_file` looks up each file's parsed
+tree by the git blob id of its bytes before parsing, and writes the trees it
+had to parse back to the cache directory as `<blob id>.json`.
+
+Only the parse step is cached: conversion to schematic data, hierarchy
+traversal and connectivity always run on the full project.
+"""
+
+import hashlib
+import json
+import logging
+from pathlib import Path
+from typing import Any, Optional, Union
+
+import sexpdata
+
+logger = logging.getLogger(__name__)
+
+# Bumped when the JSON encoding below changes
+FORMAT = 1
+
+
+def blob_id(data: bytes) -> str:
+    """The git blob id (SHA-1 of the `blob <size>` header and the bytes) of file content."""
+    header = b"blob %d\0" % len(data)
+    return hashlib.sha1(header + data).hexdigest()
+
+
+def _encode(node: Any) -> Any:
+    if isinstance(node, list):
+        return [_encode(item) for item in node]
+    # Symbols subclass str, so check them first; a dict can't be confused with a string
+    if isinstance(node, sexpdata.Symbol):
+        return {"s": str(node)}
+    if node is None or isinstance(node, (str, bool, int, float)):
+        return node
+    raise TypeError(f"Unsupported S-expression node: {type(node).__name__}")
+
+
+def _decode(node: Any) -> Any:
+    if isinstance(node, list):
+        return [_decode(item) for item in node]
+    if isinstance(node, dict):
+        return sexpdata.Symbol(node["s"])
+    return node
+
+
+def dumps(sexp: Any) -> str:
+    """Serialize a parsed tree as JSON. Raises TypeError for nodes it can't represent."""
+    return json.dumps({"format": FORMAT, "sexp": _encode(sexp)}, separators=(",", ":"))
+
+
+def loads(text: str) -> Any:
+    """Inverse of `dumps`. Raises ValueError for entries in another format."""
+    data = json.loads(text)
+    if data.get("format") != FORMAT:
+        raise ValueError(f"Unsupported parse cache format: {data.get('format')}")
+    return _decode(data["sexp"])
+
+
+class ParseCache:
+    """Parsed trees stored as `<blob id>.json` files in a directory."""
+
+    def __init__(self, directory: Union[str, Path]):
+        self.directory = Path(directory)
+        self.directory.mkdir(parents=True, exist_ok=True)
+        self.hits = 0
+        self.misses = 0
+
+    def _path(self, key: str) -> Path:
+        return self.directory / f"{key}.json"
+
+    def get(self, key: str) -> Optional[Any]:
+        path = self._path(key)
+        if not path.is_file():
+            self.misses += 1
+            return None
+        try:
+            sexp = loads(path.read_text(encoding="utf-8"))
+        except (ValueError, KeyError) as e:
+            logger.warning(f"Ignoring unreadable parse cache entry {path.name}: {e}")
+            self.misses += 1
+            return None
+        self.hits += 1
+        return sexp
+
+    def put(self, key: str, sexp: Any) -> None:
+        try:
+            text = dumps(sexp)
+        except TypeError as e:
+            logger.debug(f"Not caching parse of {key}: {e}")
+            return
+        # Write then rename, so a reader never sees a partial entry
+        tmp = self._path(f"{key}.tmp")
+        tmp.write_text(text, encoding="utf-8")
+        tmp.replace(self._path(key))
+
+
+_cache: Optional[ParseCache] = None
+
+
+def set_parse_cache(cache: Optional[ParseCache]) -> None:
+    """Install (or with None, remove) the cache used by `SExpressionParser.parse_file`."""
+    global _cache
+    _cache = cache
+
+
+def get_parse_cache() -> Optional[ParseCache]:
+    return _cache
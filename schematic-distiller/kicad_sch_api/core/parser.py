@@ -23,6 +23,7 @@ from ..parsers.elements.wire_parser import WireParser
 from ..parsers.utils import color_to_rgb255, color_to_rgba
 from ..utils.validation import ValidationError, ValidationIssue
 from .formatter import ExactFormatter
+from .parse_cache import blob_id, get_parse_cache
 from .types import Junction, Label, Net, Point, SchematicSymbol, Wire
 
 logger = logging.getLogger(__name__)
@@ -98,8 +99,14 @@ class SExpressionParser:
             with open(filepath, "r", encoding="utf-8") as f:
                 content = f.read()
 
-            # Parse S-expression
-            sexp_data = self.parse_string(content)
+            # Parse S-expression, reusing a cached parse of identical content
+            cache = get_parse_cache()
+            key = blob_id(filepath.read_bytes()) if cache else None
+            sexp_data = cache.get(key) if cache else None
+            if sexp_data is None:
+                sexp_data = self.parse_string(content)
+                if cache:
+                    cache.put(key, sexp_data)
 
             # Validate structure
             self._validate_schematic_structure(sexp_data, filepath)
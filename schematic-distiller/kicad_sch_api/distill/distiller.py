@@ -38,6 +38,7 @@ def distill_schematic(schematic, distill_config: Optional[DistillationConfig] =
 
     - Uses ConnectivityAnalyzer for net/pin mapping.
     - Uses component anchor positions (no graphics) for location + proximity scoring.
+    - Child sheets that fail to load are skipped and reported in `warnings`.
     """
     cfg = distill_config or DistillationConfig()
 
@@ -55,9 +56,15 @@ def distill_schematic(schematic, distill_config: Optional[DistillationConfig] =
     distilled_components: List[DistilledComponent] = []
     for sch, sheet_path in schematic_contexts:
         real_symbols = [comp for comp in sch.components if _is_real_symbol(comp)]
+        sheet_file = getattr(sch, "file_path", None) if cfg.hierarchical else None
         for comp in real_symbols:
             distilled_components.append(
-                _distill_component(comp, pin_to_net, sheet_path if cfg.hierarchical else None)
+                _distill_component(
+                    comp,
+                    pin_to_net,
+                    sheet_path if cfg.hierarchical else None,
+                    str(sheet_file) if sheet_file else None,
+                )
             )
 
     distilled_nets = {net.name or f"Net-{idx+1}": _distill_net(net) for idx, net in enumerate(nets)}
@@ -72,7 +79,14 @@ def distill_schematic(schematic, distill_config: Optional[DistillationConfig] =
     for comps in comps_by_sheet.values():
         proximities.extend(_compute_proximities(comps, cfg.proximity_radius_mm, cfg.weight_multipliers))
 
-    return DistilledSchematic(components=distilled_components, nets=distilled_nets, proximities=proximities)
+    warnings = analyzer.get_load_warnings() if cfg.hierarchical else []
+
+    return DistilledSchematic(
+        components=distilled_components,
+        nets=distilled_nets,
+        proximities=proximities,
+        warnings=warnings,
+    )
 
 
 def _build_pin_net_map(nets: Iterable[Net]) -> Dict[Tuple[str, str], str]:
@@ -86,7 +100,10 @@ def _build_pin_net_map(nets: Iterable[Net]) -> Dict[Tuple[str, str], str]:
 
 
 def _distill_component(
-    component: SchematicSymbol, pin_to_net: Dict[Tuple[str, str], str], sheet_path: Optional[str] = None
+    component: SchematicSymbol,
+    pin_to_net: Dict[Tuple[str, str], str],
+    sheet_path: Optional[str] = None,
+    sheet_file: Optional[str] = None,
 ) -> DistilledComponent:
     pin_positions = list_component_pins(component)
     pin_name_map = {pin.number: pin.name for pin in component.pins}
@@ -147,6 +164,7 @@ def _distill_component(
         position=(component.position.x, component.position.y),
         category=_classify_component(component),
         sheet_path=sheet_path,
+        sheet_file=sheet_file,
     )
 
 
@@ -161,6 +179,12 @@ def _classify_component(component: SchematicSymbol) -> str:
     ref = component.reference.upper()
     lib = component.lib_id.lower()
 
+    # Test points live in the Connector library, so they're checked first
+    if "testpoint" in lib or "test_point" in lib or ref.startswith("TP"):
+        return "test_point"
+    # Before capacitors, since "CN" references would otherwise match "C"
+    if lib.startswith("connector") or ref.startswith("J") or ref.startswith("CN"):
+        return "connector"
     if ref.startswith("C") or "cap" in lib:
         return "capacitor"
     if ref.startswith("U") or "mcu" in lib or "ic" in lib:
@@ -25,6 +25,8 @@ class DistilledComponent:
     value: str
     position: Tuple[float, float]
     sheet_path: Optional[str] = None
+    # Schematic file the component is placed in (hierarchical distillation only)
+    sheet_file: Optional[str] = None
     footprint: Optional[str] = None
     properties: Dict[str, str] = field(default_factory=dict)
     pins: List[DistilledPin] = field(default_factory=list)
@@ -47,6 +49,9 @@ class DistilledComponent:
         if self.sheet_path is not None:
             data["sheet_path"] = self.sheet_path
 
+        if self.sheet_file is not None:
+            data["sheet_file"] = self.sheet_file
+
         return data
 
 
@@ -89,11 +94,18 @@ class DistilledSchematic:
     components: List[DistilledComponent]
     nets: Dict[str, DistilledNet]
     proximities: List[ProximityEdge]
+    # Files skipped because they could not be loaded: {"file": ..., "error": ...}
+    warnings: List[Dict[str, str]] = field(default_factory=list)
 
     def to_dict(self) -> Dict:
-        return {
+        data = {
             "components": {c.reference: c.to_dict(include_reference=False) for c in self.components},
             "nets": {name: net.to_dict() for name, net in self.nets.items()},
             "proximities": [p.to_dict() for p in self.proximities],
         }
 
+        if self.warnings:
+            data["warnings"] = list(self.warnings)
+
+        return data
+
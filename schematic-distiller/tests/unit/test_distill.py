@@ -1,8 +1,9 @@
 from pathlib import Path
+from types import SimpleNamespace
 
 import kicad_sch_api as ksa
 from kicad_sch_api.distill import DistillationConfig, distill_schematic
-from kicad_sch_api.distill.distiller import _compute_proximities
+from kicad_sch_api.distill.distiller import _classify_component, _compute_proximities
 from kicad_sch_api.distill.model import DistilledComponent
 
 
@@ -217,3 +218,49 @@ def test_distill_hierarchical_multisheet():
     assert "R1" in data_net and any(pin.get("Pin") == "2" for pin in data_net["R1"]), "R1.2 should be on DATA net"
     assert "R2" in data_net and any(pin.get("Pin") == "1" for pin in data_net["R2"]), "R2.1 should be on DATA net"
 
+
+
+def test_distill_skips_corrupt_child_sheet(tmp_path):
+    """A truncated child sheet is reported in warnings instead of failing distillation."""
+    project_dir = (
+        Path(__file__).resolve().parent.parent
+        / "reference_kicad_projects"
+        / "connectivity"
+        / "ps2_hierarchical_power"
+    )
+    root_path = tmp_path / "ps2_hierarchical_power.kicad_sch"
+    root_path.write_text((project_dir / "ps2_hierarchical_power.kicad_sch").read_text())
+
+    child_text = (project_dir / "child_circuit.kicad_sch").read_text()
+    (tmp_path / "child_circuit.kicad_sch").write_text(child_text[: len(child_text) // 2])
+
+    schematic = ksa.load_schematic(str(root_path))
+    data = distill_schematic(schematic, DistillationConfig(hierarchical=True)).to_dict()
+
+    assert "R1" in data["components"], "Root sheet components should still be distilled"
+    assert "R2" not in data["components"], "Components from the corrupt sheet should be skipped"
+
+    assert len(data["warnings"]) == 1
+    warning = data["warnings"][0]
+    assert warning["file"].endswith("child_circuit.kicad_sch")
+    assert warning["error"]
+
+
+def test_classify_connectors():
+    def classify(reference, lib_id):
+        return _classify_component(SimpleNamespace(reference=reference, lib_id=lib_id))
+
+    assert classify("J1", "Connector_Generic:Conn_01x04") == "connector"
+    assert classify("CN2", "Custom:USB_C") == "connector"
+    assert classify("P3", "Connector:Barrel_Jack") == "connector"
+    assert classify("C1", "Device:C") == "capacitor"
+    assert classify("U1", "MCU_ST_STM32F1:STM32F103C8Tx") == "ic"
+
+
+def test_classify_test_points():
+    def classify(reference, lib_id):
+        return _classify_component(SimpleNamespace(reference=reference, lib_id=lib_id))
+
+    assert classify("TP1", "Connector:TestPoint") == "test_point"
+    assert classify("TP12", "Custom:Pad") == "test_point"
+    assert classify("J1", "Connector:Conn_01x02") == "connector"
@@ -0,0 +1,123 @@
+"""
+Regression tests for the on-disk schematic corpus.
+
+Every schematic in the corpus must load and distill to the same output on
+repeated runs, and the RC divider fixture - saved in KiCad 6, 7, 8 and 9
+syntax - must distill identically regardless of file format version. This
+catches format drift in the parser before users hit it.
+"""
+
+import json
+from pathlib import Path
+
+import pytest
+
+import kicad_sch_api as ksa
+from kicad_sch_api.distill import DistillationConfig, distill_schematic
+
+TESTS_DIR = Path(__file__).resolve().parent.parent
+CORPUS_DIR = TESTS_DIR / "fixtures" / "distill_corpus"
+EXAMPLES_DIR = TESTS_DIR.parent / "example-schematics"
+
+# Same circuit saved by each KiCad major version
+VERSION_VARIANTS = sorted(CORPUS_DIR.glob("rc_divider_kicad*.kicad_sch"))
+
+# Real-world designs: hierarchical sheets, multi-unit and exotic symbols
+REAL_WORLD = [
+    TESTS_DIR / "reference_kicad_projects" / "connectivity" / "ps2_hierarchical_power" / "ps2_hierarchical_power.kicad_sch",
+    TESTS_DIR / "reference_kicad_projects" / "multi_unit_tl072" / "test.kicad_sch",
+    EXAMPLES_DIR / "multisheet" / "uBMS-2.kicad_sch",
+    EXAMPLES_DIR / "SmartWatch V5.kicad_sch",
+]
+
+CORPUS = VERSION_VARIANTS + REAL_WORLD
+
+
+def _distill(path: Path) -> dict:
+    schematic = ksa.load_schematic(str(path))
+    return distill_schematic(schematic, DistillationConfig(proximity_radius_mm=50.0)).to_dict()
+
+
+def _connectivity(data: dict) -> dict:
+    """Net membership as {net: {ref: sorted pins}}, ignoring auto-generated net names."""
+    return {
+        name: {ref: sorted(pin["Pin"] for pin in pins) for ref, pins in refs.items()}
+        for name, refs in data["nets"].items()
+        if not name.startswith("Net-")
+    }
+
+
+def test_version_variants_present():
+    versions = {path.stem.rsplit("_", 1)[-1] for path in VERSION_VARIANTS}
+    assert versions == {"kicad6", "kicad7", "kicad8", "kicad9"}
+
+
+@pytest.mark.parametrize("path", CORPUS, ids=lambda p: p.name)
+def test_corpus_distills_deterministically(path):
+    first = _distill(path)
+    second = _distill(path)
+
+    assert json.dumps(first, sort_keys=True) == json.dumps(second, sort_keys=True)
+
+
+@pytest.mark.parametrize("path", CORPUS, ids=lambda p: p.name)
+def test_corpus_output_is_consistent(path):
+    data = _distill(path)
+
+    assert data["components"], "Expected components in distilled output"
+    json.dumps(data)  # must be serializable as-is
+
+    # Every net member is a distilled component (power symbols aside)
+    components = data["components"]
+    for net_name, refs in data["nets"].items():
+        for ref in refs:
+            if ref.startswith("#"):
+                continue
+            assert ref in components, f"{net_name} references unknown component {ref}"
+
+    # Proximity edges only join known components
+    for edge in data["proximities"]:
+        assert edge["ref_a"] in components
+        assert edge["ref_b"] in components
+
+
+@pytest.mark.parametrize("path", VERSION_VARIANTS, ids=lambda p: p.name)
+def test_version_variant_matches_expected_circuit(path):
+    data = _distill(path)
+
+    assert set(data["components"]) == {"R1", "R2", "C1"}
+    assert data["components"]["R1"]["value"] == "10k"
+    assert data["components"]["C1"]["footprint"] == "Capacitor_SMD:C_0603_1608Metric"
+
+    assert _connectivity(data) == {
+        "VIN": {"R1": ["1"]},
+        "VOUT": {"R1": ["2"], "R2": ["1"], "C1": ["1"]},
+        "GND": {"R2": ["2"], "C1": ["2"]},
+    }
+
+
+def test_version_variants_distill_identically():
+    outputs = {path.name: _distill(path) for path in VERSION_VARIANTS}
+    baseline_name, baseline = next(iter(outputs.items()))
+
+    for name, data in outputs.items():
+        assert data == baseline, f"{name} distills differently from {baseline_name}"
+
+
+@pytest.mark.parametrize(
+    "path, snapshot",
+    [
+        (EXAMPLES_DIR / "multisheet" / "uBMS-2.kicad_sch", EXAMPLES_DIR / "multisheet" / "uBMS-2_distilled.json"),
+        (EXAMPLES_DIR / "SmartWatch V5.kicad_sch", EXAMPLES_DIR / "SmartWatch_V5_distilled.json"),
+    ],
+    ids=["uBMS-2", "SmartWatch V5"],
+)
+def test_real_world_matches_snapshot(path, snapshot):
+    """Component set and identities should not drift from the checked-in distilled output."""
+    expected = json.loads(snapshot.read_text())["components"]
+    actual = _distill(path)["components"]
+
+    assert set(actual) == set(expected)
+    for ref, comp in expected.items():
+        assert actual[ref]["lib_id"] == comp["lib_id"], ref
+        assert actual[ref]["value"] == comp["value"], ref
@@ -0,0 +1,89 @@
+"""
+Tests for the parse cache and whole-project distillation through distill_demo.py.
+
+The backend distills every commit as one hierarchical project and caches
+only the parse of each file, so a warm run must produce exactly what a cold
+run does, and sheet pins must still join nets across sheets.
+"""
+
+import json
+import os
+import subprocess
+import sys
+from pathlib import Path
+
+import sexpdata
+
+from kicad_sch_api.core.parse_cache import ParseCache, blob_id, dumps, loads, set_parse_cache
+from kicad_sch_api.core.parser import SExpressionParser
+
+TESTS_DIR = Path(__file__).resolve().parent.parent
+PROJECT_DIR = TESTS_DIR / "fixtures" / "distill_corpus" / "sheet_pin_hierarchy"
+SCRIPT = TESTS_DIR.parent / "examples" / "distill" / "distill_demo.py"
+
+
+def _run_demo(directory: Path, parse_cache: Path) -> dict:
+    result = subprocess.run(
+        [sys.executable, str(SCRIPT), "--dir", str(directory), "--parse-cache", str(parse_cache)],
+        capture_output=True,
+        text=True,
+        check=True,
+        # Generated net names depend on set order, so pin the hash seed
+        env={**os.environ, "PYTHONHASHSEED": "0"},
+    )
+    return json.loads(result.stdout)
+
+
+def _net_of(data: dict, reference: str, pin: str) -> str:
+    for name, members in data["nets"].items():
+        if any(p["Pin"] == pin for p in members.get(reference, [])):
+            return name
+    raise AssertionError(f"{reference}.{pin} is on no net")
+
+
+def test_blob_id_matches_git():
+    # `git hash-object` of "hello\n"
+    assert blob_id(b"hello\n") == "ce013625030ba8dba906f756967f9e9ca394464a"
+
+
+def test_cached_tree_round_trips():
+    content = (PROJECT_DIR / "sensor.kicad_sch").read_text()
+    tree = sexpdata.loads(content)
+
+    assert loads(dumps(tree)) == tree
+
+
+def test_parse_file_uses_cache(tmp_path):
+    path = PROJECT_DIR / "sensor.kicad_sch"
+    cache = ParseCache(tmp_path)
+    set_parse_cache(cache)
+    try:
+        first = SExpressionParser().parse_file(path)
+        second = SExpressionParser().parse_file(path)
+    finally:
+        set_parse_cache(None)
+
+    assert (tmp_path / f"{blob_id(path.read_bytes())}.json").is_file()
+    assert (cache.misses, cache.hits) == (1, 1)
+    assert first["components"] == second["components"]
+
+
+def test_sheet_pin_joins_nets_across_sheets(tmp_path):
+    """R1.2 reaches R2.1 only through the DATA sheet pin; the parent names the net SENSE."""
+    data = _run_demo(PROJECT_DIR, tmp_path / "cache")
+
+    assert _net_of(data, "R1", "2") == _net_of(data, "R2", "1")
+    assert data["components"]["R1"]["sheet_file"] == "sheet_pin_hierarchy.kicad_sch"
+    assert data["components"]["R2"]["sheet_file"] == "sensor.kicad_sch"
+
+
+def test_warm_parse_cache_distills_identically(tmp_path):
+    cache_dir = tmp_path / "cache"
+    cold = _run_demo(PROJECT_DIR, cache_dir)
+
+    cached = {p.stem for p in cache_dir.glob("*.json")}
+    expected = {blob_id(p.read_bytes()) for p in PROJECT_DIR.glob("*.kicad_sch")}
+    assert cached == expected
+
+    warm = _run_demo(PROJECT_DIR, cache_dir)
+    assert warm == cold